@@ -1,6 +1,38 @@
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
 use serde::{Deserialize, Serialize};
 use serde_toon::{from_str, to_string};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The system allocator, counting the reallocations made through it.
+struct CountingAllocator;
+
+static REALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        REALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Runs `f` and returns the number of reallocations it made.
+fn reallocations<T>(f: impl FnOnce() -> T) -> usize {
+    let before = REALLOCATIONS.load(Ordering::Relaxed);
+    black_box(f());
+    REALLOCATIONS.load(Ordering::Relaxed) - before
+}
 
 #[derive(Serialize, Deserialize, Clone)]
 struct User {
@@ -223,6 +255,62 @@ fn benchmark_comparison_with_json(c: &mut Criterion) {
     group.finish();
 }
 
+fn benchmark_table_buffer_sizing(c: &mut Criterion) {
+    use serde_toon::{estimated_size, to_string_with_options, ToonOptions};
+
+    let products: Vec<Product> = (0..10_000)
+        .map(|i| Product {
+            sku: format!("SKU-{:05}", i),
+            name: format!("Product {}", i),
+            price: 9.99 + f64::from(i),
+            quantity: i % 100,
+        })
+        .collect();
+    let size = estimated_size(&products).unwrap();
+    let hinted = ToonOptions::new().with_size_hint(size.bytes);
+
+    eprintln!(
+        "10k-row table: {} reallocations by default, {} with a size hint",
+        reallocations(|| to_string(&products)),
+        reallocations(|| to_string_with_options(&products, hinted.clone())),
+    );
+
+    let mut group = c.benchmark_group("table_buffer_sizing");
+    group.bench_function("default", |b| b.iter(|| to_string(black_box(&products))));
+    group.bench_function("size_hint", |b| {
+        b.iter(|| to_string_with_options(black_box(&products), hinted.clone()))
+    });
+    group.bench_function("estimate_then_hint", |b| {
+        b.iter(|| {
+            let bytes = estimated_size(black_box(&products)).unwrap().bytes;
+            to_string_with_options(&products, ToonOptions::new().with_size_hint(bytes))
+        })
+    });
+    group.finish();
+}
+
+fn benchmark_table_from_rows(c: &mut Criterion) {
+    use serde_toon::{to_value, Table};
+
+    let products: Vec<Product> = (0..10_000)
+        .map(|i| Product {
+            sku: format!("SKU-{:05}", i),
+            name: format!("Product {}", i),
+            price: 9.99 + f64::from(i),
+            quantity: i % 100,
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("table_from_rows");
+    group.bench_function("to_value_then_convert", |b| {
+        b.iter(|| Table::try_from(to_value(black_box(&products)).unwrap()).unwrap())
+    });
+    group.bench_function("from_serialize_iter", |b| {
+        b.iter(|| Table::from_serialize_iter(black_box(&products)).unwrap())
+    });
+    group.finish();
+}
+
 fn benchmark_roundtrip(c: &mut Criterion) {
     let user = User {
         id: 123,
@@ -250,6 +338,8 @@ criterion_group!(
     benchmark_string_serialization,
     benchmark_primitive_array,
     benchmark_comparison_with_json,
+    benchmark_table_buffer_sizing,
+    benchmark_table_from_rows,
     benchmark_roundtrip
 );
 criterion_main!(benches);
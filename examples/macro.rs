@@ -65,6 +65,19 @@ fn main() -> Result<(), Box<dyn Error>> {
     println!("Dynamic construction:");
     println!("{}\n", to_string_pretty(&summary)?);
 
+    let field = "owner";
+    let extra_tags = vec!["beta", "internal"];
+    let include_debug = false;
+    let interpolated = toon!({
+        field: "Alice",
+        "tags": ["stable", extra_tags..],
+        "debug": true if include_debug,
+        ..user
+    });
+
+    println!("Interpolation, spreads and conditional fields:");
+    println!("{}\n", to_string_pretty(&interpolated)?);
+
     if let Value::Object(obj) = &config {
         if let Some(Value::Object(app)) = obj.get("app") {
             if let Some(name) = app.get("name").and_then(|v| v.as_str()) {
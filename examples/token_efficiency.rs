@@ -3,6 +3,7 @@
 //! Run with: cargo run --example token_efficiency
 
 use serde::{Deserialize, Serialize};
+use serde_toon::metrics::{compare_with_json, token_estimate, Tokenizer};
 use serde_toon::to_string_pretty;
 use std::error::Error;
 
@@ -49,19 +50,31 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // Serialize to JSON
     let json = serde_json::to_string_pretty(&response)?;
-    println!("JSON ({} chars):\n{}\n", json.len(), json);
+    println!(
+        "JSON ({} chars, ~{} tokens):\n{}\n",
+        json.len(),
+        token_estimate(&json, Tokenizer::Cl100k),
+        json
+    );
 
     // Serialize to TOON
     let toon = to_string_pretty(&response)?;
-    println!("TOON ({} chars):\n{}\n", toon.len(), toon);
+    println!(
+        "TOON ({} chars, ~{} tokens):\n{}\n",
+        toon.len(),
+        token_estimate(&toon, Tokenizer::Cl100k),
+        toon
+    );
 
-    // Calculate token savings
-    let savings = ((json.len() - toon.len()) as f64 / json.len() as f64) * 100.0;
+    // Compare against compact JSON
+    let report = compare_with_json(&response)?;
     println!(
-        "✓ Token savings: {:.1}% ({} → {} chars)",
-        savings,
-        json.len(),
-        toon.len()
+        "✓ Token savings vs compact JSON: {:.1}% ({} → {} tokens, {} → {} chars)",
+        report.token_savings(),
+        report.json_tokens,
+        report.toon_tokens,
+        report.json_chars,
+        report.toon_chars
     );
 
     Ok(())
@@ -0,0 +1,23 @@
+//! Parses arbitrary bytes in strict and lenient mode.
+//!
+//! Neither may panic, and a document that parses must write back out to TOON
+//! that reads back to the same document.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serde_toon::{from_str_untrusted, repair, to_string, Value};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    if let Ok(value) = from_str_untrusted::<Value>(input) {
+        let output = to_string(&value).unwrap();
+        let back: Value = from_str_untrusted(&output)
+            .unwrap_or_else(|err| panic!("{} reading back\n{}", err, output));
+        assert_eq!(to_string(&back).unwrap(), output);
+    }
+    let _ = repair(input);
+});
@@ -0,0 +1,22 @@
+//! Writes arbitrary `Value`s and reads them back.
+//!
+//! The output must parse, and parsing it must give a value that writes the
+//! same output again. (Comparing values directly would fail on floats such
+//! as `1.0`, which are written as `1` and read back as integers.)
+
+#![no_main]
+
+use arbitrary::{Arbitrary, Unstructured};
+use libfuzzer_sys::fuzz_target;
+use serde_toon::{from_str_untrusted, to_string, Value};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(value) = Value::arbitrary(&mut Unstructured::new(data)) else {
+        return;
+    };
+
+    let output = to_string(&value).unwrap();
+    let back: Value = from_str_untrusted(&output)
+        .unwrap_or_else(|err| panic!("{} reading back\n{}", err, output));
+    assert_eq!(to_string(&back).unwrap(), output, "for {:?}", value);
+});
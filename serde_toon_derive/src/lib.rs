@@ -0,0 +1,372 @@
+//! `#[derive(ToonSchema)]` for [`serde_toon`](https://docs.rs/serde_toon).
+//!
+//! Use it through `serde_toon` with the `derive` feature, which exports the
+//! macro next to the trait it implements:
+//!
+//! ```toml
+//! [dependencies]
+//! serde_toon = { version = "0.2", features = ["derive"] }
+//! ```
+//!
+//! The derive supports structs with named fields, newtype and tuple structs,
+//! and enums whose variants are all unit variants. Doc comments on the type
+//! and its fields become the docs of the schema, and the `#[serde(...)]`
+//! attributes that change what is deserialized are followed: `rename`,
+//! `rename_all`, `default`, `skip`, `skip_deserializing`, `flatten`,
+//! `transparent` and `deny_unknown_fields`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::meta::ParseNestedMeta;
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Error, Expr, ExprLit, Fields,
+    Lit, LitStr, Meta, Result,
+};
+
+/// Derives `serde_toon::schema::ToonSchema` for a struct or unit enum.
+#[proc_macro_derive(ToonSchema, attributes(serde))]
+pub fn derive_toon_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(mut input: DeriveInput) -> Result<TokenStream2> {
+    let container = SerdeAttrs::parse(&input.attrs)?;
+    let body = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => object(&input, &container, &fields.named)?,
+            Fields::Unnamed(fields) => {
+                let types: Vec<_> = fields.unnamed.iter().map(|field| &field.ty).collect();
+                if types.len() == 1 {
+                    let ty = types[0];
+                    quote!(<#ty as ::serde_toon::schema::ToonSchema>::toon_schema())
+                } else {
+                    quote! {
+                        ::serde_toon::schema::Schema::Tuple(::std::vec![
+                            #(<#types as ::serde_toon::schema::ToonSchema>::toon_schema()),*
+                        ])
+                    }
+                }
+            }
+            Fields::Unit => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "ToonSchema cannot be derived for unit structs",
+                ))
+            }
+        },
+        Data::Enum(data) => {
+            let mut variants = Vec::new();
+            for variant in &data.variants {
+                if !matches!(variant.fields, Fields::Unit) {
+                    return Err(Error::new_spanned(
+                        variant,
+                        "ToonSchema can only be derived for enums whose variants are all unit variants",
+                    ));
+                }
+                let attrs = SerdeAttrs::parse(&variant.attrs)?;
+                if attrs.skip {
+                    continue;
+                }
+                let name = unraw(&variant.ident.to_string());
+                variants.push(attrs.rename.unwrap_or_else(|| match &container.rename_all {
+                    Some(rule) => rule.apply_to_variant(&name),
+                    None => name,
+                }));
+            }
+            quote! {
+                ::serde_toon::schema::Schema::Enum(::std::vec![
+                    #(::std::string::String::from(#variants)),*
+                ])
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "ToonSchema cannot be derived for unions",
+            ))
+        }
+    };
+
+    for param in input.generics.type_params_mut() {
+        param
+            .bounds
+            .push(parse_quote!(::serde_toon::schema::ToonSchema));
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::serde_toon::schema::ToonSchema for #name #ty_generics #where_clause {
+            fn toon_schema() -> ::serde_toon::schema::Schema {
+                #body
+            }
+        }
+    })
+}
+
+/// Returns the schema of a struct with named fields.
+fn object<'a>(
+    input: &DeriveInput,
+    container: &SerdeAttrs,
+    fields: impl IntoIterator<Item = &'a syn::Field>,
+) -> Result<TokenStream2> {
+    let mut pushes = Vec::new();
+    let mut kept = Vec::new();
+    for field in fields {
+        let attrs = SerdeAttrs::parse(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+        let ty = &field.ty;
+        let schema = quote!(<#ty as ::serde_toon::schema::ToonSchema>::toon_schema());
+        kept.push(schema.clone());
+        if attrs.flatten {
+            pushes.push(quote!(fields.extend(::serde_toon::schema::flattened(#schema));));
+            continue;
+        }
+        let ident = field
+            .ident
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default();
+        let ident = unraw(&ident);
+        let key = attrs.rename.unwrap_or_else(|| match &container.rename_all {
+            Some(rule) => rule.apply_to_field(&ident),
+            None => ident,
+        });
+        let required = (attrs.default || container.default).then(|| quote!(.with_required(false)));
+        let doc = docs(&field.attrs).map(|doc| quote!(.with_doc(#doc)));
+        pushes.push(quote! {
+            fields.push(::serde_toon::schema::Field::new(#key, #schema) #required #doc);
+        });
+    }
+
+    if container.transparent {
+        return match kept.as_slice() {
+            [schema] => Ok(schema.clone()),
+            _ => Err(Error::new_spanned(
+                &input.ident,
+                "#[serde(transparent)] requires exactly one field that is not skipped",
+            )),
+        };
+    }
+
+    let name = input.ident.to_string();
+    let doc = match docs(&input.attrs) {
+        Some(doc) => quote!(::std::option::Option::Some(::std::string::String::from(#doc))),
+        None => quote!(::std::option::Option::None),
+    };
+    let deny_unknown_fields = container.deny_unknown_fields;
+    Ok(quote! {
+        ::serde_toon::schema::object_schema(::std::any::type_name::<Self>(), || {
+            let mut fields = ::std::vec::Vec::new();
+            #(#pushes)*
+            ::serde_toon::schema::ObjectSchema {
+                name: ::std::string::String::from(#name),
+                doc: #doc,
+                fields,
+                deny_unknown_fields: #deny_unknown_fields,
+            }
+        })
+    })
+}
+
+/// Joins the lines of the doc comments in `attrs` into one line.
+fn docs(attrs: &[Attribute]) -> Option<String> {
+    let mut lines = Vec::new();
+    for attr in attrs {
+        if let Meta::NameValue(meta) = &attr.meta {
+            if let Expr::Lit(ExprLit {
+                lit: Lit::Str(text),
+                ..
+            }) = &meta.value
+            {
+                if meta.path.is_ident("doc") {
+                    let text = text.value();
+                    lines.extend(
+                        text.lines()
+                            .map(str::trim)
+                            .filter(|line| !line.is_empty())
+                            .map(String::from),
+                    );
+                }
+            }
+        }
+    }
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join(" "))
+    }
+}
+
+/// Strips the `r#` of a raw identifier, as serde does.
+fn unraw(ident: &str) -> String {
+    ident.trim_start_matches("r#").to_string()
+}
+
+/// The `#[serde(...)]` attributes that change the schema.
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<RenameRule>,
+    default: bool,
+    skip: bool,
+    flatten: bool,
+    transparent: bool,
+    deny_unknown_fields: bool,
+}
+
+impl SerdeAttrs {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut parsed = SerdeAttrs::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+            attr.parse_nested_meta(|meta| {
+                let path = &meta.path;
+                if path.is_ident("rename") {
+                    if let Some(name) = deserialize_name(&meta)? {
+                        parsed.rename = Some(name.value());
+                    }
+                } else if path.is_ident("rename_all") {
+                    if let Some(rule) = deserialize_name(&meta)? {
+                        parsed.rename_all = Some(RenameRule::parse(&rule)?);
+                    }
+                } else if path.is_ident("default") {
+                    skip_value(&meta)?;
+                    parsed.default = true;
+                } else if path.is_ident("skip") || path.is_ident("skip_deserializing") {
+                    parsed.skip = true;
+                } else if path.is_ident("flatten") {
+                    parsed.flatten = true;
+                } else if path.is_ident("transparent") {
+                    parsed.transparent = true;
+                } else if path.is_ident("deny_unknown_fields") {
+                    parsed.deny_unknown_fields = true;
+                } else {
+                    skip_value(&meta)?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(parsed)
+    }
+}
+
+/// Reads the name in `rename = "..."` or the `deserialize` name in
+/// `rename(serialize = "...", deserialize = "...")`.
+fn deserialize_name(meta: &ParseNestedMeta<'_>) -> Result<Option<LitStr>> {
+    if meta.input.peek(syn::Token![=]) {
+        return meta.value()?.parse().map(Some);
+    }
+    let mut name = None;
+    meta.parse_nested_meta(|inner| {
+        if inner.path.is_ident("deserialize") {
+            name = Some(inner.value()?.parse()?);
+        } else {
+            skip_value(&inner)?;
+        }
+        Ok(())
+    })?;
+    Ok(name)
+}
+
+/// Consumes the value of an attribute the derive does not read.
+fn skip_value(meta: &ParseNestedMeta<'_>) -> Result<()> {
+    if meta.input.peek(syn::Token![=]) {
+        meta.value()?.parse::<Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(|inner| skip_value(&inner))?;
+    }
+    Ok(())
+}
+
+/// A `#[serde(rename_all = "...")]` rule.
+#[derive(Clone, Copy)]
+enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(rule: &LitStr) -> Result<Self> {
+        Ok(match rule.value().as_str() {
+            "lowercase" => RenameRule::Lower,
+            "UPPERCASE" => RenameRule::Upper,
+            "PascalCase" => RenameRule::Pascal,
+            "camelCase" => RenameRule::Camel,
+            "snake_case" => RenameRule::Snake,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnake,
+            "kebab-case" => RenameRule::Kebab,
+            "SCREAMING-KEBAB-CASE" => RenameRule::ScreamingKebab,
+            other => {
+                return Err(Error::new_spanned(
+                    rule,
+                    format!("unknown rename rule `{}`", other),
+                ))
+            }
+        })
+    }
+
+    /// Renames a variant, written in `PascalCase`.
+    fn apply_to_variant(self, variant: &str) -> String {
+        match self {
+            RenameRule::Pascal => variant.to_string(),
+            RenameRule::Lower => variant.to_ascii_lowercase(),
+            RenameRule::Upper => variant.to_ascii_uppercase(),
+            RenameRule::Camel => lower_first(variant),
+            _ => {
+                let mut snake = String::new();
+                for (i, ch) in variant.char_indices() {
+                    if i > 0 && ch.is_uppercase() {
+                        snake.push('_');
+                    }
+                    snake.push(ch.to_ascii_lowercase());
+                }
+                self.apply_to_field(&snake)
+            }
+        }
+    }
+
+    /// Renames a field, written in `snake_case`.
+    fn apply_to_field(self, field: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => field.to_string(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => field.to_ascii_uppercase(),
+            RenameRule::Kebab => field.replace('_', "-"),
+            RenameRule::ScreamingKebab => field.to_ascii_uppercase().replace('_', "-"),
+            RenameRule::Pascal => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for ch in field.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(ch);
+                    }
+                }
+                pascal
+            }
+            RenameRule::Camel => lower_first(&RenameRule::Pascal.apply_to_field(field)),
+        }
+    }
+}
+
+fn lower_first(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,223 @@
+use serde::Deserialize;
+use serde_toon::schema::{Field, ObjectSchema, Schema};
+use serde_toon::{from_str, toon, ToonSchema, Value};
+use std::collections::HashMap;
+
+/// How urgent a ticket is.
+#[derive(Deserialize, ToonSchema, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+enum Priority {
+    Low,
+    VeryHigh,
+    #[serde(rename = "p0")]
+    Outage,
+    #[serde(skip)]
+    #[allow(dead_code)]
+    Internal,
+}
+
+#[derive(Deserialize, ToonSchema, Debug, PartialEq)]
+struct Audit {
+    created_by: String,
+    #[serde(default)]
+    revision: u32,
+}
+
+/// A support ticket.
+///
+/// Filed by a customer.
+#[derive(Deserialize, ToonSchema, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct Ticket {
+    /// Ticket number
+    ticket_id: u64,
+    #[serde(rename = "summary")]
+    title: String,
+    priority: Priority,
+    /// Free-form labels,
+    /// lowercase.
+    tags: Vec<String>,
+    assignee: Option<String>,
+    #[serde(flatten)]
+    audit: Audit,
+    #[serde(skip)]
+    cached: bool,
+    r#type: Kind,
+}
+
+#[derive(Deserialize, ToonSchema, Debug, PartialEq)]
+struct Kind(String);
+
+#[derive(Deserialize, ToonSchema, Debug, PartialEq)]
+struct Point(f64, f64);
+
+#[derive(Deserialize, ToonSchema, Debug, PartialEq)]
+#[serde(transparent)]
+struct Labels {
+    by_name: HashMap<String, Point>,
+}
+
+#[derive(Deserialize, ToonSchema, Debug, PartialEq)]
+struct Page<T> {
+    items: Vec<T>,
+    next: Option<String>,
+}
+
+/// A directory tree.
+#[derive(Deserialize, ToonSchema, Debug, PartialEq)]
+#[serde(default)]
+struct Dir {
+    name: String,
+    children: Vec<Dir>,
+}
+
+impl Default for Dir {
+    fn default() -> Self {
+        Dir {
+            name: "/".to_string(),
+            children: Vec::new(),
+        }
+    }
+}
+
+#[test]
+fn test_struct_schema() {
+    let schema = Ticket::toon_schema();
+    let Schema::Object(object) = &schema else {
+        panic!("expected an object schema, got {:?}", schema);
+    };
+    assert_eq!(object.name, "Ticket");
+    assert_eq!(
+        object.doc.as_deref(),
+        Some("A support ticket. Filed by a customer.")
+    );
+    assert!(object.deny_unknown_fields);
+    let fields: Vec<(&str, bool)> = object
+        .fields
+        .iter()
+        .map(|field| (field.name.as_str(), field.required))
+        .collect();
+    assert_eq!(
+        fields,
+        [
+            ("ticketId", true),
+            ("summary", true),
+            ("priority", true),
+            ("tags", true),
+            ("assignee", false),
+            ("created_by", true),
+            ("revision", false),
+            ("type", true),
+        ]
+    );
+    assert_eq!(
+        object.fields[2].schema,
+        Schema::Enum(vec!["low".into(), "very-high".into(), "p0".into()])
+    );
+    assert_eq!(object.fields[7].schema, Schema::String);
+
+    assert_eq!(
+        schema.prompt(),
+        "ticketId: <int>\nsummary: <string>\npriority: <low|very-high|p0>\ntags: [N]: <string>,...\n\
+         assignee: <string|null>\ncreated_by: <string>\nrevision: <int>\ntype: <string>\n\n\
+         Fields:\n- ticketId: Ticket number\n- tags: Free-form labels, lowercase."
+    );
+}
+
+#[test]
+fn test_schema_validates_what_deserializes() {
+    let schema = Ticket::toon_schema();
+    let text = "ticketId: 7\nsummary: Login fails\npriority: very-high\ntags: [1]: auth\n\
+                created_by: ada\ntype: bug";
+    let value: Value = from_str(text).unwrap();
+    assert!(schema.validate(&value).is_ok());
+    let ticket: Ticket = from_str(text).unwrap();
+    assert_eq!(ticket.priority, Priority::VeryHigh);
+    assert!(!ticket.cached);
+
+    let value: Value =
+        from_str("ticketId: x\nsummary: s\npriority: urgent\ntags: [0]:\nextra: 1\ntype: bug")
+            .unwrap();
+    let found: Vec<String> = schema
+        .violations(&value)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        found,
+        [
+            "invalid type: string \"x\", expected an integer at ticketId",
+            "unknown variant `urgent`, expected one of `low`, `very-high`, `p0` at priority",
+            "Missing field `created_by` at (root)",
+            "Unknown field `extra` at (root), expected one of: ticketId, summary, priority, \
+             tags, assignee, created_by, revision, type",
+        ]
+    );
+}
+
+#[test]
+fn test_tuple_transparent_generic_and_recursive_schemas() {
+    assert_eq!(
+        Point::toon_schema(),
+        Schema::Tuple(vec![Schema::Number, Schema::Number])
+    );
+    assert_eq!(
+        Labels::toon_schema(),
+        Schema::Map(Box::new(Point::toon_schema()))
+    );
+    assert_eq!(
+        Page::<Point>::toon_schema().skeleton(),
+        "items: [N]:\n  - [2]: <number>,<number>\nnext: <string|null>"
+    );
+
+    let schema = Dir::toon_schema();
+    assert_eq!(
+        schema,
+        Schema::Object(ObjectSchema {
+            name: "Dir".to_string(),
+            doc: Some("A directory tree.".to_string()),
+            fields: vec![
+                Field::new("name", Schema::String).with_required(false),
+                Field::new(
+                    "children",
+                    Schema::Array(Box::new(Schema::Recursive("Dir".to_string())))
+                )
+                .with_required(false),
+            ],
+            deny_unknown_fields: false,
+        })
+    );
+    assert!(schema
+        .validate(&toon!({"children": [{"name": "a", "children": [{}]}]}))
+        .is_ok());
+    assert_eq!(
+        schema
+            .validate(&toon!({"children": [{"children": [{"name": 5}]}]}))
+            .unwrap_err()
+            .to_string(),
+        "invalid type: integer `5`, expected a string at children[0].children[0].name"
+    );
+
+    let page = serde_toon::schema::to_json_schema(&Page::<Dir>::toon_schema());
+    assert_eq!(
+        page,
+        toon!({
+            "type": "object",
+            "properties": {
+                "items": {"type": "array", "items": {"$ref": "#/$defs/Dir"}},
+                "next": {"type": ["string", "null"]}
+            },
+            "required": ["items"],
+            "$defs": {
+                "Dir": {
+                    "type": "object",
+                    "description": "A directory tree.",
+                    "properties": {
+                        "name": {"type": "string"},
+                        "children": {"type": "array", "items": {"$ref": "#/$defs/Dir"}}
+                    }
+                }
+            }
+        })
+    );
+}
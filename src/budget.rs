@@ -0,0 +1,392 @@
+//! Serializing within a token budget.
+//!
+//! When packing retrieved records into an LLM prompt, the data usually has to
+//! fit a fixed number of tokens. [`to_string_within_budget`] serializes a value
+//! and, if the result is over budget, shortens its arrays and tables (keeping a
+//! configurable number of items and adding an elision marker) until it fits.
+//!
+//! Token counts come from [`metrics::token_estimate`](crate::metrics::token_estimate),
+//! so they are exact only with the `tiktoken` feature.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde_toon::budget::{to_string_within_budget, TruncationPolicy};
+//!
+//! let numbers: Vec<u32> = (0..100).collect();
+//! let toon = to_string_within_budget(&numbers, 30, &TruncationPolicy::new()).unwrap();
+//!
+//! assert!(toon.starts_with("["));
+//! assert!(toon.ends_with("more"));
+//! ```
+//!
+//! Markers are items of the arrays and rows of the tables they shorten, so
+//! truncated output does not deserialize into the item type as it is;
+//! [`from_str_truncated`] reads it back with the markers dropped.
+
+use crate::de::from_value_with_options;
+use crate::metrics::{token_estimate, Tokenizer};
+use crate::{
+    from_str_with_options, to_string_with_options, to_value, Error, Key, Result, Table, ToonMap,
+    ToonOptions, Value,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::rc::Rc;
+
+/// Which items of a long array or table survive truncation.
+///
+/// - **Head**: Keep the first items, marker last (default)
+/// - **HeadAndTail**: Keep items from both ends, marker in the middle
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Keep {
+    #[default]
+    Head,
+    HeadAndTail,
+}
+
+/// Controls how [`to_string_within_budget`] shortens a value.
+///
+/// The marker is a string in which `{n}` is replaced by the number of elided
+/// items. In arrays it is added as a string element; in tables (and arrays of
+/// objects written as tables) it fills the first cell of an extra row whose
+/// other cells are `null`. [`from_str_truncated`] drops these items and rows
+/// when reading the output back.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::budget::{Keep, TruncationPolicy};
+/// use serde_toon::metrics::Tokenizer;
+///
+/// let policy = TruncationPolicy::new()
+///     .with_keep(Keep::HeadAndTail)
+///     .with_min_items(2)
+///     .with_marker("[{n} omitted]")
+///     .with_tokenizer(Tokenizer::O200k);
+/// assert_eq!(policy.min_items, 2);
+/// ```
+#[derive(Clone, Debug)]
+pub struct TruncationPolicy {
+    pub keep: Keep,
+    pub min_items: usize,
+    pub marker: String,
+    pub tokenizer: Tokenizer,
+    pub options: ToonOptions,
+}
+
+impl Default for TruncationPolicy {
+    fn default() -> Self {
+        TruncationPolicy {
+            keep: Keep::default(),
+            min_items: 1,
+            marker: "... {n} more".to_string(),
+            tokenizer: Tokenizer::default(),
+            options: ToonOptions::default(),
+        }
+    }
+}
+
+impl TruncationPolicy {
+    /// Creates the default policy: keep at least one leading item of each
+    /// array, mark elisions with `... {n} more`, count cl100k tokens.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets which items are kept.
+    #[must_use]
+    pub fn with_keep(mut self, keep: Keep) -> Self {
+        self.keep = keep;
+        self
+    }
+
+    /// Sets the fewest items any truncated array keeps.
+    #[must_use]
+    pub fn with_min_items(mut self, min_items: usize) -> Self {
+        self.min_items = min_items;
+        self
+    }
+
+    /// Sets the elision marker; `{n}` is replaced by the number of elided items.
+    #[must_use]
+    pub fn with_marker(mut self, marker: impl Into<String>) -> Self {
+        self.marker = marker.into();
+        self
+    }
+
+    /// Sets the tokenizer used to measure the output.
+    #[must_use]
+    pub fn with_tokenizer(mut self, tokenizer: Tokenizer) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    /// Sets the serialization options for the output.
+    #[must_use]
+    pub fn with_options(mut self, options: ToonOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+/// Serializes `value` to TOON, truncating arrays and tables to fit `max_tokens`.
+///
+/// If the full output fits, it is returned unchanged. Otherwise every array and
+/// table longer than some limit is cut down to that limit plus a marker, using
+/// the largest limit (but at least `policy.min_items`) whose output fits.
+/// Short arrays are left intact, so long lists shrink first.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde::Serialize;
+/// use serde_toon::budget::{to_string_within_budget, TruncationPolicy};
+///
+/// #[derive(Serialize)]
+/// struct Doc { id: u32, title: String }
+///
+/// let docs: Vec<Doc> = (0..50)
+///     .map(|id| Doc { id, title: format!("Document {}", id) })
+///     .collect();
+///
+/// let toon = to_string_within_budget(&docs, 60, &TruncationPolicy::new()).unwrap();
+/// assert!(toon.starts_with("["));
+/// assert!(toon.contains("more,null"));
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the value cannot be serialized, or if the output is
+/// over budget even with every array cut to `policy.min_items`.
+pub fn to_string_within_budget<T>(
+    value: &T,
+    max_tokens: usize,
+    policy: &TruncationPolicy,
+) -> Result<String>
+where
+    T: ?Sized + Serialize,
+{
+    let value = to_value(value)?;
+    let fits = |text: &str| token_estimate(text, policy.tokenizer) <= max_tokens;
+
+    let full = to_string_with_options(&value, policy.options.clone())?;
+    if fits(&full) {
+        return Ok(full);
+    }
+
+    let longest = longest_sequence(&value);
+    let render = |limit: usize| {
+        to_string_with_options(&truncate(&value, limit, policy), policy.options.clone())
+    };
+
+    let smallest = render(policy.min_items)?;
+    if policy.min_items >= longest || !fits(&smallest) {
+        return Err(Error::custom(format!(
+            "output needs {} tokens after truncation, budget is {}",
+            token_estimate(&smallest, policy.tokenizer),
+            max_tokens
+        )));
+    }
+
+    // Largest limit whose output fits; `low` always fits, `high` never does.
+    let (mut low, mut high, mut best) = (policy.min_items, longest, smallest);
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        let text = render(mid)?;
+        if fits(&text) {
+            low = mid;
+            best = text;
+        } else {
+            high = mid;
+        }
+    }
+
+    Ok(best)
+}
+
+/// Deserializes output of [`to_string_within_budget`] written with `policy`,
+/// dropping its elision markers, so that shortened arrays and tables read back
+/// into their item types.
+///
+/// A marker is an array item that is a string matching `policy.marker` with
+/// any number in place of `{n}`, or a table row (or object in an array) whose
+/// first cell is such a string and whose other cells are `null`. Data that
+/// looks exactly like a marker is dropped as well.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde::{Deserialize, Serialize};
+/// use serde_toon::budget::{from_str_truncated, to_string_within_budget, TruncationPolicy};
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// struct Doc { id: u32, title: String }
+///
+/// let docs: Vec<Doc> = (0..50)
+///     .map(|id| Doc { id, title: format!("Document {}", id) })
+///     .collect();
+///
+/// let policy = TruncationPolicy::new();
+/// let toon = to_string_within_budget(&docs, 60, &policy).unwrap();
+/// let kept: Vec<Doc> = from_str_truncated(&toon, &policy).unwrap();
+/// assert_eq!(kept[..], docs[..kept.len()]);
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if `s` is not valid TOON under `policy.options`, or if
+/// what remains without the markers cannot be deserialized to type `T`.
+pub fn from_str_truncated<T>(s: &str, policy: &TruncationPolicy) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    let mut value: Value = from_str_with_options(s, policy.options.clone())?;
+    drop_markers(&mut value, policy);
+    from_value_with_options(value, Rc::new(policy.options.clone()))
+}
+
+/// Removes the markers `policy` writes from every array and table in `value`.
+fn drop_markers(value: &mut Value, policy: &TruncationPolicy) {
+    match value {
+        Value::Array(items) => {
+            items.retain(|item| !is_marker_item(item, policy));
+            items.iter_mut().for_each(|item| drop_markers(item, policy));
+        }
+        Value::Table(Table { rows, .. }) => {
+            rows.retain(|row| !is_marker_row(row.iter(), policy));
+            rows.iter_mut()
+                .flatten()
+                .for_each(|cell| drop_markers(cell, policy));
+        }
+        Value::Object(obj) => obj.iter_mut().for_each(|(_, v)| drop_markers(v, policy)),
+        _ => {}
+    }
+}
+
+fn is_marker_item(item: &Value, policy: &TruncationPolicy) -> bool {
+    match item {
+        Value::String(text) => is_marker_text(text, policy),
+        Value::Object(obj) => is_marker_row(obj.values(), policy),
+        _ => false,
+    }
+}
+
+/// Whether a row's cells are a marker followed by `null`s.
+fn is_marker_row<'a>(
+    mut cells: impl Iterator<Item = &'a Value>,
+    policy: &TruncationPolicy,
+) -> bool {
+    matches!(cells.next(), Some(Value::String(text)) if is_marker_text(text, policy))
+        && cells.all(Value::is_null)
+}
+
+fn is_marker_text(text: &str, policy: &TruncationPolicy) -> bool {
+    match policy.marker.split_once("{n}") {
+        Some((prefix, suffix)) => text
+            .strip_prefix(prefix)
+            .and_then(|rest| rest.strip_suffix(suffix))
+            .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())),
+        None => text == policy.marker,
+    }
+}
+
+/// Length of the longest array or table anywhere in `value`.
+fn longest_sequence(value: &Value) -> usize {
+    match value {
+        Value::Array(items) => items
+            .iter()
+            .map(longest_sequence)
+            .fold(items.len(), usize::max),
+        Value::Table(Table { rows, .. }) => rows
+            .iter()
+            .flatten()
+            .map(longest_sequence)
+            .fold(rows.len(), usize::max),
+        Value::Object(obj) => obj.values().map(longest_sequence).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// Copy of `value` with every array and table longer than `limit` shortened.
+fn truncate(value: &Value, limit: usize, policy: &TruncationPolicy) -> Value {
+    match value {
+        Value::Array(items) => {
+            let items: Vec<Value> = items.iter().map(|v| truncate(v, limit, policy)).collect();
+            if items.len() <= limit {
+                return Value::Array(items);
+            }
+            let marker = match items.first() {
+                Some(Value::Object(first)) => {
+                    Value::Object(marker_row(first.keys(), items.len() - limit, policy))
+                }
+                _ => Value::String(marker_text(items.len() - limit, policy)),
+            };
+            Value::Array(elide(items, limit, marker, policy.keep))
+        }
+        Value::Table(Table { headers, rows }) => {
+            let rows: Vec<Vec<Value>> = rows
+                .iter()
+                .map(|row| row.iter().map(|v| truncate(v, limit, policy)).collect())
+                .collect();
+            if rows.len() <= limit {
+                return Value::Table(Table {
+                    headers: headers.clone(),
+                    rows,
+                });
+            }
+            let mut marker = vec![Value::Null; headers.len()];
+            if let Some(cell) = marker.first_mut() {
+                *cell = Value::String(marker_text(rows.len() - limit, policy));
+            }
+            Value::Table(Table {
+                headers: headers.clone(),
+                rows: elide(rows, limit, marker, policy.keep),
+            })
+        }
+        Value::Object(obj) => Value::Object(
+            obj.iter()
+                .map(|(k, v)| (k.clone(), truncate(v, limit, policy)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Keeps `limit` of `items` and inserts `marker` where the rest were.
+fn elide<T>(mut items: Vec<T>, limit: usize, marker: T, keep: Keep) -> Vec<T> {
+    match keep {
+        Keep::Head => {
+            items.truncate(limit);
+            items.push(marker);
+            items
+        }
+        Keep::HeadAndTail => {
+            let tail_len = limit / 2;
+            let tail = items.split_off(items.len() - tail_len);
+            items.truncate(limit - tail_len);
+            items.push(marker);
+            items.extend(tail);
+            items
+        }
+    }
+}
+
+/// An object with the same keys as a table row, holding the marker in its first field.
+fn marker_row<'a>(
+    keys: impl Iterator<Item = &'a Key>,
+    elided: usize,
+    policy: &TruncationPolicy,
+) -> ToonMap {
+    let mut text = Some(marker_text(elided, policy));
+    keys.map(|key| {
+        let cell = text.take().map_or(Value::Null, Value::String);
+        (key.clone(), cell)
+    })
+    .collect()
+}
+
+fn marker_text(elided: usize, policy: &TruncationPolicy) -> String {
+    policy.marker.replace("{n}", &elided.to_string())
+}
@@ -0,0 +1,296 @@
+//! Fluent builders for constructing [`Value`]s in code.
+//!
+//! The [`toon!`](crate::toon) macro is convenient for literals, but values
+//! assembled at runtime (and tables in particular) are easier to build with
+//! method chaining:
+//!
+//! - [`Value::object`] returns an [`ObjectBuilder`]
+//! - [`Value::array`] returns an [`ArrayBuilder`]
+//! - [`Value::table`] returns a [`TableBuilder`], whose rows are tuples, arrays
+//!   or vectors of cells (anything implementing [`IntoRow`])
+//!
+//! Builders convert into [`Value`], so they nest without calling `build`.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde_toon::{to_string, Value};
+//!
+//! let value = Value::object()
+//!     .field("id", 1)
+//!     .field("owner", Value::object().field("name", "Alice"))
+//!     .field(
+//!         "items",
+//!         Value::table(["id", "sku"])
+//!             .row((1, "A1"))
+//!             .row((2, "B2")),
+//!     )
+//!     .build();
+//!
+//! assert_eq!(
+//!     to_string(&value).unwrap(),
+//!     "id: 1\nowner:\n  name: Alice\nitems: [2]{id,sku}:\n  1,A1\n  2,B2"
+//! );
+//! ```
+
+use crate::{Key, Table, ToonMap, Value};
+
+impl Value {
+    /// Starts building an object.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::Value;
+    ///
+    /// let value = Value::object().field("id", 1).field("name", "A").build();
+    /// let name = value.as_object().and_then(|obj| obj.get("name"));
+    /// assert_eq!(name.and_then(|v| v.as_str()), Some("A"));
+    /// ```
+    #[must_use]
+    pub fn object() -> ObjectBuilder {
+        ObjectBuilder::new()
+    }
+
+    /// Starts building an array.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::Value;
+    ///
+    /// let value = Value::array().item(1).item("two").build();
+    /// assert_eq!(value.as_array().map(|a| a.len()), Some(2));
+    /// ```
+    #[must_use]
+    pub fn array() -> ArrayBuilder {
+        ArrayBuilder::new()
+    }
+
+    /// Starts building a table with the given column headers.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::Value;
+    ///
+    /// let value = Value::table(["id", "name"]).row((1, "A")).row((2, "B")).build();
+    /// assert!(value.is_table());
+    /// ```
+    pub fn table<I, S>(headers: I) -> TableBuilder
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<Key>,
+    {
+        TableBuilder::new(headers)
+    }
+}
+
+/// Builds a [`Value::Object`] field by field, preserving insertion order.
+#[derive(Clone, Debug, Default)]
+pub struct ObjectBuilder {
+    map: ToonMap,
+}
+
+impl ObjectBuilder {
+    /// Creates an empty object builder.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a field, replacing any earlier field with the same key.
+    #[must_use]
+    pub fn field(mut self, key: impl Into<Key>, value: impl Into<Value>) -> Self {
+        self.map.insert(key.into(), value.into());
+        self
+    }
+
+    /// Adds a field only if `value` is `Some`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::Value;
+    ///
+    /// let nickname: Option<&str> = None;
+    /// let value = Value::object().field("id", 1).field_opt("nickname", nickname).build();
+    /// assert_eq!(value.as_object().map(|o| o.len()), Some(1));
+    /// ```
+    #[must_use]
+    pub fn field_opt<V: Into<Value>>(self, key: impl Into<Key>, value: Option<V>) -> Self {
+        match value {
+            Some(value) => self.field(key, value),
+            None => self,
+        }
+    }
+
+    /// Finishes the object.
+    #[must_use]
+    pub fn build(self) -> Value {
+        Value::Object(self.map)
+    }
+}
+
+impl From<ObjectBuilder> for Value {
+    fn from(builder: ObjectBuilder) -> Self {
+        builder.build()
+    }
+}
+
+/// Builds a [`Value::Array`] item by item.
+#[derive(Clone, Debug, Default)]
+pub struct ArrayBuilder {
+    items: Vec<Value>,
+}
+
+impl ArrayBuilder {
+    /// Creates an empty array builder.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends an item.
+    #[must_use]
+    pub fn item(mut self, value: impl Into<Value>) -> Self {
+        self.items.push(value.into());
+        self
+    }
+
+    /// Appends every item of an iterator.
+    #[must_use]
+    pub fn items<I>(mut self, values: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Value>,
+    {
+        self.items.extend(values.into_iter().map(Into::into));
+        self
+    }
+
+    /// Finishes the array.
+    #[must_use]
+    pub fn build(self) -> Value {
+        Value::Array(self.items)
+    }
+}
+
+impl From<ArrayBuilder> for Value {
+    fn from(builder: ArrayBuilder) -> Self {
+        builder.build()
+    }
+}
+
+/// Builds a [`Value::Table`] row by row.
+#[derive(Clone, Debug)]
+pub struct TableBuilder {
+    headers: Vec<Key>,
+    rows: Vec<Vec<Value>>,
+}
+
+impl TableBuilder {
+    /// Creates a table builder with the given column headers and no rows.
+    pub fn new<I, S>(headers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<Key>,
+    {
+        TableBuilder {
+            headers: headers.into_iter().map(Into::into).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Appends a row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the row does not have exactly one cell per header.
+    #[must_use]
+    pub fn row(mut self, row: impl IntoRow) -> Self {
+        let row = row.into_row();
+        assert_eq!(
+            row.len(),
+            self.headers.len(),
+            "table row has {} cells but the table has {} columns",
+            row.len(),
+            self.headers.len()
+        );
+        self.rows.push(row);
+        self
+    }
+
+    /// Appends every row of an iterator.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any row does not have exactly one cell per header.
+    #[must_use]
+    pub fn rows<I>(self, rows: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: IntoRow,
+    {
+        rows.into_iter().fold(self, TableBuilder::row)
+    }
+
+    /// Finishes the table.
+    #[must_use]
+    pub fn build(self) -> Value {
+        Value::Table(Table {
+            headers: self.headers,
+            rows: self.rows,
+        })
+    }
+}
+
+impl From<TableBuilder> for Value {
+    fn from(builder: TableBuilder) -> Self {
+        builder.build()
+    }
+}
+
+/// Types that can be turned into a table row: tuples (up to 12 cells), arrays
+/// and vectors of values convertible into [`Value`].
+pub trait IntoRow {
+    /// Converts `self` into the cells of a row.
+    fn into_row(self) -> Vec<Value>;
+}
+
+impl<T: Into<Value>> IntoRow for Vec<T> {
+    fn into_row(self) -> Vec<Value> {
+        self.into_iter().map(Into::into).collect()
+    }
+}
+
+impl<T: Into<Value>, const N: usize> IntoRow for [T; N] {
+    fn into_row(self) -> Vec<Value> {
+        self.into_iter().map(Into::into).collect()
+    }
+}
+
+macro_rules! impl_into_row_for_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: Into<Value>),+> IntoRow for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn into_row(self) -> Vec<Value> {
+                let ($($name,)+) = self;
+                vec![$($name.into()),+]
+            }
+        }
+    };
+}
+
+impl_into_row_for_tuple!(A);
+impl_into_row_for_tuple!(A, B);
+impl_into_row_for_tuple!(A, B, C);
+impl_into_row_for_tuple!(A, B, C, D);
+impl_into_row_for_tuple!(A, B, C, D, E);
+impl_into_row_for_tuple!(A, B, C, D, E, F);
+impl_into_row_for_tuple!(A, B, C, D, E, F, G);
+impl_into_row_for_tuple!(A, B, C, D, E, F, G, H);
+impl_into_row_for_tuple!(A, B, C, D, E, F, G, H, I);
+impl_into_row_for_tuple!(A, B, C, D, E, F, G, H, I, J);
+impl_into_row_for_tuple!(A, B, C, D, E, F, G, H, I, J, K);
+impl_into_row_for_tuple!(A, B, C, D, E, F, G, H, I, J, K, L);
@@ -0,0 +1,145 @@
+//! Text encodings for byte buffers (see [`BytesFormat`](crate::BytesFormat))
+//! and packed vectors (see [`VectorFormat`](crate::VectorFormat)).
+
+use crate::{Error, Result};
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes `bytes` as standard base64 with padding.
+pub(crate) fn encode_base64(bytes: &[u8]) -> String {
+    let mut output = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+/// Decodes standard base64, with or without padding.
+pub(crate) fn decode_base64(text: &str) -> Result<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let mut output = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for ch in text.chars() {
+        let digit = match ch {
+            'A'..='Z' => ch as u32 - 'A' as u32,
+            'a'..='z' => ch as u32 - 'a' as u32 + 26,
+            '0'..='9' => ch as u32 - '0' as u32 + 52,
+            '+' => 62,
+            '/' => 63,
+            _ => return Err(Error::custom(format!("invalid base64 character '{}'", ch))),
+        };
+        buffer = (buffer << 6) | digit;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    if bits >= 6 {
+        return Err(Error::custom("invalid base64 length"));
+    }
+    Ok(output)
+}
+
+/// Encodes `bytes` as lowercase hexadecimal.
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decodes hexadecimal (either case).
+pub(crate) fn decode_hex(text: &str) -> Result<Vec<u8>> {
+    if text.len() % 2 != 0 {
+        return Err(Error::custom("hex string has an odd number of digits"));
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| {
+            text.get(i..i + 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| Error::custom(format!("invalid hex string '{}'", text)))
+        })
+        .collect()
+}
+
+/// Packs `values` as little-endian `f32`s in standard base64.
+pub(crate) fn encode_f32s(values: impl Iterator<Item = f32>) -> String {
+    let bytes: Vec<u8> = values.flat_map(f32::to_le_bytes).collect();
+    encode_base64(&bytes)
+}
+
+/// Unpacks base64 text written by [`encode_f32s`].
+pub(crate) fn decode_f32s(text: &str) -> Result<Vec<f32>> {
+    let bytes = decode_base64(text)?;
+    if bytes.len() % 4 != 0 {
+        return Err(Error::custom(format!(
+            "packed vector has {} bytes, which is not a whole number of f32s",
+            bytes.len()
+        )));
+    }
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trips() {
+        for input in [
+            &b""[..],
+            b"f",
+            b"fo",
+            b"foo",
+            b"foob",
+            b"fooba",
+            b"foobar",
+            &[0, 255, 128],
+        ] {
+            let base64 = encode_base64(input);
+            assert_eq!(decode_base64(&base64).unwrap(), input);
+            assert_eq!(decode_base64(base64.trim_end_matches('=')).unwrap(), input);
+            assert_eq!(decode_hex(&encode_hex(input)).unwrap(), input);
+        }
+        assert_eq!(encode_base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(encode_hex(&[0, 171, 255]), "00abff");
+    }
+
+    #[test]
+    fn test_rejects_invalid_input() {
+        assert!(decode_base64("Zm9v!").is_err());
+        assert!(decode_base64("Z").is_err());
+        assert!(decode_hex("abc").is_err());
+        assert!(decode_hex("zz").is_err());
+        assert!(decode_f32s("AAAA").is_err());
+    }
+
+    #[test]
+    fn test_packs_f32s() {
+        let values = [0.0, -1.5, f32::MAX, f32::MIN_POSITIVE, f32::INFINITY];
+        let packed = encode_f32s(values.iter().copied());
+        assert_eq!(packed.len(), 28);
+        assert_eq!(decode_f32s(&packed).unwrap(), values);
+        assert_eq!(encode_f32s([1.0].into_iter()), "AACAPw==");
+    }
+}
@@ -0,0 +1,216 @@
+//! Serializing into chunks for prompt assembly.
+//!
+//! [`to_chunks`] writes a value as TOON split into logical pieces: one chunk
+//! per top-level field, and for arrays and tables written as blocks, one chunk
+//! for the header line and one per row or list item. The chunks are appended
+//! to a caller-provided `Vec<String>`, so several values can be collected into
+//! one buffer.
+//!
+//! Joining the chunks with `\n` gives exactly the output of
+//! [`to_string_with_options`](crate::to_string_with_options()) (pretty output's
+//! blank lines aside). A chunk never depends on its neighbours, so the same
+//! field or row always produces the same text and chunks can be deduplicated
+//! or dropped without re-parsing. Dropping rows leaves the header's declared
+//! length stale; such documents read back with
+//! [`ToonOptions::with_lenient`].
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde_toon::{chunks::to_chunks, toon};
+//!
+//! let doc = toon!({
+//!     "task": "triage",
+//!     "tickets": [{"id": 1, "title": "Crash"}, {"id": 2, "title": "Typo"}]
+//! });
+//! let mut chunks = Vec::new();
+//! to_chunks(&doc, &mut chunks).unwrap();
+//! assert_eq!(
+//!     chunks,
+//!     ["task: triage", "tickets: [2]{id,title}:", "  1,Crash", "  2,Typo"]
+//! );
+//! assert_eq!(chunks.join("\n"), serde_toon::to_string(&doc).unwrap());
+//! ```
+
+use crate::{to_string_with_options, LineEnding, Result, ToonOptions};
+use serde::Serialize;
+
+/// Serializes `value` as TOON, appending its chunks to `chunks`.
+///
+/// See the [module documentation](self) for where chunks are split.
+///
+/// # Errors
+///
+/// Returns an error if `value` cannot be serialized.
+pub fn to_chunks<T>(value: &T, chunks: &mut Vec<String>) -> Result<()>
+where
+    T: ?Sized + Serialize,
+{
+    to_chunks_with_options(value, chunks, ToonOptions::default())
+}
+
+/// Serializes `value` as TOON with custom options, appending its chunks to
+/// `chunks`.
+///
+/// `options.pretty` is ignored, since blank lines between sections would
+/// belong to no chunk, and so are `options.line_ending` and
+/// `options.trailing_newline`: chunks hold lines joined by `\n`, and are
+/// joined by the caller.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{chunks::to_chunks_with_options, toon, ToonOptions};
+///
+/// let mut chunks = Vec::new();
+/// let options = ToonOptions::new().with_indent(4);
+/// to_chunks_with_options(&toon!([{"a": [1, 2]}, "x"]), &mut chunks, options).unwrap();
+/// assert_eq!(chunks, ["[2]:", "    - a: [2]: 1,2", "    - x"]);
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if `value` cannot be serialized.
+pub fn to_chunks_with_options<T>(
+    value: &T,
+    chunks: &mut Vec<String>,
+    options: ToonOptions,
+) -> Result<()>
+where
+    T: ?Sized + Serialize,
+{
+    let indent = options.indent;
+    let options = ToonOptions {
+        pretty: false,
+        line_ending: LineEnding::Lf,
+        trailing_newline: false,
+        ..options
+    };
+    split(&to_string_with_options(value, options)?, indent, chunks);
+    Ok(())
+}
+
+/// Splits serialized TOON at top-level lines and, inside blocks opened by an
+/// array header, at the rows or items one level down.
+fn split(toon: &str, indent: usize, chunks: &mut Vec<String>) {
+    let item_prefix = " ".repeat(indent);
+    let mut current: Option<String> = None;
+    let mut in_array = false;
+
+    for line in toon.lines() {
+        let starts_chunk = if !line.starts_with(' ') {
+            in_array = opens_array(line);
+            true
+        } else {
+            in_array
+                && line.starts_with(&item_prefix)
+                && !line[item_prefix.len()..].starts_with(' ')
+        };
+        match &mut current {
+            Some(chunk) if !starts_chunk => {
+                chunk.push('\n');
+                chunk.push_str(line);
+            }
+            _ => chunks.extend(current.replace(line.to_string())),
+        }
+    }
+    chunks.extend(current);
+}
+
+/// Whether a top-level line is the header of an array or table written as a
+/// block, like `items: [2]:` or `[3]{id,name}:`. Quoted keys end in `":`, and
+/// inline arrays have their items after the colon.
+fn opens_array(line: &str) -> bool {
+    line.ends_with("]:") || line.ends_with("}:")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_str, from_str_with_options, to_string, toon, Value};
+
+    fn chunks_of(value: &Value) -> Vec<String> {
+        let mut chunks = Vec::new();
+        to_chunks(value, &mut chunks).unwrap();
+        assert_eq!(chunks.join("\n"), to_string(value).unwrap());
+        chunks
+    }
+
+    #[test]
+    fn test_top_level_fields() {
+        let value = toon!({
+            "name": "a: b",
+            "owner": {"id": 1, "tags": ["x", "y"]},
+            "empty": [],
+            "\"odd]\"": {"k": null}
+        });
+        assert_eq!(
+            chunks_of(&value),
+            [
+                "name: \"a: b\"",
+                "owner:\n  id: 1\n  tags: [2]: x,y",
+                "empty: [0]:",
+                "\"\\\"odd]\\\"\":\n  k: null",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rows_and_items() {
+        let value = toon!({
+            "rows": [{"id": 1}, {"id": 2}],
+            "mixed": [{"a": 1, "b": [{"c": 1}, {"c": 2}]}, [1, 2], "s"]
+        });
+        assert_eq!(
+            chunks_of(&value),
+            [
+                "rows: [2]{id}:",
+                "  1",
+                "  2",
+                "mixed: [3]:",
+                "  - a: 1\n    b: [2]{c}:\n      1\n      2",
+                "  - [2]: 1,2",
+                "  - s",
+            ]
+        );
+
+        assert_eq!(chunks_of(&toon!([1, 2])), ["[2]: 1,2"]);
+        assert_eq!(chunks_of(&toon!("text")), ["text"]);
+        assert_eq!(
+            chunks_of(&toon!([{"id": 1}, {"id": 2}])),
+            ["[2]{id}:", "  1", "  2"]
+        );
+    }
+
+    #[test]
+    fn test_appends_and_drops() {
+        let mut chunks = vec!["# context".to_string()];
+        to_chunks(&toon!({"a": 1}), &mut chunks).unwrap();
+        to_chunks_with_options(
+            &toon!({"rows": [{"id": 1}, {"id": 2}, {"id": 3}], "b": true}),
+            &mut chunks,
+            ToonOptions::pretty(),
+        )
+        .unwrap();
+        assert_eq!(
+            chunks,
+            [
+                "# context",
+                "a: 1",
+                "rows: [3]{id}:",
+                "  1",
+                "  2",
+                "  3",
+                "b: true"
+            ]
+        );
+
+        // Dropping a row keeps the document readable in lenient mode
+        chunks.remove(4);
+        let doc = chunks[2..].join("\n");
+        assert!(from_str::<Value>(&doc).is_err());
+        let value: Value =
+            from_str_with_options(&doc, ToonOptions::new().with_lenient(true)).unwrap();
+        assert_eq!(value, toon!({"rows": [{"id": 1}, {"id": 3}], "b": true}));
+    }
+}
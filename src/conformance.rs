@@ -0,0 +1,400 @@
+//! Checking interop with the reference TOON implementation against its golden
+//! fixtures (requires the `json` feature).
+//!
+//! The TypeScript reference implementation at
+//! <https://github.com/johannschopplich/toon> is tested against a shared set
+//! of fixture files, laid out as:
+//!
+//! ```text
+//! tests/fixtures/
+//!   encode/*.json   JSON `input` and the TOON text it is written as
+//!   decode/*.json   TOON text as `input` and the JSON value it is read as
+//! ```
+//!
+//! Each file holds a `tests` list of cases with a `name`, the `input`, the
+//! `expected` output, optional `options` and, for input that must be
+//! rejected, `shouldError`. [`run`] runs every case against this crate and
+//! returns a [`Report`], so compatibility with the TypeScript implementation
+//! can be checked against a checkout of the fixtures, or fixtures of your own
+//! in the same layout. The fixtures in this crate's own `tests/fixtures` are
+//! local cases in that layout, not the upstream set.
+//!
+//! Options map to [`ToonOptions`]:
+//!
+//! | Option | Maps to |
+//! |--------|---------|
+//! | `delimiter` | [`ToonOptions::delimiter`] |
+//! | `indent` | [`ToonOptions::indent`], and the required indentation width when decoding strictly |
+//! | `lengthMarker` | [`ToonOptions::length_marker`] |
+//! | `strict` | not [`ToonOptions::lenient`]; decoding is strict by default, as in the reference |
+//!
+//! Cases with any other option, or with `keyFolding` or `expandPaths` set to
+//! anything but `"off"`, are skipped. Encoded text must match exactly, and is
+//! written with [`CompatMode::ReferenceTs`]. Decoded values are compared as
+//! data, as the reference suite compares them: key order does not matter,
+//! and `1.0` equals `1`.
+//!
+//! ## Examples
+//!
+//! ```rust,no_run
+//! // After `git clone https://github.com/johannschopplich/toon`
+//! let report = serde_toon::conformance::run("toon/tests/fixtures").unwrap();
+//! println!("{}", report);
+//! ```
+
+use crate::{
+    from_str_with_options, json, to_string_with_options, CompatMode, Delimiter, Error, Result,
+    ToonMap, ToonOptions, Value,
+};
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The outcome of a conformance [`run`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    /// The number of cases that passed.
+    pub passed: usize,
+    /// The cases that failed, with what went wrong.
+    pub failures: Vec<Case>,
+    /// The cases that were not run, with the option that ruled them out.
+    pub skipped: Vec<Case>,
+}
+
+impl Report {
+    /// Returns `true` if no case failed.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Lists the failures under a line of counts:
+///
+/// ```text
+/// 42 passed, 2 failed, 2 skipped
+/// FAILED decode/arrays.json: rejects blank lines inside arrays: expected an error, got {"items":[1,2]}
+/// FAILED decode/primitives.json: rejects unknown escapes: expected an error, got {"s":"\\x"}
+/// ```
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} passed, {} failed, {} skipped",
+            self.passed,
+            self.failures.len(),
+            self.skipped.len()
+        )?;
+        for case in &self.failures {
+            write!(f, "\nFAILED {}", case)?;
+        }
+        Ok(())
+    }
+}
+
+/// A fixture case that failed or was skipped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Case {
+    /// The fixture file, relative to the directory passed to [`run`].
+    pub file: PathBuf,
+    /// The name of the case.
+    pub name: String,
+    /// Why the case failed or was skipped.
+    pub reason: String,
+}
+
+impl fmt::Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.file.display(), self.name, self.reason)
+    }
+}
+
+#[derive(Deserialize)]
+struct Fixture {
+    tests: Vec<Test>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Test {
+    name: String,
+    input: Value,
+    #[serde(default)]
+    expected: Value,
+    #[serde(default)]
+    options: ToonMap,
+    #[serde(default)]
+    should_error: bool,
+}
+
+enum Outcome {
+    Passed,
+    Failed(String),
+    Skipped(String),
+}
+
+#[derive(Clone, Copy)]
+enum Direction {
+    Encode,
+    Decode,
+}
+
+/// Runs the fixtures in `dir` against this crate.
+///
+/// `dir` is the fixtures directory, holding `encode` and `decode`
+/// directories of `.json` files, or a checkout of the reference repository,
+/// whose fixtures are in `tests/fixtures`. Either directory may be missing.
+///
+/// # Errors
+///
+/// Returns an error if `dir` has neither directory, or a fixture file cannot
+/// be read or is not in the fixture format, naming the file as an
+/// [`Error::File`].
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::conformance;
+///
+/// let dir = std::env::temp_dir().join("serde_toon_conformance_example");
+/// std::fs::create_dir_all(dir.join("decode")).unwrap();
+/// std::fs::write(dir.join("decode/arrays.json"), r#"{"tests": [
+///     {"name": "reads inline arrays", "input": "tags[2]: a,b", "expected": {"tags": ["a", "b"]}},
+///     {"name": "rejects short arrays", "input": "tags[3]: a,b", "expected": null, "shouldError": true}
+/// ]}"#).unwrap();
+///
+/// let report = conformance::run(&dir).unwrap();
+/// assert!(report.is_success(), "{}", report);
+/// assert_eq!(report.to_string(), "2 passed, 0 failed, 0 skipped");
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub fn run(dir: impl AsRef<Path>) -> Result<Report> {
+    let dir = dir.as_ref();
+    let nested = dir.join("tests").join("fixtures");
+    let root = if !has_fixtures(dir) && has_fixtures(&nested) {
+        nested.as_path()
+    } else {
+        dir
+    };
+    if !has_fixtures(root) {
+        return Err(Error::io(&format!(
+            "No `encode` or `decode` fixtures directory in {}",
+            dir.display()
+        )));
+    }
+
+    let mut report = Report::default();
+    for (name, direction) in [("encode", Direction::Encode), ("decode", Direction::Decode)] {
+        let category = root.join(name);
+        if !category.is_dir() {
+            continue;
+        }
+        let mut files = fs::read_dir(&category)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| entry.map(|entry| entry.path()))
+                    .collect::<std::io::Result<Vec<_>>>()
+            })
+            .map_err(|e| file_error(&category, None, Error::io(&e.to_string())))?;
+        files.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+        files.sort();
+        for path in files {
+            let file = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+            for test in read_fixture(&path)?.tests {
+                let (cases, reason) = match run_case(&test, direction) {
+                    Outcome::Passed => {
+                        report.passed += 1;
+                        continue;
+                    }
+                    Outcome::Failed(reason) => (&mut report.failures, reason),
+                    Outcome::Skipped(reason) => (&mut report.skipped, reason),
+                };
+                cases.push(Case {
+                    file: file.clone(),
+                    name: test.name,
+                    reason,
+                });
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn has_fixtures(dir: &Path) -> bool {
+    dir.join("encode").is_dir() || dir.join("decode").is_dir()
+}
+
+fn read_fixture(path: &Path) -> Result<Fixture> {
+    let text =
+        fs::read_to_string(path).map_err(|e| file_error(path, None, Error::io(&e.to_string())))?;
+    serde_json::from_str(&text).map_err(|err| {
+        let line = Some(err.line()).filter(|&line| line > 0);
+        file_error(path, line, json::convert_error(err, &text, 0))
+    })
+}
+
+fn file_error(path: &Path, line: Option<usize>, error: Error) -> Error {
+    Error::File {
+        path: path.to_path_buf(),
+        line,
+        error: Box::new(error),
+    }
+}
+
+fn run_case(test: &Test, direction: Direction) -> Outcome {
+    let options = match case_options(&test.options, direction) {
+        Ok(options) => options,
+        Err(reason) => return Outcome::Skipped(reason),
+    };
+    let reason = match direction {
+        Direction::Encode => match (to_string_with_options(&test.input, options), &test.expected) {
+            (Err(_), _) if test.should_error => None,
+            (Ok(toon), _) if test.should_error => {
+                Some(format!("expected an error, got {:?}", toon))
+            }
+            (Ok(toon), Value::String(expected)) if toon == *expected => None,
+            (Ok(toon), expected) => Some(format!(
+                "expected {:?}, got {:?}",
+                expected.as_str().unwrap_or_default(),
+                toon
+            )),
+            (Err(err), _) => Some(format!("expected output, got error: {}", err)),
+        },
+        Direction::Decode => {
+            let Some(input) = test.input.as_str() else {
+                return Outcome::Failed("the input is not a string".to_string());
+            };
+            match from_str_with_options::<Value>(input, options) {
+                Err(_) if test.should_error => None,
+                Ok(value) if test.should_error => Some(format!(
+                    "expected an error, got {}",
+                    json::to_json_string(&value)
+                )),
+                Ok(value) if value.canonical_eq(&test.expected) => None,
+                Ok(value) => Some(format!(
+                    "expected {}, got {}",
+                    json::to_json_string(&test.expected),
+                    json::to_json_string(&value)
+                )),
+                Err(err) => Some(format!("expected a value, got error: {}", err)),
+            }
+        }
+    };
+    reason.map_or(Outcome::Passed, Outcome::Failed)
+}
+
+/// Returns the options for a case, or why they have no equivalent.
+fn case_options(
+    fixture: &ToonMap,
+    direction: Direction,
+) -> std::result::Result<ToonOptions, String> {
+    let mut options = ToonOptions::new();
+    let mut strict = true;
+    for (name, value) in fixture.iter() {
+        let unsupported = || format!("unsupported option `{}: {}`", name, value);
+        match (name.as_str(), value) {
+            ("delimiter", Value::String(delimiter)) => {
+                options.delimiter = match delimiter.as_str() {
+                    "," => Delimiter::Comma,
+                    "\t" => Delimiter::Tab,
+                    "|" => Delimiter::Pipe,
+                    _ => return Err(unsupported()),
+                };
+            }
+            ("indent", value) => match value.as_u64().and_then(|n| usize::try_from(n).ok()) {
+                Some(indent) => options.indent = indent,
+                None => return Err(unsupported()),
+            },
+            ("lengthMarker", Value::String(marker)) => {
+                let mut chars = marker.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(marker), None) => options.length_marker = Some(marker),
+                    _ => return Err(unsupported()),
+                }
+            }
+            ("lengthMarker", Value::Bool(false)) => options.length_marker = None,
+            ("strict", Value::Bool(value)) => strict = *value,
+            ("keyFolding" | "expandPaths", Value::String(mode)) if mode == "off" => {}
+            // Only read with key folding on
+            ("flattenDepth", _) => {}
+            _ => return Err(unsupported()),
+        }
+    }
+    match direction {
+        Direction::Encode => options.compat = CompatMode::ReferenceTs,
+        Direction::Decode => {
+            options.lenient = !strict;
+            if strict {
+                options.indent_width = Some(options.indent);
+            }
+        }
+    }
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_fixtures(dir: &Path, files: &[(&str, &str)]) {
+        for (name, text) in files {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+    }
+
+    #[test]
+    fn reports_failures_and_skips_unsupported_options() {
+        let dir = std::env::temp_dir().join("serde_toon_conformance_test");
+        let _ = fs::remove_dir_all(&dir);
+        write_fixtures(
+            &dir.join("tests/fixtures"),
+            &[
+                (
+                    "encode/objects.json",
+                    r#"{"version": "1.4", "category": "encode", "tests": [
+                        {"name": "pipes", "input": {"a": [1, 2]}, "expected": "a[2|]: 1|2", "options": {"delimiter": "|"}},
+                        {"name": "wrong", "input": {"a": 1}, "expected": "a: 2"},
+                        {"name": "folds", "input": {"a": {"b": 1}}, "expected": "a.b: 1", "options": {"keyFolding": "safe"}}
+                    ]}"#,
+                ),
+                (
+                    "decode/numbers.json",
+                    r#"{"tests": [
+                        {"name": "floats equal integers", "input": "n: 1.0", "expected": {"n": 1}},
+                        {"name": "lenient lengths", "input": "a[3]: 1", "expected": {"a": [1]}, "options": {"strict": false}},
+                        {"name": "strict indentation", "input": "a:\n   b: 1", "expected": null, "shouldError": true, "options": {"indent": 2}}
+                    ]}"#,
+                ),
+                ("decode/notes.txt", "not a fixture"),
+            ],
+        );
+
+        let report = run(&dir).unwrap();
+        assert_eq!((report.passed, report.failures.len()), (4, 1), "{}", report);
+        assert_eq!(
+            report.failures[0].to_string(),
+            format!(
+                "{}: wrong: expected \"a: 2\", got \"a: 1\"",
+                Path::new("encode/objects.json").display()
+            )
+        );
+        assert_eq!(report.skipped[0].name, "folds");
+        assert_eq!(
+            report.skipped[0].reason,
+            "unsupported option `keyFolding: safe`"
+        );
+        assert!(!report.is_success());
+        assert!(report
+            .to_string()
+            .starts_with("4 passed, 1 failed, 1 skipped\nFAILED "));
+
+        write_fixtures(&dir, &[("decode/broken.json", "{\"tests\": [\n  {]}")]);
+        let err = run(&dir).unwrap_err();
+        assert!(matches!(err, Error::File { line: Some(2), .. }), "{}", err);
+        assert!(run(dir.join("decode")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,555 @@
+//! CSV and TSV interop for tables (requires the `csv` feature).
+//!
+//! Spreadsheet exports are the most common source of tabular data, and TOON
+//! tables carry the same information in fewer tokens. This module reads
+//! delimited text into [`Table`]s and writes tables back out, using the `csv`
+//! crate for parsing and quoting:
+//!
+//! - [`Table::from_csv_reader`] parses CSV (or TSV, or any single-byte ASCII
+//!   delimiter) from any [`io::Read`]
+//! - [`Table::from_csv_records`] builds a table from already-split records,
+//!   such as a stream of `csv::StringRecord`s
+//! - [`Table::write_csv`] writes a table to any [`io::Write`]
+//!
+//! By default cells are typed like unquoted TOON values: `true`, `false` and
+//! numbers are read as such, and an empty cell is `null`. As in PostgreSQL's
+//! CSV format, a quoted empty cell (`""`) is an empty string, so `null` and
+//! `""` both survive a round trip.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde_toon::csv::CsvOptions;
+//! use serde_toon::{to_string, Table};
+//!
+//! let export = "id,name,score\n1,Alice,9.5\n2,\"Bob, Jr.\",\n";
+//! let table = Table::from_csv_reader(export.as_bytes(), &CsvOptions::new()).unwrap();
+//!
+//! assert_eq!(
+//!     to_string(&table).unwrap(),
+//!     "[2]{id,name,score}:\n  1,Alice,9.5\n  2,\"Bob, Jr.\",null"
+//! );
+//!
+//! let mut csv = Vec::new();
+//! table.write_csv(&mut csv, &CsvOptions::new()).unwrap();
+//! assert_eq!(String::from_utf8(csv).unwrap(), export);
+//! ```
+
+use crate::{to_string, Error, Key, Result, Table, Value};
+use csv_core::WriteResult;
+use std::io;
+
+/// Options for reading and writing delimited text.
+///
+/// Renames map a CSV column name to a table header: on reading, column `from`
+/// becomes header `to`; on writing, header `to` is written as column `from`.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::csv::CsvOptions;
+///
+/// let options = CsvOptions::tsv()
+///     .with_rename("Customer ID", "id")
+///     .with_infer_types(false);
+/// assert_eq!(options.delimiter, '\t');
+/// ```
+#[derive(Clone, Debug)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    pub has_headers: bool,
+    pub headers: Option<Vec<String>>,
+    pub renames: Vec<(String, String)>,
+    pub infer_types: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: ',',
+            has_headers: true,
+            headers: None,
+            renames: Vec::new(),
+            infer_types: true,
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Creates options for comma-separated input with a header row.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates options for tab-separated input with a header row.
+    #[must_use]
+    pub fn tsv() -> Self {
+        Self::new().with_delimiter('\t')
+    }
+
+    /// Sets the field delimiter.
+    #[must_use]
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Sets whether the first record is a header row. Default is `true`.
+    ///
+    /// Without a header row, columns are named by [`with_headers`](Self::with_headers)
+    /// or, failing that, `column1`, `column2` and so on; no header row is written.
+    #[must_use]
+    pub fn with_has_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        self
+    }
+
+    /// Sets the column names, replacing the header row if there is one.
+    #[must_use]
+    pub fn with_headers<I, S>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.headers = Some(headers.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Maps CSV column `from` to table header `to`.
+    #[must_use]
+    pub fn with_rename(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.renames.push((from.into(), to.into()));
+        self
+    }
+
+    /// Sets whether cells are typed (`null`, booleans, numbers) or kept as
+    /// strings. Default is `true`.
+    #[must_use]
+    pub fn with_infer_types(mut self, infer_types: bool) -> Self {
+        self.infer_types = infer_types;
+        self
+    }
+
+    fn header_for(&self, column: &str) -> Key {
+        Key::from(
+            self.renames
+                .iter()
+                .find(|(from, _)| from == column)
+                .map_or(column, |(_, to)| to),
+        )
+    }
+
+    fn delimiter_byte(&self) -> Result<u8> {
+        u8::try_from(self.delimiter)
+            .ok()
+            .filter(u8::is_ascii)
+            .ok_or_else(|| Error::custom("CSV delimiter must be a single-byte ASCII character"))
+    }
+
+    fn column_for<'a>(&'a self, header: &'a str) -> &'a str {
+        self.renames
+            .iter()
+            .find(|(_, to)| to == header)
+            .map_or(header, |(from, _)| from)
+    }
+}
+
+impl Table {
+    /// Reads delimited text into a table.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::csv::CsvOptions;
+    /// use serde_toon::{Table, Value};
+    ///
+    /// let tsv = "Customer ID\tplan\n7\tpro\n";
+    /// let options = CsvOptions::tsv().with_rename("Customer ID", "id");
+    /// let table = Table::from_csv_reader(tsv.as_bytes(), &options).unwrap();
+    /// assert_eq!(table.column("id").unwrap(), vec![&Value::from(7)]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading fails, the input is not UTF-8, the
+    /// delimiter is not ASCII, or a record has more fields than there are
+    /// columns.
+    pub fn from_csv_reader<R: io::Read>(mut reader: R, options: &CsvOptions) -> Result<Table> {
+        let mut text = String::new();
+        reader
+            .read_to_string(&mut text)
+            .map_err(|e| Error::io(&e.to_string()))?;
+
+        let delimiter = options.delimiter_byte()?;
+        let mut reader = ::csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(text.as_bytes());
+
+        let mut header_row = None;
+        let mut rows = Vec::new();
+        let mut record = ::csv::StringRecord::new();
+        let mut start = 0;
+        while reader.read_record(&mut record).map_err(csv_error)? {
+            let end = usize::try_from(reader.position().byte()).unwrap_or(text.len());
+            if options.has_headers && header_row.is_none() {
+                header_row = Some(record.iter().map(str::to_string).collect::<Vec<_>>());
+            } else {
+                // The csv crate does not report quoting, so look at the raw
+                // record to tell `""` from an empty cell
+                let quoted = if record.iter().any(str::is_empty) {
+                    quoted_fields(&text.as_bytes()[start..end], delimiter)
+                } else {
+                    Vec::new()
+                };
+                let row: Vec<Value> = record
+                    .iter()
+                    .enumerate()
+                    .map(|(i, cell)| {
+                        if cell.is_empty() && quoted.get(i) == Some(&true) {
+                            Value::String(String::new())
+                        } else {
+                            cell_value(cell, options)
+                        }
+                    })
+                    .collect();
+                rows.push(row);
+            }
+            start = end;
+        }
+
+        let headers = header_row.unwrap_or_else(|| {
+            let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+            (1..=width).map(|i| format!("column{}", i)).collect()
+        });
+        table_from_rows(headers.iter(), rows, options)
+    }
+
+    /// Builds a table from a header record and data records.
+    ///
+    /// Records are any iterables of string-like cells, so a stream of
+    /// `csv::StringRecord`s can be passed as is. Column names go through
+    /// [`CsvOptions::headers`] and renames, and cells are typed unless
+    /// [`CsvOptions::infer_types`] is off. Short records are padded with
+    /// `null`. Records carry no quoting, so empty cells are `null` when types
+    /// are inferred.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::csv::CsvOptions;
+    /// use serde_toon::{to_string, Table};
+    ///
+    /// let mut reader = csv::Reader::from_reader("sku,qty\nA1,2\nB2,\n".as_bytes());
+    /// let headers = reader.headers().unwrap().clone();
+    /// let records = reader.records().collect::<Result<Vec<_>, _>>().unwrap();
+    /// let table = Table::from_csv_records(&headers, &records, &CsvOptions::new()).unwrap();
+    /// assert_eq!(to_string(&table).unwrap(), "[2]{sku,qty}:\n  A1,2\n  B2,null");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a record has more cells than there are columns.
+    pub fn from_csv_records<H, R>(headers: H, records: R, options: &CsvOptions) -> Result<Table>
+    where
+        H: IntoIterator,
+        H::Item: AsRef<str>,
+        R: IntoIterator,
+        R::Item: IntoIterator,
+        <R::Item as IntoIterator>::Item: AsRef<str>,
+    {
+        let rows = records.into_iter().map(|record| {
+            record
+                .into_iter()
+                .map(|cell| cell_value(cell.as_ref(), options))
+                .collect()
+        });
+        table_from_rows(headers, rows, options)
+    }
+
+    /// Writes the table as delimited text with a trailing newline after every
+    /// record. `null` cells are written empty and empty strings as `""`.
+    ///
+    /// An array of objects with the same keys can be written by converting it
+    /// with `Table::try_from` first.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::csv::CsvOptions;
+    /// use serde_toon::{toon, Table};
+    ///
+    /// let rows = toon!([{"id": 1, "note": "a\tb"}, {"id": 2, "note": null}, {"id": 3, "note": ""}]);
+    /// let table = Table::try_from(rows).unwrap();
+    /// let mut out = Vec::new();
+    /// table.write_csv(&mut out, &CsvOptions::tsv()).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(out).unwrap(),
+    ///     "id\tnote\n1\t\"a\tb\"\n2\t\n3\t\"\"\n"
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the delimiter is not ASCII or writing fails.
+    pub fn write_csv<W: io::Write>(&self, mut writer: W, options: &CsvOptions) -> Result<()> {
+        let mut record_writer = csv_core::WriterBuilder::new()
+            .delimiter(options.delimiter_byte()?)
+            .build();
+
+        let mut out = Vec::new();
+        if options.has_headers {
+            let columns: Vec<Option<&str>> = match &options.headers {
+                Some(names) => names.iter().map(|name| Some(name.as_str())).collect(),
+                None => self
+                    .headers
+                    .iter()
+                    .map(|h| Some(options.column_for(h)))
+                    .collect(),
+            };
+            write_record(&mut record_writer, &mut out, &columns);
+        }
+        for row in &self.rows {
+            let cells: Vec<Option<String>> = row.iter().map(field_text).collect();
+            let cells: Vec<Option<&str>> = cells.iter().map(Option::as_deref).collect();
+            write_record(&mut record_writer, &mut out, &cells);
+        }
+
+        writer
+            .write_all(&out)
+            .map_err(|e| Error::io(&e.to_string()))
+    }
+}
+
+fn cell_value(cell: &str, options: &CsvOptions) -> Value {
+    if options.infer_types {
+        Value::from_cell_text(cell.to_string())
+    } else {
+        Value::String(cell.to_string())
+    }
+}
+
+fn table_from_rows<H, R>(headers: H, rows: R, options: &CsvOptions) -> Result<Table>
+where
+    H: IntoIterator,
+    H::Item: AsRef<str>,
+    R: IntoIterator<Item = Vec<Value>>,
+{
+    let headers: Vec<Key> = match &options.headers {
+        Some(names) => names.iter().map(|name| options.header_for(name)).collect(),
+        None => headers
+            .into_iter()
+            .map(|name| options.header_for(name.as_ref()))
+            .collect(),
+    };
+
+    let mut table_rows = Vec::new();
+    for (i, mut row) in rows.into_iter().enumerate() {
+        if row.len() > headers.len() {
+            return Err(Error::custom(format!(
+                "CSV record {} has {} fields, expected {}",
+                i + 1,
+                row.len(),
+                headers.len()
+            )));
+        }
+        row.resize(headers.len(), Value::Null);
+        table_rows.push(row);
+    }
+
+    Ok(Table {
+        headers,
+        rows: table_rows,
+    })
+}
+
+fn csv_error(err: ::csv::Error) -> Error {
+    match err.position() {
+        Some(position) => Error::syntax(
+            usize::try_from(position.line()).unwrap_or(usize::MAX),
+            1,
+            &err.to_string(),
+        ),
+        None => Error::io(&err.to_string()),
+    }
+}
+
+/// Which fields of a raw record were wrapped in quotes.
+fn quoted_fields(raw: &[u8], delimiter: u8) -> Vec<bool> {
+    let mut quoted = vec![false];
+    let mut in_quotes = false;
+    let mut at_start = true;
+    for &byte in raw
+        .iter()
+        .skip_while(|&&byte| matches!(byte, b'\r' | b'\n'))
+    {
+        match byte {
+            b'"' => {
+                if at_start {
+                    if let Some(last) = quoted.last_mut() {
+                        *last = true;
+                    }
+                }
+                in_quotes = !in_quotes;
+            }
+            b'\r' | b'\n' if !in_quotes => break,
+            _ if byte == delimiter && !in_quotes => {
+                quoted.push(false);
+                at_start = true;
+                continue;
+            }
+            _ => {}
+        }
+        at_start = false;
+    }
+    quoted
+}
+
+/// The text of a cell, or `None` for `null`.
+fn field_text(value: &Value) -> Option<String> {
+    Some(match value {
+        Value::Null => return None,
+        Value::String(s) => s.clone(),
+        #[cfg(feature = "datetime")]
+        Value::Date(dt) => dt.to_rfc3339(),
+        Value::Array(_) | Value::Object(_) | Value::Table(_) => {
+            to_string(value).unwrap_or_default()
+        }
+        other => other.to_string(),
+    })
+}
+
+/// Writes one record, quoting fields where needed. `None` is written as an
+/// empty field and an empty string as `""`, which the csv crate's own
+/// `Writer` cannot tell apart.
+fn write_record(writer: &mut csv_core::Writer, out: &mut Vec<u8>, fields: &[Option<&str>]) {
+    let mut buf = [0; 1024];
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            let (_, written) = writer.delimiter(&mut buf);
+            out.extend_from_slice(&buf[..written]);
+        }
+        match field {
+            None => {}
+            // A record of one empty field is quoted by the terminator
+            Some("") if fields.len() == 1 => {}
+            Some("") => out.extend_from_slice(b"\"\""),
+            Some(text) => {
+                let mut input = text.as_bytes();
+                loop {
+                    let (result, read, written) = writer.field(input, &mut buf);
+                    out.extend_from_slice(&buf[..written]);
+                    input = &input[read..];
+                    if result == WriteResult::InputEmpty {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    let (_, written) = writer.terminator(&mut buf);
+    out.extend_from_slice(&buf[..written]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_quoted_fields() {
+        let text = "a,b\r\n\"x, \"\"y\"\"\",\"two\nlines\"\r\n\n3,\n";
+        let table = Table::from_csv_reader(text.as_bytes(), &CsvOptions::new()).unwrap();
+        assert_eq!(
+            Value::Table(table),
+            Value::table(["a", "b"])
+                .row(("x, \"y\"", "two\nlines"))
+                .row((3, Value::Null))
+                .build()
+        );
+        assert!(Table::from_csv_reader("a\n1,2".as_bytes(), &CsvOptions::new()).is_err());
+        assert!(Table::from_csv_reader(&b"a\n\xff"[..], &CsvOptions::new()).is_err());
+        assert!(
+            Table::from_csv_reader("a".as_bytes(), &CsvOptions::new().with_delimiter('§')).is_err()
+        );
+    }
+
+    #[test]
+    fn keeps_empty_strings_apart_from_null() {
+        let table = Value::table(["id", "a", "b"])
+            .row((1, "", Value::Null))
+            .row((2, Value::Null, ""))
+            .row((3, "\"\"", "x, y"))
+            .build();
+        let Value::Table(table) = table else {
+            unreachable!()
+        };
+        let mut out = Vec::new();
+        table.write_csv(&mut out, &CsvOptions::new()).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text, "id,a,b\n1,\"\",\n2,,\"\"\n3,\"\"\"\"\"\",\"x, y\"\n");
+        assert_eq!(
+            Table::from_csv_reader(text.as_bytes(), &CsvOptions::new()).unwrap(),
+            table
+        );
+
+        let single = Table::from_parts(
+            vec!["s".to_string()],
+            vec![vec![Value::from("")], vec![Value::from("x")]],
+        )
+        .unwrap();
+        let mut out = Vec::new();
+        single.write_csv(&mut out, &CsvOptions::new()).unwrap();
+        assert_eq!(String::from_utf8(out.clone()).unwrap(), "s\n\"\"\nx\n");
+        assert_eq!(
+            Table::from_csv_reader(out.as_slice(), &CsvOptions::new()).unwrap(),
+            single
+        );
+    }
+
+    #[test]
+    fn maps_headers() {
+        let options = CsvOptions::new()
+            .with_has_headers(false)
+            .with_infer_types(false);
+        let table = Table::from_csv_reader("1,x\n2,y".as_bytes(), &options).unwrap();
+        assert_eq!(
+            Value::Table(table),
+            Value::table(["column1", "column2"])
+                .row(("1", "x"))
+                .row(("2", "y"))
+                .build()
+        );
+
+        let options = CsvOptions::new()
+            .with_delimiter('|')
+            .with_rename("Full Name", "name");
+        let table = Table::from_csv_reader("Full Name|age\nAda|36".as_bytes(), &options).unwrap();
+        assert_eq!(
+            Value::Table(table.clone()),
+            Value::table(["name", "age"]).row(("Ada", 36)).build()
+        );
+
+        let mut out = Vec::new();
+        table.write_csv(&mut out, &options).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "Full Name|age\nAda|36\n");
+    }
+
+    #[test]
+    fn reads_string_records() {
+        let mut reader = ::csv::ReaderBuilder::new()
+            .delimiter(b';')
+            .from_reader("k;v\na;1\nb;true\n".as_bytes());
+        let headers = reader.headers().unwrap().clone();
+        let records: Vec<::csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+        let table = Table::from_csv_records(&headers, &records, &CsvOptions::new()).unwrap();
+        assert_eq!(
+            Value::Table(table),
+            Value::table(["k", "v"])
+                .row(("a", 1))
+                .row(("b", true))
+                .build()
+        );
+    }
+}
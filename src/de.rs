@@ -7,10 +7,11 @@
 //!
 //! The deserializer handles all TOON format features:
 //!
-//! - **Single-pass parsing**: Efficient O(n) parsing with no backtracking
+//! - **Single-pass parsing**: The input is split into tokens line by line and
+//!   parsed with a small lookahead buffer, with no backtracking
 //! - **Format detection**: Automatically recognizes inline, list, and tabular formats
 //! - **Error reporting**: Detailed error messages with line/column information
-//! - **Indentation tracking**: Proper handling of nested structures
+//! - **Indentation tracking**: Nesting follows the indentation blocks found by the tokenizer
 //!
 //! ## Usage
 //!
@@ -39,742 +40,1624 @@
 //! let nums: Vec<i32> = from_str("[3]: 1,2,3").unwrap();
 //! assert_eq!(nums, vec![1, 2, 3]);
 //! ```
-
-use crate::options::Delimiter;
-use crate::{Error, Number, Result, ToonMap, Value};
-use serde::de::IntoDeserializer;
+//!
+//! ## Lenient Mode
+//!
+//! With [`ToonOptions::with_lenient`], the parser recovers from common defects in
+//! LLM-generated TOON (wrong array lengths, trailing prose, unterminated strings)
+//! and records a [`Warning`] for each fix. See also [`crate::repair`].
+
+use crate::bytes;
+use crate::error::{Warning, WarningKind};
+use crate::lexer::{self, Header, IndentIssue, LineKind, Token, TokenKind, Tokens, Values};
+use crate::options::{
+    BytesFormat, Delimiter, DuplicateKeys, EnumRepr, QuotePolicy, VectorEncoding,
+};
+use crate::path::{Path, PathSegment};
+use crate::raw;
+use crate::ser::{widen_f32, write_number, MAX_SAFE_INTEGER};
+use crate::span::{Span, Spans};
+use crate::trace;
+use crate::value::NUMBER_TOKEN;
+#[cfg(feature = "rust_decimal")]
+use crate::value::{parse_decimal, DECIMAL_TOKEN, VALUE_TOKEN};
+use crate::{Error, Key, Number, Result, Table, ToonMap, ToonOptions, Value};
 use serde::{de, forward_to_deserialize_any};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::rc::Rc;
+
+/// A value read from a document that may have been cut off, returned by
+/// [`from_str_partial`](crate::from_str_partial).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Partial<T> {
+    /// Everything read up to `offset`.
+    pub value: T,
+    /// The byte offset in the input where the text that was read ends: the
+    /// end of the input if the document was complete, and otherwise the
+    /// start of the line it was cut off in, from which generation can resume.
+    pub offset: usize,
+    /// Whether the whole document was read as it is.
+    pub complete: bool,
+    /// The arrays and tables that hold fewer items than their headers
+    /// declare, because the rest was cut off.
+    pub warnings: Vec<Warning>,
+}
 
 /// The TOON deserializer.
 ///
 /// Parses TOON format strings into Rust values implementing `Deserialize`.
-/// Created via [`Deserializer::from_str`].
+/// Created via [`Deserializer::from_str`] or [`Deserializer::with_options`].
+///
+/// `&mut Deserializer` works with `serde_transcode::transcode` for converting
+/// TOON to other formats. A root object or table is then visited one field or
+/// row at a time as it is parsed; each row, and each field value once the
+/// visitor asks for it, is built in full before being visited. Root structs
+/// and maps are read field by field in the same way.
 pub struct Deserializer<'de> {
     input: &'de str,
-    position: usize,
-    line: usize,
-    column: usize,
-    indent_stack: Vec<usize>, // Stack of indentation levels for nested scopes
-    current_indent: usize,    // Current line's detected indentation
+    tokens: Tokens<'de>,
+    // End of the last token with content that was consumed
+    last_end: usize,
+    options: Rc<ToonOptions>,
+    warnings: Vec<Warning>,
+    // Recorded only when enabled by `with_spans`
+    spans: Option<Spans>,
+    path: Path,
+    // Objects and arrays currently open, checked against `max_depth`
+    depth: usize,
+    // Delimiter of the first array read
+    delimiter: Option<Delimiter>,
+    // Set by `with_trailing_content`, which stops at trailing content
+    // instead of rejecting it
+    allow_trailing: bool,
+    // Byte offset of the trailing content left unread
+    trailing: Option<usize>,
+    // Keys read so far, so that each distinct key is allocated once and
+    // shared by every object and table that uses it
+    keys: HashSet<Key>,
 }
 
 impl<'de> Deserializer<'de> {
     #[allow(clippy::should_implement_trait)]
     pub fn from_str(input: &'de str) -> Self {
+        Self::with_options(input, ToonOptions::default())
+    }
+
+    /// Creates a deserializer over bytes of TOON text, such as a network
+    /// payload.
+    ///
+    /// The parser works on `str` slices of the input, so the whole input is
+    /// validated as UTF-8 up front, as by [`std::str::from_utf8`], before any
+    /// of it is parsed. Invalid UTF-8 is reported as [`Error::Syntax`] at the
+    /// line and column where it starts.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{Deserializer, Error};
+    ///
+    /// assert!(Deserializer::from_slice(b"x: 1").is_ok());
+    /// let err = Deserializer::from_slice(b"x: 1\ny: \xff").err().unwrap();
+    /// assert!(matches!(err, Error::Syntax { line: 2, col: 4, .. }));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `input` is not valid UTF-8.
+    pub fn from_slice(input: &'de [u8]) -> Result<Self> {
+        Self::from_slice_with_options(input, ToonOptions::default())
+    }
+
+    /// Like [`Deserializer::from_slice`], with custom options. An input over
+    /// [`ToonOptions::max_document_size`] is rejected before it is checked
+    /// for UTF-8.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `input` is too large or not valid UTF-8.
+    pub fn from_slice_with_options(input: &'de [u8], options: ToonOptions) -> Result<Self> {
+        if let Some(max) = options.max_document_size.filter(|&max| input.len() > max) {
+            return Err(Error::LimitExceeded {
+                limit: "max_document_size".to_string(),
+                max,
+                line: 1,
+                col: 1,
+            });
+        }
+        let input = std::str::from_utf8(input).map_err(|e| {
+            let valid = String::from_utf8_lossy(&input[..e.valid_up_to()]);
+            let line = valid.matches('\n').count() + 1;
+            let column = valid
+                .rsplit('\n')
+                .next()
+                .map_or(0, |last| last.chars().count())
+                + 1;
+            Error::syntax(line, column, "Invalid UTF-8")
+        })?;
+        Ok(Self::with_options(input, options))
+    }
+
+    /// Creates a deserializer with custom options (e.g. lenient parsing).
+    pub fn with_options(input: &'de str, options: ToonOptions) -> Self {
+        let tab_width = options.indent_width.unwrap_or(options.indent);
         Deserializer {
             input,
-            position: 0,
-            line: 1,
-            column: 1,
-            indent_stack: vec![0], // Start with base indentation level
-            current_indent: 0,
+            tokens: Tokens::new(input)
+                .with_indent_width(options.indent_width)
+                .with_tabs(options.allow_tabs, tab_width)
+                .with_code_fences(options.strip_code_fences),
+            last_end: 0,
+            options: Rc::new(options),
+            warnings: Vec::new(),
+            spans: None,
+            path: Path::root(),
+            depth: 0,
+            delimiter: None,
+            allow_trailing: false,
+            trailing: None,
+            keys: HashSet::new(),
         }
     }
 
-    fn peek_char(&self) -> Option<char> {
-        self.input[self.position..].chars().next()
+    /// Records the location of every parsed value, for [`Self::spans`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::Deserialize;
+    /// use serde_toon::Deserializer;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Point { x: i32, y: i32 }
+    ///
+    /// let mut de = Deserializer::from_str("x: 1\ny: 20").with_spans();
+    /// let point = Point::deserialize(&mut de).unwrap();
+    /// assert_eq!(point.y, 20);
+    /// assert_eq!(de.spans().unwrap().find("y").unwrap().range(), 8..10);
+    /// ```
+    #[must_use]
+    pub fn with_spans(mut self) -> Self {
+        self.spans = Some(Spans::default());
+        self
+    }
+
+    /// Returns the spans recorded so far, or `None` unless created with
+    /// [`Self::with_spans`].
+    #[must_use]
+    pub fn spans(&self) -> Option<&Spans> {
+        self.spans.as_ref()
+    }
+
+    /// Consumes the deserializer, returning the recorded spans.
+    #[must_use]
+    pub fn into_spans(self) -> Option<Spans> {
+        self.spans
+    }
+
+    /// Accepts content after the end of the document, such as commentary
+    /// appended to a model's answer, leaving it unread for
+    /// [`Self::remainder`] instead of rejecting it with
+    /// [`Error::TrailingContent`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::Deserialize;
+    /// use serde_toon::{Deserializer, Value};
+    ///
+    /// let mut de = Deserializer::from_str("[2]: 1,2\nHope this helps!").with_trailing_content();
+    /// let numbers = Vec::<u32>::deserialize(&mut de).unwrap();
+    /// assert_eq!(numbers, [1, 2]);
+    /// assert_eq!(de.remainder(), "Hope this helps!");
+    /// ```
+    #[must_use]
+    pub fn with_trailing_content(mut self) -> Self {
+        self.allow_trailing = true;
+        self
+    }
+
+    /// Returns the input left unread after the document, starting at the
+    /// first trailing content, or an empty string if there is none.
+    #[must_use]
+    pub fn remainder(&self) -> &'de str {
+        self.trailing.map_or("", |start| &self.input[start..])
+    }
+
+    /// Returns the delimiter of the first array read, as named by its header
+    /// or detected with [`ToonOptions::with_sniff_delimiters`], or `None`
+    /// before any array is read.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::Deserialize;
+    /// use serde_toon::{Deserializer, Delimiter, ToonOptions, Value};
+    ///
+    /// let options = ToonOptions::new().with_sniff_delimiters(true);
+    /// let mut de = Deserializer::with_options("tags: [3]: a|b|c", options);
+    /// Value::deserialize(&mut de).unwrap();
+    /// assert_eq!(de.detected_delimiter(), Some(&Delimiter::Pipe));
+    /// ```
+    #[must_use]
+    pub fn detected_delimiter(&self) -> Option<&Delimiter> {
+        self.delimiter.as_ref()
+    }
+
+    /// Returns the warnings recorded while parsing in lenient mode.
+    #[must_use]
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Consumes the deserializer, returning the recorded warnings.
+    #[must_use]
+    pub fn into_warnings(self) -> Vec<Warning> {
+        self.warnings
+    }
+
+    fn warn(&mut self, kind: WarningKind, (line, column): (usize, usize), message: &str) {
+        self.warnings
+            .push(Warning::at_position(kind, line, column, message));
+    }
+
+    pub(crate) fn peek_kind(&mut self) -> Option<&TokenKind<'de>> {
+        self.tokens.peek().map(|token| &token.kind)
+    }
+
+    pub(crate) fn next_token(&mut self) -> Option<Token<'de>> {
+        let token = self.tokens.next()?;
+        if token.has_content() {
+            self.last_end = token.span.end;
+        }
+        Some(token)
     }
 
-    fn next_char(&mut self) -> Option<char> {
-        if let Some(ch) = self.input[self.position..].chars().next() {
-            self.position += ch.len_utf8();
-            if ch == '\n' {
-                self.line += 1;
-                self.column = 1;
-            } else {
-                self.column += 1;
+    /// Returns the line and column of the next token with content, or of the
+    /// end of the input.
+    pub(crate) fn position(&mut self) -> (usize, usize) {
+        let mut n = 0;
+        while let Some(token) = self.tokens.peek_nth(n) {
+            if token.has_content() {
+                return (token.line, token.column);
             }
-            Some(ch)
-        } else {
-            None
+            n += 1;
         }
+        self.location(self.input.len())
     }
 
-    fn skip_whitespace(&mut self) {
-        while let Some(ch) = self.peek_char() {
-            if ch.is_whitespace() && ch != '\n' {
-                self.next_char();
-            } else {
-                break;
+    /// Returns the line and column of a byte offset in the input.
+    fn location(&self, offset: usize) -> (usize, usize) {
+        let before = &self.input[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        (
+            before.matches('\n').count() + 1,
+            before[line_start..].chars().count() + 1,
+        )
+    }
+
+    /// Returns the byte offset, line and column where the next value starts:
+    /// its first token, on the next line if the current one ends before an
+    /// indented block, or the end of the current line for an empty value.
+    fn value_start(&mut self) -> (usize, usize, usize) {
+        let mut n = 0;
+        loop {
+            let Some(token) = self.tokens.peek_nth(n) else {
+                let (line, column) = self.location(self.last_end);
+                return (self.last_end, line, column);
+            };
+            let found = (token.span.start, token.line, token.column);
+            match token.kind {
+                TokenKind::Indent | TokenKind::BadIndent(_) => n += 1,
+                TokenKind::Newline => {
+                    let block = matches!(
+                        self.tokens.peek_nth(n + 1).map(|next| &next.kind),
+                        Some(TokenKind::Indent)
+                    );
+                    if !block {
+                        return found;
+                    }
+                    n += 2;
+                }
+                _ => return found,
             }
         }
     }
 
-    /// Checks if we're at the end of input
-    fn at_end(&self) -> bool {
-        self.position >= self.input.len()
+    /// Consumes the rest of the current line, returning how many blocks it
+    /// opened (after a list item's dash).
+    pub(crate) fn skip_line(&mut self) -> usize {
+        let mut opened = 0;
+        while let Some(token) = self.next_token() {
+            match token.kind {
+                TokenKind::Newline => break,
+                TokenKind::Indent => opened += 1,
+                _ => {}
+            }
+        }
+        opened
     }
 
-    /// Detects the indentation level at the current position
-    /// Counts leading spaces on current line
-    fn detect_indent_level(&self) -> usize {
-        let mut count = 0;
-        let mut pos = self.position;
+    /// Consumes the end of the current line and the start of an indented
+    /// block after it, returning whether there is one.
+    pub(crate) fn open_block(&mut self) -> Result<bool> {
+        self.skip_line();
+        if matches!(self.peek_kind(), Some(TokenKind::Indent)) {
+            self.next_token();
+            self.check_indent()?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
 
-        while pos < self.input.len() {
-            match self.input.as_bytes()[pos] {
-                b' ' => {
-                    count += 1;
-                    pos += 1;
+    /// Advances to the next line of the current block, returning `false` at
+    /// its end. Blocks indented deeper than the lines around them are read as
+    /// part of the block (after [`Self::check_indent`] has reported them);
+    /// `open` counts those entered so far.
+    pub(crate) fn next_line(&mut self, open: &mut usize) -> Result<bool> {
+        loop {
+            match self.peek_kind() {
+                None => return Ok(false),
+                Some(TokenKind::Dedent) if *open == 0 => return Ok(false),
+                Some(TokenKind::Dedent) => *open -= 1,
+                Some(TokenKind::Indent) => *open += 1,
+                Some(TokenKind::BadIndent(_)) => {
+                    self.check_indent()?;
+                    continue;
                 }
-                _ => break,
+                Some(TokenKind::Newline) => {}
+                Some(_) => return Ok(true),
             }
+            self.next_token();
         }
-
-        count
     }
 
-    /// Pushes a new indentation scope
-    fn push_indent(&mut self, level: usize) {
-        self.indent_stack.push(level);
+    /// Consumes the indentation problems reported before the next line's
+    /// content: an error, or a warning in lenient mode.
+    pub(crate) fn check_indent(&mut self) -> Result<()> {
+        while let Some(Token {
+            kind: TokenKind::BadIndent(issue),
+            line,
+            column,
+            span,
+        }) = self.tokens.peek()
+        {
+            let (issue, at) = (*issue, (*line, *column));
+            let line_start = self.input[..span.start].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = self.input[span.start..]
+                .find('\n')
+                .map_or(self.input.len(), |i| span.start + i);
+            let context = self.input[line_start..line_end].trim_end();
+            match issue {
+                IndentIssue::Tab if !self.options.lenient => {
+                    return Err(Error::syntax(
+                        at.0,
+                        at.1,
+                        "Tabs are not allowed in indentation",
+                    ));
+                }
+                IndentIssue::Width { expected, found } if !self.options.lenient => {
+                    return Err(Error::indentation_error(
+                        at.0, at.1, expected, found, context,
+                    ));
+                }
+                IndentIssue::Tab => {
+                    self.warn(WarningKind::Indentation, at, "read tab in indentation");
+                }
+                IndentIssue::Width { expected, found } => {
+                    let msg = format!(
+                        "read line indented {} spaces as part of the block at {}",
+                        found, expected
+                    );
+                    self.warn(WarningKind::Indentation, at, &msg);
+                }
+            }
+            self.next_token();
+        }
+        Ok(())
     }
 
-    /// Pops the current indentation scope
-    fn pop_indent(&mut self) -> Option<usize> {
-        if self.indent_stack.len() > 1 {
-            self.indent_stack.pop()
-        } else {
-            None
+    /// Consumes the `Dedent` ending the current block.
+    pub(crate) fn close_block(&mut self) {
+        if matches!(self.peek_kind(), Some(TokenKind::Dedent)) {
+            self.next_token();
         }
     }
 
-    /// Skips whitespace on the same line only (no newlines)
-    fn skip_whitespace_same_line(&mut self) {
-        while let Some(ch) = self.peek_char() {
-            if ch == ' ' || ch == '\t' {
-                self.next_char();
-            } else {
-                break;
+    /// Skips the rest of a block whose `Indent` was just consumed, up to and
+    /// including its `Dedent`.
+    fn skip_block(&mut self) {
+        let mut depth = 1;
+        while let Some(token) = self.next_token() {
+            match token.kind {
+                TokenKind::Indent => depth += 1,
+                TokenKind::Dedent => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {}
             }
         }
     }
 
-    fn parse_string(&mut self) -> Result<String> {
-        if self.peek_char() == Some('"') {
-            self.next_char(); // consume opening quote
-            let mut result = String::new();
-
-            while let Some(ch) = self.next_char() {
-                match ch {
-                    '"' => return Ok(result),
-                    '\\' => {
-                        match self.next_char() {
-                            Some('\\') => result.push('\\'),
-                            Some('"') => result.push('"'),
-                            Some('n') => result.push('\n'),
-                            Some('r') => result.push('\r'),
-                            Some('t') => result.push('\t'),
-                            Some('b') => result.push('\u{0008}'), // backspace
-                            Some('f') => result.push('\u{000C}'), // form feed
-                            Some('0') => result.push('\0'),       // null
-                            Some('u') => {
-                                // Unicode escape: \uXXXX
-                                let mut hex = String::new();
-                                for _ in 0..4 {
-                                    match self.next_char() {
-                                        Some(ch) if ch.is_ascii_hexdigit() => hex.push(ch),
-                                        _ => return Err(Error::syntax(
-                                            self.line,
-                                            self.column,
-                                            "Invalid unicode escape sequence (expected 4 hex digits)"
-                                        )),
-                                    }
-                                }
-
-                                let code_point = u32::from_str_radix(&hex, 16).map_err(|_| {
-                                    Error::syntax(
-                                        self.line,
-                                        self.column,
-                                        "Invalid hex in unicode escape",
-                                    )
-                                })?;
-
-                                let ch = char::from_u32(code_point).ok_or_else(|| {
-                                    Error::syntax(
-                                        self.line,
-                                        self.column,
-                                        "Invalid unicode code point",
-                                    )
-                                })?;
-
-                                result.push(ch);
-                            }
-                            Some(other) => {
-                                // Unknown escape - preserve literally (lenient parsing)
-                                result.push('\\');
-                                result.push(other);
-                            }
-                            None => {
-                                return Err(Error::syntax(
-                                    self.line,
-                                    self.column,
-                                    "Unexpected end of input in string",
-                                ))
-                            }
-                        }
-                    }
-                    other => result.push(other),
-                }
+    /// Closes a block that should hold nothing more, such as a list item
+    /// after its value. Further lines are an error, or skipped with a warning
+    /// in lenient mode.
+    pub(crate) fn finish_block(&mut self) -> Result<()> {
+        let mut open = 0;
+        while self.next_line(&mut open)? {
+            let at = self.position();
+            if !self.options.lenient {
+                return Err(Error::syntax(at.0, at.1, "Unexpected content after value"));
             }
-            Err(Error::syntax(self.line, self.column, "Unterminated string"))
+            self.warn(WarningKind::TrailingContent, at, "ignored line after value");
+            open += self.skip_line();
+        }
+        self.close_block();
+        Ok(())
+    }
+
+    /// Parses a complete document.
+    ///
+    /// The first non-blank line decides what the document is:
+    ///
+    /// - an array document if it starts with an array header (`[3]: ...`, `[2]{a,b}:`)
+    /// - an object document if it is a field (`key: ...`, `"quoted key": ...`), or
+    ///   if the document is empty
+    /// - otherwise a scalar document: a single primitive, where an unquoted string
+    ///   runs to the end of the line
+    ///
+    /// Content left after the root value is an error; in lenient mode it is
+    /// skipped with a warning.
+    pub(crate) fn parse_root(&mut self) -> Result<Value> {
+        self.check_document_size()?;
+        let value = if self.spans.is_some() {
+            self.record_span(Self::parse_root_value)?
         } else {
-            // Unquoted string - read until delimiter or newline
-            let start = self.position;
-            while let Some(ch) = self.peek_char() {
-                if ch == ':'
-                    || ch == ','
-                    || ch == '\n'
-                    || ch == '\t'
-                    || ch == '|'
-                    || ch == ']'
-                    || ch == '}'
-                {
+            self.parse_root_value()?
+        };
+        self.finish_root()?;
+        Ok(value)
+    }
+
+    fn parse_root_value(&mut self) -> Result<Value> {
+        self.check_indent()?;
+        match self.peek_kind() {
+            None => Ok(Value::Object(ToonMap::new())),
+            Some(TokenKind::TableHeader(_)) => self.parse_line_value(),
+            Some(TokenKind::Key(_)) => self.parse_object(),
+            Some(_) => self.parse_root_scalar(),
+        }
+    }
+
+    /// Checks that nothing but whitespace follows the root value, or records
+    /// where trailing content starts when it is allowed.
+    pub(crate) fn finish_root(&mut self) -> Result<()> {
+        while let Some(token) = self.tokens.peek() {
+            if token.has_content() {
+                let (start, at) = (token.span.start, (token.line, token.column));
+                if self.allow_trailing {
+                    self.trailing = Some(start);
                     break;
                 }
-                self.next_char();
+                if !self.options.lenient {
+                    return Err(Error::TrailingContent {
+                        line: at.0,
+                        col: at.1,
+                    });
+                }
+                self.warn(WarningKind::TrailingContent, at, "ignored trailing content");
+                while self.next_token().is_some() {}
+                break;
             }
+            self.next_token();
+        }
+        Ok(())
+    }
 
-            if start == self.position {
-                Err(Error::syntax(self.line, self.column, "Expected string"))
-            } else {
-                Ok(self.input[start..self.position].trim().to_string())
-            }
+    /// Parses the single primitive of a scalar document, which is the whole
+    /// of its line.
+    pub(crate) fn parse_root_scalar(&mut self) -> Result<Value> {
+        let Some(first) = self.next_token() else {
+            return Ok(Value::Object(ToonMap::new()));
+        };
+        let start = first.span.start;
+        if first.kind != TokenKind::Newline {
+            self.skip_line();
         }
+        let text = &self.input[start..self.last_end.max(start)];
+        self.parse_scalar(text, (first.line, first.column))
     }
 
-    fn parse_number(&mut self) -> Result<Number> {
-        let start = self.position;
+    /// Runs `parse`, recording the span of what it consumed at the current path
+    /// extended by `segment` when spans are tracked.
+    fn spanned<T>(
+        &mut self,
+        segment: impl FnOnce() -> PathSegment,
+        parse: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        if self.spans.is_none() {
+            return parse(self);
+        }
+        self.path.push(segment());
+        let result = self.record_span(parse);
+        self.path.pop();
+        result
+    }
+
+    /// Runs `parse`, recording the span of what it consumed at the current path.
+    fn record_span<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let (start, line, column) = self.value_start();
+        // Claim the entry now so that containers come before their contents
+        let first_wins = self.options.duplicate_keys == DuplicateKeys::FirstWins;
+        let record = match &mut self.spans {
+            Some(spans) => spans.claim(&self.path, first_wins),
+            None => false,
+        };
+        let value = match parse(self) {
+            Ok(value) => value,
+            Err(err) => {
+                if let (true, Some(spans)) = (record, &mut self.spans) {
+                    spans.release(&self.path);
+                }
+                return Err(err);
+            }
+        };
+        if !record {
+            return Ok(value);
+        }
 
-        // Handle negative sign
-        if self.peek_char() == Some('-') {
-            self.next_char();
+        let span = Span {
+            start,
+            end: self.last_end.max(start),
+            line,
+            column,
+        };
+        if let Some(spans) = &mut self.spans {
+            spans.insert(self.path.clone(), span);
         }
+        Ok(value)
+    }
 
-        // Parse digits
-        let mut has_decimal = false;
-        while let Some(ch) = self.peek_char() {
-            if ch.is_ascii_digit() {
-                self.next_char();
-            } else if ch == '.' && !has_decimal {
-                has_decimal = true;
-                self.next_char();
-            } else {
-                break;
+    /// Records `span` for a value read from within a line, at the current
+    /// path extended by `segment`.
+    fn record(&mut self, segment: impl FnOnce() -> PathSegment, span: Span) {
+        let first_wins = self.options.duplicate_keys == DuplicateKeys::FirstWins;
+        if let Some(spans) = &mut self.spans {
+            self.path.push(segment());
+            if spans.claim(&self.path, first_wins) {
+                spans.insert(self.path.clone(), span);
             }
+            self.path.pop();
         }
+    }
 
-        let number_str = &self.input[start..self.position];
-
-        if has_decimal {
-            number_str
-                .parse::<f64>()
-                .map(Number::Float)
-                .map_err(|_| Error::syntax(self.line, self.column, "Invalid float"))
-        } else {
-            number_str
-                .parse::<i64>()
-                .map(Number::Integer)
-                .map_err(|_| Error::syntax(self.line, self.column, "Invalid integer"))
+    /// Parses a primitive that makes up the rest of a line: a quoted string,
+    /// or an unquoted keyword, number or string.
+    fn parse_scalar(&mut self, text: &str, (line, column): (usize, usize)) -> Result<Value> {
+        if text.starts_with('[') {
+            // Only a malformed array header is left as a scalar
+            let (offset, msg) = lexer::header_error(text);
+            return Err(Error::syntax(
+                line,
+                column + text[..offset].chars().count(),
+                msg,
+            ));
+        }
+        if !text.starts_with('"') {
+            return self.parse_unquoted(text, (line, column));
         }
+
+        let (string, len) = self.parse_string(text, (line, column))?;
+        self.check_after_string(&text[len..], column + text[..len].chars().count(), line)?;
+        let string = self.interpolated(string, (line, column))?;
+        Ok(Value::String(string))
     }
 
-    fn parse_bool(&mut self) -> Result<bool> {
-        let _start = self.position;
+    /// Reports anything but whitespace after a quoted string, which is an
+    /// error unless lenient.
+    fn check_after_string(&mut self, after: &str, column: usize, line: usize) -> Result<()> {
+        let rest = after.trim_start();
+        if rest.is_empty() {
+            return Ok(());
+        }
+        let at = (line, column + (after.len() - rest.len()));
+        if !self.options.lenient {
+            return Err(Error::syntax(at.0, at.1, "Unexpected content after string"));
+        }
+        self.warn(
+            WarningKind::TrailingContent,
+            at,
+            "ignored content after string",
+        );
+        Ok(())
+    }
+
+    /// Reads the quoted string at the start of `text`, returning it and the
+    /// number of bytes read. In lenient mode an unterminated string runs to
+    /// the end of `text`.
+    fn parse_string(
+        &mut self,
+        text: &str,
+        (line, column): (usize, usize),
+    ) -> Result<(String, usize)> {
+        let quoted = lexer::read_quoted(text).map_err(|(offset, msg)| {
+            Error::syntax(line, column + text[..offset].chars().count(), msg)
+        })?;
+        if !quoted.closed {
+            if !self.options.lenient {
+                return Err(Error::syntax(line, column, "Unterminated string"));
+            }
+            self.warn(
+                WarningKind::UnterminatedString,
+                (line, column),
+                "closed unterminated string",
+            );
+        }
+        for &offset in &quoted.unknown_escapes {
+            let escape: String = text[offset..].chars().take(2).collect();
+            self.warn(
+                WarningKind::UnknownEscape,
+                (line, column + text[..offset].chars().count()),
+                &format!("kept unknown escape `{}` as written", escape),
+            );
+        }
+        self.check_limit(
+            "max_string_length",
+            self.options.max_string_length,
+            quoted.value.len(),
+            line,
+            column,
+        )?;
+        Ok((quoted.value, quoted.len))
+    }
+
+    /// Reads an unquoted value: `true`, `false`, `null`, a number, or else a
+    /// string.
+    fn parse_unquoted(&self, text: &str, (line, column): (usize, usize)) -> Result<Value> {
+        let text = &*self.interpolate(text, |offset| {
+            (line, column + text[..offset].chars().count())
+        })?;
+        Ok(match text {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            "null" => Value::Null,
+            _ => match parse_number(text) {
+                // A number that would be written back differently keeps its text
+                Some(number) if self.options.preserve_number_text => {
+                    let mut written = String::new();
+                    write_number(&mut written, &number, &self.options.float_format);
+                    if written == text {
+                        Value::Number(number)
+                    } else {
+                        Value::Number(Number::Raw(text.to_string()))
+                    }
+                }
+                // The reference decoder reads an unquoted integer beyond
+                // `Number.MAX_SAFE_INTEGER` as a JavaScript number, which is
+                // how its encoder writes whole floats that large; ints that
+                // large are written quoted
+                Some(Number::Integer(i))
+                    if self.options.matches_reference()
+                        && i.unsigned_abs() > MAX_SAFE_INTEGER as u64 =>
+                {
+                    Value::Number(Number::Float(i as f64))
+                }
+                #[cfg(feature = "rust_decimal")]
+                Some(Number::Float(f)) => Value::Number(exact_decimal(text, f)),
+                Some(number) => Value::Number(number),
+                None => {
+                    self.check_limit(
+                        "max_string_length",
+                        self.options.max_string_length,
+                        text.len(),
+                        line,
+                        column,
+                    )?;
+                    Value::String(text.to_string())
+                }
+            },
+        })
+    }
 
-        // Try to match "true" or "false"
-        if self.input[self.position..].starts_with("true") {
-            for _ in 0..4 {
-                self.next_char();
+    /// Parses the value in `range` of `text`, the content of `token`'s line
+    /// from the token on: an inline array item or a table cell.
+    fn parse_cell(&mut self, token: &Token<'de>, text: &str, range: Range<usize>) -> Result<Value> {
+        let at = cell_position(token, text, &range);
+        let cell = &text[range];
+        if cell.is_empty() {
+            return Err(Error::syntax(at.0, at.1, "Expected value"));
+        }
+        if !cell.starts_with('"') {
+            return self.parse_unquoted(cell, at);
+        }
+        let (string, len) = self.parse_string(cell, at)?;
+        self.check_after_string(&cell[len..], at.1 + cell[..len].chars().count(), at.0)?;
+        Ok(Value::String(self.interpolated(string, at)?))
+    }
+
+    /// Substitutes environment variables into `text`, a string value, if
+    /// interpolation is on. `locate` gives the position of a byte offset in
+    /// `text`, for errors.
+    fn interpolate<'a>(
+        &self,
+        text: &'a str,
+        locate: impl Fn(usize) -> (usize, usize),
+    ) -> Result<Cow<'a, str>> {
+        let Some(env) = &self.options.env_lookup else {
+            return Ok(Cow::Borrowed(text));
+        };
+        lexer::interpolate(text, |name| env.get(name)).map_err(|(offset, msg)| {
+            let (line, column) = locate(offset);
+            Error::syntax(line, column, &msg)
+        })
+    }
+
+    /// Substitutes environment variables into a quoted or block string read
+    /// at `at`, if interpolation is on.
+    fn interpolated(&self, string: String, at: (usize, usize)) -> Result<String> {
+        Ok(match self.interpolate(&string, |_| at)? {
+            Cow::Borrowed(_) => string,
+            Cow::Owned(replaced) => replaced,
+        })
+    }
+
+    /// Resolves a key as written, unquoting a quoted key and expanding an alias.
+    fn parse_key(&mut self, raw: &str, at: (usize, usize)) -> Result<Key> {
+        if raw.starts_with('"') {
+            let key = self.parse_string(raw, at)?.0;
+            return Ok(self.full_key(&key));
+        }
+        self.check_limit(
+            "max_string_length",
+            self.options.max_string_length,
+            raw.len(),
+            at.0,
+            at.1,
+        )?;
+        Ok(self.full_key(raw))
+    }
+
+    /// Parses a value that starts on the current line and is not an object:
+    /// an array from its header, or a primitive.
+    pub(crate) fn parse_line_value(&mut self) -> Result<Value> {
+        let Some(token) = self.next_token() else {
+            return Ok(Value::Object(ToonMap::new()));
+        };
+        let at = (token.line, token.column);
+        match &token.kind {
+            TokenKind::TableHeader(header) => self.parse_array(&token, header),
+            TokenKind::Scalar(text) => {
+                let value = self.parse_scalar(text, at)?;
+                self.skip_line();
+                Ok(value)
             }
-            Ok(true)
-        } else if self.input[self.position..].starts_with("false") {
-            for _ in 0..5 {
-                self.next_char();
+            TokenKind::Block(lines) => {
+                let text = self.interpolated(lexer::block_text(lines), at)?;
+                self.check_limit(
+                    "max_string_length",
+                    self.options.max_string_length,
+                    text.len(),
+                    at.0,
+                    at.1,
+                )?;
+                self.skip_line();
+                Ok(Value::String(text))
             }
-            Ok(false)
-        } else {
-            Err(Error::syntax(self.line, self.column, "Expected boolean"))
+            _ => Err(Error::syntax(at.0, at.1, "Expected value")),
         }
     }
 
-    fn parse_null(&mut self) -> Result<()> {
-        if self.input[self.position..].starts_with("null") {
-            for _ in 0..4 {
-                self.next_char();
+    /// Parses an array from its header `token` through its last item or row.
+    fn parse_array(&mut self, token: &Token<'de>, header: &Header) -> Result<Value> {
+        let at = (token.line, token.column);
+        let header = &self.settle_delimiter(token, header);
+        self.nested(at, |de| {
+            let declared_length = de.declared_length(token, header)?;
+            if !header.fields.is_empty() {
+                return de.parse_table(header, declared_length, at);
             }
-            Ok(())
-        } else {
-            Err(Error::syntax(self.line, self.column, "Expected null"))
-        }
+            match de.tokens.peek() {
+                Some(Token {
+                    kind: TokenKind::Scalar(_),
+                    ..
+                }) => {
+                    let Some(items) = de.next_token() else {
+                        return Ok(Value::Array(vec![]));
+                    };
+                    de.parse_inline_array(&items, declared_length, &header.delimiter, at)
+                }
+                _ => de.parse_list_array(declared_length, at),
+            }
+        })
     }
 
-    fn parse_array(&mut self) -> Result<Value> {
-        // Parse array format like "[3]: a,b,c" or "[2]{id,name}: 1,Alice 2,Bob" or "[3]:"
-        if self.peek_char() != Some('[') {
-            return Err(Error::syntax(self.line, self.column, "Expected '['"));
+    /// Settles the delimiter of the array whose header is `token`: the one
+    /// the header names, or one sniffed from the first row if the header names
+    /// none and [`ToonOptions::sniff_delimiters`] is set.
+    pub(crate) fn settle_delimiter(&mut self, token: &Token<'de>, header: &Header) -> Header {
+        let mut header = header.clone();
+        if self.options.sniff_delimiters && !header.marked {
+            if let Some(sniffed) = self.sniff_delimiter(token, &header) {
+                header = sniffed;
+            }
         }
-        self.next_char(); // consume '['
-
-        // Parse optional length marker
-        let _length_marker = if let Some(ch) = self.peek_char() {
-            if ch == '#' {
-                self.next_char();
-                Some('#')
-            } else {
-                None
+        self.delimiter.get_or_insert(header.delimiter.clone());
+        header
+    }
+
+    /// Finds the delimiter that splits the array's first row into one value
+    /// per field, or its inline items into the declared length. When several
+    /// do, or no length is declared, the one giving the most values wins,
+    /// the earliest of comma, tab and pipe on a tie.
+    fn sniff_delimiter(&mut self, token: &Token<'de>, header: &Header) -> Option<Header> {
+        let sample = if header.fields.is_empty() {
+            match self.peek_kind() {
+                Some(TokenKind::Scalar(items)) => *items,
+                _ => return None,
             }
         } else {
-            None
+            self.first_row()?
         };
-
-        // Parse length
-        let start = self.position;
-        while let Some(ch) = self.peek_char() {
-            if ch.is_ascii_digit() {
-                self.next_char();
+        let mut best: Option<(usize, Header)> = None;
+        for delimiter in [Delimiter::Comma, Delimiter::Tab, Delimiter::Pipe] {
+            let count = Values::new(sample, &delimiter).count();
+            let fields = if header.fields.is_empty() {
+                Vec::new()
             } else {
-                break;
+                lexer::fields_with(&self.input[token.span.clone()], &delimiter)?
+            };
+            let fits = match header.length {
+                _ if !fields.is_empty() => fields.len() == count,
+                Some(length) => length == count,
+                None => true,
+            };
+            if fits && best.as_ref().map_or(true, |(most, _)| count > *most) {
+                let sniffed = Header {
+                    fields,
+                    delimiter,
+                    ..header.clone()
+                };
+                best = Some((count, sniffed));
             }
         }
+        best.map(|(_, header)| header)
+    }
 
-        let declared_length: usize = self.input[start..self.position]
-            .parse()
-            .map_err(|_| Error::syntax(self.line, self.column, "Invalid array length"))?;
-
-        // Parse optional delimiter indicator in header
-        let delimiter = if self.peek_char() == Some('|') {
-            self.next_char(); // consume '|'
-            Delimiter::Pipe
-        } else {
-            // Check for tab indicator (spaces in header)
-            let mut space_count = 0;
-            let temp_pos = self.position;
-            while self.peek_char() == Some(' ') {
-                self.next_char();
-                space_count += 1;
-            }
-            if space_count >= 4 {
-                Delimiter::Tab
-            } else {
-                // Reset position if not enough spaces
-                self.position = temp_pos;
-                Delimiter::Comma
+    /// Returns the text of the first row of the table whose header was just
+    /// read, the first line of the block below it.
+    fn first_row(&mut self) -> Option<&'de str> {
+        let input = self.input;
+        let (mut n, mut block) = (0, false);
+        loop {
+            let token = self.tokens.peek_nth(n)?;
+            match token.kind {
+                TokenKind::Indent => block = true,
+                TokenKind::Newline | TokenKind::BadIndent(_) => {}
+                _ if token.has_content() && block => {
+                    let start = token.span.start;
+                    let end = input[start..].find('\n').map_or(input.len(), |i| start + i);
+                    return Some(input[start..end].trim_end());
+                }
+                _ => return None,
             }
-        };
-
-        if self.peek_char() != Some(']') {
-            return Err(Error::syntax(self.line, self.column, "Expected ']'"));
+            n += 1;
         }
-        self.next_char(); // consume ']'
-
-        // Check if this is a table format
-        if self.peek_char() == Some('{') {
-            self.parse_table(declared_length, delimiter)
-        } else {
-            // Simple array format or list format
-            if self.peek_char() != Some(':') {
-                return Err(Error::syntax(self.line, self.column, "Expected ':'"));
-            }
-            self.next_char(); // consume ':'
+    }
 
-            if declared_length == 0 {
-                return Ok(Value::Array(vec![]));
+    /// Checks the declared length of the array header `token`, which is
+    /// missing only in lenient mode.
+    pub(crate) fn declared_length(
+        &mut self,
+        token: &Token<'de>,
+        header: &Header,
+    ) -> Result<Option<usize>> {
+        // The length follows `[`, or `[#` with a length marker
+        let marker = self.input[token.span.clone()].starts_with("[#");
+        let (line, col) = (token.line, token.column + 1 + usize::from(marker));
+        match header.length {
+            Some(length) => {
+                self.check_limit(
+                    "max_array_length",
+                    self.options.max_array_length,
+                    length,
+                    line,
+                    col,
+                )?;
+                Ok(Some(length))
             }
+            // Without a length, the items are counted as they are read
+            None => Ok(None),
+        }
+    }
 
-            self.skip_whitespace();
-
-            // Check if this is inline format (same line) or list format (next line with -)
-            if self.peek_char() == Some('\n') {
-                // List format
-                self.parse_list_array(declared_length)
-            } else {
-                // Inline format
-                self.parse_inline_array(declared_length, delimiter)
-            }
+    /// Runs `parse` on a container one level deeper, enforcing
+    /// [`ToonOptions::max_depth`]. `at` is where the container starts.
+    fn nested<T>(
+        &mut self,
+        at: (usize, usize),
+        parse: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        self.enter(at)?;
+        let result = parse(self);
+        self.leave();
+        result
+    }
+
+    /// Enters a container starting at `(line, col)`, one level deeper.
+    /// Every successful call is paired with a call to [`Self::leave`].
+    pub(crate) fn enter(&mut self, (line, col): (usize, usize)) -> Result<()> {
+        self.check_limit(
+            "max_depth",
+            self.options.max_depth,
+            self.depth + 1,
+            line,
+            col,
+        )?;
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Leaves the container last entered with [`Self::enter`].
+    pub(crate) fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Checks `value` against one of the limits in [`ToonOptions`], reporting
+    /// `line` and `col` if it is exceeded.
+    fn check_limit(
+        &self,
+        limit: &str,
+        max: Option<usize>,
+        value: usize,
+        line: usize,
+        col: usize,
+    ) -> Result<()> {
+        match max {
+            Some(max) if value > max => Err(Error::LimitExceeded {
+                limit: limit.to_string(),
+                max,
+                line,
+                col,
+            }),
+            _ => Ok(()),
         }
     }
 
-    fn parse_inline_array(
+    /// Checks that an array may hold `length` items.
+    pub(crate) fn check_array_length(
+        &self,
+        length: usize,
+        (line, col): (usize, usize),
+    ) -> Result<()> {
+        self.check_limit(
+            "max_array_length",
+            self.options.max_array_length,
+            length,
+            line,
+            col,
+        )
+    }
+
+    /// Checks the whole input against [`ToonOptions::max_document_size`]
+    /// before anything is parsed.
+    pub(crate) fn check_document_size(&self) -> Result<()> {
+        self.check_limit(
+            "max_document_size",
+            self.options.max_document_size,
+            self.input.len(),
+            1,
+            1,
+        )
+    }
+
+    /// Parses the items of an inline array, such as `a,b,c` after `[3]: `.
+    pub(crate) fn parse_inline_array(
         &mut self,
-        declared_length: usize,
-        delimiter: Delimiter,
+        token: &Token<'de>,
+        declared_length: Option<usize>,
+        delimiter: &Delimiter,
+        at: (usize, usize),
     ) -> Result<Value> {
+        let TokenKind::Scalar(text) = token.kind else {
+            return Ok(Value::Array(vec![]));
+        };
         let mut elements = Vec::new();
-
-        for i in 0..declared_length {
-            if i > 0 {
-                // Skip delimiter
-                match delimiter {
-                    Delimiter::Comma => {
-                        if self.peek_char() == Some(',') {
-                            self.next_char();
-                        }
-                    }
-                    Delimiter::Tab => {
-                        if self.peek_char() == Some('\t') {
-                            self.next_char();
-                        }
-                    }
-                    Delimiter::Pipe => {
-                        if self.peek_char() == Some('|') {
-                            self.next_char();
-                        }
-                    }
-                }
-                self.skip_whitespace();
-            }
-
-            let value = self.parse_primitive_value()?;
+        for range in Values::new(text, delimiter) {
+            let index = elements.len();
+            self.check_array_length(index + 1, cell_position(token, text, &range))?;
+            let span = cell_span(token, text, &range);
+            let value = self.parse_cell(token, text, range)?;
+            self.record(|| PathSegment::Index(index), span);
             elements.push(value);
         }
+        self.skip_line();
 
+        self.check_length(declared_length, elements.len(), at)?;
+        trace::event!(TRACE, format = "inline", len = elements.len());
         Ok(Value::Array(elements))
     }
 
-    fn parse_list_array(&mut self, declared_length: usize) -> Result<Value> {
-        let mut elements = Vec::new();
-
-        for _ in 0..declared_length {
-            // Skip to next line
-            if self.peek_char() == Some('\n') {
-                self.next_char();
-            }
-
-            // Update current indentation level for proper nested object parsing
-            self.current_indent = self.detect_indent_level();
-            self.skip_whitespace();
-
-            // Expect "- " prefix
-            if self.peek_char() != Some('-') {
-                return Err(Error::syntax(
-                    self.line,
-                    self.column,
-                    "Expected '- ' prefix in list format",
-                ));
-            }
-            self.next_char(); // consume '-'
-
-            if self.peek_char() != Some(' ') {
-                return Err(Error::syntax(
-                    self.line,
-                    self.column,
-                    "Expected space after '-'",
-                ));
+    /// Checks the number of items found in an array against its declared
+    /// length: a mismatch is an error, or a warning in lenient mode.
+    pub(crate) fn check_length(
+        &mut self,
+        declared_length: Option<usize>,
+        found: usize,
+        at: (usize, usize),
+    ) -> Result<()> {
+        match declared_length {
+            Some(declared) if declared != found => {
+                let msg = format!("array declared {} items but has {}", declared, found);
+                if !self.options.lenient {
+                    return Err(Error::syntax(at.0, at.1, &msg));
+                }
+                self.warn(WarningKind::LengthMismatch, at, &msg);
+                Ok(())
             }
-            self.next_char(); // consume ' '
+            _ => Ok(()),
+        }
+    }
 
-            let value = self.parse_value()?;
+    /// Parses the `- ` items of a list array, which follow its header in an
+    /// indented block or line up with it.
+    fn parse_list_array(
+        &mut self,
+        declared_length: Option<usize>,
+        at: (usize, usize),
+    ) -> Result<Value> {
+        let block = self.open_block()?;
+        let mut elements = Vec::new();
+        let mut open = 0;
+        while let Some(item_at) = self.next_item(&mut open, block)? {
+            let index = elements.len();
+            self.check_array_length(index + 1, item_at)?;
+            let value = self.spanned(|| PathSegment::Index(index), Self::parse_item)?;
             elements.push(value);
         }
+        if block {
+            self.close_block();
+        }
 
+        self.check_length(declared_length, elements.len(), at)?;
+        trace::event!(TRACE, format = "list", len = elements.len());
         Ok(Value::Array(elements))
     }
 
-    fn parse_table(&mut self, declared_length: usize, delimiter: Delimiter) -> Result<Value> {
-        // Parse table headers
-        if self.peek_char() != Some('{') {
-            return Err(Error::syntax(self.line, self.column, "Expected '{'"));
-        }
-        self.next_char(); // consume '{'
-
-        let mut headers = Vec::new();
-
-        while !self.at_end() && self.peek_char() != Some('}') {
-            let header = self.parse_string()?;
-            headers.push(header);
-
-            if self.peek_char() == Some(',') {
-                self.next_char();
+    /// Reads the dash of the next item of a list array, returning its
+    /// position, or `None` once the list ends. The items are in an indented
+    /// `block`, or line up with the header.
+    ///
+    /// A line without a dash is an error, or skipped with a warning in
+    /// lenient mode.
+    pub(crate) fn next_item(
+        &mut self,
+        open: &mut usize,
+        block: bool,
+    ) -> Result<Option<(usize, usize)>> {
+        loop {
+            if block {
+                if !self.next_line(open)? {
+                    return Ok(None);
+                }
             } else {
-                break;
+                self.check_indent()?;
+                if !matches!(self.peek_kind(), Some(TokenKind::Dash)) {
+                    return Ok(None);
+                }
             }
-        }
 
-        if self.peek_char() != Some('}') {
-            return Err(Error::syntax(self.line, self.column, "Expected '}'"));
+            let item_at = self.position();
+            if !matches!(self.peek_kind(), Some(TokenKind::Dash)) {
+                if !self.options.lenient {
+                    return Err(Error::syntax(
+                        item_at.0,
+                        item_at.1,
+                        "Expected '- ' prefix in list format",
+                    ));
+                }
+                self.warn(
+                    WarningKind::TrailingContent,
+                    item_at,
+                    "ignored line without '- ' in list",
+                );
+                *open += self.skip_line();
+                continue;
+            }
+            self.next_token();
+            return Ok(Some(item_at));
         }
-        self.next_char(); // consume '}'
+    }
 
-        if self.peek_char() != Some(':') {
-            return Err(Error::syntax(self.line, self.column, "Expected ':'"));
+    /// Parses a list item after its dash. The content after the dash is a
+    /// block of its own, so an object item's later fields line up with its
+    /// first one.
+    fn parse_item(&mut self) -> Result<Value> {
+        if !matches!(self.peek_kind(), Some(TokenKind::Indent)) {
+            // A lone `-` is an empty object
+            self.skip_line();
+            return Ok(Value::Object(ToonMap::new()));
         }
-        self.next_char(); // consume ':'
+        self.next_token();
+        let value = match self.peek_kind() {
+            Some(TokenKind::Key(_)) => self.parse_object()?,
+            _ => self.parse_line_value()?,
+        };
+        self.finish_block()?;
+        Ok(value)
+    }
 
-        // Parse table rows
+    fn parse_table(
+        &mut self,
+        header: &Header,
+        declared_length: Option<usize>,
+        at: (usize, usize),
+    ) -> Result<Value> {
+        let mut cursor = self.begin_table(header, declared_length, at)?;
         let mut rows = Vec::new();
-
-        for _ in 0..declared_length {
-            // Skip to next line
-            if self.peek_char() == Some('\n') {
-                self.next_char();
-            }
-            self.skip_whitespace();
-
-            if self.at_end() {
-                break;
-            }
-
-            // Parse row
-            let mut row = Vec::new();
-
-            for (i, _header) in headers.iter().enumerate() {
-                if i > 0 {
-                    // Skip delimiter
-                    match delimiter {
-                        Delimiter::Comma => {
-                            if self.peek_char() == Some(',') {
-                                self.next_char();
-                            }
-                        }
-                        Delimiter::Tab => {
-                            if self.peek_char() == Some('\t') {
-                                self.next_char();
-                            }
-                        }
-                        Delimiter::Pipe => {
-                            if self.peek_char() == Some('|') {
-                                self.next_char();
-                            }
-                        }
-                    }
-                    self.skip_whitespace();
-                }
-
-                let value = self.parse_primitive_value()?;
-                row.push(value);
-            }
-
+        while let Some(row) = self.next_table_row(&mut cursor)? {
             rows.push(row);
         }
-
-        Ok(Value::Table { headers, rows })
+        Ok(Value::Table(Table {
+            headers: cursor.headers,
+            rows,
+        }))
     }
 
-    fn parse_object(&mut self) -> Result<Value> {
-        let mut map = ToonMap::new();
-
-        // Detect the base indentation for this object
-        let base_indent = self.current_indent;
-
-        // Handle empty object case - peek ahead
-        self.skip_whitespace_same_line();
-        if self.peek_char() == Some('\n') || self.at_end() {
-            // Empty object - no fields on same line, check next line
-            if self.peek_char() == Some('\n') {
-                let saved_pos = self.position;
-                let saved_line = self.line;
-                let saved_col = self.column;
-
-                self.next_char(); // consume newline
-                let next_indent = self.detect_indent_level();
-
-                // If next line has LESS indent (not same), object is empty
-                // Exception: if base_indent is 0, we need to check if there's content
-                if base_indent > 0 && next_indent < base_indent {
-                    // Restore position
-                    self.position = saved_pos;
-                    self.line = saved_line;
-                    self.column = saved_col;
-                    return Ok(Value::Object(map));
-                } else if base_indent == 0 && next_indent == 0 {
-                    // For top-level objects, check if next line looks like a key
-                    // by looking for a ':' character
-                    let mut temp_pos = self.position;
-                    let mut found_colon = false;
-                    while temp_pos < self.input.len() {
-                        match self.input.as_bytes()[temp_pos] {
-                            b':' => {
-                                found_colon = true;
-                                break;
-                            }
-                            b'\n' => break,
-                            _ => temp_pos += 1,
-                        }
-                    }
-
-                    if !found_colon {
-                        // No colon found, assume empty object
-                        self.position = saved_pos;
-                        self.line = saved_line;
-                        self.column = saved_col;
-                        return Ok(Value::Object(map));
-                    }
-                }
+    /// Reads a table's field names from its `header` and enters the block of
+    /// rows after the header line.
+    pub(crate) fn begin_table(
+        &mut self,
+        header: &Header,
+        declared_length: Option<usize>,
+        at: (usize, usize),
+    ) -> Result<TableCursor> {
+        let mut headers = Vec::with_capacity(header.fields.len());
+        for field in &header.fields {
+            self.check_limit(
+                "max_string_length",
+                self.options.max_string_length,
+                field.len(),
+                at.0,
+                at.1,
+            )?;
+            headers.push(self.full_key(field));
+        }
 
-                // Otherwise continue parsing with content
-                self.position = saved_pos;
-                self.line = saved_line;
-                self.column = saved_col;
-            } else if self.at_end() {
-                return Ok(Value::Object(map));
+        if matches!(self.peek_kind(), Some(TokenKind::Scalar(_))) {
+            let after = self.position();
+            return Err(Error::syntax(
+                after.0,
+                after.1,
+                "Expected table rows on the lines after the header",
+            ));
+        }
+        let block = self.open_block()?;
+        Ok(TableCursor::new(
+            &self.options,
+            headers,
+            declared_length,
+            header.delimiter.clone(),
+            at,
+            block,
+        ))
+    }
+
+    /// Maps a key read from the input to the key it is an alias for, if any,
+    /// reusing the allocation of an earlier occurrence of the same key.
+    fn full_key(&mut self, key: &str) -> Key {
+        let options = Rc::clone(&self.options);
+        let key = options.key_aliases.key(key).unwrap_or(key);
+        if let Some(known) = self.keys.get(key) {
+            return known.clone();
+        }
+        let key = Key::from(key);
+        self.keys.insert(key.clone());
+        key
+    }
+
+    /// Parses the next row of a table, or returns `None` (after checking the
+    /// declared length) once the table ends.
+    pub(crate) fn next_table_row(&mut self, table: &mut TableCursor) -> Result<Option<Vec<Value>>> {
+        if !table.block || !self.next_line(&mut table.open)? {
+            if table.block {
+                table.block = false;
+                self.close_block();
             }
+            self.check_length(table.declared_length, table.rows_read, table.at)?;
+            trace::event!(
+                TRACE,
+                format = "table",
+                len = table.rows_read,
+                columns = table.headers.len()
+            );
+            return Ok(None);
         }
 
-        // Push indent scope
-        self.push_indent(base_indent);
+        let index = table.rows_read;
+        let at = self.position();
+        self.check_array_length(index + 1, at)?;
+        let row = self.spanned(|| PathSegment::Index(index), |de| de.parse_row(table))?;
+        table.rows_read += 1;
+        Ok(Some(row))
+    }
 
-        loop {
-            self.skip_whitespace_same_line();
-
-            // Check for newline or end
-            if self.peek_char() == Some('\n') {
-                self.next_char(); // consume newline
-                self.current_indent = self.detect_indent_level();
-
-                // Check if we've dedented (exited object scope)
-                // For nested objects (base_indent > 0), dedent means we've exited
-                // For top-level objects (base_indent == 0), we stay at the same level
-                if base_indent > 0 && self.current_indent < base_indent {
-                    self.pop_indent();
-                    break;
-                }
+    /// Parses the cells of a table row.
+    fn parse_row(&mut self, table: &mut TableCursor) -> Result<Vec<Value>> {
+        let Some(first) = self.next_token() else {
+            return Ok(Vec::new());
+        };
+        // The row is the whole line, however the tokenizer split it: a cell
+        // such as `-` or `a: b` reads like a list item or a key
+        table.open += self.skip_line();
+        let text = &self.input[first.span.start..self.last_end.max(first.span.start)];
+        let mut cells: Vec<Range<usize>> = Values::new(text, &table.delimiter).collect();
+
+        if table.row_numbers {
+            let range = cells.remove(0);
+            self.check_row_number(&first, text, range, table.rows_read + 1)?;
+        }
 
-                // Skip blank lines
-                if self.peek_char() == Some('\n') {
-                    continue;
-                }
-            } else if self.at_end() {
-                self.pop_indent();
-                break;
+        let width = table.headers.len();
+        if cells.len() != width {
+            let msg = if cells.len() < width {
+                format!("row has {} values for {} headers", cells.len(), width)
+            } else {
+                format!("row has more values than {} headers", width)
+            };
+            if !self.options.lenient {
+                return Err(Error::syntax(first.line, first.column, &msg));
             }
+            self.warn(
+                WarningKind::LengthMismatch,
+                (first.line, first.column),
+                &msg,
+            );
+        }
 
-            // Skip any leading whitespace on this line
-            self.skip_whitespace_same_line();
+        let mut row = Vec::with_capacity(width);
+        for (header, range) in table.headers.iter().zip(cells) {
+            let span = cell_span(&first, text, &range);
+            let value = self.parse_cell(&first, text, range)?;
+            self.record(|| PathSegment::Key(header.to_string()), span);
+            row.push(value);
+        }
+        row.resize(width, Value::Null);
+        Ok(row)
+    }
 
-            if self.at_end() || self.peek_char() == Some('\n') {
-                continue;
+    /// Checks the `#` cell of a numbered table row against `expected`.
+    fn check_row_number(
+        &mut self,
+        token: &Token<'de>,
+        text: &str,
+        range: Range<usize>,
+        expected: usize,
+    ) -> Result<()> {
+        let at = cell_position(token, text, &range);
+        let number = self.parse_cell(token, text, range)?;
+        if number.as_i64() != i64::try_from(expected).ok() {
+            let msg = format!("Expected row number {}, found {}", expected, number);
+            if !self.options.lenient {
+                return Err(Error::syntax(at.0, at.1, &msg));
             }
+            self.warn(WarningKind::RowNumberMismatch, at, &msg);
+        }
+        Ok(())
+    }
 
-            // Parse key
-            let key = self.parse_string()?;
-
-            self.skip_whitespace_same_line();
-
-            if self.peek_char() != Some(':') {
-                return Err(Error::syntax(
-                    self.line,
-                    self.column,
-                    "Expected ':' after key",
-                ));
+    fn parse_object(&mut self) -> Result<Value> {
+        let at = self.position();
+        self.nested(at, |de| {
+            let mut map = ToonMap::new();
+            let mut open = 0;
+            while let Some((key, at)) = de.next_key(&mut open)? {
+                let value = de.spanned(
+                    || PathSegment::Key(key.to_string()),
+                    Self::parse_field_value,
+                )?;
+                de.insert_field(&mut map, key, value, at)?;
+            }
+            Ok(Value::Object(map))
+        })
+    }
+
+    /// Reads the key of the next field of the object in the current block,
+    /// returning it with its position, or `None` once the block ends.
+    ///
+    /// A line without a key is an error, or skipped with a warning in
+    /// lenient mode. At the top level of the document it ends the root
+    /// object instead, as trailing content.
+    pub(crate) fn next_key(&mut self, open: &mut usize) -> Result<Option<(Key, (usize, usize))>> {
+        while self.next_line(open)? {
+            let at = self.position();
+            if let Some(TokenKind::Key(raw)) = self.peek_kind() {
+                let raw = *raw;
+                self.next_token();
+                return Ok(Some((self.parse_key(raw, at)?, at)));
+            }
+            if self.depth == 1 && *open == 0 && (self.allow_trailing || !self.options.lenient) {
+                return Ok(None);
+            }
+            if !self.options.lenient {
+                return Err(Error::syntax(at.0, at.1, "Expected ':' after key"));
             }
-            self.next_char(); // consume ':'
+            self.warn(
+                WarningKind::TrailingContent,
+                at,
+                "ignored line without a key",
+            );
+            *open += self.skip_line();
+        }
+        Ok(None)
+    }
 
-            self.skip_whitespace_same_line();
+    /// Parses the value of the field whose key [`Self::next_key`] just read:
+    /// the rest of the key's line, or the indented block below it. A key with
+    /// neither has an empty object as its value.
+    fn parse_field_value(&mut self) -> Result<Value> {
+        if !matches!(self.peek_kind(), Some(TokenKind::Newline) | None) {
+            return self.parse_line_value();
+        }
+        if !self.open_block()? {
+            return Ok(Value::Object(ToonMap::new()));
+        }
+        let value = match self.peek_kind() {
+            Some(TokenKind::Key(_)) => self.parse_object()?,
+            _ => self.parse_line_value()?,
+        };
+        self.finish_block()?;
+        Ok(value)
+    }
+
+    /// Skips over the value of the root field whose key [`Self::next_key`]
+    /// just read, returning its text as a standalone document.
+    ///
+    /// The value runs to the end of the line it starts on, or of the last
+    /// indented line after it. A nested value is dedented, and `\r\n` line
+    /// breaks become `\n`, so only then is the text copied.
+    fn raw_root_field_value(&mut self) -> Result<Cow<'de, str>> {
+        let input = self.input;
+        let inline = !matches!(self.peek_kind(), Some(TokenKind::Newline) | None);
+        let (start, strip) = if inline {
+            let start = self
+                .tokens
+                .peek()
+                .map_or(input.len(), |token| token.span.start);
+            self.skip_line();
+            if matches!(self.peek_kind(), Some(TokenKind::Indent)) {
+                self.next_token();
+                self.skip_block();
+            }
+            (start, None)
+        } else {
+            if !self.open_block()? {
+                return Ok(Cow::Borrowed(""));
+            }
+            let first = self
+                .tokens
+                .peek()
+                .map_or(input.len(), |token| token.span.start);
+            let line_start = input[..first].rfind('\n').map_or(0, |i| i + 1);
+            self.skip_block();
+            (line_start, Some(first - line_start))
+        };
 
-            // Check if value is on same line or nested
-            if self.peek_char() == Some('\n') || self.at_end() {
-                // Value is on next line(s) - nested structure
-                if self.peek_char() == Some('\n') {
-                    self.next_char(); // consume newline
-                    self.current_indent = self.detect_indent_level();
+        let text = input[start..self.last_end.max(start)].trim_end();
+        let indent_of = |line: &str| line.len() - line.trim_start_matches([' ', '\t']).len();
+        if strip.is_none() && !text.contains('\r') {
+            return Ok(Cow::Borrowed(text));
+        }
+        Ok(Cow::Owned(
+            text.split('\n')
+                .map(|line| {
+                    let line = line.strip_suffix('\r').unwrap_or(line);
+                    &line[indent_of(line).min(strip.unwrap_or(0))..]
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ))
+    }
+
+    /// Whether the document is an object whose fields can be handed to a map
+    /// or struct visitor as they are parsed.
+    ///
+    /// Struct visitors reject repeated fields, so when the last of several
+    /// values should win the whole object is parsed first instead.
+    fn at_streamable_object(&mut self) -> Result<bool> {
+        self.check_indent()?;
+        Ok(matches!(self.peek_kind(), Some(TokenKind::Key(_)))
+            && (self.options.duplicate_keys != DuplicateKeys::LastWins || !self.root_keys_repeat()))
+    }
+
+    /// Whether a key appears more than once at the top level of the document.
+    fn root_keys_repeat(&self) -> bool {
+        let mut keys = HashSet::new();
+        lexer::lines(self.input)
+            .filter(|line| line.indent == 0)
+            .any(|line| match line.kind() {
+                LineKind::Field { key, .. } => !keys.insert(key),
+                _ => false,
+            })
+    }
+
+    /// Visits the root object one field at a time, parsing each value only
+    /// when the visitor asks for it.
+    fn visit_root_fields<V>(
+        &mut self,
+        fields: Option<&'static [&'static str]>,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let at = self.position();
+        let result = self.nested(at, |de| {
+            visitor.visit_map(FieldStream {
+                de,
+                open: Some(0),
+                fields,
+                seen: HashSet::new(),
+                key: None,
+            })
+        })?;
+        self.finish_root()?;
+        Ok(result)
+    }
+
+    /// Adds a parsed field to `map`, resolving repeated keys according to
+    /// [`ToonOptions::duplicate_keys`].
+    fn insert_field(
+        &mut self,
+        map: &mut ToonMap,
+        key: Key,
+        value: Value,
+        (line, column): (usize, usize),
+    ) -> Result<()> {
+        if map.contains_key(&key) {
+            match self.options.duplicate_keys {
+                DuplicateKeys::Error => {
+                    return Err(Error::DuplicateKey {
+                        key: key.into(),
+                        line,
+                        col: column,
+                    })
+                }
+                DuplicateKeys::FirstWins => {
+                    let msg = format!("repeated key `{}`; kept the first value", key);
+                    self.warn(WarningKind::DuplicateKey, (line, column), &msg);
+                    return Ok(());
+                }
+                DuplicateKeys::LastWins => {
+                    let msg = format!("repeated key `{}`; kept the last value", key);
+                    self.warn(WarningKind::DuplicateKey, (line, column), &msg);
                 }
-
-                let value = self.parse_value()?;
-                map.insert(key, value);
-            } else {
-                // Inline value
-                let value = self.parse_value()?;
-                map.insert(key, value);
             }
+        }
+        map.insert(key, value);
+        Ok(())
+    }
 
-            // Continue to next field or end
-            self.skip_whitespace_same_line();
+    /// Parses a scalar document for one of the primitive `deserialize_*`
+    /// methods, which accept only a value matching `is_match`.
+    fn parse_root_primitive(
+        &mut self,
+        expected: &str,
+        is_match: impl FnOnce(&Value) -> bool,
+    ) -> Result<Value> {
+        let at = self.position();
+        let value = self.parse_root()?;
+        if !is_match(&value) {
+            return Err(Error::syntax(at.0, at.1, &format!("Expected {}", expected)));
         }
+        Ok(value)
+    }
 
-        Ok(Value::Object(map))
+    /// Parses a number document for the numeric `deserialize_*` methods,
+    /// resolving a number kept as written, so the result is never a
+    /// [`Number::Raw`].
+    fn parse_root_number(&mut self) -> Result<Number> {
+        match self.parse_root_primitive("number", |value| matches!(value, Value::Number(_)))? {
+            Value::Number(number) => Ok(number.resolved().into_owned()),
+            _ => Err(Error::custom("Expected number")),
+        }
     }
+}
 
-    fn parse_primitive_value(&mut self) -> Result<Value> {
-        self.skip_whitespace();
+/// Reads `text` as a number, if it has the number grammar and is finite: an
+/// `i64` where it fits, or else an `f64` (such as `1.5`, `1e5` or an integer
+/// too large for `i64`).
+pub(crate) fn parse_number(text: &str) -> Option<Number> {
+    if !lexer::is_numeric(text) {
+        return None;
+    }
+    match text.parse::<i64>() {
+        Ok(i) => Some(Number::Integer(i)),
+        // Out of range, like `1e400`, stays a string
+        Err(_) => text
+            .parse::<f64>()
+            .ok()
+            .filter(|f| f.is_finite())
+            .map(Number::Float),
+    }
+}
 
-        match self.peek_char() {
-            Some('"') => Ok(Value::String(self.parse_string()?)),
-            Some('t') | Some('f') => Ok(Value::Bool(self.parse_bool()?)),
-            Some('n') => {
-                self.parse_null()?;
-                Ok(Value::Null)
-            }
-            Some(ch) if ch.is_ascii_digit() || ch == '-' => Ok(Value::Number(self.parse_number()?)),
-            _ => {
-                // Try parsing as unquoted string
-                let s = self.parse_string()?;
-                if s == "true" {
-                    Ok(Value::Bool(true))
-                } else if s == "false" {
-                    Ok(Value::Bool(false))
-                } else if s == "null" {
-                    Ok(Value::Null)
-                } else if let Ok(n) = s.parse::<i64>() {
-                    Ok(Value::Number(Number::Integer(n)))
-                } else if let Ok(f) = s.parse::<f64>() {
-                    Ok(Value::Number(Number::Float(f)))
-                } else {
-                    Ok(Value::String(s))
-                }
-            }
-        }
+/// Reads `text`, which parsed as the float `f`, as a [`Number::Decimal`] if
+/// it has more digits than `f` keeps (such as `1.2345678901234567890123` or
+/// an integer beyond `i64`) and fits a decimal exactly, or else as `f`.
+#[cfg(feature = "rust_decimal")]
+fn exact_decimal(text: &str, f: f64) -> Number {
+    let Some(decimal) = parse_decimal(text).filter(|d| d.to_string() == text) else {
+        return Number::Float(f);
+    };
+    match parse_decimal(ryu::Buffer::new().format_finite(f)) {
+        Some(widened) if widened == decimal => Number::Float(f),
+        _ => Number::Decimal(decimal),
     }
+}
 
-    fn parse_value(&mut self) -> Result<Value> {
-        self.skip_whitespace();
+/// Returns the line and column of `range` within `text`, the content of
+/// `token`'s line from the token on.
+fn cell_position(token: &Token<'_>, text: &str, range: &Range<usize>) -> (usize, usize) {
+    (
+        token.line,
+        token.column + text[..range.start].chars().count(),
+    )
+}
 
-        match self.peek_char() {
-            Some('[') => self.parse_array(),
-            Some('"') => Ok(Value::String(self.parse_string()?)),
-            Some('t') | Some('f') => Ok(Value::Bool(self.parse_bool()?)),
-            Some('n') => {
-                self.parse_null()?;
-                Ok(Value::Null)
-            }
-            Some(ch) if ch.is_ascii_digit() || ch == '-' => Ok(Value::Number(self.parse_number()?)),
-            _ => {
-                // Check if we're at end of input (empty object case)
-                if self.at_end() {
-                    return Ok(Value::Object(ToonMap::new()));
-                }
+/// Returns the span of `range` within `text`, the content of `token`'s line
+/// from the token on.
+fn cell_span(token: &Token<'_>, text: &str, range: &Range<usize>) -> Span {
+    let (line, column) = cell_position(token, text, range);
+    Span {
+        start: token.span.start + range.start,
+        end: token.span.start + range.end,
+        line,
+        column,
+    }
+}
 
-                // Could be object or unquoted string
-                // Look ahead to see if we have key: value pattern
-                let _start_pos = self.position;
-                let _start_line = self.line;
-                let _start_column = self.column;
-
-                // Try to find ':'
-                let mut found_colon = false;
-                let mut temp_pos = self.position;
-
-                while temp_pos < self.input.len() {
-                    match self.input.as_bytes()[temp_pos] {
-                        b':' => {
-                            found_colon = true;
-                            break;
-                        }
-                        b'\n' => break,
-                        _ => temp_pos += 1,
-                    }
-                }
+/// Progress through the rows of a table being parsed.
+pub(crate) struct TableCursor {
+    pub(crate) headers: Vec<Key>,
+    // Whether rows start with a `#` cell to check and drop
+    row_numbers: bool,
+    pub(crate) declared_length: Option<usize>,
+    delimiter: Delimiter,
+    // Position of the header, for length mismatches
+    at: (usize, usize),
+    // Whether the block of rows is still being read
+    block: bool,
+    // Deeper blocks entered within the block of rows
+    open: usize,
+    rows_read: usize,
+}
 
-                if found_colon {
-                    // Parse as object
-                    self.parse_object()
-                } else {
-                    // Parse as string
-                    let s = self.parse_string()?;
-                    if s == "true" {
-                        Ok(Value::Bool(true))
-                    } else if s == "false" {
-                        Ok(Value::Bool(false))
-                    } else if s == "null" {
-                        Ok(Value::Null)
-                    } else if let Ok(n) = s.parse::<i64>() {
-                        Ok(Value::Number(Number::Integer(n)))
-                    } else if let Ok(f) = s.parse::<f64>() {
-                        Ok(Value::Number(Number::Float(f)))
-                    } else {
-                        Ok(Value::String(s))
-                    }
-                }
-            }
+impl TableCursor {
+    fn new(
+        options: &ToonOptions,
+        mut headers: Vec<Key>,
+        declared_length: Option<usize>,
+        delimiter: Delimiter,
+        at: (usize, usize),
+        block: bool,
+    ) -> Self {
+        let row_numbers = options.row_numbers && headers.first().map(Key::as_str) == Some("#");
+        if row_numbers {
+            headers.remove(0);
+        }
+        TableCursor {
+            headers,
+            row_numbers,
+            declared_length,
+            delimiter,
+            at,
+            block,
+            open: 0,
+            rows_read: 0,
         }
     }
 }
@@ -786,7 +1669,50 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let value = self.parse_value()?;
+        self.check_document_size()?;
+        self.check_indent()?;
+        // Objects and tables at the root are visited field by field and row by
+        // row as they are parsed, so converting a large document (e.g. with
+        // serde-transcode) never holds all of it as a `Value`
+        let header = match self.tokens.peek() {
+            Some(Token {
+                kind: TokenKind::TableHeader(header),
+                line,
+                column,
+                ..
+            }) => Some((header.clone(), (*line, *column))),
+            _ => None,
+        };
+        if let Some((header, at)) = header {
+            let Some(token) = self.next_token() else {
+                return Err(Error::custom("Expected array"));
+            };
+            if header.fields.is_empty() {
+                let value = self.parse_array(&token, &header)?;
+                self.finish_root()?;
+                return ValueDeserializer::new(value, self.options.clone())
+                    .deserialize_any(visitor);
+            }
+            let header = self.settle_delimiter(&token, &header);
+            let result = self.nested(at, |de| {
+                let declared_length = de.declared_length(&token, &header)?;
+                let cursor = de.begin_table(&header, declared_length, at)?;
+                let headers = Rc::new(RowHeaders::new(cursor.headers.clone()));
+                visitor.visit_seq(RowStream {
+                    de,
+                    cursor,
+                    headers,
+                    done: false,
+                })
+            })?;
+            self.finish_root()?;
+            return Ok(result);
+        }
+        if matches!(self.peek_kind(), Some(TokenKind::Key(_))) {
+            return self.visit_root_fields(None, visitor);
+        }
+
+        let value = self.parse_root()?;
         match value {
             Value::Null => visitor.visit_unit(),
             Value::Bool(b) => visitor.visit_bool(b),
@@ -795,24 +1721,20 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
             Value::Number(Number::Infinity) => visitor.visit_f64(f64::INFINITY),
             Value::Number(Number::NegativeInfinity) => visitor.visit_f64(f64::NEG_INFINITY),
             Value::Number(Number::NaN) => visitor.visit_f64(f64::NAN),
+            Value::Number(Number::Raw(text)) => {
+                visit_raw_number(text, self.options.clone(), visitor)
+            }
+            #[cfg(feature = "rust_decimal")]
+            Value::Number(Number::Decimal(d)) => visitor.visit_string(d.to_string()),
             Value::String(s) => visitor.visit_string(s),
-            Value::Array(arr) => visitor.visit_seq(SeqDeserializer::new(arr)),
-            Value::Object(obj) => visitor.visit_map(MapDeserializer::new(obj)),
-            Value::Table { headers, rows } => {
-                // Convert table to array of objects
-                let mut objects = Vec::new();
-                for row in rows {
-                    let mut obj = ToonMap::new();
-                    for (i, value) in row.into_iter().enumerate() {
-                        if let Some(header) = headers.get(i) {
-                            obj.insert(header.clone(), value);
-                        }
-                    }
-                    objects.push(Value::Object(obj));
-                }
-                visitor.visit_seq(SeqDeserializer::new(objects))
+            Value::Array(arr) => visitor.visit_seq(SeqDeserializer::new(arr, self.options.clone())),
+            Value::Object(obj) => {
+                visitor.visit_map(MapDeserializer::new(obj, self.options.clone()))
             }
+            Value::Table(table) => visitor.visit_seq(TableRows::new(table, self.options.clone())),
+            #[cfg(feature = "datetime")]
             Value::Date(dt) => visitor.visit_string(dt.to_rfc3339()),
+            #[cfg(feature = "bigint")]
             Value::BigInt(bi) => visitor.visit_string(format!("{}n", bi)),
         }
     }
@@ -821,19 +1743,24 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_bool(self.parse_bool()?)
+        self.check_document_size()?;
+        match self.parse_root_primitive("boolean", |value| matches!(value, Value::Bool(_)))? {
+            Value::Bool(b) => visitor.visit_bool(b),
+            _ => Err(Error::custom("Expected boolean")),
+        }
     }
 
     fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        match self.parse_number()? {
+        self.check_document_size()?;
+        match self.parse_root_number()? {
             Number::Integer(i) => visitor.visit_i8(i as i8),
             Number::Float(f) => visitor.visit_i8(f as i8),
             Number::Infinity => visitor.visit_i8(i8::MAX),
             Number::NegativeInfinity => visitor.visit_i8(i8::MIN),
-            Number::NaN => visitor.visit_i8(0),
+            _ => visitor.visit_i8(0),
         }
     }
 
@@ -841,12 +1768,13 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        match self.parse_number()? {
+        self.check_document_size()?;
+        match self.parse_root_number()? {
             Number::Integer(i) => visitor.visit_i16(i as i16),
             Number::Float(f) => visitor.visit_i16(f as i16),
             Number::Infinity => visitor.visit_i16(i16::MAX),
             Number::NegativeInfinity => visitor.visit_i16(i16::MIN),
-            Number::NaN => visitor.visit_i16(0),
+            _ => visitor.visit_i16(0),
         }
     }
 
@@ -854,12 +1782,13 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        match self.parse_number()? {
+        self.check_document_size()?;
+        match self.parse_root_number()? {
             Number::Integer(i) => visitor.visit_i32(i as i32),
             Number::Float(f) => visitor.visit_i32(f as i32),
             Number::Infinity => visitor.visit_i32(i32::MAX),
             Number::NegativeInfinity => visitor.visit_i32(i32::MIN),
-            Number::NaN => visitor.visit_i32(0),
+            _ => visitor.visit_i32(0),
         }
     }
 
@@ -867,12 +1796,13 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        match self.parse_number()? {
+        self.check_document_size()?;
+        match self.parse_root_number()? {
             Number::Integer(i) => visitor.visit_i64(i),
             Number::Float(f) => visitor.visit_i64(f as i64),
             Number::Infinity => visitor.visit_i64(i64::MAX),
             Number::NegativeInfinity => visitor.visit_i64(i64::MIN),
-            Number::NaN => visitor.visit_i64(0),
+            _ => visitor.visit_i64(0),
         }
     }
 
@@ -880,12 +1810,13 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        match self.parse_number()? {
+        self.check_document_size()?;
+        match self.parse_root_number()? {
             Number::Integer(i) => visitor.visit_u8(i as u8),
             Number::Float(f) => visitor.visit_u8(f as u8),
             Number::Infinity => visitor.visit_u8(u8::MAX),
             Number::NegativeInfinity => visitor.visit_u8(u8::MIN),
-            Number::NaN => visitor.visit_u8(0),
+            _ => visitor.visit_u8(0),
         }
     }
 
@@ -893,12 +1824,13 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        match self.parse_number()? {
+        self.check_document_size()?;
+        match self.parse_root_number()? {
             Number::Integer(i) => visitor.visit_u16(i as u16),
             Number::Float(f) => visitor.visit_u16(f as u16),
             Number::Infinity => visitor.visit_u16(u16::MAX),
             Number::NegativeInfinity => visitor.visit_u16(u16::MIN),
-            Number::NaN => visitor.visit_u16(0),
+            _ => visitor.visit_u16(0),
         }
     }
 
@@ -906,12 +1838,13 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        match self.parse_number()? {
+        self.check_document_size()?;
+        match self.parse_root_number()? {
             Number::Integer(i) => visitor.visit_u32(i as u32),
             Number::Float(f) => visitor.visit_u32(f as u32),
             Number::Infinity => visitor.visit_u32(u32::MAX),
             Number::NegativeInfinity => visitor.visit_u32(u32::MIN),
-            Number::NaN => visitor.visit_u32(0),
+            _ => visitor.visit_u32(0),
         }
     }
 
@@ -919,12 +1852,13 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        match self.parse_number()? {
+        self.check_document_size()?;
+        match self.parse_root_number()? {
             Number::Integer(i) => visitor.visit_u64(i as u64),
             Number::Float(f) => visitor.visit_u64(f as u64),
             Number::Infinity => visitor.visit_u64(u64::MAX),
             Number::NegativeInfinity => visitor.visit_u64(u64::MIN),
-            Number::NaN => visitor.visit_u64(0),
+            _ => visitor.visit_u64(0),
         }
     }
 
@@ -932,62 +1866,69 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_f32(self.parse_number()?.as_f64() as f32)
+        self.check_document_size()?;
+        visitor.visit_f32(self.parse_root_number()?.as_f64() as f32)
     }
 
     fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_f64(self.parse_number()?.as_f64())
+        self.check_document_size()?;
+        visitor.visit_f64(self.parse_root_number()?.as_f64())
     }
 
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        let s = self.parse_string()?;
-        if s.len() == 1 {
-            visitor.visit_char(s.chars().next().unwrap())
-        } else {
-            Err(Error::custom("Expected single character"))
-        }
+        self.check_document_size()?;
+        self.deserialize_string(visitor)
     }
 
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_string(self.parse_string()?)
+        self.check_document_size()?;
+        self.deserialize_string(visitor)
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_string(self.parse_string()?)
+        self.check_document_size()?;
+        // A scalar document runs to the end of its line, like any other value
+        let value = self.parse_root()?;
+        ValueDeserializer::new(value, self.options.clone()).deserialize_string(visitor)
     }
 
     fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_seq(visitor)
+        self.check_document_size()?;
+        self.deserialize_byte_buf(visitor)
     }
 
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_seq(visitor)
+        self.check_document_size()?;
+        let value = self.parse_root()?;
+        visit_byte_buf(value, self.options.clone(), visitor)
     }
 
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        if self.peek_char() == Some('n') && self.input[self.position..].starts_with("null") {
-            self.parse_null()?;
+        self.check_document_size()?;
+        self.check_indent()?;
+        if matches!(self.peek_kind(), Some(TokenKind::Scalar("null"))) {
+            self.parse_root()?;
             visitor.visit_none()
         } else {
             visitor.visit_some(self)
@@ -998,7 +1939,8 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        self.parse_null()?;
+        self.check_document_size()?;
+        self.parse_root_primitive("null", |value| matches!(value, Value::Null))?;
         visitor.visit_unit()
     }
 
@@ -1006,13 +1948,43 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.check_document_size()?;
         self.deserialize_unit(visitor)
     }
 
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
+        self.check_document_size()?;
+        // A scalar document that may be a decimal; objects, tables and arrays
+        // hand their values over one by one
+        #[cfg(feature = "rust_decimal")]
+        if name == VALUE_TOKEN
+            && !matches!(
+                self.peek_kind(),
+                Some(TokenKind::Key(_) | TokenKind::TableHeader(_))
+            )
+        {
+            self.check_indent()?;
+            let value = self.parse_root()?;
+            return ValueDeserializer::new(value, self.options.clone())
+                .deserialize_newtype_struct(name, visitor);
+        }
+        if self.options.type_codec(name).is_some() {
+            let value = self.parse_root()?;
+            return ValueDeserializer::new(value, self.options.clone())
+                .deserialize_newtype_struct(name, visitor);
+        }
+        if name == raw::TOKEN {
+            let rest = self
+                .tokens
+                .peek()
+                .map_or(self.input.len(), |token| token.span.start);
+            let text = self.input[rest.min(self.last_end)..].trim();
+            while self.next_token().is_some() {}
+            return visitor.visit_borrowed_str(text);
+        }
         visitor.visit_newtype_struct(self)
     }
 
@@ -1020,21 +1992,15 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let value = self.parse_value()?;
+        self.check_document_size()?;
+        let value = self.parse_root()?;
         match value {
-            Value::Array(arr) => visitor.visit_seq(SeqDeserializer::new(arr)),
-            Value::Table { headers, rows } => {
-                let mut objects = Vec::new();
-                for row in rows {
-                    let mut obj = ToonMap::new();
-                    for (i, value) in row.into_iter().enumerate() {
-                        if let Some(header) = headers.get(i) {
-                            obj.insert(header.clone(), value);
-                        }
-                    }
-                    objects.push(Value::Object(obj));
-                }
-                visitor.visit_seq(SeqDeserializer::new(objects))
+            Value::Array(arr) => visitor.visit_seq(SeqDeserializer::new(arr, self.options.clone())),
+            Value::Table(table) => visitor.visit_seq(TableRows::new(table, self.options.clone())),
+            Value::String(s)
+                if self.options.vector_format.encoding == VectorEncoding::PackedF32 =>
+            {
+                visit_packed_vector(&s, self.options.clone(), visitor)
             }
             _ => Err(Error::custom("Expected array")),
         }
@@ -1044,18 +2010,25 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.check_document_size()?;
         self.deserialize_seq(visitor)
     }
 
     fn deserialize_tuple_struct<V>(
         self,
-        _name: &'static str,
-        _len: usize,
+        name: &'static str,
+        len: usize,
         visitor: V,
     ) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
+        self.check_document_size()?;
+        if self.options.type_codec(name).is_some() {
+            let value = self.parse_root()?;
+            return ValueDeserializer::new(value, self.options.clone())
+                .deserialize_tuple_struct(name, len, visitor);
+        }
         self.deserialize_seq(visitor)
     }
 
@@ -1063,23 +2036,42 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let value = self.parse_value()?;
+        self.check_document_size()?;
+        if self.at_streamable_object()? {
+            return self.visit_root_fields(None, visitor);
+        }
+        let value = self.parse_root()?;
         match value {
-            Value::Object(obj) => visitor.visit_map(MapDeserializer::new(obj)),
+            Value::Object(obj) => {
+                visitor.visit_map(MapDeserializer::new(obj, self.options.clone()))
+            }
             _ => Err(Error::custom("Expected object")),
         }
     }
 
     fn deserialize_struct<V>(
         self,
-        _name: &'static str,
-        _fields: &'static [&'static str],
+        name: &'static str,
+        fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_map(visitor)
+        self.check_document_size()?;
+        if self.options.type_codec(name).is_some() {
+            let value = self.parse_root()?;
+            return ValueDeserializer::new(value, self.options.clone())
+                .deserialize_struct(name, fields, visitor);
+        }
+        if self.at_streamable_object()? {
+            return self.visit_root_fields(Some(fields), visitor);
+        }
+        let value = self.parse_root()?;
+        match value {
+            Value::Object(obj) => visit_struct(obj, fields, self.options.clone(), visitor),
+            _ => Err(Error::custom("Expected object")),
+        }
     }
 
     fn deserialize_enum<V>(
@@ -1091,25 +2083,16 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let value = self.parse_value()?;
-        match value {
-            Value::String(s) => visitor.visit_enum(s.into_deserializer()),
-            Value::Object(obj) => {
-                if obj.len() == 1 {
-                    let (variant, value) = obj.into_iter().next().unwrap();
-                    visitor.visit_enum(EnumDeserializer::new(variant, value))
-                } else {
-                    Err(Error::custom("Expected enum variant"))
-                }
-            }
-            _ => Err(Error::custom("Expected enum")),
-        }
+        self.check_document_size()?;
+        let value = self.parse_root()?;
+        visit_enum(value, self.options.clone(), visitor)
     }
 
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
+        self.check_document_size()?;
         self.deserialize_string(visitor)
     }
 
@@ -1117,18 +2100,23 @@ impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.check_document_size()?;
         self.deserialize_any(visitor)
     }
 }
 
 struct SeqDeserializer {
     iter: std::vec::IntoIter<Value>,
+    index: usize,
+    options: Rc<ToonOptions>,
 }
 
 impl SeqDeserializer {
-    fn new(vec: Vec<Value>) -> Self {
+    fn new(vec: Vec<Value>, options: Rc<ToonOptions>) -> Self {
         SeqDeserializer {
             iter: vec.into_iter(),
+            index: 0,
+            options,
         }
     }
 }
@@ -1141,7 +2129,13 @@ impl<'de> de::SeqAccess<'de> for SeqDeserializer {
         T: de::DeserializeSeed<'de>,
     {
         match self.iter.next() {
-            Some(value) => seed.deserialize(ValueDeserializer::new(value)).map(Some),
+            Some(value) => {
+                let index = self.index;
+                self.index += 1;
+                seed.deserialize(ValueDeserializer::new(value, self.options.clone()))
+                    .map(Some)
+                    .map_err(|err| err.within(PathSegment::Index(index)))
+            }
             None => Ok(None),
         }
     }
@@ -1154,16 +2148,310 @@ impl<'de> de::SeqAccess<'de> for SeqDeserializer {
     }
 }
 
+/// Visits the rows of a table, each as a [`RowDeserializer`].
+struct TableRows {
+    headers: Rc<RowHeaders>,
+    iter: std::vec::IntoIter<Vec<Value>>,
+    index: usize,
+    options: Rc<ToonOptions>,
+}
+
+impl TableRows {
+    fn new(table: Table, options: Rc<ToonOptions>) -> Self {
+        let (headers, rows) = table.into_parts();
+        TableRows {
+            headers: Rc::new(RowHeaders::new(headers)),
+            iter: rows.into_iter(),
+            index: 0,
+            options,
+        }
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for TableRows {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some(row) => {
+                let index = self.index;
+                self.index += 1;
+                let headers = Rc::clone(&self.headers);
+                seed.deserialize(RowDeserializer::new(headers, row, self.options.clone()))
+                    .map(Some)
+                    .map_err(|err| err.for_table_row(index, &self.headers.names))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// The headers of a table, shared by all of its rows so that deserializing a
+/// row into a struct does not copy them.
+struct RowHeaders {
+    names: Vec<Key>,
+    // Whether every header is different, so cells map one-to-one to fields
+    distinct: bool,
+}
+
+impl RowHeaders {
+    fn new(names: Vec<Key>) -> Self {
+        let distinct = names.iter().collect::<HashSet<_>>().len() == names.len();
+        RowHeaders { names, distinct }
+    }
+}
+
+/// Deserializes a table row: as an object keyed by the headers, or, for
+/// tuples, arrays and sequences, as its cells in header order.
+struct RowDeserializer {
+    headers: Rc<RowHeaders>,
+    cells: Vec<Value>,
+    options: Rc<ToonOptions>,
+}
+
+impl RowDeserializer {
+    fn new(headers: Rc<RowHeaders>, cells: Vec<Value>, options: Rc<ToonOptions>) -> Self {
+        if headers.distinct {
+            return RowDeserializer {
+                headers,
+                cells,
+                options,
+            };
+        }
+        // Repeated headers collapse as repeated keys of an object do
+        let row: ToonMap = headers.names.iter().cloned().zip(cells).collect();
+        let (names, cells) = row.into_iter().unzip();
+        RowDeserializer {
+            headers: Rc::new(RowHeaders {
+                names,
+                distinct: true,
+            }),
+            cells,
+            options,
+        }
+    }
+
+    fn into_object(self) -> ValueDeserializer {
+        let row = self.headers.names.iter().cloned().zip(self.cells).collect();
+        ValueDeserializer::new(Value::Object(row), self.options)
+    }
+
+    fn into_cells(self) -> CellSeq {
+        CellSeq {
+            headers: self.headers,
+            cells: self.cells.into_iter().enumerate(),
+            options: self.options,
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for RowDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_map(RowFields {
+            cells: self.into_cells(),
+            value: None,
+        })
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let mut cells = self.into_cells();
+        let value = visitor.visit_seq(&mut cells)?;
+        match cells.cells.len() {
+            0 => Ok(value),
+            remaining => Err(Error::custom(format!(
+                "table row has {} more cells than expected",
+                remaining
+            ))),
+        }
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        check_fields(self.headers.names.iter(), fields, &self.options)?;
+        visitor.visit_map(RowFields {
+            cells: self.into_cells(),
+            value: None,
+        })
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.into_object().deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        if name == raw::TOKEN {
+            return self.into_object().deserialize_newtype_struct(name, visitor);
+        }
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct map identifier ignored_any
+    }
+}
+
+/// The cells of a table row, with errors located by column header.
+struct CellSeq {
+    headers: Rc<RowHeaders>,
+    cells: std::iter::Enumerate<std::vec::IntoIter<Value>>,
+    options: Rc<ToonOptions>,
+}
+
+impl CellSeq {
+    fn deserialize_cell<'de, T>(&self, column: usize, value: Value, seed: T) -> Result<T::Value>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(ValueDeserializer::new(value, self.options.clone()))
+            .map_err(|err| err.within(PathSegment::Key(self.headers.names[column].to_string())))
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for &mut CellSeq {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.cells.next() {
+            Some((column, value)) => self.deserialize_cell(column, value, seed).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.cells.len())
+    }
+}
+
+/// The cells of a table row as map entries, keyed by the shared headers.
+struct RowFields {
+    cells: CellSeq,
+    value: Option<(usize, Value)>,
+}
+
+impl<'de> de::MapAccess<'de> for RowFields {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        match self.cells.cells.next() {
+            Some((column, value)) => {
+                self.value = Some((column, value));
+                let header = self.cells.headers.names[column].clone();
+                seed.deserialize(KeyDeserializer::new(header, self.cells.options.clone()))
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            Some((column, value)) => self.cells.deserialize_cell(column, value, seed),
+            None => Err(Error::custom("next_value_seed called before next_key_seed")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.cells.cells.len())
+    }
+}
+
+/// Deserializes `T` from a value with default options.
+pub(crate) fn from_value<T: de::DeserializeOwned>(value: Value) -> Result<T> {
+    from_value_with_options(value, Rc::new(ToonOptions::default()))
+}
+
+/// Deserializes `T` from a value with `options`.
+pub(crate) fn from_value_with_options<T: de::DeserializeOwned>(
+    value: Value,
+    options: Rc<ToonOptions>,
+) -> Result<T> {
+    T::deserialize(ValueDeserializer::new(value, options))
+}
+
 struct MapDeserializer {
-    iter: indexmap::map::IntoIter<String, Value>,
+    iter: indexmap::map::IntoIter<Key, Value>,
     value: Option<Value>,
+    // Key of `value`, for the paths of errors
+    key: Option<Key>,
+    options: Rc<ToonOptions>,
 }
 
 impl MapDeserializer {
-    fn new(map: ToonMap) -> Self {
+    fn new(map: ToonMap, options: Rc<ToonOptions>) -> Self {
         MapDeserializer {
             iter: map.into_iter(),
             value: None,
+            key: None,
+            options,
         }
     }
 }
@@ -1178,7 +2466,8 @@ impl<'de> de::MapAccess<'de> for MapDeserializer {
         match self.iter.next() {
             Some((key, value)) => {
                 self.value = Some(value);
-                seed.deserialize(ValueDeserializer::new(Value::String(key)))
+                self.key = Some(key.clone());
+                seed.deserialize(KeyDeserializer::new(key, self.options.clone()))
                     .map(Some)
             }
             None => Ok(None),
@@ -1190,7 +2479,13 @@ impl<'de> de::MapAccess<'de> for MapDeserializer {
         V: de::DeserializeSeed<'de>,
     {
         match self.value.take() {
-            Some(value) => seed.deserialize(ValueDeserializer::new(value)),
+            Some(value) => {
+                let result = seed.deserialize(ValueDeserializer::new(value, self.options.clone()));
+                match self.key.take() {
+                    Some(key) => result.map_err(|err| err.within(PathSegment::Key(key.into()))),
+                    None => result,
+                }
+            }
             None => Err(Error::custom("next_value_seed called before next_key_seed")),
         }
     }
@@ -1203,16 +2498,380 @@ impl<'de> de::MapAccess<'de> for MapDeserializer {
     }
 }
 
+/// Visits the fields of the root object as they are parsed.
+struct FieldStream<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    // Deeper blocks entered among the fields, or `None` once they end
+    open: Option<usize>,
+    // Field names of the struct being deserialized, for rejecting unknown keys
+    fields: Option<&'static [&'static str]>,
+    // Keys seen so far, tracked only when repeated keys are not simply passed on
+    seen: HashSet<Key>,
+    // Key just visited, whose value is parsed next
+    key: Option<Key>,
+}
+
+impl<'de> de::MapAccess<'de> for FieldStream<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        let Some(mut open) = self.open else {
+            return Ok(None);
+        };
+        loop {
+            let next = self.de.next_key(&mut open)?;
+            self.open = Some(open);
+            let Some((key, (line, column))) = next else {
+                self.open = None;
+                return Ok(None);
+            };
+            match self.de.options.duplicate_keys {
+                // Visitors building maps keep the last value of a repeated key
+                DuplicateKeys::LastWins => {
+                    if !self.seen.insert(key.clone()) {
+                        let msg = format!("repeated key `{}`; kept the last value", key);
+                        self.de
+                            .warn(WarningKind::DuplicateKey, (line, column), &msg);
+                    }
+                }
+                DuplicateKeys::Error if self.seen.contains(&key) => {
+                    return Err(Error::DuplicateKey {
+                        key: key.into(),
+                        line,
+                        col: column,
+                    })
+                }
+                DuplicateKeys::FirstWins if self.seen.contains(&key) => {
+                    let msg = format!("repeated key `{}`; kept the first value", key);
+                    self.de
+                        .warn(WarningKind::DuplicateKey, (line, column), &msg);
+                    self.de.parse_field_value()?;
+                    continue;
+                }
+                _ => {
+                    self.seen.insert(key.clone());
+                }
+            }
+
+            if let Some(fields) = self.fields {
+                if self.de.options.deny_unknown_fields && !fields.contains(&key.as_str()) {
+                    let mut path = Path::root();
+                    path.push(PathSegment::Key(key.to_string()));
+                    return Err(Error::UnknownField {
+                        field: key.into(),
+                        path,
+                        expected: fields.join(", "),
+                    });
+                }
+            }
+            self.key = Some(key.clone());
+            return seed
+                .deserialize(KeyDeserializer::new(key, self.de.options.clone()))
+                .map(Some);
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        match self.key.take() {
+            Some(key) => seed
+                .deserialize(FieldValueDeserializer {
+                    de: &mut *self.de,
+                    key: &key,
+                })
+                .map_err(|err| err.within(PathSegment::Key(key.into()))),
+            None => Err(Error::custom("next_value_seed called before next_key_seed")),
+        }
+    }
+}
+
+/// Deserializes the value of a root field, parsing it only once the type
+/// being deserialized is known. A [`RawToon`](crate::RawToon) takes the text
+/// without parsing it.
+struct FieldValueDeserializer<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    key: &'a str,
+}
+
+impl FieldValueDeserializer<'_, '_> {
+    fn parse(self) -> Result<ValueDeserializer> {
+        let value = self.de.spanned(
+            || PathSegment::Key(self.key.to_string()),
+            Deserializer::parse_field_value,
+        )?;
+        Ok(ValueDeserializer::new(value, self.de.options.clone()))
+    }
+}
+
+macro_rules! deserialize_parsed_value {
+    ($($method:ident,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: de::Visitor<'de>,
+            {
+                self.parse()?.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for FieldValueDeserializer<'_, 'de> {
+    type Error = Error;
+
+    deserialize_parsed_value! {
+        deserialize_any,
+        deserialize_bool,
+        deserialize_i8,
+        deserialize_i16,
+        deserialize_i32,
+        deserialize_i64,
+        deserialize_i128,
+        deserialize_u8,
+        deserialize_u16,
+        deserialize_u32,
+        deserialize_u64,
+        deserialize_u128,
+        deserialize_f32,
+        deserialize_f64,
+        deserialize_char,
+        deserialize_str,
+        deserialize_string,
+        deserialize_bytes,
+        deserialize_byte_buf,
+        deserialize_option,
+        deserialize_unit,
+        deserialize_seq,
+        deserialize_map,
+        deserialize_identifier,
+        deserialize_ignored_any,
+    }
+
+    fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.parse()?.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        if name == raw::TOKEN {
+            let text = self.de.spanned(
+                || PathSegment::Key(self.key.to_string()),
+                Deserializer::raw_root_field_value,
+            )?;
+            return match text {
+                Cow::Borrowed(text) => visitor.visit_borrowed_str(text),
+                Cow::Owned(text) => visitor.visit_string(text),
+            };
+        }
+        self.parse()?.deserialize_newtype_struct(name, visitor)
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.parse()?.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.parse()?.deserialize_tuple_struct(name, len, visitor)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.parse()?.deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.parse()?.deserialize_enum(name, variants, visitor)
+    }
+}
+
+/// Visits the rows of a root table, as objects keyed by its headers, as they
+/// are parsed.
+struct RowStream<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    cursor: TableCursor,
+    headers: Rc<RowHeaders>,
+    done: bool,
+}
+
+impl<'de> de::SeqAccess<'de> for RowStream<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        if self.done {
+            return Ok(None);
+        }
+        let index = self.cursor.rows_read;
+        let Some(row) = self.de.next_table_row(&mut self.cursor)? else {
+            self.done = true;
+            return Ok(None);
+        };
+
+        let headers = Rc::clone(&self.headers);
+        seed.deserialize(RowDeserializer::new(headers, row, self.de.options.clone()))
+            .map(Some)
+            .map_err(|err| err.for_table_row(index, &self.headers.names))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        match self.cursor.declared_length {
+            Some(length) if !self.de.options.lenient && !self.done => {
+                Some(length.saturating_sub(self.cursor.rows_read))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Deserializes an object key. Keys are always text, so numeric and boolean
+/// key types (e.g. in a `HashMap<u32, T>`) parse it.
+struct KeyDeserializer {
+    key: Key,
+    options: Rc<ToonOptions>,
+}
+
+impl KeyDeserializer {
+    fn new(key: Key, options: Rc<ToonOptions>) -> Self {
+        KeyDeserializer { key, options }
+    }
+}
+
+macro_rules! deserialize_parsed_key {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: de::Visitor<'de>,
+            {
+                match self.key.parse() {
+                    Ok(parsed) => visitor.$visit(parsed),
+                    Err(_) => Err(de::Error::invalid_value(
+                        de::Unexpected::Str(&self.key),
+                        &visitor,
+                    )),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_str(&self.key)
+    }
+
+    deserialize_parsed_key! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visit_enum(Value::String(self.key.into()), self.options, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
 struct EnumDeserializer {
     variant: String,
     value: Option<Value>,
+    // Where the content sits relative to the enum, for the paths of errors
+    segment: Option<PathSegment>,
+    options: Rc<ToonOptions>,
 }
 
 impl EnumDeserializer {
-    fn new(variant: String, value: Value) -> Self {
+    fn new(
+        variant: String,
+        value: Value,
+        segment: Option<PathSegment>,
+        options: Rc<ToonOptions>,
+    ) -> Self {
         EnumDeserializer {
             variant,
             value: Some(value),
+            segment,
+            options,
         }
     }
 }
@@ -1225,14 +2884,33 @@ impl<'de> de::EnumAccess<'de> for EnumDeserializer {
     where
         V: de::DeserializeSeed<'de>,
     {
-        let variant = seed.deserialize(ValueDeserializer::new(Value::String(self.variant)))?;
-        let visitor = VariantDeserializer { value: self.value };
+        let variant = seed.deserialize(ValueDeserializer::new(
+            Value::String(self.variant),
+            self.options.clone(),
+        ))?;
+        let visitor = VariantDeserializer {
+            value: self.value,
+            segment: self.segment,
+            options: self.options,
+        };
         Ok((variant, visitor))
     }
 }
 
 struct VariantDeserializer {
     value: Option<Value>,
+    segment: Option<PathSegment>,
+    options: Rc<ToonOptions>,
+}
+
+impl VariantDeserializer {
+    /// Locates an error in the variant's content at the content's path.
+    fn locate<T>(segment: Option<PathSegment>, result: Result<T>) -> Result<T> {
+        match segment {
+            Some(segment) => result.map_err(|err| err.within(segment)),
+            None => result,
+        }
+    }
 }
 
 impl<'de> de::VariantAccess<'de> for VariantDeserializer {
@@ -1241,7 +2919,8 @@ impl<'de> de::VariantAccess<'de> for VariantDeserializer {
     fn unit_variant(self) -> Result<()> {
         match self.value {
             Some(Value::Null) | None => Ok(()),
-            _ => Err(Error::custom("Expected unit variant")),
+            Some(Value::Object(obj)) if obj.is_empty() => Ok(()),
+            _ => Self::locate(self.segment, Err(Error::custom("Expected unit variant"))),
         }
     }
 
@@ -1249,41 +2928,84 @@ impl<'de> de::VariantAccess<'de> for VariantDeserializer {
     where
         T: de::DeserializeSeed<'de>,
     {
-        match self.value {
-            Some(value) => seed.deserialize(ValueDeserializer::new(value)),
+        let result = match self.value {
+            Some(value) => seed.deserialize(ValueDeserializer::new(value, self.options.clone())),
             None => Err(Error::custom("Expected newtype variant")),
-        }
+        };
+        Self::locate(self.segment, result)
     }
 
     fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        match self.value {
-            Some(Value::Array(arr)) => visitor.visit_seq(SeqDeserializer::new(arr)),
+        let result = match self.value {
+            Some(Value::Array(arr)) => {
+                visitor.visit_seq(SeqDeserializer::new(arr, self.options.clone()))
+            }
             _ => Err(Error::custom("Expected tuple variant")),
-        }
+        };
+        Self::locate(self.segment, result)
     }
 
-    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        match self.value {
-            Some(Value::Object(obj)) => visitor.visit_map(MapDeserializer::new(obj)),
+        let result = match self.value {
+            Some(Value::Object(obj)) => visit_struct(obj, fields, self.options, visitor),
             _ => Err(Error::custom("Expected struct variant")),
-        }
+        };
+        Self::locate(self.segment, result)
     }
 }
 
 struct ValueDeserializer {
     value: Value,
+    options: Rc<ToonOptions>,
 }
 
 impl ValueDeserializer {
-    fn new(value: Value) -> Self {
-        ValueDeserializer { value }
+    fn new(value: Value, options: Rc<ToonOptions>) -> Self {
+        ValueDeserializer { value, options }
     }
+
+    /// Replaces a number kept as written or a decimal with the number it parses to, for
+    /// visitors expecting a number rather than the map [`Value`] reads.
+    fn resolve_number(self) -> Self {
+        match &self.value {
+            Value::Number(number) if matches!(number.resolved(), Cow::Owned(_)) => {
+                let value = Value::Number(number.resolved().into_owned());
+                ValueDeserializer::new(value, self.options)
+            }
+            _ => self,
+        }
+    }
+
+    /// Replaces the value with what the [`TypeCodec`](crate::TypeCodec) set
+    /// for the type named `name` decodes it to, if there is one.
+    fn decoded(self, name: &str) -> Result<Self> {
+        match self.options.type_codec(name) {
+            Some(codec) => {
+                let value = codec.decode(&self.value)?;
+                Ok(ValueDeserializer::new(value, self.options))
+            }
+            None => Ok(self),
+        }
+    }
+}
+
+macro_rules! deserialize_resolved_number {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: de::Visitor<'de>,
+            {
+                self.resolve_number().deserialize_any(visitor)
+            }
+        )*
+    };
 }
 
 impl<'de> de::Deserializer<'de> for ValueDeserializer {
@@ -1301,30 +3023,290 @@ impl<'de> de::Deserializer<'de> for ValueDeserializer {
             Value::Number(Number::Infinity) => visitor.visit_f64(f64::INFINITY),
             Value::Number(Number::NegativeInfinity) => visitor.visit_f64(f64::NEG_INFINITY),
             Value::Number(Number::NaN) => visitor.visit_f64(f64::NAN),
+            Value::Number(Number::Raw(text)) => visit_raw_number(text, self.options, visitor),
+            // As its text, which decimal types read exactly
+            #[cfg(feature = "rust_decimal")]
+            Value::Number(Number::Decimal(d)) => visitor.visit_string(d.to_string()),
             Value::String(s) => visitor.visit_string(s),
-            Value::Array(arr) => visitor.visit_seq(SeqDeserializer::new(arr)),
-            Value::Object(obj) => visitor.visit_map(MapDeserializer::new(obj)),
-            Value::Table { headers, rows } => {
-                let mut objects = Vec::new();
-                for row in rows {
-                    let mut obj = ToonMap::new();
-                    for (i, value) in row.into_iter().enumerate() {
-                        if let Some(header) = headers.get(i) {
-                            obj.insert(header.clone(), value);
-                        }
-                    }
-                    objects.push(Value::Object(obj));
-                }
-                visitor.visit_seq(SeqDeserializer::new(objects))
+            Value::Array(arr) => visitor.visit_seq(SeqDeserializer::new(arr, self.options.clone())),
+            Value::Object(obj) => {
+                visitor.visit_map(MapDeserializer::new(obj, self.options.clone()))
             }
+            Value::Table(table) => visitor.visit_seq(TableRows::new(table, self.options.clone())),
+            #[cfg(feature = "datetime")]
             Value::Date(dt) => visitor.visit_string(dt.to_rfc3339()),
+            #[cfg(feature = "bigint")]
             Value::BigInt(bi) => visitor.visit_string(format!("{}n", bi)),
         }
     }
 
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visit_byte_buf(self.value, self.options, visitor)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visit_byte_buf(self.value, self.options, visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let de = self.decoded(name)?;
+        match de.value {
+            Value::Object(obj) => visit_struct(obj, fields, de.options, visitor),
+            _ => de.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        visit_enum(self.value, self.options, visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        #[cfg(feature = "rust_decimal")]
+        if name == VALUE_TOKEN {
+            return match self.value {
+                Value::Number(Number::Decimal(d)) => visit_decimal(d, self.options, visitor),
+                _ => self.deserialize_any(visitor),
+            };
+        }
+        if name == raw::TOKEN {
+            return visitor.visit_string(self.value.to_toon_string(&self.options));
+        }
+        visitor.visit_newtype_struct(self.decoded(name)?)
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        // Strings written unquoted under this policy read back as their text
+        if self.options.quote_policy == QuotePolicy::PreserveInput {
+            match self.value {
+                Value::Number(n) => return visitor.visit_string(n.to_string()),
+                Value::Bool(b) => return visitor.visit_string(b.to_string()),
+                _ => {}
+            }
+        }
+        self.deserialize_any(visitor)
+    }
+
+    deserialize_resolved_number! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.value {
+            Value::String(s)
+                if self.options.vector_format.encoding == VectorEncoding::PackedF32 =>
+            {
+                visit_packed_vector(&s, self.options, visitor)
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.decoded(name)?.deserialize_seq(visitor)
+    }
+
     forward_to_deserialize_any! {
-        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-        bytes byte_buf option unit unit_struct newtype_struct seq tuple
-        tuple_struct map struct enum identifier ignored_any
+        bool char unit unit_struct map identifier ignored_any
+    }
+}
+
+/// Hands a vector packed as [`VectorEncoding::PackedF32`] to `visitor` as a
+/// sequence of floats.
+fn visit_packed_vector<'de, V>(text: &str, options: Rc<ToonOptions>, visitor: V) -> Result<V::Value>
+where
+    V: de::Visitor<'de>,
+{
+    let elements = bytes::decode_f32s(text)?
+        .into_iter()
+        .map(|f| Value::Number(Number::Float(widen_f32(f))))
+        .collect();
+    visitor.visit_seq(SeqDeserializer::new(elements, options))
+}
+
+/// Hands a number kept as written to `visitor` as a map of one entry, which
+/// [`Value`] reads back as a [`Number::Raw`].
+fn visit_raw_number<'de, V>(text: String, options: Rc<ToonOptions>, visitor: V) -> Result<V::Value>
+where
+    V: de::Visitor<'de>,
+{
+    let mut map = ToonMap::new();
+    map.insert(NUMBER_TOKEN.to_string(), Value::String(text));
+    visitor.visit_map(MapDeserializer::new(map, options))
+}
+
+/// Hands an exact decimal to a [`Value`] `visitor` as a map of one entry,
+/// which it reads back as a [`Number::Decimal`]. Other types read decimals
+/// from their text.
+#[cfg(feature = "rust_decimal")]
+fn visit_decimal<'de, V>(
+    decimal: rust_decimal::Decimal,
+    options: Rc<ToonOptions>,
+    visitor: V,
+) -> Result<V::Value>
+where
+    V: de::Visitor<'de>,
+{
+    let mut map = ToonMap::new();
+    map.insert(DECIMAL_TOKEN, Value::String(decimal.to_string()));
+    visitor.visit_map(MapDeserializer::new(map, options))
+}
+
+/// Hands an object to a struct `visitor`, first rejecting keys outside
+/// `fields` if [`ToonOptions::deny_unknown_fields`] is set.
+fn visit_struct<'de, V>(
+    obj: ToonMap,
+    fields: &'static [&'static str],
+    options: Rc<ToonOptions>,
+    visitor: V,
+) -> Result<V::Value>
+where
+    V: de::Visitor<'de>,
+{
+    check_fields(obj.keys(), fields, &options)?;
+    visitor.visit_map(MapDeserializer::new(obj, options))
+}
+
+/// Rejects the first of `keys` outside `fields` if
+/// [`ToonOptions::deny_unknown_fields`] is set.
+fn check_fields<'a>(
+    mut keys: impl Iterator<Item = &'a Key>,
+    fields: &'static [&'static str],
+    options: &ToonOptions,
+) -> Result<()> {
+    if options.deny_unknown_fields {
+        if let Some(field) = keys.find(|key| !fields.contains(&key.as_str())) {
+            let mut path = Path::root();
+            path.push(PathSegment::Key(field.to_string()));
+            return Err(Error::UnknownField {
+                field: field.to_string(),
+                path,
+                expected: fields.join(", "),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Hands an enum to `visitor`, locating the variant name and content according
+/// to the configured [`EnumRepr`].
+fn visit_enum<'de, V>(value: Value, options: Rc<ToonOptions>, visitor: V) -> Result<V::Value>
+where
+    V: de::Visitor<'de>,
+{
+    let (variant, content, segment) = match (&options.enum_repr, value) {
+        (EnumRepr::Untagged, _) => {
+            return Err(Error::custom(
+                "untagged enums cannot be deserialized: the variant name is not recorded",
+            ))
+        }
+        (_, Value::String(variant)) => (variant, Value::Null, None),
+        (EnumRepr::External, Value::Object(obj)) => {
+            let mut entries = obj.into_iter();
+            match (entries.next(), entries.next()) {
+                (Some((variant, content)), None) => {
+                    let segment = PathSegment::Key(variant.to_string());
+                    (variant.into(), content, Some(segment))
+                }
+                _ => return Err(Error::custom("Expected enum variant")),
+            }
+        }
+        (EnumRepr::Internal { tag }, Value::Object(mut obj)) => match obj.shift_remove(tag) {
+            Some(Value::String(variant)) => (variant, Value::Object(obj), None),
+            _ => return Err(Error::custom(format!("Expected enum tag '{}'", tag))),
+        },
+        (EnumRepr::Adjacent { tag, content }, Value::Object(mut obj)) => {
+            match obj.shift_remove(tag) {
+                Some(Value::String(variant)) => (
+                    variant,
+                    obj.shift_remove(content).unwrap_or(Value::Null),
+                    Some(PathSegment::Key(content.clone())),
+                ),
+                _ => return Err(Error::custom(format!("Expected enum tag '{}'", tag))),
+            }
+        }
+        _ => return Err(Error::custom("Expected enum")),
+    };
+    visitor.visit_enum(EnumDeserializer::new(variant, content, segment, options))
+}
+
+/// Hands a byte buffer to `visitor`, decoding strings with the configured
+/// [`BytesFormat`]. Integer arrays are passed through as sequences.
+fn visit_byte_buf<'de, V>(value: Value, options: Rc<ToonOptions>, visitor: V) -> Result<V::Value>
+where
+    V: de::Visitor<'de>,
+{
+    match (value, options.bytes_format) {
+        (Value::String(s), BytesFormat::Base64) => {
+            visitor.visit_byte_buf(bytes::decode_base64(&s)?)
+        }
+        (Value::String(s), BytesFormat::Hex) => visitor.visit_byte_buf(bytes::decode_hex(&s)?),
+        (value, _) => {
+            de::Deserializer::deserialize_any(ValueDeserializer::new(value, options), visitor)
+        }
     }
 }
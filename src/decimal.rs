@@ -0,0 +1,58 @@
+//! Serde helpers that write `rust_decimal::Decimal` fields as bare TOON
+//! numbers (requires the `rust_decimal` feature).
+//!
+//! `Decimal` serializes itself as a string, which TOON has to quote since it
+//! looks numeric (`price: "1.10"`). A field using this module with
+//! `#[serde(with = "serde_toon::decimal")]` is written unquoted with every
+//! digit and its scale instead (`price: 1.10`), and other formats still see
+//! a string. Reading accepts both forms: unquoted numbers with more digits
+//! than an `f64` holds are read as exact decimals.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use rust_decimal::Decimal;
+//! use serde::{Deserialize, Serialize};
+//! use serde_toon::{from_str, to_string};
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! struct Payment {
+//!     #[serde(with = "serde_toon::decimal")]
+//!     amount: Decimal,
+//! }
+//!
+//! let payment = Payment {
+//!     amount: "1.2345678901234567890123".parse().unwrap(),
+//! };
+//! let toon = to_string(&payment).unwrap();
+//! assert_eq!(toon, "amount: 1.2345678901234567890123");
+//! assert_eq!(from_str::<Payment>(&toon).unwrap(), payment);
+//! ```
+
+use crate::value::DECIMAL_TOKEN;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Serializes `decimal` as its text, which TOON serializers write unquoted.
+///
+/// # Errors
+///
+/// Returns the error of the underlying serializer.
+pub fn serialize<S>(decimal: &Decimal, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_newtype_struct(DECIMAL_TOKEN, &decimal.to_string())
+}
+
+/// Deserializes a decimal from a number or a string holding one.
+///
+/// # Errors
+///
+/// Returns an error if the value is neither.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    <Decimal as Deserialize>::deserialize(deserializer)
+}
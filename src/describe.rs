@@ -0,0 +1,536 @@
+//! Skeleton TOON documents ("shape prompts") generated from Rust types.
+//!
+//! When asking an LLM to answer in TOON, showing it the exact document shape
+//! that will be deserialized is far more reliable than describing it in prose.
+//! [`describe`] derives that shape from a type's `Deserialize` implementation:
+//! field names, table headers and typed placeholders such as `<int>`.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde::Deserialize;
+//! use serde_toon::describe;
+//!
+//! #[derive(Deserialize)]
+//! struct Item { sku: String, qty: u32 }
+//!
+//! #[derive(Deserialize)]
+//! struct Order { id: u64, note: Option<String>, items: Vec<Item> }
+//!
+//! let skeleton = describe::<Order>().unwrap();
+//! assert_eq!(
+//!     skeleton,
+//!     "id: <int>\nnote: <string|null>\nitems: [N]{sku,qty}:\n  <string>,<int>"
+//! );
+//! ```
+
+use crate::{Error, Result};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+
+/// Nesting depth after which recursive types are cut off.
+const MAX_DEPTH: usize = 16;
+
+/// The shape of a type as observed through its `Deserialize` implementation,
+/// or as declared by its [`ToonSchema`](crate::schema::ToonSchema).
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Shape {
+    Bool,
+    Integer,
+    Float,
+    String,
+    Bytes,
+    Unit,
+    Any,
+    Optional(Box<Shape>),
+    Seq(Box<Shape>),
+    Tuple(Vec<Shape>),
+    Map(Box<Shape>),
+    Struct(Vec<(String, Shape)>),
+    Enum(Vec<String>),
+    /// A type shown by name, such as a recursive one inside itself.
+    Named(String),
+}
+
+impl Shape {
+    /// Returns `true` if the shape renders as a single placeholder.
+    fn is_primitive(&self) -> bool {
+        match self {
+            Shape::Optional(inner) => inner.is_primitive(),
+            Shape::Seq(_) | Shape::Tuple(_) | Shape::Map(_) | Shape::Struct(_) => false,
+            _ => true,
+        }
+    }
+
+    /// Strips `Option` from composite shapes, which render the same either way.
+    fn composite(&self) -> &Shape {
+        match self {
+            Shape::Optional(inner) if !inner.is_primitive() => inner.composite(),
+            other => other,
+        }
+    }
+
+    fn placeholder(&self) -> String {
+        match self {
+            Shape::Bool => "<bool>".to_string(),
+            Shape::Integer => "<int>".to_string(),
+            Shape::Float => "<number>".to_string(),
+            Shape::String => "<string>".to_string(),
+            Shape::Bytes => "<bytes>".to_string(),
+            Shape::Unit => "null".to_string(),
+            Shape::Enum(variants) => format!("<{}>", variants.join("|")),
+            Shape::Named(name) => format!("<{}>", name),
+            Shape::Optional(inner) => {
+                let inner = inner.placeholder();
+                match inner.strip_suffix('>') {
+                    Some(open) => format!("{}|null>", open),
+                    None => inner,
+                }
+            }
+            _ => "<any>".to_string(),
+        }
+    }
+}
+
+/// Generates a skeleton TOON document for type `T`.
+///
+/// Every field is shown with a typed placeholder (`<int>`, `<number>`,
+/// `<string>`, `<bool>`, `<A|B>` for enums, `|null` for optional values).
+/// Arrays use `[N]` as their length, and arrays of flat structs are shown as a
+/// table with one placeholder row. Enums with data are shown by their variant names only.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde::Deserialize;
+/// use serde_toon::describe;
+///
+/// #[derive(Deserialize)]
+/// enum Status { Active, Banned }
+///
+/// #[derive(Deserialize)]
+/// struct Address { city: String }
+///
+/// #[derive(Deserialize)]
+/// struct User { name: String, status: Status, address: Address, tags: Vec<String> }
+///
+/// let skeleton = describe::<User>().unwrap();
+/// assert_eq!(
+///     skeleton,
+///     "name: <string>\nstatus: <Active|Banned>\naddress:\n  city: <string>\ntags: [N]: <string>,..."
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the type's `Deserialize` implementation cannot be traced,
+/// e.g. self-describing types such as [`Value`](crate::Value) or
+/// `#[serde(flatten)]`/untagged layouts that require `deserialize_any`.
+pub fn describe<T: DeserializeOwned>() -> Result<String> {
+    let mut shape = Shape::Any;
+    T::deserialize(Tracer {
+        out: &mut shape,
+        depth: 0,
+    })?;
+    Ok(render(&shape))
+}
+
+/// Renders `shape` as a skeleton document.
+pub(crate) fn render(shape: &Shape) -> String {
+    let mut lines = Vec::new();
+    match shape.composite() {
+        Shape::Struct(fields) => {
+            for (name, shape) in fields {
+                write_field(&mut lines, 0, name, shape);
+            }
+        }
+        Shape::Map(value) => write_field(&mut lines, 0, "<key>", value),
+        array @ (Shape::Seq(_) | Shape::Tuple(_)) => {
+            write_array(&mut lines, String::new(), 0, array)
+        }
+        other => lines.push(other.placeholder()),
+    }
+    lines.join("\n")
+}
+
+fn write_field(lines: &mut Vec<String>, pad: usize, key: &str, shape: &Shape) {
+    let prefix = format!("{}{}:", " ".repeat(pad), key);
+    match shape.composite() {
+        Shape::Struct(fields) => {
+            lines.push(prefix);
+            for (name, shape) in fields {
+                write_field(lines, pad + 2, name, shape);
+            }
+        }
+        Shape::Map(value) => {
+            lines.push(prefix);
+            write_field(lines, pad + 2, "<key>", value);
+        }
+        array @ (Shape::Seq(_) | Shape::Tuple(_)) => {
+            write_array(lines, format!("{} ", prefix), pad, array)
+        }
+        other => lines.push(format!("{} {}", prefix, other.placeholder())),
+    }
+}
+
+/// Writes an array header (after `prefix`) and its placeholder content.
+fn write_array(lines: &mut Vec<String>, prefix: String, pad: usize, shape: &Shape) {
+    match shape {
+        Shape::Seq(element) => match element.composite() {
+            element if element.is_primitive() => {
+                lines.push(format!("{}[N]: {},...", prefix, element.placeholder()));
+            }
+            Shape::Struct(fields)
+                if !fields.is_empty() && fields.iter().all(|(_, s)| s.is_primitive()) =>
+            {
+                let headers: Vec<&str> = fields.iter().map(|(name, _)| name.as_str()).collect();
+                let row: Vec<String> = fields.iter().map(|(_, s)| s.placeholder()).collect();
+                lines.push(format!("{}[N]{{{}}}:", prefix, headers.join(",")));
+                lines.push(format!("{}{}", " ".repeat(pad + 2), row.join(",")));
+            }
+            element => {
+                lines.push(format!("{}[N]:", prefix));
+                write_item(lines, pad + 2, element);
+            }
+        },
+        Shape::Tuple(items) => {
+            if items.iter().all(Shape::is_primitive) {
+                let values: Vec<String> = items.iter().map(Shape::placeholder).collect();
+                lines.push(format!("{}[{}]: {}", prefix, items.len(), values.join(",")));
+            } else {
+                lines.push(format!("{}[{}]:", prefix, items.len()));
+                for item in items {
+                    write_item(lines, pad + 2, item.composite());
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Writes a `- ` list item at `pad`.
+fn write_item(lines: &mut Vec<String>, pad: usize, shape: &Shape) {
+    let start = lines.len();
+    // Render the content two columns deeper, then put the dash in front of it
+    match shape {
+        Shape::Struct(fields) if !fields.is_empty() => {
+            for (name, shape) in fields {
+                write_field(lines, pad + 2, name, shape);
+            }
+        }
+        Shape::Map(value) => write_field(lines, pad + 2, "<key>", value),
+        Shape::Seq(_) | Shape::Tuple(_) => write_array(lines, " ".repeat(pad + 2), pad + 2, shape),
+        other => lines.push(format!("{}{}", " ".repeat(pad + 2), other.placeholder())),
+    }
+    if let Some(first) = lines.get_mut(start) {
+        first.replace_range(..pad + 2, &format!("{}- ", " ".repeat(pad)));
+    }
+}
+
+/// A deserializer that fabricates placeholder data while recording the shape requested.
+struct Tracer<'a> {
+    out: &'a mut Shape,
+    depth: usize,
+}
+
+impl<'a> Tracer<'a> {
+    fn nested<'b>(&self, out: &'b mut Shape) -> Tracer<'b> {
+        Tracer {
+            out,
+            depth: self.depth + 1,
+        }
+    }
+
+    fn too_deep(&self) -> bool {
+        self.depth >= MAX_DEPTH
+    }
+}
+
+macro_rules! trace_primitive {
+    ($($method:ident => $shape:ident, $visit:ident($($value:expr)?);)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                *self.out = Shape::$shape;
+                visitor.$visit($($value)?)
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> de::Deserializer<'de> for Tracer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::custom(
+            "cannot describe a self-describing type (deserialize_any)",
+        ))
+    }
+
+    trace_primitive! {
+        deserialize_bool => Bool, visit_bool(false);
+        deserialize_i8 => Integer, visit_i8(0);
+        deserialize_i16 => Integer, visit_i16(0);
+        deserialize_i32 => Integer, visit_i32(0);
+        deserialize_i64 => Integer, visit_i64(0);
+        deserialize_u8 => Integer, visit_u8(0);
+        deserialize_u16 => Integer, visit_u16(0);
+        deserialize_u32 => Integer, visit_u32(0);
+        deserialize_u64 => Integer, visit_u64(0);
+        deserialize_f32 => Float, visit_f32(0.0);
+        deserialize_f64 => Float, visit_f64(0.0);
+        deserialize_char => String, visit_char('a');
+        deserialize_str => String, visit_str("");
+        deserialize_string => String, visit_string(String::new());
+        deserialize_identifier => String, visit_str("");
+        deserialize_bytes => Bytes, visit_bytes(&[]);
+        deserialize_byte_buf => Bytes, visit_byte_buf(Vec::new());
+        deserialize_unit => Unit, visit_unit();
+        deserialize_ignored_any => Any, visit_unit();
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        *self.out = Shape::Integer;
+        visitor.visit_i128(0)
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        *self.out = Shape::Integer;
+        visitor.visit_u128(0)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.too_deep() {
+            *self.out = Shape::Optional(Box::new(Shape::Any));
+            return visitor.visit_none();
+        }
+        let mut inner = Shape::Any;
+        let value = visitor.visit_some(self.nested(&mut inner))?;
+        *self.out = Shape::Optional(Box::new(inner));
+        Ok(value)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        *self.out = Shape::Unit;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut access = SeqTrace {
+            shapes: vec![Shape::Any],
+            index: 0,
+            len: usize::from(!self.too_deep()),
+            depth: self.depth,
+        };
+        let value = visitor.visit_seq(&mut access)?;
+        *self.out = Shape::Seq(Box::new(access.shapes.remove(0)));
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        let mut access = SeqTrace {
+            shapes: vec![Shape::Any; len],
+            index: 0,
+            len,
+            depth: self.depth,
+        };
+        let value = visitor.visit_seq(&mut access)?;
+        *self.out = Shape::Tuple(access.shapes);
+        Ok(value)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut access = MapTrace {
+            keys: &[],
+            shapes: Vec::new(),
+            value: Shape::Any,
+            remaining: usize::from(!self.too_deep()),
+            depth: self.depth,
+        };
+        let value = visitor.visit_map(&mut access)?;
+        *self.out = Shape::Map(Box::new(access.value));
+        Ok(value)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let mut access = MapTrace {
+            keys: fields,
+            shapes: Vec::with_capacity(fields.len()),
+            value: Shape::Any,
+            remaining: fields.len(),
+            depth: self.depth,
+        };
+        let value = visitor.visit_map(&mut access)?;
+        *self.out = Shape::Struct(access.shapes);
+        Ok(value)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        *self.out = Shape::Enum(variants.iter().map(ToString::to_string).collect());
+        let variant = variants
+            .first()
+            .ok_or_else(|| Error::custom("cannot describe an enum without variants"))?;
+        visitor.visit_enum(EnumTrace {
+            variant,
+            depth: self.depth,
+        })
+    }
+}
+
+struct SeqTrace {
+    shapes: Vec<Shape>,
+    index: usize,
+    len: usize,
+    depth: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqTrace {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.index >= self.len {
+            return Ok(None);
+        }
+        let out = &mut self.shapes[self.index];
+        self.index += 1;
+        seed.deserialize(Tracer {
+            out,
+            depth: self.depth + 1,
+        })
+        .map(Some)
+    }
+}
+
+/// Map access for both structs (`keys` are the field names) and maps (one traced entry).
+struct MapTrace {
+    keys: &'static [&'static str],
+    shapes: Vec<(String, Shape)>,
+    value: Shape,
+    remaining: usize,
+    depth: usize,
+}
+
+impl<'de> de::MapAccess<'de> for MapTrace {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        match self.keys.get(self.shapes.len()) {
+            Some(key) => {
+                self.shapes.push((key.to_string(), Shape::Any));
+                seed.deserialize(key.into_deserializer()).map(Some)
+            }
+            None => {
+                let mut key_shape = Shape::Any;
+                seed.deserialize(Tracer {
+                    out: &mut key_shape,
+                    depth: self.depth + 1,
+                })
+                .map(Some)
+            }
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let out = match self.shapes.last_mut() {
+            Some((_, shape)) if !self.keys.is_empty() => shape,
+            _ => &mut self.value,
+        };
+        seed.deserialize(Tracer {
+            out,
+            depth: self.depth + 1,
+        })
+    }
+}
+
+struct EnumTrace {
+    variant: &'static str,
+    depth: usize,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumTrace {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for EnumTrace {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        let mut ignored = Shape::Any;
+        seed.deserialize(Tracer {
+            out: &mut ignored,
+            depth: self.depth + 1,
+        })
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        let mut ignored = Shape::Any;
+        de::Deserializer::deserialize_tuple(
+            Tracer {
+                out: &mut ignored,
+                depth: self.depth + 1,
+            },
+            len,
+            visitor,
+        )
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let mut ignored = Shape::Any;
+        de::Deserializer::deserialize_struct(
+            Tracer {
+                out: &mut ignored,
+                depth: self.depth + 1,
+            },
+            "",
+            fields,
+            visitor,
+        )
+    }
+}
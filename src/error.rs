@@ -31,7 +31,10 @@
 //! }
 //! ```
 
+use crate::path::{Path, PathSegment};
+use crate::Key;
 use std::fmt;
+use std::path::PathBuf;
 use thiserror::Error;
 
 /// Represents all possible errors that can occur during TOON serialization/deserialization.
@@ -73,8 +76,8 @@ pub enum Error {
     },
 
     /// Unsupported type for serialization
-    #[error("Unsupported type: {0}")]
-    UnsupportedType(String),
+    #[error("Unsupported type: {type_name} at {path}")]
+    UnsupportedType { type_name: String, path: String },
 
     /// Invalid TOON format
     #[error("Invalid TOON format at line {line}, column {col}: {msg}")]
@@ -95,6 +98,65 @@ pub enum Error {
         context: String,
     },
 
+    /// The same key appears twice in one object (with [`DuplicateKeys::Error`](crate::DuplicateKeys::Error))
+    #[error("Duplicate key `{key}` at line {line}, column {col}")]
+    DuplicateKey {
+        key: String,
+        line: usize,
+        col: usize,
+    },
+
+    /// Content after the end of the document, such as prose following a
+    /// complete value
+    #[error("Unexpected content after the end of the document at line {line}, column {col}")]
+    TrailingContent { line: usize, col: usize },
+
+    /// A struct field that the target type does not declare (with
+    /// [`ToonOptions::with_deny_unknown_fields`](crate::ToonOptions::with_deny_unknown_fields))
+    #[error("Unknown field `{field}` at {path}, expected one of: {expected}")]
+    UnknownField {
+        field: String,
+        path: Path,
+        expected: String,
+    },
+
+    /// A struct field that is absent from the input and has no default
+    #[error("Missing field `{field}` at {path}")]
+    MissingField { field: String, path: Path },
+
+    /// A table without a column that its rows need to fill a struct field
+    #[error("Missing column `{column}` in table at {path}, which has columns: {headers}")]
+    MissingColumn {
+        column: String,
+        path: Path,
+        headers: String,
+    },
+
+    /// The input exceeds a limit set in [`ToonOptions`](crate::ToonOptions),
+    /// such as [`max_depth`](crate::ToonOptions::with_max_depth)
+    #[error("Input exceeds {limit} of {max} at line {line}, column {col}")]
+    LimitExceeded {
+        limit: String,
+        max: usize,
+        line: usize,
+        col: usize,
+    },
+
+    /// A value that does not fit the type it is deserialized into, such as a
+    /// string where a number is expected, with the path of the value
+    #[error("{msg} at {path}")]
+    Data { msg: String, path: Path },
+
+    /// An error reading or writing a file with [`from_file`](crate::from_file)
+    /// or [`to_file`](crate::to_file), with the file's path and, when it is
+    /// known, the line the error is on
+    #[error("{}{}: {error}", .path.display(), .line.map(|line| format!(":{}", line)).unwrap_or_default())]
+    File {
+        path: PathBuf,
+        line: Option<usize>,
+        error: Box<Error>,
+    },
+
     /// Custom error
     #[error("Error: {0}")]
     Custom(String),
@@ -229,8 +291,20 @@ impl Error {
     }
 
     /// Creates an unsupported type error for types that cannot be serialized to TOON.
-    pub fn unsupported_type(msg: &str) -> Self {
-        Error::UnsupportedType(msg.to_string())
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::Error;
+    ///
+    /// let err = Error::unsupported_type("map key of type sequence", "users[3].tags");
+    /// assert!(err.to_string().contains("at users[3].tags"));
+    /// ```
+    pub fn unsupported_type(type_name: &str, path: &str) -> Self {
+        Error::UnsupportedType {
+            type_name: type_name.to_string(),
+            path: path.to_string(),
+        }
     }
 
     /// Creates a custom error with a display message.
@@ -251,6 +325,49 @@ impl Error {
     pub fn io(msg: &str) -> Self {
         Error::Io(msg.to_string())
     }
+
+    /// Prefixes the path of a path-carrying error with the segment of the
+    /// enclosing value, as the error propagates out of nested deserializers.
+    /// A custom error raised inside the value gains a path as [`Error::Data`].
+    pub(crate) fn within(mut self, segment: PathSegment) -> Self {
+        match &mut self {
+            Error::UnknownField { path, .. }
+            | Error::MissingField { path, .. }
+            | Error::MissingColumn { path, .. }
+            | Error::Data { path, .. } => path.prepend(segment),
+            Error::Custom(msg) => {
+                let mut path = Path::root();
+                path.push(segment);
+                return Error::Data {
+                    msg: std::mem::take(msg),
+                    path,
+                };
+            }
+            _ => {}
+        }
+        self
+    }
+
+    /// Turns a missing field of a table row into a missing column of the
+    /// table, if none of the table's `headers` is the field.
+    pub(crate) fn for_table_row(self, index: usize, headers: &[Key]) -> Self {
+        match self {
+            Error::MissingField { field, path }
+                if path.is_root() && !headers.iter().any(|header| *header == field) =>
+            {
+                Error::MissingColumn {
+                    column: field,
+                    path,
+                    headers: headers
+                        .iter()
+                        .map(Key::as_str)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                }
+            }
+            err => err.within(PathSegment::Index(index)),
+        }
+    }
 }
 
 impl serde::ser::Error for Error {
@@ -261,8 +378,104 @@ impl serde::ser::Error for Error {
 
 impl serde::de::Error for Error {
     fn custom<T: fmt::Display>(msg: T) -> Self {
-        Error::Custom(msg.to_string())
+        Error::Data {
+            msg: msg.to_string(),
+            path: Path::root(),
+        }
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Error::MissingField {
+            field: field.to_string(),
+            path: Path::root(),
+        }
     }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+/// The category of a [`Warning`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WarningKind {
+    /// A value could not be represented in TOON and was replaced or dropped.
+    UnsupportedValue,
+    /// An array or table row had a different number of items than declared.
+    LengthMismatch,
+    /// Content that is not part of the document (e.g. prose) was skipped.
+    TrailingContent,
+    /// A quoted string was not closed and was ended at the end of its line.
+    UnterminatedString,
+    /// A table row's `#` cell did not match its position (with
+    /// [`ToonOptions::with_row_numbers`](crate::ToonOptions::with_row_numbers)).
+    RowNumberMismatch,
+    /// A line was indented in a way its blocks do not allow and was read as
+    /// part of the enclosing block.
+    Indentation,
+    /// An object repeated a key, and one of the values was dropped (with
+    /// [`DuplicateKeys::FirstWins`](crate::DuplicateKeys::FirstWins) or
+    /// [`DuplicateKeys::LastWins`](crate::DuplicateKeys::LastWins)).
+    DuplicateKey,
+    /// A quoted string contained an unknown escape such as `\q`, which was
+    /// kept as written.
+    UnknownEscape,
+}
+
+/// A non-fatal issue encountered during serialization or deserialization.
+///
+/// Warnings are collected instead of aborting the operation, for example when
+/// [`UnsupportedPolicy::Null`](crate::UnsupportedPolicy::Null) replaces a value
+/// that TOON cannot represent, or when lenient parsing repairs malformed input.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{Warning, WarningKind};
+///
+/// let warning = Warning::at_path(WarningKind::UnsupportedValue, "config.limits", "replaced with null");
+/// assert_eq!(warning.to_string(), "replaced with null at config.limits");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub message: String,
+    pub path: Option<String>,
+    pub line: Option<usize>,
+    pub col: Option<usize>,
+}
+
+impl Warning {
+    /// Creates a warning attached to a value path (used during serialization).
+    pub fn at_path(kind: WarningKind, path: &str, message: &str) -> Self {
+        Warning {
+            kind,
+            message: message.to_string(),
+            path: Some(path.to_string()),
+            line: None,
+            col: None,
+        }
+    }
+
+    /// Creates a warning attached to a position in the input (used during parsing).
+    pub fn at_position(kind: WarningKind, line: usize, col: usize, message: &str) -> Self {
+        Warning {
+            kind,
+            message: message.to_string(),
+            path: None,
+            line: Some(line),
+            col: Some(col),
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if let Some(path) = &self.path {
+            write!(f, " at {}", path)?;
+        }
+        if let (Some(line), Some(col)) = (self.line, self.col) {
+            write!(f, " at line {}, column {}", line, col)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1062 @@
+//! Low-level, event-based reading and writing of TOON documents.
+//!
+//! [`ToonReader`] pulls a document apart into a stream of [`Event`]s one call
+//! at a time, and [`ToonWriter`] puts a stream of events back together as
+//! TOON text. Neither builds a [`Value`] for the whole document, so tools such
+//! as pretty-printers, statistics collectors and transcoders can work on
+//! large documents in little memory.
+//!
+//! The events of a document are those of its root value. An object is
+//! [`Event::StartObject`], then an [`Event::Key`] followed by the events of
+//! its value for each field, then [`Event::End`]. Arrays and tables open with
+//! [`Event::StartArray`] and [`Event::StartTable`], hold the events of their
+//! items or one [`Event::Row`] per row, and close with [`Event::End`] too.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde_toon::event::{Event, ToonReader};
+//!
+//! // Count the rows of every table in a document
+//! let doc = "users: [2]{id,name}:\n  1,Ada\n  2,Grace\nadmins: [1]{id}:\n  1";
+//! let mut rows = 0;
+//! for event in ToonReader::new(doc) {
+//!     if let Event::Row(_) = event.unwrap() {
+//!         rows += 1;
+//!     }
+//! }
+//! assert_eq!(rows, 3);
+//! ```
+//!
+//! Events read from one document can be written straight back out with other
+//! options:
+//!
+//! ```rust
+//! use serde_toon::event::{ToonReader, ToonWriter};
+//! use serde_toon::{Delimiter, ToonOptions};
+//!
+//! let mut reader = ToonReader::new("tags: [3]: a,b,c");
+//! let options = ToonOptions::new().with_delimiter(Delimiter::Pipe);
+//! let mut writer = ToonWriter::with_options(Vec::new(), options);
+//! while let Some(event) = reader.next_event().unwrap() {
+//!     writer.write_event(&event).unwrap();
+//! }
+//! let output = writer.finish().unwrap();
+//! assert_eq!(String::from_utf8(output).unwrap(), "tags: [3|]: a|b|c");
+//! ```
+
+use crate::de::{Deserializer, TableCursor};
+use crate::lexer::TokenKind;
+use crate::ser::{
+    key_text, write_inline_array, write_length, write_table_header, write_toon_value_quoted,
+};
+use crate::{Error, LineEnding, Result, ToonOptions, Value, Warning};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io;
+
+/// A step through a TOON document, as read by [`ToonReader`] and written by
+/// [`ToonWriter`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The start of an object, whose fields follow as an [`Event::Key`] and
+    /// the events of its value each, up to the matching [`Event::End`].
+    StartObject,
+    /// The key of the next field of the enclosing object.
+    Key(String),
+    /// A primitive value: `null`, a boolean, a number or a string.
+    Scalar(Value),
+    /// The start of an inline or list-form array, whose items follow up to
+    /// the matching [`Event::End`]. `len` is the length its header declares,
+    /// missing only in lenient mode.
+    StartArray { len: Option<usize> },
+    /// The start of a tabular array with its column headers, whose rows
+    /// follow as [`Event::Row`]s up to the matching [`Event::End`]. `len` is
+    /// the length its header declares, missing only in lenient mode.
+    StartTable {
+        headers: Vec<String>,
+        len: Option<usize>,
+    },
+    /// A row of the enclosing table, with one cell per header.
+    Row(Vec<Value>),
+    /// The end of the innermost object, array or table.
+    End,
+}
+
+/// What the reader does once a value has been read.
+#[derive(Debug, Clone, Copy)]
+enum Then {
+    Nothing,
+    /// Close the indented block the value was in
+    FinishBlock,
+    /// Check that nothing follows the root value
+    FinishRoot,
+}
+
+/// A container the reader is inside of.
+enum Frame {
+    Object {
+        // Deeper blocks entered within the object
+        open: usize,
+        // Whether a key was just read, so its value is next
+        field: bool,
+        then: Then,
+    },
+    List {
+        // Deeper blocks entered within the list
+        open: usize,
+        // Whether the items are in an indented block below the header
+        block: bool,
+        items: usize,
+        declared_length: Option<usize>,
+        // Position of the header, for length mismatches
+        at: (usize, usize),
+        then: Then,
+    },
+    Table {
+        cursor: TableCursor,
+        then: Then,
+    },
+}
+
+/// A pull parser yielding the [`Event`]s of a TOON document one at a time.
+///
+/// The reader parses exactly as [`from_str`](crate::from_str) and
+/// [`Deserializer`] do, with the same errors, limits and lenient-mode
+/// repairs, but never holds more than one line's values. It is also an
+/// [`Iterator`] over the events.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::event::{Event, ToonReader};
+/// use serde_toon::Value;
+///
+/// let mut reader = ToonReader::new("name: Ada\ntags: [2]: math,code");
+/// assert_eq!(reader.next_event().unwrap(), Some(Event::StartObject));
+/// assert_eq!(reader.next_event().unwrap(), Some(Event::Key("name".into())));
+/// assert_eq!(reader.next_event().unwrap(), Some(Event::Scalar(Value::from("Ada"))));
+/// assert_eq!(reader.next_event().unwrap(), Some(Event::Key("tags".into())));
+/// assert_eq!(reader.next_event().unwrap(), Some(Event::StartArray { len: Some(2) }));
+/// ```
+pub struct ToonReader<'a> {
+    de: Deserializer<'a>,
+    stack: Vec<Frame>,
+    // Events already parsed, such as the items of an inline array
+    queued: VecDeque<Event>,
+    // Whether the whole document has been read, or reading failed
+    done: bool,
+}
+
+impl<'a> ToonReader<'a> {
+    /// Creates a reader over a TOON document.
+    pub fn new(input: &'a str) -> Self {
+        Self::with_options(input, ToonOptions::default())
+    }
+
+    /// Creates a reader with custom options (e.g. lenient parsing).
+    pub fn with_options(input: &'a str, options: ToonOptions) -> Self {
+        ToonReader {
+            de: Deserializer::with_options(input, options),
+            stack: Vec::new(),
+            queued: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Reads the next event, or returns `None` at the end of the document.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document is not valid TOON format. No events
+    /// follow an error.
+    pub fn next_event(&mut self) -> Result<Option<Event>> {
+        if let Some(event) = self.queued.pop_front() {
+            return Ok(Some(event));
+        }
+        if self.done {
+            return Ok(None);
+        }
+        let event = self.read();
+        if event.is_err() {
+            self.done = true;
+            self.stack.clear();
+        }
+        event.map(Some)
+    }
+
+    /// Returns the warnings recorded so far while reading in lenient mode.
+    #[must_use]
+    pub fn warnings(&self) -> &[Warning] {
+        self.de.warnings()
+    }
+
+    fn read(&mut self) -> Result<Event> {
+        let Some(frame) = self.stack.last_mut() else {
+            return self.root_value();
+        };
+        match frame {
+            Frame::Object { open, field, then } => {
+                if *field {
+                    *field = false;
+                    return self.field_value();
+                }
+                if let Some((key, _)) = self.de.next_key(open)? {
+                    *field = true;
+                    return Ok(Event::Key(key.into()));
+                }
+                let then = *then;
+                self.close(then)
+            }
+            Frame::List {
+                open,
+                block,
+                items,
+                declared_length,
+                at,
+                then,
+            } => {
+                if self.de.next_item(open, *block)?.is_some() {
+                    self.de.check_array_length(*items + 1, *at)?;
+                    *items += 1;
+                    return self.item_value();
+                }
+                if *block {
+                    self.de.close_block();
+                }
+                let then = *then;
+                self.de.check_length(*declared_length, *items, *at)?;
+                self.close(then)
+            }
+            Frame::Table { cursor, then } => {
+                if let Some(row) = self.de.next_table_row(cursor)? {
+                    return Ok(Event::Row(row));
+                }
+                let then = *then;
+                self.close(then)
+            }
+        }
+    }
+
+    /// Leaves the innermost container, returning its [`Event::End`].
+    fn close(&mut self, then: Then) -> Result<Event> {
+        self.stack.pop();
+        self.de.leave();
+        self.then(then)?;
+        Ok(Event::End)
+    }
+
+    fn then(&mut self, then: Then) -> Result<()> {
+        match then {
+            Then::Nothing => Ok(()),
+            Then::FinishBlock => self.de.finish_block(),
+            Then::FinishRoot => {
+                self.done = true;
+                self.de.finish_root()
+            }
+        }
+    }
+
+    /// Starts reading the document: its first line decides what it is.
+    fn root_value(&mut self) -> Result<Event> {
+        self.de.check_document_size()?;
+        self.de.check_indent()?;
+        match self.de.peek_kind() {
+            None => self.empty_object(Then::FinishRoot),
+            Some(TokenKind::TableHeader(_)) => self.line_value(Then::FinishRoot),
+            Some(TokenKind::Key(_)) => self.begin_object(Then::FinishRoot),
+            Some(_) => {
+                let value = self.de.parse_root_scalar()?;
+                self.then(Then::FinishRoot)?;
+                Ok(Event::Scalar(value))
+            }
+        }
+    }
+
+    /// Starts reading the value of the field whose key was just read: the
+    /// rest of the key's line, or the indented block below it.
+    fn field_value(&mut self) -> Result<Event> {
+        if !matches!(self.de.peek_kind(), Some(TokenKind::Newline) | None) {
+            return self.line_value(Then::Nothing);
+        }
+        if !self.de.open_block()? {
+            return self.empty_object(Then::Nothing);
+        }
+        match self.de.peek_kind() {
+            Some(TokenKind::Key(_)) => self.begin_object(Then::FinishBlock),
+            _ => self.line_value(Then::FinishBlock),
+        }
+    }
+
+    /// Starts reading a list item after its dash.
+    fn item_value(&mut self) -> Result<Event> {
+        if !matches!(self.de.peek_kind(), Some(TokenKind::Indent)) {
+            // A lone `-` is an empty object
+            self.de.skip_line();
+            return self.empty_object(Then::Nothing);
+        }
+        self.de.next_token();
+        match self.de.peek_kind() {
+            Some(TokenKind::Key(_)) => self.begin_object(Then::FinishBlock),
+            _ => self.line_value(Then::FinishBlock),
+        }
+    }
+
+    fn empty_object(&mut self, then: Then) -> Result<Event> {
+        self.then(then)?;
+        self.queued.push_back(Event::End);
+        Ok(Event::StartObject)
+    }
+
+    fn begin_object(&mut self, then: Then) -> Result<Event> {
+        let at = self.de.position();
+        self.de.enter(at)?;
+        self.stack.push(Frame::Object {
+            open: 0,
+            field: false,
+            then,
+        });
+        Ok(Event::StartObject)
+    }
+
+    /// Starts reading a value that starts on the current line and is not an
+    /// object: an array from its header, or a primitive.
+    fn line_value(&mut self, then: Then) -> Result<Event> {
+        if !matches!(self.de.peek_kind(), Some(TokenKind::TableHeader(_))) {
+            let value = self.de.parse_line_value()?;
+            self.then(then)?;
+            return Ok(Event::Scalar(value));
+        }
+        let Some(token) = self.de.next_token() else {
+            return Err(Error::custom("Expected array"));
+        };
+        let TokenKind::TableHeader(header) = &token.kind else {
+            return Err(Error::custom("Expected array"));
+        };
+        let at = (token.line, token.column);
+        let header = self.de.settle_delimiter(&token, header);
+        self.de.enter(at)?;
+        let declared_length = self.de.declared_length(&token, &header)?;
+
+        if !header.fields.is_empty() {
+            let cursor = self.de.begin_table(&header, declared_length, at)?;
+            let event = Event::StartTable {
+                headers: cursor.headers.iter().map(ToString::to_string).collect(),
+                len: cursor.declared_length,
+            };
+            self.stack.push(Frame::Table { cursor, then });
+            return Ok(event);
+        }
+
+        if matches!(self.de.peek_kind(), Some(TokenKind::Scalar(_))) {
+            let Some(items) = self.de.next_token() else {
+                return Err(Error::custom("Expected array"));
+            };
+            let array =
+                self.de
+                    .parse_inline_array(&items, declared_length, &header.delimiter, at)?;
+            let Value::Array(elements) = array else {
+                return Err(Error::custom("Expected array"));
+            };
+            self.de.leave();
+            self.then(then)?;
+            let len = elements.len();
+            self.queued.extend(elements.into_iter().map(Event::Scalar));
+            self.queued.push_back(Event::End);
+            return Ok(Event::StartArray { len: Some(len) });
+        }
+
+        let block = self.de.open_block()?;
+        self.stack.push(Frame::List {
+            open: 0,
+            block,
+            items: 0,
+            declared_length,
+            at,
+            then,
+        });
+        Ok(Event::StartArray {
+            len: declared_length,
+        })
+    }
+}
+
+impl Iterator for ToonReader<'_> {
+    type Item = Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+/// Where the next value goes in the output.
+struct Slot {
+    // The text before the value on its line: `key:` or `-` with their
+    // indentation, or nothing at the root
+    head: String,
+    // Indentation of the fields of an object value
+    fields: String,
+    // Written before the first field of an object value instead of
+    // `fields`, as `- ` for a list item
+    first_field: Option<String>,
+    // Indentation of the items or rows of an array value
+    items: String,
+}
+
+impl Slot {
+    /// Returns the line of a value written right after the slot's head.
+    fn line(&self, value: &str) -> String {
+        if self.head.is_empty() {
+            value.to_string()
+        } else {
+            format!("{} {}", self.head, value)
+        }
+    }
+}
+
+/// A container the writer is inside of.
+enum Open {
+    Object {
+        slot: Slot,
+        // Key of the field whose value is next
+        key: Option<String>,
+        fields: usize,
+    },
+    Array {
+        slot: Slot,
+        len: Option<usize>,
+        // Items held while they may all still fit on one inline line
+        scalars: Vec<Value>,
+        // Number of items written in list form, once one is not a primitive
+        listed: Option<usize>,
+        // Lines held until the length is known, if not declared
+        buffer: Option<String>,
+    },
+    Table {
+        slot: Slot,
+        headers: Vec<String>,
+        len: Option<usize>,
+        rows: usize,
+        // Lines held until the length is known, if not declared
+        buffer: Option<String>,
+    },
+}
+
+impl Open {
+    fn buffer(&mut self) -> Option<&mut String> {
+        match self {
+            Open::Object { .. } => None,
+            Open::Array { buffer, .. } | Open::Table { buffer, .. } => buffer.as_mut(),
+        }
+    }
+}
+
+/// Writes a stream of [`Event`]s as a TOON document.
+///
+/// Lines are written as soon as they are complete, with the indentation,
+/// delimiter, quoting, length marker and key aliases of the given
+/// [`ToonOptions`]. Only an array is held back until it ends, while its items
+/// may all still go on one line, and an array or table whose length is not
+/// given, until it can be counted. Layout that needs a whole value up front
+/// (sorted keys, aligned table columns, blank lines in pretty output and
+/// block strings) does not apply.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::event::{Event, ToonWriter};
+/// use serde_toon::Value;
+///
+/// let mut writer = ToonWriter::new(Vec::new());
+/// for event in [
+///     Event::StartObject,
+///     Event::Key("users".into()),
+///     Event::StartTable { headers: vec!["id".into(), "name".into()], len: Some(2) },
+///     Event::Row(vec![Value::from(1), Value::from("Ada")]),
+///     Event::Row(vec![Value::from(2), Value::from("Grace")]),
+///     Event::End,
+///     Event::End,
+/// ] {
+///     writer.write_event(&event).unwrap();
+/// }
+/// let output = String::from_utf8(writer.finish().unwrap()).unwrap();
+/// assert_eq!(output, "users: [2]{id,name}:\n  1,Ada\n  2,Grace");
+/// ```
+pub struct ToonWriter<W> {
+    writer: W,
+    options: ToonOptions,
+    stack: Vec<Open>,
+    // Whether a line has been written, so the next one starts with a newline
+    started: bool,
+    // Whether the root value is complete
+    done: bool,
+}
+
+impl<W: io::Write> ToonWriter<W> {
+    /// Creates a writer with the default options.
+    pub fn new(writer: W) -> Self {
+        Self::with_options(writer, ToonOptions::default())
+    }
+
+    /// Creates a writer with custom options.
+    pub fn with_options(writer: W, options: ToonOptions) -> Self {
+        ToonWriter {
+            writer,
+            options,
+            stack: Vec::new(),
+            started: false,
+            done: false,
+        }
+    }
+
+    /// Writes the next event of the document.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the event cannot come next, such as a key outside
+    /// an object, a row whose width does not match its table's headers, or an
+    /// array with more or fewer items than its declared length, or if writing
+    /// to the underlying writer fails.
+    pub fn write_event(&mut self, event: &Event) -> Result<()> {
+        if self.done {
+            return Err(Error::custom(
+                "Unexpected event after the end of the document",
+            ));
+        }
+        match event {
+            Event::Key(key) => match self.stack.last_mut() {
+                Some(Open::Object {
+                    key: next @ None, ..
+                }) => {
+                    *next = Some(key.clone());
+                    Ok(())
+                }
+                _ => Err(Error::custom(format!("Unexpected key `{}`", key))),
+            },
+            Event::Scalar(value) => {
+                if !is_primitive(value) {
+                    return Err(Error::custom("Expected a primitive value"));
+                }
+                if let Some(Open::Array {
+                    scalars,
+                    listed: None,
+                    ..
+                }) = self.stack.last_mut()
+                {
+                    scalars.push(value.clone());
+                    return Ok(());
+                }
+                let slot = self.slot()?;
+                let mut text = String::new();
+                write_toon_value_quoted(&mut text, value, &self.options);
+                self.line(&slot.line(&text))?;
+                self.value_done();
+                Ok(())
+            }
+            Event::StartObject => {
+                let slot = self.slot()?;
+                // Fields go below their key; a list item's first field goes
+                // after its dash, and the root object's start the document
+                if slot.first_field.is_none() && !slot.head.is_empty() {
+                    self.line(&slot.head)?;
+                }
+                self.stack.push(Open::Object {
+                    slot,
+                    key: None,
+                    fields: 0,
+                });
+                Ok(())
+            }
+            Event::StartArray { len } => {
+                let slot = self.slot()?;
+                self.stack.push(Open::Array {
+                    slot,
+                    len: *len,
+                    scalars: Vec::new(),
+                    listed: None,
+                    buffer: None,
+                });
+                Ok(())
+            }
+            Event::StartTable { headers, len } => {
+                if headers.is_empty() {
+                    return Err(Error::custom("Expected table headers"));
+                }
+                let slot = self.slot()?;
+                let buffer = match len {
+                    Some(len) => {
+                        let header = self.table_header(*len, headers);
+                        self.line(&slot.line(&header))?;
+                        None
+                    }
+                    None => Some(String::new()),
+                };
+                self.stack.push(Open::Table {
+                    slot,
+                    headers: headers.clone(),
+                    len: *len,
+                    rows: 0,
+                    buffer,
+                });
+                Ok(())
+            }
+            Event::Row(cells) => self.row(cells),
+            Event::End => self.end(),
+        }
+    }
+
+    /// Checks that the document is complete and returns the underlying
+    /// writer, flushed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an object, array or table is still open, or if
+    /// flushing the underlying writer fails.
+    pub fn finish(mut self) -> Result<W> {
+        if !self.stack.is_empty() {
+            return Err(Error::custom("Expected end of object, array or table"));
+        }
+        if self.options.trailing_newline && self.started {
+            self.write("\n")?;
+        }
+        self.writer.flush().map_err(|e| Error::io(&e.to_string()))?;
+        Ok(self.writer)
+    }
+
+    fn row(&mut self, cells: &[Value]) -> Result<()> {
+        let Some(Open::Table {
+            slot,
+            headers,
+            len,
+            rows,
+            ..
+        }) = self.stack.last_mut()
+        else {
+            return Err(Error::custom("Unexpected row outside a table"));
+        };
+        if cells.len() != headers.len() {
+            return Err(Error::custom(format!(
+                "row has {} values for {} headers",
+                cells.len(),
+                headers.len()
+            )));
+        }
+        if len.is_some_and(|len| *rows == len) {
+            return Err(Error::custom(format!(
+                "table declared {} rows but has more",
+                rows
+            )));
+        }
+        if !cells.iter().all(is_primitive) {
+            return Err(Error::custom("Expected a primitive value"));
+        }
+        *rows += 1;
+
+        let mut text = slot.items.clone();
+        for (i, cell) in cells.iter().enumerate() {
+            if i > 0 {
+                text.push_str(self.options.delimiter.as_str());
+            }
+            write_toon_value_quoted(&mut text, cell, &self.options);
+        }
+        self.line(&text)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        let Some(open) = self.stack.pop() else {
+            return Err(Error::custom(
+                "Unexpected end outside an object, array or table",
+            ));
+        };
+        match open {
+            Open::Object { slot, key, fields } => {
+                if let Some(key) = key {
+                    return Err(Error::custom(format!("Expected a value for key `{}`", key)));
+                }
+                // An empty list item is a lone dash
+                if fields == 0 && slot.first_field.is_some() {
+                    self.line(&slot.head)?;
+                }
+            }
+            Open::Array {
+                slot,
+                len,
+                scalars,
+                listed: None,
+                ..
+            } => {
+                check_length(len, scalars.len())?;
+                let mut text = String::new();
+                if scalars.is_empty() {
+                    write_length(&mut text, 0, &self.options, false);
+                    text.push(':');
+                } else {
+                    write_inline_array(&mut text, &scalars, &self.options);
+                }
+                self.line(&slot.line(&text))?;
+            }
+            Open::Array {
+                slot,
+                len,
+                listed: Some(items),
+                buffer,
+                ..
+            } => {
+                check_length(len, items)?;
+                if let Some(buffer) = buffer {
+                    let mut header = String::new();
+                    write_length(&mut header, items, &self.options, false);
+                    header.push(':');
+                    self.line(&slot.line(&header))?;
+                    self.write(&buffer)?;
+                }
+            }
+            Open::Table {
+                slot,
+                headers,
+                len,
+                rows,
+                buffer,
+            } => {
+                check_length(len, rows)?;
+                if let Some(buffer) = buffer {
+                    let header = self.table_header(rows, &headers);
+                    self.line(&slot.line(&header))?;
+                    self.write(&buffer)?;
+                }
+            }
+        }
+        self.value_done();
+        Ok(())
+    }
+
+    /// Finds where the next value goes, taking the key it is the value of or
+    /// turning an array of primitives so far into a list.
+    fn slot(&mut self) -> Result<Slot> {
+        let indent = " ".repeat(self.options.indent);
+        let Some(open) = self.stack.last_mut() else {
+            return Ok(Slot {
+                head: String::new(),
+                fields: String::new(),
+                first_field: None,
+                items: indent,
+            });
+        };
+        match open {
+            Open::Object { slot, key, fields } => {
+                let Some(key) = key.take() else {
+                    return Err(Error::custom("Expected a key before the value"));
+                };
+                let start = match slot.first_field.take() {
+                    Some(first) => first,
+                    None => slot.fields.clone(),
+                };
+                *fields += 1;
+                let key = key_text(self.options.written_key(&key), &self.options);
+                let nested = format!("{}{}", slot.fields, indent);
+                Ok(Slot {
+                    head: format!("{}{}:", start, key),
+                    fields: nested.clone(),
+                    first_field: None,
+                    items: nested,
+                })
+            }
+            Open::Array { .. } => {
+                let items = self.list_item()?;
+                Ok(Slot {
+                    head: format!("{}-", items),
+                    fields: format!("{}  ", items),
+                    first_field: Some(format!("{}- ", items)),
+                    items: format!("{}  {}", items, indent),
+                })
+            }
+            Open::Table { .. } => Err(Error::custom("Expected a row")),
+        }
+    }
+
+    /// Counts the next item of the innermost array, which is written in list
+    /// form, and returns the indentation of its dash. The first call writes
+    /// the header and any primitive items held so far.
+    fn list_item(&mut self) -> Result<String> {
+        let Some(Open::Array {
+            slot,
+            len,
+            scalars,
+            listed,
+            buffer,
+        }) = self.stack.last_mut()
+        else {
+            return Err(Error::custom("Expected an array"));
+        };
+        let items = slot.items.clone();
+        if let Some(count) = listed {
+            *count += 1;
+            return Ok(items);
+        }
+
+        *listed = Some(scalars.len() + 1);
+        let held = std::mem::take(scalars);
+        let header = match len {
+            Some(len) => {
+                let mut header = String::new();
+                write_length(&mut header, *len, &self.options, false);
+                header.push(':');
+                Some(slot.line(&header))
+            }
+            None => {
+                *buffer = Some(String::new());
+                None
+            }
+        };
+        if let Some(header) = header {
+            self.line(&header)?;
+        }
+        for value in &held {
+            let mut text = format!("{}- ", items);
+            write_toon_value_quoted(&mut text, value, &self.options);
+            self.line(&text)?;
+        }
+        Ok(items)
+    }
+
+    fn table_header(&self, len: usize, headers: &[String]) -> String {
+        let headers: Vec<_> = headers
+            .iter()
+            .map(|header| key_text(self.options.written_key(header), &self.options))
+            .collect();
+        let mut header = String::new();
+        write_table_header(&mut header, len, &headers, &self.options);
+        header
+    }
+
+    fn value_done(&mut self) {
+        if self.stack.is_empty() {
+            self.done = true;
+        }
+    }
+
+    /// Writes a line, held back by the innermost container that is waiting
+    /// for its length, if any.
+    fn line(&mut self, text: &str) -> Result<()> {
+        if let Some(buffer) = self.stack.iter_mut().rev().find_map(Open::buffer) {
+            buffer.push('\n');
+            buffer.push_str(text);
+            return Ok(());
+        }
+        if self.started {
+            self.write("\n")?;
+        }
+        self.started = true;
+        self.write(text)
+    }
+
+    /// Writes text as is, to the innermost held lines if any. Line breaks
+    /// become [`ToonOptions::line_ending`] on their way to the writer.
+    fn write(&mut self, text: &str) -> Result<()> {
+        if let Some(buffer) = self.stack.iter_mut().rev().find_map(Open::buffer) {
+            buffer.push_str(text);
+            return Ok(());
+        }
+        let text = match self.options.line_ending {
+            LineEnding::Lf => Cow::Borrowed(text),
+            LineEnding::CrLf => Cow::Owned(text.replace('\n', "\r\n")),
+        };
+        self.writer
+            .write_all(text.as_bytes())
+            .map_err(|e| Error::io(&e.to_string()))
+    }
+}
+
+fn is_primitive(value: &Value) -> bool {
+    !matches!(value, Value::Array(_) | Value::Object(_) | Value::Table(_))
+}
+
+fn check_length(declared: Option<usize>, found: usize) -> Result<()> {
+    match declared {
+        Some(declared) if declared != found => Err(Error::custom(format!(
+            "array declared {} items but has {}",
+            declared, found
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_str, to_string, toon, Delimiter};
+
+    fn events(input: &str) -> Vec<Event> {
+        ToonReader::new(input).collect::<Result<_>>().unwrap()
+    }
+
+    fn write(events: &[Event], options: ToonOptions) -> Result<String> {
+        let mut writer = ToonWriter::with_options(Vec::new(), options);
+        for event in events {
+            writer.write_event(event)?;
+        }
+        Ok(String::from_utf8(writer.finish()?).unwrap())
+    }
+
+    #[test]
+    fn reads_nested_values() {
+        let input = "id: 7\nuser:\n  name: Ada\n  tags: [2]: a,b\nitems: [2]:\n  - x: 1\n  - 3";
+        assert_eq!(
+            events(input),
+            [
+                Event::StartObject,
+                Event::Key("id".into()),
+                Event::Scalar(Value::from(7)),
+                Event::Key("user".into()),
+                Event::StartObject,
+                Event::Key("name".into()),
+                Event::Scalar(Value::from("Ada")),
+                Event::Key("tags".into()),
+                Event::StartArray { len: Some(2) },
+                Event::Scalar(Value::from("a")),
+                Event::Scalar(Value::from("b")),
+                Event::End,
+                Event::End,
+                Event::Key("items".into()),
+                Event::StartArray { len: Some(2) },
+                Event::StartObject,
+                Event::Key("x".into()),
+                Event::Scalar(Value::from(1)),
+                Event::End,
+                Event::Scalar(Value::from(3)),
+                Event::End,
+                Event::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_tables_and_root_values() {
+        assert_eq!(
+            events("[2]{a,b}:\n  1,x\n  2,y"),
+            [
+                Event::StartTable {
+                    headers: vec!["a".into(), "b".into()],
+                    len: Some(2)
+                },
+                Event::Row(vec![Value::from(1), Value::from("x")]),
+                Event::Row(vec![Value::from(2), Value::from("y")]),
+                Event::End,
+            ]
+        );
+        assert_eq!(events("42"), [Event::Scalar(Value::from(42))]);
+        assert_eq!(events(""), [Event::StartObject, Event::End]);
+        assert_eq!(
+            events("a:\nb: 1"),
+            [
+                Event::StartObject,
+                Event::Key("a".into()),
+                Event::StartObject,
+                Event::End,
+                Event::Key("b".into()),
+                Event::Scalar(Value::from(1)),
+                Event::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_errors_once() {
+        let mut reader = ToonReader::new("a: 1\nb: [3]: x,y");
+        let error = reader.by_ref().find_map(Result::err).unwrap();
+        assert!(matches!(error, Error::Syntax { line: 2, .. }));
+        assert!(reader.next().is_none());
+
+        let trailing: Result<Vec<_>> = ToonReader::new("[2]: 1,2\nthanks").collect();
+        assert!(matches!(
+            trailing,
+            Err(Error::TrailingContent { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn round_trips_through_the_writer() {
+        let value = toon!({
+            "name": "Store",
+            "empty": {},
+            "tags": ["a", "b, c"],
+            "none": [],
+            "products": [{"id": 1, "name": "Widget"}, {"id": 2, "name": "Gadget"}],
+            "mixed": [1, {"x": 1, "y": {"z": [true, false]}}, [1, 2], "last"],
+            "nested": {"deeper": {"deepest": null}}
+        });
+        let input = to_string(&value).unwrap();
+        let output = write(&events(&input), ToonOptions::default()).unwrap();
+        assert_eq!(output, input);
+        assert_eq!(from_str::<Value>(&output).unwrap(), value);
+
+        let options = ToonOptions::new().with_delimiter(Delimiter::Pipe);
+        let output = write(&events(&input), options.clone()).unwrap();
+        assert_eq!(
+            output,
+            crate::to_string_with_options(&value, options).unwrap()
+        );
+    }
+
+    #[test]
+    fn counts_arrays_without_a_length() {
+        let output = write(
+            &[
+                Event::StartArray { len: None },
+                Event::Scalar(Value::from(1)),
+                Event::StartObject,
+                Event::Key("a".into()),
+                Event::StartTable {
+                    headers: vec!["x".into()],
+                    len: None,
+                },
+                Event::Row(vec![Value::from(1)]),
+                Event::Row(vec![Value::from(2)]),
+                Event::End,
+                Event::End,
+                Event::End,
+            ],
+            ToonOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(output, "[2]:\n  - 1\n  - a: [2]{x}:\n      1\n      2");
+    }
+
+    #[test]
+    fn rejects_malformed_streams() {
+        let default = ToonOptions::default;
+        assert!(write(&[Event::Key("a".into())], default()).is_err());
+        assert!(write(
+            &[Event::StartObject, Event::Scalar(Value::from(1))],
+            default()
+        )
+        .is_err());
+        assert!(write(&[Event::StartObject], default()).is_err());
+        assert!(write(&[Event::End], default()).is_err());
+        assert!(write(
+            &[
+                Event::StartArray { len: Some(2) },
+                Event::Scalar(Value::from(1)),
+                Event::End
+            ],
+            default()
+        )
+        .is_err());
+        assert!(write(
+            &[
+                Event::StartTable {
+                    headers: vec!["a".into(), "b".into()],
+                    len: Some(1)
+                },
+                Event::Row(vec![Value::from(1)]),
+            ],
+            default()
+        )
+        .is_err());
+        assert!(write(
+            &[Event::Scalar(Value::from(1)), Event::Scalar(Value::from(2))],
+            default()
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! Locating TOON documents inside mixed text.
+//!
+//! LLM responses usually wrap the requested data in prose, often inside a
+//! fenced ```` ```toon ```` block. [`extract_from_text`] finds and parses the
+//! first well-formed TOON document in such text.
+//!
+//! ## Examples
+//!
+//! ````rust
+//! use serde_toon::extract_from_text;
+//!
+//! let reply = "Sure! Here are the users:\n\n```toon\n[2]{id,name}:\n  1,Alice\n  2,Bob\n```\n\nAnything else?";
+//! let (value, range) = extract_from_text(reply).unwrap();
+//!
+//! assert!(value.is_table());
+//! assert_eq!(&reply[range], "[2]{id,name}:\n  1,Alice\n  2,Bob");
+//! ````
+
+use crate::lexer::{self, Line, LineKind};
+use crate::{Deserializer, Error, Result, Value};
+use std::ops::Range;
+
+/// Finds and parses the first well-formed TOON document in `text`.
+///
+/// Fenced code blocks tagged `toon` (or untagged) are tried first, in order.
+/// If none parses, unfenced runs of lines that look like TOON (`key: value`
+/// lines with identifier-like keys, or array headers, plus their indented
+/// continuation lines) are tried. Returns the parsed value and the byte range
+/// of the document within `text`.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::extract_from_text;
+///
+/// let reply = "The result is below.\n\nstatus: ok\ncount: 3\n\nLet me know!";
+/// let (value, range) = extract_from_text(reply).unwrap();
+///
+/// assert_eq!(&reply[range], "status: ok\ncount: 3");
+/// assert_eq!(value.as_object().map(|obj| obj.len()), Some(2));
+/// ```
+///
+/// # Errors
+///
+/// Returns the parse error of the first `toon`-tagged block if no document could
+/// be parsed, or [`Error::InvalidFormat`] if the text contains no TOON candidate.
+pub fn extract_from_text(text: &str) -> Result<(Value, Range<usize>)> {
+    let lines: Vec<Line<'_>> = lexer::lines(text).collect();
+    let mut in_fence = vec![false; lines.len()];
+    let mut first_error = None;
+
+    // Fenced blocks
+    let mut i = 0;
+    while i < lines.len() {
+        let Some(lang) = lines[i].text.strip_prefix("```") else {
+            i += 1;
+            continue;
+        };
+        let close = (i + 1..lines.len())
+            .find(|&j| lines[j].text.starts_with("```"))
+            .unwrap_or(lines.len());
+        in_fence[i..close.min(lines.len() - 1) + 1].fill(true);
+
+        let lang = lang.trim();
+        if close > i + 1 && (lang.is_empty() || lang.eq_ignore_ascii_case("toon")) {
+            let range = line_start(&lines[i + 1])..lines[close - 1].span.end;
+            match parse_document(&text[range.clone()]) {
+                Ok(value) => return Ok((value, range)),
+                Err(err) if !lang.is_empty() => {
+                    first_error.get_or_insert(err);
+                }
+                Err(_) => {}
+            }
+        }
+        i = close + 1;
+    }
+
+    // Unfenced runs of TOON-looking lines
+    for start in 0..lines.len() {
+        if in_fence[start] || lines[start].indent > 0 || !starts_document(&lines[start]) {
+            continue;
+        }
+        let mut end = start;
+        while end + 1 < lines.len()
+            && !in_fence[end + 1]
+            && lines[end + 1].number == lines[end].number + 1
+            && (lines[end + 1].indent > 0 || starts_document(&lines[end + 1]))
+        {
+            end += 1;
+        }
+
+        let range = line_start(&lines[start])..lines[end].span.end;
+        if let Ok(value) = parse_document(&text[range.clone()]) {
+            return Ok((value, range));
+        }
+    }
+
+    Err(first_error
+        .unwrap_or_else(|| Error::invalid_format(1, 1, "no TOON document found in text")))
+}
+
+/// Byte offset of the start of `line`, including its indentation.
+fn line_start(line: &Line<'_>) -> usize {
+    line.span.start - line.indent
+}
+
+/// Returns `true` if `line` can begin (or continue at the top level) a TOON document.
+fn starts_document(line: &Line<'_>) -> bool {
+    match line.kind() {
+        LineKind::Header { .. } => true,
+        LineKind::Field { key, .. } => {
+            line.text.starts_with('"')
+                || key
+                    .chars()
+                    .all(|ch| ch.is_alphanumeric() || ch == '_' || ch == '-' || ch == '.')
+        }
+        _ => false,
+    }
+}
+
+fn parse_document(input: &str) -> Result<Value> {
+    Deserializer::from_str(input).parse_root()
+}
@@ -0,0 +1,275 @@
+//! Reading and writing TOON files, such as service configuration.
+//!
+//! [`from_file`] and [`to_file`] wrap the string functions with the file
+//! handling every project would otherwise write itself. Their errors are
+//! [`Error::File`], which names the file and, where it can be worked out, the
+//! line at fault, including for errors such as a missing field that only know
+//! the path of a value:
+//!
+//! ```text
+//! config/service.toon:3: invalid type: string "eighty", expected u16 at server.port
+//! ```
+//!
+//! [`to_file_atomic`] writes through a temporary file, so readers never see a
+//! half-written file.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//! use serde_toon::{from_file, to_file, ToonOptions};
+//!
+//! #[derive(Serialize, Deserialize, PartialEq, Debug)]
+//! struct Config { name: String, port: u16 }
+//!
+//! let path = std::env::temp_dir().join("serde_toon_file_example.toon");
+//! let config = Config { name: "api".into(), port: 8080 };
+//! to_file(&path, &config, ToonOptions::new()).unwrap();
+//! assert_eq!(from_file::<Config, _>(&path).unwrap(), config);
+//! # std::fs::remove_file(&path).unwrap();
+//! ```
+
+use crate::de::Deserializer;
+use crate::path::Path as ValuePath;
+use crate::{from_slice_with_options, to_string_with_options, Error, Result, ToonOptions};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Reads a `T` from the TOON file at `path`.
+///
+/// # Errors
+///
+/// Returns [`Error::File`] if the file cannot be read, is not valid TOON, or
+/// cannot be deserialized to `T`.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde::Deserialize;
+/// use serde_toon::{from_file, Error};
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Server { host: String, port: u16 }
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Config { name: String, server: Server }
+///
+/// let path = std::env::temp_dir().join("serde_toon_from_file_example.toon");
+/// std::fs::write(&path, "name: api\nserver:\n  host: localhost\n  port: eighty").unwrap();
+///
+/// let err = from_file::<Config, _>(&path).unwrap_err();
+/// assert!(matches!(err, Error::File { line: Some(4), .. }));
+/// assert!(err.to_string().ends_with(
+///     "serde_toon_from_file_example.toon:4: invalid type: string \"eighty\", expected u16 at server.port"
+/// ));
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+#[must_use = "this returns the result of the operation, errors must be handled"]
+pub fn from_file<T, P>(path: P) -> Result<T>
+where
+    T: DeserializeOwned,
+    P: AsRef<Path>,
+{
+    from_file_with_options(path, ToonOptions::default())
+}
+
+/// Reads a `T` from the TOON file at `path` with custom options.
+///
+/// # Errors
+///
+/// Returns [`Error::File`] if the file cannot be read, is not valid TOON, or
+/// cannot be deserialized to `T`.
+#[must_use = "this returns the result of the operation, errors must be handled"]
+pub fn from_file_with_options<T, P>(path: P, options: ToonOptions) -> Result<T>
+where
+    T: DeserializeOwned,
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|e| file_error(path, None, Error::io(&e.to_string())))?;
+    from_slice_with_options(&bytes, options.clone()).map_err(|error| {
+        let line = std::str::from_utf8(&bytes)
+            .ok()
+            .and_then(|text| error_line(text, &options, &error));
+        file_error(path, line, error)
+    })
+}
+
+/// Writes `value` as TOON to the file at `path`, replacing the file if it
+/// exists.
+///
+/// # Errors
+///
+/// Returns [`Error::File`] if serialization fails or the file cannot be
+/// written.
+#[must_use = "this returns the result of the operation, errors must be handled"]
+pub fn to_file<T, P>(path: P, value: &T, options: ToonOptions) -> Result<()>
+where
+    T: ?Sized + Serialize,
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let text = to_string_with_options(value, options).map_err(|e| file_error(path, None, e))?;
+    fs::write(path, text).map_err(|e| file_error(path, None, Error::io(&e.to_string())))
+}
+
+/// Writes `value` as TOON to the file at `path` atomically.
+///
+/// The output is written and flushed to a temporary file next to `path`,
+/// which is then renamed over it, so the file holds either its old contents
+/// or the new ones, never a mix. If anything fails, the temporary file is
+/// removed and `path` is left untouched.
+///
+/// # Errors
+///
+/// Returns [`Error::File`] if serialization fails or the file cannot be
+/// written.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{from_file, to_file_atomic, toon, ToonOptions, Value};
+///
+/// let path = std::env::temp_dir().join("serde_toon_atomic_example.toon");
+/// let state = toon!({"version": 2, "leader": "node-1"});
+/// to_file_atomic(&path, &state, ToonOptions::new()).unwrap();
+/// assert_eq!(from_file::<Value, _>(&path).unwrap(), state);
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+#[must_use = "this returns the result of the operation, errors must be handled"]
+pub fn to_file_atomic<T, P>(path: P, value: &T, options: ToonOptions) -> Result<()>
+where
+    T: ?Sized + Serialize,
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let text = to_string_with_options(value, options).map_err(|e| file_error(path, None, e))?;
+
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(path.file_name().unwrap_or_default());
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    let temp = path.with_file_name(temp_name);
+
+    let written = fs::File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(text.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&temp, path));
+    written.map_err(|e| {
+        let _ = fs::remove_file(&temp);
+        file_error(path, None, Error::io(&e.to_string()))
+    })
+}
+
+fn file_error(path: &Path, line: Option<usize>, error: Error) -> Error {
+    Error::File {
+        path: path.to_path_buf(),
+        line,
+        error: Box::new(error),
+    }
+}
+
+/// Returns the line of `text` that `error` is about. Errors that only know
+/// the path of a value are located by parsing `text` again with spans, at the
+/// closest enclosing value that has one.
+fn error_line(text: &str, options: &ToonOptions, error: &Error) -> Option<usize> {
+    let path = match error {
+        Error::Syntax { line, .. }
+        | Error::TypeMismatch { line, .. }
+        | Error::IndentationError { line, .. }
+        | Error::InvalidFormat { line, .. }
+        | Error::UnexpectedEof { line, .. }
+        | Error::DuplicateKey { line, .. }
+        | Error::TrailingContent { line, .. }
+        | Error::LimitExceeded { line, .. } => return Some(*line),
+        Error::UnknownField { path, .. }
+        | Error::MissingField { path, .. }
+        | Error::MissingColumn { path, .. }
+        | Error::Data { path, .. } => path,
+        _ => return None,
+    };
+
+    let mut deserializer = Deserializer::with_options(text, options.clone()).with_spans();
+    deserializer.parse_root().ok()?;
+    let spans = deserializer.into_spans()?;
+    let mut path: ValuePath = path.clone();
+    while !path.is_root() {
+        if let Some(span) = spans.get(&path) {
+            return Some(span.line);
+        }
+        path.pop();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Limits {
+        rps: u32,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Service {
+        name: String,
+        limits: Limits,
+        hosts: Vec<String>,
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("serde_toon_{}_{}.toon", name, std::process::id()))
+    }
+
+    #[test]
+    fn round_trips_and_locates_errors() {
+        let path = temp_path("service");
+        let service = Service {
+            name: "api".to_string(),
+            limits: Limits { rps: 100 },
+            hosts: vec!["a".to_string(), "b".to_string()],
+        };
+        to_file_atomic(&path, &service, ToonOptions::new()).unwrap();
+        assert_eq!(from_file::<Service, _>(&path).unwrap(), service);
+
+        let line_of = |text: &str| {
+            fs::write(&path, text).unwrap();
+            match from_file::<Service, _>(&path).unwrap_err() {
+                Error::File { path: p, line, .. } => {
+                    assert_eq!(p, path);
+                    line
+                }
+                other => panic!("expected a file error, got {:?}", other),
+            }
+        };
+        // Missing field, wrong type, syntax error and unparsable input
+        assert_eq!(
+            line_of("name: api\nlimits:\n  burst: 5\nhosts[0]:"),
+            Some(3)
+        );
+        assert_eq!(
+            line_of("name: api\nlimits:\n  rps: 1\nhosts: [1]: a\nhosts: x"),
+            Some(5)
+        );
+        assert_eq!(
+            line_of("name: api\nlimits:\n  rps: \"fast\"\nhosts: [0]:"),
+            Some(3)
+        );
+        assert_eq!(line_of("name: api\nhosts: [2]: a"), Some(2));
+        assert_eq!(line_of("limits:\n  rps: 1\nhosts: [0]:"), None);
+        fs::remove_file(&path).unwrap();
+
+        let err = from_file::<Service, _>(&path).unwrap_err();
+        assert!(
+            matches!(&err, Error::File { line: None, error, .. } if matches!(**error, Error::Io(_)))
+        );
+        let missing_dir = path.join("nested.toon");
+        assert!(to_file_atomic(&missing_dir, &service, ToonOptions::new()).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+//! `arbitrary::Arbitrary` for [`Value`] and [`ToonMap`] (requires the
+//! `arbitrary` feature), for fuzz targets and other property tests driven by
+//! raw bytes.
+//!
+//! Like the proptest strategies in `strategy`, generated values cover every
+//! shape TOON writes, with strings drawn mostly from characters that mean
+//! something in TOON to exercise quoting, and only finite numbers.
+
+use crate::{Number, Table, ToonMap, Value};
+use arbitrary::{Arbitrary, Result, Unstructured};
+#[cfg(feature = "datetime")]
+use chrono::{TimeZone, Utc};
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
+
+/// Deepest nesting of arrays, objects and tables.
+const MAX_DEPTH: usize = 4;
+
+/// Most items in an array, fields in an object or rows in a table.
+const MAX_LEN: usize = 4;
+
+/// Generates values of every shape, nested up to four levels deep.
+///
+/// # Examples
+///
+/// ```rust
+/// use arbitrary::{Arbitrary, Unstructured};
+/// use serde_toon::{from_str, to_string, Value};
+///
+/// let mut u = Unstructured::new(b"some fuzzer input bytes");
+/// let value = Value::arbitrary(&mut u).unwrap();
+/// let toon = to_string(&value).unwrap();
+/// let back: Value = from_str(&toon).unwrap();
+/// assert_eq!(to_string(&back).unwrap(), toon);
+/// ```
+impl<'a> Arbitrary<'a> for Value {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        value(u, 0)
+    }
+}
+
+/// Generates objects of up to four fields, with values nested up to three
+/// levels deep.
+impl<'a> Arbitrary<'a> for ToonMap {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        object(u, 1)
+    }
+}
+
+fn value(u: &mut Unstructured<'_>, depth: usize) -> Result<Value> {
+    if depth >= MAX_DEPTH || u.ratio(1, 2)? {
+        return scalar(u);
+    }
+    Ok(match u.int_in_range(0..=2)? {
+        0 => Value::Array(
+            (0..u.int_in_range(0..=MAX_LEN)?)
+                .map(|_| value(u, depth + 1))
+                .collect::<Result<_>>()?,
+        ),
+        1 => Value::Object(object(u, depth + 1)?),
+        _ => table(u)?,
+    })
+}
+
+fn object(u: &mut Unstructured<'_>, depth: usize) -> Result<ToonMap> {
+    let mut map = ToonMap::new();
+    for _ in 0..u.int_in_range(0..=MAX_LEN)? {
+        map.insert(string(u)?, value(u, depth)?);
+    }
+    Ok(map)
+}
+
+/// A table of scalar cells under distinct headers.
+fn table(u: &mut Unstructured<'_>) -> Result<Value> {
+    let mut headers: Vec<String> = Vec::new();
+    for _ in 0..u.int_in_range(1..=MAX_LEN)? {
+        let header = string(u)?;
+        if !headers.contains(&header) {
+            headers.push(header);
+        }
+    }
+    let rows = (0..u.int_in_range(0..=MAX_LEN)?)
+        .map(|_| headers.iter().map(|_| scalar(u)).collect())
+        .collect::<Result<_>>()?;
+    Table::from_parts(headers, rows)
+        .map(Value::Table)
+        .map_err(|_| arbitrary::Error::IncorrectFormat)
+}
+
+/// Null, booleans, finite numbers, strings, and dates and big integers when
+/// the `datetime` and `bigint` features are enabled.
+fn scalar(u: &mut Unstructured<'_>) -> Result<Value> {
+    Ok(match u.int_in_range(0..=7)? {
+        0 => Value::Null,
+        1 => Value::Bool(bool::arbitrary(u)?),
+        2 => Value::Number(Number::Integer(i64::arbitrary(u)?)),
+        3 => {
+            let f = f64::arbitrary(u)?;
+            Value::Number(Number::Float(if f.is_finite() { f } else { 0.0 }))
+        }
+        #[cfg(feature = "datetime")]
+        4 => {
+            let secs = u.int_in_range(0..=4_102_444_799)?;
+            Value::Date(Utc.timestamp_opt(secs, 0).single().unwrap_or_default())
+        }
+        #[cfg(feature = "bigint")]
+        5 => Value::BigInt(BigInt::from(i128::arbitrary(u)?)),
+        _ => Value::String(string(u)?),
+    })
+}
+
+/// Strings that are often hard to write unquoted: built mostly from pieces
+/// with meaning in TOON, with the odd arbitrary character.
+fn string(u: &mut Unstructured<'_>) -> Result<String> {
+    const PIECES: &[&str] = &[
+        "a", "Z", " ", ":", ",", "|", "-", "- ", "#", "\"", "\\", "\n", "\r", "\t", "[", "]", "{",
+        "}", "0", "1", ".", "e", "é", "true", "false", "null",
+    ];
+    let mut s = String::new();
+    for _ in 0..u.int_in_range(0..=6)? {
+        if u.ratio(1, 8)? {
+            s.push(char::arbitrary(u)?);
+        } else {
+            s.push_str(u.choose(PIECES)?);
+        }
+    }
+    Ok(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_str, to_string};
+
+    #[test]
+    fn written_values_read_back_stable() {
+        let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+        for _ in 0..256 {
+            let bytes: Vec<u8> = (0..256)
+                .map(|_| {
+                    seed ^= seed << 13;
+                    seed ^= seed >> 7;
+                    seed ^= seed << 17;
+                    seed as u8
+                })
+                .collect();
+            let value = Value::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+            let toon = to_string(&value).unwrap();
+            let back: Value = from_str(&toon).unwrap();
+            assert_eq!(to_string(&back).unwrap(), toon);
+        }
+        let map = ToonMap::arbitrary(&mut Unstructured::new(&[7; 64])).unwrap();
+        assert!(map.len() <= MAX_LEN);
+    }
+}
@@ -0,0 +1,608 @@
+//! Parsing a document while it is still being written.
+//!
+//! A model streams its answer a few characters at a time. An
+//! [`IncrementalParser`] takes those chunks as they arrive with
+//! [`feed`](IncrementalParser::feed), and returns an [`Update`] for each part
+//! of the document as soon as it is complete:
+//!
+//! - each row of a table, and each item of a list array, once its line (or
+//!   the next item) has arrived
+//! - each field of the root object, once its line has arrived if it fits on
+//!   one, and otherwise once the next field starts
+//!
+//! [`finish`](IncrementalParser::finish) ends the document and returns what
+//! its last lines completed. The rows of a table field arrive one by one and
+//! then the whole table as a field, so a UI can show rows as they stream in
+//! and replace them with the field when it is done.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde_toon::incremental::{IncrementalParser, Update};
+//! use serde_toon::toon;
+//!
+//! let mut parser = IncrementalParser::new();
+//! assert_eq!(parser.feed("status: o").unwrap(), []);
+//! assert_eq!(
+//!     parser.feed("k\nus").unwrap(),
+//!     [Update::Field { key: "status".into(), value: toon!("ok") }]
+//! );
+//! assert_eq!(
+//!     parser.feed("ers[2]{id,name}:\n  1,Ada\n  2,Gr").unwrap(),
+//!     [Update::Row { key: Some("users".into()), index: 0, value: toon!({"id": 1, "name": "Ada"}) }]
+//! );
+//! assert_eq!(parser.feed("ace").unwrap(), []);
+//! let updates = parser.finish().unwrap();
+//! assert_eq!(
+//!     updates[0],
+//!     Update::Row { key: Some("users".into()), index: 1, value: toon!({"id": 2, "name": "Grace"}) }
+//! );
+//! assert!(matches!(&updates[1], Update::Field { key, .. } if key == "users"));
+//! ```
+//!
+//! An [`IncrementalDeserializer`] does the same for one array of the
+//! document, returning each of its items as a Rust value once it is
+//! complete, so that work on the first rows can start before the last ones
+//! have been written.
+
+use crate::de::from_value_with_options;
+use crate::lexer::{classify, Header, LineKind};
+use crate::path::PathSegment;
+use crate::ser::{key_text, write_table_header};
+use crate::{from_str_with_options, Result, ToonOptions, Value};
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+/// A part of a document completed by the text fed to an
+/// [`IncrementalParser`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Update {
+    /// A field of the root object, with its whole value.
+    Field { key: String, value: Value },
+    /// The row of a table, or item of a list array, at `index`. `key` names
+    /// the field of the root object that holds the array, and is `None` if
+    /// the array is the root.
+    Row {
+        key: Option<String>,
+        index: usize,
+        value: Value,
+    },
+}
+
+/// A push parser for TOON text that arrives in chunks. See the
+/// [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct IncrementalParser {
+    options: ToonOptions,
+    /// Everything fed so far.
+    text: String,
+    /// The end of the last complete line in `text`.
+    scanned: usize,
+    /// The root-level field or array being read.
+    open: Option<Open>,
+}
+
+/// A root-level field, or root array, whose lines are still arriving.
+#[derive(Clone, Debug)]
+struct Open {
+    key: Option<String>,
+    /// Byte offset of its first line in the parser's text.
+    start: usize,
+    kind: OpenKind,
+    /// The number of rows or items returned so far.
+    rows: usize,
+}
+
+#[derive(Clone, Debug)]
+enum OpenKind {
+    /// A table, with a header for one of its rows.
+    Table { header: String },
+    /// A list array, with the offset and indentation of its last item.
+    List { item: Option<(usize, usize)> },
+    /// A nested object, block string, or anything else on several lines.
+    Block,
+}
+
+impl IncrementalParser {
+    /// Creates a parser with the default options.
+    #[must_use]
+    pub fn new() -> Self {
+        IncrementalParser::with_options(ToonOptions::default())
+    }
+
+    /// Creates a parser that reads the document with `options`.
+    #[must_use]
+    pub fn with_options(options: ToonOptions) -> Self {
+        IncrementalParser {
+            options,
+            text: String::new(),
+            scanned: 0,
+            open: None,
+        }
+    }
+
+    /// Returns the text fed so far.
+    #[must_use]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Adds the next chunk of the document, returning the rows, items and
+    /// fields its complete lines finish.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, located in the whole document, if a completed part
+    /// is not valid TOON.
+    pub fn feed(&mut self, chunk: &str) -> Result<Vec<Update>> {
+        self.text.push_str(chunk);
+        let mut updates = Vec::new();
+        while let Some(newline) = self.text[self.scanned..].find('\n') {
+            let end = self.scanned + newline + 1;
+            self.line(self.scanned, end, &mut updates)?;
+            self.scanned = end;
+        }
+        Ok(updates)
+    }
+
+    /// Ends the document, returning the rows, items and fields its last
+    /// lines finish.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if they are not valid TOON.
+    pub fn finish(mut self) -> Result<Vec<Update>> {
+        let mut updates = Vec::new();
+        let end = self.text.len();
+        if self.scanned < end {
+            self.line(self.scanned, end, &mut updates)?;
+            self.scanned = end;
+        }
+        self.close(end, &mut updates)?;
+        Ok(updates)
+    }
+
+    /// Reads the line at `start..end` of the text.
+    fn line(&mut self, start: usize, end: usize, updates: &mut Vec<Update>) -> Result<()> {
+        let line = self.text[start..end].trim_end_matches(['\n', '\r']);
+        let content = line.trim_start();
+        if content.is_empty() {
+            return Ok(());
+        }
+        let indent = line.len() - content.len();
+        if indent > 0 {
+            return self.indented(start, end, indent, updates);
+        }
+
+        let (key, header, value) = match classify(content) {
+            LineKind::Field { key, header, value } => (Some(key), header, value),
+            LineKind::Header { header, value } => (None, Some(header), value),
+            _ => (None, None, ""),
+        };
+        let complete = !value.is_empty() && value != "|";
+        let kind = match header {
+            Some(header) if !complete && !header.fields.is_empty() => OpenKind::Table {
+                header: row_header(&header),
+            },
+            Some(_) if !complete => OpenKind::List { item: None },
+            _ => OpenKind::Block,
+        };
+        self.close(start, updates)?;
+        self.open = Some(Open {
+            key,
+            start,
+            kind,
+            rows: 0,
+        });
+        if complete {
+            self.close(end, updates)?;
+        }
+        Ok(())
+    }
+
+    /// Reads an indented line, which belongs to the open field.
+    fn indented(
+        &mut self,
+        start: usize,
+        end: usize,
+        indent: usize,
+        updates: &mut Vec<Update>,
+    ) -> Result<()> {
+        let Some(open) = &mut self.open else {
+            return Ok(());
+        };
+        match &mut open.kind {
+            OpenKind::Table { header } => {
+                let row = format!("{}\n{}", header, &self.text[start..end]);
+                let value = self.first_item(&row, end)?;
+                self.push_row(value, updates);
+            }
+            OpenKind::List { item } => {
+                let content = self.text[start..end].trim();
+                let at_items = item.map_or(true, |(_, item_indent)| item_indent == indent);
+                if at_items && matches!(classify(content), LineKind::Item { .. }) {
+                    if let Some((item_start, _)) = item.replace((start, indent)) {
+                        self.close_item(item_start, start, updates)?;
+                    }
+                }
+            }
+            OpenKind::Block => {}
+        }
+        Ok(())
+    }
+
+    /// Ends the open field or root array at `end`, returning its last item
+    /// and the field.
+    fn close(&mut self, end: usize, updates: &mut Vec<Update>) -> Result<()> {
+        if let Some(Open {
+            kind: OpenKind::List {
+                item: Some((item_start, _)),
+            },
+            ..
+        }) = self.open
+        {
+            self.close_item(item_start, end, updates)?;
+        }
+        let Some(open) = self.open.take() else {
+            return Ok(());
+        };
+
+        let value = self.parse(&self.text[open.start..end], end)?;
+        match (open.key, value) {
+            (Some(_), Value::Object(fields)) => {
+                updates.extend(fields.into_iter().map(|(key, value)| Update::Field {
+                    key: key.into(),
+                    value,
+                }));
+            }
+            // A root array on one line arrives whole
+            (None, value) if open.rows == 0 && matches!(open.kind, OpenKind::Block) => {
+                let items = match value {
+                    Value::Array(items) => items,
+                    Value::Table(table) => table.iter_rows().map(Value::Object).collect(),
+                    _ => Vec::new(),
+                };
+                for (index, value) in items.into_iter().enumerate() {
+                    updates.push(Update::Row {
+                        key: None,
+                        index,
+                        value,
+                    });
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Returns the list item at `start..end` of the text.
+    fn close_item(&mut self, start: usize, end: usize, updates: &mut Vec<Update>) -> Result<()> {
+        let item = format!("[1]:\n{}", &self.text[start..end]);
+        let value = self.first_item(&item, end)?;
+        self.push_row(value, updates);
+        Ok(())
+    }
+
+    /// Returns the next row or item of the open array.
+    fn push_row(&mut self, value: Value, updates: &mut Vec<Update>) {
+        let Some(open) = &mut self.open else {
+            return;
+        };
+        updates.push(Update::Row {
+            key: open.key.clone(),
+            index: open.rows,
+            value,
+        });
+        open.rows += 1;
+    }
+
+    /// Parses a one-item array, for a row or item that ends at `end`.
+    fn first_item(&self, text: &str, end: usize) -> Result<Value> {
+        match self.parse(text, end)? {
+            Value::Array(items) => Ok(items.into_iter().next().unwrap_or(Value::Null)),
+            Value::Table(table) => Ok(table.iter_rows().next().map_or(Value::Null, Value::Object)),
+            value => Ok(value),
+        }
+    }
+
+    /// Parses part of the document, which ends at `end`. Errors are located
+    /// by parsing the document up to there, and the error of the part is
+    /// returned only if that succeeds.
+    fn parse(&self, text: &str, end: usize) -> Result<Value> {
+        from_str_with_options(text, self.options.clone()).map_err(|error| {
+            match from_str_with_options::<Value>(&self.text[..end], self.options.clone()) {
+                Err(located) => located,
+                Ok(_) => error,
+            }
+        })
+    }
+}
+
+impl Default for IncrementalParser {
+    fn default() -> Self {
+        IncrementalParser::new()
+    }
+}
+
+/// Writes the header of a one-row table with the fields and delimiter of
+/// `header`.
+fn row_header(header: &Header) -> String {
+    let options = ToonOptions::new().with_delimiter(header.delimiter.clone());
+    let fields: Vec<_> = header
+        .fields
+        .iter()
+        .map(|field| key_text(field, &options))
+        .collect();
+    let mut text = String::new();
+    write_table_header(&mut text, 1, &fields, &options);
+    text
+}
+
+/// Reads the items of one array of a document streamed in chunks, as Rust
+/// values.
+///
+/// The array is the root of the document, or with
+/// [`in_field`](IncrementalDeserializer::in_field), a field of the root
+/// object. Each item is returned by the [`feed`](IncrementalDeserializer::feed)
+/// or [`finish`](IncrementalDeserializer::finish) call that completes it, as
+/// [`IncrementalParser`] finds it; the rest of the document is checked but
+/// not returned.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde::Deserialize;
+/// use serde_toon::incremental::IncrementalDeserializer;
+///
+/// #[derive(Deserialize, Debug, PartialEq)]
+/// struct Ticket { id: u32, title: String }
+///
+/// let mut tickets = IncrementalDeserializer::<Ticket>::new().in_field("tickets");
+/// let mut seen = Vec::new();
+/// for chunk in ["summary: 2 open\ntick", "ets[2]{id,title}:\n  1,Crash\n", "  2,Typo"] {
+///     seen.extend(tickets.feed(chunk).unwrap());
+///     if seen.len() == 1 {
+///         assert_eq!(seen[0], Ticket { id: 1, title: "Crash".into() });
+///     }
+/// }
+/// seen.extend(tickets.finish().unwrap());
+/// assert_eq!(seen[1], Ticket { id: 2, title: "Typo".into() });
+/// ```
+#[derive(Debug)]
+pub struct IncrementalDeserializer<T> {
+    parser: IncrementalParser,
+    options: Rc<ToonOptions>,
+    key: Option<String>,
+    /// The number of items returned so far.
+    items: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> IncrementalDeserializer<T> {
+    /// Creates a deserializer for the items of a root array, with the
+    /// default options.
+    #[must_use]
+    pub fn new() -> Self {
+        IncrementalDeserializer::with_options(ToonOptions::default())
+    }
+
+    /// Creates a deserializer for the items of a root array that reads the
+    /// document with `options`.
+    #[must_use]
+    pub fn with_options(options: ToonOptions) -> Self {
+        IncrementalDeserializer {
+            parser: IncrementalParser::with_options(options.clone()),
+            options: Rc::new(options),
+            key: None,
+            items: 0,
+            marker: PhantomData,
+        }
+    }
+
+    /// Reads the items of the array in the root object's field `key`
+    /// instead.
+    #[must_use]
+    pub fn in_field(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    /// Adds the next chunk of the document, returning the items it completes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a completed part of the document is not valid
+    /// TOON, or an item cannot be deserialized to `T`.
+    pub fn feed(&mut self, chunk: &str) -> Result<Vec<T>> {
+        let updates = self.parser.feed(chunk)?;
+        self.items(updates)
+    }
+
+    /// Ends the document, returning the items its last lines complete.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the end of the document is not valid TOON, or an
+    /// item cannot be deserialized to `T`.
+    pub fn finish(mut self) -> Result<Vec<T>> {
+        let parser = std::mem::take(&mut self.parser);
+        let updates = parser.finish()?;
+        self.items(updates)
+    }
+
+    fn items(&mut self, updates: Vec<Update>) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        for update in updates {
+            match update {
+                Update::Row { key, value, .. } if key == self.key => {
+                    items.push(self.item(value)?);
+                }
+                // An inline array arrives as a field only
+                Update::Field { key, value } if self.key.as_ref() == Some(&key) => {
+                    let values = match value {
+                        Value::Array(values) => values,
+                        Value::Table(table) => table.iter_rows().map(Value::Object).collect(),
+                        _ => Vec::new(),
+                    };
+                    for value in values.into_iter().skip(self.items) {
+                        items.push(self.item(value)?);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(items)
+    }
+
+    /// Deserializes the next item.
+    fn item(&mut self, value: Value) -> Result<T> {
+        let index = self.items;
+        self.items += 1;
+        from_value_with_options(value, Rc::clone(&self.options)).map_err(|error| {
+            let error = error.within(PathSegment::Index(index));
+            match &self.key {
+                Some(key) => error.within(PathSegment::Key(key.clone())),
+                None => error,
+            }
+        })
+    }
+}
+
+impl<T: DeserializeOwned> Default for IncrementalDeserializer<T> {
+    fn default() -> Self {
+        IncrementalDeserializer::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_str, toon};
+
+    /// Feeds `text` one character at a time, returning all updates.
+    fn stream(text: &str) -> Result<Vec<Update>> {
+        let mut parser = IncrementalParser::new();
+        let mut updates = Vec::new();
+        for ch in text.chars() {
+            updates.extend(parser.feed(&ch.to_string())?);
+        }
+        updates.extend(parser.finish()?);
+        Ok(updates)
+    }
+
+    fn row(key: Option<&str>, index: usize, value: Value) -> Update {
+        Update::Row {
+            key: key.map(str::to_string),
+            index,
+            value,
+        }
+    }
+
+    fn field(key: &str, value: Value) -> Update {
+        Update::Field {
+            key: key.to_string(),
+            value,
+        }
+    }
+
+    #[test]
+    fn streams_fields_rows_and_items() {
+        let text = "title: Report\nrows[2|]{id|\"full name\"}:\n  1|Ada L\n  2|\"B|C\"\n\
+                    meta:\n  note: |\n    line one\n\n    line two\n  tags: [2]: a,b\n\
+                    items[2]:\n  - id: 1\n    sub[1]{x}:\n      9\n  - plain\ncount: 3\n";
+        let document: Value = from_str(text).unwrap();
+        let updates = stream(text).unwrap();
+        assert_eq!(
+            updates,
+            [
+                field("title", toon!("Report")),
+                row(Some("rows"), 0, toon!({"id": 1, "full name": "Ada L"})),
+                row(Some("rows"), 1, toon!({"id": 2, "full name": "B|C"})),
+                field(
+                    "rows",
+                    document.as_object().unwrap().get("rows").unwrap().clone()
+                ),
+                field(
+                    "meta",
+                    toon!({"note": "line one\n\nline two", "tags": ["a", "b"]})
+                ),
+                row(Some("items"), 0, toon!({"id": 1, "sub": [{"x": 9}]})),
+                row(Some("items"), 1, toon!("plain")),
+                field("items", toon!([{"id": 1, "sub": [{"x": 9}]}, "plain"])),
+                field("count", toon!(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn streams_root_arrays() {
+        assert_eq!(
+            stream("[2]{a}:\r\n  1\r\n  2").unwrap(),
+            [row(None, 0, toon!({"a": 1})), row(None, 1, toon!({"a": 2}))]
+        );
+        assert_eq!(
+            stream("[2]:\n  - x\n  - y\n").unwrap(),
+            [row(None, 0, toon!("x")), row(None, 1, toon!("y"))]
+        );
+        assert_eq!(
+            stream("[3]: 1,2,3").unwrap(),
+            [
+                row(None, 0, toon!(1)),
+                row(None, 1, toon!(2)),
+                row(None, 2, toon!(3))
+            ]
+        );
+        assert_eq!(stream("").unwrap(), []);
+    }
+
+    #[test]
+    fn locates_errors_in_the_whole_document() {
+        let mut parser = IncrementalParser::new();
+        assert_eq!(
+            parser.feed("a: 1\nrows[2]{x,y}:\n  1,2\n").unwrap().len(),
+            2
+        );
+        let err = parser.feed("  \"3,4\n").unwrap_err();
+        assert!(err.to_string().contains("line 4"), "{}", err);
+
+        let err = stream("a: 1\nb: [2]: 1").unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{}", err);
+    }
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[test]
+    fn deserializes_items_as_they_complete() {
+        let text = "name: path\npoints[3]:\n  - x: 1\n    y: 2\n  - x: 3\n    y: 4\n  - x: 5\n    y: oops\nlen: 3";
+        let mut points = IncrementalDeserializer::<Point>::new().in_field("points");
+        let mut counts = Vec::new();
+        let mut seen = Vec::new();
+        for line in text.split_inclusive('\n') {
+            let items = points.feed(line).unwrap();
+            counts.push(items.len());
+            seen.extend(items);
+        }
+        assert_eq!(counts, [0, 0, 0, 0, 1, 0, 1, 0, 0]);
+        assert_eq!(seen, [Point { x: 1, y: 2 }, Point { x: 3, y: 4 }]);
+        assert_eq!(
+            points.finish().unwrap_err().to_string(),
+            "invalid type: string \"oops\", expected i32 at points[2].y"
+        );
+
+        let mut tags = IncrementalDeserializer::<String>::new().in_field("tags");
+        assert_eq!(
+            tags.feed("other: [1]: x\ntags: [2]: a,b\n").unwrap(),
+            ["a", "b"]
+        );
+        assert_eq!(tags.finish().unwrap(), Vec::<String>::new());
+
+        let mut numbers = IncrementalDeserializer::<u8>::new();
+        assert_eq!(numbers.feed("[2]:\n  - 1\n  - 2\n").unwrap(), [1]);
+        assert_eq!(numbers.finish().unwrap(), [2]);
+    }
+}
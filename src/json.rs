@@ -0,0 +1,459 @@
+//! Minimal JSON writer for [`Value`], used for TOON/JSON comparisons and
+//! [`to_dual_strings`](crate::to_dual_strings), and with the `json` feature,
+//! conversions to and from `serde_json::Value` and detection of JSON input
+//! for `from_str_any`.
+//!
+//! Without options, output matches `serde_json::to_string` for the
+//! JSON-compatible subset:
+//! tables are written as arrays of objects, dates as RFC 3339 strings, big
+//! integers as bare numbers, non-finite floats as `null` and numbers kept as
+//! written as the number their text parses to.
+
+use crate::lexer;
+#[cfg(feature = "json")]
+use crate::lexer::LineKind;
+use crate::ser;
+#[cfg(feature = "json")]
+use crate::{Error, ToonMap};
+use crate::{Number, Table, ToonOptions, Value};
+#[cfg(feature = "json")]
+use std::ops::Range;
+
+/// Writes `value` as compact JSON.
+pub(crate) fn to_json_string(value: &Value) -> String {
+    let mut output = String::with_capacity(256);
+    write_value(&mut output, value, None);
+    output
+}
+
+/// Writes `value` as compact JSON, making the choices the TOON writer makes
+/// with `options`: keys are renamed by [`ToonOptions::key_aliases`] and
+/// sorted by [`ToonOptions::canonical_key_order`], `null` fields are left out
+/// with [`ToonOptions::skip_none_fields`], and numbers are written as the TOON
+/// writer writes them where that is valid JSON.
+pub(crate) fn to_json_string_with_options(value: &Value, options: &ToonOptions) -> String {
+    let mut output = String::with_capacity(256);
+    write_value(&mut output, value, Some(options));
+    output
+}
+
+fn write_value(output: &mut String, value: &Value, options: Option<&ToonOptions>) {
+    match value {
+        Value::Null => output.push_str("null"),
+        Value::Bool(b) => output.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => match options {
+            Some(options) => write_toon_number(output, n, options),
+            None => write_number(output, n),
+        },
+        Value::String(s) => write_string(output, s),
+        Value::Array(arr) => {
+            output.push('[');
+            for (i, item) in arr.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                write_value(output, item, options);
+            }
+            output.push(']');
+        }
+        Value::Object(obj) => {
+            let mut entries: Vec<(&str, &Value)> = obj
+                .iter()
+                .filter(|(_, item)| {
+                    !(options.is_some_and(|o| o.skip_none_fields) && item.is_null())
+                })
+                .map(|(key, item)| (written_key(key, options), item))
+                .collect();
+            if options.is_some_and(ToonOptions::sorts_keys) {
+                entries.sort_by_key(|(key, _)| *key);
+            }
+            write_object(output, entries, options);
+        }
+        Value::Table(Table { headers, rows }) => {
+            let mut columns: Vec<usize> = (0..headers.len()).collect();
+            if options.is_some_and(ToonOptions::sorts_keys) {
+                columns.sort_by_key(|&i| written_key(&headers[i], options));
+            }
+            output.push('[');
+            for (i, row) in rows.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                let entries = columns
+                    .iter()
+                    .filter_map(|&j| Some((written_key(&headers[j], options), row.get(j)?)))
+                    .collect();
+                write_object(output, entries, options);
+            }
+            output.push(']');
+        }
+        #[cfg(feature = "datetime")]
+        Value::Date(dt) => write_string(output, &dt.to_rfc3339()),
+        #[cfg(feature = "bigint")]
+        Value::BigInt(bi) => output.push_str(&bi.to_string()),
+    }
+}
+
+fn write_object(output: &mut String, entries: Vec<(&str, &Value)>, options: Option<&ToonOptions>) {
+    output.push('{');
+    for (i, (key, item)) in entries.into_iter().enumerate() {
+        if i > 0 {
+            output.push(',');
+        }
+        write_string(output, key);
+        output.push(':');
+        write_value(output, item, options);
+    }
+    output.push('}');
+}
+
+fn written_key<'a>(key: &'a str, options: Option<&'a ToonOptions>) -> &'a str {
+    options.map_or(key, |options| options.written_key(key))
+}
+
+fn write_number(output: &mut String, number: &Number) {
+    match number {
+        Number::Integer(i) => output.push_str(&i.to_string()),
+        Number::Float(f) if f.is_finite() => {
+            let text = f.to_string();
+            output.push_str(&text);
+            if !text.contains(['.', 'e', 'E']) {
+                output.push_str(".0");
+            }
+        }
+        Number::Raw(_) => write_number(output, &number.resolved()),
+        // Every digit, which JSON can hold
+        #[cfg(feature = "rust_decimal")]
+        Number::Decimal(d) => output.push_str(&d.to_string()),
+        _ => output.push_str("null"),
+    }
+}
+
+/// Writes `number` as the TOON writer does, or as [`write_number`] does if
+/// that text is not a JSON number.
+fn write_toon_number(output: &mut String, number: &Number, options: &ToonOptions) {
+    let mut text = String::new();
+    ser::write_number(&mut text, number, &options.float_format);
+    if text == "null" || lexer::is_numeric(&text) {
+        output.push_str(&text);
+    } else {
+        write_number(output, number);
+    }
+}
+
+fn write_string(output: &mut String, s: &str) {
+    output.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            '\u{0008}' => output.push_str("\\b"),
+            '\u{000C}' => output.push_str("\\f"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+/// Numbers become integers where they fit an `i64`, integers above
+/// `i64::MAX` become [`Value::BigInt`] (or floats without the `bigint`
+/// feature), and objects keep the order of the `serde_json` map.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_json::json;
+/// use serde_toon::Value;
+///
+/// let payload = json!({"model": "m1", "messages": [{"role": "user", "text": "hi"}]});
+/// let value = Value::from(payload);
+/// assert_eq!(value.to_string(), "messages: [1]{role,text}:\n  user,hi\nmodel: m1");
+/// ```
+#[cfg(feature = "json")]
+impl From<serde_json::Value> for Value {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(b) => Value::Bool(b),
+            serde_json::Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+                (Some(i), _) => Value::Number(Number::Integer(i)),
+                (None, Some(u)) => Value::from(u),
+                (None, None) => n.as_f64().map_or(Value::Null, Value::from),
+            },
+            serde_json::Value::String(s) => Value::String(s),
+            serde_json::Value::Array(items) => {
+                Value::Array(items.into_iter().map(Value::from).collect())
+            }
+            serde_json::Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| (key, Value::from(value)))
+                    .collect::<ToonMap>(),
+            ),
+        }
+    }
+}
+
+/// Tables become arrays of objects, dates RFC 3339 strings, numbers kept as
+/// written the number their text parses to, and NaN and infinities `null`. Big integers become `u64` numbers where they fit, and
+/// floats otherwise, as `serde_json` parses them.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_json::json;
+/// use serde_toon::{toon_table, Value};
+///
+/// let table = Value::from(toon_table!((id, name); (1, "Ada"); (2, "Bob")));
+/// assert_eq!(
+///     serde_json::Value::from(table),
+///     json!([{"id": 1, "name": "Ada"}, {"id": 2, "name": "Bob"}])
+/// );
+/// ```
+#[cfg(feature = "json")]
+impl From<Value> for serde_json::Value {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => serde_json::Value::Null,
+            Value::Bool(b) => serde_json::Value::Bool(b),
+            Value::Number(Number::Integer(i)) => serde_json::Value::from(i),
+            Value::Number(Number::Float(f)) => serde_json::Number::from_f64(f)
+                .map_or(serde_json::Value::Null, serde_json::Value::Number),
+            Value::Number(number @ Number::Raw(_)) => {
+                serde_json::Value::from(Value::Number(number.resolved().into_owned()))
+            }
+            #[cfg(feature = "rust_decimal")]
+            Value::Number(number @ Number::Decimal(_)) => {
+                serde_json::Value::from(Value::Number(number.resolved().into_owned()))
+            }
+            Value::Number(_) => serde_json::Value::Null,
+            Value::String(s) => serde_json::Value::String(s),
+            Value::Array(items) => {
+                serde_json::Value::Array(items.into_iter().map(serde_json::Value::from).collect())
+            }
+            Value::Object(map) => serde_json::Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| (key.into(), serde_json::Value::from(value)))
+                    .collect(),
+            ),
+            Value::Table(table) => serde_json::Value::Array(
+                Vec::<ToonMap>::from(table)
+                    .into_iter()
+                    .map(|row| serde_json::Value::from(Value::Object(row)))
+                    .collect(),
+            ),
+            #[cfg(feature = "datetime")]
+            Value::Date(dt) => serde_json::Value::String(dt.to_rfc3339()),
+            #[cfg(feature = "bigint")]
+            Value::BigInt(bi) => match u64::try_from(&bi) {
+                Ok(u) => serde_json::Value::from(u),
+                Err(_) => bi
+                    .to_string()
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(serde_json::Number::from_f64)
+                    .map_or(serde_json::Value::Null, serde_json::Value::Number),
+            },
+        }
+    }
+}
+
+/// Returns the byte range of the JSON document in `input`, or `None` if
+/// `input` is not JSON.
+///
+/// `input` is JSON if it starts with `{`, or with `[` on a line that is not a
+/// TOON array header, after leading whitespace and an opening code fence
+/// that is untagged or tagged `json`. Scalars read the same in both formats,
+/// so they are left to the TOON parser.
+#[cfg(feature = "json")]
+pub(crate) fn sniff(input: &str) -> Option<Range<usize>> {
+    let after_space = |at: usize| input.len() - input[at..].trim_start().len();
+    let mut start = after_space(input.len() - input.trim_start_matches('\u{feff}').len());
+    let mut end = input.len();
+    if let Some(fenced) = input[start..].strip_prefix("```") {
+        let (tag, body) = fenced.split_once('\n')?;
+        let tag = tag.trim();
+        if !tag.is_empty() && !tag.eq_ignore_ascii_case("json") {
+            return None;
+        }
+        let body_start = end - body.len();
+        end = body.find("\n```").map_or(end, |close| body_start + close);
+        start = after_space(body_start).min(end);
+    }
+
+    let text = &input[start..end];
+    let first_line = text.split('\n').next().unwrap_or_default().trim_end();
+    let json = text.starts_with('{')
+        || (text.starts_with('[')
+            && !matches!(lexer::classify(first_line), LineKind::Header { .. }));
+    json.then_some(start..end)
+}
+
+/// Converts a `serde_json` error from parsing the JSON document at `start`
+/// in `input`, so that its position is within `input`.
+#[cfg(feature = "json")]
+pub(crate) fn convert_error(err: serde_json::Error, input: &str, start: usize) -> Error {
+    use serde_json::error::Category;
+
+    let text = err.to_string();
+    let msg = text
+        .rsplit_once(" at line ")
+        .map_or(text.as_str(), |(msg, _)| msg);
+    match err.classify() {
+        Category::Syntax | Category::Eof => {
+            let before = &input[..start];
+            let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+            let (line, col) = match err.line() {
+                1 => (
+                    before.matches('\n').count() + 1,
+                    before[line_start..].chars().count() + err.column().max(1),
+                ),
+                line => (before.matches('\n').count() + line, err.column().max(1)),
+            };
+            Error::syntax(line, col, &format!("Invalid JSON: {}", msg))
+        }
+        Category::Data => Error::custom(msg),
+        Category::Io => Error::io(msg),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toon;
+
+    // serde_json sorts object keys, so keys here are already in sorted order
+    #[test]
+    fn test_matches_serde_json() {
+        let value = toon!({
+            "n": 3,
+            "name": "Al \"Bo\"\n",
+            "score": 1.0,
+            "tags": ["a", null, true]
+        });
+        let expected = serde_json::to_string(&serde_json::json!({
+            "n": 3,
+            "name": "Al \"Bo\"\n",
+            "score": 1.0,
+            "tags": ["a", null, true]
+        }))
+        .unwrap();
+        assert_eq!(to_json_string(&value), expected);
+    }
+
+    #[test]
+    fn test_follows_toon_options() {
+        let rows = Table::try_from(toon!([{"b": 1.25, "a": "x"}])).unwrap();
+        let raw = Number::Raw("1.50".to_string());
+        let value = toon!({
+            "zeta": 1.0,
+            "alpha": null,
+            "rows": (Value::Table(rows)),
+            "raw": (Value::Number(raw)),
+        });
+        let options = ToonOptions::new()
+            .with_sort_keys(true)
+            .with_skip_none_fields(true)
+            .with_key_aliases([("zeta", "z")]);
+        assert_eq!(
+            to_json_string_with_options(&value, &options),
+            r#"{"raw":1.50,"rows":[{"a":"x","b":1.25}],"z":1}"#
+        );
+        assert_eq!(
+            to_json_string(&value),
+            r#"{"zeta":1.0,"alpha":null,"rows":[{"b":1.25,"a":"x"}],"raw":1.5}"#
+        );
+    }
+
+    #[cfg(all(feature = "json", feature = "bigint"))]
+    #[test]
+    fn test_serde_json_conversions() {
+        use serde_json::json;
+
+        let payload = json!({
+            "big": u64::MAX,
+            "f": 2.5,
+            "list": [1, "two", null, {"x": false}],
+            "n": -3
+        });
+        let value = Value::from(payload.clone());
+        let fields = value.as_object().unwrap();
+        assert_eq!(fields.get("n"), Some(&Value::from(-3)));
+        assert_eq!(fields.get("big"), Some(&Value::from(u64::MAX)));
+        assert_eq!(serde_json::Value::from(value), payload);
+
+        let payload = json!({"id": 7, "rows": [{"a": 1.5}, {"a": -2}], "tags": ["x"]});
+        let toon = crate::to_string(&Value::from(payload.clone())).unwrap();
+        let back: Value = crate::from_str(&toon).unwrap();
+        assert_eq!(serde_json::Value::from(back), payload);
+
+        let items = vec![
+            Value::from(1.5),
+            Value::Null,
+            Value::Number(Number::NaN),
+            Value::Number(Number::Infinity),
+        ];
+        assert_eq!(
+            serde_json::Value::from(Value::Array(items)),
+            json!([1.5, null, null, null])
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_sniffs_json_input() {
+        fn json(input: &str) -> Option<&str> {
+            sniff(input).map(|range| &input[range])
+        }
+        assert_eq!(json("{\"a\": 1}"), Some("{\"a\": 1}"));
+        assert_eq!(json("\u{feff}\n  [1, 2]\n"), Some("[1, 2]\n"));
+        assert_eq!(json("[]"), Some("[]"));
+        assert_eq!(json("[3]"), Some("[3]"));
+        assert_eq!(
+            json("```json\n[{\"id\": 1}]\n```\nDone."),
+            Some("[{\"id\": 1}]")
+        );
+        assert_eq!(json("```\n{}\n```"), Some("{}"));
+
+        for toon in [
+            "[2]: 1,2",
+            "[]:",
+            "[2]{a}:\n  1\n  2",
+            "a: {x}",
+            "\"text\"",
+            "42",
+            "```toon\n{}\n```",
+        ] {
+            assert_eq!(json(toon), None, "{}", toon);
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json_errors_point_into_input() {
+        let input = "\n```json\n{\"a\": [1,\n  2,]}\n```";
+        let range = sniff(input).unwrap();
+        let err = serde_json::from_str::<serde_json::Value>(&input[range.clone()]).unwrap_err();
+        let err = convert_error(err, input, range.start);
+        assert!(
+            matches!(
+                err,
+                Error::Syntax {
+                    line: 4,
+                    col: 5,
+                    ..
+                }
+            ),
+            "{}",
+            err
+        );
+        assert!(
+            err.to_string().contains("Invalid JSON: trailing comma"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,1316 @@
+//! Lexing of TOON documents.
+//!
+//! TOON is indentation-based, so most structure is visible one line at a time.
+//! This module splits input into [`Line`]s annotated with their byte span,
+//! indentation and a coarse classification ([`LineKind`]), without building
+//! any [`Value`](crate::Value)s. Tooling such as [`crate::outline`] is built on it.
+//!
+//! For parsing, [`Tokens`] turns the lines into spanned [`Token`]s: keys,
+//! array headers, list item dashes and the scalar text after them, with
+//! indentation changes made explicit as [`TokenKind::Indent`] and
+//! [`TokenKind::Dedent`] in the manner of Python's tokenizer. A list item's
+//! content opens a block at the column after its dash, so the fields of an
+//! object item line up inside it.
+
+use crate::options::Delimiter;
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::ops::Range;
+
+/// A single non-blank line of input.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Line<'a> {
+    /// 1-based line number.
+    pub(crate) number: usize,
+    /// Length in bytes of the indentation: leading spaces and tabs.
+    pub(crate) indent: usize,
+    /// Byte range of the line's content (after indentation, without trailing whitespace).
+    pub(crate) span: Range<usize>,
+    /// The line's content.
+    pub(crate) text: &'a str,
+}
+
+impl<'a> Line<'a> {
+    pub(crate) fn kind(&self) -> LineKind<'a> {
+        classify(self.text)
+    }
+}
+
+/// A parsed array header such as `[3]`, `[#2|]` or `[2]{id,name}`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Header {
+    /// Declared length, or `None` if missing.
+    pub(crate) length: Option<usize>,
+    /// Table field names; empty for non-tabular arrays.
+    pub(crate) fields: Vec<String>,
+    pub(crate) delimiter: Delimiter,
+    /// Whether the header names its delimiter (`|` or a tab after the
+    /// length); without a marker the delimiter is a comma.
+    pub(crate) marked: bool,
+}
+
+/// The shape of a line's content.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum LineKind<'a> {
+    /// `key: value` or `key:`, optionally with an array header after the key or colon.
+    Field {
+        key: String,
+        header: Option<Header>,
+        value: &'a str,
+    },
+    /// A list item (`- content`); `offset` is the byte offset of `content` within the line.
+    Item { content: &'a str, offset: usize },
+    /// A root array header without a key, e.g. `[2]{id,name}:`.
+    Header { header: Header, value: &'a str },
+    /// Anything else: table rows and bare values.
+    Other,
+}
+
+/// Iterator over the non-blank lines of a document, created by [`lines`].
+#[derive(Debug, Clone)]
+pub(crate) struct Lines<'a> {
+    input: &'a str,
+    offset: usize,
+    number: usize,
+    // Whether ``` lines opening and closing the document are skipped
+    fences: bool,
+    started: bool,
+}
+
+impl Lines<'_> {
+    /// Skips a Markdown code fence line (```` ``` ```` or ```` ```toon ````)
+    /// before the first line of the document, and an unindented one after its
+    /// last, as an indented one may be the end of a block string.
+    pub(crate) fn with_code_fences(mut self, fences: bool) -> Self {
+        self.fences = fences;
+        self
+    }
+
+    fn is_fence(&self, text: &str, indent: usize) -> bool {
+        let last = || {
+            let rest = self.input.get(self.offset..).unwrap_or("");
+            indent == 0 && rest.trim().is_empty()
+        };
+        self.fences && text.starts_with("```") && (!self.started || last())
+    }
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = Line<'a>;
+
+    fn next(&mut self) -> Option<Line<'a>> {
+        while self.offset <= self.input.len() {
+            let start = self.offset;
+            let rest = &self.input[start..];
+            let raw = rest.find('\n').map_or(rest, |end| &rest[..end]);
+            self.offset += raw.len() + 1;
+            self.number += 1;
+
+            let content = raw.trim_start_matches([' ', '\t']);
+            let indent = raw.len() - content.len();
+            let text = content.trim_end();
+            if !text.is_empty() && !self.is_fence(text, indent) {
+                self.started = true;
+                return Some(Line {
+                    number: self.number,
+                    indent,
+                    span: start + indent..start + indent + text.len(),
+                    text,
+                });
+            }
+        }
+        None
+    }
+}
+
+/// Splits `input` into its non-blank lines, after any byte order mark.
+pub(crate) fn lines(input: &str) -> Lines<'_> {
+    Lines {
+        input,
+        offset: if input.starts_with('\u{feff}') {
+            '\u{feff}'.len_utf8()
+        } else {
+            0
+        },
+        number: 0,
+        fences: false,
+        started: false,
+    }
+}
+
+/// Classifies a line's content (without indentation).
+pub(crate) fn classify(text: &str) -> LineKind<'_> {
+    if let Some(offset) = item_content(text) {
+        return LineKind::Item {
+            content: &text[offset..],
+            offset,
+        };
+    }
+
+    if let Some((header, rest)) = header_line(text) {
+        return LineKind::Header {
+            header,
+            value: rest.trim(),
+        };
+    }
+
+    match key_at(text) {
+        Some(found) => {
+            let key = match read_quoted(found.key) {
+                Ok(quoted) if found.key.starts_with('"') => quoted.value,
+                _ => found.key.to_string(),
+            };
+            let mut value = text[found.after..].trim();
+            let mut header = found.header.map(|(header, _)| header);
+            if header.is_none() {
+                // Repository form `key: [3]{a,b}: ...` carries it after the colon
+                if let Some((parsed, rest)) = header_line(value) {
+                    header = Some(parsed);
+                    value = rest.trim();
+                }
+            }
+            LineKind::Field { key, header, value }
+        }
+        None => LineKind::Other,
+    }
+}
+
+/// Returns the byte offset of a list item's content if `text` starts with a
+/// `- ` dash (or is a lone `-`).
+fn item_content(text: &str) -> Option<usize> {
+    if text == "-" {
+        return Some(1);
+    }
+    let content = text.strip_prefix("- ")?.trim_start();
+    Some(text.len() - content.len())
+}
+
+/// Reads an array header followed by `:` at the start of `text`, returning
+/// the header and the text after the colon.
+fn header_line(text: &str) -> Option<(Header, &str)> {
+    let (header, len) = header_at(text)?;
+    let rest = text[len..].strip_prefix(':')?;
+    Some((header, rest))
+}
+
+/// A key found at the start of a line by [`key_at`].
+struct KeyAt<'a> {
+    /// The key as written, with the quotes and escapes of a quoted key.
+    key: &'a str,
+    /// An array header between the key and the colon (`tags[2]: ...`), with
+    /// its byte range in the line.
+    header: Option<(Header, Range<usize>)>,
+    /// Byte offset just past the colon.
+    after: usize,
+}
+
+/// Reads the key of a `key: value` line.
+///
+/// An unquoted key runs to the first `:` and may not contain quotes,
+/// delimiters or brackets, except for a header just before the colon. A
+/// numeric key must be followed by a space or the end of the line, so that
+/// values such as `12:30` are not read as keys.
+fn key_at(text: &str) -> Option<KeyAt<'_>> {
+    let key_end = if let Some(body) = text.strip_prefix('"') {
+        closing_quote(body)? + 2
+    } else {
+        let name_end = text.find([':', '['])?;
+        let name = text[..name_end].trim_end();
+        if name.is_empty() || name.contains(['"', ',', '|', '\t', ']', '{', '}']) {
+            return None;
+        }
+        name.len()
+    };
+
+    let mut pos = key_end + spaces(&text[key_end..]);
+    let mut header = None;
+    if text[pos..].starts_with('[') {
+        let (parsed, len) = header_at(&text[pos..])?;
+        header = Some((parsed, pos..pos + len));
+        pos += len;
+        pos += spaces(&text[pos..]);
+    }
+    if !text[pos..].starts_with(':') {
+        return None;
+    }
+
+    let after = &text[pos + 1..];
+    let key = &text[..key_end];
+    if !after.is_empty() && !after.starts_with([' ', '\t']) && is_numeric(key) {
+        return None;
+    }
+    Some(KeyAt {
+        key,
+        header,
+        after: pos + 1,
+    })
+}
+
+/// Counts the spaces at the start of `text`.
+fn spaces(text: &str) -> usize {
+    text.len() - text.trim_start_matches(' ').len()
+}
+
+/// Whether `text` reads as a number, like `42`, `-1.5` or `1e5`: JSON's
+/// number grammar, so `+5`, `.5`, `1.`, `NaN`, `inf` and `007` are not.
+pub(crate) fn is_numeric(text: &str) -> bool {
+    let digits = |s: &str| s.bytes().take_while(u8::is_ascii_digit).count();
+    let rest = text.strip_prefix('-').unwrap_or(text);
+    let int = digits(rest);
+    if int == 0 || (int > 1 && rest.starts_with('0')) {
+        return false;
+    }
+    let mut rest = &rest[int..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let len = digits(fraction);
+        if len == 0 {
+            return false;
+        }
+        rest = &fraction[len..];
+    }
+    if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        let len = digits(exponent);
+        if len == 0 {
+            return false;
+        }
+        rest = &exponent[len..];
+    }
+    rest.is_empty()
+}
+
+/// Finds the byte index of the closing quote in a string body (after the opening quote).
+fn closing_quote(body: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, ch) in body.char_indices() {
+        match ch {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some(i),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+/// Reads an array header such as `[3]`, `[#2|]` or `[2]{id,name}` at the
+/// start of `text`, returning it and its length in bytes (without the `:`
+/// that follows it).
+pub(crate) fn header_at(text: &str) -> Option<(Header, usize)> {
+    let inner_end = text.find(']')?;
+    let inner = text.get(1..inner_end)?;
+    if !text.starts_with('[') {
+        return None;
+    }
+    let inner = inner.strip_prefix('#').unwrap_or(inner);
+
+    let digits_end = inner
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(inner.len());
+    let length = match &inner[..digits_end] {
+        "" => None,
+        digits => Some(digits.parse().ok()?),
+    };
+    let delimiter = delimiter_marker(&inner[digits_end..])?;
+    let marked = !inner[digits_end..].is_empty();
+
+    let mut len = inner_end + 1;
+    let mut fields = Vec::new();
+    if let Some(body) = text[len..].strip_prefix('{') {
+        let close = closing_brace(body)?;
+        fields = header_fields(&body[..close], &delimiter)?;
+        len += close + 2;
+    }
+
+    Some((
+        Header {
+            length,
+            fields,
+            delimiter,
+            marked,
+        },
+        len,
+    ))
+}
+
+/// Reads the delimiter marker after an array header's length: nothing for
+/// a comma, `|`, or a tab (written as four spaces by the serializer).
+fn delimiter_marker(marker: &str) -> Option<Delimiter> {
+    match marker {
+        "" => Some(Delimiter::Comma),
+        "|" => Some(Delimiter::Pipe),
+        "\t" | "    " => Some(Delimiter::Tab),
+        _ => None,
+    }
+}
+
+/// Splits the field list of the table header at the start of `text` with
+/// `delimiter` instead of the one the header names.
+pub(crate) fn fields_with(text: &str, delimiter: &Delimiter) -> Option<Vec<String>> {
+    let body = &text[text.find("]{")? + 2..];
+    header_fields(&body[..closing_brace(body)?], delimiter)
+}
+
+/// Finds the `}` closing a table's field list, outside quoted field names.
+fn closing_brace(body: &str) -> Option<usize> {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (i, ch) in body.char_indices() {
+        match ch {
+            '\\' if in_quotes && !escaped => {
+                escaped = true;
+                continue;
+            }
+            '"' if !escaped => in_quotes = !in_quotes,
+            '}' if !in_quotes => return Some(i),
+            _ => {}
+        }
+        escaped = false;
+    }
+    None
+}
+
+/// Splits the field list of a table header, unquoting quoted names.
+///
+/// Tab-delimited headers are written with four spaces between fields, so
+/// either separates them.
+fn header_fields(list: &str, delimiter: &Delimiter) -> Option<Vec<String>> {
+    let mut fields = Vec::new();
+    for range in Values::new(list, delimiter) {
+        let mut rest = &list[range];
+        loop {
+            // A quoted name is stepped over whole, so the separator is only
+            // looked for after its closing quote
+            let (field, len) = if rest.starts_with('"') {
+                let quoted = read_quoted(rest).ok()?;
+                if !quoted.closed {
+                    return None;
+                }
+                (quoted.value, quoted.len)
+            } else {
+                let len = match delimiter {
+                    Delimiter::Tab => rest.find("    ").unwrap_or(rest.len()),
+                    _ => rest.len(),
+                };
+                (rest[..len].trim().to_string(), len)
+            };
+            fields.push(field);
+            rest = &rest[len..];
+            if rest.is_empty() {
+                break;
+            }
+            if *delimiter != Delimiter::Tab {
+                return None;
+            }
+            rest = rest.strip_prefix("    ")?.trim_start();
+        }
+    }
+    Some(fields)
+}
+
+/// Explains why `text`, which starts with `[`, is not an array header
+/// followed by `:`, returning the byte offset of the problem and a message.
+pub(crate) fn header_error(text: &str) -> (usize, &'static str) {
+    let Some(inner_end) = text.find(']') else {
+        return (text.len(), "Expected ']'");
+    };
+    let inner = &text[1..inner_end];
+    let skip = usize::from(inner.starts_with('#'));
+    let inner = &inner[skip..];
+    let digits_end = inner
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(inner.len());
+    if digits_end == 0 || inner[..digits_end].parse::<usize>().is_err() {
+        return (1 + skip, "Invalid array length");
+    }
+    if delimiter_marker(&inner[digits_end..]).is_none() {
+        return (
+            1 + skip + digits_end,
+            "Expected ']', '|' or a tab after the array length",
+        );
+    }
+    match header_at(text) {
+        None if text[inner_end + 1..].starts_with('{') => (inner_end + 1, "Expected '}'"),
+        None => (1 + skip + digits_end, "Expected ']'"),
+        Some((_, len)) => (len, "Expected ':' after array header"),
+    }
+}
+
+/// A quoted string read by [`read_quoted`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Quoted {
+    /// The string with its escapes resolved.
+    pub(crate) value: String,
+    /// Bytes read, including the quotes.
+    pub(crate) len: usize,
+    /// Whether the closing quote was found before the end of the text.
+    pub(crate) closed: bool,
+    /// Byte offsets of the unknown escapes kept as written.
+    pub(crate) unknown_escapes: Vec<usize>,
+}
+
+/// Reads the quoted string at the start of `text`, resolving escapes.
+/// Unknown escapes are kept as written.
+///
+/// Returns the byte offset and a description of an invalid `\u` escape.
+pub(crate) fn read_quoted(text: &str) -> Result<Quoted, (usize, &'static str)> {
+    let mut value = String::new();
+    let mut unknown_escapes = Vec::new();
+    let mut chars = text.char_indices().skip(1);
+    while let Some((i, ch)) = chars.next() {
+        match ch {
+            '"' => {
+                return Ok(Quoted {
+                    value,
+                    len: i + 1,
+                    closed: true,
+                    unknown_escapes,
+                })
+            }
+            '\\' => match chars.next() {
+                Some((_, '\\')) => value.push('\\'),
+                Some((_, '"')) => value.push('"'),
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 'r')) => value.push('\r'),
+                Some((_, 't')) => value.push('\t'),
+                Some((_, 'b')) => value.push('\u{0008}'),
+                Some((_, 'f')) => value.push('\u{000C}'),
+                Some((_, '0')) => value.push('\0'),
+                Some((_, 'u')) if text[i + 2..].starts_with('{') => {
+                    // `\u{...}`: one to six hex digits
+                    let hex = text[i + 3..]
+                        .split_once('}')
+                        .map(|(hex, _)| hex)
+                        .filter(|hex| {
+                            (1..=6).contains(&hex.len())
+                                && hex.bytes().all(|b| b.is_ascii_hexdigit())
+                        });
+                    let Some(hex) = hex else {
+                        return Err((i, "Invalid unicode escape sequence (expected 1 to 6 hex digits in braces)"));
+                    };
+                    let code_point = u32::from_str_radix(hex, 16)
+                        .map_err(|_| (i, "Invalid hex in unicode escape"))?;
+                    let ch = char::from_u32(code_point).ok_or((i, "Invalid unicode code point"))?;
+                    value.push(ch);
+                    for _ in 0..hex.len() + 2 {
+                        chars.next();
+                    }
+                }
+                Some((_, 'u')) => {
+                    let hex = text
+                        .get(i + 2..i + 6)
+                        .filter(|hex| hex.len() == 4 && hex.bytes().all(|b| b.is_ascii_hexdigit()));
+                    let Some(hex) = hex else {
+                        return Err((i, "Invalid unicode escape sequence (expected 4 hex digits)"));
+                    };
+                    let code_point = u32::from_str_radix(hex, 16)
+                        .map_err(|_| (i, "Invalid hex in unicode escape"))?;
+                    let ch = char::from_u32(code_point).ok_or((i, "Invalid unicode code point"))?;
+                    value.push(ch);
+                    for _ in 0..4 {
+                        chars.next();
+                    }
+                }
+                Some((_, other)) => {
+                    unknown_escapes.push(i);
+                    value.push('\\');
+                    value.push(other);
+                }
+                None => break,
+            },
+            other => value.push(other),
+        }
+    }
+    Ok(Quoted {
+        value,
+        len: text.len(),
+        closed: false,
+        unknown_escapes,
+    })
+}
+
+/// Iterator over the byte ranges of the values in an inline array body or
+/// table row, such as `a,"b,c",d`, split at `delimiter` outside quotes.
+/// Each range is trimmed of surrounding whitespace.
+#[derive(Debug, Clone)]
+pub(crate) struct Values<'a> {
+    text: &'a str,
+    delimiter: u8,
+    // Start of the next value, or `None` once all have been returned
+    next: Option<usize>,
+}
+
+impl<'a> Values<'a> {
+    pub(crate) fn new(text: &'a str, delimiter: &Delimiter) -> Self {
+        Values {
+            text,
+            delimiter: delimiter.as_str().as_bytes()[0],
+            next: Some(0),
+        }
+    }
+}
+
+impl Iterator for Values<'_> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Range<usize>> {
+        let start = self.next?;
+        let bytes = self.text.as_bytes();
+        let mut in_quotes = false;
+        let mut escaped = false;
+        let mut end = bytes.len();
+        for (i, &byte) in bytes.iter().enumerate().skip(start) {
+            if in_quotes {
+                match byte {
+                    b'\\' if !escaped => escaped = true,
+                    b'"' if !escaped => in_quotes = false,
+                    _ => escaped = false,
+                }
+            } else if byte == b'"' {
+                in_quotes = true;
+            } else if byte == self.delimiter {
+                end = i;
+                break;
+            }
+        }
+        self.next = (end < bytes.len()).then_some(end + 1);
+
+        let value = &self.text[start..end];
+        let trimmed = value.trim_start();
+        let from = start + (value.len() - trimmed.len());
+        Some(from..from + trimmed.trim_end().len())
+    }
+}
+
+/// Counts the values in an inline array body such as `a,"b,c",d`.
+pub(crate) fn count_inline_values(body: &str, delimiter: &Delimiter) -> usize {
+    if body.trim().is_empty() {
+        return 0;
+    }
+    Values::new(body, delimiter).count()
+}
+
+/// A token of a TOON document, produced by [`Tokens`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Token<'a> {
+    pub(crate) kind: TokenKind<'a>,
+    /// Byte range of the token in the input; empty for indentation changes.
+    pub(crate) span: Range<usize>,
+    /// 1-based line number.
+    pub(crate) line: usize,
+    /// 1-based column (in characters) of the token's start.
+    pub(crate) column: usize,
+}
+
+impl Token<'_> {
+    /// Whether the token holds text, as opposed to marking a line or block boundary.
+    pub(crate) fn has_content(&self) -> bool {
+        !matches!(
+            self.kind,
+            TokenKind::Indent | TokenKind::Dedent | TokenKind::Newline | TokenKind::BadIndent(_)
+        )
+    }
+}
+
+/// The kinds of [`Token`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TokenKind<'a> {
+    /// Opens a block: a line indented deeper than the block it is in, or
+    /// the content of a list item after its dash.
+    Indent,
+    /// Closes the innermost open block.
+    Dedent,
+    /// The `-` of a list item.
+    Dash,
+    /// An object key as written (a quoted key keeps its quotes and escapes).
+    /// The `:` after it, or after the array header following it, is implied.
+    Key(&'a str),
+    /// An array header such as `[3]`, `[#2|]` or `[2]{id,name}`. The `:`
+    /// after it is implied.
+    TableHeader(Header),
+    /// The rest of a line after a key, header or dash, or a line with none of
+    /// them: a primitive, the items of an inline array or a table row.
+    Scalar(&'a str),
+    /// The lines of a block string after `key: |`, as written with their
+    /// indentation; the token itself starts at the `|`. See [`block_text`].
+    Block(&'a str),
+    /// The end of a line.
+    Newline,
+    /// Indentation that the blocks around it do not allow, just before the
+    /// content of its line. The line is read as if it were indented as expected.
+    BadIndent(IndentIssue),
+}
+
+/// What is wrong with a line's indentation, reported by [`TokenKind::BadIndent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum IndentIssue {
+    /// Indented `found` columns where `expected` were required. Tabs count as
+    /// the tab width given to [`Tokens::with_tabs`].
+    Width { expected: usize, found: usize },
+    /// A tab in the indentation, when tabs are not allowed.
+    Tab,
+}
+
+/// Tokenizes a document line by line, buffering tokens for lookahead.
+///
+/// Blank lines produce no tokens. A block may only open after a line ending
+/// in a key or array header; any other deeper line, or a line indented less
+/// than the block it closes but more than the enclosing one, is reported with
+/// [`TokenKind::BadIndent`] and then read as part of the enclosing block.
+#[derive(Debug, Clone)]
+pub(crate) struct Tokens<'a> {
+    input: &'a str,
+    lines: Lines<'a>,
+    // Indentation of each open block, innermost last; empty before the first line
+    indents: Vec<usize>,
+    // Whether the last line read may be followed by a deeper block
+    opens_block: bool,
+    // Width required of each level of indentation, if any
+    indent_width: Option<usize>,
+    tab_width: usize,
+    allow_tabs: bool,
+    buffer: VecDeque<Token<'a>>,
+    // Line number of the last line read, for the blocks closed at the end
+    last_line: usize,
+    done: bool,
+}
+
+impl<'a> Tokens<'a> {
+    pub(crate) fn new(input: &'a str) -> Self {
+        Tokens {
+            input,
+            lines: lines(input),
+            indents: Vec::new(),
+            opens_block: false,
+            indent_width: None,
+            tab_width: 2,
+            allow_tabs: false,
+            buffer: VecDeque::new(),
+            last_line: 1,
+            done: false,
+        }
+    }
+
+    /// Requires every indented block to be exactly `width` columns deeper than
+    /// the one it is in.
+    pub(crate) fn with_indent_width(mut self, width: Option<usize>) -> Self {
+        self.indent_width = width;
+        self
+    }
+
+    /// Sets whether code fences around the document are skipped (see
+    /// [`Lines::with_code_fences`]).
+    pub(crate) fn with_code_fences(mut self, fences: bool) -> Self {
+        self.lines = self.lines.with_code_fences(fences);
+        self
+    }
+
+    /// Sets whether tabs are accepted in indentation, and how many columns
+    /// each one counts as either way.
+    pub(crate) fn with_tabs(mut self, allow: bool, width: usize) -> Self {
+        self.allow_tabs = allow;
+        self.tab_width = width;
+        self
+    }
+
+    /// Returns the next token without consuming it.
+    pub(crate) fn peek(&mut self) -> Option<&Token<'a>> {
+        self.peek_nth(0)
+    }
+
+    /// Returns the token `n` places ahead without consuming anything.
+    pub(crate) fn peek_nth(&mut self, n: usize) -> Option<&Token<'a>> {
+        while self.buffer.len() <= n {
+            if !self.fill() {
+                return None;
+            }
+        }
+        self.buffer.get(n)
+    }
+
+    /// Reads the next line into the buffer, returning `false` once the input
+    /// is exhausted.
+    fn fill(&mut self) -> bool {
+        if self.done {
+            return false;
+        }
+        match self.lines.next() {
+            Some(line) => {
+                self.push_line(&line);
+                true
+            }
+            None => {
+                self.done = true;
+                let end = self.input.len();
+                let column = self.input[..end]
+                    .rsplit('\n')
+                    .next()
+                    .map_or(0, |last| last.chars().count())
+                    + 1;
+                let open = self.indents.len().saturating_sub(1);
+                for _ in 0..open {
+                    self.buffer.push_back(Token {
+                        kind: TokenKind::Dedent,
+                        span: end..end,
+                        line: self.last_line,
+                        column,
+                    });
+                }
+                open > 0
+            }
+        }
+    }
+
+    fn push_line(&mut self, line: &Line<'a>) {
+        self.last_line = line.number;
+        let token = |kind, range: Range<usize>| Token {
+            kind,
+            span: line.span.start + range.start..line.span.start + range.end,
+            line: line.number,
+            column: line.indent + line.text[..range.start].chars().count() + 1,
+        };
+
+        let indentation = &self.input[line.span.start - line.indent..line.span.start];
+        let tabs = indentation.matches('\t').count();
+        let width = self.width(line);
+        let mut issues = Vec::new();
+        if tabs > 0 && !self.allow_tabs {
+            issues.push(IndentIssue::Tab);
+        }
+        match self.indents.last() {
+            None => self.indents.push(width),
+            Some(&top) if width > top => {
+                let expected = match self.indent_width {
+                    _ if !self.opens_block => Some(top),
+                    Some(step) if width != top + step => Some(top + step),
+                    _ => None,
+                };
+                if let Some(expected) = expected {
+                    issues.push(IndentIssue::Width {
+                        expected,
+                        found: width,
+                    });
+                }
+                self.indents.push(width);
+                self.buffer.push_back(token(TokenKind::Indent, 0..0));
+            }
+            Some(_) => {
+                while self.indents.len() > 1 && self.indents.last() > Some(&width) {
+                    self.indents.pop();
+                    self.buffer.push_back(token(TokenKind::Dedent, 0..0));
+                }
+                match self.indents.last() {
+                    Some(&top) if top != width => issues.push(IndentIssue::Width {
+                        expected: top,
+                        found: width,
+                    }),
+                    _ => {}
+                }
+            }
+        }
+        for issue in issues {
+            self.buffer
+                .push_back(token(TokenKind::BadIndent(issue), 0..0));
+        }
+
+        let mut offset = 0;
+        if let Some(content) = item_content(line.text) {
+            self.buffer.push_back(token(TokenKind::Dash, 0..1));
+            if content == line.text.len() {
+                self.buffer
+                    .push_back(token(TokenKind::Newline, content..content));
+                self.opens_block = false;
+                return;
+            }
+            // The item's content is a block at its own column
+            self.indents.push(width + content);
+            self.buffer
+                .push_back(token(TokenKind::Indent, content..content));
+            offset = content;
+        }
+
+        let text = &line.text[offset..];
+        self.opens_block = false;
+        let mut after_key = false;
+        for (kind, range) in content_tokens(text) {
+            self.opens_block = matches!(kind, TokenKind::Key(_) | TokenKind::TableHeader(_));
+            let range = offset + range.start..offset + range.end;
+            let body = if after_key && kind == TokenKind::Scalar("|") {
+                let indent = self.indents.last().copied().unwrap_or(0);
+                self.block_string_lines(line.span.end, indent)
+            } else {
+                0..0
+            };
+            if !body.is_empty() {
+                // A block string: the lines below, read as they are
+                let mut block = token(TokenKind::Block(&self.input[body.clone()]), range);
+                block.span.end = block.span.end.max(body.end);
+                let end = block.span.end;
+                self.buffer.push_back(block);
+                self.buffer.push_back(Token {
+                    kind: TokenKind::Newline,
+                    span: end..end,
+                    line: line.number,
+                    column: line.indent + line.text.chars().count() + 1,
+                });
+                return;
+            }
+            after_key = matches!(kind, TokenKind::Key(_));
+            self.buffer.push_back(token(kind, range));
+        }
+        let end = line.text.len();
+        self.buffer.push_back(token(TokenKind::Newline, end..end));
+    }
+
+    /// Columns of indentation before `line`'s content, with tabs counting as
+    /// the tab width.
+    fn width(&self, line: &Line<'_>) -> usize {
+        let indentation = &self.input[line.span.start - line.indent..line.span.start];
+        let tabs = indentation.matches('\t').count();
+        line.indent - tabs + tabs * self.tab_width
+    }
+
+    /// Consumes the lines of a block string after a `key: |` line ending at
+    /// byte `after`: every line indented deeper than `indent`, and the blank
+    /// lines among them. Returns their byte range, from the start of the
+    /// line after the key; empty if there are none, in which case the `|` is
+    /// the string `"|"`.
+    fn block_string_lines(&mut self, after: usize, indent: usize) -> Range<usize> {
+        let start = self.input[after..]
+            .find('\n')
+            .map_or(self.input.len(), |i| after + i + 1);
+        let mut end = start;
+        loop {
+            let mut lines = self.lines.clone();
+            match lines.next() {
+                Some(line) if self.width(&line) > indent => {
+                    end = line.span.end;
+                    self.last_line = line.number;
+                    self.lines = lines;
+                }
+                _ => return start..end,
+            }
+        }
+    }
+}
+
+/// Returns the text of a block string from its lines as written (see
+/// [`TokenKind::Block`]): each line loses the indentation of the first
+/// non-blank line and any trailing whitespace.
+pub(crate) fn block_text(lines: &str) -> String {
+    let spaces = |line: &str| line.len() - line.trim_start_matches(' ').len();
+    let indent = lines
+        .split('\n')
+        .find(|line| !line.trim().is_empty())
+        .map_or(0, spaces);
+    lines
+        .split('\n')
+        .map(|line| {
+            let line = line.trim_end();
+            &line[spaces(line).min(indent)..]
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Replaces each `${NAME}` in `text` with the value `lookup` gives for the
+/// variable `NAME`. A `${NAME:-default}` reference takes `default` when the
+/// variable is unset or empty, and `$${` stands for a literal `${`. Errors
+/// give the byte offset of the reference at fault.
+pub(crate) fn interpolate<'a>(
+    text: &'a str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<Cow<'a, str>, (usize, String)> {
+    if !text.contains("${") {
+        return Ok(Cow::Borrowed(text));
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        let offset = text.len() - rest.len() + start;
+        if rest[..start].ends_with('$') {
+            out.push_str(&rest[..start - 1]);
+            out.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            return Err((offset, "Unterminated `${` reference".to_string()));
+        };
+        let reference = &rest[start + 2..start + end];
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+        if name.is_empty() || !name.chars().all(|c| c == '_' || c.is_ascii_alphanumeric()) {
+            return Err((
+                offset,
+                format!("Invalid environment variable name `{}`", name),
+            ));
+        }
+        match (lookup(name), default) {
+            (Some(value), Some(default)) if value.is_empty() => out.push_str(default),
+            (Some(value), _) => out.push_str(&value),
+            (None, Some(default)) => out.push_str(default),
+            (None, None) => {
+                return Err((
+                    offset,
+                    format!("Environment variable `{}` is not set", name),
+                ))
+            }
+        }
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    Ok(Cow::Owned(out))
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        if self.buffer.is_empty() && !self.fill() {
+            return None;
+        }
+        self.buffer.pop_front()
+    }
+}
+
+/// Splits the content of a line (after indentation and any dash) into a key,
+/// an array header and the scalar text after them, with their byte ranges.
+fn content_tokens(text: &str) -> Vec<(TokenKind<'_>, Range<usize>)> {
+    let mut tokens = Vec::with_capacity(3);
+    let mut rest_start = 0;
+    if text.starts_with('[') {
+        if let Some((header, len)) = header_at(text) {
+            if text[len..].starts_with(':') {
+                tokens.push((TokenKind::TableHeader(header), 0..len));
+                rest_start = len + 1;
+            }
+        }
+    }
+    if tokens.is_empty() {
+        if let Some(found) = key_at(text) {
+            tokens.push((TokenKind::Key(found.key), 0..found.key.len()));
+            if let Some((header, range)) = found.header {
+                tokens.push((TokenKind::TableHeader(header), range));
+            }
+            rest_start = found.after;
+            // Repository form `key: [3]{a,b}: ...` carries the header after the colon
+            if tokens.len() == 1 {
+                let value = text[rest_start..].trim_start();
+                let start = text.len() - value.len();
+                if let Some((header, len)) = header_at(value) {
+                    if value[len..].starts_with(':') {
+                        tokens.push((TokenKind::TableHeader(header), start..start + len));
+                        rest_start = start + len + 1;
+                    }
+                }
+            }
+        }
+    }
+
+    let rest = text[rest_start..].trim_start();
+    if !rest.is_empty() {
+        let start = text.len() - rest.len();
+        tokens.push((TokenKind::Scalar(rest), start..text.len()));
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_spans_and_indent() {
+        let input = "a: 1\n\n  b: 2  \n";
+        let lines: Vec<_> = lines(input).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].number, 3);
+        assert_eq!(lines[1].indent, 2);
+        assert_eq!(&input[lines[1].span.clone()], "b: 2");
+    }
+
+    #[test]
+    fn test_lines_skip_bom_and_fences() {
+        let input = "\u{feff}a: 1";
+        let first = lines(input).next().unwrap();
+        assert_eq!(first.indent, 0);
+        assert_eq!(&input[first.span], "a: 1");
+
+        let input = "\n```toon\na: |\n  ```\n```\n\n";
+        let texts = |fences| {
+            lines(input)
+                .with_code_fences(fences)
+                .map(|line| line.text)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(texts(true), ["a: |", "```"]);
+        assert_eq!(texts(false), ["```toon", "a: |", "```", "```"]);
+    }
+
+    #[test]
+    fn test_is_numeric() {
+        for valid in ["0", "-0", "12", "1.5", "-0.25e-3", "6E10", "1E400"] {
+            assert!(is_numeric(valid), "{}", valid);
+        }
+        for invalid in [
+            "", "-", "+1", "01", "1.", ".5", "1e", "0x10", "1.5.2", "NaN", "inf",
+        ] {
+            assert!(!is_numeric(invalid), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_classify_fields_and_headers() {
+        match classify("users: [2]{id,name}:") {
+            LineKind::Field { key, header, value } => {
+                assert_eq!(key, "users");
+                let header = header.unwrap();
+                assert_eq!(header.length, Some(2));
+                assert_eq!(header.fields, vec!["id", "name"]);
+                assert_eq!(value, "");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        match classify("tags[3|]: a|b|c") {
+            LineKind::Field { key, header, value } => {
+                assert_eq!(key, "tags");
+                assert_eq!(header.unwrap().delimiter, Delimiter::Pipe);
+                assert_eq!(value, "a|b|c");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        assert!(matches!(classify("[#3]: 1,2,3"), LineKind::Header { .. }));
+        assert!(matches!(
+            classify("- id: 1"),
+            LineKind::Item { offset: 2, .. }
+        ));
+        assert!(matches!(classify("1,Alice"), LineKind::Other));
+    }
+
+    #[test]
+    fn test_count_inline_values() {
+        assert_eq!(count_inline_values("a,\"b,c\",d", &Delimiter::Comma), 3);
+        assert_eq!(count_inline_values("", &Delimiter::Comma), 0);
+    }
+
+    fn kinds(input: &str) -> Vec<TokenKind<'_>> {
+        Tokens::new(input).map(|token| token.kind).collect()
+    }
+
+    #[test]
+    fn test_tokens_blocks() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("a:\n  b: 1\nc: x"),
+            [
+                Key("a"),
+                Newline,
+                Indent,
+                Key("b"),
+                Scalar("1"),
+                Newline,
+                Dedent,
+                Key("c"),
+                Scalar("x"),
+                Newline
+            ]
+        );
+
+        // Item content is a block, so later fields of the item continue it
+        let header = Header {
+            length: Some(2),
+            fields: vec![],
+            delimiter: Delimiter::Comma,
+            marked: false,
+        };
+        assert_eq!(
+            kinds("[2]:\n  - id: 1\n    ok: true\n  - -3"),
+            [
+                TableHeader(header),
+                Newline,
+                Indent,
+                Dash,
+                Indent,
+                Key("id"),
+                Scalar("1"),
+                Newline,
+                Key("ok"),
+                Scalar("true"),
+                Newline,
+                Dedent,
+                Dash,
+                Indent,
+                Scalar("-3"),
+                Newline,
+                Dedent,
+                Dedent
+            ]
+        );
+
+        // Dedenting between two levels is reported, then continues the outer block
+        assert_eq!(
+            kinds("a:\n    b: 1\n  c: 2"),
+            [
+                Key("a"),
+                Newline,
+                Indent,
+                Key("b"),
+                Scalar("1"),
+                Newline,
+                Dedent,
+                BadIndent(IndentIssue::Width {
+                    expected: 0,
+                    found: 2
+                }),
+                Key("c"),
+                Scalar("2"),
+                Newline
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokens_indentation() {
+        let issues = |tokens: Tokens<'_>| -> Vec<IndentIssue> {
+            tokens
+                .filter_map(|token| match token.kind {
+                    TokenKind::BadIndent(issue) => Some(issue),
+                    _ => None,
+                })
+                .collect()
+        };
+        let width = |expected, found| IndentIssue::Width { expected, found };
+
+        // Only keys and headers open blocks
+        assert_eq!(issues(Tokens::new("a: 1\n  b: 2")), [width(0, 2)]);
+        assert_eq!(issues(Tokens::new("[1]{a}:\n  1\n    2")), [width(2, 4)]);
+        assert_eq!(
+            issues(Tokens::new("[1]:\n  - a: 1\n      b: 2")),
+            [width(4, 6)]
+        );
+        assert!(issues(Tokens::new("a:\n      b:\n         c: 1")).is_empty());
+
+        // A required width applies to every block but list item content
+        let tokens = |input| Tokens::new(input).with_indent_width(Some(2));
+        assert_eq!(issues(tokens("a:\n   b: 1")), [width(2, 3)]);
+        assert!(issues(tokens("k: [1]:\n  - a:\n      b: 1")).is_empty());
+
+        // Tabs are reported unless allowed, and count as the tab width
+        assert_eq!(issues(Tokens::new("a:\n\tb: 1")), [IndentIssue::Tab]);
+        let tokens = Tokens::new("a:\n\tb:\n\t\tc: 1\n    d: 2").with_tabs(true, 4);
+        let levels: Vec<_> = tokens.map(|token| (token.kind, token.column)).collect();
+        assert!(levels.contains(&(TokenKind::Key("d"), 5)));
+        assert!(!levels
+            .iter()
+            .any(|(kind, _)| matches!(kind, TokenKind::BadIndent(_))));
+    }
+
+    #[test]
+    fn test_block_strings() {
+        use TokenKind::*;
+        let input = "a: |\n  x: 1\n\n    - y\nb: |\nc: 2";
+        let tokens: Vec<_> = Tokens::new(input).collect();
+        let block = "  x: 1\n\n    - y";
+        assert_eq!(tokens[1].kind, Block(block));
+        assert_eq!(input[tokens[1].span.clone()], format!("|\n{}", block));
+        assert_eq!(tokens[2].kind, Newline);
+        assert_eq!(tokens[3].kind, Key("b"));
+        // Without indented lines below, `|` is a plain string
+        assert_eq!(tokens[4].kind, Scalar("|"));
+        assert_eq!(tokens[6].kind, Key("c"));
+        assert_eq!(block_text(block), "x: 1\n\n  - y");
+
+        // Only a `|` after a key starts a block string
+        assert_eq!(kinds("- |")[..2], [Dash, Indent]);
+        assert_eq!(kinds("- |")[2], Scalar("|"));
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let env = |name: &str| match name {
+            "HOST" => Some("db.local".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        };
+        let run = |text| interpolate(text, env);
+        assert!(matches!(run("plain").unwrap(), Cow::Borrowed("plain")));
+        assert_eq!(run("${HOST}:5432").unwrap(), "db.local:5432");
+        assert_eq!(run("${PORT:-5432}").unwrap(), "5432");
+        assert_eq!(run("[${EMPTY}|${EMPTY:-x}]").unwrap(), "[|x]");
+        assert_eq!(run("$${HOST} ${HOST}").unwrap(), "${HOST} db.local");
+        assert_eq!(run("a ${PORT}").unwrap_err().0, 2);
+        assert_eq!(run("${HOST").unwrap_err().0, 0);
+        assert_eq!(run("x${}").unwrap_err().0, 1);
+    }
+
+    #[test]
+    fn test_tokens_keys_and_headers() {
+        use TokenKind::*;
+        let tokens: Vec<_> = Tokens::new("\"a: b\"[2|]: x|y\nrows: [1]{\"id\"}:").collect();
+        assert_eq!(tokens[0].kind, Key("\"a: b\""));
+        assert!(matches!(
+            &tokens[1].kind,
+            TableHeader(Header {
+                length: Some(2),
+                delimiter: Delimiter::Pipe,
+                ..
+            })
+        ));
+        assert_eq!(tokens[2].kind, Scalar("x|y"));
+        assert_eq!((tokens[2].span.clone(), tokens[2].column), (12..15, 13));
+        assert!(matches!(&tokens[5].kind, TableHeader(header) if header.fields == ["id"]));
+        assert_eq!((tokens[5].line, tokens[5].column), (2, 7));
+
+        // Text that only looks like a key or header is a scalar
+        for line in ["12:30", "a,b: 1", "[abc]: x", "\"x\" y: 1", ":x", "[3]"] {
+            assert_eq!(kinds(line), [Scalar(line), Newline], "{}", line);
+        }
+        assert_eq!(kinds("at: 12:30")[..2], [Key("at"), Scalar("12:30")]);
+    }
+
+    #[test]
+    fn test_values_and_quotes() {
+        let text = "1, \"a,\\\"b\" ,x";
+        let values: Vec<_> = Values::new(text, &Delimiter::Comma)
+            .map(|range| &text[range])
+            .collect();
+        assert_eq!(values, ["1", "\"a,\\\"b\"", "x"]);
+
+        let quoted = read_quoted("\"a\\u00e9\\q\" rest").unwrap();
+        assert_eq!(
+            (quoted.value.as_str(), quoted.len, quoted.closed),
+            ("aé\\q", 11, true)
+        );
+        assert_eq!(quoted.unknown_escapes, [8]);
+        assert!(!read_quoted("\"open").unwrap().closed);
+        assert_eq!(read_quoted("\"\\u12\"").unwrap_err().0, 1);
+        let quoted = read_quoted("\"\\u{200b}x\\u{1F600}\"").unwrap();
+        assert_eq!((quoted.value.as_str(), quoted.len), ("\u{200B}x😀", 20));
+        assert_eq!(read_quoted("\"\\u{}\"").unwrap_err().0, 1);
+        assert_eq!(read_quoted("\"\\u{110000}\"").unwrap_err().0, 1);
+
+        assert_eq!(header_error("[abc]: x"), (1, "Invalid array length"));
+        assert_eq!(header_error("[3]"), (3, "Expected ':' after array header"));
+    }
+
+    #[test]
+    fn test_delimiter_markers() {
+        let delimiter = |text| header_at(text).map(|(header, _)| (header.delimiter, header.marked));
+        assert_eq!(delimiter("[2]:"), Some((Delimiter::Comma, false)));
+        assert_eq!(delimiter("[#2|]:"), Some((Delimiter::Pipe, true)));
+        assert_eq!(delimiter("[2\t]:"), Some((Delimiter::Tab, true)));
+        assert_eq!(delimiter("[2    ]{a    b}:"), Some((Delimiter::Tab, true)));
+        for text in ["[2  ]:", "[2;]:", "[2,]:", "[2 |]:"] {
+            assert_eq!(delimiter(text), None, "{}", text);
+        }
+        assert_eq!(header_error("[2;]: a").0, 2);
+
+        let fields = fields_with("[2]{id|\"a|b\"|name}", &Delimiter::Pipe).unwrap();
+        assert_eq!(fields, ["id", "a|b", "name"]);
+    }
+}
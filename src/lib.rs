@@ -128,20 +128,89 @@
 //!
 //! Run any example with: `cargo run --example <name>`
 
+// Malformed input must surface as an `Error`, never as a panic
+#![cfg_attr(
+    not(test),
+    deny(clippy::unwrap_used, clippy::expect_used, clippy::panic)
+)]
+
+pub mod budget;
+pub mod builder;
+mod bytes;
+pub mod chunks;
+#[cfg(feature = "json")]
+pub mod conformance;
+#[cfg(feature = "csv")]
+pub mod csv;
 pub mod de;
+#[cfg(feature = "rust_decimal")]
+pub mod decimal;
+pub mod describe;
 pub mod error;
+pub mod event;
+pub mod extract;
+pub mod file;
+#[cfg(feature = "arbitrary")]
+mod fuzzing;
+pub mod incremental;
+mod json;
+mod lexer;
+pub mod llm;
 pub mod macros;
 pub mod map;
+pub mod markdown;
+pub mod metrics;
 pub mod options;
+pub mod outline;
+pub mod path;
+#[cfg(feature = "figment")]
+pub mod provider;
+pub mod query;
+pub mod raw;
+pub mod scan;
+pub mod schema;
 pub mod ser;
+pub mod size;
+pub mod span;
 pub mod spec;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+pub mod table;
+pub mod template;
+pub mod testing;
+pub mod trace;
+pub mod update;
 pub mod value;
 
-pub use de::Deserializer;
-pub use error::{Error, Result};
-pub use map::ToonMap;
-pub use options::{Delimiter, ToonOptions};
+pub use budget::{to_string_within_budget, TruncationPolicy};
+pub use builder::{ArrayBuilder, IntoRow, ObjectBuilder, TableBuilder};
+pub use chunks::{to_chunks, to_chunks_with_options};
+pub use de::{Deserializer, Partial};
+pub use describe::describe;
+pub use error::{Error, Result, Warning, WarningKind};
+pub use event::{Event, ToonReader, ToonWriter};
+pub use extract::extract_from_text;
+pub use file::{from_file, from_file_with_options, to_file, to_file_atomic};
+pub use llm::{from_str_or_explain, ModelFeedback};
+pub use map::{Key, ToonMap};
+pub use options::{
+    BytesFormat, CompatMode, Delimiter, DelimiterStrategy, DuplicateKeys, EnumRepr, EnvLookup,
+    FieldAction, FieldFilter, FloatFormat, KeyAliases, KeyOrder, LineEnding, NonStringKeys,
+    QuotePolicy, RoundingMode, ToonOptions, TypeCodec, UnsupportedPolicy, VectorEncoding,
+    VectorFormat, DEFAULT_MAX_DEPTH,
+};
+pub use outline::{outline, OutlineKind, OutlineNode};
+pub use query::Query;
+pub use raw::RawToon;
+pub use scan::get_field;
+pub use schema::ToonSchema;
 pub use ser::{Serializer, ValueSerializer};
+#[cfg(feature = "derive")]
+pub use serde_toon_derive::ToonSchema;
+pub use size::{estimated_size, SizeEstimate};
+pub use span::{Span, Spans};
+pub use table::{ColumnType, HeaderPolicy, Table};
+pub use update::update_in_place;
 pub use value::{Number, Value};
 
 use serde::{Deserialize, Serialize};
@@ -175,7 +244,8 @@ where
 
 /// Serialize any `T: Serialize` to a pretty-printed TOON string.
 ///
-/// Pretty-printing adds newlines and indentation for readability.
+/// Pretty-printing sets top-level sections apart with blank lines and aligns
+/// table columns; see [`ToonOptions::pretty`].
 ///
 /// # Examples
 ///
@@ -229,9 +299,112 @@ pub fn to_string_with_options<T>(value: &T, options: ToonOptions) -> Result<Stri
 where
     T: ?Sized + Serialize,
 {
+    trace::span!("serialize");
     let mut serializer = Serializer::new(options);
     value.serialize(&mut serializer)?;
-    Ok(serializer.into_inner())
+    let output = serializer.into_inner();
+    trace::event!(DEBUG, bytes = output.len(), "serialized");
+    Ok(output)
+}
+
+/// Serialize any `T: Serialize` to TOON in `buf`, replacing its contents.
+///
+/// Unlike [`to_string`], this reuses the allocation of `buf`, so serializing
+/// many documents into one buffer allocates only when it must grow. See the
+/// [`ser`] module for keeping a buffer per thread.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::to_string_into;
+///
+/// let mut buf = String::with_capacity(64);
+/// for n in 1..=3 {
+///     to_string_into(&vec![n; n], &mut buf).unwrap();
+/// }
+/// assert_eq!(buf, "[3]: 3,3,3");
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the value cannot be serialized, leaving `buf` empty.
+pub fn to_string_into<T>(value: &T, buf: &mut String) -> Result<()>
+where
+    T: ?Sized + Serialize,
+{
+    to_string_into_with_options(value, buf, ToonOptions::default())
+}
+
+/// Serialize any `T: Serialize` to TOON in `buf` with custom options,
+/// replacing its contents. See [`to_string_into`].
+///
+/// # Errors
+///
+/// Returns an error if the value cannot be serialized, leaving `buf` empty.
+pub fn to_string_into_with_options<T>(
+    value: &T,
+    buf: &mut String,
+    options: ToonOptions,
+) -> Result<()>
+where
+    T: ?Sized + Serialize,
+{
+    trace::span!("serialize");
+    buf.clear();
+    let mut serializer = Serializer::with_output(options, std::mem::take(buf));
+    let result = value.serialize(&mut serializer);
+    *buf = serializer.into_inner();
+    if result.is_err() {
+        buf.clear();
+    }
+    trace::event!(DEBUG, bytes = buf.len(), "serialized");
+    result
+}
+
+/// Serialize any `T: Serialize` to both TOON and JSON, returned as
+/// `(toon, json)`, for comparing prompts written in each format.
+///
+/// `value` is converted to a [`Value`] once, with `options`, and both
+/// documents are written from it, so they hold the same data: fields omitted
+/// or masked by a [`FieldFilter`] are missing or masked in both, and the JSON
+/// uses the TOON output's key aliases, key order, `null` field omission and
+/// number formatting. Tables are written to JSON as arrays of objects.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{to_dual_strings, toon, FloatFormat, ToonOptions};
+///
+/// let value = toon!({"b": 2.137, "a": [{"id": 1}, {"id": 2}], "note": null});
+/// let options = ToonOptions::new()
+///     .with_sort_keys(true)
+///     .with_skip_none_fields(true)
+///     .with_float_format(FloatFormat::new().with_max_decimals(2));
+///
+/// let (toon, json) = to_dual_strings(&value, options).unwrap();
+/// assert_eq!(toon, "a: [2]{id}:\n  1\n  2\nb: 2.14");
+/// assert_eq!(json, r#"{"a":[{"id":1},{"id":2}],"b":2.14}"#);
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the value cannot be serialized.
+#[must_use = "this returns the result of the operation, errors must be handled"]
+pub fn to_dual_strings<T>(value: &T, options: ToonOptions) -> Result<(String, String)>
+where
+    T: ?Sized + Serialize,
+{
+    trace::span!("serialize", format = "dual");
+    let snapshot = value.serialize(ValueSerializer::with_options(options.clone()))?;
+    let toon = snapshot.to_toon_string(&options);
+    let json = json::to_json_string_with_options(&snapshot, &options);
+    trace::event!(
+        DEBUG,
+        bytes = toon.len(),
+        json_bytes = json.len(),
+        "serialized"
+    );
+    Ok((toon, json))
 }
 
 /// Convert any `T: Serialize` to a `Value`.
@@ -260,7 +433,7 @@ pub fn to_value<T>(value: &T) -> Result<Value>
 where
     T: ?Sized + Serialize,
 {
-    value.serialize(crate::ser::ValueSerializer)
+    value.serialize(crate::ser::ValueSerializer::new())
 }
 
 /// Serialize any `T: Serialize` to a writer in TOON format.
@@ -335,10 +508,339 @@ pub fn from_str<'a, T>(s: &'a str) -> Result<T>
 where
     T: Deserialize<'a>,
 {
+    trace::span!("parse", bytes = s.len());
     let mut deserializer = Deserializer::from_str(s);
     T::deserialize(&mut deserializer)
 }
 
+/// Deserialize an instance of type `T` from a string of TOON text with custom options.
+///
+/// Use [`ToonOptions::with_lenient`] to accept slightly malformed input.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{from_str_with_options, ToonOptions};
+///
+/// let options = ToonOptions::new().with_lenient(true);
+/// let nums: Vec<i32> = from_str_with_options("[2]: 1,2,3", options).unwrap();
+/// assert_eq!(nums, vec![1, 2, 3]);
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the input is not valid TOON format or cannot be deserialized to type `T`.
+#[must_use = "this returns the result of the operation, errors must be handled"]
+pub fn from_str_with_options<'a, T>(s: &'a str, options: ToonOptions) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    trace::span!("parse", bytes = s.len());
+    let mut deserializer = Deserializer::with_options(s, options);
+    T::deserialize(&mut deserializer)
+}
+
+/// Deserialize an instance of type `T` from a string of TOON text, along with
+/// the [`Warning`]s recorded while parsing it.
+///
+/// Warnings describe issues that did not stop the parse, such as a repeated
+/// key whose earlier value was dropped or an unknown escape kept as written.
+/// They are returned whether or not deserialization succeeds. For lenient
+/// parsing, whose repairs are reported as warnings too, use
+/// [`Deserializer::with_options`] and [`Deserializer::warnings`].
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{from_str_with_warnings, WarningKind};
+/// use std::collections::BTreeMap;
+///
+/// let (result, warnings) = from_str_with_warnings::<BTreeMap<String, String>>(
+///     "name: Ada\nname: \"Grace\\q\"",
+/// );
+/// assert_eq!(result.unwrap()["name"], "Grace\\q");
+/// let kinds: Vec<_> = warnings.iter().map(|w| w.kind).collect();
+/// assert_eq!(kinds, [WarningKind::UnknownEscape, WarningKind::DuplicateKey]);
+/// ```
+pub fn from_str_with_warnings<'a, T>(s: &'a str) -> (Result<T>, Vec<Warning>)
+where
+    T: Deserialize<'a>,
+{
+    trace::span!("parse", bytes = s.len());
+    let mut deserializer = Deserializer::from_str(s);
+    let result = T::deserialize(&mut deserializer);
+    (result, deserializer.into_warnings())
+}
+
+/// Deserialize an instance of type `T` from the document at the start of a
+/// string, returning it with the rest of the input.
+///
+/// [`from_str`] rejects anything after the document with
+/// [`Error::TrailingContent`]; this stops there instead, as when a model
+/// follows its answer with commentary. The rest starts at the first line that
+/// is not part of the document, and is empty if the whole input was read.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::from_str_with_remainder;
+/// use std::collections::BTreeMap;
+///
+/// let (scores, rest) = from_str_with_remainder::<BTreeMap<String, u32>>(
+///     "alice: 3\nbob: 5\n\nLet me know if you need more.",
+/// )
+/// .unwrap();
+/// assert_eq!(scores["bob"], 5);
+/// assert_eq!(rest, "Let me know if you need more.");
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the document is not valid TOON format or cannot be
+/// deserialized to type `T`.
+pub fn from_str_with_remainder<'a, T>(s: &'a str) -> Result<(T, &'a str)>
+where
+    T: Deserialize<'a>,
+{
+    trace::span!("parse", bytes = s.len());
+    let mut deserializer = Deserializer::from_str(s).with_trailing_content();
+    let value = T::deserialize(&mut deserializer)?;
+    Ok((value, deserializer.remainder()))
+}
+
+/// Deserialize an instance of type `T` from a document that may have been cut
+/// off, as when a model stops at its output token limit in the middle of a
+/// table.
+///
+/// A document that parses as it is is returned whole. Otherwise the line it
+/// ends in, which may be incomplete, is dropped, and the text before it is
+/// read with arrays and tables allowed to hold fewer items than declared.
+/// The result is a [`Partial`] with everything read so far and the byte
+/// offset the text was cut at, so that generation can continue from there.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde::Deserialize;
+/// use serde_toon::from_str_partial;
+///
+/// #[derive(Deserialize, Debug, PartialEq)]
+/// struct Row { id: u32, name: String }
+///
+/// #[derive(Deserialize, Debug, PartialEq)]
+/// struct Answer { rows: Vec<Row> }
+///
+/// let text = "rows[3]{id,name}:\n  1,Ada\n  2,Bo";
+/// let partial = from_str_partial::<Answer>(text).unwrap();
+/// assert!(!partial.complete);
+/// assert_eq!(partial.value.rows, [Row { id: 1, name: "Ada".into() }]);
+/// assert_eq!(&text[partial.offset..], "  2,Bo");
+///
+/// let partial = from_str_partial::<Answer>("rows[1]{id,name}:\n  1,Ada").unwrap();
+/// assert!(partial.complete);
+/// ```
+///
+/// # Errors
+///
+/// Returns the error from parsing the whole document if the text before its
+/// last line is not valid TOON either, has problems other than missing
+/// items, or cannot be deserialized to type `T`.
+pub fn from_str_partial<'a, T>(s: &'a str) -> Result<Partial<T>>
+where
+    T: Deserialize<'a>,
+{
+    let error = match from_str(s) {
+        Ok(value) => {
+            return Ok(Partial {
+                value,
+                offset: s.len(),
+                complete: true,
+                warnings: Vec::new(),
+            })
+        }
+        Err(error) => error,
+    };
+
+    let offset = if s.ends_with('\n') {
+        s.len()
+    } else {
+        s.rfind('\n').map_or(0, |i| i + 1)
+    };
+    let mut deserializer =
+        Deserializer::with_options(&s[..offset], ToonOptions::new().with_lenient(true));
+    let Ok(value) = T::deserialize(&mut deserializer) else {
+        return Err(error);
+    };
+    let warnings = deserializer.into_warnings();
+    if warnings
+        .iter()
+        .any(|warning| warning.kind != WarningKind::LengthMismatch)
+    {
+        return Err(error);
+    }
+    Ok(Partial {
+        value,
+        offset,
+        complete: false,
+        warnings,
+    })
+}
+
+/// Deserialize an instance of type `T` from TOON text that may be hostile, such
+/// as user uploads.
+///
+/// The input is parsed with [`ToonOptions::untrusted`], so deeply nested or
+/// oversized documents are rejected with [`Error::LimitExceeded`]. Parsing
+/// never panics, whatever the input: it returns `Ok` or an error. The
+/// targets in the repository's `fuzz/` directory check this.
+/// (`T`'s own `Deserialize` implementation may still panic.)
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{from_str_untrusted, Error, Value};
+///
+/// let nums: Vec<u32> = from_str_untrusted("[3]: 1,2,3").unwrap();
+/// assert_eq!(nums, [1, 2, 3]);
+///
+/// let bomb: String = (0..1000).map(|i| format!("{}k:\n", "  ".repeat(i))).collect();
+/// let err = from_str_untrusted::<Value>(&bomb).unwrap_err();
+/// assert!(matches!(err, Error::LimitExceeded { .. }));
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the input is not valid TOON format, exceeds a limit,
+/// or cannot be deserialized to type `T`.
+#[must_use = "this returns the result of the operation, errors must be handled"]
+pub fn from_str_untrusted<'a, T>(s: &'a str) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    from_str_with_options(s, ToonOptions::untrusted())
+}
+
+/// Deserialize an instance of type `T` from TOON or JSON text, whichever the
+/// input is.
+///
+/// Models asked for TOON sometimes answer in JSON. Input that starts with
+/// `{`, or with a `[` that does not open a TOON array header, is parsed as
+/// JSON with `serde_json`, after any leading whitespace and a ```` ```json ````
+/// (or untagged) code fence around it. Anything else is parsed as TOON.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{from_str_any, Value};
+///
+/// let toon: Vec<u32> = from_str_any("[3]: 1,2,3").unwrap();
+/// let json: Vec<u32> = from_str_any("[1, 2, 3]").unwrap();
+/// let fenced: Vec<u32> = from_str_any("```json\n[1, 2, 3]\n```").unwrap();
+/// assert_eq!((&toon, &json), (&fenced, &fenced));
+///
+/// let err = from_str_any::<Value>("\n{\"a\": 1,}").unwrap_err();
+/// assert!(err.to_string().starts_with("Syntax error at line 2, column 9:"), "{}", err);
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the input is neither valid TOON nor valid JSON, or
+/// cannot be deserialized to type `T`. Positions in JSON syntax errors are
+/// within `s`.
+#[cfg(feature = "json")]
+#[must_use = "this returns the result of the operation, errors must be handled"]
+pub fn from_str_any<'a, T>(s: &'a str) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    from_str_any_with_options(s, ToonOptions::default())
+}
+
+/// Deserialize an instance of type `T` from TOON or JSON text with custom
+/// options for TOON input. See [`from_str_any`].
+///
+/// # Errors
+///
+/// Returns an error if the input is neither valid TOON nor valid JSON, or
+/// cannot be deserialized to type `T`.
+#[cfg(feature = "json")]
+#[must_use = "this returns the result of the operation, errors must be handled"]
+pub fn from_str_any_with_options<'a, T>(s: &'a str, options: ToonOptions) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    match json::sniff(s) {
+        Some(range) => {
+            trace::span!("parse", bytes = s.len(), format = "json");
+            serde_json::from_str(&s[range.clone()])
+                .map_err(|err| json::convert_error(err, s, range.start))
+        }
+        None => from_str_with_options(s, options),
+    }
+}
+
+/// Best-effort recovery of a `Value` from malformed TOON, such as LLM output.
+///
+/// Parses `input` in lenient mode and returns the recovered value together with
+/// a [`Warning`] describing each fix that was applied (array length
+/// mismatches, trailing prose, unterminated strings). A Markdown code
+/// fence around the document is skipped.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{repair, WarningKind};
+///
+/// let input = "tags: [3]: a,b\nname: \"Widget\nHope this helps!";
+/// let (value, warnings) = repair(input).unwrap();
+///
+/// let obj = value.as_object().unwrap();
+/// assert_eq!(obj.get("name").and_then(|v| v.as_str()), Some("Widget"));
+/// assert_eq!(warnings.len(), 3);
+/// assert_eq!(warnings[0].kind, WarningKind::LengthMismatch);
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the input is too broken to recover a value.
+#[must_use = "this returns the result of the operation, errors must be handled"]
+pub fn repair(input: &str) -> Result<(Value, Vec<Warning>)> {
+    let options = ToonOptions::new()
+        .with_lenient(true)
+        .with_strip_code_fences(true);
+    trace::span!("parse", bytes = input.len());
+    let mut deserializer = Deserializer::with_options(input, options);
+    let value = deserializer.parse_root()?;
+    Ok((value, deserializer.into_warnings()))
+}
+
+/// Parses `input` into a `Value` along with the location of every value in it.
+///
+/// See the [`span`] module for what each span covers.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::from_str_spanned;
+///
+/// let input = "user:\n  name: Ada\n  tags: [2]: x,y";
+/// let (_, spans) = from_str_spanned(input).unwrap();
+///
+/// assert_eq!(&input[spans.find("user.tags[1]").unwrap().range()], "y");
+/// assert_eq!(spans.find("user.name").unwrap().line, 2);
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the input is not valid TOON format.
+#[must_use = "this returns the result of the operation, errors must be handled"]
+pub fn from_str_spanned(input: &str) -> Result<(Value, Spans)> {
+    trace::span!("parse", bytes = input.len());
+    let mut deserializer = Deserializer::from_str(input).with_spans();
+    let value = deserializer.parse_root()?;
+    Ok((value, deserializer.into_spans().unwrap_or_default()))
+}
+
 /// Deserialize an instance of type `T` from an I/O stream of TOON.
 ///
 /// # Examples
@@ -367,11 +869,11 @@ where
     R: io::Read,
     T: for<'de> Deserialize<'de>,
 {
-    let mut string = String::new();
+    let mut bytes = Vec::new();
     reader
-        .read_to_string(&mut string)
+        .read_to_end(&mut bytes)
         .map_err(|e| Error::io(&e.to_string()))?;
-    from_str(&string)
+    from_slice(&bytes)
 }
 
 /// Deserialize an instance of type `T` from bytes of TOON text.
@@ -390,17 +892,53 @@ where
 /// assert_eq!(point, Point { x: 1, y: 2 });
 /// ```
 ///
+/// The whole input is validated as UTF-8 before parsing starts, then parsed
+/// as by [`from_str`]. This accepts a `Vec<u8>`, or a `bytes::Bytes` through
+/// its `Deref`.
+///
 /// # Errors
 ///
-/// Returns an error if the bytes are not valid UTF-8, not valid TOON format,
-/// or cannot be deserialized to type `T`.
+/// Returns an error if the bytes are not valid UTF-8 (reported as
+/// [`Error::Syntax`] at the offending line and column), not valid TOON
+/// format, or cannot be deserialized to type `T`.
 #[must_use = "this returns the result of the operation, errors must be handled"]
 pub fn from_slice<'a, T>(v: &'a [u8]) -> Result<T>
 where
     T: Deserialize<'a>,
 {
-    let s = std::str::from_utf8(v).map_err(|e| Error::custom(e.to_string()))?;
-    from_str(s)
+    trace::span!("parse", bytes = v.len());
+    let mut deserializer = Deserializer::from_slice(v)?;
+    T::deserialize(&mut deserializer)
+}
+
+/// Deserialize an instance of type `T` from bytes of TOON text with custom
+/// options.
+///
+/// With [`ToonOptions::max_document_size`] set, oversized input is rejected
+/// before any of it is read.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{from_slice_with_options, Error, ToonOptions};
+///
+/// let options = ToonOptions::new().with_max_document_size(4);
+/// let result: Result<Vec<u32>, _> = from_slice_with_options(b"[3]: 1,2,3", options);
+/// assert!(matches!(result, Err(Error::LimitExceeded { .. })));
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the bytes are too large, not valid UTF-8, not valid
+/// TOON format, or cannot be deserialized to type `T`.
+#[must_use = "this returns the result of the operation, errors must be handled"]
+pub fn from_slice_with_options<'a, T>(v: &'a [u8], options: ToonOptions) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    trace::span!("parse", bytes = v.len());
+    let mut deserializer = Deserializer::from_slice_with_options(v, options)?;
+    T::deserialize(&mut deserializer)
 }
 
 #[cfg(test)]
@@ -497,4 +1035,32 @@ mod tests {
         let user_back: User = from_str(&toon).unwrap();
         assert_eq!(user, user_back);
     }
+
+    #[test]
+    fn test_from_bytes() {
+        let point: Point = from_slice(b"x: 1\ny: 2").unwrap();
+        assert_eq!(point, Point { x: 1, y: 2 });
+        let point: Point = from_reader(&b"x: 3\ny: 4"[..]).unwrap();
+        assert_eq!(point, Point { x: 3, y: 4 });
+
+        // Invalid UTF-8 is located by character, after valid multibyte text
+        let err = from_slice::<Point>(b"x: 1\ny: \"\xc3\xa9\xc3\"").unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Syntax {
+                line: 2,
+                col: 6,
+                ..
+            }
+        ));
+        let err = from_reader::<_, Point>(&b"\xff"[..]).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Syntax {
+                line: 1,
+                col: 1,
+                ..
+            }
+        ));
+    }
 }
@@ -0,0 +1,422 @@
+//! Chat transcripts as TOON.
+//!
+//! A [`Transcript`] is a list of [`Message`]s, each a `role` and its
+//! `content`, written as one `messages` field:
+//!
+//! - when every message is short and on one line, the list is a table with
+//!   one row per message, which is what TOON saves the most tokens on
+//! - otherwise each message is a list item, and content that is long or
+//!   spans lines is folded into a block string, so that it reads as written
+//!   instead of as one line of `\n` escapes
+//!
+//! [`Transcript::from_toon`] reads either form back.
+//!
+//! When a model's answer does not parse, [`from_str_or_explain`] returns a
+//! [`ModelFeedback`] whose text says what is wrong in plain words and shows
+//! the shape the answer should have, ready to be sent back to the model to
+//! correct itself.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde_toon::llm::{Message, Transcript};
+//!
+//! let mut transcript = Transcript::new();
+//! transcript.push(Message::system("Answer in TOON."));
+//! transcript.push(Message::user("List two colors."));
+//! transcript.push(Message::assistant("colors: [2]: red,blue"));
+//!
+//! let toon = transcript.to_toon();
+//! assert_eq!(
+//!     toon,
+//!     "messages: [3]{role,content}:\n  system,Answer in TOON.\n  user,List two colors.\n  \
+//!      assistant,\"colors: [2]: red,blue\""
+//! );
+//! assert_eq!(Transcript::from_toon(&toon).unwrap(), transcript);
+//! ```
+
+use crate::path::Path;
+use crate::{describe, from_str, Error, Result, ToonMap, ToonOptions, Value};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The number of characters beyond which [`Transcript::to_toon`] writes
+/// content as a block string.
+pub const BLOCK_CONTENT_CHARS: usize = 80;
+
+/// One message of a chat: who sent it and what it says.
+///
+/// The role is a string, such as `system`, `user`, `assistant` or `tool`, so
+/// that the roles of any chat API fit.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Message {
+    pub role: String,
+    pub content: String,
+}
+
+impl Message {
+    /// Creates a message with the given role.
+    pub fn new(role: impl Into<String>, content: impl Into<String>) -> Self {
+        Message {
+            role: role.into(),
+            content: content.into(),
+        }
+    }
+
+    /// Creates a `system` message.
+    pub fn system(content: impl Into<String>) -> Self {
+        Message::new("system", content)
+    }
+
+    /// Creates a `user` message.
+    pub fn user(content: impl Into<String>) -> Self {
+        Message::new("user", content)
+    }
+
+    /// Creates an `assistant` message.
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Message::new("assistant", content)
+    }
+}
+
+/// The messages of a chat, in order. See the [module documentation](self)
+/// for how it is written.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Transcript {
+    pub messages: Vec<Message>,
+}
+
+impl Transcript {
+    /// Creates an empty transcript.
+    #[must_use]
+    pub fn new() -> Self {
+        Transcript::default()
+    }
+
+    /// Appends a message.
+    pub fn push(&mut self, message: Message) {
+        self.messages.push(message);
+    }
+
+    /// Writes the transcript as TOON, with content that spans lines or is
+    /// longer than [`BLOCK_CONTENT_CHARS`] as block strings.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::llm::{Message, Transcript};
+    ///
+    /// let transcript: Transcript = vec![
+    ///     Message::user("Summarize:\nThe cat sat on the mat."),
+    ///     Message::assistant("A cat sat."),
+    /// ]
+    /// .into();
+    /// assert_eq!(
+    ///     transcript.to_toon(),
+    ///     "messages: [2]:\n  - role: user\n    content: |\n      Summarize:\n      \
+    ///      The cat sat on the mat.\n  - role: assistant\n    content: A cat sat."
+    /// );
+    /// ```
+    #[must_use]
+    pub fn to_toon(&self) -> String {
+        self.to_toon_with_options(&ToonOptions::new().with_block_strings(BLOCK_CONTENT_CHARS))
+    }
+
+    /// Writes the transcript as TOON with `options`. Content is folded into
+    /// block strings only if [`ToonOptions::block_strings`] is set.
+    #[must_use]
+    pub fn to_toon_with_options(&self, options: &ToonOptions) -> String {
+        let messages = self
+            .messages
+            .iter()
+            .map(|message| {
+                let mut fields = ToonMap::new();
+                fields.insert("role".to_string(), Value::from(message.role.as_str()));
+                fields.insert("content".to_string(), Value::from(message.content.as_str()));
+                Value::Object(fields)
+            })
+            .collect();
+        let mut document = ToonMap::new();
+        document.insert("messages".to_string(), Value::Array(messages));
+        Value::Object(document).to_toon_string(options)
+    }
+
+    /// Reads a transcript written by [`to_toon`](Transcript::to_toon), as a
+    /// table or a list.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `text` is not valid TOON or not a transcript.
+    pub fn from_toon(text: &str) -> Result<Transcript> {
+        from_str(text)
+    }
+}
+
+impl From<Vec<Message>> for Transcript {
+    fn from(messages: Vec<Message>) -> Self {
+        Transcript { messages }
+    }
+}
+
+impl FromIterator<Message> for Transcript {
+    fn from_iter<I: IntoIterator<Item = Message>>(iter: I) -> Self {
+        Transcript {
+            messages: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Extend<Message> for Transcript {
+    fn extend<I: IntoIterator<Item = Message>>(&mut self, iter: I) {
+        self.messages.extend(iter);
+    }
+}
+
+/// Parses a model's answer as a `T`, or explains what is wrong with it.
+///
+/// The error is a [`ModelFeedback`], whose `Display` is a message for the
+/// model: what is wrong, where, and a skeleton of the document `T` expects
+/// (from [`describe`](fn@crate::describe)). Send it back as the next user
+/// message to have the model correct its answer.
+///
+/// # Errors
+///
+/// Returns a [`ModelFeedback`] wrapping the parse error if `text` is not
+/// valid TOON or does not fit `T`.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde::Deserialize;
+/// use serde_toon::llm::from_str_or_explain;
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Item { sku: String, qty: u32 }
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Order { id: u64, items: Vec<Item> }
+///
+/// let feedback = from_str_or_explain::<Order>("id: 7\nitems[1]{sku,qty}:\n  A1,many").unwrap_err();
+/// assert_eq!(
+///     feedback.to_string(),
+///     "Your answer could not be read: the value at items[0].qty is wrong: invalid type: \
+///      string \"many\", expected u32.\n\n\
+///      Reply with only the corrected TOON document, in this shape:\n\n\
+///      id: <int>\nitems: [N]{sku,qty}:\n  <string>,<int>"
+/// );
+/// ```
+pub fn from_str_or_explain<T: DeserializeOwned>(
+    text: &str,
+) -> std::result::Result<T, ModelFeedback> {
+    from_str(text).map_err(|error| ModelFeedback {
+        error,
+        expected: describe::<T>().ok(),
+    })
+}
+
+/// A parse error explained for the model that wrote the document, returned
+/// by [`from_str_or_explain`].
+///
+/// Displays as a message to send back to the model: the
+/// [`explanation`](ModelFeedback::explanation) of the error, then the shape
+/// of the expected document if it is known.
+#[derive(Clone, Debug)]
+pub struct ModelFeedback {
+    /// The error the document failed with.
+    pub error: Error,
+    /// A skeleton of the expected document, if the target type could be
+    /// described.
+    pub expected: Option<String>,
+}
+
+impl ModelFeedback {
+    /// Describes the error in a sentence, without the source excerpts and
+    /// help of its own message.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::llm::from_str_or_explain;
+    /// use serde_toon::Value;
+    ///
+    /// let feedback = from_str_or_explain::<Value>("tags: [3]: a,b").unwrap_err();
+    /// assert!(feedback.explanation().starts_with("line 1 is not valid TOON: "));
+    /// assert_eq!(feedback.expected, None);
+    /// ```
+    #[must_use]
+    pub fn explanation(&self) -> String {
+        explain(&self.error)
+    }
+}
+
+fn explain(error: &Error) -> String {
+    match error {
+        Error::Syntax { line, msg, .. } | Error::InvalidFormat { line, msg, .. } => {
+            format!("line {} is not valid TOON: {}", line, msg)
+        }
+        Error::TypeMismatch {
+            line,
+            expected,
+            found,
+            ..
+        } => format!("line {} has {} where {} is expected", line, found, expected),
+        Error::IndentationError {
+            line,
+            expected,
+            found,
+            ..
+        } => format!(
+            "line {} is indented by {} spaces where {} are expected",
+            line, found, expected
+        ),
+        Error::UnexpectedEof { expected, .. } => {
+            format!("the document ends early, before {}", expected)
+        }
+        Error::DuplicateKey { key, line, .. } => {
+            format!("the key `{}` appears again on line {}", key, line)
+        }
+        Error::TrailingContent { line, .. } => format!(
+            "there is more text after the document, on line {}; send the document alone",
+            line
+        ),
+        Error::UnknownField {
+            field,
+            path,
+            expected,
+        } => format!(
+            "the field `{}` {} is not allowed; the allowed fields are {}",
+            field,
+            place(path),
+            expected
+        ),
+        Error::MissingField { field, path } => {
+            format!("the required field `{}` is missing {}", field, place(path))
+        }
+        Error::MissingColumn {
+            column,
+            path,
+            headers,
+        } => format!(
+            "the table {} has no `{}` column; its columns are {}",
+            place(path),
+            column,
+            headers
+        ),
+        Error::Data { msg, path } => format!("the value at {} is wrong: {}", path, msg),
+        Error::File { error, .. } => explain(error),
+        _ => error.to_string(),
+    }
+}
+
+/// Where a path is, in words.
+fn place(path: &Path) -> String {
+    if path.is_root() {
+        "at the top level".to_string()
+    } else {
+        format!("in {}", path)
+    }
+}
+
+impl fmt::Display for ModelFeedback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let explanation = self.explanation();
+        write!(
+            f,
+            "Your answer could not be read: {}.",
+            explanation.trim_end_matches('.')
+        )?;
+        if let Some(expected) = &self.expected {
+            write!(
+                f,
+                "\n\nReply with only the corrected TOON document, in this shape:\n\n{}",
+                expected
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ModelFeedback {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_tables_and_lists() {
+        let long = "word ".repeat(20).trim_end().to_string();
+        let transcript: Transcript = [
+            Message::system("Be brief."),
+            Message::new("tool", "  indented,\n\"quoted\"\n"),
+            Message::user(long.clone()),
+            Message::assistant(""),
+        ]
+        .into_iter()
+        .collect();
+
+        let toon = transcript.to_toon();
+        assert_eq!(
+            toon,
+            format!(
+                "messages: [4]:\n  - role: system\n    content: Be brief.\n  - role: tool\n    \
+                 content: \"  indented,\\n\\\"quoted\\\"\\n\"\n  - role: user\n    content: |\n      \
+                 {}\n  - role: assistant\n    content: \"\"",
+                long
+            )
+        );
+        assert_eq!(Transcript::from_toon(&toon).unwrap(), transcript);
+
+        let table = transcript.to_toon_with_options(&ToonOptions::default());
+        assert!(
+            table.starts_with("messages: [4]{role,content}:\n"),
+            "{}",
+            table
+        );
+        assert_eq!(Transcript::from_toon(&table).unwrap(), transcript);
+        assert_eq!(Transcript::new().to_toon(), "messages: [0]:");
+        assert_eq!(
+            Transcript::from_toon("messages: [0]:").unwrap(),
+            Transcript::new()
+        );
+        assert!(Transcript::from_toon("messages: [1]{role}:\n  user").is_err());
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Answer {
+        city: String,
+        days: Vec<u8>,
+    }
+
+    #[test]
+    fn explains_errors_for_the_model() {
+        let explain = |text: &str| from_str_or_explain::<Answer>(text).unwrap_err();
+
+        let feedback = explain("days: [1]: 3");
+        assert_eq!(
+            feedback.to_string(),
+            "Your answer could not be read: the required field `city` is missing at the top \
+             level.\n\nReply with only the corrected TOON document, in this shape:\n\n\
+             city: <string>\ndays: [N]: <int>,..."
+        );
+        assert!(matches!(feedback.error, Error::MissingField { .. }));
+
+        assert_eq!(
+            explain("city: Oslo\ndays: [2]: 1").explanation(),
+            "line 2 is not valid TOON: array declared 2 items but has 1"
+        );
+        assert_eq!(
+            explain("city: Oslo\ndays: [1]: 300").explanation(),
+            "the value at days[0] is wrong: invalid value: integer `300`, expected u8"
+        );
+        assert!(explain("city: Oslo\ndays: [0]:\nThanks!")
+            .explanation()
+            .starts_with("there is more text after the document, on line 3"));
+        assert!(from_str_or_explain::<Answer>("city: Oslo\ndays: [1]: 2").is_ok());
+    }
+}
@@ -1,56 +1,240 @@
+/// Constructs a [`Value`](crate::Value) from a JSON-like literal.
+///
+/// Keys and values may be arbitrary Rust expressions: keys are converted with
+/// `ToString`, values with [`to_value`](crate::to_value) (falling back to
+/// `null` if they cannot be serialized). Arrays and objects nest, and a few
+/// extras are supported:
+///
+/// - `items..` inside an array splices in every element of a collection
+///   (iterated by reference, like `for item in &items`)
+/// - `..other` inside an object copies the fields of another object
+/// - `"key": value if condition` includes the field only when the condition holds
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{toon, to_string};
+///
+/// let key = "role";
+/// let tags = vec!["a", "b"];
+/// let admin = false;
+/// let defaults = toon!({ "active": true });
+///
+/// let value = toon!({
+///     "id": 1 + 1,
+///     key: "editor",
+///     "tags": ["first", tags.., "last"],
+///     "admin": true if admin,
+///     ..defaults
+/// });
+///
+/// assert_eq!(
+///     to_string(&value).unwrap(),
+///     "id: 2\nrole: editor\ntags: [4]: first,a,b,last\nactive: true"
+/// );
+/// ```
 #[macro_export]
 macro_rules! toon {
-    // Handle null
+    // ---- Array elements: munch tokens up to each `,` ----
+
+    (@array $array:ident ()) => {};
+
+    (@array $array:ident ($($elem:tt)+) .. $(, $($rest:tt)*)?) => {
+        $array.extend(
+            ::std::iter::IntoIterator::into_iter(&($($elem)+))
+                .map(|item| $crate::to_value(&item).unwrap_or($crate::Value::Null)),
+        );
+        $crate::toon!(@array $array () $($($rest)*)?);
+    };
+
+    (@array $array:ident ($($elem:tt)+) , $($rest:tt)*) => {
+        $array.push($crate::toon!($($elem)+));
+        $crate::toon!(@array $array () $($rest)*);
+    };
+
+    (@array $array:ident ($($elem:tt)+)) => {
+        $array.push($crate::toon!($($elem)+));
+    };
+
+    (@array $array:ident ($($elem:tt)*) $next:tt $($rest:tt)*) => {
+        $crate::toon!(@array $array ($($elem)* $next) $($rest)*);
+    };
+
+    // ---- Object keys: munch tokens up to `:`, or a `..` spread ----
+
+    (@object $object:ident ()) => {};
+
+    (@object $object:ident () .. $($rest:tt)*) => {
+        $crate::toon!(@spread $object () $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)+) : $($rest:tt)*) => {
+        $crate::toon!(@value $object ($($key)+) () $($rest)*);
+    };
+
+    (@object $object:ident ($($key:tt)*) $next:tt $($rest:tt)*) => {
+        $crate::toon!(@object $object ($($key)* $next) $($rest)*);
+    };
+
+    // ---- Object values: munch tokens up to `,` or a trailing `if` ----
+
+    (@value $object:ident ($($key:tt)+) ($($value:tt)+) if $($rest:tt)*) => {
+        $crate::toon!(@cond $object ($($key)+) ($($value)+) () $($rest)*);
+    };
+
+    (@value $object:ident ($($key:tt)+) ($($value:tt)+) , $($rest:tt)*) => {
+        $crate::toon!(@insert $object ($($key)+) ($($value)+));
+        $crate::toon!(@object $object () $($rest)*);
+    };
+
+    (@value $object:ident ($($key:tt)+) ($($value:tt)+)) => {
+        $crate::toon!(@insert $object ($($key)+) ($($value)+));
+    };
+
+    (@value $object:ident ($($key:tt)+) ($($value:tt)*) $next:tt $($rest:tt)*) => {
+        $crate::toon!(@value $object ($($key)+) ($($value)* $next) $($rest)*);
+    };
+
+    // ---- Conditional fields: munch the condition up to `,` ----
+
+    (@cond $object:ident ($($key:tt)+) ($($value:tt)+) ($($cond:tt)+) , $($rest:tt)*) => {
+        if $($cond)+ {
+            $crate::toon!(@insert $object ($($key)+) ($($value)+));
+        }
+        $crate::toon!(@object $object () $($rest)*);
+    };
+
+    (@cond $object:ident ($($key:tt)+) ($($value:tt)+) ($($cond:tt)+)) => {
+        if $($cond)+ {
+            $crate::toon!(@insert $object ($($key)+) ($($value)+));
+        }
+    };
+
+    (@cond $object:ident ($($key:tt)+) ($($value:tt)+) ($($cond:tt)*) $next:tt $($rest:tt)*) => {
+        $crate::toon!(@cond $object ($($key)+) ($($value)+) ($($cond)* $next) $($rest)*);
+    };
+
+    // ---- Object spreads: munch the expression up to `,` ----
+
+    (@spread $object:ident ($($other:tt)+) , $($rest:tt)*) => {
+        $crate::toon!(@spread $object ($($other)+));
+        $crate::toon!(@object $object () $($rest)*);
+    };
+
+    (@spread $object:ident ($($other:tt)+)) => {
+        if let $crate::Value::Object(fields) = $crate::toon!($($other)+) {
+            $object.extend(fields);
+        }
+    };
+
+    (@spread $object:ident ($($other:tt)*) $next:tt $($rest:tt)*) => {
+        $crate::toon!(@spread $object ($($other)* $next) $($rest)*);
+    };
+
+    (@insert $object:ident ($($key:tt)+) ($($value:tt)+)) => {
+        $object.insert(
+            ::std::string::ToString::to_string(&($($key)+)),
+            $crate::toon!($($value)+),
+        );
+    };
+
+    // ---- Entry points ----
+
     (null) => {
         $crate::Value::Null
     };
 
-    // Handle true
     (true) => {
         $crate::Value::Bool(true)
     };
 
-    // Handle false
     (false) => {
         $crate::Value::Bool(false)
     };
 
-    // Handle empty array
     ([]) => {
         $crate::Value::Array(vec![])
     };
 
-    // Handle non-empty array
-    ([ $($elem:tt),* $(,)? ]) => {
-        $crate::Value::Array(vec![$($crate::toon!($elem)),*])
-    };
+    ([ $($tt:tt)+ ]) => {{
+        #[allow(clippy::vec_init_then_push)]
+        let array = {
+            let mut array = ::std::vec::Vec::new();
+            $crate::toon!(@array array () $($tt)+);
+            array
+        };
+        $crate::Value::Array(array)
+    }};
 
-    // Handle empty object
     ({}) => {
         $crate::Value::Object($crate::ToonMap::new())
     };
 
-    // Handle non-empty object
-    ({ $($key:literal : $value:tt),* $(,)? }) => {{
+    ({ $($tt:tt)+ }) => {{
         let mut object = $crate::ToonMap::new();
-        $(
-            object.insert($key.to_string(), $crate::toon!($value));
-        )*
+        $crate::toon!(@object object () $($tt)+);
         $crate::Value::Object(object)
     }};
 
-    // Handle different literal types explicitly
+    // Any other expression
+    ($other:expr) => {
+        $crate::to_value(&$other).unwrap_or($crate::Value::Null)
+    };
+}
+
+/// Constructs a [`Value::Table`](crate::Value::Table) from a header row and row literals.
+///
+/// Headers are identifiers or string literals. Each row is a parenthesized,
+/// comma-separated list of cells written as in [`toon!`](crate::toon), so cells
+/// may be `null`, nested arrays or objects, or arbitrary expressions.
+///
+/// # Panics
+///
+/// Panics if a row does not have exactly one cell per header.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{toon_table, to_string};
+///
+/// let discount = 0.5;
+/// let table = toon_table! {
+///     (id, name, price);
+///     (1, "Widget", 9.99);
+///     (2, "Gadget", 29.0 * discount);
+///     (3, "Gizmo", null);
+/// };
+///
+/// assert_eq!(
+///     to_string(&table).unwrap(),
+///     "[3]{id,name,price}:\n  1,Widget,9.99\n  2,Gadget,14.5\n  3,Gizmo,null"
+/// );
+/// ```
+#[macro_export]
+macro_rules! toon_table {
+    (@header $header:ident) => {
+        stringify!($header)
+    };
 
-    // String literals (quoted)
-    ($s:expr) => {{
-        // This is a fallback for any expression
-        $crate::to_value(&$s).unwrap_or($crate::Value::Null)
-    }};
+    (@header $header:literal) => {
+        $header
+    };
+
+    (( $($header:tt),+ $(,)? ) $(; ( $($row:tt)* ))* $(;)?) => {
+        $crate::Value::table([$($crate::toon_table!(@header $header)),+])
+            $(
+                .row(match $crate::toon!([$($row)*]) {
+                    $crate::Value::Array(cells) => cells,
+                    _ => unreachable!(),
+                })
+            )*
+            .build()
+    };
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{Number, ToonMap, Value};
+    use crate::{Number, Table, ToonMap, Value};
 
     #[test]
     fn test_toon_macro_primitives() {
@@ -96,4 +280,78 @@ mod tests {
             _ => panic!("Expected object"),
         }
     }
+
+    #[test]
+    fn test_toon_macro_interpolation() {
+        let key = String::from("dynamic");
+        let count = 3;
+        let nested = toon!({ "inner": [count, -count] });
+
+        let obj = toon!({
+            key: count * 2,
+            "nested": nested,
+            "literal": { "list": [count, { "x": count }] },
+        });
+
+        let map = obj.as_object().unwrap();
+        assert_eq!(map.get("dynamic"), Some(&Value::from(6)));
+        assert_eq!(map.get("nested"), Some(&toon!({ "inner": [3, -3] })));
+        assert_eq!(
+            map.get("literal"),
+            Some(&toon!({ "list": [3, { "x": 3 }] }))
+        );
+    }
+
+    #[test]
+    fn test_toon_macro_spreads_and_conditions() {
+        let items = vec![2, 3];
+        let base = toon!({ "a": 1, "b": 2 });
+        let flag = true;
+
+        assert_eq!(toon!([1, items.., 4]), toon!([1, 2, 3, 4]));
+        assert_eq!(toon!([items..]), toon!([2, 3]));
+
+        let obj = toon!({
+            ..base,
+            "b": 20,
+            "c": 3 if flag,
+            "d": 4 if !flag && items.is_empty(),
+        });
+        let keys: Vec<_> = obj.as_object().unwrap().keys().cloned().collect();
+        assert_eq!(keys, vec!["a", "b", "c"]);
+        assert_eq!(obj.as_object().unwrap().get("b"), Some(&Value::from(20)));
+    }
+
+    #[test]
+    fn test_toon_table_macro() {
+        let name = "Gadget";
+        let table = toon_table! {
+            (id, "display name", tags);
+            (1, "Widget", ["a", "b"]);
+            (2, name, []);
+        };
+
+        assert_eq!(
+            table,
+            Value::Table(
+                Table::from_parts(
+                    vec!["id", "display name", "tags"],
+                    vec![
+                        vec![Value::from(1), Value::from("Widget"), toon!(["a", "b"])],
+                        vec![Value::from(2), Value::from("Gadget"), toon!([])],
+                    ],
+                )
+                .unwrap()
+            )
+        );
+
+        let empty = toon_table! { (id, name) };
+        assert!(matches!(empty, Value::Table(Table { ref rows, .. }) if rows.is_empty()));
+    }
+
+    #[test]
+    #[should_panic(expected = "table row has 1 cells but the table has 2 columns")]
+    fn test_toon_table_macro_rejects_ragged_rows() {
+        let _ = toon_table! { (id, name); (1); };
+    }
 }
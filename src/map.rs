@@ -1,9 +1,10 @@
 //! Ordered map type for TOON objects.
 //!
 //! This module provides [`ToonMap`], a wrapper around [`IndexMap`] that maintains
-//! insertion order for object fields. This is important for TOON as field order
-//! affects serialization output (fields are typically sorted alphabetically for
-//! deterministic output in tabular format).
+//! insertion order for object fields. Fields are written in this order (unless
+//! [`ToonOptions::with_canonical_key_order`](crate::ToonOptions::with_canonical_key_order)
+//! asks for alphabetical output), and parsing preserves document order, so a
+//! map survives a serialization round trip with its order intact.
 //!
 //! ## Why IndexMap?
 //!
@@ -13,6 +14,17 @@
 //! - **Iteration order**: Fields are iterated in insertion order
 //! - **Compatibility**: Easier testing and debugging with predictable output
 //!
+//! ## Shared keys
+//!
+//! Keys are [`Key`]s, reference-counted strings that are cheap to clone. When
+//! the parser builds a [`Value`](crate::Value) itself (`str::parse`,
+//! [`repair`](crate::repair), [`from_str_spanned`](crate::from_str_spanned)),
+//! it hands out one `Key` per distinct key in the document, so the keys of a
+//! thousand list items, or the headers of a table and the fields of nested
+//! objects, share one allocation each. Deserializing a `Value` through serde,
+//! as [`from_str`](crate::from_str) does, allocates the keys of each object
+//! separately.
+//!
 //! ## Examples
 //!
 //! ```rust
@@ -27,7 +39,201 @@
 //! ```
 
 use indexmap::IndexMap;
-use std::collections::HashMap;
+use serde::de::{self, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// A key of a [`ToonMap`] or a header of a [`Table`](crate::Table): an
+/// immutable string whose clones share one allocation.
+///
+/// A `Key` derefs to `str` and compares equal to string types, so it can
+/// mostly be used as one.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{Key, Value};
+///
+/// let key = Key::from("id");
+/// assert_eq!(key, "id");
+/// assert_eq!(key.len(), 2);
+///
+/// let value: Value = "[2]:\n  - id: 1\n  - id: 2".parse().unwrap();
+/// let keys: Vec<&Key> = value
+///     .as_array()
+///     .unwrap()
+///     .iter()
+///     .flat_map(|item| item.as_object().unwrap().keys())
+///     .collect();
+/// assert!(Key::ptr_eq(keys[0], keys[1]));
+/// ```
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Key(Arc<str>);
+
+impl Key {
+    /// Returns the key as a string slice.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns `true` if both keys share the same allocation.
+    #[must_use]
+    pub fn ptr_eq(a: &Key, b: &Key) -> bool {
+        Arc::ptr_eq(&a.0, &b.0)
+    }
+}
+
+impl Deref for Key {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Key {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Key {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Hashes as the `str` it holds, so maps keyed by `Key` can be looked up by
+/// `&str`.
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+impl From<&str> for Key {
+    fn from(key: &str) -> Self {
+        Key(Arc::from(key))
+    }
+}
+
+impl From<String> for Key {
+    fn from(key: String) -> Self {
+        Key(Arc::from(key))
+    }
+}
+
+impl From<&String> for Key {
+    fn from(key: &String) -> Self {
+        Key(Arc::from(key.as_str()))
+    }
+}
+
+impl From<&Key> for Key {
+    fn from(key: &Key) -> Self {
+        key.clone()
+    }
+}
+
+impl From<Arc<str>> for Key {
+    fn from(key: Arc<str>) -> Self {
+        Key(key)
+    }
+}
+
+impl From<Key> for String {
+    fn from(key: Key) -> Self {
+        key.0.to_string()
+    }
+}
+
+impl From<Key> for Arc<str> {
+    fn from(key: Key) -> Self {
+        key.0
+    }
+}
+
+macro_rules! impl_key_eq {
+    ($($other:ty),*) => {
+        $(
+            impl PartialEq<$other> for Key {
+                fn eq(&self, other: &$other) -> bool {
+                    *self.0 == **other
+                }
+            }
+
+            impl PartialEq<Key> for $other {
+                fn eq(&self, other: &Key) -> bool {
+                    **self == *other.0
+                }
+            }
+        )*
+    };
+}
+
+impl_key_eq!(&str, String);
+
+impl PartialEq<str> for Key {
+    fn eq(&self, other: &str) -> bool {
+        *self.0 == *other
+    }
+}
+
+impl PartialEq<Key> for str {
+    fn eq(&self, other: &Key) -> bool {
+        *self == *other.0
+    }
+}
+
+impl Serialize for Key {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KeyVisitor;
+
+        impl<'de> Visitor<'de> for KeyVisitor {
+            type Value = Key;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a string key")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Key, E> {
+                Ok(Key::from(value))
+            }
+
+            fn visit_string<E: de::Error>(self, value: String) -> Result<Key, E> {
+                Ok(Key::from(value))
+            }
+        }
+
+        deserializer.deserialize_str(KeyVisitor)
+    }
+}
 
 /// An ordered map of string keys to TOON values.
 ///
@@ -48,7 +254,7 @@ use std::collections::HashMap;
 /// assert_eq!(keys, vec!["first", "second"]);
 /// ```
 #[derive(Debug, Clone, PartialEq)]
-pub struct ToonMap(IndexMap<String, crate::Value>);
+pub struct ToonMap(IndexMap<Key, crate::Value>);
 
 impl ToonMap {
     /// Creates an empty `ToonMap`.
@@ -94,8 +300,8 @@ impl ToonMap {
     /// assert!(map.insert("key".to_string(), Value::from(42)).is_none());
     /// assert!(map.insert("key".to_string(), Value::from(43)).is_some());
     /// ```
-    pub fn insert(&mut self, key: String, value: crate::Value) -> Option<crate::Value> {
-        self.0.insert(key, value)
+    pub fn insert(&mut self, key: impl Into<Key>, value: crate::Value) -> Option<crate::Value> {
+        self.0.insert(key.into(), value)
     }
 
     /// Returns a reference to the value corresponding to the key.
@@ -114,6 +320,86 @@ impl ToonMap {
         self.0.get(key)
     }
 
+    /// Returns a mutable reference to the value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{ToonMap, Value};
+    ///
+    /// let mut map = ToonMap::new();
+    /// map.insert("count".to_string(), Value::from(1));
+    /// if let Some(count) = map.get_mut("count") {
+    ///     *count = Value::from(2);
+    /// }
+    /// assert_eq!(map.get("count").and_then(|v| v.as_i64()), Some(2));
+    /// ```
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut crate::Value> {
+        self.0.get_mut(key)
+    }
+
+    /// Returns `true` if the map contains a value for the key.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{ToonMap, Value};
+    ///
+    /// let mut map = ToonMap::new();
+    /// map.insert("key".to_string(), Value::Null);
+    /// assert!(map.contains_key("key"));
+    /// assert!(!map.contains_key("other"));
+    /// ```
+    #[must_use]
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+
+    /// Removes a key from the map, returning its value if it was present.
+    ///
+    /// The remaining entries keep their relative order, so this is the same as
+    /// [`ToonMap::shift_remove`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{ToonMap, Value};
+    ///
+    /// let mut map: ToonMap = [("a", 1), ("b", 2), ("c", 3)]
+    ///     .into_iter()
+    ///     .map(|(k, v)| (k.to_string(), Value::from(v)))
+    ///     .collect();
+    /// assert_eq!(map.remove("a").and_then(|v| v.as_i64()), Some(1));
+    /// assert_eq!(map.keys().collect::<Vec<_>>(), vec!["b", "c"]);
+    /// ```
+    pub fn remove(&mut self, key: &str) -> Option<crate::Value> {
+        self.shift_remove(key)
+    }
+
+    /// Removes a key from the map by shifting all following entries down,
+    /// preserving insertion order. This is O(n).
+    pub fn shift_remove(&mut self, key: &str) -> Option<crate::Value> {
+        self.0.shift_remove(key)
+    }
+
+    /// Gets the entry for the key for in-place manipulation.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{ToonMap, Value};
+    ///
+    /// let mut map = ToonMap::new();
+    /// map.entry("tags".to_string()).or_insert_with(|| Value::Array(vec![]));
+    /// if let Value::Array(tags) = map.entry("tags".to_string()).or_insert(Value::Null) {
+    ///     tags.push(Value::from("new"));
+    /// }
+    /// assert_eq!(map.get("tags").and_then(|v| v.as_array()).map(|a| a.len()), Some(1));
+    /// ```
+    pub fn entry(&mut self, key: impl Into<Key>) -> indexmap::map::Entry<'_, Key, crate::Value> {
+        self.0.entry(key.into())
+    }
+
     /// Returns the number of elements in the map.
     ///
     /// # Examples
@@ -147,19 +433,95 @@ impl ToonMap {
     }
 
     /// Returns an iterator over the keys of the map, in insertion order.
-    pub fn keys(&self) -> indexmap::map::Keys<'_, String, crate::Value> {
+    pub fn keys(&self) -> indexmap::map::Keys<'_, Key, crate::Value> {
         self.0.keys()
     }
 
     /// Returns an iterator over the values of the map, in insertion order.
-    pub fn values(&self) -> indexmap::map::Values<'_, String, crate::Value> {
+    pub fn values(&self) -> indexmap::map::Values<'_, Key, crate::Value> {
         self.0.values()
     }
 
     /// Returns an iterator over the key-value pairs of the map, in insertion order.
-    pub fn iter(&self) -> indexmap::map::Iter<'_, String, crate::Value> {
+    pub fn iter(&self) -> indexmap::map::Iter<'_, Key, crate::Value> {
         self.0.iter()
     }
+
+    /// Returns an iterator over the key-value pairs of the map with mutable
+    /// values, in insertion order.
+    pub fn iter_mut(&mut self) -> indexmap::map::IterMut<'_, Key, crate::Value> {
+        self.0.iter_mut()
+    }
+
+    /// Retains only the entries for which the predicate returns `true`,
+    /// preserving the order of the kept entries.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{ToonMap, Value};
+    ///
+    /// let mut map = ToonMap::new();
+    /// map.insert("a".to_string(), Value::Null);
+    /// map.insert("b".to_string(), Value::from(1));
+    /// map.retain(|_, v| !v.is_null());
+    /// assert_eq!(map.keys().collect::<Vec<_>>(), vec!["b"]);
+    /// ```
+    pub fn retain<F>(&mut self, keep: F)
+    where
+        F: FnMut(&Key, &mut crate::Value) -> bool,
+    {
+        self.0.retain(keep);
+    }
+
+    /// Sorts the entries alphabetically by key.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{ToonMap, Value};
+    ///
+    /// let mut map = ToonMap::new();
+    /// map.insert("b".to_string(), Value::Null);
+    /// map.insert("a".to_string(), Value::Null);
+    /// map.sort_keys();
+    /// assert_eq!(map.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+    /// ```
+    pub fn sort_keys(&mut self) {
+        self.0.sort_keys();
+    }
+}
+
+impl ToonMap {
+    /// The entries sorted by key, for comparing and hashing maps regardless
+    /// of insertion order.
+    fn sorted_entries(&self) -> Vec<(&Key, &crate::Value)> {
+        let mut entries: Vec<_> = self.0.iter().collect();
+        entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        entries
+    }
+}
+
+impl Eq for ToonMap {}
+
+/// Maps are ordered by their entries sorted by key, so that, as with
+/// equality, insertion order does not matter.
+impl PartialOrd for ToonMap {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ToonMap {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sorted_entries().cmp(&other.sorted_entries())
+    }
+}
+
+impl Hash for ToonMap {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.sorted_entries().hash(state);
+    }
 }
 
 impl Default for ToonMap {
@@ -170,27 +532,108 @@ impl Default for ToonMap {
 
 impl From<HashMap<String, crate::Value>> for ToonMap {
     fn from(map: HashMap<String, crate::Value>) -> Self {
-        ToonMap(map.into_iter().collect())
+        map.into_iter().collect()
+    }
+}
+
+impl From<BTreeMap<String, crate::Value>> for ToonMap {
+    fn from(map: BTreeMap<String, crate::Value>) -> Self {
+        map.into_iter().collect()
     }
 }
 
 impl From<ToonMap> for HashMap<String, crate::Value> {
     fn from(map: ToonMap) -> Self {
-        map.0.into_iter().collect()
+        map.0
+            .into_iter()
+            .map(|(key, value)| (key.into(), value))
+            .collect()
     }
 }
 
 impl IntoIterator for ToonMap {
-    type Item = (String, crate::Value);
-    type IntoIter = indexmap::map::IntoIter<String, crate::Value>;
+    type Item = (Key, crate::Value);
+    type IntoIter = indexmap::map::IntoIter<Key, crate::Value>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.0.into_iter()
     }
 }
 
-impl FromIterator<(String, crate::Value)> for ToonMap {
-    fn from_iter<T: IntoIterator<Item = (String, crate::Value)>>(iter: T) -> Self {
-        ToonMap(IndexMap::from_iter(iter))
+impl<'a> IntoIterator for &'a ToonMap {
+    type Item = (&'a Key, &'a crate::Value);
+    type IntoIter = indexmap::map::Iter<'a, Key, crate::Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut ToonMap {
+    type Item = (&'a Key, &'a mut crate::Value);
+    type IntoIter = indexmap::map::IterMut<'a, Key, crate::Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter_mut()
+    }
+}
+
+impl<K: Into<Key>> Extend<(K, crate::Value)> for ToonMap {
+    fn extend<T: IntoIterator<Item = (K, crate::Value)>>(&mut self, iter: T) {
+        self.0
+            .extend(iter.into_iter().map(|(key, value)| (key.into(), value)));
+    }
+}
+
+impl<K: Into<Key>> FromIterator<(K, crate::Value)> for ToonMap {
+    fn from_iter<T: IntoIterator<Item = (K, crate::Value)>>(iter: T) -> Self {
+        ToonMap(
+            iter.into_iter()
+                .map(|(key, value)| (key.into(), value))
+                .collect(),
+        )
+    }
+}
+
+impl Serialize for ToonMap {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (k, v) in self.iter() {
+            map.serialize_entry(k, v)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for ToonMap {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ToonMapVisitor;
+
+        impl<'de> Visitor<'de> for ToonMapVisitor {
+            type Value = ToonMap;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map with string keys")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut values = ToonMap::with_capacity(map.size_hint().unwrap_or(0));
+                while let Some((key, value)) = map.next_entry::<Key, _>()? {
+                    values.insert(key, value);
+                }
+                Ok(values)
+            }
+        }
+
+        deserializer.deserialize_map(ToonMapVisitor)
     }
 }
@@ -0,0 +1,336 @@
+//! Converting between tables and GitHub-flavored markdown.
+//!
+//! LLMs often answer with markdown tables even when asked for TOON. This
+//! module reads such tables back into [`Table`]s and writes tables (or whole
+//! documents containing them) as markdown for display.
+//!
+//! - [`Table::to_markdown`] renders a table as a markdown table; convert an
+//!   array of objects with [`Table::try_from`] first
+//! - [`Table::from_markdown`] parses the first markdown table in some text
+//! - [`to_markdown_document`] renders a whole document, turning its top-level
+//!   tables into markdown tables and keeping everything else as fenced TOON
+//!
+//! ## Cells
+//!
+//! Empty cells are `null`. `true`, `false`, `null` and numbers are read as
+//! such; anything else is a string. Strings that would otherwise read back as
+//! something else (such as `"42"` or `""`) are written in double quotes, `|`
+//! is escaped as `\|` and line breaks become `<br>`.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde_toon::{to_string, Table};
+//!
+//! let reply = "Here you go:\n\n| id | name  |\n|---:|-------|\n| 1  | Alice |\n| 2  | Bob   |\n";
+//! let table = Table::from_markdown(reply).unwrap();
+//!
+//! assert_eq!(to_string(&table).unwrap(), "[2]{id,name}:\n  1,Alice\n  2,Bob");
+//! assert_eq!(
+//!     table.to_markdown(),
+//!     "| id | name |\n| --- | --- |\n| 1 | Alice |\n| 2 | Bob |"
+//! );
+//! ```
+
+use crate::lexer::{self, Line};
+use crate::ser::can_be_tabular;
+use crate::{to_string, to_value, Error, Key, Result, Table, ToonMap, Value};
+use serde::Serialize;
+
+impl Table {
+    /// Renders the table as a GitHub-flavored markdown table.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{toon, Table};
+    ///
+    /// let users = Table::try_from(toon!([{"id": 1, "name": "A|B"}, {"id": 2, "name": null}])).unwrap();
+    /// assert_eq!(
+    ///     users.to_markdown(),
+    ///     "| id | name |\n| --- | --- |\n| 1 | A\\|B |\n| 2 |  |"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn to_markdown(&self) -> String {
+        render_table(&self.headers, &self.rows)
+    }
+
+    /// Parses the first markdown table in `text`.
+    ///
+    /// Lines before the table and after its last row are ignored, so the table
+    /// may be embedded in prose. Leading and trailing pipes are optional, the
+    /// delimiter row may carry alignment colons, and rows with missing cells
+    /// are padded with `null`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{Table, Value};
+    ///
+    /// let table = Table::from_markdown("sku | qty\n:-- | --:\nA1 | 2\nB2 |").unwrap();
+    /// assert_eq!(table.column("qty").unwrap(), vec![&Value::from(2), &Value::Null]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `text` contains no markdown table, or if a row has
+    /// more cells than the table has columns.
+    pub fn from_markdown(text: &str) -> Result<Table> {
+        let lines: Vec<Line<'_>> = lexer::lines(text).collect();
+        let start = lines
+            .windows(2)
+            .position(|pair| pair[0].text.contains('|') && is_delimiter_row(pair[1].text))
+            .ok_or_else(|| Error::custom("no markdown table found"))?;
+
+        let headers = split_row(lines[start].text);
+        let mut rows = Vec::new();
+        for line in lines[start + 2..]
+            .iter()
+            .take_while(|line| line.text.contains('|'))
+        {
+            let cells = split_row(line.text);
+            if cells.len() > headers.len() {
+                return Err(Error::custom(format!(
+                    "markdown table row on line {} has {} cells, expected {}",
+                    line.number,
+                    cells.len(),
+                    headers.len()
+                )));
+            }
+            let mut row: Vec<Value> = cells.iter().map(|cell| parse_cell(cell)).collect();
+            row.resize(headers.len(), Value::Null);
+            rows.push(row);
+        }
+
+        Ok(Table {
+            headers: headers.into_iter().map(Key::from).collect(),
+            rows,
+        })
+    }
+}
+
+/// Serializes `value` as a markdown document.
+///
+/// A top-level table (or array of objects) becomes a markdown table. In a
+/// top-level object, each field holding a table becomes a `### key` heading
+/// followed by a markdown table, and runs of other fields are kept as TOON in
+/// ```` ```toon ```` fenced blocks. Anything else is a single fenced block.
+///
+/// # Examples
+///
+/// ````rust
+/// use serde_toon::{markdown::to_markdown_document, toon};
+///
+/// let report = toon!({
+///     "title": "Stock",
+///     "items": [{"sku": "A1", "qty": 2}, {"sku": "B2", "qty": 0}]
+/// });
+/// assert_eq!(
+///     to_markdown_document(&report).unwrap(),
+///     "```toon\ntitle: Stock\n```\n\n### items\n\n| sku | qty |\n| --- | --- |\n| A1 | 2 |\n| B2 | 0 |"
+/// );
+/// ````
+///
+/// # Errors
+///
+/// Returns an error if the value cannot be serialized.
+pub fn to_markdown_document<T>(value: &T) -> Result<String>
+where
+    T: ?Sized + Serialize,
+{
+    let value = to_value(value)?;
+    if let Some(table) = markdown_table(&value) {
+        return Ok(table);
+    }
+    let Value::Object(obj) = &value else {
+        return Ok(fenced(&to_string(&value)?));
+    };
+
+    let mut sections = Vec::new();
+    let mut pending = ToonMap::new();
+    for (key, field) in obj.iter() {
+        match markdown_table(field) {
+            Some(table) => {
+                if !pending.is_empty() {
+                    sections.push(fenced(&to_string(&std::mem::take(&mut pending))?));
+                }
+                sections.push(format!("### {}\n\n{}", key, table));
+            }
+            None => {
+                pending.insert(key.clone(), field.clone());
+            }
+        }
+    }
+    if !pending.is_empty() {
+        sections.push(fenced(&to_string(&pending)?));
+    }
+
+    Ok(sections.join("\n\n"))
+}
+
+/// Renders a table, or an array of objects that would be written as one.
+fn markdown_table(value: &Value) -> Option<String> {
+    match value {
+        Value::Table(table) => Some(table.to_markdown()),
+        Value::Array(items) => {
+            can_be_tabular(items).map(|(headers, rows)| render_table(&headers, &rows))
+        }
+        _ => None,
+    }
+}
+
+fn fenced(toon: &str) -> String {
+    format!("```toon\n{}\n```", toon)
+}
+
+fn render_table(headers: &[Key], rows: &[Vec<Value>]) -> String {
+    let mut lines = Vec::with_capacity(rows.len() + 2);
+    let headers: Vec<String> = headers.iter().map(|h| escape_cell(h)).collect();
+    lines.push(format!("| {} |", headers.join(" | ")));
+    lines.push(format!("|{}", " --- |".repeat(headers.len())));
+    for row in rows {
+        let cells: Vec<String> = row.iter().map(cell_text).collect();
+        lines.push(format!("| {} |", cells.join(" | ")));
+    }
+    lines.join("\n")
+}
+
+fn cell_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => {
+            if parse_cell(s) == *value && !s.starts_with('"') {
+                escape_cell(s)
+            } else {
+                let quoted = format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+                escape_cell(&quoted)
+            }
+        }
+        #[cfg(feature = "datetime")]
+        Value::Date(dt) => dt.to_rfc3339(),
+        Value::Array(_) | Value::Object(_) | Value::Table(_) => {
+            escape_cell(&to_string(value).unwrap_or_default())
+        }
+        other => other.to_string(),
+    }
+}
+
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', "<br>")
+}
+
+/// Whether `line` is the row of dashes separating the header from the body.
+fn is_delimiter_row(line: &str) -> bool {
+    let cells = split_row(line);
+    !cells.is_empty()
+        && cells.iter().all(|cell| {
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+            !dashes.is_empty() && dashes.chars().all(|ch| ch == '-')
+        })
+}
+
+/// Splits a table row into trimmed cells, honouring `\|` escapes.
+fn split_row(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = match line.strip_suffix('|') {
+        Some(rest) if !rest.ends_with('\\') => rest,
+        _ => line,
+    };
+
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if chars.peek() == Some(&'|') => {
+                cell.push('|');
+                chars.next();
+            }
+            '|' => cells.push(std::mem::take(&mut cell).trim().to_string()),
+            _ => cell.push(ch),
+        }
+    }
+    cells.push(cell.trim().to_string());
+    cells
+}
+
+fn parse_cell(cell: &str) -> Value {
+    let text = cell.replace("<br>", "\n");
+    if let Some(inner) = text
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+    {
+        let mut unescaped = String::with_capacity(inner.len());
+        let mut chars = inner.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\\' => unescaped.extend(chars.next()),
+                _ => unescaped.push(ch),
+            }
+        }
+        return Value::String(unescaped);
+    }
+
+    Value::from_cell_text(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toon;
+
+    #[test]
+    fn round_trips_awkward_cells() {
+        let value = Value::table(["text", "n"])
+            .row(("a|b", 1))
+            .row(("42", 2))
+            .row(("", 3))
+            .row(("line\nbreak", 4))
+            .row(("\"quoted\"", 5))
+            .row(("null", Value::Null))
+            .build();
+        let table = Table::try_from(value).unwrap();
+        let markdown = table.to_markdown();
+        assert!(markdown.contains("| a\\|b | 1 |"));
+        assert!(markdown.contains("| \"42\" | 2 |"));
+        assert!(markdown.contains("| line<br>break | 4 |"));
+        assert_eq!(Table::from_markdown(&markdown).unwrap(), table);
+    }
+
+    #[test]
+    fn parses_tables_in_prose() {
+        let text = "Results:\n\n| id | score | ok |\n| :- | ----: | :-: |\n| 1 | 2.5 | true |\n| 2 | -3 |\n\nThat's all.";
+        let table = Table::from_markdown(text).unwrap();
+        assert_eq!(
+            Value::Table(table),
+            Value::table(["id", "score", "ok"])
+                .row((1, 2.5, true))
+                .row((2, -3, Value::Null))
+                .build()
+        );
+    }
+
+    #[test]
+    fn rejects_missing_or_ragged_tables() {
+        assert!(Table::from_markdown("no table here").is_err());
+        assert!(Table::from_markdown("| a |\n| - |\n| 1 | 2 |").is_err());
+    }
+
+    #[test]
+    fn renders_documents() {
+        let doc = toon!({
+            "a": 1,
+            "rows": [{"x": 1}],
+            "b": {"c": true},
+            "more": [{"y": "z"}]
+        });
+        assert_eq!(
+            to_markdown_document(&doc).unwrap(),
+            "```toon\na: 1\n```\n\n### rows\n\n| x |\n| --- |\n| 1 |\n\n\
+             ```toon\nb:\n  c: true\n```\n\n### more\n\n| y |\n| --- |\n| z |"
+        );
+        assert_eq!(to_markdown_document(&7).unwrap(), "```toon\n7\n```");
+    }
+}
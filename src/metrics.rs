@@ -0,0 +1,297 @@
+//! Token counting and TOON vs JSON size reports.
+//!
+//! TOON exists to save tokens, so it is useful to measure how many. This module
+//! provides:
+//!
+//! - [`token_estimate`]: token count of a string for a given [`Tokenizer`]
+//! - [`compare_with_json`]: character and token counts of a value encoded as
+//!   TOON and as compact JSON
+//!
+//! By default token counts are approximations computed from a pre-tokenization
+//! pass modelled on the cl100k/o200k splitting rules; they are typically within
+//! ~10% of the real count. Enable the `tiktoken` feature to get exact counts from
+//! the real BPE vocabularies (via the `tiktoken-rs` crate).
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde::Serialize;
+//! use serde_toon::metrics::compare_with_json;
+//!
+//! #[derive(Serialize)]
+//! struct User { id: u32, name: String }
+//!
+//! let users: Vec<User> = (0..20)
+//!     .map(|id| User { id, name: format!("user{}", id) })
+//!     .collect();
+//!
+//! let report = compare_with_json(&users).unwrap();
+//! assert!(report.toon_tokens < report.json_tokens);
+//! assert!(report.token_savings() > 0.0);
+//! ```
+
+use crate::json::to_json_string;
+use crate::{to_string, to_value, Result};
+use serde::Serialize;
+
+/// The tokenizer whose token counts should be reported.
+///
+/// - **Cl100k**: Used by GPT-4 and GPT-3.5 models (default)
+/// - **O200k**: Used by GPT-4o and newer models
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum Tokenizer {
+    #[default]
+    Cl100k,
+    O200k,
+}
+
+/// Character and token counts for a value encoded as TOON and as JSON.
+///
+/// Produced by [`compare_with_json`]. JSON is measured in its compact form.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comparison {
+    pub tokenizer: Tokenizer,
+    pub toon_chars: usize,
+    pub json_chars: usize,
+    pub toon_tokens: usize,
+    pub json_tokens: usize,
+}
+
+impl Comparison {
+    /// Percentage of characters saved by TOON relative to JSON (negative if TOON is larger).
+    #[must_use]
+    pub fn char_savings(&self) -> f64 {
+        savings(self.toon_chars, self.json_chars)
+    }
+
+    /// Percentage of tokens saved by TOON relative to JSON (negative if TOON is larger).
+    #[must_use]
+    pub fn token_savings(&self) -> f64 {
+        savings(self.toon_tokens, self.json_tokens)
+    }
+}
+
+fn savings(toon: usize, json: usize) -> f64 {
+    if json == 0 {
+        return 0.0;
+    }
+    (json as f64 - toon as f64) / json as f64 * 100.0
+}
+
+/// Counts (or, without the `tiktoken` feature, estimates) the tokens in `text`.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::metrics::{token_estimate, Tokenizer};
+///
+/// assert_eq!(token_estimate("", Tokenizer::Cl100k), 0);
+/// assert!(token_estimate("users[2]{id,name}:\n  1,Alice\n  2,Bob", Tokenizer::O200k) > 5);
+/// ```
+#[must_use]
+pub fn token_estimate(text: &str, tokenizer: Tokenizer) -> usize {
+    #[cfg(feature = "tiktoken")]
+    {
+        exact::count(text, tokenizer)
+    }
+    #[cfg(not(feature = "tiktoken"))]
+    {
+        approximate(text, tokenizer)
+    }
+}
+
+/// Compares the TOON and compact JSON encodings of `value` using [`Tokenizer::Cl100k`].
+///
+/// # Errors
+///
+/// Returns an error if the value cannot be serialized.
+pub fn compare_with_json<T>(value: &T) -> Result<Comparison>
+where
+    T: ?Sized + Serialize,
+{
+    compare_with_json_using(value, Tokenizer::default())
+}
+
+/// Compares the TOON and compact JSON encodings of `value` using `tokenizer`.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::metrics::{compare_with_json_using, Tokenizer};
+///
+/// let report = compare_with_json_using(&vec![1, 2, 3], Tokenizer::O200k).unwrap();
+/// assert_eq!(report.json_chars, "[1,2,3]".len());
+/// assert_eq!(report.toon_chars, "[3]: 1,2,3".len());
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the value cannot be serialized.
+pub fn compare_with_json_using<T>(value: &T, tokenizer: Tokenizer) -> Result<Comparison>
+where
+    T: ?Sized + Serialize,
+{
+    let toon = to_string(value)?;
+    let json = to_json_string(&to_value(value)?);
+
+    Ok(Comparison {
+        tokenizer,
+        toon_chars: toon.chars().count(),
+        json_chars: json.chars().count(),
+        toon_tokens: token_estimate(&toon, tokenizer),
+        json_tokens: token_estimate(&json, tokenizer),
+    })
+}
+
+/// Approximates BPE token counts.
+///
+/// The text is split the way the cl100k/o200k pre-tokenizers do (letter runs with
+/// an optional leading space or symbol, digit groups of up to three, punctuation
+/// runs, whitespace runs), then each piece is charged by length.
+#[cfg_attr(feature = "tiktoken", allow(dead_code))]
+fn approximate(text: &str, tokenizer: Tokenizer) -> usize {
+    // Letters covered by one token: common words are single tokens, rarer long
+    // words split into several. o200k has a larger vocabulary.
+    let letters_per_token = match tokenizer {
+        Tokenizer::Cl100k => 8.0,
+        Tokenizer::O200k => 9.0,
+    };
+    // Words glued to a symbol (`,Alice`, `@example`) are far less likely to be
+    // in the vocabulary as a whole and usually split once more.
+    let prefixed_letters_per_token = letters_per_token / 1.6;
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = 0;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let ch = chars[i];
+        let start = i;
+        let next = chars.get(i + 1).copied();
+        if ch.is_alphabetic()
+            || (!is_word_char(ch) && ch != '\n' && next.is_some_and(char::is_alphabetic))
+        {
+            // A letter run, optionally prefixed by one space or symbol
+            i += 1;
+            while i < chars.len() && chars[i].is_alphabetic() {
+                i += 1;
+            }
+            let per_token = if ch.is_alphabetic() || ch == ' ' {
+                letters_per_token
+            } else {
+                prefixed_letters_per_token
+            };
+            tokens += ((i - start) as f64 / per_token).ceil() as usize;
+        } else if ch.is_numeric() {
+            while i < chars.len() && chars[i].is_numeric() {
+                i += 1;
+            }
+            tokens += (i - start + 2) / 3;
+        } else if is_symbol(ch) || (ch == ' ' && next.is_some_and(is_symbol)) {
+            // A symbol run, optionally prefixed by a space and absorbing trailing newlines
+            i += 1;
+            while i < chars.len() && is_symbol(chars[i]) {
+                i += 1;
+            }
+            // Delimiter clusters such as `":"` or `"},{"` are usually one token
+            tokens += (i - start + 4) / 5;
+            while i < chars.len() && (chars[i] == '\n' || chars[i] == '\r') {
+                i += 1;
+            }
+        } else {
+            let mut end = i;
+            while end < chars.len() && chars[end].is_whitespace() {
+                end += 1;
+            }
+            // Whitespace up to the last newline is one token
+            if let Some(newline) = (i..end)
+                .rev()
+                .find(|&j| chars[j] == '\n' || chars[j] == '\r')
+            {
+                tokens += 1;
+                i = newline + 1;
+            }
+            if i < end {
+                let run = end - i;
+                if end < chars.len() && !chars[end].is_numeric() {
+                    // The last space joins the following word or symbol run
+                    tokens += usize::from(run > 1);
+                    i = end - 1;
+                } else {
+                    // Before digits, the last space is a token of its own
+                    tokens += if run > 1 && end < chars.len() { 2 } else { 1 };
+                    i = end;
+                }
+            }
+        }
+    }
+
+    tokens
+}
+
+#[cfg_attr(feature = "tiktoken", allow(dead_code))]
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphabetic() || ch.is_numeric()
+}
+
+#[cfg_attr(feature = "tiktoken", allow(dead_code))]
+fn is_symbol(ch: char) -> bool {
+    !ch.is_whitespace() && !is_word_char(ch)
+}
+
+#[cfg(feature = "tiktoken")]
+#[allow(clippy::expect_used)] // The vocabularies are compiled into tiktoken-rs
+mod exact {
+    use super::Tokenizer;
+    use std::sync::OnceLock;
+    use tiktoken_rs::CoreBPE;
+
+    static CL100K: OnceLock<CoreBPE> = OnceLock::new();
+    static O200K: OnceLock<CoreBPE> = OnceLock::new();
+
+    pub(super) fn count(text: &str, tokenizer: Tokenizer) -> usize {
+        let bpe = match tokenizer {
+            Tokenizer::Cl100k => CL100K.get_or_init(|| {
+                tiktoken_rs::cl100k_base().expect("bundled cl100k vocabulary is valid")
+            }),
+            Tokenizer::O200k => O200K.get_or_init(|| {
+                tiktoken_rs::o200k_base().expect("bundled o200k vocabulary is valid")
+            }),
+        };
+        bpe.encode_ordinary(text).len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approximation_is_close_for_typical_text() {
+        // Real cl100k counts: "hello world" = 2, "[3]: 1,2,3" = 9
+        assert_eq!(approximate("hello world", Tokenizer::Cl100k), 2);
+        let estimate = approximate("[3]: 1,2,3", Tokenizer::Cl100k);
+        assert!((7..=11).contains(&estimate), "{}", estimate);
+
+        let toon = "users: [2]{id,name,email}:\n  1,Alice Johnson,alice@example.com\n  2,Bob,bob@example.com";
+        let estimate = approximate(toon, Tokenizer::Cl100k);
+        assert!((27..=33).contains(&estimate), "{}", estimate); // real: 30
+
+        let json = r#"{"users":[{"id":1,"name":"Alice Johnson","email":"alice@example.com"},{"id":2,"name":"Bob","email":"bob@example.com"}]}"#;
+        let estimate = approximate(json, Tokenizer::Cl100k);
+        assert!((30..=36).contains(&estimate), "{}", estimate); // real: 33
+    }
+
+    #[test]
+    fn test_savings() {
+        let report = Comparison {
+            tokenizer: Tokenizer::Cl100k,
+            toon_chars: 60,
+            json_chars: 100,
+            toon_tokens: 30,
+            json_tokens: 40,
+        };
+        assert!((report.char_savings() - 40.0).abs() < f64::EPSILON);
+        assert!((report.token_savings() - 25.0).abs() < f64::EPSILON);
+    }
+}
@@ -1,9 +1,21 @@
-//! Configuration options for TOON serialization.
+//! Configuration options for TOON serialization and deserialization.
 //!
-//! This module provides types to customize TOON output format:
+//! This module provides types to customize TOON output format and parsing:
 //!
 //! - [`ToonOptions`]: Main configuration struct
 //! - [`Delimiter`]: Choice of delimiter for arrays and tables (comma, tab, or pipe)
+//! - [`DelimiterStrategy`]: Whether every array uses that delimiter or each picks its own
+//! - [`QuotePolicy`]: When string values are quoted
+//! - [`BytesFormat`]: How byte buffers are written (base64, hex, or integer arrays)
+//! - [`EnumRepr`]: How enum variants are tagged
+//! - [`DuplicateKeys`]: What happens when an object repeats a key
+//! - [`KeyOrder`]: The order in which object keys and table columns are written
+//! - [`NonStringKeys`]: Whether integer and boolean map keys are stringified
+//! - [`LineEnding`]: The line break written between lines of output
+//! - [`KeyAliases`]: Short names written in place of long keys
+//! - [`FieldAction`] and [`FieldFilter`]: Which fields are written, omitted or masked
+//! - [`TypeCodec`]: A custom written form for a named type
+//! - [`EnvLookup`]: Where parsed `${VAR}` references find their values
 //!
 //! ## Examples
 //!
@@ -26,6 +38,12 @@
 //! // Output: "[#3]: 1,2,3"
 //! ```
 
+use crate::path::Path;
+use crate::{Result, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
 /// Delimiter choice for TOON arrays and tables.
 ///
 /// TOON supports multiple delimiters to optimize for different contexts:
@@ -63,128 +81,2244 @@ impl Delimiter {
     }
 }
 
-/// Configuration options for TOON serialization.
+/// How the delimiter of each array and table is chosen when serializing.
+///
+/// - **Fixed**: Every array uses [`ToonOptions::delimiter`] (default)
+/// - **Auto**: Each array uses the delimiter that leaves the fewest of its
+///   values quoted, preferring [`ToonOptions::delimiter`] on a tie
 ///
-/// Controls formatting aspects like indentation, delimiters, and special markers.
+/// With `Auto`, a table whose cells contain commas is written with pipes
+/// (`[2|]{a|b}:`), and values only need quotes for the delimiter their array
+/// uses. Headers name the delimiter, so the output parses without options.
 ///
 /// # Examples
 ///
 /// ```rust
-/// use serde_toon::{ToonOptions, Delimiter};
+/// use serde_toon::{to_string_with_options, DelimiterStrategy, ToonOptions};
 ///
-/// // Default compact options
-/// let options = ToonOptions::new();
+/// let options = ToonOptions::new().with_delimiter_strategy(DelimiterStrategy::Auto);
+/// let toon = to_string_with_options(&vec!["a,b", "c"], options).unwrap();
+/// assert_eq!(toon, "[2|]: a,b|c");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DelimiterStrategy {
+    #[default]
+    Fixed,
+    Auto,
+}
+
+/// When string values are written in double quotes.
 ///
-/// // Pretty-printed with 2-space indentation
-/// let options = ToonOptions::pretty();
+/// - **Minimal**: Only where the text would otherwise read back as something
+///   else, such as a number, `true`, or a value containing a delimiter (default)
+/// - **AlwaysStrings**: Every string value, for parsers that require it
+/// - **PreserveInput**: Only where the text would otherwise break the
+///   document's structure; strings that look like numbers or booleans are
+///   written as they are
 ///
-/// // Custom configuration
-/// let options = ToonOptions::new()
-///     .with_delimiter(Delimiter::Pipe)
-///     .with_length_marker('#')
-///     .with_indent(4);
+/// With `PreserveInput`, a string such as the ID `"12345"` is written as
+/// `12345` and read back as a string by fields typed as strings when parsing
+/// with the same option. Untyped targets such as [`Value`] see
+/// a number. A string only stays unquoted if it reads back exactly, so
+/// `"1.50"` and `"null"` keep their quotes.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{to_string_with_options, QuotePolicy, ToonOptions};
+///
+/// let options = ToonOptions::new().with_quote_policy(QuotePolicy::AlwaysStrings);
+/// let toon = to_string_with_options(&vec!["a", "b"], options).unwrap();
+/// assert_eq!(toon, r#"[2]: "a","b""#);
 /// ```
-#[derive(Clone, Debug)]
-pub struct ToonOptions {
-    pub indent: usize,
-    pub delimiter: Delimiter,
-    pub length_marker: Option<char>,
-    pub pretty: bool,
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum QuotePolicy {
+    #[default]
+    Minimal,
+    AlwaysStrings,
+    PreserveInput,
 }
 
-impl Default for ToonOptions {
+/// What to do when a value cannot be represented in TOON.
+///
+/// Most Rust shapes map onto TOON directly. The exceptions are values such as
+/// maps whose keys are themselves collections, which have no textual key form.
+///
+/// - **Error**: Fail with [`Error::UnsupportedType`](crate::Error::UnsupportedType)
+///   naming the type and path (default)
+/// - **Stringify**: Replace the value with its TOON text as a string
+/// - **Null**: Replace the value with `null` (or drop the map entry) and record a
+///   [`Warning`](crate::Warning)
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{ToonOptions, UnsupportedPolicy};
+///
+/// let options = ToonOptions::new().with_unsupported_policy(UnsupportedPolicy::Stringify);
+/// assert_eq!(options.unsupported_policy, UnsupportedPolicy::Stringify);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum UnsupportedPolicy {
+    #[default]
+    Error,
+    Stringify,
+    Null,
+}
+
+/// How byte buffers (`serialize_bytes`, e.g. `serde_bytes::ByteBuf`) are written.
+///
+/// - **IntArray**: An array of integers, `[3]: 1,2,3` (default)
+/// - **Base64**: A standard base64 string with padding, `AQID`
+/// - **Hex**: A lowercase hexadecimal string, `010203`
+///
+/// When deserializing byte buffers, strings are decoded with the configured
+/// format (base64 also accepts unpadded input) and integer arrays are always
+/// accepted.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{BytesFormat, ToonOptions};
+///
+/// let options = ToonOptions::new().with_bytes_format(BytesFormat::Base64);
+/// assert_eq!(options.bytes_format, BytesFormat::Base64);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum BytesFormat {
+    #[default]
+    IntArray,
+    Base64,
+    Hex,
+}
+
+/// How enum variants are represented, mirroring serde's enum representations.
+///
+/// - **External**: `Variant: content`, unit variants as a bare `Variant` string (default)
+/// - **Internal**: The variant name under `tag`, next to the variant's fields.
+///   Only unit, struct and newtype-of-struct variants can be represented.
+/// - **Adjacent**: The variant name under `tag` and its content under `content`
+/// - **Untagged**: Just the content (`null` for unit variants). The variant name
+///   is not recorded, so untagged values cannot be deserialized back into the enum.
+///
+/// This applies to enums without serde representation attributes; enums marked
+/// `#[serde(tag = "...")]` or `#[serde(untagged)]` are handled by serde itself.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde::{Deserialize, Serialize};
+/// use serde_toon::{from_str_with_options, to_string_with_options, EnumRepr, ToonOptions};
+///
+/// #[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// enum Shape {
+///     Circle { radius: u32 },
+///     Empty,
+/// }
+///
+/// let options = ToonOptions::new().with_enum_repr(EnumRepr::internal("type"));
+/// let toon = to_string_with_options(&Shape::Circle { radius: 2 }, options.clone()).unwrap();
+/// assert_eq!(toon, "type: Circle\nradius: 2");
+///
+/// let shape: Shape = from_str_with_options(&toon, options).unwrap();
+/// assert_eq!(shape, Shape::Circle { radius: 2 });
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub enum EnumRepr {
+    #[default]
+    External,
+    Internal {
+        tag: String,
+    },
+    Adjacent {
+        tag: String,
+        content: String,
+    },
+    Untagged,
+}
+
+impl EnumRepr {
+    /// Internally tagged representation with the variant name under `tag`.
+    #[must_use]
+    pub fn internal(tag: &str) -> Self {
+        EnumRepr::Internal {
+            tag: tag.to_string(),
+        }
+    }
+
+    /// Adjacently tagged representation with the variant name under `tag` and
+    /// its content under `content`.
+    #[must_use]
+    pub fn adjacent(tag: &str, content: &str) -> Self {
+        EnumRepr::Adjacent {
+            tag: tag.to_string(),
+            content: content.to_string(),
+        }
+    }
+}
+
+/// What to do with map keys that serialize to numbers or booleans, such as the
+/// keys of a `HashMap<u32, T>`.
+///
+/// - **Stringify**: Write the key as text, e.g. `"42": value` (default). Such
+///   maps deserialize back, since numeric and boolean key types parse their keys
+///   from the text.
+/// - **Error**: Fail with [`Error::UnsupportedType`](crate::Error::UnsupportedType)
+///
+/// Keys that are collections (e.g. `Vec<u32>`) are not covered by this option;
+/// they follow the [`UnsupportedPolicy`].
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{to_string_with_options, NonStringKeys, ToonOptions};
+/// use std::collections::BTreeMap;
+///
+/// let scores = BTreeMap::from([(1, "gold"), (2, "silver")]);
+/// let toon = to_string_with_options(&scores, ToonOptions::new()).unwrap();
+/// assert_eq!(toon, "\"1\": gold\n\"2\": silver");
+///
+/// let options = ToonOptions::new().with_non_string_keys(NonStringKeys::Error);
+/// assert!(to_string_with_options(&scores, options).is_err());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum NonStringKeys {
+    #[default]
+    Stringify,
+    Error,
+}
+
+/// What to do when an object being parsed contains the same key twice.
+///
+/// - **Error**: Fail with [`Error::DuplicateKey`](crate::Error::DuplicateKey)
+///   pointing at the second occurrence
+/// - **FirstWins**: Keep the first value and ignore later ones
+/// - **LastWins**: Keep the last value (default)
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{from_str_with_options, DuplicateKeys, ToonOptions, Value};
+///
+/// let options = ToonOptions::new().with_duplicate_keys(DuplicateKeys::FirstWins);
+/// let value: Value = from_str_with_options("a: 1\na: 2", options).unwrap();
+/// assert_eq!(value.as_object().and_then(|o| o.get("a")).and_then(|v| v.as_i64()), Some(1));
+///
+/// let options = ToonOptions::new().with_duplicate_keys(DuplicateKeys::Error);
+/// assert!(from_str_with_options::<Value>("a: 1\na: 2", options).is_err());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DuplicateKeys {
+    Error,
+    FirstWins,
+    #[default]
+    LastWins,
+}
+
+/// The order in which object keys and table columns are written.
+///
+/// - **Insertion**: The order in which fields were serialized or inserted
+///   (struct declaration order, `IndexMap`/[`ToonMap`](crate::ToonMap) order)
+/// - **Alphabetical**: Sorted by key, for output that does not depend on how
+///   the data was built
+///
+/// The order applies to plain objects, table headers (including tables built
+/// from arrays of objects) and objects inside list items alike. Parsing always
+/// keeps the document's order, so output written in insertion order reads back
+/// with the same key order.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{to_string_with_options, toon, KeyOrder, ToonOptions};
+///
+/// let value = toon!([{"name": "A", "id": 1}, {"name": "B", "id": 2}]);
+/// assert_eq!(
+///     to_string_with_options(&value, ToonOptions::new()).unwrap(),
+///     "[2]{name,id}:\n  A,1\n  B,2"
+/// );
+///
+/// let options = ToonOptions::new().with_canonical_key_order(KeyOrder::Alphabetical);
+/// assert_eq!(
+///     to_string_with_options(&value, options).unwrap(),
+///     "[2]{id,name}:\n  1,A\n  2,B"
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum KeyOrder {
+    #[default]
+    Insertion,
+    Alphabetical,
+}
+
+/// How [`FloatFormat`] rounds a float with more decimals than it allows.
+///
+/// Rounding works on the shortest decimal text of the float, the one written
+/// without a [`FloatFormat::max_decimals`], so `2.675` is a tie even though
+/// the nearest `f64` is slightly below it.
+///
+/// - **HalfToEven**: Round to the nearest, ties to an even last digit (default)
+/// - **HalfAwayFromZero**: Round to the nearest, ties away from zero
+/// - **TowardZero**: Drop the extra decimals
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{to_string_with_options, FloatFormat, RoundingMode, ToonOptions};
+///
+/// let round = |mode| {
+///     let format = FloatFormat::new().with_max_decimals(2).with_mode(mode);
+///     to_string_with_options(&[2.675, 2.665, -1.239], ToonOptions::new().with_float_format(format))
+///         .unwrap()
+/// };
+/// assert_eq!(round(RoundingMode::HalfToEven), "[3]: 2.68,2.66,-1.24");
+/// assert_eq!(round(RoundingMode::HalfAwayFromZero), "[3]: 2.68,2.67,-1.24");
+/// assert_eq!(round(RoundingMode::TowardZero), "[3]: 2.67,2.66,-1.23");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum RoundingMode {
+    #[default]
+    HalfToEven,
+    HalfAwayFromZero,
+    TowardZero,
+}
+
+/// How floats are written, set with [`ToonOptions::with_float_format`].
+///
+/// By default floats are written with as many decimals as it takes to read
+/// them back exactly. With [`max_decimals`](FloatFormat::max_decimals) set,
+/// longer floats are rounded by [`mode`](FloatFormat::mode), which saves
+/// tokens in numeric tables that do not need full precision. Trailing zeros
+/// are dropped unless [`trim_trailing_zeros`](FloatFormat::trim_trailing_zeros)
+/// is `false`, in which case every float gets exactly `max_decimals` decimals.
+///
+/// Integers, non-finite floats (written as `null`) and numbers kept with
+/// [`ToonOptions::preserve_number_text`] are written as usual.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{to_string_with_options, FloatFormat, ToonOptions};
+///
+/// let latencies = [0.123456, 1.5, 2.0];
+/// let format = FloatFormat::new().with_max_decimals(3);
+/// let options = ToonOptions::new().with_float_format(format);
+/// assert_eq!(to_string_with_options(&latencies, options).unwrap(), "[3]: 0.123,1.5,2");
+///
+/// let format = format.with_trim_trailing_zeros(false);
+/// let options = ToonOptions::new().with_float_format(format);
+/// assert_eq!(to_string_with_options(&latencies, options).unwrap(), "[3]: 0.123,1.500,2.000");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FloatFormat {
+    pub max_decimals: Option<usize>,
+    pub trim_trailing_zeros: bool,
+    pub mode: RoundingMode,
+}
+
+impl Default for FloatFormat {
     fn default() -> Self {
-        ToonOptions {
-            indent: 2,
-            delimiter: Delimiter::default(),
-            length_marker: None,
-            pretty: false,
+        FloatFormat {
+            max_decimals: None,
+            trim_trailing_zeros: true,
+            mode: RoundingMode::default(),
         }
     }
 }
 
-impl ToonOptions {
-    /// Creates default options (compact format, comma delimiter, 2-space indent).
-    ///
-    /// # Examples
-    ///
-    /// ```rust
-    /// use serde_toon::ToonOptions;
-    ///
-    /// let options = ToonOptions::new();
-    /// assert_eq!(options.indent, 2);
-    /// assert!(!options.pretty);
-    /// ```
+impl FloatFormat {
+    /// Creates the default format: full precision, trailing zeros trimmed,
+    /// ties rounded to even.
     #[must_use]
     pub fn new() -> Self {
         Self::default()
     }
 
-    /// Creates options for pretty-printed output with newlines and indentation.
-    ///
-    /// # Examples
-    ///
-    /// ```rust
-    /// use serde_toon::ToonOptions;
-    ///
-    /// let options = ToonOptions::pretty();
-    /// assert!(options.pretty);
-    /// ```
+    /// Sets the most decimals a float is written with.
     #[must_use]
-    pub fn pretty() -> Self {
-        ToonOptions {
-            pretty: true,
-            ..Default::default()
-        }
+    pub fn with_max_decimals(mut self, max_decimals: usize) -> Self {
+        self.max_decimals = Some(max_decimals);
+        self
     }
 
-    /// Sets the indentation size (number of spaces per level).
-    ///
-    /// Default is 2. Only affects pretty-printed output.
-    ///
-    /// # Examples
-    ///
-    /// ```rust
-    /// use serde_toon::ToonOptions;
-    ///
-    /// let options = ToonOptions::pretty().with_indent(4);
-    /// assert_eq!(options.indent, 4);
-    /// ```
+    /// Sets whether trailing zeros after the decimal point are dropped, or
+    /// kept to write exactly [`max_decimals`](FloatFormat::max_decimals).
     #[must_use]
-    pub fn with_indent(mut self, indent: usize) -> Self {
-        self.indent = indent;
+    pub fn with_trim_trailing_zeros(mut self, trim: bool) -> Self {
+        self.trim_trailing_zeros = trim;
         self
     }
 
-    /// Sets the delimiter for arrays and tables.
-    ///
-    /// # Examples
-    ///
-    /// ```rust
-    /// use serde_toon::{ToonOptions, Delimiter};
-    ///
-    /// let options = ToonOptions::new().with_delimiter(Delimiter::Pipe);
-    /// ```
+    /// Sets how floats with more decimals than allowed are rounded.
     #[must_use]
-    pub fn with_delimiter(mut self, delimiter: Delimiter) -> Self {
-        self.delimiter = delimiter;
+    pub fn with_mode(mut self, mode: RoundingMode) -> Self {
+        self.mode = mode;
         self
     }
+}
 
-    /// Sets an optional length marker character for arrays.
-    ///
-    /// When set, array lengths are prefixed with this character (e.g., `[#3]` instead of `[3]`).
-    ///
-    /// # Examples
-    ///
-    /// ```rust
-    /// use serde_toon::ToonOptions;
-    ///
-    /// let options = ToonOptions::new().with_length_marker('#');
-    /// ```
+/// How [`VectorFormat`] writes a vector.
+///
+/// - **Plain**: An inline array like any other, `[3]: 0.1,-0.2,0.3` (default)
+/// - **Decimals**: An inline array with its floats rounded to this many
+///   decimals, in place of the [`FloatFormat::max_decimals`] of
+///   [`ToonOptions::float_format`]
+/// - **PackedF32**: The little-endian `f32` bytes of the elements as a
+///   standard base64 string, `zczMPc3MTL6amZk+`. Elements lose any precision
+///   beyond `f32`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum VectorEncoding {
+    #[default]
+    Plain,
+    Decimals(usize),
+    PackedF32,
+}
+
+/// How vectors, such as embeddings, are written, set with
+/// [`ToonOptions::with_vector_format`].
+///
+/// A vector is an array of at least [`min_len`](VectorFormat::min_len)
+/// numbers with at least one float among them. A 1536-dimension `f32`
+/// embedding takes about 18 KB written in full, 11 KB with 4 decimals and
+/// 8 KB packed.
+///
+/// When deserializing, a string where a sequence is expected is unpacked if
+/// the encoding is [`VectorEncoding::PackedF32`]. Packed vectors read into a
+/// [`Value`] stay strings.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{from_str_with_options, to_string_with_options, ToonOptions, VectorFormat};
+///
+/// let embedding = vec![0.1f32, -0.2, 0.3];
+/// let options = ToonOptions::new().with_vector_format(VectorFormat::packed_f32().with_min_len(3));
+/// let toon = to_string_with_options(&embedding, options.clone()).unwrap();
+/// assert_eq!(toon, "zczMPc3MTL6amZk+");
+/// assert_eq!(from_str_with_options::<Vec<f32>>(&toon, options).unwrap(), embedding);
+///
+/// let options = ToonOptions::new().with_vector_format(VectorFormat::decimals(2).with_min_len(3));
+/// let toon = to_string_with_options(&[0.1234, -0.5, 0.987], options).unwrap();
+/// assert_eq!(toon, "[3]: 0.12,-0.5,0.99");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VectorFormat {
+    pub encoding: VectorEncoding,
+    pub min_len: usize,
+}
+
+impl Default for VectorFormat {
+    fn default() -> Self {
+        VectorFormat {
+            encoding: VectorEncoding::default(),
+            min_len: 32,
+        }
+    }
+}
+
+impl VectorFormat {
+    /// Creates the default format: vectors are written as plain arrays.
     #[must_use]
-    pub fn with_length_marker(mut self, marker: char) -> Self {
-        self.length_marker = Some(marker);
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a format that rounds the floats of vectors to `decimals`.
+    #[must_use]
+    pub fn decimals(decimals: usize) -> Self {
+        Self::new().with_encoding(VectorEncoding::Decimals(decimals))
+    }
+
+    /// Creates a format that packs vectors into base64 `f32` strings.
+    #[must_use]
+    pub fn packed_f32() -> Self {
+        Self::new().with_encoding(VectorEncoding::PackedF32)
+    }
+
+    /// Sets how vectors are written.
+    #[must_use]
+    pub fn with_encoding(mut self, encoding: VectorEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Sets the fewest elements an array needs to count as a vector. Shorter
+    /// arrays of floats, such as coordinates, are written as usual. The
+    /// default is 32.
+    #[must_use]
+    pub fn with_min_len(mut self, min_len: usize) -> Self {
+        self.min_len = min_len;
         self
     }
 }
+
+/// The line break written between lines of output.
+///
+/// - **Lf**: `\n` (default)
+/// - **CrLf**: `\r\n`, for tools on Windows that expect it
+///
+/// Input is read with either, so the choice only affects writing.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{from_str, to_string_with_options, toon, LineEnding, ToonOptions, Value};
+///
+/// let value = toon!({"id": 1, "tags": ["a", "b"], "owner": {"name": "Ada"}});
+/// let options = ToonOptions::new().with_line_ending(LineEnding::CrLf);
+/// let toon = to_string_with_options(&value, options).unwrap();
+/// assert_eq!(toon, "id: 1\r\ntags: [2]: a,b\r\nowner:\r\n  name: Ada");
+/// assert_eq!(from_str::<Value>(&toon).unwrap(), value);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    /// Returns the characters of this line ending.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Which implementation's output the serializer reproduces.
+///
+/// - **Native**: This crate's own output (default)
+/// - **ReferenceTs**: Byte for byte the output of `encode` in the reference
+///   TypeScript implementation with its default options, so documents built
+///   in Rust match those built by Node services
+///
+/// Under `ReferenceTs`:
+///
+/// - array fields put the header straight after the key: `tags[2]: a,b`,
+///   `users[2]{id,name}:`
+/// - keys are quoted unless they match `^[A-Za-z_][A-Za-z0-9_.]*$`, and are
+///   never folded into dotted paths
+/// - strings are quoted by the reference's rules, with the active delimiter
+///   as the only one that forces quotes, and only `\`, `"`, `\n`, `\r` and
+///   `\t` are escaped
+/// - NaN and infinities are `null`, and integers beyond
+///   ±(2<sup>53</sup> − 1), which JavaScript holds as a `BigInt`, are quoted
+///   strings
+/// - dates written by `Value::to_toon_string` are quoted as
+///   `Date.prototype.toISOString` writes them, such as
+///   `"2024-01-15T10:30:00.000Z"`
+/// - a tab delimiter is written as a tab in headers
+///
+/// [`delimiter`](ToonOptions::delimiter), [`indent`](ToonOptions::indent)
+/// and [`length_marker`](ToonOptions::length_marker), which the reference
+/// encoder has options for, apply as usual. Block strings, vector formats,
+/// automatic delimiters, quote policies and `\u{...}` escapes, which it does
+/// not have, are turned off.
+///
+/// Parsing is the same in either mode, except that under `ReferenceTs` an
+/// unquoted integer beyond ±(2<sup>53</sup> − 1) is read as a float, as the
+/// reference decoder reads it. Whole floats that large are written without
+/// quotes and integers that large with them, so a value read back from text
+/// written in this mode writes the same text again.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{to_string_with_options, toon, CompatMode, ToonOptions};
+///
+/// let value = toon!({"user-id": 7, "tags": ["a|b", "c"], "big": 9007199254740993_i64});
+/// let options = ToonOptions::new().with_compat(CompatMode::ReferenceTs);
+/// assert_eq!(
+///     to_string_with_options(&value, options).unwrap(),
+///     "\"user-id\": 7\ntags[2]: a|b,c\nbig: \"9007199254740993\""
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum CompatMode {
+    #[default]
+    Native,
+    ReferenceTs,
+}
+
+/// A dictionary of short aliases for keys, set with
+/// [`ToonOptions::with_key_aliases`].
+///
+/// Keys with an alias are written as the alias, in objects and table headers
+/// alike, and aliases are read back as the keys they stand for. Keys without
+/// an alias, including the full names of aliased keys, are read and written
+/// unchanged.
+///
+/// Each key has at most one alias and each alias stands for one key; inserting
+/// a pair replaces any earlier pair with the same key or alias. An alias
+/// should not also be used as a key of its own, since it would be read back
+/// as the key it stands for.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::KeyAliases;
+///
+/// let aliases: KeyAliases = [("description", "desc"), ("identifier", "id")].into_iter().collect();
+/// assert_eq!(aliases.alias("description"), Some("desc"));
+/// assert_eq!(aliases.key("id"), Some("identifier"));
+/// assert_eq!(aliases.alias("name"), None);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeyAliases {
+    aliases: HashMap<String, String>,
+    keys: HashMap<String, String>,
+}
+
+impl KeyAliases {
+    /// Creates an empty dictionary.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `alias` as the short name for `key`.
+    pub fn insert(&mut self, key: impl Into<String>, alias: impl Into<String>) {
+        let (key, alias) = (key.into(), alias.into());
+        if let Some(old_alias) = self.aliases.remove(&key) {
+            self.keys.remove(&old_alias);
+        }
+        if let Some(old_key) = self.keys.remove(&alias) {
+            self.aliases.remove(&old_key);
+        }
+        self.aliases.insert(key.clone(), alias.clone());
+        self.keys.insert(alias, key);
+    }
+
+    /// Returns the alias written for `key`, if it has one.
+    #[must_use]
+    pub fn alias(&self, key: &str) -> Option<&str> {
+        self.aliases.get(key).map(String::as_str)
+    }
+
+    /// Returns the key that `alias` stands for, if it is an alias.
+    #[must_use]
+    pub fn key(&self, alias: &str) -> Option<&str> {
+        self.keys.get(alias).map(String::as_str)
+    }
+
+    /// Returns the number of aliases.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.aliases.len()
+    }
+
+    /// Returns `true` if there are no aliases.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+}
+
+impl<K: Into<String>, A: Into<String>> FromIterator<(K, A)> for KeyAliases {
+    fn from_iter<I: IntoIterator<Item = (K, A)>>(pairs: I) -> Self {
+        let mut aliases = KeyAliases::new();
+        aliases.extend(pairs);
+        aliases
+    }
+}
+
+impl<K: Into<String>, A: Into<String>> Extend<(K, A)> for KeyAliases {
+    fn extend<I: IntoIterator<Item = (K, A)>>(&mut self, pairs: I) {
+        for (key, alias) in pairs {
+            self.insert(key, alias);
+        }
+    }
+}
+
+/// What the serializer does with a field, as decided by a [`FieldFilter`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FieldAction {
+    /// Write the field as usual.
+    Keep,
+    /// Leave the field out.
+    Omit,
+    /// Write the given text in place of the field's value, which is never
+    /// serialized.
+    Mask(String),
+}
+
+/// Decides for each object field, by its [`Path`], whether it is written,
+/// omitted or masked when serializing. Set with
+/// [`ToonOptions::with_field_filter`], [`ToonOptions::with_masked_fields`] or
+/// [`ToonOptions::with_field_action`].
+#[derive(Clone)]
+pub struct FieldFilter(Arc<dyn Fn(&Path) -> FieldAction + Send + Sync>);
+
+impl FieldFilter {
+    /// Creates a filter from a function of each field's path.
+    pub fn new<F>(action: F) -> Self
+    where
+        F: Fn(&Path) -> FieldAction + Send + Sync + 'static,
+    {
+        FieldFilter(Arc::new(action))
+    }
+
+    /// Returns what to do with the field at `path`.
+    #[must_use]
+    pub fn action(&self, path: &Path) -> FieldAction {
+        (self.0)(path)
+    }
+}
+
+impl fmt::Debug for FieldFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FieldFilter(..)")
+    }
+}
+
+/// The function type behind both halves of a [`TypeCodec`].
+type CodecFn = Arc<dyn Fn(&Value) -> Result<Value> + Send + Sync>;
+
+/// A custom written form for a named type, set with
+/// [`ToonOptions::with_type_codec`].
+///
+/// `encode` is given the [`Value`] the type serializes to and returns the
+/// value written in its place, usually a short string. `decode` is given the
+/// value read back and returns the one the type deserializes from. Codecs
+/// apply to structs, tuple structs and newtype structs, which serde passes by
+/// name, wherever they appear: at the root, in fields, in list items and in
+/// table cells.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde::{Deserialize, Serialize};
+/// use serde_toon::{from_str_with_options, to_string_with_options, Error, ToonOptions, TypeCodec, Value};
+///
+/// #[derive(Serialize, Deserialize, PartialEq, Debug)]
+/// struct Cents(i64);
+///
+/// #[derive(Serialize, Deserialize, PartialEq, Debug)]
+/// struct Item { sku: String, price: Cents }
+///
+/// let money = TypeCodec::new(
+///     |value| {
+///         let cents = value.as_i64().ok_or_else(|| Error::custom("expected cents"))?;
+///         Ok(Value::from(format!("${}.{:02}", cents / 100, cents % 100)))
+///     },
+///     |value| {
+///         let text = value.as_str().and_then(|text| text.strip_prefix('$'));
+///         let (dollars, cents) = text
+///             .and_then(|text| text.split_once('.'))
+///             .ok_or_else(|| Error::custom("expected an amount like $12.50"))?;
+///         let dollars: i64 = dollars.parse().map_err(Error::custom)?;
+///         let cents: i64 = cents.parse().map_err(Error::custom)?;
+///         Ok(Value::from(dollars * 100 + cents))
+///     },
+/// );
+/// let options = ToonOptions::new().with_type_codec("Cents", money);
+///
+/// let items = vec![
+///     Item { sku: "A1".into(), price: Cents(1250) },
+///     Item { sku: "B2".into(), price: Cents(99) },
+/// ];
+/// let toon = to_string_with_options(&items, options.clone()).unwrap();
+/// assert_eq!(toon, "[2]{sku,price}:\n  A1,$12.50\n  B2,$0.99");
+/// assert_eq!(from_str_with_options::<Vec<Item>>(&toon, options).unwrap(), items);
+/// ```
+#[derive(Clone)]
+pub struct TypeCodec {
+    encode: CodecFn,
+    decode: CodecFn,
+}
+
+impl TypeCodec {
+    /// Creates a codec from its `encode` and `decode` halves.
+    pub fn new<E, D>(encode: E, decode: D) -> Self
+    where
+        E: Fn(&Value) -> Result<Value> + Send + Sync + 'static,
+        D: Fn(&Value) -> Result<Value> + Send + Sync + 'static,
+    {
+        TypeCodec {
+            encode: Arc::new(encode),
+            decode: Arc::new(decode),
+        }
+    }
+
+    /// Returns the value written in place of `value`.
+    pub fn encode(&self, value: &Value) -> Result<Value> {
+        (self.encode)(value)
+    }
+
+    /// Returns the value to deserialize from in place of the `value` read.
+    pub fn decode(&self, value: &Value) -> Result<Value> {
+        (self.decode)(value)
+    }
+}
+
+impl fmt::Debug for TypeCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TypeCodec(..)")
+    }
+}
+
+/// Looks up the environment variables that `${VAR}` references in parsed
+/// strings stand for. Set with [`ToonOptions::with_env_interpolation`], which
+/// reads the process environment, or [`ToonOptions::with_env_lookup`].
+#[derive(Clone)]
+pub struct EnvLookup(LookupFn);
+
+/// The function type behind an [`EnvLookup`].
+type LookupFn = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+impl EnvLookup {
+    /// Creates a lookup from a function of each variable's name.
+    pub fn new<F>(lookup: F) -> Self
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        EnvLookup(Arc::new(lookup))
+    }
+
+    /// Returns the value of the variable `name`, or `None` if it is unset.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<String> {
+        (self.0)(name)
+    }
+}
+
+impl fmt::Debug for EnvLookup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EnvLookup(..)")
+    }
+}
+
+/// Configuration options for TOON serialization and deserialization.
+///
+/// Controls formatting aspects like indentation, delimiters, and special markers,
+/// as well as how strictly input is parsed.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{ToonOptions, Delimiter};
+///
+/// // Default compact options
+/// let options = ToonOptions::new();
+///
+/// // Pretty-printed with 2-space indentation
+/// let options = ToonOptions::pretty();
+///
+/// // Custom configuration
+/// let options = ToonOptions::new()
+///     .with_delimiter(Delimiter::Pipe)
+///     .with_length_marker('#')
+///     .with_indent(4);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ToonOptions {
+    pub indent: usize,
+    pub delimiter: Delimiter,
+    pub delimiter_strategy: DelimiterStrategy,
+    pub length_marker: Option<char>,
+    pub omit_lengths: bool,
+    pub quote_policy: QuotePolicy,
+    pub escape_non_ascii: bool,
+    pub block_strings: Option<usize>,
+    pub pretty: bool,
+    pub align_table_columns: bool,
+    pub float_format: FloatFormat,
+    pub vector_format: VectorFormat,
+    pub unsupported_policy: UnsupportedPolicy,
+    pub lenient: bool,
+    pub indent_width: Option<usize>,
+    pub allow_tabs: bool,
+    pub sniff_delimiters: bool,
+    pub strip_code_fences: bool,
+    pub preserve_number_text: bool,
+    pub bytes_format: BytesFormat,
+    pub enum_repr: EnumRepr,
+    pub duplicate_keys: DuplicateKeys,
+    pub deny_unknown_fields: bool,
+    pub canonical_key_order: Option<KeyOrder>,
+    pub non_string_keys: NonStringKeys,
+    pub sparse_tables: Option<f64>,
+    pub row_numbers: bool,
+    pub skip_none_fields: bool,
+    pub line_ending: LineEnding,
+    pub trailing_newline: bool,
+    pub compat: CompatMode,
+    pub size_hint: Option<usize>,
+    pub max_depth: Option<usize>,
+    pub max_document_size: Option<usize>,
+    pub max_array_length: Option<usize>,
+    pub max_string_length: Option<usize>,
+    pub key_aliases: KeyAliases,
+    pub field_filter: Option<FieldFilter>,
+    pub type_codecs: HashMap<String, TypeCodec>,
+    pub env_lookup: Option<EnvLookup>,
+}
+
+/// Default for [`ToonOptions::max_depth`], matching serde_json's recursion limit.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+impl Default for ToonOptions {
+    fn default() -> Self {
+        ToonOptions {
+            indent: 2,
+            delimiter: Delimiter::default(),
+            delimiter_strategy: DelimiterStrategy::default(),
+            length_marker: None,
+            omit_lengths: false,
+            quote_policy: QuotePolicy::default(),
+            escape_non_ascii: false,
+            block_strings: None,
+            pretty: false,
+            align_table_columns: false,
+            float_format: FloatFormat::default(),
+            vector_format: VectorFormat::default(),
+            unsupported_policy: UnsupportedPolicy::default(),
+            lenient: false,
+            indent_width: None,
+            allow_tabs: false,
+            sniff_delimiters: false,
+            strip_code_fences: false,
+            preserve_number_text: false,
+            bytes_format: BytesFormat::default(),
+            enum_repr: EnumRepr::default(),
+            duplicate_keys: DuplicateKeys::default(),
+            deny_unknown_fields: false,
+            canonical_key_order: None,
+            non_string_keys: NonStringKeys::default(),
+            sparse_tables: None,
+            row_numbers: false,
+            skip_none_fields: false,
+            line_ending: LineEnding::default(),
+            trailing_newline: false,
+            compat: CompatMode::default(),
+            size_hint: None,
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            max_document_size: None,
+            max_array_length: None,
+            max_string_length: None,
+            key_aliases: KeyAliases::new(),
+            field_filter: None,
+            type_codecs: HashMap::new(),
+            env_lookup: None,
+        }
+    }
+}
+
+impl std::str::FromStr for ToonOptions {
+    type Err = crate::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        ToonOptions::preset(name)
+    }
+}
+
+impl ToonOptions {
+    /// Creates default options (compact format, comma delimiter, 2-space indent).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::ToonOptions;
+    ///
+    /// let options = ToonOptions::new();
+    /// assert_eq!(options.indent, 2);
+    /// assert!(!options.pretty);
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates options for pretty-printed output.
+    ///
+    /// Top-level fields holding nested objects, tables or lists are set apart
+    /// by blank lines, and table columns are aligned (see
+    /// [`with_align_table_columns`](Self::with_align_table_columns)). The output parses
+    /// back to the same value as compact output.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{toon, to_string_with_options, ToonOptions};
+    ///
+    /// let value = toon!({
+    ///     "name": "inventory",
+    ///     "items": [{"sku": "A1", "qty": 2}, {"sku": "B22", "qty": 10}],
+    ///     "owner": "ops"
+    /// });
+    /// assert_eq!(
+    ///     to_string_with_options(&value, ToonOptions::pretty()).unwrap(),
+    ///     "name: inventory\n\nitems: [2]{sku,qty}:\n  A1, 2\n  B22,10\n\nowner: ops"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn pretty() -> Self {
+        ToonOptions {
+            pretty: true,
+            align_table_columns: true,
+            ..Default::default()
+        }
+    }
+
+    /// Options for parsing input from untrusted sources, as used by
+    /// [`from_str_untrusted`](crate::from_str_untrusted).
+    ///
+    /// Besides the default depth limit, documents are limited to 16 MiB,
+    /// arrays to a million items and strings to 1 MiB.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::ToonOptions;
+    ///
+    /// let options = ToonOptions::untrusted().with_max_string_length(64 * 1024);
+    /// assert_eq!(options.max_array_length, Some(1_000_000));
+    /// ```
+    #[must_use]
+    pub fn untrusted() -> Self {
+        ToonOptions {
+            max_document_size: Some(16 * 1024 * 1024),
+            max_array_length: Some(1_000_000),
+            max_string_length: Some(1024 * 1024),
+            ..Default::default()
+        }
+    }
+
+    /// Options for documents read by a model, where every token counts.
+    ///
+    /// Fields holding `None` are left out, each array picks the delimiter
+    /// that needs the fewest quotes (see [`DelimiterStrategy::Auto`]), strings
+    /// are only quoted where they would otherwise read back differently, and
+    /// array headers leave out their lengths (see
+    /// [`with_omit_lengths`](Self::with_omit_lengths)).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{toon, to_string_with_options, ToonOptions};
+    ///
+    /// let value = toon!({"note": null, "tags": ["a,b", "c"]});
+    /// assert_eq!(
+    ///     to_string_with_options(&value, ToonOptions::llm_compact()).unwrap(),
+    ///     "tags: [|]: a,b|c"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn llm_compact() -> Self {
+        ToonOptions {
+            delimiter_strategy: DelimiterStrategy::Auto,
+            length_marker: None,
+            omit_lengths: true,
+            quote_policy: QuotePolicy::Minimal,
+            skip_none_fields: true,
+            ..Default::default()
+        }
+    }
+
+    /// Options for documents read by people, such as config files and logs.
+    ///
+    /// Like [`ToonOptions::pretty`], with blank lines between top-level
+    /// sections and aligned table columns, and the output ends with a line
+    /// break.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{toon, to_string_with_options, ToonOptions};
+    ///
+    /// let value = toon!({"name": "ops", "hosts": [{"id": 1, "ip": "10.0.0.1"}, {"id": 12, "ip": "10.0.0.12"}]});
+    /// assert_eq!(
+    ///     to_string_with_options(&value, ToonOptions::human()).unwrap(),
+    ///     "name: ops\n\nhosts: [2]{id,ip}:\n  1, 10.0.0.1\n  12,10.0.0.12\n"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn human() -> Self {
+        ToonOptions {
+            trailing_newline: true,
+            ..Self::pretty()
+        }
+    }
+
+    /// Options for exchanging documents between programs, where either side
+    /// may be another TOON implementation.
+    ///
+    /// Every string is quoted, so no reader has to guess whether `1.0` or
+    /// `true` was text. Parsing is strict: array lengths must match their
+    /// headers, blocks must be indented by exactly [`indent`](Self::indent)
+    /// spaces, and a repeated key is an error.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{from_str_with_options, to_string_with_options, ToonOptions, Value};
+    ///
+    /// let options = ToonOptions::strict_interchange();
+    /// assert_eq!(
+    ///     to_string_with_options(&vec!["1.0", "ok"], options.clone()).unwrap(),
+    ///     r#"[2]: "1.0","ok""#
+    /// );
+    /// assert!(from_str_with_options::<Value>("[3]: 1,2", options.clone()).is_err());
+    /// assert!(from_str_with_options::<Value>("a: 1\na: 2", options).is_err());
+    /// ```
+    #[must_use]
+    pub fn strict_interchange() -> Self {
+        ToonOptions {
+            quote_policy: QuotePolicy::AlwaysStrings,
+            lenient: false,
+            indent_width: Some(2),
+            duplicate_keys: DuplicateKeys::Error,
+            ..Default::default()
+        }
+    }
+
+    /// The names accepted by [`ToonOptions::preset`].
+    pub const PRESETS: &'static [&'static str] = &[
+        "default",
+        "pretty",
+        "untrusted",
+        "llm_compact",
+        "human",
+        "strict_interchange",
+    ];
+
+    /// Returns the preset options called `name`, for services that choose
+    /// their options in configuration. Names are those of the constructors,
+    /// such as `llm_compact` for [`ToonOptions::llm_compact`], and may use
+    /// `-` in place of `_`. [`ToonOptions::PRESETS`] lists them all.
+    ///
+    /// `ToonOptions` also implements [`FromStr`](std::str::FromStr) with this
+    /// method.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error listing the presets if there is none called `name`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::ToonOptions;
+    ///
+    /// let options = ToonOptions::preset("llm-compact").unwrap();
+    /// assert!(options.skip_none_fields);
+    ///
+    /// let options: ToonOptions = "human".parse().unwrap();
+    /// assert!(options.pretty);
+    ///
+    /// let err = ToonOptions::preset("tiny").unwrap_err();
+    /// assert!(err.to_string().contains("expected one of default, pretty"));
+    /// ```
+    pub fn preset(name: &str) -> Result<Self> {
+        match name.replace('-', "_").as_str() {
+            "default" => Ok(Self::default()),
+            "pretty" => Ok(Self::pretty()),
+            "untrusted" => Ok(Self::untrusted()),
+            "llm_compact" => Ok(Self::llm_compact()),
+            "human" => Ok(Self::human()),
+            "strict_interchange" => Ok(Self::strict_interchange()),
+            _ => Err(crate::Error::custom(format!(
+                "Unknown options preset `{}`, expected one of {}",
+                name,
+                Self::PRESETS.join(", ")
+            ))),
+        }
+    }
+
+    /// Sets the indentation size (number of spaces per level) of the output.
+    ///
+    /// Default is 2. To require a width when parsing, see
+    /// [`with_indent_width`](Self::with_indent_width).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::ToonOptions;
+    ///
+    /// let options = ToonOptions::pretty().with_indent(4);
+    /// assert_eq!(options.indent, 4);
+    /// ```
+    #[must_use]
+    pub fn with_indent(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Pads table cells so that every column starts at the same offset.
+    ///
+    /// Padding goes after the delimiter and is skipped when parsing, so aligned
+    /// tables read back unchanged. Default is `false`, or `true` for
+    /// [`ToonOptions::pretty`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{toon_table, to_string_with_options, ToonOptions};
+    ///
+    /// let table = toon_table! {
+    ///     (id, name, role);
+    ///     (1, "Alice", "admin");
+    ///     (10, "Bob", "user");
+    /// };
+    /// let options = ToonOptions::new().with_align_table_columns(true);
+    /// assert_eq!(
+    ///     to_string_with_options(&table, options).unwrap(),
+    ///     "[2]{id,name,role}:\n  1, Alice,admin\n  10,Bob,  user"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn with_align_table_columns(mut self, align: bool) -> Self {
+        self.align_table_columns = align;
+        self
+    }
+
+    /// Sets how floats are written; see [`FloatFormat`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{toon_table, to_string_with_options, FloatFormat, ToonOptions};
+    ///
+    /// let metrics = toon_table! {
+    ///     (step, loss, accuracy);
+    ///     (1, 0.693147, 0.5123);
+    ///     (2, 0.41872, 0.80391);
+    /// };
+    /// let options = ToonOptions::new().with_float_format(FloatFormat::new().with_max_decimals(3));
+    /// assert_eq!(
+    ///     to_string_with_options(&metrics, options).unwrap(),
+    ///     "[2]{step,loss,accuracy}:\n  1,0.693,0.512\n  2,0.419,0.804"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn with_float_format(mut self, format: FloatFormat) -> Self {
+        self.float_format = format;
+        self
+    }
+
+    /// Sets how vectors, long arrays of floats such as embeddings, are written
+    /// and read.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::{Deserialize, Serialize};
+    /// use serde_toon::{from_str_with_options, to_string_with_options, ToonOptions, VectorFormat};
+    ///
+    /// #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    /// struct Chunk {
+    ///     id: u32,
+    ///     embedding: Vec<f32>,
+    /// }
+    ///
+    /// let chunk = Chunk { id: 7, embedding: vec![0.5; 32] };
+    /// let options = ToonOptions::new().with_vector_format(VectorFormat::packed_f32());
+    /// let toon = to_string_with_options(&chunk, options.clone()).unwrap();
+    /// assert!(toon.starts_with("id: 7\nembedding: AAAAPwAAAD8"));
+    /// assert_eq!(from_str_with_options::<Chunk>(&toon, options).unwrap(), chunk);
+    /// ```
+    #[must_use]
+    pub fn with_vector_format(mut self, format: VectorFormat) -> Self {
+        self.vector_format = format;
+        self
+    }
+
+    /// Writes arrays of objects whose keys differ as tables, with `null` cells
+    /// for missing fields.
+    ///
+    /// By default an array of objects is only written as a table when every
+    /// object has the same keys. With this option the table's headers are the
+    /// union of all keys, as long as each key appears in at least
+    /// `min_presence` (a fraction from `0.0` to `1.0`) of the objects; arrays
+    /// with rarer keys are still written as lists, so no field is lost.
+    ///
+    /// A missing field reads back as `null`, which suits `Option` fields.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{to_string_with_options, toon, ToonOptions};
+    ///
+    /// let users = toon!([
+    ///     {"id": 1, "name": "Alice", "email": "a@example.com"},
+    ///     {"id": 2, "name": "Bob"},
+    ///     {"id": 3, "name": "Carol", "email": "c@example.com"}
+    /// ]);
+    /// let options = ToonOptions::new().with_sparse_tables(0.5);
+    /// assert_eq!(
+    ///     to_string_with_options(&users, options).unwrap(),
+    ///     "[3]{id,name,email}:\n  1,Alice,a@example.com\n  2,Bob,null\n  3,Carol,c@example.com"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn with_sparse_tables(mut self, min_presence: f64) -> Self {
+        self.sparse_tables = Some(min_presence);
+        self
+    }
+
+    /// Numbers table rows in a leading `#` column.
+    ///
+    /// When writing, every table gets a `#` column holding each row's 1-based
+    /// position, so a reader (such as a language model) can refer to "row 3".
+    /// When reading, a leading `#` column is checked against the row positions
+    /// and removed; a mismatch is an error, or a
+    /// [`WarningKind::RowNumberMismatch`](crate::WarningKind::RowNumberMismatch)
+    /// in lenient mode. Tables without a `#` column are read as usual.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{from_str_with_options, to_string_with_options, toon, ToonOptions, Value};
+    ///
+    /// let users = toon!([{"id": 7, "name": "Alice"}, {"id": 9, "name": "Bob"}]);
+    /// let options = ToonOptions::new().with_row_numbers(true);
+    /// let toon = to_string_with_options(&users, options.clone()).unwrap();
+    /// assert_eq!(toon, "[2]{#,id,name}:\n  1,7,Alice\n  2,9,Bob");
+    ///
+    /// assert_eq!(from_str_with_options::<Value>(&toon, options.clone()).unwrap(), users);
+    /// assert!(from_str_with_options::<Value>("[1]{#,id}:\n  5,7", options).is_err());
+    /// ```
+    #[must_use]
+    pub fn with_row_numbers(mut self, row_numbers: bool) -> Self {
+        self.row_numbers = row_numbers;
+        self
+    }
+
+    /// Leaves out object fields whose value is `null`, such as `None` fields
+    /// of structs, instead of writing `field: null`.
+    ///
+    /// Table cells are still written as `null`, since a table row needs a cell
+    /// for every column. Missing fields read back as `None` for `Option`
+    /// fields, with or without `#[serde(default)]`. Note that map entries with
+    /// a `null` value are left out too.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::{Deserialize, Serialize};
+    /// use serde_toon::{from_str, to_string_with_options, ToonOptions};
+    ///
+    /// #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    /// struct User {
+    ///     name: String,
+    ///     email: Option<String>,
+    /// }
+    ///
+    /// let user = User { name: "Ada".into(), email: None };
+    /// let options = ToonOptions::new().with_skip_none_fields(true);
+    /// let toon = to_string_with_options(&user, options).unwrap();
+    /// assert_eq!(toon, "name: Ada");
+    /// assert_eq!(from_str::<User>(&toon).unwrap(), user);
+    /// ```
+    #[must_use]
+    pub fn with_skip_none_fields(mut self, skip: bool) -> Self {
+        self.skip_none_fields = skip;
+        self
+    }
+
+    /// Sets the line break written between lines of output. Default is
+    /// [`LineEnding::Lf`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{to_string_with_options, LineEnding, ToonOptions};
+    ///
+    /// let options = ToonOptions::new().with_line_ending(LineEnding::CrLf);
+    /// let toon = to_string_with_options(&vec![vec![1, 2], vec![3]], options).unwrap();
+    /// assert_eq!(toon, "[2]:\r\n  - [2]: 1,2\r\n  - [1]: 3");
+    /// ```
+    #[must_use]
+    pub fn with_line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    /// Ends non-empty output with a line break, as many editors and POSIX
+    /// tools expect of text files. Default is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{to_string_with_options, toon, LineEnding, ToonOptions};
+    ///
+    /// let options = ToonOptions::new().with_trailing_newline(true);
+    /// let toon = to_string_with_options(&toon!({"a": 1, "b": 2}), options.clone()).unwrap();
+    /// assert_eq!(toon, "a: 1\nb: 2\n");
+    ///
+    /// let options = options.with_line_ending(LineEnding::CrLf);
+    /// let toon = to_string_with_options(&toon!({"a": 1, "b": 2}), options).unwrap();
+    /// assert_eq!(toon, "a: 1\r\nb: 2\r\n");
+    /// ```
+    #[must_use]
+    pub fn with_trailing_newline(mut self, trailing_newline: bool) -> Self {
+        self.trailing_newline = trailing_newline;
+        self
+    }
+
+    /// Sets which implementation's output to reproduce; see [`CompatMode`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{to_string_with_options, toon, CompatMode, ToonOptions};
+    ///
+    /// let value = toon!({"users": [{"id": 1, "name": "Ada"}], "note": "-1 left"});
+    /// let options = ToonOptions::new().with_compat(CompatMode::ReferenceTs);
+    /// assert_eq!(
+    ///     to_string_with_options(&value, options).unwrap(),
+    ///     "users[1]{id,name}:\n  1,Ada\nnote: \"-1 left\""
+    /// );
+    /// ```
+    #[must_use]
+    pub fn with_compat(mut self, compat: CompatMode) -> Self {
+        self.compat = compat;
+        self
+    }
+
+    /// Sets the expected length in bytes of the output, which the serializer
+    /// allocates up front instead of growing its buffer from 256 bytes.
+    ///
+    /// A hint that is too small costs only the reallocations it would have
+    /// saved. [`estimated_size`](crate::estimated_size) gives the exact
+    /// length without writing the output.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{estimated_size, to_string_with_options, ToonOptions};
+    ///
+    /// let rows: Vec<(u32, String)> = (0..1000).map(|i| (i, format!("row {}", i))).collect();
+    /// let size = estimated_size(&rows).unwrap();
+    /// let options = ToonOptions::new().with_size_hint(size.bytes);
+    /// let toon = to_string_with_options(&rows, options).unwrap();
+    /// assert_eq!(toon.len(), size.bytes);
+    /// ```
+    #[must_use]
+    pub fn with_size_hint(mut self, bytes: usize) -> Self {
+        self.size_hint = Some(bytes);
+        self
+    }
+
+    /// Limits how deeply objects and arrays may nest in parsed input.
+    ///
+    /// The root object or array is level 1. Deeper input fails with
+    /// [`Error::LimitExceeded`](crate::Error::LimitExceeded) instead of
+    /// exhausting the stack. The default is [`DEFAULT_MAX_DEPTH`]; set the
+    /// `max_depth` field to `None` to remove the limit.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{from_str_with_options, ToonOptions, Value};
+    ///
+    /// let options = ToonOptions::new().with_max_depth(2);
+    /// assert!(from_str_with_options::<Value>("a:\n  b: 1", options.clone()).is_ok());
+    /// let err = from_str_with_options::<Value>("a:\n  b:\n    c: 1", options).unwrap_err();
+    /// assert_eq!(err.to_string(), "Input exceeds max_depth of 2 at line 3, column 5");
+    /// ```
+    #[must_use]
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Limits the size of parsed input, in bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{from_str_with_options, ToonOptions, Value};
+    ///
+    /// let options = ToonOptions::new().with_max_document_size(1024);
+    /// let huge = "x: ".to_string() + &"y".repeat(2000);
+    /// assert!(from_str_with_options::<Value>(&huge, options).is_err());
+    /// ```
+    #[must_use]
+    pub fn with_max_document_size(mut self, bytes: usize) -> Self {
+        self.max_document_size = Some(bytes);
+        self
+    }
+
+    /// Limits the number of items or rows in a parsed array.
+    ///
+    /// A declared length (`[1000000]:`) over the limit is rejected before any
+    /// item is read, as are arrays whose items turn out to exceed it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{from_str_with_options, ToonOptions, Value};
+    ///
+    /// let options = ToonOptions::new().with_max_array_length(100);
+    /// let bomb = "rows: [4294967295]{id}:\n  1";
+    /// assert!(from_str_with_options::<Value>(bomb, options).is_err());
+    /// ```
+    #[must_use]
+    pub fn with_max_array_length(mut self, length: usize) -> Self {
+        self.max_array_length = Some(length);
+        self
+    }
+
+    /// Limits the length of parsed strings and keys, in bytes after escapes
+    /// are resolved.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{from_str_with_options, ToonOptions, Value};
+    ///
+    /// let options = ToonOptions::new().with_max_string_length(8);
+    /// assert!(from_str_with_options::<Value>("name: \"Ada\"", options.clone()).is_ok());
+    /// assert!(from_str_with_options::<Value>("name: Ada Lovelace", options).is_err());
+    /// ```
+    #[must_use]
+    pub fn with_max_string_length(mut self, length: usize) -> Self {
+        self.max_string_length = Some(length);
+        self
+    }
+
+    /// Sets the delimiter for arrays and tables.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{ToonOptions, Delimiter};
+    ///
+    /// let options = ToonOptions::new().with_delimiter(Delimiter::Pipe);
+    /// ```
+    #[must_use]
+    pub fn with_delimiter(mut self, delimiter: Delimiter) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Sets how each array's delimiter is chosen; see [`DelimiterStrategy`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{DelimiterStrategy, ToonOptions};
+    ///
+    /// let options = ToonOptions::new().with_delimiter_strategy(DelimiterStrategy::Auto);
+    /// assert_eq!(options.delimiter_strategy, DelimiterStrategy::Auto);
+    /// ```
+    #[must_use]
+    pub fn with_delimiter_strategy(mut self, strategy: DelimiterStrategy) -> Self {
+        self.delimiter_strategy = strategy;
+        self
+    }
+
+    /// Sets when string values are quoted; see [`QuotePolicy`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::{Deserialize, Serialize};
+    /// use serde_toon::{from_str_with_options, to_string_with_options, QuotePolicy, ToonOptions};
+    ///
+    /// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    /// struct Account { id: String, open: String }
+    ///
+    /// let options = ToonOptions::new().with_quote_policy(QuotePolicy::PreserveInput);
+    /// let account = Account { id: "12345".into(), open: "true".into() };
+    /// let toon = to_string_with_options(&account, options.clone()).unwrap();
+    /// assert_eq!(toon, "id: 12345\nopen: true");
+    /// let back: Account = from_str_with_options(&toon, options).unwrap();
+    /// assert_eq!(back, account);
+    /// ```
+    #[must_use]
+    pub fn with_quote_policy(mut self, policy: QuotePolicy) -> Self {
+        self.quote_policy = policy;
+        self
+    }
+
+    /// Writes every non-ASCII character in strings and keys as a `\u{...}`
+    /// escape, quoting them as needed, so the output is plain ASCII.
+    ///
+    /// Invisible characters such as zero-width spaces and bidi controls are
+    /// always escaped this way.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{to_string_with_options, ToonOptions};
+    ///
+    /// let options = ToonOptions::new().with_escape_non_ascii(true);
+    /// let toon = to_string_with_options(&vec!["café"], options).unwrap();
+    /// assert_eq!(toon, r#"[1]: "caf\u{e9}""#);
+    /// ```
+    #[must_use]
+    pub fn with_escape_non_ascii(mut self, escape: bool) -> Self {
+        self.escape_non_ascii = escape;
+        self
+    }
+
+    /// Writes string field values that contain line breaks, or are longer
+    /// than `min_chars` characters, as block strings: a `|` after the key and
+    /// the text on indented lines below it.
+    ///
+    /// Strings whose text a block cannot hold exactly, such as those with
+    /// trailing whitespace on a line, a trailing line break or invisible
+    /// characters, stay quoted. Arrays of objects with a field written as a
+    /// block string are written as lists rather than tables. Block strings are
+    /// always read when parsing.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_toon::{to_string_with_options, ToonOptions};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Prompt { name: String, text: String }
+    ///
+    /// let prompt = Prompt { name: "greet".into(), text: "Say hello.\nBe brief.".into() };
+    /// let options = ToonOptions::new().with_block_strings(80);
+    /// let toon = to_string_with_options(&prompt, options).unwrap();
+    /// assert_eq!(toon, "name: greet\ntext: |\n  Say hello.\n  Be brief.");
+    /// ```
+    #[must_use]
+    pub fn with_block_strings(mut self, min_chars: usize) -> Self {
+        self.block_strings = Some(min_chars);
+        self
+    }
+
+    /// Sets an optional length marker character for arrays.
+    ///
+    /// When set, array lengths are prefixed with this character (e.g., `[#3]` instead of `[3]`).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::ToonOptions;
+    ///
+    /// let options = ToonOptions::new().with_length_marker('#');
+    /// ```
+    #[must_use]
+    pub fn with_length_marker(mut self, marker: char) -> Self {
+        self.length_marker = Some(marker);
+        self
+    }
+
+    /// Leaves the length out of array and table headers, writing `[]: 1,2,3`
+    /// and `[]{id,name}:`, to save the tokens the lengths cost.
+    ///
+    /// Headers without a length are always accepted when parsing, with or
+    /// without this option: the items run to the end of the line, or of the
+    /// indented block. Headers that do give a length are still checked
+    /// against it. Default is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{from_str_with_options, toon, to_string_with_options, ToonOptions, Value};
+    ///
+    /// let value = toon!({"ids": [1, 2, 3], "users": [{"id": 1, "name": "Ada"}]});
+    /// let options = ToonOptions::new().with_omit_lengths(true);
+    /// let toon = to_string_with_options(&value, options.clone()).unwrap();
+    /// assert_eq!(toon, "ids: []: 1,2,3\nusers: []{id,name}:\n  1,Ada");
+    /// assert_eq!(from_str_with_options::<Value>(&toon, options).unwrap(), value);
+    /// ```
+    #[must_use]
+    pub fn with_omit_lengths(mut self, omit: bool) -> Self {
+        self.omit_lengths = omit;
+        self
+    }
+
+    /// Sets how values that TOON cannot represent are handled.
+    ///
+    /// See [`UnsupportedPolicy`] for the available behaviors.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{ToonOptions, UnsupportedPolicy};
+    ///
+    /// let options = ToonOptions::new().with_unsupported_policy(UnsupportedPolicy::Null);
+    /// ```
+    #[must_use]
+    pub fn with_unsupported_policy(mut self, policy: UnsupportedPolicy) -> Self {
+        self.unsupported_policy = policy;
+        self
+    }
+
+    /// Enables lenient parsing, which recovers from common defects in the input.
+    ///
+    /// Wrong array lengths are corrected by counting the actual items,
+    /// unterminated strings are closed at the end of the line, and trailing prose is
+    /// skipped. Each fix is recorded as a [`Warning`](crate::Warning). Default is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{from_str_with_options, ToonOptions};
+    ///
+    /// let options = ToonOptions::new().with_lenient(true);
+    /// let nums: Vec<i32> = from_str_with_options("[2]: 1,2,3", options).unwrap();
+    /// assert_eq!(nums, vec![1, 2, 3]);
+    /// ```
+    #[must_use]
+    pub fn with_lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Requires every indented block to be exactly `width` spaces deeper than
+    /// the one it is in when parsing.
+    ///
+    /// Whatever the width, a block may only follow a line that opens one
+    /// (`key:` or an array header), and a line that ends blocks must line up
+    /// with an enclosing one. Other indentation is an
+    /// [`Error::IndentationError`](crate::Error::IndentationError), or a
+    /// warning in lenient mode. Default is `None`, accepting any width.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{from_str_with_options, Error, ToonOptions, Value};
+    ///
+    /// let options = ToonOptions::new().with_indent_width(2);
+    /// assert!(from_str_with_options::<Value>("a:\n  b: 1", options.clone()).is_ok());
+    /// let err = from_str_with_options::<Value>("a:\n    b: 1", options).unwrap_err();
+    /// assert!(matches!(err, Error::IndentationError { expected: 2, found: 4, .. }));
+    /// ```
+    #[must_use]
+    pub fn with_indent_width(mut self, width: usize) -> Self {
+        self.indent_width = Some(width);
+        self
+    }
+
+    /// Accepts tabs in indentation when parsing, each counting as one level:
+    /// [`indent_width`](Self::with_indent_width) spaces, or else
+    /// [`indent`](Self::with_indent). Default is `false`, rejecting tabs.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{from_str, from_str_with_options, ToonOptions, Value};
+    ///
+    /// assert!(from_str::<Value>("a:\n\tb: 1").is_err());
+    /// let options = ToonOptions::new().with_allow_tabs(true);
+    /// let value: Value = from_str_with_options("a:\n\tb: 1", options).unwrap();
+    /// assert_eq!(value.get_as::<i64>("a.b").unwrap(), 1);
+    /// ```
+    #[must_use]
+    pub fn with_allow_tabs(mut self, allow: bool) -> Self {
+        self.allow_tabs = allow;
+        self
+    }
+
+    /// Detects the delimiter of arrays whose header does not name one from
+    /// their first row when parsing.
+    ///
+    /// A header names a pipe (`[2|]`) or tab delimiter after its length, and
+    /// otherwise means a comma. With sniffing, such a header instead takes the
+    /// first of comma, tab and pipe that splits the first table row into one
+    /// value per field, or the inline items into the declared length, which
+    /// reads hand-written or LLM-written arrays that left out the marker.
+    /// [`Deserializer::detected_delimiter`](crate::Deserializer::detected_delimiter)
+    /// reports the result. Default is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{from_str_with_options, toon, ToonOptions, Value};
+    ///
+    /// let toon = "[2]{id|name}:\n  1|Ada\n  2|Bob";
+    /// let options = ToonOptions::new().with_sniff_delimiters(true);
+    /// let value: Value = from_str_with_options(toon, options).unwrap();
+    /// assert_eq!(value, toon!([{"id": 1, "name": "Ada"}, {"id": 2, "name": "Bob"}]));
+    /// ```
+    #[must_use]
+    pub fn with_sniff_delimiters(mut self, sniff: bool) -> Self {
+        self.sniff_delimiters = sniff;
+        self
+    }
+
+    /// Skips a Markdown code fence around the document when parsing, such as
+    /// a model's reply that wraps the data in ```` ```toon ```` and
+    /// ```` ``` ````.
+    ///
+    /// A line starting with ```` ``` ```` is skipped when it comes before the
+    /// first line of the document or after its last; fences elsewhere are read
+    /// as usual. A byte order mark and blank lines before the document are
+    /// skipped whatever this is set to. Default is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{from_str, from_str_with_options, toon, ToonOptions, Value};
+    ///
+    /// let reply = "\n```toon\nid: 7\ntags: [2]: a,b\n```\n";
+    /// assert!(from_str::<Value>(reply).is_err());
+    ///
+    /// let options = ToonOptions::new().with_strip_code_fences(true);
+    /// let value: Value = from_str_with_options(reply, options).unwrap();
+    /// assert_eq!(value, toon!({"id": 7, "tags": ["a", "b"]}));
+    /// ```
+    #[must_use]
+    pub fn with_strip_code_fences(mut self, strip: bool) -> Self {
+        self.strip_code_fences = strip;
+        self
+    }
+
+    /// Keeps the text of parsed numbers that TOON would write differently,
+    /// such as `1.50`, `1e3` or `-0`, as a [`Number::Raw`](crate::Number::Raw),
+    /// so that serializing the value writes them back exactly as read.
+    ///
+    /// The numbers still compare, convert and deserialize into numeric types
+    /// by value. When deserializing, a kept number reaches a
+    /// [`Value`] intact but looks like a map to other types
+    /// that accept anything, such as untagged enums; and serializers other
+    /// than this crate's see it as a string. Default is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{from_str_with_options, to_string, toon, ToonOptions, Value};
+    ///
+    /// let options = ToonOptions::new().with_preserve_number_text(true);
+    /// let value: Value = from_str_with_options("price: 1.50\nqty: 2", options).unwrap();
+    /// assert_eq!(value, toon!({"price": 1.5, "qty": 2}));
+    /// assert_eq!(to_string(&value).unwrap(), "price: 1.50\nqty: 2");
+    /// ```
+    #[must_use]
+    pub fn with_preserve_number_text(mut self, preserve: bool) -> Self {
+        self.preserve_number_text = preserve;
+        self
+    }
+
+    /// Sets how byte buffers are written and read.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{to_string_with_options, BytesFormat, ToonOptions};
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Blob<'a> {
+    ///     #[serde(with = "serde_bytes_like")]
+    ///     data: &'a [u8],
+    /// }
+    /// # mod serde_bytes_like {
+    /// #     pub fn serialize<S: serde::Serializer>(v: &[u8], s: S) -> Result<S::Ok, S::Error> {
+    /// #         s.serialize_bytes(v)
+    /// #     }
+    /// # }
+    ///
+    /// let blob = Blob { data: b"hi!" };
+    /// let options = ToonOptions::new().with_bytes_format(BytesFormat::Base64);
+    /// assert_eq!(to_string_with_options(&blob, options).unwrap(), "data: aGkh");
+    ///
+    /// let options = ToonOptions::new().with_bytes_format(BytesFormat::Hex);
+    /// assert_eq!(to_string_with_options(&blob, options).unwrap(), "data: \"686921\"");
+    /// ```
+    #[must_use]
+    pub fn with_bytes_format(mut self, format: BytesFormat) -> Self {
+        self.bytes_format = format;
+        self
+    }
+
+    /// Sets how enum variants are represented when serializing and deserializing.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_toon::{to_string_with_options, EnumRepr, ToonOptions};
+    ///
+    /// #[derive(Serialize)]
+    /// enum Event {
+    ///     Click(u32),
+    /// }
+    ///
+    /// let options = ToonOptions::new().with_enum_repr(EnumRepr::adjacent("kind", "data"));
+    /// assert_eq!(
+    ///     to_string_with_options(&Event::Click(3), options).unwrap(),
+    ///     "kind: Click\ndata: 3"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn with_enum_repr(mut self, repr: EnumRepr) -> Self {
+        self.enum_repr = repr;
+        self
+    }
+
+    /// Sets how repeated keys within one object are handled when parsing.
+    ///
+    /// See [`DuplicateKeys`] for the available behaviors.
+    #[must_use]
+    pub fn with_duplicate_keys(mut self, duplicate_keys: DuplicateKeys) -> Self {
+        self.duplicate_keys = duplicate_keys;
+        self
+    }
+
+    /// Rejects object keys that the target struct does not declare, like
+    /// `#[serde(deny_unknown_fields)]` on every struct. Default is `false`.
+    ///
+    /// The error is [`Error::UnknownField`](crate::Error::UnknownField) and
+    /// names the path of the offending key.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::Deserialize;
+    /// use serde_toon::{from_str_with_options, ToonOptions};
+    ///
+    /// #[derive(Debug, Deserialize)]
+    /// struct User { id: u32 }
+    ///
+    /// #[derive(Debug, Deserialize)]
+    /// struct Team { users: Vec<User> }
+    ///
+    /// let options = ToonOptions::new().with_deny_unknown_fields(true);
+    /// let err = from_str_with_options::<Team>("users: [2]{id,role}:\n  1,admin\n  2,dev", options)
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "Unknown field `role` at users[0].role, expected one of: id"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn with_deny_unknown_fields(mut self, deny: bool) -> Self {
+        self.deny_unknown_fields = deny;
+        self
+    }
+
+    /// Writes object keys and table columns in a canonical order.
+    ///
+    /// Without this (`canonical_key_order: None`, the default) keys are written
+    /// in insertion order, the same as [`KeyOrder::Insertion`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_toon::{to_string_with_options, KeyOrder, ToonOptions};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Point { y: i32, x: i32 }
+    ///
+    /// let options = ToonOptions::new().with_canonical_key_order(KeyOrder::Alphabetical);
+    /// assert_eq!(
+    ///     to_string_with_options(&Point { y: 2, x: 1 }, options).unwrap(),
+    ///     "x: 1\ny: 2"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn with_canonical_key_order(mut self, order: KeyOrder) -> Self {
+        self.canonical_key_order = Some(order);
+        self
+    }
+
+    /// Sorts the keys of every object in the output, and the columns of every
+    /// table, so that it does not depend on the iteration order of a
+    /// `HashMap` or on how the data was built: for snapshot tests and cache
+    /// keys.
+    ///
+    /// Shorthand for [`with_canonical_key_order`](Self::with_canonical_key_order)
+    /// with [`KeyOrder::Alphabetical`], or with [`KeyOrder::Insertion`] for
+    /// `false`. A [`ToonWriter`](crate::ToonWriter) writes keys as they come
+    /// either way.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::collections::HashMap;
+    /// use serde_toon::{to_string_with_options, ToonOptions};
+    ///
+    /// let scores: HashMap<&str, Vec<HashMap<&str, u32>>> = [
+    ///     ("zed", vec![[("try", 2), ("best", 9)].into()]),
+    ///     ("amy", vec![[("try", 1), ("best", 7)].into()]),
+    /// ]
+    /// .into();
+    /// let options = ToonOptions::new().with_sort_keys(true);
+    /// assert_eq!(
+    ///     to_string_with_options(&scores, options).unwrap(),
+    ///     "amy: [1]{best,try}:\n  7,1\nzed: [1]{best,try}:\n  9,2"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn with_sort_keys(self, sort: bool) -> Self {
+        self.with_canonical_key_order(if sort {
+            KeyOrder::Alphabetical
+        } else {
+            KeyOrder::Insertion
+        })
+    }
+
+    /// Sets whether integer and boolean map keys are written as strings.
+    ///
+    /// See [`NonStringKeys`] for the available behaviors.
+    #[must_use]
+    pub fn with_non_string_keys(mut self, non_string_keys: NonStringKeys) -> Self {
+        self.non_string_keys = non_string_keys;
+        self
+    }
+
+    /// Writes keys as short aliases and reads the aliases back as the full
+    /// keys.
+    ///
+    /// `aliases` pairs each key with its alias; a `HashMap`, `BTreeMap`, array
+    /// of pairs or [`KeyAliases`] all work. With
+    /// [`KeyOrder::Alphabetical`], keys are sorted by the text written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::{Deserialize, Serialize};
+    /// use serde_toon::{from_str_with_options, to_string_with_options, ToonOptions};
+    ///
+    /// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    /// struct Tool { name: String, description: String }
+    ///
+    /// let options = ToonOptions::new().with_key_aliases([("description", "desc")]);
+    /// let tools = vec![Tool { name: "grep".into(), description: "Search files".into() }];
+    ///
+    /// let toon = to_string_with_options(&tools, options.clone()).unwrap();
+    /// assert_eq!(toon, "[1]{name,desc}:\n  grep,Search files");
+    /// assert_eq!(from_str_with_options::<Vec<Tool>>(&toon, options).unwrap(), tools);
+    /// ```
+    #[must_use]
+    pub fn with_key_aliases<I, K, A>(mut self, aliases: I) -> Self
+    where
+        I: IntoIterator<Item = (K, A)>,
+        K: Into<String>,
+        A: Into<String>,
+    {
+        self.key_aliases = aliases.into_iter().collect();
+        self
+    }
+
+    /// Omits from serialized output every object field for which `keep`
+    /// returns `false`, given the field's [`Path`].
+    ///
+    /// The filter applies to the fields of structs, maps, struct variants and
+    /// [`Value`] objects alike, at any depth, and to table rows
+    /// (a cell is at `rows[i].column`). A field that is left out is never
+    /// serialized, so no part of it reaches the output. Filters set with this
+    /// method, [`Self::with_masked_fields`] and [`Self::with_field_action`]
+    /// apply in the order they are set, and the first that does not keep a
+    /// field decides what happens to it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_toon::{to_string_with_options, ToonOptions};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Login { user: String, password: String }
+    ///
+    /// let options = ToonOptions::new().with_field_filter(|path| !path.matches("**.password"));
+    /// let login = Login { user: "ada".into(), password: "hunter2".into() };
+    /// assert_eq!(to_string_with_options(&login, options).unwrap(), "user: ada");
+    /// ```
+    #[must_use]
+    pub fn with_field_filter<F>(self, keep: F) -> Self
+    where
+        F: Fn(&Path) -> bool + Send + Sync + 'static,
+    {
+        self.with_field_action(move |path| {
+            if keep(path) {
+                FieldAction::Keep
+            } else {
+                FieldAction::Omit
+            }
+        })
+    }
+
+    /// Writes `mask` in place of the value of every field whose path matches
+    /// one of `patterns` (see [`Path::matches`]), such as `**.api_key` or
+    /// `users[*].email`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{to_string_with_options, toon, ToonOptions};
+    ///
+    /// let config = toon!({"name": "svc", "auth": {"api_key": "sk-123"}});
+    /// let options = ToonOptions::new().with_masked_fields(["**.api_key"], "***");
+    /// assert_eq!(
+    ///     to_string_with_options(&config, options).unwrap(),
+    ///     "name: svc\nauth:\n  api_key: ***"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn with_masked_fields<I, S>(self, patterns: I, mask: &str) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let patterns: Vec<String> = patterns.into_iter().map(Into::into).collect();
+        let mask = mask.to_string();
+        self.with_field_action(move |path| {
+            if patterns.iter().any(|pattern| path.matches(pattern)) {
+                FieldAction::Mask(mask.clone())
+            } else {
+                FieldAction::Keep
+            }
+        })
+    }
+
+    /// Decides with `action` whether each object field is written, omitted
+    /// or masked when serializing, after any filter set before.
+    #[must_use]
+    pub fn with_field_action<F>(mut self, action: F) -> Self
+    where
+        F: Fn(&Path) -> FieldAction + Send + Sync + 'static,
+    {
+        self.field_filter = Some(match self.field_filter.take() {
+            None => FieldFilter::new(action),
+            Some(earlier) => FieldFilter::new(move |path| match earlier.action(path) {
+                FieldAction::Keep => action(path),
+                decided => decided,
+            }),
+        });
+        self
+    }
+
+    /// Returns what to do with the field at `path` when serializing.
+    pub(crate) fn field_action(&self, path: &Path) -> FieldAction {
+        match &self.field_filter {
+            Some(filter) => filter.action(path),
+            None => FieldAction::Keep,
+        }
+    }
+
+    /// Writes values of the struct, tuple struct or newtype struct named
+    /// `name` with `codec`, and reads them back with it. The name is the one
+    /// serde sees, which `#[serde(rename)]` changes. Setting a codec for a
+    /// name again replaces the earlier one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::{Deserialize, Serialize};
+    /// use serde_toon::{from_str_with_options, to_string_with_options, toon, Error, ToonOptions, TypeCodec, Value};
+    /// use std::time::Duration;
+    ///
+    /// #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    /// struct Job { name: String, timeout: Duration }
+    ///
+    /// let duration = TypeCodec::new(
+    ///     |value| {
+    ///         let secs = value.as_object().and_then(|d| d.get("secs")).and_then(Value::as_u64);
+    ///         let secs = secs.ok_or_else(|| Error::custom("expected a duration"))?;
+    ///         Ok(Value::from(format!("{}h{}m", secs / 3600, secs / 60 % 60)))
+    ///     },
+    ///     |value| {
+    ///         let text = value.as_str().and_then(|text| text.strip_suffix('m'));
+    ///         let (hours, minutes) = text
+    ///             .and_then(|text| text.split_once('h'))
+    ///             .ok_or_else(|| Error::custom("expected a duration like 1h30m"))?;
+    ///         let hours: u64 = hours.parse().map_err(Error::custom)?;
+    ///         let minutes: u64 = minutes.parse().map_err(Error::custom)?;
+    ///         Ok(toon!({"secs": hours * 3600 + minutes * 60, "nanos": 0}))
+    ///     },
+    /// );
+    /// let options = ToonOptions::new().with_type_codec("Duration", duration);
+    ///
+    /// let job = Job { name: "backup".into(), timeout: Duration::from_secs(5400) };
+    /// let toon = to_string_with_options(&job, options.clone()).unwrap();
+    /// assert_eq!(toon, "name: backup\ntimeout: 1h30m");
+    /// assert_eq!(from_str_with_options::<Job>(&toon, options).unwrap(), job);
+    /// ```
+    #[must_use]
+    pub fn with_type_codec(mut self, name: impl Into<String>, codec: TypeCodec) -> Self {
+        self.type_codecs.insert(name.into(), codec);
+        self
+    }
+
+    /// Returns the codec set for the type named `name`, if any.
+    pub(crate) fn type_codec(&self, name: &str) -> Option<&TypeCodec> {
+        if self.type_codecs.is_empty() {
+            return None;
+        }
+        self.type_codecs.get(name)
+    }
+
+    /// Substitutes environment variables into string values when parsing,
+    /// for config files that take settings such as secrets from the
+    /// environment. Default is `false`.
+    ///
+    /// `${NAME}` is replaced by the variable's value and is an error if it is
+    /// unset; `${NAME:-default}` falls back to `default` when the variable is
+    /// unset or empty. `$${` stands for a literal `${`. Unquoted values are
+    /// substituted before they are typed, so `port: ${PORT}` reads as a
+    /// number when `PORT` holds one; quoted and block strings stay strings.
+    /// Keys are never substituted. Use
+    /// [`with_env_lookup`](Self::with_env_lookup) to read variables from
+    /// somewhere other than the process environment.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{from_str_with_options, toon, ToonOptions, Value};
+    ///
+    /// let options = ToonOptions::new().with_env_interpolation(true);
+    /// let config = "home: ${HOME}\nlevel: ${SERDE_TOON_UNSET:-info}";
+    /// let value: Value = from_str_with_options(config, options).unwrap();
+    /// let home = std::env::var("HOME").unwrap_or_default();
+    /// assert_eq!(value, toon!({"home": home, "level": "info"}));
+    /// ```
+    #[must_use]
+    pub fn with_env_interpolation(mut self, interpolate: bool) -> Self {
+        self.env_lookup = interpolate.then(|| EnvLookup::new(|name| std::env::var(name).ok()));
+        self
+    }
+
+    /// Substitutes variables into string values when parsing, as
+    /// [`with_env_interpolation`](Self::with_env_interpolation) does, but
+    /// looks them up with `lookup` in place of the process environment.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::Deserialize;
+    /// use serde_toon::{from_str_with_options, ToonOptions};
+    ///
+    /// #[derive(Deserialize, Debug, PartialEq)]
+    /// struct Database { url: String, pool: u32 }
+    ///
+    /// let options = ToonOptions::new().with_env_lookup(|name| match name {
+    ///     "DB_HOST" => Some("db.internal".to_string()),
+    ///     "DB_POOL" => Some("20".to_string()),
+    ///     _ => None,
+    /// });
+    /// let config = "url: \"postgres://${DB_HOST}:${DB_PORT:-5432}/app\"\npool: ${DB_POOL}";
+    /// let database: Database = from_str_with_options(config, options.clone()).unwrap();
+    /// assert_eq!(database.url, "postgres://db.internal:5432/app");
+    /// assert_eq!(database.pool, 20);
+    ///
+    /// let err = from_str_with_options::<Database>("url: ${DB_URL}\npool: 1", options).unwrap_err();
+    /// assert!(err.to_string().contains("Environment variable `DB_URL` is not set"));
+    /// ```
+    #[must_use]
+    pub fn with_env_lookup<F>(mut self, lookup: F) -> Self
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.env_lookup = Some(EnvLookup::new(lookup));
+        self
+    }
+
+    /// Returns the text written for `key`: its alias, or the key itself.
+    pub(crate) fn written_key<'a>(&'a self, key: &'a str) -> &'a str {
+        self.key_aliases.alias(key).unwrap_or(key)
+    }
+
+    /// Returns `true` if keys should be written in alphabetical order.
+    pub(crate) fn sorts_keys(&self) -> bool {
+        self.canonical_key_order == Some(KeyOrder::Alphabetical)
+    }
+
+    /// Returns `true` if output should match the reference TypeScript encoder.
+    pub(crate) fn matches_reference(&self) -> bool {
+        self.compat == CompatMode::ReferenceTs
+    }
+}
@@ -0,0 +1,250 @@
+//! Document outlines for navigation UIs.
+//!
+//! [`outline`] scans a TOON document line by line and returns one
+//! [`OutlineNode`] per field and list item, with its path, kind and byte span.
+//! Values are never materialized, so this is cheap even for large documents and
+//! works on documents that would not fully parse.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde_toon::outline::{outline, OutlineKind};
+//!
+//! let doc = "name: Store\nproducts: [2]{id,name}:\n  1,Widget\n  2,Gadget";
+//! let nodes = outline(doc);
+//!
+//! assert_eq!(nodes[1].path.to_string(), "products");
+//! assert_eq!(nodes[1].kind, OutlineKind::Table);
+//! assert_eq!(nodes[1].row_count, Some(2));
+//! assert_eq!(&doc[nodes[1].span.clone()], "products: [2]{id,name}:\n  1,Widget\n  2,Gadget");
+//! ```
+
+use crate::lexer::{self, Header, LineKind};
+use crate::path::{Path, PathSegment};
+use std::ops::Range;
+
+/// The kind of value an [`OutlineNode`] points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutlineKind {
+    /// A nested object.
+    Object,
+    /// An inline or list-form array.
+    Array,
+    /// A tabular array.
+    Table,
+    /// A primitive value.
+    Value,
+}
+
+/// An entry in a document outline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlineNode {
+    /// Location of the value in the document.
+    pub path: Path,
+    pub kind: OutlineKind,
+    /// Byte range covering the node's first line through its last nested line.
+    pub span: Range<usize>,
+    /// 1-based line number where the node starts.
+    pub line: usize,
+    /// Number of nesting levels below the root.
+    pub depth: usize,
+    /// Number of rows (tables) or items (arrays); `None` for other kinds.
+    pub row_count: Option<usize>,
+}
+
+/// A container that may still receive nested lines.
+struct Open {
+    /// Lines must be indented deeper than this to belong to the container.
+    indent: usize,
+    /// Index of the node in the output, or `None` for the root.
+    node: Option<usize>,
+    path: Path,
+    kind: OutlineKind,
+    items: usize,
+}
+
+/// Builds an outline of `input`.
+///
+/// Nodes are returned in document order. Malformed lines are skipped rather than
+/// reported, so an outline can be shown while a document is being edited.
+#[must_use]
+pub fn outline(input: &str) -> Vec<OutlineNode> {
+    let mut nodes: Vec<OutlineNode> = Vec::new();
+    let mut stack = vec![Open {
+        indent: 0,
+        node: None,
+        path: Path::root(),
+        kind: OutlineKind::Object,
+        items: 0,
+    }];
+
+    for line in lexer::lines(input) {
+        // Close containers this line is not nested in
+        while stack.len() > 1 && stack.last().is_some_and(|open| line.indent <= open.indent) {
+            stack.pop();
+        }
+        for open in &stack {
+            if let Some(index) = open.node {
+                nodes[index].span.end = line.span.end;
+            }
+        }
+
+        // The root is never popped
+        let Some(parent) = stack.last_mut() else {
+            break;
+        };
+        match (parent.kind, line.kind()) {
+            // The lines of a block string
+            (OutlineKind::Value, _) => {}
+            (OutlineKind::Table, _) => {
+                parent.items += 1;
+                set_count(&mut nodes, parent);
+            }
+            (OutlineKind::Array, LineKind::Item { content, offset }) => {
+                let mut path = parent.path.clone();
+                path.push(PathSegment::Index(parent.items));
+                parent.items += 1;
+                set_count(&mut nodes, parent);
+
+                let item_indent = line.indent;
+                let content_indent = line.indent + offset;
+                let span = line.span.clone();
+                match lexer::classify(content) {
+                    LineKind::Field { key, header, value } => {
+                        // The item is an object whose first field shares the dash line
+                        let index =
+                            push_node(&mut nodes, &path, OutlineKind::Object, &span, line.number);
+                        stack.push(Open {
+                            indent: item_indent,
+                            node: Some(index),
+                            path: path.clone(),
+                            kind: OutlineKind::Object,
+                            items: 0,
+                        });
+                        path.push(PathSegment::Key(key));
+                        let span = line.span.start + offset..line.span.end;
+                        open_value(
+                            &mut nodes,
+                            &mut stack,
+                            path,
+                            header,
+                            value,
+                            span,
+                            line.number,
+                            content_indent,
+                        );
+                    }
+                    LineKind::Header { header, value } => {
+                        open_value(
+                            &mut nodes,
+                            &mut stack,
+                            path,
+                            Some(header),
+                            value,
+                            span,
+                            line.number,
+                            item_indent,
+                        );
+                    }
+                    _ => {
+                        push_node(&mut nodes, &path, OutlineKind::Value, &span, line.number);
+                    }
+                }
+            }
+            (_, LineKind::Field { key, header, value }) => {
+                let mut path = parent.path.clone();
+                path.push(PathSegment::Key(key));
+                let span = line.span.clone();
+                open_value(
+                    &mut nodes,
+                    &mut stack,
+                    path,
+                    header,
+                    value,
+                    span,
+                    line.number,
+                    line.indent,
+                );
+            }
+            (_, LineKind::Header { header, value }) if stack.len() == 1 => {
+                let span = line.span.clone();
+                open_value(
+                    &mut nodes,
+                    &mut stack,
+                    Path::root(),
+                    Some(header),
+                    value,
+                    span,
+                    line.number,
+                    line.indent,
+                );
+            }
+            _ => {}
+        }
+    }
+
+    nodes
+}
+
+/// Records a node for a field value and opens it if it can contain nested lines.
+#[allow(clippy::too_many_arguments)]
+fn open_value(
+    nodes: &mut Vec<OutlineNode>,
+    stack: &mut Vec<Open>,
+    path: Path,
+    header: Option<Header>,
+    value: &str,
+    span: Range<usize>,
+    line: usize,
+    indent: usize,
+) {
+    let (kind, items) = match &header {
+        Some(header) if !header.fields.is_empty() => (OutlineKind::Table, 0),
+        Some(header) => (
+            OutlineKind::Array,
+            lexer::count_inline_values(value, &header.delimiter),
+        ),
+        None if value.is_empty() => (OutlineKind::Object, 0),
+        None => (OutlineKind::Value, 0),
+    };
+
+    let index = push_node(nodes, &path, kind, &span, line);
+    // Only a block string's `|` takes the lines below a value
+    if kind == OutlineKind::Value && value != "|" {
+        return;
+    }
+    if matches!(kind, OutlineKind::Array | OutlineKind::Table) {
+        nodes[index].row_count = Some(items);
+    }
+    stack.push(Open {
+        indent,
+        node: Some(index),
+        path,
+        kind,
+        items,
+    });
+}
+
+fn push_node(
+    nodes: &mut Vec<OutlineNode>,
+    path: &Path,
+    kind: OutlineKind,
+    span: &Range<usize>,
+    line: usize,
+) -> usize {
+    nodes.push(OutlineNode {
+        path: path.clone(),
+        kind,
+        span: span.clone(),
+        line,
+        depth: path.segments().len(),
+        row_count: None,
+    });
+    nodes.len() - 1
+}
+
+fn set_count(nodes: &mut [OutlineNode], open: &Open) {
+    if let Some(index) = open.node {
+        nodes[index].row_count = Some(open.items);
+    }
+}
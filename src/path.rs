@@ -0,0 +1,173 @@
+//! Locations inside a TOON document.
+//!
+//! A [`Path`] identifies a value by the chain of object keys and array indices
+//! leading to it from the document root. Paths are used in error messages and
+//! warnings so that problems deep inside a structure can be traced back to the
+//! offending field.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde_toon::path::{Path, PathSegment};
+//!
+//! let mut path = Path::root();
+//! path.push(PathSegment::Key("users".to_string()));
+//! path.push(PathSegment::Index(3));
+//! path.push(PathSegment::Key("price".to_string()));
+//!
+//! assert_eq!(path.to_string(), "users[3].price");
+//! ```
+
+use std::fmt;
+
+/// A single step in a [`Path`]: either an object key or an array index.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// The location of a value relative to the document root.
+///
+/// Displays as a dotted path with bracketed indices (e.g. `users[3].price`).
+/// The root path displays as `(root)`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Path {
+    segments: Vec<PathSegment>,
+}
+
+impl Path {
+    /// Creates a path pointing at the document root.
+    #[must_use]
+    pub fn root() -> Self {
+        Path::default()
+    }
+
+    /// Returns `true` if this path points at the document root.
+    #[must_use]
+    pub fn is_root(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Appends a segment to the end of the path.
+    pub fn push(&mut self, segment: PathSegment) {
+        self.segments.push(segment);
+    }
+
+    /// Removes and returns the last segment of the path.
+    pub fn pop(&mut self) -> Option<PathSegment> {
+        self.segments.pop()
+    }
+
+    /// Inserts a segment at the start of the path.
+    pub(crate) fn prepend(&mut self, segment: PathSegment) {
+        self.segments.insert(0, segment);
+    }
+
+    /// Returns the segments of this path, outermost first.
+    #[must_use]
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    /// Returns `true` if this path matches `pattern`, written like a
+    /// displayed path (`users[3].price`) with wildcards:
+    ///
+    /// - `*` matches any one key, and `[*]` any one index
+    /// - `**` matches any number of segments, including none
+    ///
+    /// A pattern without `**` matches paths of its own length only, so
+    /// `password` matches a top-level field and `**.password` one at any
+    /// depth.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::path::{Path, PathSegment};
+    ///
+    /// let mut path = Path::root();
+    /// path.push(PathSegment::Key("users".to_string()));
+    /// path.push(PathSegment::Index(3));
+    /// path.push(PathSegment::Key("email".to_string()));
+    ///
+    /// assert!(path.matches("users[*].email"));
+    /// assert!(path.matches("**.email"));
+    /// assert!(path.matches("*[3].*"));
+    /// assert!(!path.matches("email"));
+    /// assert!(!path.matches("users[0].email"));
+    /// ```
+    #[must_use]
+    pub fn matches(&self, pattern: &str) -> bool {
+        matches_from(&pattern_steps(pattern), &self.segments)
+    }
+}
+
+/// A step of a pattern given to [`Path::matches`].
+enum Step<'a> {
+    Key(&'a str),
+    AnyKey,
+    Index(&'a str),
+    AnyIndex,
+    AnyDepth,
+}
+
+fn pattern_steps(pattern: &str) -> Vec<Step<'_>> {
+    let mut steps = Vec::new();
+    for part in pattern.split('.').filter(|part| !part.is_empty()) {
+        let (key, indices) = part.split_at(part.find('[').unwrap_or(part.len()));
+        match key {
+            "" => {}
+            "*" => steps.push(Step::AnyKey),
+            "**" => steps.push(Step::AnyDepth),
+            key => steps.push(Step::Key(key)),
+        }
+        for index in indices.split('[').skip(1) {
+            match index.trim_end_matches(']') {
+                "*" => steps.push(Step::AnyIndex),
+                "**" => steps.push(Step::AnyDepth),
+                index => steps.push(Step::Index(index)),
+            }
+        }
+    }
+    steps
+}
+
+fn matches_from(steps: &[Step<'_>], segments: &[PathSegment]) -> bool {
+    let Some((step, rest)) = steps.split_first() else {
+        return segments.is_empty();
+    };
+    if let Step::AnyDepth = step {
+        return (0..=segments.len()).any(|skip| matches_from(rest, &segments[skip..]));
+    }
+    let Some((segment, remaining)) = segments.split_first() else {
+        return false;
+    };
+    let matched = match (step, segment) {
+        (Step::Key(key), PathSegment::Key(actual)) => key == actual,
+        (Step::AnyKey, PathSegment::Key(_)) | (Step::AnyIndex, PathSegment::Index(_)) => true,
+        (Step::Index(index), PathSegment::Index(actual)) => index.parse() == Ok(*actual),
+        _ => false,
+    };
+    matched && matches_from(rest, remaining)
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.segments.is_empty() {
+            return f.write_str("(root)");
+        }
+
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                PathSegment::Key(key) => {
+                    if i > 0 {
+                        f.write_str(".")?;
+                    }
+                    f.write_str(key)?;
+                }
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
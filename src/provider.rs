@@ -0,0 +1,130 @@
+//! Loading TOON config files with [`figment`] (requires the `figment`
+//! feature).
+//!
+//! [`Toon`] is a [`Format`], so TOON files and strings become figment
+//! providers that merge with any others, such as the environment or the
+//! defaults of a Rocket application, with no adapter of your own:
+//!
+//! - `Toon::file(path)` reads a file, and provides nothing if it is missing
+//! - `Toon::file_exact(path)` reads a file and fails if it is missing
+//! - `Toon::string(text)` reads TOON text
+//!
+//! Add `.nested()` to any of them to read the top-level keys as profiles.
+//! Documents are parsed with [`ToonOptions::default`](crate::ToonOptions::default).
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use figment::providers::{Format, Serialized};
+//! use figment::Figment;
+//! use serde::{Deserialize, Serialize};
+//! use serde_toon::provider::Toon;
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! struct Config { name: String, port: u16, hosts: Vec<String> }
+//!
+//! let defaults = Config { name: "api".into(), port: 80, hosts: vec![] };
+//! let config: Config = Figment::from(Serialized::defaults(defaults))
+//!     .merge(Toon::string("port: 8080\nhosts: [2]: a,b"))
+//!     .extract()
+//!     .unwrap();
+//!
+//! assert_eq!(config, Config { name: "api".into(), port: 8080, hosts: vec!["a".into(), "b".into()] });
+//! ```
+
+use crate::Error;
+use figment::providers::Format;
+use serde::de::DeserializeOwned;
+
+/// The TOON [`Format`], for reading TOON files and strings as figment
+/// providers.
+///
+/// # Examples
+///
+/// ```rust
+/// use figment::providers::Format;
+/// use figment::{Figment, Profile};
+/// use serde::Deserialize;
+/// use serde_toon::provider::Toon;
+///
+/// #[derive(Deserialize)]
+/// struct Config { workers: u32 }
+///
+/// let text = "default:\n  workers: 4\nrelease:\n  workers: 32";
+/// let figment = Figment::from(Toon::string(text).nested());
+///
+/// let config: Config = figment.clone().select(Profile::Default).extract().unwrap();
+/// assert_eq!(config.workers, 4);
+/// let config: Config = figment.select("release").extract().unwrap();
+/// assert_eq!(config.workers, 32);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Toon;
+
+impl Format for Toon {
+    type Error = Error;
+
+    const NAME: &'static str = "TOON";
+
+    fn from_str<T: DeserializeOwned>(string: &str) -> Result<T, Error> {
+        crate::from_str(string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use figment::Figment;
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Upstream {
+        host: String,
+        weight: u8,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Config {
+        name: String,
+        upstreams: Vec<Upstream>,
+    }
+
+    #[test]
+    fn provides_files_and_strings() {
+        let path =
+            std::env::temp_dir().join(format!("serde_toon_figment_{}.toon", std::process::id()));
+        std::fs::write(&path, "name: api\nupstreams[2]{host,weight}:\n  a,1\n  b,3").unwrap();
+        let config: Config = Figment::from(Toon::file(&path))
+            .merge(Toon::string("name: edge"))
+            .extract()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(config.name, "edge");
+        assert_eq!(
+            config.upstreams,
+            vec![
+                Upstream {
+                    host: "a".to_string(),
+                    weight: 1
+                },
+                Upstream {
+                    host: "b".to_string(),
+                    weight: 3
+                },
+            ]
+        );
+
+        // A missing file provides nothing unless it is required
+        let missing =
+            Figment::from(Toon::file(&path)).merge(Toon::string("name: x\nupstreams[0]:"));
+        assert_eq!(missing.extract::<Config>().unwrap().name, "x");
+        assert!(Figment::from(Toon::file_exact(&path))
+            .extract::<Config>()
+            .is_err());
+
+        let err = Figment::from(Toon::string("name: [2]: a"))
+            .extract::<Config>()
+            .unwrap_err();
+        assert!(err.to_string().contains("line 1, column 7"), "{}", err);
+    }
+}
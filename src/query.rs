@@ -0,0 +1,734 @@
+//! Querying values with path expressions.
+//!
+//! [`Value::query`] evaluates a small, jq-like expression against a value and
+//! returns every match. It is meant for inspecting data whose shape is only
+//! known at runtime, such as tool results in an agent loop.
+//!
+//! ## Syntax
+//!
+//! | Expression | Matches |
+//! |------------|---------|
+//! | `.` | The value itself |
+//! | `.name`, `name` | Field `name` of an object |
+//! | `."odd key"`, `["odd key"]` | A field whose name is not an identifier |
+//! | `[2]`, `[-1]` | An array element or table row, counting from the end if negative |
+//! | `[*]`, `[]` | Every array element, table row, or object field value |
+//! | `.*` | Every object field value |
+//! | `a \| b` | `b` applied to every match of `a` |
+//! | `select(cond)` | The current value, if `cond` holds for it |
+//!
+//! Table rows are matched as objects keyed by the table's headers, so
+//! `users[*].name` works the same whether `users` was written as a table or a
+//! list. Missing fields and out-of-range indices simply match nothing.
+//!
+//! Conditions compare a path against a literal (`==`, `!=`, `<`, `<=`, `>`,
+//! `>=`) or test a path for truthiness (anything but `null` and `false`), and
+//! combine with `and`, `or`, `not` and parentheses. A condition holds if any
+//! value matched by its path satisfies it. Literals are numbers, double-quoted
+//! strings, `true`, `false` and `null`.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde_toon::{from_str, Value};
+//!
+//! let doc: Value = from_str(
+//!     "users: [3]{id,name,active}:\n  1,Alice,true\n  2,Bob,false\n  3,Carol,true",
+//! )
+//! .unwrap();
+//!
+//! let names = doc.query("users[*].name").unwrap();
+//! assert_eq!(names, vec![Value::from("Alice"), Value::from("Bob"), Value::from("Carol")]);
+//!
+//! let active = doc.query(".users[] | select(.active and .id > 1) | .name").unwrap();
+//! assert_eq!(active, vec![Value::from("Carol")]);
+//! ```
+
+use crate::de::from_value;
+use crate::{Error, Number, Result, Value};
+use serde::de::DeserializeOwned;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+impl Value {
+    /// Returns every value matched by the query expression `expr`.
+    ///
+    /// See the [`query`](crate::query) module for the expression syntax. To run
+    /// the same expression repeatedly, parse it once with [`Query::parse`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{toon, Value};
+    ///
+    /// let value = toon!({"items": [{"sku": "A1", "qty": 2}, {"sku": "B2", "qty": 0}]});
+    /// let skus = value.query(".items[] | select(.qty > 0) | .sku").unwrap();
+    /// assert_eq!(skus, vec![Value::from("A1")]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `expr` is not a valid query.
+    pub fn query(&self, expr: &str) -> Result<Vec<Value>> {
+        Ok(Query::parse(expr)?.run(self))
+    }
+
+    /// Looks up `path` and deserializes the match into `T`.
+    ///
+    /// `path` uses the [`query`](crate::query) syntax. A path made only of
+    /// field names and indices names at most one value; a missing value reads
+    /// as `null`, so it becomes `None` for an `Option`. A path with wildcards
+    /// or `select` gives every match, deserialized as a sequence.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::Deserialize;
+    /// use serde_toon::toon;
+    ///
+    /// #[derive(Deserialize, Debug, PartialEq)]
+    /// struct User {
+    ///     id: u32,
+    ///     name: String,
+    /// }
+    ///
+    /// let doc = toon!({"users": [{"id": 1, "name": "Ada"}, {"id": 2, "name": "Bob"}]});
+    /// let first: User = doc.get_as("users[0]").unwrap();
+    /// assert_eq!(first, User { id: 1, name: "Ada".into() });
+    /// assert_eq!(doc.get_as::<u32>("users[-1].id").unwrap(), 2);
+    /// assert_eq!(doc.get_as::<Vec<String>>("users[*].name").unwrap(), ["Ada", "Bob"]);
+    /// assert_eq!(doc.get_as::<Option<String>>("users[0].email").unwrap(), None);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `path` is not a valid query, if a single-value path
+    /// matches nothing and `T` cannot be read from `null`, or if the match
+    /// cannot be deserialized into `T`.
+    pub fn get_as<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let query = Query::parse(path)?;
+        let mut matches = query.run(self);
+        if !query.is_singular() {
+            return from_value(Value::Array(matches));
+        }
+        match matches.pop() {
+            Some(value) => from_value(value),
+            None => from_value(Value::Null)
+                .map_err(|_| Error::custom(format!("no value at `{}`", path))),
+        }
+    }
+}
+
+/// A parsed query expression.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{query::Query, toon, Value};
+///
+/// let query = Query::parse("tags[-1]").unwrap();
+/// assert_eq!(query.run(&toon!({"tags": ["a", "b"]})), vec![Value::from("b")]);
+/// assert!(query.run(&toon!({"tags": []})).is_empty());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Query {
+    source: String,
+    stages: Vec<Stage>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Stage {
+    Path(Vec<Step>),
+    Select(Condition),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Step {
+    Key(String),
+    Index(i64),
+    Wildcard,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Condition {
+    Truthy(Vec<Step>),
+    Compare(Vec<Step>, CompareOp, Value),
+    Not(Box<Condition>),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Query {
+    /// Parses a query expression.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the column of the first invalid character.
+    pub fn parse(expr: &str) -> Result<Self> {
+        let mut parser = Parser {
+            input: expr,
+            pos: 0,
+        };
+        let stages = parser.query()?;
+        Ok(Query {
+            source: expr.to_string(),
+            stages,
+        })
+    }
+
+    /// Whether the query can match at most one value: it has no wildcards and
+    /// no `select` stages.
+    fn is_singular(&self) -> bool {
+        self.stages.iter().all(|stage| match stage {
+            Stage::Path(steps) => !steps.contains(&Step::Wildcard),
+            Stage::Select(_) => false,
+        })
+    }
+
+    /// Returns every value in `value` matched by this query, in document order.
+    #[must_use]
+    pub fn run(&self, value: &Value) -> Vec<Value> {
+        let mut current = vec![Cow::Borrowed(value)];
+        for stage in &self.stages {
+            current = match stage {
+                Stage::Path(steps) => follow(current, steps),
+                Stage::Select(condition) => current
+                    .into_iter()
+                    .filter(|value| condition.holds(value))
+                    .collect(),
+            };
+        }
+        current.into_iter().map(Cow::into_owned).collect()
+    }
+}
+
+impl FromStr for Query {
+    type Err = Error;
+
+    fn from_str(expr: &str) -> Result<Self> {
+        Query::parse(expr)
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Applies `steps` to every value in `values`.
+fn follow<'a>(mut values: Vec<Cow<'a, Value>>, steps: &[Step]) -> Vec<Cow<'a, Value>> {
+    for step in steps {
+        let mut next = Vec::new();
+        for value in values {
+            match value {
+                Cow::Borrowed(value) => apply(value, step, &mut next),
+                Cow::Owned(value) => {
+                    let mut owned = Vec::new();
+                    apply(&value, step, &mut owned);
+                    next.extend(owned.into_iter().map(|v| Cow::Owned(v.into_owned())));
+                }
+            }
+        }
+        values = next;
+    }
+    values
+}
+
+fn apply<'a>(value: &'a Value, step: &Step, out: &mut Vec<Cow<'a, Value>>) {
+    match (step, value) {
+        (Step::Key(key), Value::Object(obj)) => out.extend(obj.get(key).map(Cow::Borrowed)),
+        (Step::Index(index), Value::Array(items)) => {
+            out.extend(resolve_index(*index, items.len()).map(|i| Cow::Borrowed(&items[i])));
+        }
+        (Step::Index(index), Value::Table(table)) => {
+            out.extend(
+                resolve_index(*index, table.len())
+                    .and_then(|i| table.row(i))
+                    .map(|row| Cow::Owned(Value::Object(row))),
+            );
+        }
+        (Step::Wildcard, Value::Array(items)) => out.extend(items.iter().map(Cow::Borrowed)),
+        (Step::Wildcard, Value::Table(table)) => {
+            out.extend(table.iter_rows().map(|row| Cow::Owned(Value::Object(row))));
+        }
+        (Step::Wildcard, Value::Object(obj)) => out.extend(obj.values().map(Cow::Borrowed)),
+        _ => {}
+    }
+}
+
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 {
+        len.checked_sub(usize::try_from(index.unsigned_abs()).ok()?)?
+    } else {
+        usize::try_from(index).ok()?
+    };
+    (index < len).then_some(index)
+}
+
+impl Condition {
+    fn holds(&self, value: &Value) -> bool {
+        match self {
+            Condition::Truthy(steps) => follow(vec![Cow::Borrowed(value)], steps)
+                .iter()
+                .any(|v| !matches!(v.as_ref(), Value::Null | Value::Bool(false))),
+            Condition::Compare(steps, op, literal) => follow(vec![Cow::Borrowed(value)], steps)
+                .iter()
+                .any(|v| op.test(v, literal)),
+            Condition::Not(inner) => !inner.holds(value),
+            Condition::And(a, b) => a.holds(value) && b.holds(value),
+            Condition::Or(a, b) => a.holds(value) || b.holds(value),
+        }
+    }
+}
+
+impl CompareOp {
+    fn test(self, left: &Value, right: &Value) -> bool {
+        match self {
+            CompareOp::Eq => compare(left, right) == Some(Ordering::Equal),
+            CompareOp::Ne => compare(left, right) != Some(Ordering::Equal),
+            CompareOp::Lt => compare(left, right) == Some(Ordering::Less),
+            CompareOp::Le => matches!(compare(left, right), Some(Ordering::Less | Ordering::Equal)),
+            CompareOp::Gt => compare(left, right) == Some(Ordering::Greater),
+            CompareOp::Ge => matches!(
+                compare(left, right),
+                Some(Ordering::Greater | Ordering::Equal)
+            ),
+        }
+    }
+}
+
+/// Orders two values of the same kind; values of different kinds are unordered.
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64().partial_cmp(&b.as_f64()),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        (Value::Null, Value::Null) => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn query(&mut self) -> Result<Vec<Stage>> {
+        let mut stages = vec![self.stage()?];
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                None => return Ok(stages),
+                Some('|') => {
+                    self.pos += 1;
+                    stages.push(self.stage()?);
+                }
+                Some(_) => return Err(self.error("expected `|` or end of query")),
+            }
+        }
+    }
+
+    fn stage(&mut self) -> Result<Stage> {
+        self.skip_spaces();
+        let start = self.pos;
+        if self.eat_keyword("select") {
+            self.skip_spaces();
+            if self.peek() == Some('(') {
+                self.pos += 1;
+                let condition = self.or()?;
+                self.skip_spaces();
+                self.expect(')')?;
+                return Ok(Stage::Select(condition));
+            }
+            // A field that happens to be called `select`
+            self.pos = start;
+        }
+        Ok(Stage::Path(self.path()?))
+    }
+
+    /// Parses a path such as `.a.b[0]`, `a[*]` or `.`.
+    fn path(&mut self) -> Result<Vec<Step>> {
+        self.skip_spaces();
+        let mut steps = Vec::new();
+
+        match self.peek() {
+            Some('.') => {
+                self.pos += 1;
+                // A lone `.` (identity) is followed by nothing path-like
+                if let Some(step) = self.field_step()? {
+                    steps.push(step);
+                }
+            }
+            Some('[') => {}
+            Some(ch) if is_ident_start(ch) => steps.push(Step::Key(self.ident())),
+            _ => return Err(self.error("expected a path")),
+        }
+
+        loop {
+            match self.peek() {
+                Some('.') => {
+                    self.pos += 1;
+                    match self.field_step()? {
+                        Some(step) => steps.push(step),
+                        None => return Err(self.error("expected a field name after `.`")),
+                    }
+                }
+                Some('[') => {
+                    self.pos += 1;
+                    steps.push(self.bracket_step()?);
+                }
+                _ => return Ok(steps),
+            }
+        }
+    }
+
+    /// Parses what follows a `.`: an identifier, a quoted key or `*`.
+    fn field_step(&mut self) -> Result<Option<Step>> {
+        match self.peek() {
+            Some('*') => {
+                self.pos += 1;
+                Ok(Some(Step::Wildcard))
+            }
+            Some('"') => Ok(Some(Step::Key(self.string()?))),
+            Some(ch) if is_ident_start(ch) => Ok(Some(Step::Key(self.ident()))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Parses the inside of `[...]`, after the opening bracket.
+    fn bracket_step(&mut self) -> Result<Step> {
+        self.skip_spaces();
+        let step = match self.peek() {
+            Some(']') => Step::Wildcard,
+            Some('*') => {
+                self.pos += 1;
+                Step::Wildcard
+            }
+            Some('"') => Step::Key(self.string()?),
+            Some(ch) if ch == '-' || ch.is_ascii_digit() => {
+                let start = self.pos;
+                self.pos += 1;
+                while self.peek().is_some_and(|ch| ch.is_ascii_digit()) {
+                    self.pos += 1;
+                }
+                let index = self.input[start..self.pos]
+                    .parse()
+                    .map_err(|_| self.error("invalid index"))?;
+                Step::Index(index)
+            }
+            _ => return Err(self.error("expected an index, `*` or a quoted key")),
+        };
+        self.skip_spaces();
+        self.expect(']')?;
+        Ok(step)
+    }
+
+    fn or(&mut self) -> Result<Condition> {
+        let mut condition = self.and()?;
+        while self.eat_keyword("or") {
+            condition = Condition::Or(Box::new(condition), Box::new(self.and()?));
+        }
+        Ok(condition)
+    }
+
+    fn and(&mut self) -> Result<Condition> {
+        let mut condition = self.unary()?;
+        while self.eat_keyword("and") {
+            condition = Condition::And(Box::new(condition), Box::new(self.unary()?));
+        }
+        Ok(condition)
+    }
+
+    fn unary(&mut self) -> Result<Condition> {
+        self.skip_spaces();
+        if self.eat_keyword("not") {
+            return Ok(Condition::Not(Box::new(self.unary()?)));
+        }
+        if self.peek() == Some('(') {
+            self.pos += 1;
+            let condition = self.or()?;
+            self.skip_spaces();
+            self.expect(')')?;
+            return Ok(condition);
+        }
+
+        let path = self.path()?;
+        self.skip_spaces();
+        let op = match self.peek_pair() {
+            (Some('='), Some('=')) => CompareOp::Eq,
+            (Some('!'), Some('=')) => CompareOp::Ne,
+            (Some('<'), Some('=')) => CompareOp::Le,
+            (Some('>'), Some('=')) => CompareOp::Ge,
+            (Some('<'), _) => CompareOp::Lt,
+            (Some('>'), _) => CompareOp::Gt,
+            _ => return Ok(Condition::Truthy(path)),
+        };
+        self.pos += if matches!(op, CompareOp::Lt | CompareOp::Gt) {
+            1
+        } else {
+            2
+        };
+        Ok(Condition::Compare(path, op, self.literal()?))
+    }
+
+    fn literal(&mut self) -> Result<Value> {
+        self.skip_spaces();
+        match self.peek() {
+            Some('"') => Ok(Value::String(self.string()?)),
+            Some(ch) if ch == '-' || ch.is_ascii_digit() => {
+                let start = self.pos;
+                self.pos += 1;
+                while self.peek().is_some_and(|ch| {
+                    ch.is_ascii_digit() || matches!(ch, '.' | 'e' | 'E' | '+' | '-')
+                }) {
+                    self.pos += 1;
+                }
+                let text = &self.input[start..self.pos];
+                if let Ok(i) = text.parse::<i64>() {
+                    Ok(Value::Number(Number::Integer(i)))
+                } else {
+                    text.parse::<f64>()
+                        .map(|f| Value::Number(Number::Float(f)))
+                        .map_err(|_| self.error("invalid number"))
+                }
+            }
+            _ if self.eat_keyword("true") => Ok(Value::Bool(true)),
+            _ if self.eat_keyword("false") => Ok(Value::Bool(false)),
+            _ if self.eat_keyword("null") => Ok(Value::Null),
+            _ => Err(self.error("expected a number, string, `true`, `false` or `null`")),
+        }
+    }
+
+    /// Parses a double-quoted string with `\"` and `\\` escapes.
+    fn string(&mut self) -> Result<String> {
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(out),
+                Some('\\') => match self.next() {
+                    Some(ch @ ('"' | '\\')) => out.push(ch),
+                    _ => return Err(self.error("invalid escape in string")),
+                },
+                Some(ch) => out.push(ch),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn ident(&mut self) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(is_ident_char) {
+            self.pos += 1;
+        }
+        self.input[start..self.pos].to_string()
+    }
+
+    /// Consumes `keyword` if it appears next as a whole word.
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        self.skip_spaces();
+        let rest = &self.input[self.pos..];
+        let whole_word = rest
+            .strip_prefix(keyword)
+            .is_some_and(|after| !after.starts_with(is_ident_char));
+        if whole_word {
+            self.pos += keyword.len();
+        }
+        whole_word
+    }
+
+    fn expect(&mut self, ch: char) -> Result<()> {
+        if self.peek() == Some(ch) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", ch)))
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn peek_pair(&self) -> (Option<char>, Option<char>) {
+        let mut chars = self.input[self.pos..].chars();
+        (chars.next(), chars.next())
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += ch.len_utf8();
+        Some(ch)
+    }
+
+    fn error(&self, msg: &str) -> Error {
+        let column = self.input[..self.pos].chars().count() + 1;
+        Error::custom(format!(
+            "invalid query `{}` at column {}: {}",
+            self.input, column, msg
+        ))
+    }
+}
+
+fn is_ident_start(ch: char) -> bool {
+    ch.is_alphabetic() || ch == '_'
+}
+
+fn is_ident_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toon;
+
+    fn strings(values: Vec<Value>) -> Vec<String> {
+        values.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_paths() {
+        let value = toon!({
+            "users": [{"name": "A", "tags": ["x", "y"]}, {"name": "B", "tags": []}],
+            "meta": {"odd key": 1, "n": 2}
+        });
+
+        assert_eq!(strings(value.query("users[*].name").unwrap()), ["A", "B"]);
+        assert_eq!(strings(value.query(".users[].tags[0]").unwrap()), ["x"]);
+        assert_eq!(strings(value.query("users[-1].name").unwrap()), ["B"]);
+        assert_eq!(strings(value.query(r#".meta."odd key""#).unwrap()), ["1"]);
+        assert_eq!(strings(value.query(r#"meta["n"]"#).unwrap()), ["2"]);
+        assert_eq!(strings(value.query("meta.*").unwrap()), ["1", "2"]);
+        assert_eq!(value.query(".").unwrap(), vec![value.clone()]);
+        assert_eq!(
+            strings(toon!({"select": 1}).query("select").unwrap()),
+            ["1"]
+        );
+        assert!(value.query("users[5].name").unwrap().is_empty());
+        assert!(value.query("missing.deeper[*]").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_tables_and_select() {
+        let value = Value::object()
+            .field(
+                "items",
+                Value::table(["sku", "qty", "active"])
+                    .row(("A1", 2, true))
+                    .row(("B2", 0, true))
+                    .row(("C3", 5, false)),
+            )
+            .build();
+
+        assert_eq!(
+            strings(value.query("items[*].sku").unwrap()),
+            ["A1", "B2", "C3"]
+        );
+        assert_eq!(strings(value.query("items[1].qty").unwrap()), ["0"]);
+        assert_eq!(
+            strings(value.query(".items[] | select(.active) | .sku").unwrap()),
+            ["A1", "B2"]
+        );
+        assert_eq!(
+            strings(
+                value
+                    .query(r#"items[] | select(qty >= 2 and not sku == "C3") | sku"#)
+                    .unwrap()
+            ),
+            ["A1"]
+        );
+        assert_eq!(
+            strings(
+                value
+                    .query("items[] | select(.qty < 1 or .active == false) | .sku")
+                    .unwrap()
+            ),
+            ["B2", "C3"]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for expr in ["", "users[", "a..b", "select(.a ==)", "a b", r#"a["x"#] {
+            let err = Query::parse(expr).unwrap_err();
+            assert!(
+                err.to_string().contains("invalid query"),
+                "{}: {}",
+                expr,
+                err
+            );
+        }
+        let err = Query::parse("a | b c").unwrap_err();
+        assert!(err.to_string().contains("column 7"), "{}", err);
+    }
+
+    #[test]
+    fn test_get_as() {
+        #[derive(serde::Deserialize, Debug, PartialEq)]
+        struct Row {
+            id: i64,
+            tags: Vec<String>,
+        }
+
+        let mut value = toon!({
+            "rows": [{"id": 1, "tags": ["a"]}, {"id": 2, "tags": []}],
+            "meta": {"count": 2, "next": null}
+        });
+        let table = Value::table(["x"]).row((1.5,)).row((2.5,)).build();
+        value.as_object_mut().unwrap().insert("table", table);
+
+        assert_eq!(value.get_as::<i64>("meta.count").unwrap(), 2);
+        assert_eq!(
+            value.get_as::<Row>("rows[0]").unwrap(),
+            Row {
+                id: 1,
+                tags: vec!["a".into()]
+            }
+        );
+        assert_eq!(value.get_as::<Vec<Row>>("rows").unwrap().len(), 2);
+        assert_eq!(value.get_as::<f64>("table[1].x").unwrap(), 2.5);
+        assert_eq!(value.get_as::<Vec<f64>>("table[*].x").unwrap(), [1.5, 2.5]);
+        assert_eq!(
+            value
+                .get_as::<Vec<i64>>("rows[] | select(.tags[0]) | .id")
+                .unwrap(),
+            [1]
+        );
+        assert!(value
+            .get_as::<Vec<i64>>("rows[*].missing")
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(value.get_as::<Option<i64>>("meta.next").unwrap(), None);
+        assert_eq!(value.get_as::<Option<i64>>("meta.absent").unwrap(), None);
+        let err = value.get_as::<i64>("meta.absent").unwrap_err();
+        assert!(
+            err.to_string().contains("no value at `meta.absent`"),
+            "{}",
+            err
+        );
+        assert!(value.get_as::<String>("meta.count").is_err());
+        assert!(value.get_as::<i64>("meta[").is_err());
+    }
+}
@@ -0,0 +1,267 @@
+//! Unparsed fragments of a TOON document.
+//!
+//! [`RawToon`] is the TOON counterpart of `serde_json::value::RawValue`: a
+//! field of this type receives the text of its value instead of a parsed
+//! value, so a large sub-section can be handed on, stored or parsed later
+//! (with [`RawToon::parse`]) without being parsed up front.
+//!
+//! Fields of a top-level struct or map borrow their text straight from the
+//! input and are not parsed at all. A `RawToon` nested deeper holds its value
+//! re-rendered as TOON instead. Serializing writes a top-level `RawToon` out
+//! unchanged; elsewhere the text is parsed and written in this crate's layout,
+//! which reproduces any text this crate wrote with the same options.
+//!
+//! The captured text is a standalone document: a nested object or array has
+//! its indentation stripped, and an array keeps its `[N]` header.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//! use serde_toon::{from_str, to_string, RawToon};
+//!
+//! #[derive(Deserialize, Serialize)]
+//! struct Envelope<'a> {
+//!     kind: String,
+//!     #[serde(borrow)]
+//!     payload: RawToon<'a>,
+//! }
+//!
+//! let input = "kind: users\npayload:\n  users: [2]{id,name}:\n    1,Alice\n    2,Bob";
+//! let envelope: Envelope = from_str(input).unwrap();
+//! assert_eq!(envelope.payload.get(), "users: [2]{id,name}:\n  1,Alice\n  2,Bob");
+//!
+//! // Written back as it was read
+//! assert_eq!(to_string(&envelope).unwrap(), input);
+//! ```
+
+use crate::{Error, Result, Value};
+use serde::{de, ser, Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+
+/// Newtype struct name through which the serializer and deserializer recognise
+/// [`RawToon`].
+pub(crate) const TOKEN: &str = "$serde_toon::private::RawToon";
+
+/// A fragment of TOON text that has not been parsed.
+///
+/// See the [module documentation](self) for how fragments are captured.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct RawToon<'a> {
+    text: Cow<'a, str>,
+}
+
+impl<'a> RawToon<'a> {
+    /// Wraps `text` after checking that it is a valid TOON document.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{to_string, toon, RawToon};
+    ///
+    /// let raw = RawToon::from_string("[3]: 1,2,3".to_string()).unwrap();
+    /// assert_eq!(to_string(&toon!({"ids": raw})).unwrap(), "ids: [3]: 1,2,3");
+    /// assert!(RawToon::from_string("a: \"open".to_string()).is_err());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns the parse error if `text` is not valid TOON.
+    pub fn from_string(text: String) -> Result<RawToon<'static>> {
+        crate::from_str::<Value>(&text)?;
+        Ok(RawToon {
+            text: Cow::Owned(text),
+        })
+    }
+
+    /// Returns the captured text.
+    #[must_use]
+    pub fn get(&self) -> &str {
+        &self.text
+    }
+
+    /// Copies borrowed text so the fragment can outlive its input.
+    #[must_use]
+    pub fn into_owned(self) -> RawToon<'static> {
+        RawToon {
+            text: Cow::Owned(self.text.into_owned()),
+        }
+    }
+
+    /// Parses the captured text.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::Deserialize;
+    /// use serde_toon::{from_str, RawToon};
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Job<'a> {
+    ///     #[serde(borrow)]
+    ///     args: RawToon<'a>,
+    /// }
+    ///
+    /// let job: Job = from_str("args: [3]: 1,2,3").unwrap();
+    /// let args: Vec<u32> = job.args.parse().unwrap();
+    /// assert_eq!(args, vec![1, 2, 3]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the text is not valid TOON or does not match `T`.
+    pub fn parse<'b, T>(&'b self) -> Result<T>
+    where
+        T: Deserialize<'b>,
+    {
+        crate::from_str(&self.text)
+    }
+}
+
+impl fmt::Debug for RawToon<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RawToon").field(&self.text).finish()
+    }
+}
+
+impl fmt::Display for RawToon<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl Serialize for RawToon<'_> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_newtype_struct(TOKEN, self.get())
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for RawToon<'a> {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(TOKEN, RawToonVisitor)
+    }
+}
+
+struct RawToonVisitor;
+
+impl<'de> de::Visitor<'de> for RawToonVisitor {
+    type Value = RawToon<'de>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("raw TOON text")
+    }
+
+    fn visit_borrowed_str<E>(self, text: &'de str) -> std::result::Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(RawToon {
+            text: Cow::Borrowed(text),
+        })
+    }
+
+    fn visit_str<E>(self, text: &str) -> std::result::Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(RawToon {
+            text: Cow::Owned(text.to_string()),
+        })
+    }
+
+    fn visit_string<E>(self, text: String) -> std::result::Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(RawToon {
+            text: Cow::Owned(text),
+        })
+    }
+}
+
+/// Parses raw text handed to a serializer, for writing it back out.
+pub(crate) fn parse_for_output(text: &str) -> Result<Value> {
+    crate::from_str(text).map_err(|err| Error::custom(format!("invalid RawToon: {}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_str, from_str_with_options, to_string, DuplicateKeys, ToonOptions};
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Deserialize, Serialize, Debug)]
+    struct Doc<'a> {
+        id: u32,
+        #[serde(borrow)]
+        body: RawToon<'a>,
+    }
+
+    #[test]
+    fn borrows_top_level_fields() {
+        let input = "id: 1\nbody: [2]{a,b}:\n  1,2\n  3,4";
+        let doc: Doc = from_str(input).unwrap();
+        assert_eq!(doc.body.get(), "[2]{a,b}:\n  1,2\n  3,4");
+        assert!(matches!(doc.body.text, Cow::Borrowed(_)));
+        assert_eq!(to_string(&doc).unwrap(), input);
+
+        let map: HashMap<String, RawToon> = from_str("x: hello world\ny:\n  z: 1").unwrap();
+        assert_eq!(map["x"].get(), "hello world");
+        assert_eq!(map["y"].get(), "z: 1");
+    }
+
+    #[test]
+    fn leaves_captured_text_unparsed() {
+        let input = "body:\n  a: \"open\n\n    b: 2\nid: 7";
+        let doc: Doc = from_str(input).unwrap();
+        assert_eq!(doc.id, 7);
+        assert_eq!(doc.body.get(), "a: \"open\n\n  b: 2");
+        assert!(doc.body.parse::<crate::Value>().is_err());
+    }
+
+    #[test]
+    fn renders_nested_values() {
+        #[derive(Deserialize)]
+        struct Outer<'a> {
+            #[serde(borrow)]
+            inner: Doc<'a>,
+        }
+
+        let outer: Outer = from_str("inner:\n  id: 1\n  body:\n    tags: [2]: a,b").unwrap();
+        assert_eq!(outer.inner.body.get(), "tags: [2]: a,b");
+    }
+
+    #[test]
+    fn captures_whole_documents() {
+        let raw: RawToon = from_str("\n[2]: 1,2\n").unwrap();
+        assert_eq!(raw.get(), "[2]: 1,2");
+        assert_eq!(to_string(&raw).unwrap(), "[2]: 1,2");
+    }
+
+    #[test]
+    fn keeps_repeated_key_rules() {
+        #[derive(Deserialize, Debug)]
+        struct Plain {
+            a: u32,
+        }
+
+        let plain: Plain = from_str("a: 1\na: 2").unwrap();
+        assert_eq!(plain.a, 2);
+
+        let options = ToonOptions::new().with_duplicate_keys(DuplicateKeys::FirstWins);
+        let plain: Plain = from_str_with_options("a: 1\na: 2", options).unwrap();
+        assert_eq!(plain.a, 1);
+
+        let options = ToonOptions::new().with_deny_unknown_fields(true);
+        let err = from_str_with_options::<Plain>("a: 1\nb: 2", options).unwrap_err();
+        assert!(matches!(err, Error::UnknownField { .. }));
+    }
+}
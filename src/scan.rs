@@ -0,0 +1,159 @@
+//! Cheap lookups in serialized documents.
+//!
+//! [`get_field`] finds one field of a TOON document and returns its value as
+//! written, without building any [`Value`](crate::Value)s. Only the lines of
+//! the objects along the key path are looked at, and the scan stops at the
+//! field, so this suits routing decisions on large payloads where a full parse
+//! would be wasted.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde_toon::get_field;
+//!
+//! let doc = "kind: invoice\nitems: [2]{sku,qty}:\n  A1,2\n  B2,5\nmeta:\n  region: eu";
+//!
+//! assert_eq!(get_field(doc, "kind").unwrap(), Some("invoice"));
+//! assert_eq!(get_field(doc, "meta.region").unwrap(), Some("eu"));
+//! assert_eq!(get_field(doc, "meta.owner").unwrap(), None);
+//! ```
+
+use crate::lexer::{self, LineKind};
+use crate::{Error, Result};
+
+/// Returns the text of the scalar at `key_path` in `input`.
+///
+/// `key_path` is a key of the root object, or a dotted path such as
+/// `meta.owner.name` through nested objects. The value is returned exactly as
+/// written: a quoted string keeps its quotes and escapes, and a block string
+/// (`key: |`) runs from the `|` through its last line. Parse it with
+/// [`from_str`](crate::from_str) to get a typed value.
+///
+/// The scan stops at the first match, so when a key is repeated this returns
+/// the first value, where [`from_str`](crate::from_str) keeps the last by
+/// default. Keys containing `.` cannot be looked up.
+///
+/// # Errors
+///
+/// Returns [`Error::TypeMismatch`] if the field holds an object or an array.
+/// A path that leads through anything other than an object finds nothing.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{get_field, Error};
+///
+/// let doc = "id: 42\nname: \"Ada, L.\"\ntags[2]: a,b\nuser:\n  role: admin";
+///
+/// assert_eq!(get_field(doc, "id").unwrap(), Some("42"));
+/// assert_eq!(get_field(doc, "name").unwrap(), Some("\"Ada, L.\""));
+/// assert_eq!(get_field(doc, "user.role").unwrap(), Some("admin"));
+/// assert_eq!(get_field(doc, "role").unwrap(), None);
+/// assert!(matches!(get_field(doc, "tags"), Err(Error::TypeMismatch { line: 3, .. })));
+/// ```
+pub fn get_field<'a>(input: &'a str, key_path: &str) -> Result<Option<&'a str>> {
+    let keys: Vec<&str> = key_path.split('.').collect();
+    let mut matched = 0;
+    // Fields of the object being searched are indented deeper than `parent`,
+    // all by the same amount, which is learned from the first of them
+    let mut parent: Option<usize> = None;
+    let mut level: Option<usize> = None;
+
+    let mut lines = lexer::lines(input);
+    while let Some(line) = lines.next() {
+        if parent.is_some_and(|parent| line.indent <= parent) {
+            return Ok(None);
+        }
+        if line.indent != *level.get_or_insert(line.indent) {
+            continue;
+        }
+        let LineKind::Field { key, header, value } = line.kind() else {
+            continue;
+        };
+        if key != keys[matched] {
+            continue;
+        }
+
+        let start = line.span.end - value.len();
+        let found = if header.is_some() {
+            Some("an array")
+        } else if value.is_empty() {
+            Some("an object")
+        } else {
+            None
+        };
+        if matched + 1 < keys.len() {
+            // Only an object can hold the rest of the path
+            if found != Some("an object") {
+                return Ok(None);
+            }
+            matched += 1;
+            parent = Some(line.indent);
+            level = None;
+            continue;
+        }
+        if let Some(found) = found {
+            return Err(Error::TypeMismatch {
+                line: line.number,
+                col: line.indent + 1,
+                expected: "a scalar".to_string(),
+                found: found.to_string(),
+            });
+        }
+
+        let mut end = line.span.end;
+        if value == "|" {
+            for block_line in lines.by_ref() {
+                if block_line.indent <= line.indent {
+                    break;
+                }
+                end = block_line.span.end;
+            }
+        }
+        return Ok(Some(&input[start..end]));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_nested_fields() {
+        let doc = "a:\n  b:\n    c: 1\n  d: \"x: y\"\ne: 2";
+        assert_eq!(get_field(doc, "a.b.c").unwrap(), Some("1"));
+        assert_eq!(get_field(doc, "a.d").unwrap(), Some("\"x: y\""));
+        assert_eq!(get_field(doc, "e").unwrap(), Some("2"));
+
+        // Fields of other objects are not fields of the one searched
+        assert_eq!(get_field(doc, "c").unwrap(), None);
+        assert_eq!(get_field(doc, "a.e").unwrap(), None);
+        assert_eq!(get_field(doc, "e.f").unwrap(), None);
+    }
+
+    #[test]
+    fn skips_arrays_and_tables() {
+        let doc = "rows: [2]{id,status}:\n  1,open\n  2,done\nitems[1]:\n  - status: x\nstatus: ok";
+        assert_eq!(get_field(doc, "status").unwrap(), Some("ok"));
+        assert_eq!(get_field(doc, "items.status").unwrap(), None);
+        assert!(matches!(
+            get_field(doc, "rows"),
+            Err(Error::TypeMismatch { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn returns_block_strings_and_empty_values() {
+        let doc = "note: |\n  first\n\n  second\nnext: \"\"\nempty:";
+        assert_eq!(
+            get_field(doc, "note").unwrap(),
+            Some("|\n  first\n\n  second")
+        );
+        assert_eq!(get_field(doc, "next").unwrap(), Some("\"\""));
+        assert!(matches!(
+            get_field(doc, "empty"),
+            Err(Error::TypeMismatch { line: 6, .. })
+        ));
+    }
+}
@@ -0,0 +1,1023 @@
+//! Declared schemas for the TOON form of Rust types.
+//!
+//! A [`Schema`] records what a type reads from TOON: its field names and
+//! their types, which fields are required, and the doc comments that explain
+//! them. Types provide one through the [`ToonSchema`] trait, which is
+//! implemented for the standard library's types and derived for your own
+//! structs and unit enums with `#[derive(ToonSchema)]` (requires the `derive`
+//! feature). A schema then serves both ends of a conversation with a model:
+//!
+//! - [`Schema::prompt`] renders the shape the answer should take, with the
+//!   documented fields explained, for the prompt
+//! - [`Schema::validate`] and [`Schema::violations`] check the parsed answer
+//!   against it, reporting every problem by path
+//! - [`to_json_schema`] and [`tool_definition`] declare it as the JSON Schema
+//!   of a tool's arguments, for the tool-calling APIs of OpenAI and Anthropic
+//!
+//! The derive reads the `#[serde(...)]` attributes that change what is read:
+//! `rename`, `rename_all`, `default`, `skip`, `skip_deserializing`, `flatten`
+//! and `deny_unknown_fields`.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! # #[cfg(feature = "derive")]
+//! # fn main() {
+//! use serde_toon::{from_str, ToonSchema, Value};
+//!
+//! /// A line of an order.
+//! #[derive(ToonSchema)]
+//! struct Item {
+//!     sku: String,
+//!     /// Units ordered, at least 1
+//!     qty: u32,
+//! }
+//!
+//! #[derive(ToonSchema)]
+//! struct Order {
+//!     id: u64,
+//!     /// Delivery instructions, if the customer gave any
+//!     note: Option<String>,
+//!     items: Vec<Item>,
+//! }
+//!
+//! let schema = Order::toon_schema();
+//! assert_eq!(
+//!     schema.prompt(),
+//!     "id: <int>\nnote: <string|null>\nitems: [N]{sku,qty}:\n  <string>,<int>\n\n\
+//!      Fields:\n\
+//!      - note: Delivery instructions, if the customer gave any (optional)\n\
+//!      - items[].qty: Units ordered, at least 1"
+//! );
+//!
+//! let answer: Value = from_str("id: 7\nitems[1]{sku,qty}:\n  A1,many").unwrap();
+//! let problems: Vec<String> = schema.violations(&answer).iter().map(ToString::to_string).collect();
+//! assert_eq!(problems, ["invalid type: string \"many\", expected an integer at items[0].qty"]);
+//! # }
+//! # #[cfg(not(feature = "derive"))]
+//! # fn main() {}
+//! ```
+
+use crate::describe::{render, Shape};
+use crate::path::{Path, PathSegment};
+use crate::{toon, Error, Result, ToonMap, Value};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// The TOON form of a type, as declared by its [`ToonSchema`] implementation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Schema {
+    /// Any value.
+    Any,
+    /// `true` or `false`.
+    Bool,
+    /// An integer.
+    Integer,
+    /// Any number.
+    Number,
+    /// A string.
+    String,
+    /// `null` or a value of the inner schema. Fields of nullable schemas are
+    /// not required.
+    Nullable(Box<Schema>),
+    /// An array whose items all have the inner schema.
+    Array(Box<Schema>),
+    /// An array of exactly these items, such as a Rust tuple.
+    Tuple(Vec<Schema>),
+    /// An object with any keys, whose values all have the inner schema.
+    Map(Box<Schema>),
+    /// An object with declared fields.
+    Object(ObjectSchema),
+    /// One of these strings, such as the variants of a unit enum.
+    Enum(Vec<String>),
+    /// The enclosing object of this name, where a type contains itself.
+    Recursive(String),
+}
+
+/// The fields of an [`Schema::Object`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ObjectSchema {
+    /// The name of the type, such as `Order`.
+    pub name: String,
+    /// The type's doc comment.
+    pub doc: Option<String>,
+    pub fields: Vec<Field>,
+    /// Whether fields other than those declared are an error.
+    pub deny_unknown_fields: bool,
+}
+
+/// A field of an [`ObjectSchema`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub schema: Schema,
+    /// Whether the field must be present.
+    pub required: bool,
+    /// The field's doc comment.
+    pub doc: Option<String>,
+}
+
+impl Field {
+    /// Creates a field, which is required unless its schema is nullable.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::schema::{Field, Schema};
+    ///
+    /// assert!(Field::new("id", Schema::Integer).required);
+    /// assert!(!Field::new("note", Schema::Nullable(Box::new(Schema::String))).required);
+    /// ```
+    #[must_use]
+    pub fn new(name: impl Into<String>, schema: Schema) -> Self {
+        Field {
+            name: name.into(),
+            required: !matches!(schema, Schema::Nullable(_)),
+            schema,
+            doc: None,
+        }
+    }
+
+    /// Sets whether the field must be present.
+    #[must_use]
+    pub fn with_required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Sets the field's doc comment.
+    #[must_use]
+    pub fn with_doc(mut self, doc: impl Into<String>) -> Self {
+        self.doc = Some(doc.into());
+        self
+    }
+}
+
+/// A type with a declared [`Schema`].
+///
+/// Derive it with `#[derive(ToonSchema)]` (requires the `derive` feature),
+/// or implement it by hand for types with a custom `Deserialize`.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::schema::{Schema, ToonSchema};
+///
+/// struct Celsius(f64);
+///
+/// impl ToonSchema for Celsius {
+///     fn toon_schema() -> Schema {
+///         Schema::Number
+///     }
+/// }
+///
+/// assert_eq!(Vec::<Celsius>::toon_schema().skeleton(), "[N]: <number>,...");
+/// ```
+pub trait ToonSchema {
+    /// Returns the schema of the type.
+    fn toon_schema() -> Schema;
+}
+
+impl Schema {
+    /// Renders the schema as a skeleton document, in the form of
+    /// [`describe`](fn@crate::describe).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::schema::ToonSchema;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let schema = BTreeMap::<String, Vec<Option<u8>>>::toon_schema();
+    /// assert_eq!(schema.skeleton(), "<key>: [N]: <int|null>,...");
+    /// ```
+    #[must_use]
+    pub fn skeleton(&self) -> String {
+        render(&self.shape())
+    }
+
+    /// Renders the skeleton followed by a list of the documented fields and
+    /// their docs, by path, for a prompt asking for a value of this schema.
+    /// Fields that are not required are marked as optional. Without
+    /// documented fields, this is the skeleton alone.
+    #[must_use]
+    pub fn prompt(&self) -> String {
+        let mut notes = Vec::new();
+        self.field_notes("", &mut notes);
+        let mut prompt = self.skeleton();
+        if !notes.is_empty() {
+            prompt.push_str("\n\nFields:");
+            for note in notes {
+                prompt.push_str("\n- ");
+                prompt.push_str(&note);
+            }
+        }
+        prompt
+    }
+
+    /// Checks `value` against the schema.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first of the [`violations`](Self::violations).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::schema::ToonSchema;
+    /// use serde_toon::toon;
+    ///
+    /// let schema = Vec::<(String, u32)>::toon_schema();
+    /// assert!(schema.validate(&toon!([["a", 1], ["b", 2]])).is_ok());
+    ///
+    /// let err = schema.validate(&toon!([["a", 1], ["b"]])).unwrap_err();
+    /// assert_eq!(err.to_string(), "invalid length 1, expected an array of 2 items at [1]");
+    /// ```
+    pub fn validate(&self, value: &Value) -> Result<()> {
+        match self.violations(value).into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns every place at which `value` does not match the schema: a
+    /// value of the wrong type, a missing required field, or an undeclared
+    /// field where those are denied. Each error carries the path of the
+    /// value at fault.
+    #[must_use]
+    pub fn violations(&self, value: &Value) -> Vec<Error> {
+        let mut found = Vec::new();
+        self.check(value, &mut Path::root(), &mut Vec::new(), &mut found);
+        found
+    }
+
+    fn check<'a>(
+        &'a self,
+        value: &Value,
+        path: &mut Path,
+        ancestors: &mut Vec<&'a ObjectSchema>,
+        found: &mut Vec<Error>,
+    ) {
+        if !self.admits(value) {
+            found.push(Error::Data {
+                msg: format!(
+                    "invalid type: {}, expected {}",
+                    unexpected(value),
+                    self.expected()
+                ),
+                path: path.clone(),
+            });
+            return;
+        }
+        match (self, value) {
+            (Schema::Nullable(inner), _) if !value.is_null() => {
+                inner.check(value, path, ancestors, found);
+            }
+            (Schema::Array(item), Value::Array(items)) => {
+                for (index, value) in items.iter().enumerate() {
+                    path.push(PathSegment::Index(index));
+                    item.check(value, path, ancestors, found);
+                    path.pop();
+                }
+            }
+            (Schema::Array(item), Value::Table(table)) => {
+                for (index, row) in table.iter_rows().enumerate() {
+                    path.push(PathSegment::Index(index));
+                    item.check(&Value::Object(row), path, ancestors, found);
+                    path.pop();
+                }
+            }
+            (Schema::Tuple(schemas), Value::Array(items)) if items.len() != schemas.len() => {
+                found.push(Error::Data {
+                    msg: format!(
+                        "invalid length {}, expected {}",
+                        items.len(),
+                        self.expected()
+                    ),
+                    path: path.clone(),
+                });
+            }
+            (Schema::Tuple(schemas), Value::Array(items)) => {
+                for (index, (schema, value)) in schemas.iter().zip(items).enumerate() {
+                    path.push(PathSegment::Index(index));
+                    schema.check(value, path, ancestors, found);
+                    path.pop();
+                }
+            }
+            (Schema::Map(schema), Value::Object(map)) => {
+                for (key, value) in map {
+                    path.push(PathSegment::Key(key.to_string()));
+                    schema.check(value, path, ancestors, found);
+                    path.pop();
+                }
+            }
+            (Schema::Object(object), Value::Object(map)) => {
+                ancestors.push(object);
+                object.check(map, path, ancestors, found);
+                ancestors.pop();
+            }
+            (Schema::Recursive(name), Value::Object(map)) => {
+                let ancestor = ancestors.iter().rev().find(|object| object.name == *name);
+                if let Some(&object) = ancestor {
+                    ancestors.push(object);
+                    object.check(map, path, ancestors, found);
+                    ancestors.pop();
+                }
+            }
+            (Schema::Enum(variants), Value::String(variant)) if !variants.contains(variant) => {
+                let expected: Vec<String> = variants.iter().map(|v| format!("`{}`", v)).collect();
+                found.push(Error::Data {
+                    msg: format!(
+                        "unknown variant `{}`, expected one of {}",
+                        variant,
+                        expected.join(", ")
+                    ),
+                    path: path.clone(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns `true` if `value` is of a type the schema accepts, without
+    /// looking inside it.
+    fn admits(&self, value: &Value) -> bool {
+        match (self, value) {
+            (Schema::Any, _)
+            | (Schema::Bool, Value::Bool(_))
+            | (Schema::Number, Value::Number(_))
+            | (Schema::String | Schema::Enum(_), Value::String(_))
+            | (Schema::Nullable(_), Value::Null)
+            | (Schema::Array(_), Value::Array(_) | Value::Table(_))
+            | (Schema::Tuple(_), Value::Array(_))
+            | (Schema::Map(_) | Schema::Object(_) | Schema::Recursive(_), Value::Object(_)) => true,
+            (Schema::Integer, Value::Number(n)) => n.is_integer(),
+            #[cfg(feature = "datetime")]
+            (Schema::String, Value::Date(_)) => true,
+            #[cfg(feature = "bigint")]
+            (Schema::Integer | Schema::Number, Value::BigInt(_)) => true,
+            (Schema::Nullable(inner), _) => inner.admits(value),
+            _ => false,
+        }
+    }
+
+    /// Describes the values the schema accepts, for errors.
+    fn expected(&self) -> String {
+        match self {
+            Schema::Any => "any value".to_string(),
+            Schema::Bool => "a boolean".to_string(),
+            Schema::Integer => "an integer".to_string(),
+            Schema::Number => "a number".to_string(),
+            Schema::String => "a string".to_string(),
+            Schema::Nullable(inner) => format!("{} or null", inner.expected()),
+            Schema::Array(_) => "an array".to_string(),
+            Schema::Tuple(items) => format!("an array of {} items", items.len()),
+            Schema::Map(_) | Schema::Object(_) | Schema::Recursive(_) => "an object".to_string(),
+            Schema::Enum(variants) => format!("one of {}", variants.join(", ")),
+        }
+    }
+
+    /// Returns the shape that [`describe`](fn@crate::describe) would trace for
+    /// a type of this schema.
+    fn shape(&self) -> Shape {
+        match self {
+            Schema::Any => Shape::Any,
+            Schema::Bool => Shape::Bool,
+            Schema::Integer => Shape::Integer,
+            Schema::Number => Shape::Float,
+            Schema::String => Shape::String,
+            Schema::Nullable(inner) => Shape::Optional(Box::new(inner.shape())),
+            Schema::Array(item) => Shape::Seq(Box::new(item.shape())),
+            Schema::Tuple(items) => Shape::Tuple(items.iter().map(Schema::shape).collect()),
+            Schema::Map(value) => Shape::Map(Box::new(value.shape())),
+            Schema::Object(object) => Shape::Struct(
+                object
+                    .fields
+                    .iter()
+                    .map(|field| (field.name.clone(), field.schema.shape()))
+                    .collect(),
+            ),
+            Schema::Enum(variants) => Shape::Enum(variants.clone()),
+            Schema::Recursive(name) => Shape::Named(name.clone()),
+        }
+    }
+
+    /// Collects `path: doc` notes for the documented fields under `prefix`.
+    fn field_notes(&self, prefix: &str, notes: &mut Vec<String>) {
+        match self {
+            Schema::Nullable(inner) => inner.field_notes(prefix, notes),
+            Schema::Array(item) => item.field_notes(&format!("{}[]", prefix), notes),
+            Schema::Map(value) => value.field_notes(&join(prefix, "<key>"), notes),
+            Schema::Object(object) => {
+                for field in &object.fields {
+                    let path = join(prefix, &field.name);
+                    if let Some(doc) = &field.doc {
+                        let optional = if field.required { "" } else { " (optional)" };
+                        notes.push(format!("{}: {}{}", path, doc, optional));
+                    }
+                    field.schema.field_notes(&path, notes);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl ObjectSchema {
+    fn check<'a>(
+        &'a self,
+        map: &ToonMap,
+        path: &mut Path,
+        ancestors: &mut Vec<&'a ObjectSchema>,
+        found: &mut Vec<Error>,
+    ) {
+        for field in &self.fields {
+            match map.get(&field.name) {
+                Some(value) => {
+                    path.push(PathSegment::Key(field.name.clone()));
+                    field.schema.check(value, path, ancestors, found);
+                    path.pop();
+                }
+                None if field.required => found.push(Error::MissingField {
+                    field: field.name.clone(),
+                    path: path.clone(),
+                }),
+                None => {}
+            }
+        }
+        if self.deny_unknown_fields {
+            let names: Vec<&str> = self.fields.iter().map(|f| f.name.as_str()).collect();
+            for key in map.keys() {
+                if !names.contains(&key.as_str()) {
+                    found.push(Error::UnknownField {
+                        field: key.to_string(),
+                        path: path.clone(),
+                        expected: names.join(", "),
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn join(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", prefix, name)
+    }
+}
+
+/// Describes the value found where another was expected, as serde does.
+fn unexpected(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => format!("boolean `{}`", b),
+        Value::Number(n) if n.is_integer() => format!("integer `{}`", Value::Number(n.clone())),
+        Value::Number(n) => format!("floating point `{}`", Value::Number(n.clone())),
+        Value::String(s) => format!("string {:?}", s),
+        Value::Array(_) | Value::Table(_) => "sequence".to_string(),
+        Value::Object(_) => "map".to_string(),
+        #[cfg(feature = "datetime")]
+        Value::Date(date) => format!("string {:?}", date.to_rfc3339()),
+        #[cfg(feature = "bigint")]
+        Value::BigInt(n) => format!("integer `{}`", n),
+    }
+}
+
+/// The request format of a tool-calling API, for [`tool_definition`].
+///
+/// - **Anthropic**: `{"name", "description", "input_schema"}`, as in the
+///   `tools` of Anthropic's Messages API
+/// - **OpenAi**: `{"type": "function", "function": {"name", "description",
+///   "parameters"}}`, as in the `tools` of OpenAI's Chat Completions API
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ToolFormat {
+    #[default]
+    Anthropic,
+    OpenAi,
+}
+
+/// Converts `schema` to a JSON Schema, as tool-calling APIs take for the
+/// arguments of a tool.
+///
+/// Objects list their fields under `properties` and the required ones under
+/// `required`, and deny other fields with `"additionalProperties": false` if
+/// they do. Docs become `description`s, nullable schemas add `"null"` to
+/// their `type`, and tuples are arrays with `prefixItems`. A type that
+/// contains itself is a `$ref`: to `#` for the root, and otherwise to an
+/// entry of `$defs`. The result is a [`Value`], which serializes as JSON
+/// with `serde_json`.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::schema::{to_json_schema, ToonSchema};
+/// use serde_toon::toon;
+///
+/// let schema = to_json_schema(&Vec::<Option<String>>::toon_schema());
+/// assert_eq!(schema, toon!({"type": "array", "items": {"type": ["string", "null"]}}));
+/// ```
+#[must_use]
+pub fn to_json_schema(schema: &Schema) -> Value {
+    let root = match schema {
+        Schema::Object(object) => Some(object.name.as_str()),
+        _ => None,
+    };
+    let mut converter = JsonSchema {
+        root,
+        defs: ToonMap::new(),
+        referenced: Vec::new(),
+    };
+    let mut json = converter.convert(schema);
+    if let (Value::Object(map), false) = (&mut json, converter.defs.is_empty()) {
+        map.insert("$defs".to_string(), Value::Object(converter.defs));
+    }
+    json
+}
+
+/// Returns the declaration of a tool named `name` whose arguments are a
+/// `T`, in the format of the API it is sent to. The tool's parameters are
+/// [`to_json_schema`] of `T`'s schema, so the type that parses the
+/// arguments also declares them.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(feature = "derive")]
+/// # fn main() {
+/// use serde_toon::schema::{tool_definition, ToolFormat};
+/// use serde_toon::{toon, ToonSchema};
+///
+/// #[derive(ToonSchema)]
+/// struct Weather {
+///     /// City name, such as Paris
+///     city: String,
+///     days: Option<u8>,
+/// }
+///
+/// let tool = tool_definition::<Weather>("get_weather", "Look up a forecast", ToolFormat::Anthropic);
+/// assert_eq!(
+///     tool,
+///     toon!({
+///         "name": "get_weather",
+///         "description": "Look up a forecast",
+///         "input_schema": {
+///             "type": "object",
+///             "properties": {
+///                 "city": {"type": "string", "description": "City name, such as Paris"},
+///                 "days": {"type": ["integer", "null"]}
+///             },
+///             "required": ["city"]
+///         }
+///     })
+/// );
+/// # }
+/// # #[cfg(not(feature = "derive"))]
+/// # fn main() {}
+/// ```
+#[must_use]
+pub fn tool_definition<T: ToonSchema + ?Sized>(
+    name: &str,
+    description: &str,
+    format: ToolFormat,
+) -> Value {
+    let parameters = to_json_schema(&T::toon_schema());
+    match format {
+        ToolFormat::Anthropic => toon!({
+            "name": name,
+            "description": description,
+            "input_schema": parameters,
+        }),
+        ToolFormat::OpenAi => toon!({
+            "type": "function",
+            "function": {
+                "name": name,
+                "description": description,
+                "parameters": parameters,
+            },
+        }),
+    }
+}
+
+/// The state of a [`to_json_schema`] conversion.
+struct JsonSchema<'a> {
+    /// The name of the root object, which `#` refers to.
+    root: Option<&'a str>,
+    /// Objects that contain themselves, by name.
+    defs: ToonMap,
+    /// Names of enclosing objects referred to from within.
+    referenced: Vec<&'a str>,
+}
+
+impl<'a> JsonSchema<'a> {
+    fn convert(&mut self, schema: &'a Schema) -> Value {
+        match schema {
+            Schema::Any => Value::Object(ToonMap::new()),
+            Schema::Bool => toon!({"type": "boolean"}),
+            Schema::Integer => toon!({"type": "integer"}),
+            Schema::Number => toon!({"type": "number"}),
+            Schema::String => toon!({"type": "string"}),
+            Schema::Nullable(inner) => {
+                let mut json = self.convert(inner);
+                let Value::Object(map) = &mut json else {
+                    return json;
+                };
+                if let Some(Value::Array(variants)) = map.get_mut("enum") {
+                    variants.push(Value::Null);
+                }
+                if map.is_empty() {
+                    return json;
+                }
+                match map.get_mut("type") {
+                    Some(Value::String(kind)) => {
+                        let kind = std::mem::take(kind);
+                        map.insert("type".to_string(), toon!([kind, "null"]));
+                        json
+                    }
+                    _ => toon!({"anyOf": [json, {"type": "null"}]}),
+                }
+            }
+            Schema::Array(item) => toon!({"type": "array", "items": self.convert(item)}),
+            Schema::Tuple(items) => {
+                let items: Vec<Value> = items.iter().map(|item| self.convert(item)).collect();
+                let len = items.len();
+                toon!({
+                    "type": "array",
+                    "prefixItems": items,
+                    "minItems": len,
+                    "maxItems": len,
+                })
+            }
+            Schema::Map(value) => {
+                toon!({"type": "object", "additionalProperties": self.convert(value)})
+            }
+            Schema::Object(object) => self.object(object),
+            Schema::Enum(variants) => toon!({"type": "string", "enum": variants}),
+            Schema::Recursive(name) if Some(name.as_str()) == self.root => toon!({"$ref": "#"}),
+            Schema::Recursive(name) => {
+                self.referenced.push(name);
+                toon!({"$ref": format!("#/$defs/{}", name)})
+            }
+        }
+    }
+
+    fn object(&mut self, object: &'a ObjectSchema) -> Value {
+        let mut properties = ToonMap::new();
+        let mut required = Vec::new();
+        for field in &object.fields {
+            let mut property = self.convert(&field.schema);
+            if let (Some(doc), Value::Object(map)) = (&field.doc, &mut property) {
+                map.insert("description".to_string(), Value::from(doc.as_str()));
+            }
+            properties.insert(field.name.clone(), property);
+            if field.required {
+                required.push(Value::from(field.name.as_str()));
+            }
+        }
+
+        let mut map = ToonMap::new();
+        map.insert("type".to_string(), Value::from("object"));
+        if let Some(doc) = &object.doc {
+            map.insert("description".to_string(), Value::from(doc.as_str()));
+        }
+        map.insert("properties".to_string(), Value::Object(properties));
+        if !required.is_empty() {
+            map.insert("required".to_string(), Value::Array(required));
+        }
+        if object.deny_unknown_fields {
+            map.insert("additionalProperties".to_string(), Value::Bool(false));
+        }
+
+        let name = object.name.as_str();
+        if Some(name) != self.root && self.referenced.contains(&name) {
+            self.referenced.retain(|referenced| *referenced != name);
+            self.defs.insert(name.to_string(), Value::Object(map));
+            return toon!({"$ref": format!("#/$defs/{}", name)});
+        }
+        Value::Object(map)
+    }
+}
+
+thread_local! {
+    /// The types whose object schemas are being built, by type name.
+    static BUILDING: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Builds the object schema of the type `type_name` with `build`, or returns
+/// a [`Schema::Recursive`] reference to it when called while building it.
+/// Called by derived implementations.
+#[doc(hidden)]
+pub fn object_schema(type_name: &'static str, build: impl FnOnce() -> ObjectSchema) -> Schema {
+    let recursive = BUILDING.with(|building| building.borrow().contains(&type_name));
+    if recursive {
+        let path = type_name.split('<').next().unwrap_or(type_name);
+        let name = path.rsplit("::").next().unwrap_or(path);
+        return Schema::Recursive(name.to_string());
+    }
+    BUILDING.with(|building| building.borrow_mut().push(type_name));
+    let object = build();
+    BUILDING.with(|building| building.borrow_mut().pop());
+    Schema::Object(object)
+}
+
+/// Returns the fields that a `#[serde(flatten)]` field of schema `schema`
+/// adds to its parent. Called by derived implementations.
+#[doc(hidden)]
+#[must_use]
+pub fn flattened(schema: Schema) -> Vec<Field> {
+    match schema {
+        Schema::Object(object) => object.fields,
+        Schema::Nullable(inner) => flattened(*inner)
+            .into_iter()
+            .map(|field| field.with_required(false))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+macro_rules! impl_schema {
+    ($schema:expr => $($ty:ty),*) => {
+        $(
+            impl ToonSchema for $ty {
+                fn toon_schema() -> Schema {
+                    $schema
+                }
+            }
+        )*
+    };
+}
+
+impl_schema!(Schema::Bool => bool);
+impl_schema!(Schema::Integer => i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+impl_schema!(Schema::Number => f32, f64);
+impl_schema!(Schema::String => String, str, char);
+impl_schema!(Schema::Any => Value);
+impl_schema!(Schema::Map(Box::new(Schema::Any)) => ToonMap);
+
+impl<T: ToonSchema> ToonSchema for Option<T> {
+    fn toon_schema() -> Schema {
+        match T::toon_schema() {
+            nullable @ Schema::Nullable(_) => nullable,
+            schema => Schema::Nullable(Box::new(schema)),
+        }
+    }
+}
+
+macro_rules! impl_wrapper_schema {
+    ($($ty:ident),*) => {
+        $(
+            impl<T: ToonSchema + ?Sized> ToonSchema for $ty<T> {
+                fn toon_schema() -> Schema {
+                    T::toon_schema()
+                }
+            }
+        )*
+    };
+}
+
+impl_wrapper_schema!(Box, Rc, Arc);
+
+impl<T: ToonSchema + ?Sized> ToonSchema for &T {
+    fn toon_schema() -> Schema {
+        T::toon_schema()
+    }
+}
+
+impl<T: ToonSchema> ToonSchema for Vec<T> {
+    fn toon_schema() -> Schema {
+        Schema::Array(Box::new(T::toon_schema()))
+    }
+}
+
+impl<T: ToonSchema> ToonSchema for VecDeque<T> {
+    fn toon_schema() -> Schema {
+        Schema::Array(Box::new(T::toon_schema()))
+    }
+}
+
+impl<T: ToonSchema, S> ToonSchema for HashSet<T, S> {
+    fn toon_schema() -> Schema {
+        Schema::Array(Box::new(T::toon_schema()))
+    }
+}
+
+impl<T: ToonSchema> ToonSchema for BTreeSet<T> {
+    fn toon_schema() -> Schema {
+        Schema::Array(Box::new(T::toon_schema()))
+    }
+}
+
+impl<T: ToonSchema> ToonSchema for [T] {
+    fn toon_schema() -> Schema {
+        Schema::Array(Box::new(T::toon_schema()))
+    }
+}
+
+impl<T: ToonSchema, const N: usize> ToonSchema for [T; N] {
+    fn toon_schema() -> Schema {
+        Schema::Tuple(vec![T::toon_schema(); N])
+    }
+}
+
+impl<K, V: ToonSchema, S> ToonSchema for HashMap<K, V, S> {
+    fn toon_schema() -> Schema {
+        Schema::Map(Box::new(V::toon_schema()))
+    }
+}
+
+impl<K, V: ToonSchema> ToonSchema for BTreeMap<K, V> {
+    fn toon_schema() -> Schema {
+        Schema::Map(Box::new(V::toon_schema()))
+    }
+}
+
+impl<K, V: ToonSchema, S> ToonSchema for indexmap::IndexMap<K, V, S> {
+    fn toon_schema() -> Schema {
+        Schema::Map(Box::new(V::toon_schema()))
+    }
+}
+
+macro_rules! impl_tuple_schema {
+    ($(($($name:ident),+))*) => {
+        $(
+            impl<$($name: ToonSchema),+> ToonSchema for ($($name,)+) {
+                fn toon_schema() -> Schema {
+                    Schema::Tuple(vec![$($name::toon_schema()),+])
+                }
+            }
+        )*
+    };
+}
+
+impl_tuple_schema! {
+    (A)
+    (A, B)
+    (A, B, C)
+    (A, B, C, D)
+    (A, B, C, D, E)
+    (A, B, C, D, E, F)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toon;
+
+    /// A hand-written version of what the derive generates for a recursive
+    /// type.
+    struct Node;
+
+    impl ToonSchema for Node {
+        fn toon_schema() -> Schema {
+            object_schema(std::any::type_name::<Node>(), || ObjectSchema {
+                name: "Node".to_string(),
+                doc: None,
+                fields: vec![
+                    Field::new("name", String::toon_schema()).with_doc("Node name"),
+                    Field::new("children", Vec::<Node>::toon_schema()).with_required(false),
+                ],
+                deny_unknown_fields: true,
+            })
+        }
+    }
+
+    #[test]
+    fn renders_and_validates_recursive_types() {
+        let schema = Node::toon_schema();
+        assert_eq!(
+            schema.prompt(),
+            "name: <string>\nchildren: [N]: <Node>,...\n\nFields:\n- name: Node name"
+        );
+
+        let tree = toon!({"name": "a", "children": [{"name": "b"}, {"name": "c", "children": []}]});
+        assert!(schema.validate(&tree).is_ok());
+
+        let tree = toon!({"children": [{"name": 1, "extra": true}, "leaf"]});
+        let found: Vec<String> = schema
+            .violations(&tree)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            found,
+            [
+                "Missing field `name` at (root)",
+                "invalid type: integer `1`, expected a string at children[0].name",
+                "Unknown field `extra` at children[0], expected one of: name, children",
+                "invalid type: string \"leaf\", expected an object at children[1]",
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_primitives_tables_and_enums() {
+        let schema = Schema::Object(ObjectSchema {
+            name: "Row".to_string(),
+            fields: vec![
+                Field::new("id", Schema::Integer),
+                Field::new("score", Option::<f64>::toon_schema()),
+                Field::new(
+                    "tier",
+                    Schema::Enum(vec!["free".to_string(), "pro".to_string()]),
+                ),
+            ],
+            ..ObjectSchema::default()
+        });
+        let rows = Schema::Array(Box::new(schema));
+        let table = crate::toon_table! { (id, score, tier); (1, 0.5, "pro"); (2, null, "free"); };
+        assert!(rows.validate(&table).is_ok());
+
+        let found: Vec<String> = rows
+            .violations(&toon!([{"id": 1.5, "score": "high", "tier": "gold"}]))
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            found,
+            [
+                "invalid type: floating point `1.5`, expected an integer at [0].id",
+                "invalid type: string \"high\", expected a number or null at [0].score",
+                "unknown variant `gold`, expected one of `free`, `pro` at [0].tier",
+            ]
+        );
+        assert_eq!(
+            <[u8; 2]>::toon_schema()
+                .validate(&toon!({"a": 1}))
+                .unwrap_err()
+                .to_string(),
+            "invalid type: map, expected an array of 2 items at (root)"
+        );
+    }
+
+    #[test]
+    fn exports_json_schemas_and_tools() {
+        let tree = Schema::Object(ObjectSchema {
+            name: "Tree".to_string(),
+            doc: Some("A tree".to_string()),
+            fields: vec![
+                Field::new("root", Node::toon_schema()),
+                Field::new("size", <[u8; 2]>::toon_schema()),
+                Field::new(
+                    "kind",
+                    Schema::Nullable(Box::new(Schema::Enum(vec!["a".to_string()]))),
+                ),
+                Field::new("meta", HashMap::<String, Option<Value>>::toon_schema()),
+            ],
+            deny_unknown_fields: false,
+        });
+        assert_eq!(
+            to_json_schema(&tree),
+            toon!({
+                "type": "object",
+                "description": "A tree",
+                "properties": {
+                    "root": {"$ref": "#/$defs/Node"},
+                    "size": {
+                        "type": "array",
+                        "prefixItems": [{"type": "integer"}, {"type": "integer"}],
+                        "minItems": 2,
+                        "maxItems": 2
+                    },
+                    "kind": {"type": ["string", "null"], "enum": ["a", null]},
+                    "meta": {"type": "object", "additionalProperties": {}}
+                },
+                "required": ["root", "size", "meta"],
+                "$defs": {
+                    "Node": {
+                        "type": "object",
+                        "properties": {
+                            "name": {"type": "string", "description": "Node name"},
+                            "children": {"type": "array", "items": {"$ref": "#/$defs/Node"}}
+                        },
+                        "required": ["name"],
+                        "additionalProperties": false
+                    }
+                }
+            })
+        );
+
+        let node = to_json_schema(&Node::toon_schema());
+        let fields = node.as_object().unwrap();
+        assert_eq!(
+            fields
+                .get("properties")
+                .and_then(Value::as_object)
+                .unwrap()
+                .get("children"),
+            Some(&toon!({"type": "array", "items": {"$ref": "#"}}))
+        );
+        assert!(!fields.contains_key("$defs"));
+
+        let tool = tool_definition::<Node>("walk", "Walk a tree", ToolFormat::OpenAi);
+        assert_eq!(
+            tool,
+            toon!({
+                "type": "function",
+                "function": {"name": "walk", "description": "Walk a tree", "parameters": node}
+            })
+        );
+    }
+}
@@ -49,10 +49,101 @@
 //! let toon_string = serializer.into_inner();
 //! assert_eq!(toon_string, "[5]: 1,2,3,4,5");
 //! ```
+//!
+//! ## Reusing Buffers
+//!
+//! Each [`to_string`](crate::to_string) call allocates a new `String`. A
+//! service writing many small documents can reuse one buffer instead, with
+//! [`to_string_into`](crate::to_string_into) or a [`Serializer`] that is
+//! [`reset`](Serializer::reset) between documents. Serializers are not
+//! `Send`, so keep one buffer per thread:
+//!
+//! ```rust
+//! use serde::Serialize;
+//! use serde_toon::to_string_into;
+//! use std::cell::RefCell;
+//!
+//! thread_local! {
+//!     static BUFFER: RefCell<String> = RefCell::new(String::with_capacity(1024));
+//! }
+//!
+//! #[derive(Serialize)]
+//! struct Event { id: u64, kind: &'static str }
+//!
+//! fn respond(event: &Event) -> serde_toon::Result<usize> {
+//!     BUFFER.with(|buffer| {
+//!         let mut buffer = buffer.borrow_mut();
+//!         to_string_into(event, &mut buffer)?;
+//!         // Send `buffer` while it is borrowed, instead of cloning it
+//!         Ok(buffer.len())
+//!     })
+//! }
+//!
+//! assert_eq!(respond(&Event { id: 1, kind: "click" }).unwrap(), 17);
+//! ```
 
-use crate::{Error, Number, Result, ToonMap, ToonOptions, Value};
+use crate::bytes;
+use crate::error::{Warning, WarningKind};
+use crate::options::{
+    BytesFormat, Delimiter, DelimiterStrategy, EnumRepr, FieldAction, FloatFormat, LineEnding,
+    NonStringKeys, QuotePolicy, RoundingMode, TypeCodec, VectorEncoding,
+};
+use crate::path::{Path, PathSegment};
+use crate::raw;
+use crate::trace;
+#[cfg(feature = "rust_decimal")]
+use crate::value::parse_decimal;
+use crate::value::{DECIMAL_TOKEN, NUMBER_TOKEN};
+use crate::{Error, Key, Number, Result, Table, ToonMap, ToonOptions, UnsupportedPolicy, Value};
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
 use serde::ser::SerializeSeq;
 use serde::{ser, Serialize};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fmt::{self, Write};
+use std::rc::Rc;
+
+/// State shared by every serializer taking part in one serialization run.
+///
+/// Tracks the path of the value currently being serialized (for error messages)
+/// and collects warnings produced by [`UnsupportedPolicy::Null`].
+struct State {
+    options: ToonOptions,
+    path: RefCell<Path>,
+    warnings: RefCell<Vec<Warning>>,
+}
+
+impl State {
+    fn new(options: ToonOptions) -> Rc<Self> {
+        Rc::new(State {
+            options,
+            path: RefCell::new(Path::root()),
+            warnings: RefCell::new(Vec::new()),
+        })
+    }
+
+    /// Runs `f` with `segment` appended to the current path.
+    fn with_segment<T>(&self, segment: PathSegment, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        self.path.borrow_mut().push(segment);
+        let result = f();
+        self.path.borrow_mut().pop();
+        result
+    }
+
+    fn current_path(&self) -> String {
+        self.path.borrow().to_string()
+    }
+
+    fn warn(&self, message: &str) {
+        let path = self.current_path();
+        self.warnings.borrow_mut().push(Warning::at_path(
+            WarningKind::UnsupportedValue,
+            &path,
+            message,
+        ));
+    }
+}
 
 /// The TOON serializer.
 ///
@@ -60,68 +151,122 @@ use serde::{ser, Serialize};
 /// Created via [`Serializer::new`] with customizable options.
 pub struct Serializer {
     output: String,
-    options: ToonOptions,
+    state: Rc<State>,
     indent_level: usize,
+    // Set once line endings and the trailing newline have been applied
+    finished: bool,
 }
 
 impl Serializer {
+    /// Creates a serializer whose output starts with room for
+    /// [`ToonOptions::size_hint`] bytes, or 256 without a hint.
     pub fn new(options: ToonOptions) -> Self {
-        // Pre-allocate with reasonable capacity to reduce reallocations
         // 256 bytes is a good starting point for typical structs
+        let capacity = options.size_hint.unwrap_or(256);
+        Serializer::with_capacity(options, capacity)
+    }
+
+    /// Creates a serializer whose output starts with room for `capacity`
+    /// bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_toon::{Serializer, ToonOptions};
+    ///
+    /// let mut serializer = Serializer::with_capacity(ToonOptions::new(), 64 * 1024);
+    /// vec![1, 2, 3].serialize(&mut serializer).unwrap();
+    /// assert_eq!(serializer.into_inner(), "[3]: 1,2,3");
+    /// ```
+    pub fn with_capacity(options: ToonOptions, capacity: usize) -> Self {
+        Serializer::with_output(options, String::with_capacity(capacity))
+    }
+
+    /// Creates a serializer that writes into `output`, which must be empty.
+    pub(crate) fn with_output(options: ToonOptions, output: String) -> Self {
         Serializer {
-            output: String::with_capacity(256),
-            options,
+            output,
+            state: State::new(options),
             indent_level: 0,
+            finished: false,
         }
     }
 
-    pub fn into_inner(self) -> String {
+    pub fn into_inner(mut self) -> String {
+        self.finish();
         self.output
     }
 
-    fn write_newline(&mut self) {
-        if self.options.pretty {
-            self.output.push('\n');
+    /// Ends the document and returns it, without giving up the output's
+    /// allocation as [`into_inner`](Serializer::into_inner) does. Call
+    /// [`reset`](Serializer::reset) before writing the next document.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_toon::{Serializer, ToonOptions};
+    ///
+    /// let mut serializer = Serializer::new(ToonOptions::new());
+    /// for (id, expected) in [(1, "id: 1"), (22, "id: 22")] {
+    ///     serializer.reset();
+    ///     std::collections::BTreeMap::from([("id", id)]).serialize(&mut serializer).unwrap();
+    ///     assert_eq!(serializer.finish(), expected);
+    /// }
+    /// ```
+    pub fn finish(&mut self) -> &str {
+        if !self.finished {
+            finish_lines(&mut self.output, &self.state.options);
+            self.finished = true;
+        }
+        &self.output
+    }
+
+    /// Clears the output and warnings so that the serializer can write
+    /// another document with the same options, keeping the output's
+    /// allocation.
+    pub fn reset(&mut self) {
+        self.output.clear();
+        self.indent_level = 0;
+        self.finished = false;
+        match Rc::get_mut(&mut self.state) {
+            Some(state) => {
+                *state.path.get_mut() = Path::root();
+                state.warnings.get_mut().clear();
+            }
+            None => self.state = State::new(self.state.options.clone()),
         }
     }
 
-    #[inline]
-    fn needs_quotes(s: &str) -> bool {
-        s.is_empty()
-            || s.contains(':')
-            || s.contains(',')
-            || s.contains('\n')
-            || s.contains('\t')
-            || s.contains('|')
-            || s.contains('"')
-            || s.contains('\\')
-            || s.contains('\0')
-            || s.starts_with(' ')
-            || s.ends_with(' ')
-            || s == "true"
-            || s == "false"
-            || s == "null"
-            || s.parse::<f64>().is_ok()
+    /// Returns the warnings recorded so far.
+    ///
+    /// Warnings are only produced when [`UnsupportedPolicy::Null`] replaces a value.
+    #[must_use]
+    pub fn warnings(&self) -> Vec<Warning> {
+        self.state.warnings.borrow().clone()
+    }
+
+    fn value_serializer(&self) -> ValueSerializer {
+        ValueSerializer {
+            state: Rc::clone(&self.state),
+        }
+    }
+
+    /// Writes an already-built value at the current position.
+    fn write_value(&mut self, value: &Value) {
+        write_value(
+            &mut self.output,
+            value,
+            &self.state.options,
+            self.indent_level,
+        );
     }
 
     #[inline]
     fn write_string(&mut self, s: &str) {
-        if Self::needs_quotes(s) {
-            self.output.push('"');
-            for ch in s.chars() {
-                match ch {
-                    '"' => self.output.push_str("\\\""),
-                    '\\' => self.output.push_str("\\\\"),
-                    '\n' => self.output.push_str("\\n"),
-                    '\r' => self.output.push_str("\\r"),
-                    '\t' => self.output.push_str("\\t"),
-                    '\u{0008}' => self.output.push_str("\\b"), // backspace
-                    '\u{000C}' => self.output.push_str("\\f"), // form feed
-                    '\0' => self.output.push_str("\\0"),
-                    _ => self.output.push(ch),
-                }
-            }
-            self.output.push('"');
+        if quotes_string(s, &self.state.options) {
+            write_escaped(&mut self.output, s, &self.state.options);
         } else {
             self.output.push_str(s);
         }
@@ -158,7 +303,13 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     }
 
     fn serialize_i64(self, v: i64) -> Result<Self::Ok> {
-        self.output.push_str(&v.to_string());
+        write_integer(&mut self.output, v.into(), &self.state.options);
+        Ok(())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok> {
+        let value = self.value_serializer().serialize_i128(v)?;
+        self.write_value(&value);
         Ok(())
     }
 
@@ -175,16 +326,22 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     }
 
     fn serialize_u64(self, v: u64) -> Result<Self::Ok> {
-        self.output.push_str(&v.to_string());
+        write_integer(&mut self.output, v.into(), &self.state.options);
+        Ok(())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok> {
+        let value = self.value_serializer().serialize_u128(v)?;
+        self.write_value(&value);
         Ok(())
     }
 
     fn serialize_f32(self, v: f32) -> Result<Self::Ok> {
-        self.serialize_f64(v as f64)
+        self.serialize_f64(widen_f32(v))
     }
 
     fn serialize_f64(self, v: f64) -> Result<Self::Ok> {
-        self.output.push_str(&v.to_string());
+        write_f64(&mut self.output, v, &self.state.options.float_format);
         Ok(())
     }
 
@@ -199,6 +356,11 @@ impl<'a> ser::Serializer for &'a mut Serializer {
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
         use ser::SerializeSeq;
+        match self.state.options.bytes_format {
+            BytesFormat::Base64 => return self.serialize_str(&bytes::encode_base64(v)),
+            BytesFormat::Hex => return self.serialize_str(&bytes::encode_hex(v)),
+            BytesFormat::IntArray => {}
+        }
         let mut seq = self.serialize_seq(Some(v.len()))?;
         for byte in v {
             seq.serialize_element(byte)?;
@@ -228,104 +390,136 @@ impl<'a> ser::Serializer for &'a mut Serializer {
 
     fn serialize_unit_variant(
         self,
-        _name: &'static str,
-        _variant_index: u32,
+        name: &'static str,
+        variant_index: u32,
         variant: &'static str,
     ) -> Result<Self::Ok> {
-        self.serialize_str(variant)
+        if self.state.options.enum_repr == EnumRepr::External {
+            return self.serialize_str(variant);
+        }
+        let value = self
+            .value_serializer()
+            .serialize_unit_variant(name, variant_index, variant)?;
+        self.write_value(&value);
+        Ok(())
     }
 
-    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Self::Ok>
     where
         T: ?Sized + Serialize,
     {
+        // A whole document held as `RawToon` is written out unchanged, unless
+        // a field filter has to see its fields
+        if name == raw::TOKEN {
+            if let Value::String(text) = value.serialize(self.value_serializer())? {
+                if self.state.options.field_filter.is_none() {
+                    self.output.push_str(&text);
+                    return Ok(());
+                }
+                return raw::parse_for_output(&text)?.serialize(self);
+            }
+        }
+        if name == NUMBER_TOKEN || name == DECIMAL_TOKEN {
+            if let Value::String(text) = value.serialize(self.value_serializer())? {
+                self.write_value(&Value::Number(Number::Raw(text)));
+                return Ok(());
+            }
+        }
+        if let Some(codec) = self.state.options.type_codec(name).cloned() {
+            let value = codec.encode(&value.serialize(self.value_serializer())?)?;
+            self.write_value(&value);
+            return Ok(());
+        }
         value.serialize(self)
     }
 
     fn serialize_newtype_variant<T>(
         self,
-        _name: &'static str,
-        _variant_index: u32,
+        name: &'static str,
+        variant_index: u32,
         variant: &'static str,
         value: &T,
     ) -> Result<Self::Ok>
     where
         T: ?Sized + Serialize,
     {
-        self.output.push_str(variant);
-        self.output.push(':');
-        if self.options.pretty {
-            self.output.push(' ');
-        }
-        value.serialize(self)
+        let value = self.value_serializer().serialize_newtype_variant(
+            name,
+            variant_index,
+            variant,
+            value,
+        )?;
+        self.write_value(&value);
+        Ok(())
     }
 
-    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
         Ok(SeqSerializer {
             ser: self,
-            elements: Vec::new(),
+            elements: Vec::with_capacity(len.unwrap_or(0)),
         })
     }
 
-    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
         Ok(TupleSerializer {
             ser: self,
-            elements: Vec::new(),
+            elements: Vec::with_capacity(len),
         })
     }
 
     fn serialize_tuple_struct(
         self,
-        _name: &'static str,
-        _len: usize,
+        name: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
         Ok(TupleStructSerializer {
+            codec: self.state.options.type_codec(name).cloned(),
             ser: self,
-            elements: Vec::new(),
+            elements: Vec::with_capacity(len),
         })
     }
 
     fn serialize_tuple_variant(
         self,
-        _name: &'static str,
-        _variant_index: u32,
+        name: &'static str,
+        variant_index: u32,
         variant: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        Ok(TupleVariantSerializer {
-            ser: self,
-            variant: variant.to_string(),
-            elements: Vec::new(),
-        })
+        let inner =
+            self.value_serializer()
+                .serialize_tuple_variant(name, variant_index, variant, len)?;
+        Ok(TupleVariantSerializer { ser: self, inner })
     }
 
-    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
         Ok(MapSerializer {
             ser: self,
-            entries: Vec::new(),
+            entries: Vec::with_capacity(len.unwrap_or(0)),
             current_key: None,
+            skip_value: false,
         })
     }
 
-    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
         Ok(StructSerializer {
+            codec: self.state.options.type_codec(name).cloned(),
             ser: self,
-            entries: Vec::new(),
+            entries: Vec::with_capacity(len),
         })
     }
 
     fn serialize_struct_variant(
         self,
-        _name: &'static str,
-        _variant_index: u32,
+        name: &'static str,
+        variant_index: u32,
         variant: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        Ok(StructVariantSerializer {
-            ser: self,
-            variant: variant.to_string(),
-            entries: Vec::new(),
-        })
+        let inner =
+            self.value_serializer()
+                .serialize_struct_variant(name, variant_index, variant, len)?;
+        Ok(StructVariantSerializer { ser: self, inner })
     }
 }
 
@@ -342,18 +536,19 @@ impl<'a> ser::SerializeSeq for SeqSerializer<'a> {
     where
         T: ?Sized + Serialize,
     {
-        let toon_value = to_toon_value(value)?;
+        let toon_value = element_to_value(&self.ser.state, self.elements.len(), value)?;
         self.elements.push(toon_value);
         Ok(())
     }
 
     fn end(self) -> Result<Self::Ok> {
         if self.elements.is_empty() {
-            self.ser.output.push_str("[0]:");
+            write_length(&mut self.ser.output, 0, &self.ser.state.options, false);
+            self.ser.output.push(':');
             return Ok(());
         }
 
-        let tabular = can_be_tabular(&self.elements);
+        let tabular = tabular_rows(&self.elements, &self.ser.state.options);
 
         if let Some((headers, rows)) = tabular {
             // Tabular format: [N]{field1,field2}:
@@ -361,7 +556,7 @@ impl<'a> ser::SerializeSeq for SeqSerializer<'a> {
                 &mut self.ser.output,
                 &headers,
                 &rows,
-                &self.ser.options,
+                &self.ser.state.options,
                 self.ser.indent_level,
             );
         } else {
@@ -370,13 +565,17 @@ impl<'a> ser::SerializeSeq for SeqSerializer<'a> {
 
             if all_primitives {
                 // Inline format: [N]: val1,val2,val3
-                write_inline_array(&mut self.ser.output, &self.elements, &self.ser.options);
+                write_inline_array(
+                    &mut self.ser.output,
+                    &self.elements,
+                    &self.ser.state.options,
+                );
             } else {
                 // List format with "- " prefix
                 write_list_array(
                     &mut self.ser.output,
                     &self.elements,
-                    &self.ser.options,
+                    &self.ser.state.options,
                     self.ser.indent_level,
                 );
             }
@@ -399,7 +598,7 @@ impl<'a> ser::SerializeTuple for TupleSerializer<'a> {
     where
         T: ?Sized + Serialize,
     {
-        let toon_value = to_toon_value(value)?;
+        let toon_value = element_to_value(&self.ser.state, self.elements.len(), value)?;
         self.elements.push(toon_value);
         Ok(())
     }
@@ -416,6 +615,7 @@ impl<'a> ser::SerializeTuple for TupleSerializer<'a> {
 pub struct TupleStructSerializer<'a> {
     ser: &'a mut Serializer,
     elements: Vec<Value>,
+    codec: Option<TypeCodec>,
 }
 
 impl<'a> ser::SerializeTupleStruct for TupleStructSerializer<'a> {
@@ -426,12 +626,17 @@ impl<'a> ser::SerializeTupleStruct for TupleStructSerializer<'a> {
     where
         T: ?Sized + Serialize,
     {
-        let toon_value = to_toon_value(value)?;
+        let toon_value = element_to_value(&self.ser.state, self.elements.len(), value)?;
         self.elements.push(toon_value);
         Ok(())
     }
 
     fn end(self) -> Result<Self::Ok> {
+        if let Some(codec) = self.codec {
+            let value = codec.encode(&Value::Array(self.elements))?;
+            self.ser.write_value(&value);
+            return Ok(());
+        }
         let seq_ser = SeqSerializer {
             ser: self.ser,
             elements: self.elements,
@@ -442,8 +647,7 @@ impl<'a> ser::SerializeTupleStruct for TupleStructSerializer<'a> {
 
 pub struct TupleVariantSerializer<'a> {
     ser: &'a mut Serializer,
-    variant: String,
-    elements: Vec<Value>,
+    inner: SerializeVec,
 }
 
 impl<'a> ser::SerializeTupleVariant for TupleVariantSerializer<'a> {
@@ -454,23 +658,13 @@ impl<'a> ser::SerializeTupleVariant for TupleVariantSerializer<'a> {
     where
         T: ?Sized + Serialize,
     {
-        let toon_value = to_toon_value(value)?;
-        self.elements.push(toon_value);
-        Ok(())
+        ser::SerializeTupleVariant::serialize_field(&mut self.inner, value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        self.ser.output.push_str(&self.variant);
-        self.ser.output.push(':');
-        if self.ser.options.pretty {
-            self.ser.output.push(' ');
-        }
-
-        let seq_ser = SeqSerializer {
-            ser: self.ser,
-            elements: self.elements,
-        };
-        seq_ser.end()
+        let value = ser::SerializeTupleVariant::end(self.inner)?;
+        self.ser.write_value(&value);
+        Ok(())
     }
 }
 
@@ -478,6 +672,7 @@ pub struct MapSerializer<'a> {
     ser: &'a mut Serializer,
     entries: Vec<(String, Value)>,
     current_key: Option<String>,
+    skip_value: bool,
 }
 
 impl<'a> ser::SerializeMap for MapSerializer<'a> {
@@ -488,26 +683,25 @@ impl<'a> ser::SerializeMap for MapSerializer<'a> {
     where
         T: ?Sized + Serialize,
     {
-        let key_value = to_toon_value(key)?;
-        match key_value {
-            Value::String(s) => {
-                self.current_key = Some(s);
-                Ok(())
-            }
-            _ => Err(Error::custom("Map keys must be strings")),
-        }
+        self.current_key = map_key(&self.ser.state, key)?;
+        self.skip_value = self.current_key.is_none();
+        Ok(())
     }
 
     fn serialize_value<T>(&mut self, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
+        if std::mem::take(&mut self.skip_value) {
+            return Ok(());
+        }
         let key = self
             .current_key
             .take()
             .ok_or_else(|| Error::custom("serialize_value called without serialize_key"))?;
-        let toon_value = to_toon_value(value)?;
-        self.entries.push((key, toon_value));
+        if let Some(toon_value) = field_to_value(&self.ser.state, &key, value)? {
+            self.entries.push((key, toon_value));
+        }
         Ok(())
     }
 
@@ -515,7 +709,7 @@ impl<'a> ser::SerializeMap for MapSerializer<'a> {
         write_object(
             &mut self.ser.output,
             &self.entries,
-            &self.ser.options,
+            &self.ser.state.options,
             self.ser.indent_level,
         );
         Ok(())
@@ -525,6 +719,7 @@ impl<'a> ser::SerializeMap for MapSerializer<'a> {
 pub struct StructSerializer<'a> {
     ser: &'a mut Serializer,
     entries: Vec<(String, Value)>,
+    codec: Option<TypeCodec>,
 }
 
 impl<'a> ser::SerializeStruct for StructSerializer<'a> {
@@ -535,16 +730,22 @@ impl<'a> ser::SerializeStruct for StructSerializer<'a> {
     where
         T: ?Sized + Serialize,
     {
-        let toon_value = to_toon_value(value)?;
-        self.entries.push((key.to_string(), toon_value));
+        if let Some(toon_value) = field_to_value(&self.ser.state, key, value)? {
+            self.entries.push((key.to_string(), toon_value));
+        }
         Ok(())
     }
 
     fn end(self) -> Result<Self::Ok> {
+        if let Some(codec) = self.codec {
+            let value = codec.encode(&Value::Object(self.entries.into_iter().collect()))?;
+            self.ser.write_value(&value);
+            return Ok(());
+        }
         write_object(
             &mut self.ser.output,
             &self.entries,
-            &self.ser.options,
+            &self.ser.state.options,
             self.ser.indent_level,
         );
         Ok(())
@@ -553,8 +754,7 @@ impl<'a> ser::SerializeStruct for StructSerializer<'a> {
 
 pub struct StructVariantSerializer<'a> {
     ser: &'a mut Serializer,
-    variant: String,
-    entries: Vec<(String, Value)>,
+    inner: SerializeMap,
 }
 
 impl<'a> ser::SerializeStructVariant for StructVariantSerializer<'a> {
@@ -565,44 +765,108 @@ impl<'a> ser::SerializeStructVariant for StructVariantSerializer<'a> {
     where
         T: ?Sized + Serialize,
     {
-        let toon_value = to_toon_value(value)?;
-        self.entries.push((key.to_string(), toon_value));
-        Ok(())
+        ser::SerializeStructVariant::serialize_field(&mut self.inner, key, value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        self.ser.output.push_str(&self.variant);
-        self.ser.output.push(':');
+        let value = ser::SerializeStructVariant::end(self.inner)?;
+        self.ser.write_value(&value);
+        Ok(())
+    }
+}
 
-        if self.ser.options.pretty {
-            self.ser.write_newline();
-            self.ser.indent_level += 1;
-        }
+/// A serializer that builds a [`Value`] instead of TOON text.
+///
+/// This is what [`to_value`](crate::to_value) uses. Create one with
+/// [`ValueSerializer::new`] or [`ValueSerializer::with_options`]; the options'
+/// [`UnsupportedPolicy`] decides what happens to values TOON cannot represent.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde::Serialize;
+/// use serde_toon::{ToonOptions, UnsupportedPolicy, Value, ValueSerializer};
+/// use std::collections::BTreeMap;
+///
+/// let mut data = BTreeMap::new();
+/// data.insert(vec![1, 2], "point");
+///
+/// let options = ToonOptions::new().with_unsupported_policy(UnsupportedPolicy::Null);
+/// let serializer = ValueSerializer::with_options(options);
+/// let value = data.serialize(serializer.clone()).unwrap();
+///
+/// assert_eq!(value.as_object().map(|obj| obj.len()), Some(0));
+/// assert_eq!(serializer.warnings().len(), 1);
+/// ```
+#[derive(Clone)]
+pub struct ValueSerializer {
+    state: Rc<State>,
+}
 
-        write_object(
-            &mut self.ser.output,
-            &self.entries,
-            &self.ser.options,
-            self.ser.indent_level,
-        );
+impl ValueSerializer {
+    /// Creates a value serializer with default options.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_options(ToonOptions::default())
+    }
 
-        if self.ser.options.pretty {
-            self.ser.indent_level -= 1;
+    /// Creates a value serializer with custom options.
+    #[must_use]
+    pub fn with_options(options: ToonOptions) -> Self {
+        ValueSerializer {
+            state: State::new(options),
         }
+    }
 
-        Ok(())
+    /// Returns the warnings recorded by this serializer and its clones.
+    #[must_use]
+    pub fn warnings(&self) -> Vec<Warning> {
+        self.state.warnings.borrow().clone()
+    }
+
+    /// Converts a map key as this serializer does, or returns `None` for a
+    /// key whose entry is dropped.
+    pub(crate) fn map_key<T: Serialize + ?Sized>(&self, key: &T) -> Result<Option<String>> {
+        map_key(&self.state, key)
+    }
+
+    /// Applies the [`UnsupportedPolicy`] to a value that cannot be represented.
+    fn unsupported(&self, type_name: &str, value: Value) -> Result<Value> {
+        match self.state.options.unsupported_policy {
+            UnsupportedPolicy::Error => Err(Error::unsupported_type(
+                type_name,
+                &self.state.current_path(),
+            )),
+            UnsupportedPolicy::Stringify => Ok(Value::String(inline_text(&value))),
+            UnsupportedPolicy::Null => {
+                self.state
+                    .warn(&format!("unsupported {} replaced with null", type_name));
+                Ok(Value::Null)
+            }
+        }
     }
 }
 
-pub struct ValueSerializer;
+impl Default for ValueSerializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 pub struct SerializeVec {
+    state: Rc<State>,
     vec: Vec<Value>,
+    variant: Option<&'static str>,
+    codec: Option<TypeCodec>,
 }
 
 pub struct SerializeMap {
+    state: Rc<State>,
     map: ToonMap,
     current_key: Option<String>,
+    skip_value: bool,
+    variant: Option<&'static str>,
+    codec: Option<TypeCodec>,
 }
 
 impl ser::Serializer for ValueSerializer {
@@ -657,8 +921,28 @@ impl ser::Serializer for ValueSerializer {
         }
     }
 
+    fn serialize_i128(self, v: i128) -> Result<Value> {
+        match i64::try_from(v) {
+            Ok(i) => Ok(Value::Number(Number::Integer(i))),
+            #[cfg(feature = "bigint")]
+            Err(_) => Ok(Value::BigInt(BigInt::from(v))),
+            #[cfg(not(feature = "bigint"))]
+            Err(_) => Ok(Value::Number(Number::Float(v as f64))),
+        }
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value> {
+        match i64::try_from(v) {
+            Ok(i) => Ok(Value::Number(Number::Integer(i))),
+            #[cfg(feature = "bigint")]
+            Err(_) => Ok(Value::BigInt(BigInt::from(v))),
+            #[cfg(not(feature = "bigint"))]
+            Err(_) => Ok(Value::Number(Number::Float(v as f64))),
+        }
+    }
+
     fn serialize_f32(self, v: f32) -> Result<Value> {
-        Ok(Value::Number(Number::Float(v as f64)))
+        Ok(Value::Number(Number::Float(widen_f32(v))))
     }
 
     fn serialize_f64(self, v: f64) -> Result<Value> {
@@ -674,6 +958,11 @@ impl ser::Serializer for ValueSerializer {
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Value> {
+        match self.state.options.bytes_format {
+            BytesFormat::Base64 => return Ok(Value::String(bytes::encode_base64(v))),
+            BytesFormat::Hex => return Ok(Value::String(bytes::encode_hex(v))),
+            BytesFormat::IntArray => {}
+        }
         let vec = v
             .iter()
             .map(|&b| Value::Number(Number::Integer(b as i64)))
@@ -706,81 +995,166 @@ impl ser::Serializer for ValueSerializer {
         _variant_index: u32,
         variant: &'static str,
     ) -> Result<Value> {
-        Ok(Value::String(variant.to_string()))
+        tag_variant(&self.state, variant, None)
     }
 
-    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Value>
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Value>
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(self)
+        let filtered = self.state.options.field_filter.is_some();
+        let value = value.serialize(self.clone())?;
+        match value {
+            Value::String(text) if name == raw::TOKEN => {
+                let parsed = raw::parse_for_output(&text)?;
+                if filtered {
+                    parsed.serialize(self)
+                } else {
+                    Ok(parsed)
+                }
+            }
+            Value::String(text) if name == NUMBER_TOKEN => Ok(Value::Number(Number::Raw(text))),
+            #[cfg(feature = "rust_decimal")]
+            Value::String(text) if name == DECIMAL_TOKEN => Ok(Value::Number(
+                parse_decimal(&text).map_or(Number::Raw(text), Number::Decimal),
+            )),
+            #[cfg(not(feature = "rust_decimal"))]
+            Value::String(text) if name == DECIMAL_TOKEN => Ok(Value::Number(Number::Raw(text))),
+            _ => match self.state.options.type_codec(name) {
+                Some(codec) => codec.encode(&value),
+                None => Ok(value),
+            },
+        }
     }
 
     fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _value: &T,
+        variant: &'static str,
+        value: &T,
     ) -> Result<Value>
     where
         T: ?Sized + Serialize,
     {
-        Err(Error::unsupported_type("newtype variants"))
+        let inner = field_to_value(&self.state, variant, value)?.unwrap_or(Value::Null);
+        tag_variant(&self.state, variant, Some(inner))
     }
 
-    fn serialize_seq(self, _len: Option<usize>) -> Result<SerializeVec> {
-        Ok(SerializeVec::new())
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeVec> {
+        Ok(SerializeVec::new(self.state, len.unwrap_or(0), None))
     }
 
-    fn serialize_tuple(self, _len: usize) -> Result<SerializeVec> {
-        Ok(SerializeVec::new())
+    fn serialize_tuple(self, len: usize) -> Result<SerializeVec> {
+        Ok(SerializeVec::new(self.state, len, None))
     }
 
-    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<SerializeVec> {
-        Ok(SerializeVec::new())
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<SerializeVec> {
+        let mut vec = SerializeVec::new(self.state, len, None);
+        vec.codec = vec.state.options.type_codec(name).cloned();
+        Ok(vec)
     }
 
     fn serialize_tuple_variant(
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<SerializeVec> {
-        Err(Error::unsupported_type("tuple variants"))
+        Ok(SerializeVec::new(self.state, len, Some(variant)))
     }
 
-    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap> {
-        Ok(SerializeMap::new())
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeMap> {
+        Ok(SerializeMap::new(self.state, len.unwrap_or(0), None))
     }
 
-    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<SerializeMap> {
-        Ok(SerializeMap::new())
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<SerializeMap> {
+        let mut map = SerializeMap::new(self.state, len, None);
+        map.codec = map.state.options.type_codec(name).cloned();
+        Ok(map)
     }
 
     fn serialize_struct_variant(
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<SerializeMap> {
-        Err(Error::unsupported_type("struct variants"))
+        Ok(SerializeMap::new(self.state, len, Some(variant)))
     }
 }
 
 impl SerializeVec {
-    fn new() -> Self {
-        SerializeVec { vec: Vec::new() }
+    fn new(state: Rc<State>, len: usize, variant: Option<&'static str>) -> Self {
+        if let Some(variant) = variant {
+            state
+                .path
+                .borrow_mut()
+                .push(PathSegment::Key(variant.to_string()));
+        }
+        SerializeVec {
+            state,
+            vec: Vec::with_capacity(len),
+            variant,
+            codec: None,
+        }
+    }
+
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let value = element_to_value(&self.state, self.vec.len(), value)?;
+        self.vec.push(value);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value> {
+        let array = Value::Array(self.vec);
+        match (self.variant, self.codec) {
+            (Some(variant), _) => {
+                self.state.path.borrow_mut().pop();
+                tag_variant(&self.state, variant, Some(array))
+            }
+            (None, Some(codec)) => codec.encode(&array),
+            (None, None) => Ok(array),
+        }
     }
 }
 
 impl SerializeMap {
-    fn new() -> Self {
+    fn new(state: Rc<State>, len: usize, variant: Option<&'static str>) -> Self {
+        if let Some(variant) = variant {
+            state
+                .path
+                .borrow_mut()
+                .push(PathSegment::Key(variant.to_string()));
+        }
         SerializeMap {
-            map: ToonMap::new(),
+            state,
+            map: ToonMap::with_capacity(len),
             current_key: None,
+            skip_value: false,
+            variant,
+            codec: None,
+        }
+    }
+
+    fn insert<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> Result<()> {
+        if let Some(value) = field_to_value(&self.state, key, value)? {
+            self.map.insert(key.to_string(), value);
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value> {
+        let object = Value::Object(self.map);
+        match (self.variant, self.codec) {
+            (Some(variant), _) => {
+                self.state.path.borrow_mut().pop();
+                tag_variant(&self.state, variant, Some(object))
+            }
+            (None, Some(codec)) => codec.encode(&object),
+            (None, None) => Ok(object),
         }
     }
 }
@@ -793,12 +1167,11 @@ impl ser::SerializeSeq for SerializeVec {
     where
         T: ?Sized + Serialize,
     {
-        self.vec.push(to_toon_value(value)?);
-        Ok(())
+        self.push(value)
     }
 
     fn end(self) -> Result<Value> {
-        Ok(Value::Array(self.vec))
+        self.finish()
     }
 }
 
@@ -810,12 +1183,11 @@ impl ser::SerializeTuple for SerializeVec {
     where
         T: ?Sized + Serialize,
     {
-        self.vec.push(to_toon_value(value)?);
-        Ok(())
+        self.push(value)
     }
 
     fn end(self) -> Result<Value> {
-        Ok(Value::Array(self.vec))
+        self.finish()
     }
 }
 
@@ -827,12 +1199,11 @@ impl ser::SerializeTupleStruct for SerializeVec {
     where
         T: ?Sized + Serialize,
     {
-        self.vec.push(to_toon_value(value)?);
-        Ok(())
+        self.push(value)
     }
 
     fn end(self) -> Result<Value> {
-        Ok(Value::Array(self.vec))
+        self.finish()
     }
 }
 
@@ -844,12 +1215,11 @@ impl ser::SerializeTupleVariant for SerializeVec {
     where
         T: ?Sized + Serialize,
     {
-        self.vec.push(to_toon_value(value)?);
-        Ok(())
+        self.push(value)
     }
 
     fn end(self) -> Result<Value> {
-        Ok(Value::Array(self.vec))
+        self.finish()
     }
 }
 
@@ -861,29 +1231,27 @@ impl ser::SerializeMap for SerializeMap {
     where
         T: ?Sized + Serialize,
     {
-        match to_toon_value(key)? {
-            Value::String(s) => {
-                self.current_key = Some(s);
-                Ok(())
-            }
-            _ => Err(Error::custom("Map keys must be strings")),
-        }
+        self.current_key = map_key(&self.state, key)?;
+        self.skip_value = self.current_key.is_none();
+        Ok(())
     }
 
     fn serialize_value<T>(&mut self, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
+        if std::mem::take(&mut self.skip_value) {
+            return Ok(());
+        }
         let key = self
             .current_key
             .take()
             .ok_or_else(|| Error::custom("serialize_value called without serialize_key"))?;
-        self.map.insert(key, to_toon_value(value)?);
-        Ok(())
+        self.insert(&key, value)
     }
 
     fn end(self) -> Result<Value> {
-        Ok(Value::Object(self.map))
+        self.finish()
     }
 }
 
@@ -895,12 +1263,11 @@ impl ser::SerializeStruct for SerializeMap {
     where
         T: ?Sized + Serialize,
     {
-        self.map.insert(key.to_string(), to_toon_value(value)?);
-        Ok(())
+        self.insert(key, value)
     }
 
     fn end(self) -> Result<Value> {
-        Ok(Value::Object(self.map))
+        self.finish()
     }
 }
 
@@ -912,27 +1279,200 @@ impl ser::SerializeStructVariant for SerializeMap {
     where
         T: ?Sized + Serialize,
     {
-        self.map.insert(key.to_string(), to_toon_value(value)?);
-        Ok(())
+        self.insert(key, value)
     }
 
     fn end(self) -> Result<Value> {
-        Ok(Value::Object(self.map))
+        self.finish()
+    }
+}
+
+/// Wraps an enum variant according to the configured [`EnumRepr`]. `content` is
+/// `None` for unit variants.
+fn tag_variant(state: &State, variant: &str, content: Option<Value>) -> Result<Value> {
+    let name = Value::String(variant.to_string());
+    match &state.options.enum_repr {
+        EnumRepr::External => Ok(match content {
+            None => name,
+            Some(content) => {
+                let mut map = ToonMap::with_capacity(1);
+                map.insert(variant.to_string(), content);
+                Value::Object(map)
+            }
+        }),
+        EnumRepr::Internal { tag } => {
+            let mut map = ToonMap::new();
+            map.insert(tag.clone(), name);
+            match content {
+                None => {}
+                Some(Value::Object(fields)) => {
+                    if fields.contains_key(tag) {
+                        return Err(Error::unsupported_type(
+                            &format!(
+                                "variant {} with a field named like the tag '{}'",
+                                variant, tag
+                            ),
+                            &state.current_path(),
+                        ));
+                    }
+                    map.extend(fields);
+                }
+                Some(other) => {
+                    return Err(Error::unsupported_type(
+                        &format!(
+                            "internally tagged variant {} with {} content",
+                            variant,
+                            value_kind(&other)
+                        ),
+                        &state.current_path(),
+                    ));
+                }
+            }
+            Ok(Value::Object(map))
+        }
+        EnumRepr::Adjacent {
+            tag,
+            content: content_key,
+        } => {
+            let mut map = ToonMap::with_capacity(2);
+            map.insert(tag.clone(), name);
+            if let Some(content) = content {
+                map.insert(content_key.clone(), content);
+            }
+            Ok(Value::Object(map))
+        }
+        EnumRepr::Untagged => Ok(content.unwrap_or(Value::Null)),
+    }
+}
+
+/// Serializes an object field value with `key` appended to the current path.
+///
+/// Returns `Ok(None)` when [`ToonOptions::field_filter`] omits the field. A
+/// masked field is replaced without serializing its value.
+fn field_to_value<T: Serialize + ?Sized>(
+    state: &Rc<State>,
+    key: &str,
+    value: &T,
+) -> Result<Option<Value>> {
+    state.with_segment(PathSegment::Key(key.to_string()), || {
+        match state.options.field_action(&state.path.borrow()) {
+            FieldAction::Omit => return Ok(None),
+            FieldAction::Mask(mask) => return Ok(Some(Value::String(mask))),
+            FieldAction::Keep => {}
+        }
+        value
+            .serialize(ValueSerializer {
+                state: Rc::clone(state),
+            })
+            .map(Some)
+    })
+}
+
+/// Serializes an array element with `index` appended to the current path.
+fn element_to_value<T: Serialize + ?Sized>(
+    state: &Rc<State>,
+    index: usize,
+    value: &T,
+) -> Result<Value> {
+    state.with_segment(PathSegment::Index(index), || {
+        value.serialize(ValueSerializer {
+            state: Rc::clone(state),
+        })
+    })
+}
+
+/// Converts a map key to its string form.
+///
+/// Returns `Ok(None)` when the entry should be dropped because of
+/// [`UnsupportedPolicy::Null`].
+fn map_key<T: Serialize + ?Sized>(state: &Rc<State>, key: &T) -> Result<Option<String>> {
+    let serializer = ValueSerializer {
+        state: Rc::clone(state),
+    };
+    match key.serialize(serializer.clone())? {
+        Value::String(s) => Ok(Some(s)),
+        Value::Number(n) if state.options.non_string_keys == NonStringKeys::Stringify => {
+            Ok(Some(n.to_string()))
+        }
+        Value::Bool(b) if state.options.non_string_keys == NonStringKeys::Stringify => {
+            Ok(Some(b.to_string()))
+        }
+        #[cfg(feature = "bigint")]
+        Value::BigInt(n) if state.options.non_string_keys == NonStringKeys::Stringify => {
+            Ok(Some(n.to_string()))
+        }
+        other @ (Value::Number(_) | Value::Bool(_)) => Err(Error::unsupported_type(
+            &format!("map key of type {}", value_kind(&other)),
+            &state.current_path(),
+        )),
+        #[cfg(feature = "bigint")]
+        other @ Value::BigInt(_) => Err(Error::unsupported_type(
+            &format!("map key of type {}", value_kind(&other)),
+            &state.current_path(),
+        )),
+        other => {
+            let type_name = format!("map key of type {}", value_kind(&other));
+            match state.options.unsupported_policy {
+                UnsupportedPolicy::Null => {
+                    state.warn(&format!("entry with unsupported {} dropped", type_name));
+                    Ok(None)
+                }
+                _ => match serializer.unsupported(&type_name, other)? {
+                    Value::String(s) => Ok(Some(s)),
+                    _ => Ok(None),
+                },
+            }
+        }
     }
 }
 
-fn to_toon_value<T: Serialize + ?Sized>(value: &T) -> Result<Value> {
-    value.serialize(ValueSerializer)
+/// Renders `value` as single-line text, used by [`UnsupportedPolicy::Stringify`].
+fn inline_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(arr) => {
+            let items: Vec<String> = arr.iter().map(inline_text).collect();
+            format!("[{}]", items.join(","))
+        }
+        Value::Object(obj) => {
+            let entries: Vec<String> = obj
+                .iter()
+                .map(|(k, v)| format!("{}:{}", k, inline_text(v)))
+                .collect();
+            format!("{{{}}}", entries.join(","))
+        }
+        #[cfg(feature = "datetime")]
+        Value::Date(dt) => dt.to_rfc3339(),
+        other => other.to_string(),
+    }
 }
 
-fn can_be_tabular(elements: &[Value]) -> Option<(Vec<String>, Vec<Vec<Value>>)> {
+/// Returns a short human-readable name for the kind of `value`.
+fn value_kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "sequence",
+        Value::Object(_) => "map",
+        Value::Table(_) => "table",
+        #[cfg(feature = "datetime")]
+        Value::Date(_) => "date",
+        #[cfg(feature = "bigint")]
+        Value::BigInt(_) => "bigint",
+    }
+}
+
+pub(crate) fn can_be_tabular(elements: &[Value]) -> Option<(Vec<Key>, Vec<Vec<Value>>)> {
     if elements.is_empty() {
         return None;
     }
 
-    // All elements must be objects with identical primitive fields
+    // All elements must be objects with identical primitive fields, and at
+    // least one so the rows are not blank lines
     let first_headers = match &elements[0] {
-        Value::Object(obj) => {
+        Value::Object(obj) if !obj.is_empty() => {
             // Check that all values are primitives (not objects or arrays)
             for value in obj.values() {
                 if !is_primitive_value(value) {
@@ -940,23 +1480,20 @@ fn can_be_tabular(elements: &[Value]) -> Option<(Vec<String>, Vec<Vec<Value>>)>
                 }
             }
 
-            let mut headers: Vec<_> = obj.keys().cloned().collect();
-            headers.sort(); // TOON spec: fields are sorted alphabetically
-            headers
+            obj.keys().cloned().collect::<Vec<_>>()
         }
         _ => return None,
     };
 
-    let mut rows = Vec::new();
+    let mut rows = Vec::with_capacity(elements.len());
 
     for element in elements {
         match element {
             Value::Object(obj) => {
-                // Check that this object has the same structure
-                let mut element_headers: Vec<_> = obj.keys().cloned().collect();
-                element_headers.sort();
-
-                if element_headers != first_headers {
+                // Check that this object has the same fields (in any order)
+                if obj.len() != first_headers.len()
+                    || !first_headers.iter().all(|key| obj.contains_key(key))
+                {
                     return None;
                 }
 
@@ -981,85 +1518,296 @@ fn can_be_tabular(elements: &[Value]) -> Option<(Vec<String>, Vec<Vec<Value>>)>
 }
 
 #[inline]
-fn is_primitive_value(value: &Value) -> bool {
-    match value {
-        Value::Null
-        | Value::Bool(_)
-        | Value::Number(_)
-        | Value::String(_)
-        | Value::Date(_)
-        | Value::BigInt(_) => true,
-        Value::Array(_) | Value::Object(_) | Value::Table { .. } => false,
+/// Table headers and rows for `elements`, allowing objects with differing keys
+/// when [`ToonOptions::sparse_tables`] is set. Objects with a field that would
+/// be a block string are not a table, so that it can be one.
+fn tabular_rows(elements: &[Value], options: &ToonOptions) -> Option<(Vec<Key>, Vec<Vec<Value>>)> {
+    let (headers, rows) = can_be_tabular(elements).or_else(|| {
+        options
+            .sparse_tables
+            .and_then(|min_presence| can_be_sparse_tabular(elements, min_presence))
+    })?;
+    let has_block = |cell: &Value| matches!(cell, Value::String(s) if is_block_string(s, options));
+    if options.block_strings.is_some() && rows.iter().flatten().any(has_block) {
+        return None;
     }
+    Some((headers, rows))
+}
+
+/// Like [`can_be_tabular`], but with the union of the objects' keys as headers
+/// and `null` for missing fields. Fails if a key appears in fewer than
+/// `min_presence` of the objects.
+fn can_be_sparse_tabular(
+    elements: &[Value],
+    min_presence: f64,
+) -> Option<(Vec<Key>, Vec<Vec<Value>>)> {
+    if elements.is_empty() {
+        return None;
+    }
+
+    let mut objects = Vec::with_capacity(elements.len());
+    let mut headers: Vec<Key> = Vec::new();
+    let mut counts: Vec<usize> = Vec::new();
+    for element in elements {
+        let Value::Object(obj) = element else {
+            return None;
+        };
+        for (key, value) in obj.iter() {
+            if !is_primitive_value(value) {
+                return None;
+            }
+            match headers.iter().position(|header| header == key) {
+                Some(i) => counts[i] += 1,
+                None => {
+                    headers.push(key.clone());
+                    counts.push(1);
+                }
+            }
+        }
+        objects.push(obj);
+    }
+
+    let required = min_presence * elements.len() as f64;
+    if headers.is_empty() || counts.iter().any(|&count| (count as f64) < required) {
+        return None;
+    }
+
+    let rows = objects
+        .iter()
+        .map(|obj| {
+            headers
+                .iter()
+                .map(|key| obj.get(key).cloned().unwrap_or(Value::Null))
+                .collect()
+        })
+        .collect();
+    Some((headers, rows))
+}
+
+fn is_primitive_value(value: &Value) -> bool {
+    !matches!(value, Value::Array(_) | Value::Object(_) | Value::Table(_))
 }
 
 fn write_tabular_array(
     output: &mut String,
-    headers: &[String],
+    headers: &[Key],
     rows: &[Vec<Value>],
     options: &ToonOptions,
     indent_level: usize,
 ) {
-    // Format header: [N]{field1,field2}: or [N|]{field1|field2}: or [N    ]{field1    field2}:
+    trace::event!(
+        TRACE,
+        format = "table",
+        len = rows.len(),
+        columns = headers.len()
+    );
+    let options = &*array_options(options, rows.iter().flatten());
+
     // Cache delimiter string to avoid repeated method calls in loop
     let delimiter_str = options.delimiter.as_str();
-    let len_marker = if let Some(marker) = options.length_marker {
-        format!("{}{}", marker, rows.len())
-    } else {
-        rows.len().to_string()
-    };
 
-    // Encode delimiter in header according to TOON spec
-    // Use &str to avoid unnecessary String allocations
-    let header_suffix = match options.delimiter {
-        crate::Delimiter::Comma => "",   // implicit for comma
-        crate::Delimiter::Tab => "    ", // show tabs as spaces in header
-        crate::Delimiter::Pipe => "|",
+    // Column order: as given, or sorted by header for canonical output
+    let mut columns: Vec<usize> = (0..headers.len()).collect();
+    if options.sorts_keys() {
+        columns.sort_by_key(|&i| options.written_key(&headers[i]));
+    }
+    let mut headers: Vec<Cow<str>> = columns
+        .iter()
+        .map(|&i| key_text(options.written_key(&headers[i]), options))
+        .collect();
+    if options.row_numbers {
+        headers.insert(0, Cow::Borrowed("#"));
+    }
+
+    write_table_header(output, rows.len(), &headers, options);
+
+    let render = |(index, row): (usize, &Vec<Value>)| -> Vec<String> {
+        let number = options.row_numbers.then(|| (index + 1).to_string());
+        number
+            .into_iter()
+            .chain(columns.iter().map(|&column| {
+                let mut cell = String::new();
+                write_toon_value_quoted(
+                    &mut cell,
+                    row.get(column).unwrap_or(&Value::Null),
+                    options,
+                );
+                cell
+            }))
+            .collect()
     };
-
-    let headers_str = match options.delimiter {
-        crate::Delimiter::Comma => headers.join(","),
-        crate::Delimiter::Tab => headers.join("    "), // tabs shown as spaces in header
-        crate::Delimiter::Pipe => headers.join("|"),
+    #[cfg(feature = "rayon")]
+    let cells: Vec<Vec<String>> = if rows.len() > PARALLEL_CHUNK_ROWS {
+        use rayon::prelude::*;
+        rows.par_iter().enumerate().map(render).collect()
+    } else {
+        rows.iter().enumerate().map(render).collect()
     };
+    #[cfg(not(feature = "rayon"))]
+    let cells: Vec<Vec<String>> = rows.iter().enumerate().map(render).collect();
+
+    // Column widths for alignment; the last column is never padded
+    let mut widths = vec![0; headers.len()];
+    if options.align_table_columns {
+        for row in &cells {
+            for (width, cell) in widths.iter_mut().zip(row).take(headers.len() - 1) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+    }
+
+    // Room for every row at once: its line break, indentation, delimiters
+    // and cells, plus any padding
+    let row_indent = " ".repeat((indent_level + 1) * options.indent);
+    let cell_bytes: usize = cells.iter().flatten().map(String::len).sum();
+    let padding: usize = widths.iter().sum::<usize>() * cells.len();
+    output.reserve(
+        cells.len()
+            * (1 + row_indent.len() + delimiter_str.len() * headers.len().saturating_sub(1))
+            + cell_bytes
+            + padding,
+    );
+
+    // Large tables are written in chunks of rows, in parallel, and the chunks
+    // joined in order, so the output is the same as writing them one by one
+    #[cfg(feature = "rayon")]
+    if cells.len() > PARALLEL_CHUNK_ROWS {
+        use rayon::prelude::*;
+        let chunks: Vec<String> = cells
+            .par_chunks(PARALLEL_CHUNK_ROWS)
+            .map(|chunk| {
+                let mut text = String::new();
+                write_table_rows(&mut text, chunk, &row_indent, delimiter_str, &widths);
+                text
+            })
+            .collect();
+        for chunk in &chunks {
+            output.push_str(chunk);
+        }
+        return;
+    }
+    write_table_rows(output, &cells, &row_indent, delimiter_str, &widths);
+}
 
-    output.push_str(&format!(
-        "[{}{}]{{{}}}:",
-        len_marker, header_suffix, headers_str
-    ));
+/// Rows per parallel task when writing tables with the `rayon` feature.
+/// Tables of at most this many rows are written on the calling thread.
+#[cfg(feature = "rayon")]
+const PARALLEL_CHUNK_ROWS: usize = 1024;
 
-    // Write rows
-    for row in rows {
+/// Writes the rendered `cells` of table rows, each on its own line, padding
+/// after the delimiter so the next column lines up to `widths`.
+fn write_table_rows(
+    output: &mut String,
+    cells: &[Vec<String>],
+    row_indent: &str,
+    delimiter_str: &str,
+    widths: &[usize],
+) {
+    for row in cells {
         output.push('\n');
-        output.push_str(&" ".repeat((indent_level + 1) * options.indent));
+        output.push_str(row_indent);
 
-        for (i, value) in row.iter().enumerate() {
+        for (i, cell) in row.iter().enumerate() {
             if i > 0 {
                 output.push_str(delimiter_str);
+                let padding = widths[i - 1].saturating_sub(row[i - 1].chars().count());
+                output.push_str(&" ".repeat(padding));
             }
-            write_toon_value_quoted(output, value, options);
+            output.push_str(cell);
         }
     }
 }
 
-fn write_inline_array(output: &mut String, elements: &[Value], options: &ToonOptions) {
-    // Cache delimiter string for loop performance
-    let delimiter_str = options.delimiter.as_str();
-    let len_marker = if let Some(marker) = options.length_marker {
-        format!("{}{}", marker, elements.len())
-    } else {
-        elements.len().to_string()
+/// Writes the header of a table of `len` rows: `[N]{field1,field2}:`,
+/// `[N|]{field1|field2}:` or `[N    ]{field1    field2}:`.
+pub(crate) fn write_table_header<S: AsRef<str>>(
+    output: &mut String,
+    len: usize,
+    headers: &[S],
+    options: &ToonOptions,
+) {
+    // Tabs are shown as spaces in the header
+    let separator = match options.delimiter {
+        crate::Delimiter::Comma => ",",
+        crate::Delimiter::Tab => tab_marker(options),
+        crate::Delimiter::Pipe => "|",
     };
+    write_length(output, len, options, true);
+    output.push('{');
+    for (i, header) in headers.iter().enumerate() {
+        if i > 0 {
+            output.push_str(separator);
+        }
+        output.push_str(header.as_ref());
+    }
+    output.push_str("}:");
+}
 
-    // Encode delimiter in header
-    // Use &str to avoid unnecessary String allocations
-    let header_suffix = match options.delimiter {
-        crate::Delimiter::Comma => "",
-        crate::Delimiter::Tab => "    ",
-        crate::Delimiter::Pipe => "|",
+/// Writes the `[N]` of an array header, with the delimiter encoded in it
+/// according to the TOON spec if the array is `delimited`.
+pub(crate) fn write_length(
+    output: &mut String,
+    len: usize,
+    options: &ToonOptions,
+    delimited: bool,
+) {
+    output.push('[');
+    if !options.omit_lengths {
+        if let Some(marker) = options.length_marker {
+            output.push(marker);
+        }
+        push_display(output, len);
+    }
+    if delimited {
+        output.push_str(match options.delimiter {
+            crate::Delimiter::Comma => "", // implicit for comma
+            crate::Delimiter::Tab => tab_marker(options),
+            crate::Delimiter::Pipe => "|",
+        });
+    }
+    output.push(']');
+}
+
+/// The tab delimiter as written in headers: as spaces, or as a tab like the
+/// reference encoder.
+fn tab_marker(options: &ToonOptions) -> &'static str {
+    if options.matches_reference() {
+        "\t"
+    } else {
+        "    "
+    }
+}
+
+pub(crate) fn write_inline_array(output: &mut String, elements: &[Value], options: &ToonOptions) {
+    trace::event!(TRACE, format = "inline", len = elements.len());
+    let vector_options;
+    let options = match vector_encoding(elements, options) {
+        VectorEncoding::Plain => options,
+        VectorEncoding::Decimals(decimals) => {
+            let format = FloatFormat {
+                max_decimals: Some(decimals),
+                ..options.float_format
+            };
+            vector_options = options.clone().with_float_format(format);
+            &vector_options
+        }
+        VectorEncoding::PackedF32 => {
+            let floats = elements.iter().filter_map(|element| match element {
+                Value::Number(Number::Integer(i)) => Some(*i as f32),
+                Value::Number(Number::Float(f)) => Some(*f as f32),
+                _ => None,
+            });
+            let packed = Value::String(bytes::encode_f32s(floats));
+            write_toon_value_quoted(output, &packed, options);
+            return;
+        }
     };
+    let options = &*array_options(options, elements.iter());
 
-    output.push_str(&format!("[{}{}]: ", len_marker, header_suffix));
+    // Cache delimiter string for loop performance
+    let delimiter_str = options.delimiter.as_str();
+    write_length(output, elements.len(), options, true);
+    output.push_str(": ");
 
     for (i, element) in elements.iter().enumerate() {
         if i > 0 {
@@ -1069,56 +1817,144 @@ fn write_inline_array(output: &mut String, elements: &[Value], options: &ToonOpt
     }
 }
 
+/// Returns how to write `elements`: as [`ToonOptions::vector_format`] says
+/// if they make a vector, or plainly if not.
+fn vector_encoding(elements: &[Value], options: &ToonOptions) -> VectorEncoding {
+    let format = &options.vector_format;
+    if format.encoding == VectorEncoding::Plain
+        || elements.len() < format.min_len
+        || options.matches_reference()
+    {
+        return VectorEncoding::Plain;
+    }
+    let mut floats = false;
+    for element in elements {
+        match element {
+            Value::Number(Number::Integer(_)) => {}
+            Value::Number(Number::Float(_)) => floats = true,
+            _ => return VectorEncoding::Plain,
+        }
+    }
+    if floats {
+        format.encoding
+    } else {
+        VectorEncoding::Plain
+    }
+}
+
+/// Returns `options` to write an array of `values` with. Under
+/// [`DelimiterStrategy::Auto`] the delimiter is the one that quotes the fewest
+/// values, trying the configured delimiter first.
+fn array_options<'a>(
+    options: &'a ToonOptions,
+    values: impl Iterator<Item = &'a Value> + Clone,
+) -> Cow<'a, ToonOptions> {
+    if options.delimiter_strategy == DelimiterStrategy::Fixed || options.matches_reference() {
+        return Cow::Borrowed(options);
+    }
+
+    let quoted = |delimiter: &Delimiter| {
+        let stops = quote_stops(DelimiterStrategy::Auto, delimiter);
+        values
+            .clone()
+            .filter(|value| matches!(value, Value::String(s) if needs_quotes_with(s, stops)))
+            .count()
+    };
+    let candidates = [Delimiter::Comma, Delimiter::Pipe, Delimiter::Tab];
+    let best = std::iter::once(&options.delimiter)
+        .chain(candidates.iter().filter(|&d| *d != options.delimiter))
+        .min_by_key(|&delimiter| quoted(delimiter))
+        .unwrap_or(&options.delimiter);
+    if *best == options.delimiter {
+        return Cow::Borrowed(options);
+    }
+    Cow::Owned(options.clone().with_delimiter(best.clone()))
+}
+
 fn write_list_array(
     output: &mut String,
     elements: &[Value],
     options: &ToonOptions,
     indent_level: usize,
 ) {
-    let len_marker = if let Some(marker) = options.length_marker {
-        format!("{}{}", marker, elements.len())
+    trace::event!(TRACE, format = "list", len = elements.len());
+    write_length(output, elements.len(), options, false);
+    output.push(':');
+
+    // Items are written as if at the root, then every continuation line is
+    // shifted to line up with the content after "- "
+    let item_indent = " ".repeat((indent_level + 1) * options.indent);
+    let continuation = format!("\n{}  ", item_indent);
+
+    // Blank lines between sections belong to the document, not to list items
+    let item_options;
+    let options = if options.pretty {
+        item_options = ToonOptions {
+            pretty: false,
+            ..options.clone()
+        };
+        &item_options
     } else {
-        elements.len().to_string()
+        options
     };
 
-    output.push_str(&format!("[{}]:", len_marker));
-
     for element in elements {
-        output.push('\n');
-        output.push_str(&" ".repeat((indent_level + 1) * options.indent));
-        output.push_str("- ");
-
+        let mut item = String::new();
         match element {
             Value::Object(obj) => {
-                // For objects in list format, sort keys alphabetically for deterministic output
-                let mut sorted_entries: Vec<_> = obj.iter().collect();
-                sorted_entries.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
-
-                let mut iter = sorted_entries.into_iter();
-
-                if let Some((first_key, first_value)) = iter.next() {
-                    output.push_str(first_key);
-                    output.push_str(": ");
-                    write_toon_value_quoted(output, first_value, options);
-
-                    // Remaining fields at same indentation level as the "- "
-                    for (key, value) in iter {
-                        output.push('\n');
-                        output.push_str(&" ".repeat((indent_level + 1) * options.indent));
-                        output.push_str("  "); // align with content after "- "
-                        output.push_str(key);
-                        output.push_str(": ");
-                        write_toon_value_quoted(output, value, options);
-                    }
-                }
+                let entries: Vec<_> = obj.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+                write_object(&mut item, &entries, options, 0);
             }
-            _ => {
-                write_toon_value_quoted(output, element, options);
+            Value::Array(arr) => write_array_toon(&mut item, arr, options, 0),
+            _ => write_toon_value_quoted(&mut item, element, options),
+        }
+
+        output.push('\n');
+        output.push_str(&item_indent);
+        // The reference encoder writes an empty object as a bare dash
+        output.push_str(if item.is_empty() && options.matches_reference() {
+            "-"
+        } else {
+            "- "
+        });
+        // Blank lines, such as those of block strings, stay empty
+        for (i, line) in item.split('\n').enumerate() {
+            if i > 0 {
+                output.push_str(if line.is_empty() { "\n" } else { &continuation });
             }
+            output.push_str(line);
         }
     }
 }
 
+/// Applies [`ToonOptions::line_ending`] and [`ToonOptions::trailing_newline`]
+/// to a finished document, which is written with `\n` line breaks.
+pub(crate) fn finish_lines(output: &mut String, options: &ToonOptions) {
+    if options.line_ending == LineEnding::CrLf {
+        *output = output.replace('\n', "\r\n");
+    }
+    if options.trailing_newline && !output.is_empty() {
+        output.push_str(options.line_ending.as_str());
+    }
+}
+
+/// Writes `value` as a document (or the value of a field) at `indent_level`.
+pub(crate) fn write_value(
+    output: &mut String,
+    value: &Value,
+    options: &ToonOptions,
+    indent_level: usize,
+) {
+    match value {
+        Value::Object(obj) => {
+            let entries: Vec<_> = obj.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+            write_object(output, &entries, options, indent_level);
+        }
+        Value::Array(arr) => write_array_toon(output, arr, options, indent_level),
+        _ => write_toon_value_quoted(output, value, options),
+    }
+}
+
 fn write_array_toon(
     output: &mut String,
     arr: &[Value],
@@ -1126,12 +1962,13 @@ fn write_array_toon(
     indent_level: usize,
 ) {
     if arr.is_empty() {
-        output.push_str("[0]:");
+        write_length(output, 0, options, false);
+        output.push(':');
         return;
     }
 
     // Check if array can be tabular
-    if let Some((headers, rows)) = can_be_tabular(arr) {
+    if let Some((headers, rows)) = tabular_rows(arr, options) {
         write_tabular_array(output, &headers, &rows, options, indent_level);
     } else if arr.iter().all(is_primitive_value) {
         // Inline format for all primitives
@@ -1142,42 +1979,72 @@ fn write_array_toon(
     }
 }
 
-fn write_object(
+pub(crate) fn write_object<K: AsRef<str>>(
     output: &mut String,
-    entries: &[(String, Value)],
+    entries: &[(K, Value)],
     options: &ToonOptions,
     indent_level: usize,
 ) {
-    for (i, (key, value)) in entries.iter().enumerate() {
+    let mut entries: Vec<&(K, Value)> = entries
+        .iter()
+        .filter(|(_, value)| !(options.skip_none_fields && value.is_null()))
+        .collect();
+    if options.sorts_keys() {
+        entries.sort_by(|(a, _), (b, _)| {
+            options
+                .written_key(a.as_ref())
+                .cmp(options.written_key(b.as_ref()))
+        });
+    }
+
+    let mut previous_block = false;
+    for (i, (key, value)) in entries.into_iter().enumerate() {
+        let block = is_block(value);
         if i > 0 {
             output.push('\n');
+            // Pretty output sets top-level sections apart with a blank line
+            if options.pretty && indent_level == 0 && (block || previous_block) {
+                output.push('\n');
+            }
         }
+        previous_block = block;
 
-        // Add indentation for nested objects or pretty mode
         if indent_level > 0 {
-            // Nested objects always get indented
-            output.push_str(&" ".repeat(indent_level * options.indent));
-        } else if i > 0 && options.pretty {
-            // Top-level objects only get indented in pretty mode and after first field
             output.push_str(&" ".repeat(indent_level * options.indent));
         }
 
-        output.push_str(key);
-        output.push(':');
+        output.push_str(&key_text(options.written_key(key.as_ref()), options));
+        // The reference encoder puts an array's header straight after its key
+        let header_follows =
+            options.matches_reference() && matches!(value, Value::Array(_) | Value::Table(_));
+        if !header_follows {
+            output.push(':');
+        }
 
         match value {
             Value::Array(arr) => {
                 // Arrays get special TOON formatting
-                output.push(' ');
+                if !header_follows {
+                    output.push(' ');
+                }
                 write_array_toon(output, arr, options, indent_level);
             }
+            Value::Table(Table { headers, rows }) if header_follows => {
+                write_tabular_array(output, headers, rows, options, indent_level);
+            }
+            // An empty object is just its key
+            Value::Object(obj) if obj.is_empty() => {}
             Value::Object(obj) => {
                 // For nested objects, handle indentation properly
                 output.push('\n');
                 let entries: Vec<_> = obj.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
                 write_object(output, &entries, options, indent_level + 1);
             }
-            Value::Table { .. } => {
+            Value::String(s) if is_block_string(s, options) => {
+                output.push_str(" |");
+                write_block_lines(output, s, options, indent_level + 1);
+            }
+            Value::Table(_) => {
                 // For tables, no space after colon
                 output.push('\n');
                 output.push_str(&" ".repeat((indent_level + 1) * options.indent));
@@ -1192,28 +2059,180 @@ fn write_object(
     }
 }
 
-fn write_toon_value_quoted(output: &mut String, value: &Value, options: &ToonOptions) {
+/// Whether the field value `s` is written as a block string, as
+/// [`ToonOptions::block_strings`] asks for when the block reads back as `s`.
+fn is_block_string(s: &str, options: &ToonOptions) -> bool {
+    let Some(min_chars) = options.block_strings else {
+        return false;
+    };
+    if options.matches_reference() {
+        return false;
+    }
+    if !s.contains('\n') && s.chars().count() <= min_chars {
+        return false;
+    }
+    // The first line's indentation is taken for the block's, trailing
+    // whitespace is dropped, and escapes are not available
+    let first = s.split('\n').find(|line| !line.is_empty());
+    first.is_some_and(|line| !line.starts_with(char::is_whitespace))
+        && !s.ends_with('\n')
+        && s.split('\n')
+            .all(|line| !line.ends_with(char::is_whitespace))
+        && !s.contains(|ch: char| ch != '\n' && ch != '\t' && is_invisible(ch))
+        && (!options.escape_non_ascii || s.is_ascii())
+}
+
+/// Writes the lines of a block string at `indent_level`, each on a line of
+/// its own; blank lines are left empty.
+fn write_block_lines(output: &mut String, s: &str, options: &ToonOptions, indent_level: usize) {
+    let indent = " ".repeat(indent_level * options.indent);
+    for line in s.split('\n') {
+        output.push('\n');
+        if !line.is_empty() {
+            output.push_str(&indent);
+            output.push_str(line);
+        }
+    }
+}
+
+/// Whether `value` is written on lines of its own below its key.
+fn is_block(value: &Value) -> bool {
+    match value {
+        Value::Object(obj) => !obj.is_empty(),
+        Value::Table(_) => true,
+        Value::Array(arr) => !arr.is_empty() && !arr.iter().all(is_primitive_value),
+        _ => false,
+    }
+}
+
+/// Writes `value` as [`Display`](fmt::Display) shows it, straight into
+/// `output`.
+fn push_display(output: &mut String, value: impl fmt::Display) {
+    // Writing into a `String` never fails
+    let _ = write!(output, "{}", value);
+}
+
+/// Converts `v` to the `f64` with the same shortest decimal form, so `0.1f32`
+/// is written as `0.1` rather than `0.10000000149011612`. Reading it back as
+/// an `f32` gives `v` again.
+pub(crate) fn widen_f32(v: f32) -> f64 {
+    // The shortest digits, formatted on the stack rather than into a `String`
+    ryu::Buffer::new().format(v).parse().unwrap_or(f64::from(v))
+}
+
+/// Writes a float in canonical form, rounded as `format` says: `-0` as `0`,
+/// and NaN and infinities, which have no TOON form, as `null`.
+pub(crate) fn write_f64(output: &mut String, v: f64, format: &FloatFormat) {
+    if !v.is_finite() {
+        output.push_str("null");
+        return;
+    }
+    match format.max_decimals {
+        None if v == 0.0 => output.push('0'),
+        None => push_display(output, v),
+        Some(decimals) => {
+            let text = if v == 0.0 {
+                "0".to_string()
+            } else {
+                v.to_string()
+            };
+            write_rounded(output, &text, decimals, format);
+        }
+    }
+}
+
+/// Writes `text`, the decimal form of a finite float, rounded to `decimals`
+/// places as `format` says.
+fn write_rounded(output: &mut String, text: &str, decimals: usize, format: &FloatFormat) {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(text) => (true, text),
+        None => (false, text),
+    };
+    let (int, frac) = text.split_once('.').unwrap_or((text, ""));
+    let dropped = frac.get(decimals..).unwrap_or("").as_bytes();
+
+    // The integer digits, then exactly `decimals` fraction digits
+    let mut digits: Vec<u8> = int.bytes().chain(frac.bytes().take(decimals)).collect();
+    digits.resize(int.len() + decimals, b'0');
+
+    let round_up = match (format.mode, dropped.split_first()) {
+        (RoundingMode::TowardZero, _) | (_, None) => false,
+        (RoundingMode::HalfAwayFromZero, Some((&first, _))) => first >= b'5',
+        (RoundingMode::HalfToEven, Some((&first, rest))) => {
+            first > b'5'
+                || first == b'5'
+                    && (rest.iter().any(|&digit| digit != b'0')
+                        || digits.last().is_some_and(|digit| (digit - b'0') % 2 == 1))
+        }
+    };
+    if round_up {
+        match digits.iter().rposition(|&digit| digit != b'9') {
+            Some(at) => {
+                digits[at] += 1;
+                digits[at + 1..].fill(b'0');
+            }
+            None => {
+                digits.fill(b'0');
+                digits.insert(0, b'1');
+            }
+        }
+    }
+
+    let (int, mut frac) = digits.split_at(digits.len() - decimals);
+    if format.trim_trailing_zeros {
+        while let [rest @ .., b'0'] = frac {
+            frac = rest;
+        }
+    }
+    // Rounding to zero drops the sign, as `-0.0` is written `0`
+    if negative && digits.iter().any(|&digit| digit != b'0') {
+        output.push('-');
+    }
+    output.extend(int.iter().map(|&digit| char::from(digit)));
+    if !frac.is_empty() {
+        output.push('.');
+        output.extend(frac.iter().map(|&digit| char::from(digit)));
+    }
+}
+
+/// Largest integer a JavaScript number holds exactly, `Number.MAX_SAFE_INTEGER`.
+pub(crate) const MAX_SAFE_INTEGER: u128 = (1 << 53) - 1;
+
+/// Writes an integer. Under
+/// [`CompatMode::ReferenceTs`](crate::CompatMode::ReferenceTs), one beyond
+/// [`MAX_SAFE_INTEGER`] is quoted, as the reference encoder writes a `BigInt`.
+fn write_integer(output: &mut String, i: i128, options: &ToonOptions) {
+    let quoted = options.matches_reference() && i.unsigned_abs() > MAX_SAFE_INTEGER;
+    if quoted {
+        output.push('"');
+    }
+    push_display(output, i);
+    if quoted {
+        output.push('"');
+    }
+}
+
+/// Writes `number` as TOON writes it, or as written for a [`Number::Raw`]
+/// or [`Number::Decimal`](crate::Number).
+pub(crate) fn write_number(output: &mut String, number: &Number, format: &FloatFormat) {
+    match number {
+        Number::Integer(i) => push_display(output, i),
+        Number::Raw(text) => output.push_str(text),
+        #[cfg(feature = "rust_decimal")]
+        Number::Decimal(d) => push_display(output, d),
+        _ => write_f64(output, number.as_f64(), format),
+    }
+}
+
+pub(crate) fn write_toon_value_quoted(output: &mut String, value: &Value, options: &ToonOptions) {
     match value {
         Value::Null => output.push_str("null"),
         Value::Bool(b) => output.push_str(if *b { "true" } else { "false" }),
-        Value::Number(n) => output.push_str(&n.to_string()),
+        Value::Number(Number::Integer(i)) => write_integer(output, (*i).into(), options),
+        Value::Number(n) => write_number(output, n, &options.float_format),
         Value::String(s) => {
-            if needs_quotes_toon(s, options) {
-                output.push('"');
-                for ch in s.chars() {
-                    match ch {
-                        '"' => output.push_str("\\\""),
-                        '\\' => output.push_str("\\\\"),
-                        '\n' => output.push_str("\\n"),
-                        '\r' => output.push_str("\\r"),
-                        '\t' => output.push_str("\\t"),
-                        '\u{0008}' => output.push_str("\\b"), // backspace
-                        '\u{000C}' => output.push_str("\\f"), // form feed
-                        '\0' => output.push_str("\\0"),
-                        _ => output.push(ch),
-                    }
-                }
-                output.push('"');
+            if quotes_string(s, options) {
+                write_escaped(output, s, options);
             } else {
                 output.push_str(s);
             }
@@ -1233,9 +2252,17 @@ fn write_toon_value_quoted(output: &mut String, value: &Value, options: &ToonOpt
             let entries: Vec<_> = obj.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
             write_object(output, &entries, options, 0);
         }
-        Value::Table { headers, rows } => {
+        Value::Table(Table { headers, rows }) => {
             write_tabular_array(output, headers, rows, options, 0);
         }
+        // As `Date.prototype.toISOString` writes it
+        #[cfg(feature = "datetime")]
+        Value::Date(dt) if options.matches_reference() => {
+            output.push('"');
+            output.push_str(&dt.to_rfc3339_opts(chrono::SecondsFormat::Millis, true));
+            output.push('"');
+        }
+        #[cfg(feature = "datetime")]
         Value::Date(dt) => {
             let s = dt.to_rfc3339();
             if needs_quotes_toon(&s, options) {
@@ -1246,6 +2273,16 @@ fn write_toon_value_quoted(output: &mut String, value: &Value, options: &ToonOpt
                 output.push_str(&s);
             }
         }
+        #[cfg(feature = "bigint")]
+        Value::BigInt(bi) if options.matches_reference() => match i128::try_from(bi) {
+            Ok(i) => write_integer(output, i, options),
+            Err(_) => {
+                output.push('"');
+                push_display(output, bi);
+                output.push('"');
+            }
+        },
+        #[cfg(feature = "bigint")]
         Value::BigInt(bi) => {
             let s = format!("{}n", bi);
             if needs_quotes_toon(&s, options) {
@@ -1259,31 +2296,236 @@ fn write_toon_value_quoted(output: &mut String, value: &Value, options: &ToonOpt
     }
 }
 
+/// Writes `s` as a double-quoted string with escapes. Invisible characters
+/// without a short escape are written as `\u{...}`, as is every non-ASCII
+/// character if [`ToonOptions::escape_non_ascii`] is set. The reference
+/// encoder escapes only the first five characters here.
+fn write_escaped(output: &mut String, s: &str, options: &ToonOptions) {
+    let reference = options.matches_reference();
+    output.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            _ if reference => output.push(ch),
+            '\u{0008}' => output.push_str("\\b"), // backspace
+            '\u{000C}' => output.push_str("\\f"), // form feed
+            '\0' => output.push_str("\\0"),
+            _ if is_invisible(ch) || (options.escape_non_ascii && !ch.is_ascii()) => {
+                output.push_str(&format!("\\u{{{:x}}}", ch as u32));
+            }
+            _ => output.push(ch),
+        }
+    }
+    output.push('"');
+}
+
+/// Length in bytes of the string value `s` as written: quoted and escaped by
+/// [`write_escaped`] if [`quotes_string`] asks for it.
+pub(crate) fn string_len(s: &str, options: &ToonOptions) -> usize {
+    if !quotes_string(s, options) {
+        return s.len();
+    }
+    let reference = options.matches_reference();
+    let escaped: usize = s
+        .chars()
+        .map(|ch| match ch {
+            '"' | '\\' | '\n' | '\r' | '\t' => 2,
+            _ if reference => ch.len_utf8(),
+            '\u{0008}' | '\u{000C}' | '\0' => 2,
+            _ if is_invisible(ch) || (options.escape_non_ascii && !ch.is_ascii()) => {
+                let bits = (32 - (ch as u32).leading_zeros()).max(1) as usize;
+                (bits + 3) / 4 + 4
+            }
+            _ => ch.len_utf8(),
+        })
+        .sum();
+    escaped + 2
+}
+
+/// Whether `ch` is invisible or easily mistaken for something else when
+/// written as is: control and format characters (including bidi controls and
+/// zero-width characters), and whitespace other than a plain space.
+fn is_invisible(ch: char) -> bool {
+    ch.is_control()
+        || (ch.is_whitespace() && ch != ' ')
+        || matches!(
+            ch,
+            '\u{00AD}'
+                | '\u{0600}'..='\u{0605}'
+                | '\u{061C}'
+                | '\u{06DD}'
+                | '\u{070F}'
+                | '\u{180E}'
+                | '\u{200B}'..='\u{200F}'
+                | '\u{202A}'..='\u{202E}'
+                | '\u{2060}'..='\u{2064}'
+                | '\u{2066}'..='\u{206F}'
+                | '\u{FEFF}'
+                | '\u{FFF9}'..='\u{FFFB}'
+                | '\u{E0001}'
+                | '\u{E0020}'..='\u{E007F}'
+        )
+}
+
+/// Whether `s` has a character [`write_escaped`] escapes with `\u{...}`.
+fn has_unicode_escapes(s: &str, options: &ToonOptions) -> bool {
+    s.contains(is_invisible) || (options.escape_non_ascii && !s.is_ascii())
+}
+
+/// Returns `key` as written before a `:` or in a table header, quoted if it
+/// would otherwise read back as something other than that key.
+pub(crate) fn key_text<'a>(key: &'a str, options: &ToonOptions) -> Cow<'a, str> {
+    let needs_quotes = if options.matches_reference() {
+        // The reference encoder's `^[A-Z_][\w.]*$`, ignoring case
+        !key.starts_with(|ch: char| ch.is_ascii_alphabetic() || ch == '_')
+            || !key
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '.')
+    } else {
+        key.is_empty()
+            || key.starts_with(|ch: char| ch.is_whitespace() || ch == '-' || ch.is_ascii_digit())
+            || key.ends_with(char::is_whitespace)
+            // Four spaces separate the fields of a tab-delimited table header
+            || key.contains("    ")
+            || key.contains(|ch: char| {
+                matches!(
+                    ch,
+                    ':' | ','
+                        | '|'
+                        | '"'
+                        | '\\'
+                        | '['
+                        | ']'
+                        | '{'
+                        | '}'
+                        | '\n'
+                        | '\r'
+                        | '\t'
+                        | '\0'
+                )
+            })
+            || has_unicode_escapes(key, options)
+            || matches!(key, "true" | "false" | "null")
+    };
+    if !needs_quotes {
+        return Cow::Borrowed(key);
+    }
+
+    let mut quoted = String::with_capacity(key.len() + 2);
+    write_escaped(&mut quoted, key, options);
+    Cow::Owned(quoted)
+}
+
+/// Delimiters that end an unquoted value written with `delimiter`.
+///
+/// A tab is always among them. Comma arrays have no delimiter in their header,
+/// and a parser detecting one could split on a pipe or tab, so they avoid
+/// all three; so does every array unless each picks its own delimiter.
+fn quote_stops(strategy: DelimiterStrategy, delimiter: &Delimiter) -> &'static [char] {
+    match (strategy, delimiter) {
+        (DelimiterStrategy::Auto, Delimiter::Pipe) => &['|', '\t'],
+        (DelimiterStrategy::Auto, Delimiter::Tab) => &['\t'],
+        _ => &[',', '|', '\t'],
+    }
+}
+
+/// Whether the string value `s` is written quoted under the configured
+/// [`QuotePolicy`].
+fn quotes_string(s: &str, options: &ToonOptions) -> bool {
+    if options.matches_reference() {
+        return !reference_unquoted(s, &options.delimiter);
+    }
+    match options.quote_policy {
+        QuotePolicy::Minimal => needs_quotes_toon(s, options),
+        QuotePolicy::AlwaysStrings => true,
+        QuotePolicy::PreserveInput => needs_quotes_toon(s, options) && !reads_back_as_scalar(s),
+    }
+}
+
+/// Whether the reference encoder writes `s` unquoted where `delimiter`
+/// separates values; its `isSafeUnquoted`.
+fn reference_unquoted(s: &str, delimiter: &Delimiter) -> bool {
+    // `String.prototype.trim` trims NEL but not BOM
+    let js_whitespace = |ch: char| (ch.is_whitespace() && ch != '\u{85}') || ch == '\u{FEFF}';
+    !s.is_empty()
+        && !s.starts_with(js_whitespace)
+        && !s.ends_with(js_whitespace)
+        && !matches!(s, "true" | "false" | "null")
+        && !is_numeric_like(s)
+        && !s.contains([':', '"', '\\', '[', ']', '{', '}', '\n', '\r', '\t'])
+        && !s.contains(delimiter.as_str())
+        && !s.starts_with('-')
+}
+
+/// Whether `s` matches the reference encoder's `^-?\d+(?:\.\d+)?(?:e[+-]?\d+)?$`,
+/// ignoring case. Leading zeros, as in `007`, match as well.
+fn is_numeric_like(s: &str) -> bool {
+    fn digits(s: &str) -> Option<&str> {
+        let count = s.bytes().take_while(u8::is_ascii_digit).count();
+        (count > 0).then(|| &s[count..])
+    }
+    let Some(mut rest) = digits(s.strip_prefix('-').unwrap_or(s)) else {
+        return false;
+    };
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let Some(after) = digits(fraction) else {
+            return false;
+        };
+        rest = after;
+    }
+    if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        let Some(after) = digits(exponent) else {
+            return false;
+        };
+        rest = after;
+    }
+    rest.is_empty()
+}
+
+/// Whether `s` unquoted reads back as a number or boolean that displays as
+/// `s` again, which a string-typed field turns back into `s`.
+fn reads_back_as_scalar(s: &str) -> bool {
+    match s.parse::<i64>() {
+        Ok(i) => i.to_string() == s,
+        Err(_) => {
+            matches!(s, "true" | "false") || s.parse::<f64>().is_ok_and(|f| f.to_string() == s)
+        }
+    }
+}
+
 fn needs_quotes_toon(s: &str, options: &ToonOptions) -> bool {
+    has_unicode_escapes(s, options)
+        || needs_quotes_with(
+            s,
+            quote_stops(options.delimiter_strategy, &options.delimiter),
+        )
+}
+
+/// Whether `s` must be quoted to read back as itself, where `stops` are the
+/// delimiters that would end it.
+fn needs_quotes_with(s: &str, stops: &[char]) -> bool {
     if s.is_empty() {
         return true;
     }
 
-    // Leading or trailing spaces
-    if s.starts_with(' ') || s.ends_with(' ') {
+    // Leading or trailing whitespace, which the parser trims
+    if s.starts_with(char::is_whitespace) || s.ends_with(char::is_whitespace) {
         return true;
     }
 
-    // Contains active delimiter, colon, quote, backslash, or control chars
-    let active_delimiter = options.delimiter.as_str();
-    if s.contains(':')
-        || s.contains('"')
-        || s.contains('\\')
-        || s.contains('\n')
-        || s.contains('\r')
-        || s.contains('\t')
-        || s.contains('\0')
-    {
+    // Contains a colon, quote, backslash, or invisible characters such as
+    // line breaks, bidi controls and non-breaking spaces
+    if s.contains([':', '"', '\\']) || s.contains(is_invisible) {
         return true;
     }
 
-    // Contains active delimiter
-    if s.contains(active_delimiter) {
+    // Contains a delimiter the parser stops at
+    if s.contains(stops) {
         return true;
     }
 
@@ -1302,11 +2544,8 @@ fn needs_quotes_toon(s: &str, options: &ToonOptions) -> bool {
         return true;
     }
 
-    // Looks like structural token
-    if s.starts_with('[') && s.contains(']') {
-        return true;
-    }
-    if s.starts_with('{') && s.contains('}') {
+    // Looks like an array header, or contains a character that ends an unquoted value
+    if s.starts_with(['[', '{']) || s.contains([']', '}']) {
         return true;
     }
 
@@ -0,0 +1,886 @@
+//! Size estimates without serializing.
+//!
+//! [`estimated_size`] walks a value with a serializer that only counts: it
+//! measures the document [`to_string`](crate::to_string) would write, with the
+//! same choice of table, inline and list arrays, but never builds the string or
+//! any [`Value`]s. Use it to decide between inlining data in a prompt and
+//! summarizing it, without paying for the encoding twice.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde::Serialize;
+//! use serde_toon::{estimated_size, to_string};
+//!
+//! #[derive(Serialize)]
+//! struct User { id: u32, name: String }
+//!
+//! let users: Vec<User> = (0..50)
+//!     .map(|id| User { id, name: format!("user{}", id) })
+//!     .collect();
+//!
+//! let size = estimated_size(&users).unwrap();
+//! assert_eq!(size.bytes, to_string(&users).unwrap().len());
+//! assert!(size.approx_tokens < size.bytes);
+//! ```
+
+use crate::bytes;
+use crate::options::BytesFormat;
+use crate::raw;
+use crate::ser::{key_text, string_len, widen_f32, write_f64};
+use crate::value::{DECIMAL_TOKEN, NUMBER_TOKEN};
+use crate::{Error, Result, ToonOptions, Value, ValueSerializer};
+use serde::ser::{self, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::{self, Display, Write};
+use std::hash::{Hash, Hasher};
+use std::ops::{Add, AddAssign};
+
+/// The size of a value's TOON encoding, from [`estimated_size`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SizeEstimate {
+    /// Length in bytes of the document.
+    pub bytes: usize,
+    /// Approximate number of tokens in the document, counted piece by piece
+    /// as it is measured. This is coarser than
+    /// [`metrics::token_estimate`](crate::metrics::token_estimate) on the
+    /// written text.
+    pub approx_tokens: usize,
+}
+
+/// Measures the TOON document [`to_string`](crate::to_string) writes for
+/// `value`, without writing it.
+///
+/// `bytes` is exact: arrays are measured in the layout `to_string` picks for
+/// them, strings with the quotes and escapes they need, and nested lines with
+/// their indentation.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{estimated_size, toon};
+///
+/// let value = toon!({"id": 7, "tags": ["a", "b c"], "note": "x: y"});
+/// let size = estimated_size(&value).unwrap();
+/// assert_eq!(size.bytes, "id: 7\ntags: [2]: a,b c\nnote: \"x: y\"".len());
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the value cannot be serialized, such as a map with
+/// keys that are neither strings, numbers nor booleans.
+pub fn estimated_size<T>(value: &T) -> Result<SizeEstimate>
+where
+    T: ?Sized + Serialize,
+{
+    let options = ToonOptions::default();
+    let measure = Measure {
+        options: &options,
+        root: true,
+    };
+    let document = value.serialize(measure)?.document();
+    Ok(SizeEstimate {
+        bytes: document.size.bytes,
+        approx_tokens: document.size.tokens,
+    })
+}
+
+/// Bytes and approximate tokens of a piece of output.
+#[derive(Clone, Copy, Debug, Default)]
+struct Tally {
+    bytes: usize,
+    tokens: usize,
+}
+
+impl Tally {
+    /// Layout such as `: `, a delimiter or a line break with the indentation
+    /// after it, which tokenizers mostly merge into a single token.
+    fn punct(bytes: usize) -> Self {
+        Tally { bytes, tokens: 1 }
+    }
+
+    fn text(text: &str) -> Self {
+        Tally {
+            bytes: text.len(),
+            tokens: text_tokens(text),
+        }
+    }
+
+    /// Text written through [`Display`], counted as it is formatted.
+    fn display(value: impl Display) -> Self {
+        let mut tally = Tally::default();
+        // Writing into a `Tally` never fails
+        let _ = write!(tally, "{}", value);
+        tally
+    }
+}
+
+impl Write for Tally {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        *self += Tally::text(s);
+        Ok(())
+    }
+}
+
+impl Add for Tally {
+    type Output = Tally;
+
+    fn add(self, other: Tally) -> Tally {
+        Tally {
+            bytes: self.bytes + other.bytes,
+            tokens: self.tokens + other.tokens,
+        }
+    }
+}
+
+impl AddAssign for Tally {
+    fn add_assign(&mut self, other: Tally) {
+        *self = *self + other;
+    }
+}
+
+/// Approximates the tokens in `text` from its runs of letters (a token per
+/// eight), digits (per three) and symbols (per five), without allocating.
+fn text_tokens(text: &str) -> usize {
+    fn cost(class: u8, len: usize) -> usize {
+        match class {
+            0 => (len + 7) / 8,
+            1 => (len + 2) / 3,
+            2 => 0,
+            _ => (len + 4) / 5,
+        }
+    }
+
+    let mut tokens = 0;
+    let mut run = (u8::MAX, 0);
+    for ch in text.chars() {
+        let class = if ch.is_alphabetic() {
+            0
+        } else if ch.is_numeric() {
+            1
+        } else if ch.is_whitespace() {
+            // A space joins the word after it
+            2
+        } else {
+            3
+        };
+        if class == run.0 {
+            run.1 += 1;
+        } else {
+            tokens += cost(run.0, run.1);
+            run = (class, 1);
+        }
+    }
+    tokens + cost(run.0, run.1)
+}
+
+/// Measured text that starts at the left margin: a first line, then `lines`
+/// more, each of which moves right with wherever the text is placed.
+#[derive(Clone, Copy, Debug, Default)]
+struct Block {
+    size: Tally,
+    lines: usize,
+}
+
+impl Block {
+    fn line(size: Tally) -> Self {
+        Block { size, lines: 0 }
+    }
+
+    /// The size of the block placed `by` bytes to the right.
+    fn shifted(self, by: usize) -> Tally {
+        Tally {
+            bytes: self.size.bytes + by * self.lines,
+            tokens: self.size.tokens,
+        }
+    }
+
+    /// Adds `next` on a new line indented by `by`.
+    fn push_line(&mut self, by: usize, next: Block) {
+        self.size += Tally::punct(1 + by) + next.shifted(by);
+        self.lines += 1 + next.lines;
+    }
+}
+
+/// What a value serializes to.
+enum Shape {
+    Scalar(Tally),
+    Object(Fields),
+    /// An array from its `[N]` header on.
+    Array(Block),
+}
+
+impl Shape {
+    /// The value written as a document or a list item.
+    fn document(self) -> Block {
+        match self {
+            Shape::Scalar(size) => Block::line(size),
+            Shape::Object(fields) => fields.body,
+            Shape::Array(block) => block,
+        }
+    }
+}
+
+/// The fields of an object measured so far.
+struct Fields {
+    count: usize,
+    /// The fields one per line at the left margin, as at the root.
+    body: Block,
+    /// The fields as a table row, unless one of them is an object or array.
+    row: Option<Row>,
+}
+
+#[derive(Clone, Copy)]
+struct Row {
+    /// Sum of the hashes of the keys, which is the same for the same keys in
+    /// any order.
+    keys: u64,
+    header: Tally,
+    cells: Tally,
+}
+
+impl Fields {
+    fn new() -> Self {
+        Fields {
+            count: 0,
+            body: Block::default(),
+            row: Some(Row {
+                keys: 0,
+                header: Tally::default(),
+                cells: Tally::default(),
+            }),
+        }
+    }
+
+    fn push(&mut self, key: &str, value: Shape, options: &ToonOptions) {
+        let written = key_text(key, options);
+        let key = Tally::text(&written);
+        let mut field = Block::line(key + Tally::punct(1));
+        match value {
+            Shape::Scalar(size) => {
+                if let Some(row) = &mut self.row {
+                    if self.count > 0 {
+                        row.header += Tally::punct(1);
+                        row.cells += Tally::punct(options.delimiter.as_str().len());
+                    }
+                    let mut hasher = DefaultHasher::new();
+                    written.hash(&mut hasher);
+                    row.keys = row.keys.wrapping_add(hasher.finish());
+                    row.header += key;
+                    row.cells += size;
+                }
+                field.size += Tally::punct(1) + size;
+            }
+            Shape::Object(fields) => {
+                self.row = None;
+                if fields.count > 0 {
+                    field.push_line(options.indent, fields.body);
+                }
+            }
+            Shape::Array(block) => {
+                self.row = None;
+                field.size += Tally::punct(1) + block.size;
+                field.lines += block.lines;
+            }
+        }
+
+        if self.count == 0 {
+            self.body = field;
+        } else {
+            self.body.push_line(0, field);
+        }
+        self.count += 1;
+    }
+}
+
+/// The elements of an array measured so far, in each layout it may take.
+struct Elements {
+    len: usize,
+    /// The elements on one line, while all of them are primitives.
+    inline: Option<Tally>,
+    /// The elements as table rows, while all of them are non-empty objects of
+    /// primitives with the same keys.
+    table: Option<Rows>,
+    /// The elements as list items.
+    items: Block,
+}
+
+/// The rows of a table, and what its first row says about the header.
+struct Rows {
+    first: Row,
+    fields: usize,
+    lines: Block,
+}
+
+impl Elements {
+    fn new() -> Self {
+        Elements {
+            len: 0,
+            inline: Some(Tally::default()),
+            table: None,
+            items: Block::default(),
+        }
+    }
+
+    fn push(&mut self, element: Shape, options: &ToonOptions) {
+        let indent = options.indent;
+        if let Some(inline) = &mut self.inline {
+            match &element {
+                Shape::Scalar(size) => {
+                    if self.len > 0 {
+                        *inline += Tally::punct(options.delimiter.as_str().len());
+                    }
+                    *inline += *size;
+                }
+                _ => self.inline = None,
+            }
+        }
+
+        let row = match &element {
+            Shape::Object(fields) if fields.count > 0 => fields.row.map(|row| (row, fields.count)),
+            _ => None,
+        };
+        self.table = match (self.len, self.table.take(), row) {
+            (0, _, Some((first, fields))) => Some(Rows {
+                first,
+                fields,
+                lines: Block::default(),
+            }),
+            (_, Some(rows), Some((row, fields)))
+                if rows.first.keys == row.keys && rows.fields == fields =>
+            {
+                Some(rows)
+            }
+            _ => None,
+        };
+        if let (Some(rows), Some((row, _))) = (&mut self.table, row) {
+            rows.lines.push_line(indent, Block::line(row.cells));
+        }
+
+        // An item's lines after the dash line line up with its content
+        let item = element.document();
+        self.items.push_line(
+            indent,
+            Block {
+                size: Tally::punct(2) + item.shifted(2),
+                lines: item.lines,
+            },
+        );
+        self.len += 1;
+    }
+
+    fn finish(self) -> Shape {
+        let length = Tally::punct(2) + Tally::display(self.len);
+        let block = if self.len == 0 {
+            Block::line(length + Tally::punct(1))
+        } else if let Some(rows) = self.table {
+            let header = Tally::punct(1) + rows.first.header + Tally::punct(2);
+            Block {
+                size: length + header + rows.lines.size,
+                lines: rows.lines.lines,
+            }
+        } else if let Some(inline) = self.inline {
+            Block::line(length + Tally::punct(2) + inline)
+        } else {
+            Block {
+                size: length + Tally::punct(1) + self.items.size,
+                lines: self.items.lines,
+            }
+        };
+        Shape::Array(block)
+    }
+}
+
+/// A serializer that measures what it is given.
+#[derive(Clone, Copy)]
+struct Measure<'a> {
+    options: &'a ToonOptions,
+    /// Whether this is the whole document, which is where a
+    /// [`RawToon`](crate::RawToon) is written unchanged.
+    root: bool,
+}
+
+impl<'a> Measure<'a> {
+    fn nested(self) -> Self {
+        Measure {
+            root: false,
+            ..self
+        }
+    }
+
+    /// An object with the single field `variant`, for enum variants with
+    /// content.
+    fn variant(self, variant: &str, content: Shape) -> Shape {
+        let mut fields = Fields::new();
+        fields.push(variant, content, self.options);
+        Shape::Object(fields)
+    }
+}
+
+impl<'a> ser::Serializer for Measure<'a> {
+    type Ok = Shape;
+    type Error = Error;
+    type SerializeSeq = MeasureSeq<'a>;
+    type SerializeTuple = MeasureSeq<'a>;
+    type SerializeTupleStruct = MeasureSeq<'a>;
+    type SerializeTupleVariant = MeasureSeq<'a>;
+    type SerializeMap = MeasureMap<'a>;
+    type SerializeStruct = MeasureMap<'a>;
+    type SerializeStructVariant = MeasureMap<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<Shape> {
+        Ok(Shape::Scalar(Tally::text(if v { "true" } else { "false" })))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Shape> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Shape> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Shape> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Shape> {
+        Ok(Shape::Scalar(Tally::display(v)))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Shape> {
+        match i64::try_from(v) {
+            Ok(v) => self.serialize_i64(v),
+            #[cfg(feature = "bigint")]
+            Err(_) => Ok(Shape::Scalar(Tally::display(v) + Tally::text("n"))),
+            #[cfg(not(feature = "bigint"))]
+            Err(_) => self.serialize_f64(v as f64),
+        }
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Shape> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Shape> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Shape> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Shape> {
+        match i64::try_from(v) {
+            Ok(v) => self.serialize_i64(v),
+            Err(_) if self.root => Ok(Shape::Scalar(Tally::display(v))),
+            Err(_) => self.serialize_f64(v as f64),
+        }
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Shape> {
+        match i64::try_from(v) {
+            Ok(v) => self.serialize_i64(v),
+            #[cfg(feature = "bigint")]
+            Err(_) => Ok(Shape::Scalar(Tally::display(v) + Tally::text("n"))),
+            #[cfg(not(feature = "bigint"))]
+            Err(_) => self.serialize_f64(v as f64),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Shape> {
+        self.serialize_f64(widen_f32(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Shape> {
+        if self.options.float_format.max_decimals.is_some() {
+            let mut text = String::new();
+            write_f64(&mut text, v, &self.options.float_format);
+            return Ok(Shape::Scalar(Tally::text(&text)));
+        }
+        Ok(Shape::Scalar(if v == 0.0 {
+            Tally::text("0")
+        } else if !v.is_finite() {
+            Tally::text("null")
+        } else {
+            Tally::display(v)
+        }))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Shape> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Shape> {
+        Ok(Shape::Scalar(Tally {
+            bytes: string_len(v, self.options),
+            tokens: text_tokens(v),
+        }))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Shape> {
+        match self.options.bytes_format {
+            BytesFormat::Base64 => self.serialize_str(&bytes::encode_base64(v)),
+            BytesFormat::Hex => self.serialize_str(&bytes::encode_hex(v)),
+            BytesFormat::IntArray => {
+                let mut elements = Elements::new();
+                for byte in v {
+                    elements.push(self.serialize_u8(*byte)?, self.options);
+                }
+                Ok(elements.finish())
+            }
+        }
+    }
+
+    fn serialize_none(self) -> Result<Shape> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Shape>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Shape> {
+        Ok(Shape::Scalar(Tally::text("null")))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Shape> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Shape> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Shape>
+    where
+        T: ?Sized + Serialize,
+    {
+        if name == raw::TOKEN {
+            if let Value::String(text) = value.serialize(ValueSerializer::new())? {
+                if self.root {
+                    return Ok(Shape::Scalar(Tally::text(&text)));
+                }
+                return raw::parse_for_output(&text)?.serialize(self);
+            }
+        }
+        if name == NUMBER_TOKEN || name == DECIMAL_TOKEN {
+            if let Value::String(text) = value.serialize(ValueSerializer::new())? {
+                return Ok(Shape::Scalar(Tally::text(&text)));
+            }
+        }
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Shape>
+    where
+        T: ?Sized + Serialize,
+    {
+        let content = value.serialize(self.nested())?;
+        Ok(self.variant(variant, content))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<MeasureSeq<'a>> {
+        Ok(MeasureSeq {
+            measure: self.nested(),
+            elements: Elements::new(),
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<MeasureSeq<'a>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<MeasureSeq<'a>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<MeasureSeq<'a>> {
+        let mut seq = self.serialize_seq(Some(len))?;
+        seq.variant = Some(variant);
+        Ok(seq)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MeasureMap<'a>> {
+        Ok(MeasureMap {
+            measure: self.nested(),
+            fields: Fields::new(),
+            key: None,
+            variant: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<MeasureMap<'a>> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<MeasureMap<'a>> {
+        let mut map = self.serialize_map(Some(len))?;
+        map.variant = Some(variant);
+        Ok(map)
+    }
+}
+
+/// Measures the elements of an array, tuple or tuple variant.
+struct MeasureSeq<'a> {
+    measure: Measure<'a>,
+    elements: Elements,
+    variant: Option<&'static str>,
+}
+
+impl MeasureSeq<'_> {
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let element = value.serialize(self.measure)?;
+        self.elements.push(element, self.measure.options);
+        Ok(())
+    }
+
+    fn finish(self) -> Shape {
+        let array = self.elements.finish();
+        match self.variant {
+            Some(variant) => self.measure.variant(variant, array),
+            None => array,
+        }
+    }
+}
+
+impl ser::SerializeSeq for MeasureSeq<'_> {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Shape> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for MeasureSeq<'_> {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Shape> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for MeasureSeq<'_> {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Shape> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleVariant for MeasureSeq<'_> {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Shape> {
+        Ok(self.finish())
+    }
+}
+
+/// Measures the fields of a map, struct or struct variant.
+struct MeasureMap<'a> {
+    measure: Measure<'a>,
+    fields: Fields,
+    key: Option<String>,
+    variant: Option<&'static str>,
+}
+
+impl MeasureMap<'_> {
+    fn push<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> Result<()> {
+        let value = value.serialize(self.measure)?;
+        self.fields.push(key, value, self.measure.options);
+        Ok(())
+    }
+
+    fn finish(self) -> Shape {
+        let object = Shape::Object(self.fields);
+        match self.variant {
+            Some(variant) => self.measure.variant(variant, object),
+            None => object,
+        }
+    }
+}
+
+impl ser::SerializeMap for MeasureMap<'_> {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        // Numbers and booleans are written as their text, as by `to_string`
+        let key = match key.serialize(ValueSerializer::new())? {
+            Value::String(s) => s,
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            #[cfg(feature = "bigint")]
+            Value::BigInt(n) => n.to_string(),
+            _ => {
+                return Err(Error::custom(
+                    "map keys must be strings, numbers or booleans",
+                ))
+            }
+        };
+        self.key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error::custom("serialize_value called without serialize_key"))?;
+        self.push(&key, value)
+    }
+
+    fn end(self) -> Result<Shape> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStruct for MeasureMap<'_> {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Shape> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStructVariant for MeasureMap<'_> {
+    type Ok = Shape;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Shape> {
+        Ok(self.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::{token_estimate, Tokenizer};
+    use crate::{to_string, toon, RawToon};
+    use std::collections::BTreeMap;
+
+    fn assert_exact<T: Serialize>(value: &T) {
+        let toon = to_string(value).unwrap();
+        assert_eq!(estimated_size(value).unwrap().bytes, toon.len(), "{}", toon);
+    }
+
+    #[test]
+    fn measures_what_to_string_writes() {
+        #[derive(serde::Serialize)]
+        enum Event {
+            Ping,
+            Move(i32, i32),
+            Rename { from: String, to: String },
+            Wrap(Vec<u8>),
+        }
+
+        assert_exact(&vec![
+            Event::Ping,
+            Event::Move(1, -2),
+            Event::Rename {
+                from: "a\tb".to_string(),
+                to: "\u{200B}".to_string(),
+            },
+            Event::Wrap(vec![1, 2]),
+        ]);
+        assert_exact(&(u64::MAX, i128::MIN, u128::MAX, f64::NAN, -0.0, 'x'));
+        assert_exact(&BTreeMap::from([(1, "one"), (2, "two")]));
+        assert_exact(
+            &toon!({"rows": [{"a": 1, "b": null}, {"b": 2, "a": "x"}], "empty": {}, "list": [[], {}, [{"a": 1}]]}),
+        );
+        assert_exact(&Option::<u8>::None);
+
+        // A raw document is written as is at the root and rewritten inside another
+        let raw = RawToon::from_string("id:   7\nok:  true".to_string()).unwrap();
+        assert_exact(&raw);
+        assert_exact(&BTreeMap::from([("raw", raw)]));
+    }
+
+    #[test]
+    fn approximates_tokens() {
+        let value = toon!({
+            "users": [
+                {"id": 1, "name": "Alice Johnson", "email": "alice@example.com"},
+                {"id": 2, "name": "Bob", "email": "bob@example.com"}
+            ]
+        });
+        let tokens = token_estimate(&to_string(&value).unwrap(), Tokenizer::Cl100k);
+        let estimate = estimated_size(&value).unwrap().approx_tokens;
+        assert!(
+            estimate.abs_diff(tokens) <= tokens / 5,
+            "{} vs {}",
+            estimate,
+            tokens
+        );
+    }
+
+    #[test]
+    fn rejects_compound_map_keys() {
+        let map = BTreeMap::from([(vec![1], 1)]);
+        assert!(estimated_size(&map).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+//! Source locations of parsed values.
+//!
+//! Parsing with [`crate::from_str_spanned`] (or a [`Deserializer`] created with
+//! [`Deserializer::with_spans`]) records where every value came from, keyed by
+//! its [`Path`]. Tools such as editors can then map a problem found after
+//! parsing, e.g. a failed validation of a field, back to the exact text.
+//!
+//! Every object field, array item, table row and table cell gets a span, as
+//! does the document root. A span covers the value only: for a field it starts
+//! after `key: `, and for a nested object or array it runs from its first
+//! nested line through its last.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde_toon::from_str_spanned;
+//!
+//! let doc = "name: Store\nproducts: [2]{id,price}:\n  1,9.99\n  2,-5";
+//! let (value, spans) = from_str_spanned(doc).unwrap();
+//!
+//! // Flag the negative price
+//! let bad = spans.find("products[1].price").unwrap();
+//! assert_eq!(&doc[bad.range()], "-5");
+//! assert_eq!((bad.line, bad.column), (4, 5));
+//! # assert_eq!(value.query("products[1].price").unwrap().len(), 1);
+//! ```
+//!
+//! [`Deserializer`]: crate::Deserializer
+//! [`Deserializer::with_spans`]: crate::Deserializer::with_spans
+
+use crate::path::Path;
+use indexmap::IndexMap;
+use std::ops::Range;
+
+/// The location of a value in the input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// Byte offset of the value's first character.
+    pub start: usize,
+    /// Byte offset just past the value's last non-whitespace character.
+    pub end: usize,
+    /// 1-based line of `start`.
+    pub line: usize,
+    /// 1-based column (in characters) of `start`.
+    pub column: usize,
+}
+
+impl Span {
+    /// Returns the byte range of the value, for slicing the input.
+    #[must_use]
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+}
+
+/// The spans of the values in a document, in document order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Spans {
+    entries: IndexMap<Path, Span>,
+}
+
+impl Spans {
+    /// Returns the span of the value at `path`.
+    #[must_use]
+    pub fn get(&self, path: &Path) -> Option<&Span> {
+        self.entries.get(path)
+    }
+
+    /// Returns the span of the value whose path displays as `path`, such as
+    /// `users[1].name` or `(root)`.
+    #[must_use]
+    pub fn find(&self, path: &str) -> Option<&Span> {
+        self.entries
+            .iter()
+            .find(|(candidate, _)| candidate.to_string() == path)
+            .map(|(_, span)| span)
+    }
+
+    /// Iterates over paths and their spans in document order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Path, &Span)> {
+        self.entries.iter()
+    }
+
+    /// Returns the number of recorded spans.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no spans were recorded.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub(crate) fn insert(&mut self, path: Path, span: Span) {
+        self.entries.insert(path, span);
+    }
+
+    /// Reserves the position of `path` in document order, returning whether
+    /// its span should be recorded. With `first_wins`, a path that already has
+    /// a span keeps it.
+    pub(crate) fn claim(&mut self, path: &Path, first_wins: bool) -> bool {
+        if self.entries.contains_key(path) {
+            return !first_wins;
+        }
+        self.entries.insert(path.clone(), Span::default());
+        true
+    }
+
+    /// Drops a position claimed for a value that failed to parse.
+    pub(crate) fn release(&mut self, path: &Path) {
+        self.entries.shift_remove(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str_spanned, Deserializer, Error, ToonOptions};
+    use serde::Deserialize;
+
+    fn text<'a>(input: &'a str, spans: &super::Spans, path: &str) -> &'a str {
+        &input[spans.find(path).unwrap().range()]
+    }
+
+    #[test]
+    fn covers_every_value() {
+        let input = "id: 7\nowner:\n  name: Ada\n  roles: [2]: admin,dev\nitems: [2]{sku,qty}:\n  A1,2\n  B2,0\nlog: [2]:\n  - started\n  - at: 5";
+        let (_, spans) = from_str_spanned(input).unwrap();
+
+        assert_eq!(text(input, &spans, "(root)"), input);
+        assert_eq!(text(input, &spans, "id"), "7");
+        assert_eq!(
+            text(input, &spans, "owner"),
+            "name: Ada\n  roles: [2]: admin,dev"
+        );
+        assert_eq!(text(input, &spans, "owner.roles[1]"), "dev");
+        assert_eq!(text(input, &spans, "items[1]"), "B2,0");
+        assert_eq!(text(input, &spans, "items[1].qty"), "0");
+        assert_eq!(text(input, &spans, "log[0]"), "started");
+        assert_eq!(text(input, &spans, "log[1].at"), "5");
+
+        let qty = spans.find("items[1].qty").unwrap();
+        assert_eq!((qty.line, qty.column), (7, 6));
+        let first = spans.iter().next().unwrap();
+        assert!(first.0.is_root());
+    }
+
+    #[test]
+    fn locates_struct_fields() {
+        #[derive(Deserialize)]
+        #[allow(dead_code)]
+        struct Order {
+            id: u32,
+            lines: Vec<Line>,
+        }
+        #[derive(Deserialize)]
+        #[allow(dead_code)]
+        struct Line {
+            sku: String,
+            qty: i32,
+        }
+
+        let input = "id: 1\nlines: [2]{sku,qty}:\n  A1,2\n  B2,-1";
+        let mut de = Deserializer::from_str(input).with_spans();
+        let order = Order::deserialize(&mut de).unwrap();
+        let negative = order.lines.iter().position(|line| line.qty < 0).unwrap();
+        let spans = de.spans().unwrap();
+        let span = spans.find(&format!("lines[{}].qty", negative)).unwrap();
+        assert_eq!(&input[span.range()], "-1");
+
+        assert!(Deserializer::from_str(input).spans().is_none());
+    }
+
+    #[test]
+    fn maps_error_paths_to_spans() {
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Config {
+            name: String,
+        }
+
+        let input = "name: x\nnmae: y";
+        let options = ToonOptions::new().with_deny_unknown_fields(true);
+        let Err(Error::UnknownField { path, .. }) =
+            crate::from_str_with_options::<Config>(input, options)
+        else {
+            panic!("expected an unknown field error");
+        };
+        let (_, spans) = from_str_spanned(input).unwrap();
+        let span = spans.get(&path).unwrap();
+        assert_eq!((span.line, &input[span.range()]), (2, "y"));
+    }
+}
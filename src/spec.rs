@@ -36,8 +36,10 @@
 //!   - Keys starting with digits or containing hyphens must be quoted: `"2ndPlace"`, `"user-id"`
 //! - Values follow the `:` separator (space after `:` is optional but recommended)
 //! - Nested objects are indented (default 2 spaces per level)
-//! - Field order: This implementation sorts fields alphabetically for deterministic output
-//!   (not required by spec, but recommended for consistency)
+//! - Field order: Fields are written in insertion order (struct declaration order) and
+//!   parsed in document order, so key order survives a round trip. Use
+//!   [`ToonOptions::with_canonical_key_order`](crate::ToonOptions::with_canonical_key_order)
+//!   to sort them alphabetically instead
 //!
 //! ## Primitives
 //!
@@ -64,6 +66,8 @@
 //!   - Note: Only the active delimiter triggers quoting; others remain safe
 //! - Contains colon `:` (conflicts with key-value separator)
 //! - Contains quotes, backslashes, or control characters: `"`, `\`, `\n`, `\r`, `\t`
+//! - Contains invisible characters: format characters such as bidi controls and
+//!   zero-width spaces, or whitespace other than a plain space, such as `U+00A0`
 //! - Starts or ends with whitespace (trimming ambiguity)
 //! - Matches reserved words: `true`, `false`, `null`, `Infinity`, `-Infinity`, `NaN`
 //! - Parses as a number (would be ambiguous): `"42"`, `"-3.14"`, `"1e-6"`
@@ -91,6 +95,22 @@
 //! \f  - form feed
 //! \0  - null character
 //! \uXXXX - Unicode codepoint (4 hex digits)
+//! \u{...} - Unicode codepoint (1 to 6 hex digits)
+//! ```
+//!
+//! Invisible characters are written as `\u{...}`, as is all non-ASCII text
+//! with `ToonOptions::with_escape_non_ascii`.
+//!
+//! **Block strings**: a field whose value is `|` takes the lines indented
+//! deeper than its key as a string, read as they are. The first non-blank
+//! line's indentation is removed from every line, as is trailing whitespace,
+//! and the lines are joined with `\n`. A `|` with no indented lines below
+//! it is the string `"|"`:
+//! ```text
+//! prompt: |
+//!   Summarize the text.
+//!
+//!   Keep it short: one line.
 //! ```
 //!
 //! # Type Conversions
@@ -163,6 +183,17 @@
 //!     tags: [1]: user
 //! ```
 //!
+//! **Arrays of arrays** (matrices, embedding batches) put one inline array on
+//! each `- ` line, with its own length:
+//! ```text
+//! [3]:
+//!   - [2]: 0.5,1
+//!   - [2]: -2,3.25
+//!   - [0]:
+//! ```
+//! Rows may differ in length. Deeper nesting repeats the pattern, with an inner
+//! list array under each `- [N]:` line.
+//!
 //! ## Tabular Arrays
 //!
 //! For **homogeneous objects with primitive fields** (TOON's signature feature):
@@ -175,7 +206,7 @@
 //! ```
 //!
 //! **Syntax**: `[N]{field1,field2,...}:` followed by rows
-//! - Headers in `{}` define field order (alphabetically sorted)
+//! - Headers in `{}` define field order (the first object's field order)
 //! - One row per line, indented
 //! - Values comma-separated, matching header order
 //! - All objects must have identical structure
@@ -208,10 +239,15 @@
 //!
 //! Pipe-delimited table:
 //! ```text
-//! [3]{a|b|c}:
+//! [1|]{a|b|c}:
 //!   1|2|3
 //! ```
 //!
+//! **Decoding**: The marker alone decides the delimiter: `|`, a tab or four
+//! spaces after the length, and a comma without one. With
+//! `ToonOptions::with_sniff_delimiters`, a header without a marker takes the
+//! delimiter that splits its first row to fit the header instead.
+//!
 //! # Length Markers
 //!
 //! Optional character prefix for array lengths (e.g., `#` for clarity):
@@ -293,7 +329,6 @@
 //!
 //! - **Map keys**: Must be strings (no numeric or object keys)
 //! - **Tabular arrays**: Require identical object structure with primitive values only
-//! - **Field order**: Sorted alphabetically (may differ from original struct order)
 //! - **Comments**: Not supported in the format
 //!
 //! # Conformance
@@ -301,6 +336,16 @@
 //! This implementation follows the TOON specification from:
 //! <https://github.com/johannschopplich/toon>
 //!
+//! With the `json` feature, `conformance::run` checks this implementation
+//! against the reference implementation's golden fixture files. Known
+//! differences from the reference:
+//!
+//! - Blank lines inside arrays are accepted, even when decoding strictly
+//! - Unknown escapes in quoted strings, such as `\x`, are kept as written
+//!   rather than rejected
+//! - Key folding and path expansion (`keyFolding`, `expandPaths`) are not
+//!   supported
+//!
 //! For additional examples and use cases, see the crate's `examples/` directory.
 
 // This module contains only documentation; no implementation code
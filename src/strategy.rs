@@ -0,0 +1,264 @@
+//! Proptest strategies for [`Value`] (requires the `proptest` feature).
+//!
+//! [`any_value`] generates documents of every shape TOON writes: scalars
+//! (including dates and big integers), arrays, objects and tables, nested up
+//! to a configurable depth. `Value` also implements proptest's
+//! [`Arbitrary`], so `any::<Value>()` and `any_with::<Value>(params)` work too.
+//!
+//! Strings are drawn largely from characters with meaning in TOON (`:`, `,`,
+//! `|`, `-`, quotes, brackets, whitespace) and from keywords and numbers, to
+//! exercise quoting. Numbers are always finite, since NaN and infinities are
+//! written as `null`.
+//!
+//! A value does not always read back equal to itself. Floats such as `1.0`
+//! read back as integers, dates and big integers as strings, and tables at
+//! the root of a document as arrays of objects. Writing the value read back
+//! does give the same document again, which makes a good property to test.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use proptest::prelude::*;
+//! use serde_toon::strategy::ValueParams;
+//! use serde_toon::{from_str, to_string, Value};
+//!
+//! proptest!(|(value in any_with::<Value>(ValueParams::new().with_depth(2)))| {
+//!     let toon = to_string(&value).unwrap();
+//!     let back: Value = from_str(&toon).unwrap();
+//!     prop_assert_eq!(to_string(&back).unwrap(), toon);
+//! });
+//! ```
+
+use crate::{Number, Table, ToonMap, Value};
+#[cfg(feature = "datetime")]
+use chrono::{TimeZone, Utc};
+#[cfg(feature = "bigint")]
+use num_bigint::BigInt;
+use proptest::arbitrary::{any, Arbitrary};
+use proptest::collection::{btree_set, vec};
+use proptest::prelude::*;
+use proptest::strategy::{BoxedStrategy, Just, Strategy, Union};
+
+/// Shape of the values generated by [`value`] and `any_with::<Value>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValueParams {
+    /// Deepest nesting of arrays, objects and tables (default 3)
+    pub depth: u32,
+    /// Most items in an array, fields in an object or rows in a table
+    /// (default 4)
+    pub max_len: usize,
+}
+
+impl Default for ValueParams {
+    fn default() -> Self {
+        ValueParams {
+            depth: 3,
+            max_len: 4,
+        }
+    }
+}
+
+impl ValueParams {
+    /// Creates the default parameters.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the deepest nesting of containers; `0` generates only scalars.
+    #[must_use]
+    pub fn with_depth(mut self, depth: u32) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Sets the most items, fields or rows in one container.
+    #[must_use]
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+}
+
+/// Generates values with the default [`ValueParams`].
+pub fn any_value() -> BoxedStrategy<Value> {
+    value(ValueParams::default())
+}
+
+/// Generates values shaped by `params`.
+///
+/// # Examples
+///
+/// ```rust
+/// use proptest::strategy::{Strategy, ValueTree};
+/// use proptest::test_runner::TestRunner;
+/// use serde_toon::strategy::{value, ValueParams};
+///
+/// let strategy = value(ValueParams::new().with_depth(0));
+/// let mut runner = TestRunner::deterministic();
+/// let scalar = strategy.new_tree(&mut runner).unwrap().current();
+/// assert!(!scalar.is_array() && !scalar.is_object() && !scalar.is_table());
+/// ```
+pub fn value(params: ValueParams) -> BoxedStrategy<Value> {
+    let max_len = params.max_len;
+    scalar()
+        .prop_recursive(
+            params.depth,
+            (max_len.max(1) as u32).saturating_pow(params.depth.min(4)),
+            max_len as u32,
+            move |inner| {
+                prop_oneof![
+                    vec(inner.clone(), 0..=max_len).prop_map(Value::Array),
+                    vec((string(), inner), 0..=max_len)
+                        .prop_map(|fields| Value::Object(fields.into_iter().collect())),
+                    table(max_len),
+                ]
+            },
+        )
+        .boxed()
+}
+
+/// Generates scalar values: null, booleans, finite numbers, strings, and dates
+/// and big integers when the `datetime` and `bigint` features are enabled.
+pub fn scalar() -> BoxedStrategy<Value> {
+    #[allow(unused_mut)]
+    let mut arms = vec![
+        (1, Just(Value::Null).boxed()),
+        (1, any::<bool>().prop_map(Value::Bool).boxed()),
+        (
+            1,
+            any::<i64>()
+                .prop_map(|n| Value::Number(Number::Integer(n)))
+                .boxed(),
+        ),
+        (
+            1,
+            (prop::num::f64::NORMAL | prop::num::f64::SUBNORMAL | prop::num::f64::ZERO)
+                .prop_map(|f| Value::Number(Number::Float(f)))
+                .boxed(),
+        ),
+        (4, string().prop_map(Value::String).boxed()),
+    ];
+    #[cfg(feature = "datetime")]
+    arms.push((
+        1,
+        (0i64..4_102_444_800)
+            .prop_map(|secs| Value::Date(Utc.timestamp_opt(secs, 0).single().unwrap_or_default()))
+            .boxed(),
+    ));
+    #[cfg(feature = "bigint")]
+    arms.push((
+        1,
+        any::<i128>()
+            .prop_map(|n| Value::BigInt(BigInt::from(n)))
+            .boxed(),
+    ));
+    Union::new_weighted(arms).boxed()
+}
+
+/// Generates strings, for values and keys, that are often hard to write
+/// unquoted: empty, padded, keyword- or number-like, or containing
+/// delimiters, quotes, brackets and line breaks.
+pub fn string() -> BoxedStrategy<String> {
+    prop_oneof![
+        "[a-z_][a-z0-9_]{0,7}",
+        "[a-zA-Z0-9 :,|#\\-\\[\\]{}\"\\\\\n\t.]{0,8}",
+        prop::sample::select(
+            &["", " ", "true", "false", "null", "-", "- x", "1.5", "-0", "[2]:"][..]
+        )
+        .prop_map(str::to_string),
+        any::<String>(),
+    ]
+    .boxed()
+}
+
+/// Generates tables of scalar cells with up to `max_len` columns and rows.
+fn table(max_len: usize) -> BoxedStrategy<Value> {
+    btree_set(string(), 1..=max_len.max(1))
+        .prop_flat_map(move |headers| {
+            let width = headers.len();
+            let headers: Vec<String> = headers.into_iter().collect();
+            vec(vec(scalar(), width), 0..=max_len).prop_map(move |rows| {
+                #[allow(clippy::unwrap_used)] // Every row has `width` cells
+                let table = Table::from_parts(headers.clone(), rows).unwrap();
+                Value::Table(table)
+            })
+        })
+        .boxed()
+}
+
+impl Arbitrary for Value {
+    type Parameters = ValueParams;
+    type Strategy = BoxedStrategy<Value>;
+
+    fn arbitrary_with(params: ValueParams) -> Self::Strategy {
+        value(params)
+    }
+}
+
+impl Arbitrary for ToonMap {
+    type Parameters = ValueParams;
+    type Strategy = BoxedStrategy<ToonMap>;
+
+    fn arbitrary_with(params: ValueParams) -> Self::Strategy {
+        let max_len = params.max_len;
+        let inner = value(ValueParams {
+            depth: params.depth.saturating_sub(1),
+            ..params
+        });
+        vec((string(), inner), 0..=max_len)
+            .prop_map(|fields| fields.into_iter().collect())
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_str, to_string};
+    use proptest::test_runner::{Config, TestRunner};
+
+    fn depth(value: &Value) -> u32 {
+        match value {
+            Value::Array(items) => 1 + items.iter().map(depth).max().unwrap_or(0),
+            Value::Object(map) => 1 + map.values().map(depth).max().unwrap_or(0),
+            Value::Table(_) => 1,
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn written_values_read_back_stable() {
+        let mut runner = TestRunner::new(Config::with_cases(512));
+        runner
+            .run(&any_value(), |value| {
+                let toon = to_string(&value).unwrap();
+                let back: Value = from_str(&toon)
+                    .map_err(|err| TestCaseError::fail(format!("{} reading\n{}", err, toon)))?;
+                prop_assert_eq!(to_string(&back).unwrap(), toon);
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn respects_params() {
+        let params = ValueParams::new().with_depth(2).with_max_len(2);
+        let mut runner = TestRunner::deterministic();
+        runner
+            .run(&any_with::<Value>(params), |value| {
+                prop_assert!(depth(&value) <= 2);
+                if let Value::Array(items) = &value {
+                    prop_assert!(items.len() <= 2);
+                }
+                Ok(())
+            })
+            .unwrap();
+        runner
+            .run(&any::<ToonMap>(), |map| {
+                prop_assert!(map.len() <= 4);
+                Ok(())
+            })
+            .unwrap();
+    }
+}
@@ -0,0 +1,1122 @@
+//! Tables: arrays of objects sharing the same keys.
+//!
+//! A [`Table`] stores its column headers once and each row as a list of cells,
+//! mirroring TOON's tabular array syntax (`[2]{id,name}:`). It is the payload of
+//! [`Value::Table`] and converts to and from a `Vec<ToonMap>` of row objects.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde_toon::{to_string, Table, Value};
+//!
+//! let mut users = Table::new(["id", "name"]);
+//! users.push_row((1, "Alice")).unwrap();
+//! users.push_row((2, "Bob")).unwrap();
+//! users.add_column("active", [true, false]).unwrap();
+//!
+//! assert_eq!(users.column("name").unwrap(), vec![&Value::from("Alice"), &Value::from("Bob")]);
+//! assert_eq!(
+//!     to_string(&Value::Table(users.select_columns(&["name", "active"]))).unwrap(),
+//!     "[2]{name,active}:\n  Alice,true\n  Bob,false"
+//! );
+//! ```
+//!
+//! ## Column types
+//!
+//! [`Table::column_types`] infers the type of each column from its cells, and
+//! [`Table::coerce_columns`] converts cells to a given type. Together they
+//! repair columns where a writer (often a language model) quoted some cells:
+//!
+//! ```rust
+//! use serde_toon::{from_str, toon, ColumnType, Table, Value};
+//!
+//! let value: Value = from_str("[3]{sku,price}:\n  A1,9.5\n  B2,\"12\"\n  C3,7").unwrap();
+//! let mut table = Table::try_from(value).unwrap();
+//!
+//! let types = table.column_types();
+//! assert_eq!(types[1], ("price".to_string(), ColumnType::Float));
+//!
+//! table.coerce_columns(&types).unwrap();
+//! assert_eq!(table.column("price").unwrap(), vec![&toon!(9.5), &toon!(12.0), &toon!(7.0)]);
+//! ```
+
+use crate::de::from_value;
+use crate::{Error, IntoRow, Key, Number, Result, ToonMap, Value, ValueSerializer};
+#[cfg(feature = "datetime")]
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::ser::{self, Impossible, Serialize};
+use std::fmt;
+
+/// Rows of cells under a shared list of column headers.
+///
+/// Every row has exactly one cell per header.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Table {
+    pub(crate) headers: Vec<Key>,
+    pub(crate) rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    /// Creates a table with the given column headers and no rows.
+    pub fn new<I, S>(headers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<Key>,
+    {
+        Table {
+            headers: headers.into_iter().map(Into::into).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Creates a table from headers and rows of cells.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a row does not have exactly one cell per header.
+    pub fn from_parts<I, S>(headers: I, rows: Vec<Vec<Value>>) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<Key>,
+    {
+        let mut table = Table::new(headers);
+        for row in rows {
+            table.push_row(row)?;
+        }
+        Ok(table)
+    }
+
+    /// Builds a table from `rows`, each serialized from a struct or a map.
+    ///
+    /// The headers are the keys of the first row, in order, and every other
+    /// row must have the same keys in any order. Cells are serialized as by
+    /// [`to_value`](crate::to_value), straight into the table, which is much
+    /// faster than serializing a `Vec` of rows to a [`Value`] and converting
+    /// that.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a row is not a struct or map, if its keys differ
+    /// from the first row's, or if a cell cannot be serialized. Use
+    /// [`Table::from_serialize_iter_with`] with [`HeaderPolicy::Union`] for
+    /// rows that may skip fields.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_toon::{to_string, Table, Value};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Hit { id: u32, score: f64 }
+    ///
+    /// let hits = (1..=3).map(|id| Hit { id, score: f64::from(id) / 4.0 });
+    /// let table = Table::from_serialize_iter(hits).unwrap();
+    /// assert_eq!(table.headers(), ["id", "score"]);
+    /// assert_eq!(
+    ///     to_string(&Value::Table(table)).unwrap(),
+    ///     "[3]{id,score}:\n  1,0.25\n  2,0.5\n  3,0.75"
+    /// );
+    /// ```
+    pub fn from_serialize_iter<I>(rows: I) -> Result<Self>
+    where
+        I: IntoIterator,
+        I::Item: Serialize,
+    {
+        Self::from_serialize_iter_with(rows, HeaderPolicy::Strict)
+    }
+
+    /// Builds a table from `rows` as [`Table::from_serialize_iter`] does,
+    /// with `policy` deciding what happens to rows whose keys differ from
+    /// the first row's.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a row is not a struct or map, if a cell cannot be
+    /// serialized, or if a row's keys differ under [`HeaderPolicy::Strict`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::collections::BTreeMap;
+    /// use serde_toon::{toon, HeaderPolicy, Table};
+    ///
+    /// let rows: Vec<BTreeMap<&str, u32>> = vec![
+    ///     [("a", 1)].into(),
+    ///     [("a", 2), ("b", 3)].into(),
+    /// ];
+    /// assert!(Table::from_serialize_iter(&rows).is_err());
+    ///
+    /// let table = Table::from_serialize_iter_with(&rows, HeaderPolicy::Union).unwrap();
+    /// assert_eq!(table.headers(), ["a", "b"]);
+    /// assert_eq!(table.rows(), [vec![toon!(1), toon!(null)], vec![toon!(2), toon!(3)]]);
+    /// ```
+    pub fn from_serialize_iter_with<I>(rows: I, policy: HeaderPolicy) -> Result<Self>
+    where
+        I: IntoIterator,
+        I::Item: Serialize,
+    {
+        let rows = rows.into_iter();
+        let mut table = Table {
+            headers: Vec::new(),
+            rows: Vec::with_capacity(rows.size_hint().0),
+        };
+        let values = ValueSerializer::new();
+        for row in rows {
+            row.serialize(RowSerializer::new(&mut table, policy, &values))?;
+        }
+        Ok(table)
+    }
+
+    /// Returns the column headers.
+    #[must_use]
+    pub fn headers(&self) -> &[Key] {
+        &self.headers
+    }
+
+    /// Returns the rows as lists of cells, in header order.
+    #[must_use]
+    pub fn rows(&self) -> &[Vec<Value>] {
+        &self.rows
+    }
+
+    /// Consumes the table, returning its headers and rows.
+    #[must_use]
+    pub fn into_parts(self) -> (Vec<Key>, Vec<Vec<Value>>) {
+        (self.headers, self.rows)
+    }
+
+    /// Returns the number of rows.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns `true` if the table has no rows.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Returns the cells of the column named `name`, top to bottom.
+    #[must_use]
+    pub fn column(&self, name: &str) -> Option<Vec<&Value>> {
+        let index = self.column_index(name)?;
+        Some(self.rows.iter().map(|row| &row[index]).collect())
+    }
+
+    /// Returns row `index` as an object keyed by the headers.
+    #[must_use]
+    pub fn row(&self, index: usize) -> Option<ToonMap> {
+        self.rows.get(index).map(|row| self.row_object(row))
+    }
+
+    /// Iterates over the rows as objects keyed by the headers.
+    pub fn iter_rows(&self) -> impl Iterator<Item = ToonMap> + '_ {
+        self.rows.iter().map(move |row| self.row_object(row))
+    }
+
+    /// Appends a row given as a tuple, array or vector of cells.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the row does not have exactly one cell per header.
+    pub fn push_row(&mut self, row: impl IntoRow) -> Result<()> {
+        let row = row.into_row();
+        if row.len() != self.headers.len() {
+            return Err(Error::custom(format!(
+                "table row has {} cells but the table has {} columns",
+                row.len(),
+                self.headers.len()
+            )));
+        }
+        self.rows.push(row);
+        Ok(())
+    }
+
+    /// Appends a column, taking one cell per row from `values`.
+    ///
+    /// Rows left without a value get `null`; values beyond the last row are
+    /// ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the table already has a column named `name`.
+    pub fn add_column<I>(&mut self, name: impl Into<Key>, values: I) -> Result<()>
+    where
+        I: IntoIterator,
+        I::Item: Into<Value>,
+    {
+        let name = name.into();
+        if self.column_index(&name).is_some() {
+            return Err(Error::custom(format!(
+                "table already has a column named {}",
+                name
+            )));
+        }
+        let mut values = values.into_iter();
+        for row in &mut self.rows {
+            row.push(values.next().map_or(Value::Null, Into::into));
+        }
+        self.headers.push(name);
+        Ok(())
+    }
+
+    /// Returns a table with only the named columns, in the order given.
+    ///
+    /// Names that are not headers of this table are skipped.
+    #[must_use]
+    pub fn select_columns(&self, names: &[&str]) -> Table {
+        let indices: Vec<usize> = names
+            .iter()
+            .filter_map(|name| self.column_index(name))
+            .collect();
+        Table {
+            headers: indices.iter().map(|&i| self.headers[i].clone()).collect(),
+            rows: self
+                .rows
+                .iter()
+                .map(|row| indices.iter().map(|&i| row[i].clone()).collect())
+                .collect(),
+        }
+    }
+
+    /// Infers the type of every column, in header order.
+    ///
+    /// Null cells are ignored, and string cells count as the type their text
+    /// spells (`"42"` as an integer, `"2024-01-01T00:00:00Z"` as a date). A
+    /// column of integers and floats is [`ColumnType::Float`]; any other mix of
+    /// scalars is [`ColumnType::String`]. Columns holding arrays or objects are
+    /// [`ColumnType::Mixed`] and columns with only nulls are [`ColumnType::Null`].
+    #[must_use]
+    pub fn column_types(&self) -> Vec<(String, ColumnType)> {
+        self.headers
+            .iter()
+            .enumerate()
+            .map(|(index, header)| {
+                let column_type = self
+                    .rows
+                    .iter()
+                    .filter_map(|row| cell_type(&row[index]))
+                    .fold(ColumnType::Null, ColumnType::unify);
+                (header.to_string(), column_type)
+            })
+            .collect()
+    }
+
+    /// Converts the cells of each column named in `schema` to its type.
+    ///
+    /// Null cells stay null, and columns typed [`ColumnType::Null`] or
+    /// [`ColumnType::Mixed`] are left as they are. Floats with no fractional
+    /// part convert to integers, and any scalar converts to a string.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{toon_table, ColumnType, Table, Value};
+    ///
+    /// let mut table = Table::try_from(toon_table! {
+    ///     (id, active);
+    ///     (1, "true");
+    ///     ("2", false);
+    /// })
+    /// .unwrap();
+    /// table
+    ///     .coerce_columns(&[("id", ColumnType::Integer), ("active", ColumnType::Bool)])
+    ///     .unwrap();
+    /// assert_eq!(table.row(1).unwrap().get("id"), Some(&Value::from(2)));
+    /// assert_eq!(table.row(0).unwrap().get("active"), Some(&Value::Bool(true)));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `schema` names a column the table does not have, or
+    /// if a cell cannot be converted. The table is unchanged on error.
+    pub fn coerce_columns<S: AsRef<str>>(&mut self, schema: &[(S, ColumnType)]) -> Result<()> {
+        let mut columns = Vec::with_capacity(schema.len());
+        for (name, column_type) in schema {
+            let name = name.as_ref();
+            let index = self
+                .column_index(name)
+                .ok_or_else(|| Error::custom(format!("table has no column named {}", name)))?;
+            let cells = self
+                .rows
+                .iter()
+                .enumerate()
+                .map(|(row, cells)| {
+                    coerce(&cells[index], *column_type).ok_or_else(|| {
+                        Error::custom(format!(
+                            "cannot convert {} in column {}, row {} to {}",
+                            cells[index], name, row, column_type
+                        ))
+                    })
+                })
+                .collect::<Result<Vec<Value>>>()?;
+            columns.push((index, cells));
+        }
+        for (index, cells) in columns {
+            for (row, cell) in self.rows.iter_mut().zip(cells) {
+                row[index] = cell;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns every cell converted to `T`, row by row in header order.
+    ///
+    /// The result has `len() * headers().len()` elements, ready for a
+    /// row-major matrix type such as `ndarray::Array2::from_shape_vec`. Use
+    /// [`select_columns`](Self::select_columns) first to pick or reorder the
+    /// columns.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{from_str, Table, Value};
+    ///
+    /// let value: Value = from_str("[2]{x,y,z}:\n  1,2.5,3\n  4,5,6.5").unwrap();
+    /// let table = Table::try_from(value).unwrap();
+    /// let flat: Vec<f64> = table.to_flat_vec().unwrap();
+    /// assert_eq!(flat, [1.0, 2.5, 3.0, 4.0, 5.0, 6.5]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the column and row of the first cell that
+    /// cannot be converted.
+    pub fn to_flat_vec<T: DeserializeOwned>(&self) -> Result<Vec<T>> {
+        let mut flat = Vec::with_capacity(self.rows.len() * self.headers.len());
+        for (row, cells) in self.rows.iter().enumerate() {
+            for (header, cell) in self.headers.iter().zip(cells) {
+                flat.push(from_value(cell.clone()).map_err(|err| {
+                    Error::custom(format!(
+                        "cannot convert {} in column {}, row {}: {}",
+                        cell, header, row, err
+                    ))
+                })?);
+            }
+        }
+        Ok(flat)
+    }
+
+    fn column_index(&self, name: &str) -> Option<usize> {
+        self.headers.iter().position(|header| header == name)
+    }
+
+    fn row_object(&self, row: &[Value]) -> ToonMap {
+        self.headers
+            .iter()
+            .cloned()
+            .zip(row.iter().cloned())
+            .collect()
+    }
+}
+
+/// How [`Table::from_serialize_iter_with`] treats rows whose keys differ from
+/// the first row's.
+///
+/// - **Strict**: Reject the row (default)
+/// - **Union**: Add a column for every new key, with `null` in the rows
+///   without it, and `null` for keys a row lacks
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum HeaderPolicy {
+    #[default]
+    Strict,
+    Union,
+}
+
+/// The type of the cells in a table column, as found by
+/// [`Table::column_types`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColumnType {
+    /// Every cell is null.
+    Null,
+    /// Booleans.
+    Bool,
+    /// Integers.
+    Integer,
+    /// Floats, or a mix of floats and integers.
+    Float,
+    /// RFC 3339 dates. Requires the `datetime` feature.
+    #[cfg(feature = "datetime")]
+    Date,
+    /// Strings, or a mix of scalars with no common type.
+    String,
+    /// Arrays or objects, alone or alongside scalars.
+    Mixed,
+}
+
+impl ColumnType {
+    fn unify(self, other: ColumnType) -> ColumnType {
+        use ColumnType::*;
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Null, t) | (t, Null) => t,
+            (Mixed, _) | (_, Mixed) => Mixed,
+            (Integer, Float) | (Float, Integer) => Float,
+            _ => String,
+        }
+    }
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ColumnType::Null => "null",
+            ColumnType::Bool => "bool",
+            ColumnType::Integer => "integer",
+            ColumnType::Float => "float",
+            #[cfg(feature = "datetime")]
+            ColumnType::Date => "date",
+            ColumnType::String => "string",
+            ColumnType::Mixed => "mixed",
+        })
+    }
+}
+
+/// The type a cell counts as, or `None` for null.
+fn cell_type(cell: &Value) -> Option<ColumnType> {
+    Some(match cell {
+        Value::Null => return None,
+        Value::Bool(_) => ColumnType::Bool,
+        Value::Number(Number::Integer(_)) => ColumnType::Integer,
+        #[cfg(feature = "bigint")]
+        Value::BigInt(_) => ColumnType::Integer,
+        Value::Number(_) => ColumnType::Float,
+        #[cfg(feature = "datetime")]
+        Value::Date(_) => ColumnType::Date,
+        Value::String(text) => {
+            let text = text.trim();
+            if text == "true" || text == "false" {
+                ColumnType::Bool
+            } else if text.parse::<i64>().is_ok() {
+                ColumnType::Integer
+            } else if parse_float(text).is_some() {
+                ColumnType::Float
+            } else {
+                date_or_string(text)
+            }
+        }
+        Value::Array(_) | Value::Object(_) | Value::Table(_) => ColumnType::Mixed,
+    })
+}
+
+#[cfg(feature = "datetime")]
+fn date_or_string(text: &str) -> ColumnType {
+    if DateTime::parse_from_rfc3339(text).is_ok() {
+        ColumnType::Date
+    } else {
+        ColumnType::String
+    }
+}
+
+#[cfg(not(feature = "datetime"))]
+fn date_or_string(_text: &str) -> ColumnType {
+    ColumnType::String
+}
+
+/// Parses decimal float text, rejecting words like `inf` that `f64` accepts.
+fn parse_float(text: &str) -> Option<f64> {
+    if !text.bytes().any(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+fn coerce(cell: &Value, column_type: ColumnType) -> Option<Value> {
+    let text = match cell {
+        Value::String(text) => Some(text.trim()),
+        _ => None,
+    };
+    let value = match (column_type, cell) {
+        (_, Value::Null) | (ColumnType::Null | ColumnType::Mixed, _) => cell.clone(),
+        (ColumnType::Bool, Value::Bool(_)) => cell.clone(),
+        (ColumnType::Bool, _) => Value::Bool(text?.parse().ok()?),
+        (ColumnType::Integer, Value::Number(Number::Integer(_))) => cell.clone(),
+        #[cfg(feature = "bigint")]
+        (ColumnType::Integer, Value::BigInt(_)) => cell.clone(),
+        (ColumnType::Integer, Value::Number(Number::Float(f)))
+            if f.fract() == 0.0 && f.abs() < i64::MAX as f64 =>
+        {
+            Value::Number(Number::Integer(*f as i64))
+        }
+        (ColumnType::Integer, _) => Value::Number(Number::Integer(text?.parse().ok()?)),
+        (ColumnType::Float, Value::Number(Number::Integer(i))) => {
+            Value::Number(Number::Float(*i as f64))
+        }
+        (ColumnType::Float, Value::Number(_)) => cell.clone(),
+        (ColumnType::Float, _) => Value::Number(Number::Float(parse_float(text?)?)),
+        #[cfg(feature = "datetime")]
+        (ColumnType::Date, Value::Date(_)) => cell.clone(),
+        #[cfg(feature = "datetime")]
+        (ColumnType::Date, _) => Value::Date(
+            DateTime::parse_from_rfc3339(text?)
+                .ok()?
+                .with_timezone(&Utc),
+        ),
+        (ColumnType::String, Value::String(_)) => cell.clone(),
+        (ColumnType::String, Value::Bool(b)) => Value::String(b.to_string()),
+        (ColumnType::String, Value::Number(n)) => Value::String(n.to_string()),
+        #[cfg(feature = "bigint")]
+        (ColumnType::String, Value::BigInt(n)) => Value::String(n.to_string()),
+        #[cfg(feature = "datetime")]
+        (ColumnType::String, Value::Date(date)) => Value::String(date.to_rfc3339()),
+        (ColumnType::String, _) => return None,
+    };
+    Some(value)
+}
+
+impl From<Table> for Vec<ToonMap> {
+    fn from(table: Table) -> Self {
+        let Table { headers, rows } = table;
+        rows.into_iter()
+            .map(|row| headers.iter().cloned().zip(row).collect())
+            .collect()
+    }
+}
+
+/// Builds a table whose headers are every key of the rows, in order of first
+/// appearance. Rows missing a key get `null` in that column.
+impl From<Vec<ToonMap>> for Table {
+    fn from(objects: Vec<ToonMap>) -> Self {
+        let mut headers: Vec<Key> = Vec::new();
+        for object in &objects {
+            for key in object.keys() {
+                if !headers.contains(key) {
+                    headers.push(key.clone());
+                }
+            }
+        }
+        let rows = objects
+            .into_iter()
+            .map(|mut object| {
+                headers
+                    .iter()
+                    .map(|header| object.shift_remove(header).unwrap_or(Value::Null))
+                    .collect()
+            })
+            .collect();
+        Table { headers, rows }
+    }
+}
+
+/// Accepts a table, or an array of objects (which is how the parser returns
+/// tabular arrays), converted as by `From<Vec<ToonMap>>`.
+impl TryFrom<Value> for Table {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self> {
+        match value {
+            Value::Table(table) => Ok(table),
+            Value::Array(items) => items
+                .into_iter()
+                .map(|item| match item {
+                    Value::Object(object) => Ok(object),
+                    _ => Err(Error::custom("table rows must be objects")),
+                })
+                .collect::<Result<Vec<ToonMap>>>()
+                .map(Table::from),
+            _ => Err(Error::custom("expected a table or an array of objects")),
+        }
+    }
+}
+
+/// Serializes as a sequence of row objects, which TOON writes as a table.
+impl Serialize for Table {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use ser::SerializeSeq;
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for row in self.iter_rows() {
+            seq.serialize_element(&row)?;
+        }
+        seq.end()
+    }
+}
+
+impl From<Table> for Value {
+    fn from(table: Table) -> Self {
+        Value::Table(table)
+    }
+}
+
+/// Serializes a struct or map as a new row of a table, adding its cells by
+/// header instead of building an object first.
+struct RowSerializer<'a> {
+    table: &'a mut Table,
+    policy: HeaderPolicy,
+    values: &'a ValueSerializer,
+    cells: Vec<Option<Value>>,
+    // The column the next field most likely goes in, as rows of the same
+    // type list their fields in the same order
+    next: usize,
+    // The key of a map entry whose value comes next, or `None` to drop it
+    key: Option<String>,
+}
+
+impl<'a> RowSerializer<'a> {
+    fn new(table: &'a mut Table, policy: HeaderPolicy, values: &'a ValueSerializer) -> Self {
+        RowSerializer {
+            cells: vec![None; table.headers.len()],
+            table,
+            policy,
+            values,
+            next: 0,
+            key: None,
+        }
+    }
+
+    fn not_an_object(&self, found: &str) -> Error {
+        Error::custom(format!(
+            "table row {} must be a struct or map, found {}",
+            self.table.rows.len(),
+            found
+        ))
+    }
+
+    /// Puts `value` in the column headed `key`, adding the column if the
+    /// policy allows.
+    fn insert<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> Result<()> {
+        let value = value.serialize(self.values.clone())?;
+        let headers = &self.table.headers;
+        let column = if headers.get(self.next).is_some_and(|header| header == key) {
+            self.next
+        } else {
+            match headers.iter().position(|header| header == key) {
+                Some(column) => column,
+                None => self.add_column(key)?,
+            }
+        };
+        self.cells[column] = Some(value);
+        self.next = column + 1;
+        Ok(())
+    }
+
+    fn add_column(&mut self, key: &str) -> Result<usize> {
+        let table = &mut *self.table;
+        if !table.rows.is_empty() && self.policy == HeaderPolicy::Strict {
+            return Err(Error::custom(format!(
+                "table row {} has a field `{}` that the first row does not",
+                table.rows.len(),
+                key
+            )));
+        }
+        for row in &mut table.rows {
+            row.push(Value::Null);
+        }
+        table.headers.push(Key::from(key));
+        self.cells.push(None);
+        Ok(table.headers.len() - 1)
+    }
+
+    fn finish(self) -> Result<()> {
+        let RowSerializer {
+            table,
+            policy,
+            cells,
+            ..
+        } = self;
+        let row = cells
+            .into_iter()
+            .zip(&table.headers)
+            .map(|(cell, header)| match (cell, policy) {
+                (Some(cell), _) => Ok(cell),
+                (None, HeaderPolicy::Union) => Ok(Value::Null),
+                (None, HeaderPolicy::Strict) => Err(Error::custom(format!(
+                    "table row {} has no field `{}`",
+                    table.rows.len(),
+                    header
+                ))),
+            })
+            .collect::<Result<Vec<Value>>>()?;
+        table.rows.push(row);
+        Ok(())
+    }
+}
+
+macro_rules! reject_non_object {
+    ($($method:ident($($arg:ty),*) => $found:literal,)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<()> {
+                Err(self.not_an_object($found))
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for RowSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    reject_non_object! {
+        serialize_bool(bool) => "a boolean",
+        serialize_i8(i8) => "a number",
+        serialize_i16(i16) => "a number",
+        serialize_i32(i32) => "a number",
+        serialize_i64(i64) => "a number",
+        serialize_i128(i128) => "a number",
+        serialize_u8(u8) => "a number",
+        serialize_u16(u16) => "a number",
+        serialize_u32(u32) => "a number",
+        serialize_u64(u64) => "a number",
+        serialize_u128(u128) => "a number",
+        serialize_f32(f32) => "a number",
+        serialize_f64(f64) => "a number",
+        serialize_char(char) => "a string",
+        serialize_str(&str) => "a string",
+        serialize_bytes(&[u8]) => "bytes",
+        serialize_none() => "null",
+        serialize_unit() => "null",
+        serialize_unit_struct(&'static str) => "null",
+        serialize_unit_variant(&'static str, u32, &'static str) => "an enum variant",
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(self.not_an_object("an enum variant"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(self.not_an_object("an array"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(self.not_an_object("an array"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(self.not_an_object("an array"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(self.not_an_object("an enum variant"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(self.not_an_object("an enum variant"))
+    }
+}
+
+impl ser::SerializeStruct for RowSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeMap for RowSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.key = self.values.map_key(key)?;
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        match self.key.take() {
+            Some(key) => self.insert(&key, value),
+            None => Ok(()),
+        }
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toon;
+
+    #[test]
+    fn converts_to_and_from_objects() {
+        let objects = vec![
+            ToonMap::from_iter([("id", Value::from(1)), ("name", Value::from("A"))]),
+            ToonMap::from_iter([("id", Value::from(2)), ("tag", Value::from("x"))]),
+        ];
+        let table = Table::from(objects);
+        assert_eq!(table.headers(), ["id", "name", "tag"]);
+        assert_eq!(
+            table.rows()[1],
+            vec![Value::from(2), Value::Null, Value::from("x")]
+        );
+
+        let objects: Vec<ToonMap> = table.clone().into();
+        assert_eq!(objects.len(), 2);
+        assert_eq!(
+            Value::Object(objects[0].clone()),
+            toon!({"id": 1, "name": "A", "tag": null})
+        );
+        assert_eq!(table.row(0), Some(objects[0].clone()));
+        assert_eq!(table.iter_rows().collect::<Vec<_>>(), objects);
+
+        let parsed = crate::from_str::<Value>("[2]{id,name}:\n  1,A\n  2,B").unwrap();
+        assert_eq!(
+            Table::try_from(parsed).unwrap().column("id").unwrap().len(),
+            2
+        );
+        assert!(Table::try_from(toon!([1, 2])).is_err());
+    }
+
+    #[test]
+    fn builds_tables_from_serialized_rows() {
+        use serde::Serialize;
+
+        #[derive(Serialize)]
+        struct Row {
+            id: u32,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            note: Option<&'static str>,
+            tags: Vec<&'static str>,
+        }
+        let rows = [
+            Row {
+                id: 1,
+                note: Some("a"),
+                tags: vec!["x"],
+            },
+            Row {
+                id: 2,
+                note: Some("b"),
+                tags: vec![],
+            },
+        ];
+        let table = Table::from_serialize_iter(&rows).unwrap();
+        assert_eq!(table.headers(), ["id", "note", "tags"]);
+        assert_eq!(
+            table,
+            Table::try_from(crate::to_value(&rows).unwrap()).unwrap()
+        );
+
+        // Keys in another order still fill their columns
+        let objects = [toon!({"b": 1, "a": 2}), toon!({"a": 3, "b": 4})];
+        let table = Table::from_serialize_iter(&objects).unwrap();
+        assert_eq!(table.headers(), ["b", "a"]);
+        assert_eq!(table.column("a").unwrap(), vec![&toon!(2), &toon!(3)]);
+
+        // Differing keys are errors unless the headers are a union
+        let sparse = [
+            Row {
+                id: 1,
+                note: None,
+                tags: vec![],
+            },
+            Row {
+                id: 2,
+                note: Some("b"),
+                tags: vec![],
+            },
+            Row {
+                id: 3,
+                note: None,
+                tags: vec![],
+            },
+        ];
+        let err = Table::from_serialize_iter(&sparse).unwrap_err();
+        assert!(err.to_string().contains("table row 1 has a field `note`"));
+        let err =
+            Table::from_serialize_iter(&rows[..1].iter().chain(&sparse[..1]).collect::<Vec<_>>())
+                .unwrap_err();
+        assert!(err.to_string().contains("table row 1 has no field `note`"));
+        let table = Table::from_serialize_iter_with(&sparse, HeaderPolicy::Union).unwrap();
+        assert_eq!(table.headers(), ["id", "tags", "note"]);
+        assert_eq!(
+            table.column("note").unwrap(),
+            vec![&Value::Null, &toon!("b"), &Value::Null]
+        );
+
+        let err = Table::from_serialize_iter([Some(1)]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("table row 0 must be a struct or map, found a number"));
+        assert!(Table::from_serialize_iter(Vec::<Row>::new())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_rows_and_columns() {
+        let mut table = Table::new(["a", "b"]);
+        assert!(table.push_row((1,)).is_err());
+        assert!(table.push_row((1, 2)).is_ok());
+        assert!(table.add_column("a", [3]).is_err());
+        assert!(Table::from_parts(vec!["a".to_string()], vec![vec![]]).is_err());
+
+        table.add_column("c", Vec::<Value>::new()).unwrap();
+        assert_eq!(
+            table.rows()[0],
+            vec![Value::from(1), Value::from(2), Value::Null]
+        );
+        assert_eq!(
+            table.select_columns(&["c", "missing", "a"]).headers(),
+            ["c", "a"]
+        );
+        assert_eq!(table.column("missing"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "datetime")]
+    fn infers_column_types() {
+        let table = Table::from_parts(
+            ["n", "x", "flag", "when", "name", "tags", "empty"]
+                .map(String::from)
+                .to_vec(),
+            vec![
+                vec![
+                    toon!(1),
+                    toon!(1),
+                    toon!("true"),
+                    toon!("2024-05-01T10:00:00Z"),
+                    toon!("Ada"),
+                    toon!([1]),
+                    Value::Null,
+                ],
+                vec![
+                    toon!("2"),
+                    toon!("2.5"),
+                    toon!(false),
+                    Value::Null,
+                    toon!(42),
+                    toon!("x"),
+                    Value::Null,
+                ],
+            ],
+        )
+        .unwrap();
+        let types: Vec<ColumnType> = table.column_types().into_iter().map(|(_, t)| t).collect();
+        assert_eq!(
+            types,
+            [
+                ColumnType::Integer,
+                ColumnType::Float,
+                ColumnType::Bool,
+                ColumnType::Date,
+                ColumnType::String,
+                ColumnType::Mixed,
+                ColumnType::Null,
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "datetime")]
+    fn coerces_columns() {
+        let mut table = Table::from_parts(
+            vec!["n".to_string(), "s".to_string(), "d".to_string()],
+            vec![
+                vec![toon!("3"), toon!(1.5), toon!("2024-05-01T12:00:00+02:00")],
+                vec![toon!(4.0), toon!(true), Value::Null],
+            ],
+        )
+        .unwrap();
+        let schema = [
+            ("n", ColumnType::Integer),
+            ("s", ColumnType::String),
+            ("d", ColumnType::Date),
+        ];
+        table.coerce_columns(&schema).unwrap();
+        assert_eq!(table.column("n").unwrap(), vec![&toon!(3), &toon!(4)]);
+        assert_eq!(
+            table.column("s").unwrap(),
+            vec![&toon!("1.5"), &toon!("true")]
+        );
+        assert_eq!(
+            table.rows()[0][2].as_date().unwrap().to_rfc3339(),
+            "2024-05-01T10:00:00+00:00"
+        );
+
+        // Failures leave the table untouched
+        let before = table.clone();
+        let err = table
+            .coerce_columns(&[("n", ColumnType::Float), ("s", ColumnType::Integer)])
+            .unwrap_err();
+        assert!(err.to_string().contains("column s, row 0"));
+        assert_eq!(table, before);
+        assert!(table.coerce_columns(&[("zz", ColumnType::Bool)]).is_err());
+    }
+
+    #[test]
+    fn flattens_cells() {
+        let table = Table::from_parts(
+            vec!["a".to_string(), "b".to_string()],
+            vec![vec![toon!(1), toon!(2)], vec![toon!(3), toon!("x")]],
+        )
+        .unwrap();
+        assert_eq!(
+            table.select_columns(&["a"]).to_flat_vec::<u8>().unwrap(),
+            [1, 3]
+        );
+        let err = table.to_flat_vec::<u8>().unwrap_err();
+        assert!(err.to_string().contains("column b, row 1"));
+    }
+}
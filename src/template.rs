@@ -0,0 +1,278 @@
+//! Prompt templates with TOON placeholders.
+//!
+//! Splicing TOON into a prompt with `format!` goes wrong as soon as the
+//! placeholder is indented: only the first line of a multi-line document
+//! lands at the placeholder, and the rest start at column 0. A [`Template`]
+//! serializes each placeholder's value as TOON and indents every line of it
+//! to match its insertion point:
+//!
+//! - a placeholder alone on its line has each line of the value at the
+//!   placeholder's indentation
+//! - a placeholder after text, as in `Data: {{data}}`, takes a primitive
+//!   value inline; a multi-line value starts on the next line, one indent
+//!   level deeper than the line it follows
+//!
+//! Placeholders are written `{{name}}`, with optional spaces inside the braces,
+//! and names of letters, digits, `_` and `-`. Write `\{{` for a literal `{{`.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde_toon::template::Template;
+//! use serde_toon::{toon, ToonOptions};
+//!
+//! let template = Template::parse("Analyze these orders:\n  {{orders}}\nTask: {{task}}").unwrap();
+//! let vars = toon!({
+//!     "orders": [{"id": 1, "total": 9.5}, {"id": 2, "total": 12}],
+//!     "task": "find the largest",
+//! });
+//! let vars = vars.as_object().unwrap();
+//!
+//! assert_eq!(
+//!     template.render(vars, &ToonOptions::default()).unwrap(),
+//!     "Analyze these orders:\n  [2]{id,total}:\n    1,9.5\n    2,12\nTask: find the largest"
+//! );
+//! ```
+
+use crate::{Error, Result, ToonMap, ToonOptions};
+
+/// A parsed prompt template. See the [module documentation](self) for the
+/// syntax.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Placeholder {
+    name: String,
+    /// Position of the opening `{{`, for errors.
+    line: usize,
+    col: usize,
+    /// The leading whitespace of the placeholder's line.
+    indent: String,
+    /// Whether text precedes the placeholder on its line.
+    inline: bool,
+}
+
+impl Template {
+    /// Parses a template.
+    ///
+    /// # Errors
+    ///
+    /// Returns a syntax error for a `{{` without a closing `}}`, or a
+    /// placeholder whose name is empty or has characters other than letters,
+    /// digits, `_` and `-`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::template::Template;
+    ///
+    /// let template = Template::parse("Rows: {{ rows }}, again: {{rows}}").unwrap();
+    /// assert_eq!(template.placeholders().collect::<Vec<_>>(), ["rows", "rows"]);
+    ///
+    /// let err = Template::parse("Rows: {{rows").unwrap_err();
+    /// assert!(err.to_string().contains("line 1, column 7"));
+    /// ```
+    pub fn parse(source: &str) -> Result<Template> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut rest = source;
+        while let Some(found) = rest.find("{{") {
+            if rest[..found].ends_with('\\') {
+                text.push_str(&rest[..found - 1]);
+                text.push_str("{{");
+                rest = &rest[found + 2..];
+                continue;
+            }
+            text.push_str(&rest[..found]);
+            let offset = source.len() - rest.len() + found;
+            let (line, col) = position(source, offset);
+            let Some(end) = rest[found + 2..].find("}}") else {
+                return Err(Error::syntax(line, col, "Unterminated `{{` placeholder"));
+            };
+            let name = rest[found + 2..found + 2 + end].trim();
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+            {
+                return Err(Error::syntax(
+                    line,
+                    col,
+                    &format!("Invalid placeholder name `{}`", name),
+                ));
+            }
+
+            let before = &source[source[..offset].rfind('\n').map_or(0, |i| i + 1)..offset];
+            let indent = &before[..before.len() - before.trim_start().len()];
+            let placeholder = Placeholder {
+                name: name.to_string(),
+                line,
+                col,
+                indent: indent.to_string(),
+                inline: indent.len() < before.len(),
+            };
+            if !text.is_empty() {
+                parts.push(Part::Text(std::mem::take(&mut text)));
+            }
+            parts.push(Part::Placeholder(placeholder));
+            rest = &rest[found + 2 + end + 2..];
+        }
+        text.push_str(rest);
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template { parts })
+    }
+
+    /// Returns the names of the template's placeholders, in order and with
+    /// repeats.
+    pub fn placeholders(&self) -> impl Iterator<Item = &str> {
+        self.parts.iter().filter_map(|part| match part {
+            Part::Placeholder(placeholder) => Some(placeholder.name.as_str()),
+            Part::Text(_) => None,
+        })
+    }
+
+    /// Renders the template, writing each placeholder's value from `vars` as
+    /// TOON with `options`, indented to its insertion point.
+    ///
+    /// # Errors
+    ///
+    /// Returns a syntax error at the placeholder if `vars` has no value for
+    /// it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::template::Template;
+    /// use serde_toon::{toon, ToonOptions};
+    ///
+    /// let template = Template::parse("- item: {{item}}\n- count: {{count}}").unwrap();
+    /// let vars = toon!({"item": {"sku": "A1", "tags": ["new", "sale"]}, "count": 2});
+    /// let vars = vars.as_object().unwrap();
+    ///
+    /// assert_eq!(
+    ///     template.render(vars, &ToonOptions::default()).unwrap(),
+    ///     "- item:\n  sku: A1\n  tags: [2]: new,sale\n- count: 2"
+    /// );
+    /// let err = template.render(&Default::default(), &ToonOptions::default()).unwrap_err();
+    /// assert!(err.to_string().contains("Template variable `item` is not set"));
+    /// ```
+    pub fn render(&self, vars: &ToonMap, options: &ToonOptions) -> Result<String> {
+        let mut output = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => output.push_str(text),
+                Part::Placeholder(placeholder) => {
+                    let Some(value) = vars.get(&placeholder.name) else {
+                        return Err(Error::syntax(
+                            placeholder.line,
+                            placeholder.col,
+                            &format!("Template variable `{}` is not set", placeholder.name),
+                        ));
+                    };
+                    let toon = value.to_toon_string(options);
+                    write_indented(&mut output, &toon, placeholder, options.indent);
+                }
+            }
+        }
+        Ok(output)
+    }
+}
+
+/// Appends `toon` at a placeholder, indenting its lines after the first.
+fn write_indented(output: &mut String, toon: &str, placeholder: &Placeholder, indent: usize) {
+    let multiline = toon.contains('\n');
+    let prefix = if placeholder.inline && multiline {
+        // Start the value on a line of its own, nested under the text before it
+        let trimmed = output.trim_end_matches([' ', '\t']).len();
+        output.truncate(trimmed);
+        output.push('\n');
+        let prefix = format!("{}{}", placeholder.indent, " ".repeat(indent));
+        output.push_str(&prefix);
+        prefix
+    } else {
+        placeholder.indent.clone()
+    };
+    for (i, line) in toon.split('\n').enumerate() {
+        if i > 0 {
+            output.push('\n');
+            if !line.trim_end_matches('\r').is_empty() {
+                output.push_str(&prefix);
+            }
+        }
+        output.push_str(line);
+    }
+}
+
+/// Returns the 1-based line and column of a byte offset.
+fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let col = before[before.rfind('\n').map_or(0, |i| i + 1)..]
+        .chars()
+        .count()
+        + 1;
+    (line, col)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toon;
+
+    fn render(template: &str, vars: crate::Value) -> String {
+        Template::parse(template)
+            .unwrap()
+            .render(vars.as_object().unwrap(), &ToonOptions::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn indents_values_to_their_placeholders() {
+        let vars = toon!({"doc": {"a": 1, "b": {"c": [1, 2]}}, "n": 3, "s": "x y"});
+        assert_eq!(
+            render("<data>\n    {{doc}}\n</data>", vars.clone()),
+            "<data>\n    a: 1\n    b:\n      c: [2]: 1,2\n</data>"
+        );
+        assert_eq!(
+            render("  Input: {{doc}}\n  n={{n}}, s={{ s }}", vars.clone()),
+            "  Input:\n    a: 1\n    b:\n      c: [2]: 1,2\n  n=3, s=x y"
+        );
+        assert_eq!(
+            render("{{n}} {{doc}}", vars.clone()),
+            "3\n  a: 1\n  b:\n    c: [2]: 1,2"
+        );
+        assert_eq!(render("\\{{n}} {{n}}", vars), "{{n}} 3");
+    }
+
+    #[test]
+    fn reports_bad_placeholders_where_they_are() {
+        let err = Template::parse("ok\n  {{bad name}}").unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("Syntax error at line 2, column 3:"),
+            "{}",
+            err
+        );
+        assert!(err
+            .to_string()
+            .contains("Invalid placeholder name `bad name`"));
+        assert!(Template::parse("{{}}").is_err());
+
+        let template = Template::parse("a\nb {{missing}}").unwrap();
+        let err = template
+            .render(&ToonMap::new(), &ToonOptions::default())
+            .unwrap_err();
+        assert!(err.to_string().contains("line 2, column 3"), "{}", err);
+    }
+}
@@ -0,0 +1,312 @@
+//! Assertions for tests that check TOON output.
+//!
+//! Comparing serialized text with `assert_eq!` breaks whenever the output
+//! changes form without changing meaning: a field moves, a table is written
+//! as a list of objects, a delimiter or indent changes. [`assert_toon_eq!`]
+//! parses both sides instead and compares them as data, ignoring key order
+//! and how arrays of objects are written, then panics with a list of the
+//! paths that differ:
+//!
+//! ```text
+//! TOON documents differ at 2 paths (left != right):
+//!   users[1].name: Bob != Robert
+//!   users[1].email: (missing) != bob@example.com
+//! ```
+//!
+//! Each side may be TOON text (`&str` or `String`) or a [`Value`]. The
+//! differences themselves are available from [`differences`].
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde::Serialize;
+//! use serde_toon::{assert_toon_eq, to_string, toon};
+//!
+//! #[derive(Serialize)]
+//! struct User { id: u32, name: String }
+//!
+//! let users = vec![User { id: 1, name: "Ada".into() }, User { id: 2, name: "Bob".into() }];
+//! let output = to_string(&users).unwrap();
+//!
+//! assert_toon_eq!(output, "[2]:\n  - name: Ada\n    id: 1\n  - name: Bob\n    id: 2");
+//! assert_toon_eq!(output, toon!([{"id": 1, "name": "Ada"}, {"id": 2, "name": "Bob"}]));
+//! ```
+
+use crate::path::{Path, PathSegment};
+use crate::{from_str, Result, Value};
+use std::fmt;
+
+pub use crate::assert_toon_eq;
+
+/// Asserts that two TOON documents hold the same data.
+///
+/// Each side may be TOON text or a [`Value`](crate::Value). Object keys may
+/// be in any order, and tables equal the arrays of objects they hold. On a
+/// mismatch, the panic message lists every path at which the documents
+/// differ, after an optional message formatted as by `format!`.
+///
+/// # Panics
+///
+/// Panics if the documents differ, or if either side is not valid TOON.
+///
+/// # Examples
+///
+/// ```rust,should_panic
+/// use serde_toon::assert_toon_eq;
+///
+/// assert_toon_eq!("id: 1\ntags: [2]: a,b", "tags: [2]: a,c\nid: 1", "tags changed");
+/// ```
+#[macro_export]
+macro_rules! assert_toon_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::testing::assert_documents_eq(&$left, &$right, ::core::option::Option::None)
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        $crate::testing::assert_documents_eq(
+            &$left,
+            &$right,
+            ::core::option::Option::Some(::core::format_args!($($arg)+)),
+        )
+    };
+}
+
+/// Something that can be compared as a TOON document: TOON text, which is
+/// parsed, or a [`Value`].
+pub trait Document {
+    /// Returns the document as a value.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the text is not valid TOON.
+    fn to_document(&self) -> Result<Value>;
+}
+
+impl Document for str {
+    fn to_document(&self) -> Result<Value> {
+        from_str(self)
+    }
+}
+
+impl Document for String {
+    fn to_document(&self) -> Result<Value> {
+        from_str(self)
+    }
+}
+
+impl Document for Value {
+    fn to_document(&self) -> Result<Value> {
+        Ok(self.clone())
+    }
+}
+
+impl<T: Document + ?Sized> Document for &T {
+    fn to_document(&self) -> Result<Value> {
+        (**self).to_document()
+    }
+}
+
+/// A path at which two documents differ, and the value on each side, which
+/// is `None` where the path does not exist.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Difference {
+    pub path: Path,
+    pub left: Option<Value>,
+    pub right: Option<Value>,
+}
+
+/// Displays as `path: left != right`, with primitives written as TOON and
+/// objects and arrays summarized.
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} != {}",
+            self.path,
+            summary(self.left.as_ref()),
+            summary(self.right.as_ref())
+        )
+    }
+}
+
+fn summary(value: Option<&Value>) -> String {
+    match value {
+        None => "(missing)".to_string(),
+        Some(Value::Array(items)) => format!("an array of length {}", items.len()),
+        Some(Value::Table(table)) => format!("an array of length {}", table.len()),
+        Some(Value::Object(object)) => format!("an object of {}", plural(object.len(), "field")),
+        Some(value) => value.to_string(),
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" })
+}
+
+/// Returns the paths at which `left` and `right` differ, in the order of
+/// `left`'s fields followed by any only in `right`. Object keys may be in any
+/// order, and tables equal the arrays of objects they hold.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::testing::differences;
+/// use serde_toon::toon;
+///
+/// let left = toon!({"id": 1, "tags": ["a", "b"]});
+/// let right = toon!({"tags": ["a"], "id": 1, "new": true});
+/// let found: Vec<String> = differences(&left, &right).iter().map(ToString::to_string).collect();
+/// assert_eq!(found, ["tags[1]: b != (missing)", "new: (missing) != true"]);
+/// ```
+#[must_use]
+pub fn differences(left: &Value, right: &Value) -> Vec<Difference> {
+    let mut found = Vec::new();
+    compare(&mut Path::root(), left, right, &mut found);
+    found
+}
+
+fn compare(path: &mut Path, left: &Value, right: &Value, found: &mut Vec<Difference>) {
+    match (left, right) {
+        (Value::Table(_), _) | (_, Value::Table(_)) => {
+            compare(path, &untabled(left), &untabled(right), found);
+        }
+        (Value::Object(left), Value::Object(right)) => {
+            for (key, value) in left {
+                path.push(PathSegment::Key(key.to_string()));
+                match right.get(key) {
+                    Some(other) => compare(path, value, other, found),
+                    None => found.push(difference(path, Some(value), None)),
+                }
+                path.pop();
+            }
+            for (key, value) in right {
+                if !left.contains_key(key) {
+                    path.push(PathSegment::Key(key.to_string()));
+                    found.push(difference(path, None, Some(value)));
+                    path.pop();
+                }
+            }
+        }
+        (Value::Array(left), Value::Array(right)) => {
+            for index in 0..left.len().max(right.len()) {
+                path.push(PathSegment::Index(index));
+                match (left.get(index), right.get(index)) {
+                    (Some(value), Some(other)) => compare(path, value, other, found),
+                    (value, other) => found.push(difference(path, value, other)),
+                }
+                path.pop();
+            }
+        }
+        _ if left == right => {}
+        _ => found.push(difference(path, Some(left), Some(right))),
+    }
+}
+
+/// Returns a table as the array of objects it holds, and anything else as it
+/// is.
+fn untabled(value: &Value) -> Value {
+    match value {
+        Value::Table(table) => Value::Array(table.iter_rows().map(Value::Object).collect()),
+        _ => value.clone(),
+    }
+}
+
+fn difference(path: &Path, left: Option<&Value>, right: Option<&Value>) -> Difference {
+    Difference {
+        path: path.clone(),
+        left: left.cloned(),
+        right: right.cloned(),
+    }
+}
+
+/// Implements [`assert_toon_eq!`].
+#[doc(hidden)]
+#[track_caller]
+#[allow(clippy::panic)] // Panicking is how an assertion fails
+pub fn assert_documents_eq<L, R>(left: &L, right: &R, message: Option<fmt::Arguments<'_>>)
+where
+    L: Document + ?Sized,
+    R: Document + ?Sized,
+{
+    let parse = |side: &str, document: Result<Value>| {
+        document.unwrap_or_else(|e| panic!("{} side is not a valid TOON document: {}", side, e))
+    };
+    let left = parse("left", left.to_document());
+    let right = parse("right", right.to_document());
+    let found = differences(&left, &right);
+    if found.is_empty() {
+        return;
+    }
+
+    let mut report = String::new();
+    if let Some(message) = message {
+        report.push_str(&format!("{}\n", message));
+    }
+    report.push_str(&format!(
+        "TOON documents differ at {} (left != right):",
+        plural(found.len(), "path")
+    ));
+    for difference in &found {
+        report.push_str(&format!("\n  {}", difference));
+    }
+    panic!("{}", report);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{toon, toon_table};
+
+    #[test]
+    fn ignores_key_order_and_table_form() {
+        assert_toon_eq!("a: 1\nb: [2]: x,y", "b: [2]: x,y\na: 1");
+        assert_toon_eq!(
+            "[2]{id,name}:\n  1,Ada\n  2,Bob",
+            String::from("[2]:\n  - name: Ada\n    id: 1\n  - id: 2\n    name: Bob")
+        );
+        let table = toon_table! { (id, name); (1, "Ada"); (2, "Bob"); };
+        assert_toon_eq!(table, "[2]{name,id}:\n  Ada,1\n  Bob,2");
+        assert_toon_eq!(
+            &toon!({"rows": table}),
+            "rows[2]{id,name}:\n  1,Ada\n  2,Bob"
+        );
+    }
+
+    #[test]
+    fn reports_each_differing_path() {
+        let left = toon!({"user": {"name": "Bob", "id": 2}, "tags": ["a", "b"], "n": 1});
+        let right =
+            toon!({"tags": ["a"], "user": {"id": 2, "name": "Robert", "admin": false}, "n": "1"});
+        let found: Vec<String> = differences(&left, &right)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            found,
+            [
+                "user.name: Bob != Robert",
+                "user.admin: (missing) != false",
+                "tags[1]: b != (missing)",
+                "n: 1 != \"1\"",
+            ]
+        );
+        let found = differences(&toon!([{"a": 1}]), &toon!({"a": 1}));
+        assert_eq!(
+            found[0].to_string(),
+            "(root): an array of length 1 != an object of 1 field"
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "step 3\nTOON documents differ at 1 path (left != right):\n  id: 1 != 2"
+    )]
+    fn panics_with_message() {
+        assert_toon_eq!("id: 1", "id: 2", "step {}", 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "right side is not a valid TOON document")]
+    fn panics_on_invalid_documents() {
+        assert_toon_eq!("id: 1", "tags: [3]: a");
+    }
+}
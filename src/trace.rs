@@ -0,0 +1,117 @@
+//! Instrumentation with `tracing`, behind the `tracing` feature.
+//!
+//! The parsing and serializing functions at the crate root run inside a
+//! debug-level span, so a subscriber's span timings show where prompt building
+//! spends its time:
+//!
+//! - `parse`, with the input size in bytes as `bytes`, and `format = "json"`
+//!   for JSON read by `from_str_any` (with the `json` feature)
+//! - `serialize`, which ends with a debug-level `serialized` event giving the
+//!   output size as `bytes`, and with `format = "dual"` for
+//!   [`to_dual_strings`](crate::to_dual_strings), whose event also gives
+//!   `json_bytes`
+//!
+//! Every array read or written emits a trace-level event with its `format`
+//! (`table`, `inline` or `list`), its `len` and, for tables, its `columns`. A
+//! [`Deserializer`](crate::Deserializer) or [`Serializer`](crate::Serializer)
+//! used directly opens no span, but still emits the array events.
+//!
+//! Without the feature the macros here expand to nothing, and their
+//! arguments are not evaluated.
+
+/// Enters a debug-level span until the end of the enclosing block.
+macro_rules! span {
+    ($name:literal $(, $($field:tt)*)?) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!($name $(, $($field)*)?).entered();
+    };
+}
+
+/// Emits an event at a `tracing::Level`, such as `TRACE`.
+macro_rules! event {
+    ($level:ident, $($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::event!(tracing::Level::$level, $($arg)*);
+    };
+}
+
+pub(crate) use {event, span};
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use crate::{from_str, to_string, toon, Value};
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// Records each span and event as its name or message followed by its
+    /// fields.
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    struct Line(String);
+
+    impl Visit for Line {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            match field.name() {
+                "message" => self.0.insert_str(0, &format!("{:?}", value)),
+                name => self.0.push_str(&format!(" {}={:?}", name, value)),
+            }
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut line = Line(span.metadata().name().to_string());
+            span.record(&mut line);
+            self.0.lock().unwrap().push(line.0);
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut line = Line(String::new());
+            event.record(&mut line);
+            self.0.lock().unwrap().push(line.0.trim_start().to_string());
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn records_documents_and_array_formats() {
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let value = toon!({"users": [{"id": 1}, {"id": 2}], "tags": ["a"], "mixed": [1, [2]]});
+            let toon = to_string(&value).unwrap();
+            from_str::<Value>(&toon).unwrap();
+        });
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            [
+                "serialize",
+                "format=\"table\" len=2 columns=1",
+                "format=\"inline\" len=1",
+                "format=\"list\" len=2",
+                "format=\"inline\" len=1",
+                "serialized bytes=65",
+                "parse bytes=65",
+                "format=\"table\" len=2 columns=1",
+                "format=\"inline\" len=1",
+                "format=\"inline\" len=1",
+                "format=\"list\" len=2",
+            ]
+        );
+    }
+}
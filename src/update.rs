@@ -0,0 +1,368 @@
+//! Re-serializing edited documents with minimal changes.
+//!
+//! [`update_in_place`] writes a new value over an existing TOON document while
+//! copying every untouched part of it verbatim, so a file under version control
+//! only shows a diff where the data actually changed:
+//!
+//! - fields whose value is unchanged keep their exact text, including quoting
+//!   and spacing, and the blank lines after them; values are compared with
+//!   [`Value::canonical_eq`], so a table and the same rows as an array of
+//!   objects are unchanged
+//! - fields keep their original order; new fields are inserted after the field
+//!   that precedes them in the new value, and removed fields are dropped
+//! - changed nested objects are updated field by field
+//! - in changed tables, unchanged rows keep their text when the header (apart
+//!   from the row count) is written the same way as before
+//!
+//! Everything else that changed is written as [`crate::to_string`] would, using
+//! the document's indentation width.
+//!
+//! ## Examples
+//!
+//! ```rust
+//! use serde_toon::{from_str, update_in_place, Value};
+//!
+//! let original = "name:   \"Store\"\nproducts: [2]{id,price}:\n  1,9.99\n  2,5\nowner:\n  name: Ada";
+//! let mut value: Value = from_str(original).unwrap();
+//! if let Value::Object(fields) = &mut value {
+//!     fields.insert("open".to_string(), Value::Bool(true));
+//! }
+//!
+//! assert_eq!(
+//!     update_in_place(original, &value),
+//!     "name:   \"Store\"\nproducts: [2]{id,price}:\n  1,9.99\n  2,5\nowner:\n  name: Ada\nopen: true"
+//! );
+//! ```
+
+use crate::lexer;
+use crate::outline::outline;
+use crate::path::{Path, PathSegment};
+use crate::ser::{can_be_tabular, write_object};
+use crate::span::Spans;
+use crate::{from_str_spanned, Key, Table, ToonMap, ToonOptions, Value};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Serializes `new_value` in place of `original`, keeping the text of every
+/// part of `original` that `new_value` leaves unchanged.
+///
+/// If `original` is not valid TOON, `new_value` is written from scratch. If
+/// it uses `\r\n` line breaks, so does the result.
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{from_str, update_in_place, Value};
+///
+/// let original = "id: 7\n\nitems: [3]{sku,qty}:\n  A1, 2\n  B2, 0\n  C3, 1\n";
+/// let edited: Value = from_str("id: 7\nitems: [3]{sku,qty}:\n  A1,2\n  B2,5\n  C3,1").unwrap();
+///
+/// // Only the edited row is rewritten
+/// assert_eq!(
+///     update_in_place(original, &edited),
+///     "id: 7\n\nitems: [3]{sku,qty}:\n  A1, 2\n  B2,5\n  C3, 1\n"
+/// );
+/// ```
+#[must_use]
+pub fn update_in_place(original: &str, new_value: &Value) -> String {
+    // Documents with `\r\n` line breaks are updated as if written with `\n`,
+    // and keep `\r\n` throughout
+    if original.contains("\r\n") {
+        let updated = update_in_place(&original.replace("\r\n", "\n"), new_value);
+        return updated.replace('\n', "\r\n");
+    }
+    let Ok((old_value, spans)) = from_str_spanned(original) else {
+        return new_value.to_toon_string(&ToonOptions::default());
+    };
+    if old_value.canonical_eq(new_value) {
+        return original.to_string();
+    }
+
+    let document = Document::new(original, spans);
+    let root = Path::root();
+    let body = match (&old_value, new_value) {
+        (Value::Object(old), Value::Object(new)) if !old.is_empty() => {
+            document.object(&root, old, new, 0)
+        }
+        _ => {
+            let rendered = new_value.to_toon_string(&document.options);
+            document
+                .splice_rows(&root, &old_value, new_value, &rendered)
+                .unwrap_or(rendered)
+        }
+    };
+
+    let content = original.trim();
+    let leading = &original[..original.len() - original.trim_start().len()];
+    let trailing = &original[leading.len() + content.len()..];
+    format!("{}{}{}", leading, body, trailing)
+}
+
+/// The original document and what is known about where its values are.
+struct Document<'a> {
+    text: &'a str,
+    spans: Spans,
+    // Each field's text, from its key through its last nested line
+    blocks: HashMap<Path, Range<usize>>,
+    // Options for writing changed values, with the document's indentation
+    options: ToonOptions,
+}
+
+impl<'a> Document<'a> {
+    fn new(text: &'a str, spans: Spans) -> Self {
+        let blocks = outline(text)
+            .into_iter()
+            .map(|node| (node.path, node.span))
+            .collect();
+        let indent = lexer::lines(text)
+            .map(|line| line.indent)
+            .find(|&indent| indent > 0)
+            .unwrap_or(2);
+        Document {
+            text,
+            spans,
+            blocks,
+            options: ToonOptions::new().with_indent(indent),
+        }
+    }
+
+    fn line_start(&self, pos: usize) -> usize {
+        self.text[..pos].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    fn line_end(&self, pos: usize) -> usize {
+        self.text[pos..]
+            .find('\n')
+            .map_or(self.text.len(), |i| pos + i)
+    }
+
+    /// Writes the fields of the object at `path`, one level below `depth`'s
+    /// parent, without a trailing newline.
+    fn object(&self, path: &Path, old: &ToonMap, new: &ToonMap, depth: usize) -> String {
+        let order = merged_order(old, new);
+        let mut out = String::new();
+        for (i, (key, value)) in order.iter().enumerate() {
+            let mut child = path.clone();
+            child.push(PathSegment::Key(key.to_string()));
+            let block = self.blocks.get(&child).filter(|_| old.contains_key(key));
+
+            out.push_str(&self.field(&child, key, old.get(key), value, depth));
+            if i + 1 < order.len() {
+                out.push_str(block.map_or("\n", |block| self.gap_after(block)));
+            }
+        }
+        out
+    }
+
+    /// Writes one field of an object, reusing its original text where possible.
+    fn field(
+        &self,
+        path: &Path,
+        key: &str,
+        old: Option<&Value>,
+        new: &Value,
+        depth: usize,
+    ) -> String {
+        let mut rendered = String::new();
+        write_object(
+            &mut rendered,
+            &[(key.to_string(), new.clone())],
+            &self.options,
+            depth,
+        );
+
+        let (Some(old), Some(block)) = (old, self.blocks.get(path)) else {
+            return rendered;
+        };
+        let start = self.line_start(block.start);
+        if old.canonical_eq(new) {
+            return self.text[start..block.end].to_string();
+        }
+        match (old, new) {
+            (Value::Object(old_fields), Value::Object(new_fields))
+                if !old_fields.is_empty() && !new_fields.is_empty() =>
+            {
+                let key_line = &self.text[start..self.line_end(block.start)];
+                format!(
+                    "{}\n{}",
+                    key_line,
+                    self.object(path, old_fields, new_fields, depth + 1)
+                )
+            }
+            _ => self
+                .splice_rows(path, old, new, &rendered)
+                .unwrap_or(rendered),
+        }
+    }
+
+    /// Replaces the rows of a rendered table that are unchanged from `old` with
+    /// their original lines, provided the header is written as it was before.
+    fn splice_rows(&self, path: &Path, old: &Value, new: &Value, rendered: &str) -> Option<String> {
+        let (old_headers, old_rows) = table_rows(old)?;
+        let (new_headers, new_rows) = table_rows(new)?;
+        if old_headers != new_headers {
+            return None;
+        }
+
+        let mut lines: Vec<&str> = rendered.split('\n').collect();
+        let header = lines.len().checked_sub(new_rows.len() + 1)?;
+        let original_row = |index: usize| {
+            let mut row_path = path.clone();
+            row_path.push(PathSegment::Index(index));
+            let span = self.spans.get(&row_path)?;
+            Some(&self.text[self.line_start(span.start)..self.line_end(span.start)])
+        };
+
+        let mut first_row = path.clone();
+        first_row.push(PathSegment::Index(0));
+        let header_end = self
+            .line_start(self.spans.get(&first_row)?.start)
+            .checked_sub(1)?;
+        let original_header = &self.text[self.line_start(header_end)..header_end];
+        let expected_header = lines[header].replacen(
+            &format!("[{}", new_rows.len()),
+            &format!("[{}", old_rows.len()),
+            1,
+        );
+        if expected_header != original_header {
+            return None;
+        }
+
+        for (i, row) in new_rows.iter().enumerate() {
+            if old_rows.get(i) == Some(row) {
+                lines[header + 1 + i] = original_row(i)?;
+            }
+        }
+        Some(lines.join("\n"))
+    }
+
+    /// The whitespace that separated the field in `block` from the next one.
+    fn gap_after(&self, block: &Range<usize>) -> &'a str {
+        let text = self.text;
+        let rest = &text[block.end..];
+        let next = block.end + rest.len() - rest.trim_start().len();
+        let indent = block.start - self.line_start(block.start);
+        // After the last field of a nested object, the gap belongs to its parent
+        if next == text.len() || next - self.line_start(next) < indent {
+            return "\n";
+        }
+        let gap = &text[block.end..next];
+        gap.rfind('\n').map_or("\n", |i| &gap[..=i])
+    }
+}
+
+/// Original keys that remain, in their original order, with new keys inserted
+/// after the key preceding them in `new`.
+fn merged_order<'k>(old: &'k ToonMap, new: &'k ToonMap) -> Vec<(&'k str, &'k Value)> {
+    let mut order: Vec<(&str, &Value)> = old
+        .keys()
+        .filter_map(|key| new.get(key).map(|value| (key.as_str(), value)))
+        .collect();
+    let mut previous: Option<&str> = None;
+    for (key, value) in new.iter() {
+        if !old.contains_key(key) {
+            let at = previous
+                .and_then(|prev| order.iter().position(|(k, _)| *k == prev))
+                .map_or(0, |i| i + 1);
+            order.insert(at, (key, value));
+        }
+        previous = Some(key);
+    }
+    order
+}
+
+fn table_rows(value: &Value) -> Option<(Vec<Key>, Vec<Vec<Value>>)> {
+    match value {
+        Value::Table(Table { headers, rows }) => Some((headers.clone(), rows.clone())),
+        Value::Array(items) => can_be_tabular(items),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_str, toon};
+
+    fn update(original: &str, new: &str) -> String {
+        update_in_place(original, &from_str::<Value>(new).unwrap())
+    }
+
+    #[test]
+    fn keeps_unchanged_documents() {
+        let original = "a:  1\n\nb: \"x\"\n";
+        assert_eq!(update(original, "a: 1\nb: x"), original);
+    }
+
+    #[test]
+    fn rewrites_only_changed_fields() {
+        let original =
+            "title: \"Report\"\n\nowner:\n    name:  Ada\n    team: ops\nlimits: [2]: 1,2\n";
+        assert_eq!(
+            update(
+                original,
+                "title: Report\nowner:\n  name: Ada\n  team: dev\nlimits: [3]: 1,2,3"
+            ),
+            "title: \"Report\"\n\nowner:\n    name:  Ada\n    team: dev\nlimits: [3]: 1,2,3\n"
+        );
+    }
+
+    #[test]
+    fn adds_and_removes_fields() {
+        let original = "b: 2\nd: 4\nc: 3";
+        let new = toon!({"a": 0, "b": 2, "c": 3, "e": 5});
+        assert_eq!(update_in_place(original, &new), "a: 0\nb: 2\nc: 3\ne: 5");
+    }
+
+    #[test]
+    fn keeps_unchanged_table_rows() {
+        let original = "[3]{id,name}:\n  1, \"Alice\"\n  2, Bob\n  3, Carol";
+        assert_eq!(
+            update(
+                original,
+                "[4]{id,name}:\n  1,Alice\n  2,Robert\n  3,Carol\n  4,Dan"
+            ),
+            "[4]{id,name}:\n  1, \"Alice\"\n  2,Robert\n  3, Carol\n  4,Dan"
+        );
+
+        // A differently written header is rewritten along with every row
+        let original = "rows: [#2]{id}:\n  1\n  2";
+        assert_eq!(
+            update(original, "rows: [1]{id}:\n  1"),
+            "rows: [1]{id}:\n  1"
+        );
+    }
+
+    #[test]
+    fn keeps_unchanged_tables() {
+        let original = "rows[2]{a,b}:\n  1,2\n  3,4\nx: 1";
+        assert_eq!(
+            update(original, "rows[2]{a,b}:\n  1,2\n  3,4\nx: 1"),
+            original
+        );
+        assert_eq!(
+            update(original, "rows[2]{a,b}:\n  1,2\n  3,4\nx: 2"),
+            "rows[2]{a,b}:\n  1,2\n  3,4\nx: 2"
+        );
+        let new = toon!({"rows": [{"a": 1, "b": 2}, {"a": 3, "b": 4}], "x": 2});
+        assert_eq!(
+            update_in_place(original, &new),
+            "rows[2]{a,b}:\n  1,2\n  3,4\nx: 2"
+        );
+    }
+
+    #[test]
+    fn keeps_block_strings() {
+        let original = "note: |\n  x\n  y\nb: 1";
+        assert_eq!(
+            update_in_place(original, &toon!({"note": "x\ny", "b": 1, "zz": 1})),
+            "note: |\n  x\n  y\nb: 1\nzz: 1"
+        );
+        assert_eq!(update(original, "note: z\nb: 1"), "note: z\nb: 1");
+    }
+
+    #[test]
+    fn rewrites_invalid_documents() {
+        assert_eq!(update_in_place("a: \"open", &toon!({"a": 1})), "a: 1");
+    }
+}
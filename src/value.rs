@@ -69,11 +69,20 @@
 //! }
 //! ```
 
-use crate::ToonMap;
+use crate::de::parse_number;
+use crate::path::{Path, PathSegment};
+use crate::ser::{finish_lines, widen_f32, write_value};
+use crate::{Key, Table, ToonMap, ToonOptions};
+#[cfg(feature = "datetime")]
 use chrono::{DateTime, Utc};
+#[cfg(feature = "bigint")]
 use num_bigint::BigInt;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
 /// A dynamically-typed representation of any valid TOON value.
 ///
@@ -99,7 +108,21 @@ use std::fmt;
 /// assert!(num.is_number());
 /// assert!(text.is_string());
 /// ```
-#[derive(Clone, Debug, PartialEq, Default)]
+///
+/// Values are totally ordered and hashable, so they can be sorted and used in
+/// `HashSet`s and as `BTreeMap` keys. Values of different kinds sort in the
+/// order the variants are declared (null, booleans, numbers, strings, arrays,
+/// objects, tables, dates, big integers). Objects compare as their entries
+/// sorted by key, so, as with equality, field order does not matter.
+///
+/// ```rust
+/// use serde_toon::{toon, Value};
+/// use std::collections::BTreeSet;
+///
+/// let set: BTreeSet<Value> = [toon!("b"), toon!(2), toon!(null), toon!(1)].into();
+/// assert_eq!(set.into_iter().collect::<Vec<_>>(), [toon!(null), toon!(1), toon!(2), toon!("b")]);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
 pub enum Value {
     #[default]
     Null,
@@ -108,14 +131,28 @@ pub enum Value {
     String(String),
     Array(Vec<Value>),
     Object(ToonMap),
-    Table {
-        headers: Vec<String>,
-        rows: Vec<Vec<Value>>,
-    },
+    Table(Table),
+    /// An RFC 3339 date. Requires the `datetime` feature.
+    #[cfg(feature = "datetime")]
     Date(DateTime<Utc>),
+    /// An integer beyond the range of `i64`. Requires the `bigint` feature.
+    #[cfg(feature = "bigint")]
     BigInt(BigInt),
 }
 
+/// The newtype struct name under which [`Number::Raw`] serializes its text,
+/// and the single key of the map it deserializes from.
+pub(crate) const NUMBER_TOKEN: &str = "$serde_toon::private::Number";
+
+/// Like [`NUMBER_TOKEN`], for the text of a [`Number::Decimal`].
+pub(crate) const DECIMAL_TOKEN: &str = "$serde_toon::private::Decimal";
+
+/// The newtype struct name under which [`Value`] asks to be deserialized,
+/// so that TOON deserializers can hand over decimals as decimals rather than
+/// as the strings other types read them from.
+#[cfg(feature = "rust_decimal")]
+pub(crate) const VALUE_TOKEN: &str = "$serde_toon::private::Value";
+
 /// A numeric value that can be an integer, float, or JavaScript-style special value.
 ///
 /// TOON supports all standard numeric types plus JavaScript's special numeric values
@@ -135,13 +172,30 @@ pub enum Value {
 /// assert_eq!(float.as_f64(), 3.5);
 /// assert!(infinity.is_special());
 /// ```
-#[derive(Clone, Debug, PartialEq)]
+///
+/// Numbers are totally ordered by value, so they can be sorted and used as
+/// map keys. `NaN` equals itself and sorts after every other number, and
+/// `-0.0` equals `0.0`. Numbers of equal value but different variants, such
+/// as `Integer(1)` and `Float(1.0)`, are not equal and sort integers first;
+/// see [`Value::canonicalize`] to compare them by value alone. A
+/// [`Number::Raw`] compares as the number its text parses to.
+#[derive(Clone, Debug)]
 pub enum Number {
     Integer(i64),
     Float(f64),
     Infinity,
     NegativeInfinity,
     NaN,
+    /// A number kept as written in the input, such as `1.50` or `1e3`, and
+    /// written back out unchanged. Parsing produces these only with
+    /// [`ToonOptions::preserve_number_text`](crate::ToonOptions::preserve_number_text).
+    Raw(String),
+    /// An exact decimal, such as a price, written as its text with every
+    /// digit. Otherwise it behaves as the number its text reads as. Parsing
+    /// produces these for numbers with more digits than an `f64` keeps, such
+    /// as `1.2345678901234567890123`. Requires the `rust_decimal` feature.
+    #[cfg(feature = "rust_decimal")]
+    Decimal(rust_decimal::Decimal),
 }
 
 impl Number {
@@ -157,8 +211,8 @@ impl Number {
     /// ```
     #[inline]
     #[must_use]
-    pub const fn is_integer(&self) -> bool {
-        matches!(self, Number::Integer(_))
+    pub fn is_integer(&self) -> bool {
+        matches!(*self.resolved(), Number::Integer(_))
     }
 
     /// Returns `true` if this is a floating-point value.
@@ -170,11 +224,12 @@ impl Number {
     ///
     /// assert!(Number::Float(3.5).is_float());
     /// assert!(!Number::Integer(42).is_float());
+    /// assert!(Number::Raw("1.50".to_string()).is_float());
     /// ```
     #[inline]
     #[must_use]
-    pub const fn is_float(&self) -> bool {
-        matches!(self, Number::Float(_))
+    pub fn is_float(&self) -> bool {
+        matches!(*self.resolved(), Number::Float(_))
     }
 
     /// Returns `true` if this is a special value (Infinity, -Infinity, or NaN).
@@ -190,9 +245,9 @@ impl Number {
     /// ```
     #[inline]
     #[must_use]
-    pub const fn is_special(&self) -> bool {
+    pub fn is_special(&self) -> bool {
         matches!(
-            self,
+            *self.resolved(),
             Number::Infinity | Number::NegativeInfinity | Number::NaN
         )
     }
@@ -225,6 +280,9 @@ impl Number {
                     None
                 }
             }
+            Number::Raw(_) => self.resolved().as_i64(),
+            #[cfg(feature = "rust_decimal")]
+            Number::Decimal(_) => self.resolved().as_i64(),
             _ => None,
         }
     }
@@ -252,6 +310,148 @@ impl Number {
             Number::Infinity => f64::INFINITY,
             Number::NegativeInfinity => f64::NEG_INFINITY,
             Number::NaN => f64::NAN,
+            Number::Raw(_) => self.resolved().as_f64(),
+            #[cfg(feature = "rust_decimal")]
+            Number::Decimal(_) => self.resolved().as_f64(),
+        }
+    }
+
+    /// Returns the text of a number kept as written, or `None` for any
+    /// other number.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::Number;
+    ///
+    /// assert_eq!(Number::Raw("1.50".to_string()).as_raw(), Some("1.50"));
+    /// assert_eq!(Number::Float(1.5).as_raw(), None);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn as_raw(&self) -> Option<&str> {
+        match self {
+            Number::Raw(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Converts this number to an exact decimal if possible.
+    ///
+    /// Floats convert from their shortest text, so `14.99` becomes exactly
+    /// `14.99`, and numbers kept as written convert from their text with every
+    /// digit. Returns `None` for special values and numbers out of the
+    /// decimal range. Requires the `rust_decimal` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rust_decimal::Decimal;
+    /// use serde_toon::Number;
+    ///
+    /// let price: Decimal = "14.99".parse().unwrap();
+    /// assert_eq!(Number::Float(14.99).as_decimal(), Some(price));
+    /// assert_eq!(Number::Decimal(price).as_decimal(), Some(price));
+    /// assert_eq!(Number::Integer(7).as_decimal(), Some(Decimal::from(7)));
+    /// assert_eq!(Number::NaN.as_decimal(), None);
+    /// ```
+    #[cfg(feature = "rust_decimal")]
+    #[must_use]
+    pub fn as_decimal(&self) -> Option<rust_decimal::Decimal> {
+        use rust_decimal::Decimal;
+        match self {
+            Number::Integer(i) => Some(Decimal::from(*i)),
+            Number::Float(f) if f.is_finite() => parse_decimal(&f.to_string()),
+            Number::Raw(text) => parse_decimal(text),
+            Number::Decimal(d) => Some(*d),
+            _ => None,
+        }
+    }
+}
+
+/// Reads `text`, in plain or scientific notation, as an exact decimal.
+#[cfg(feature = "rust_decimal")]
+pub(crate) fn parse_decimal(text: &str) -> Option<rust_decimal::Decimal> {
+    use rust_decimal::Decimal;
+    text.parse::<Decimal>()
+        .or_else(|_| Decimal::from_scientific(text))
+        .ok()
+}
+
+impl Number {
+    /// The number a [`Number::Raw`] text parses to, or `NaN` if it is not a
+    /// number; for a [`Number::Decimal`], the number its text parses to; any
+    /// other number as it is.
+    pub(crate) fn resolved(&self) -> Cow<'_, Number> {
+        match self {
+            Number::Raw(text) => Cow::Owned(parse_number(text).unwrap_or(Number::NaN)),
+            #[cfg(feature = "rust_decimal")]
+            Number::Decimal(d) => Cow::Owned(parse_number(&d.to_string()).unwrap_or(Number::NaN)),
+            number => Cow::Borrowed(number),
+        }
+    }
+
+    /// The value used for ordering, with every NaN alike and `-0.0` as `0.0`.
+    fn order_value(&self) -> f64 {
+        let value = self.as_f64();
+        if value.is_nan() {
+            f64::NAN
+        } else if value == 0.0 {
+            0.0
+        } else {
+            value
+        }
+    }
+
+    /// Orders variants of equal value: integers, then floats, then the
+    /// special values.
+    fn variant_rank(&self) -> u8 {
+        match *self.resolved() {
+            Number::Integer(_) => 0,
+            Number::Float(_) => 1,
+            _ => 2,
+        }
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Number {}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Number {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.order_value()
+            .total_cmp(&other.order_value())
+            .then_with(|| self.variant_rank().cmp(&other.variant_rank()))
+            .then_with(|| match (self, other) {
+                // Distinct decimals beyond f64 precision
+                #[cfg(feature = "rust_decimal")]
+                (Number::Decimal(a), Number::Decimal(b)) => a.cmp(b),
+                _ => match (&*self.resolved(), &*other.resolved()) {
+                    // Distinct integers beyond f64 precision
+                    (Number::Integer(a), Number::Integer(b)) => a.cmp(b),
+                    _ => Ordering::Equal,
+                },
+            })
+    }
+}
+
+impl Hash for Number {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.variant_rank().hash(state);
+        match *self.resolved() {
+            Number::Integer(i) => i.hash(state),
+            _ => self.order_value().to_bits().hash(state),
         }
     }
 }
@@ -264,6 +464,9 @@ impl fmt::Display for Number {
             Number::Infinity => write!(f, "Infinity"),
             Number::NegativeInfinity => write!(f, "-Infinity"),
             Number::NaN => write!(f, "NaN"),
+            Number::Raw(text) => f.write_str(text),
+            #[cfg(feature = "rust_decimal")]
+            Number::Decimal(d) => write!(f, "{}", d),
         }
     }
 }
@@ -312,7 +515,7 @@ impl From<u32> for Number {
 
 impl From<f32> for Number {
     fn from(value: f32) -> Self {
-        Number::Float(value as f64)
+        Number::Float(widen_f32(value))
     }
 }
 
@@ -322,6 +525,13 @@ impl From<f64> for Number {
     }
 }
 
+#[cfg(feature = "rust_decimal")]
+impl From<rust_decimal::Decimal> for Number {
+    fn from(value: rust_decimal::Decimal) -> Self {
+        Number::Decimal(value)
+    }
+}
+
 impl Value {
     /// Returns `true` if the value is null.
     #[inline]
@@ -369,10 +579,11 @@ impl Value {
     #[inline]
     #[must_use]
     pub const fn is_table(&self) -> bool {
-        matches!(self, Value::Table { .. })
+        matches!(self, Value::Table(_))
     }
 
     /// Returns `true` if the value is a date.
+    #[cfg(feature = "datetime")]
     #[inline]
     #[must_use]
     pub const fn is_date(&self) -> bool {
@@ -380,6 +591,7 @@ impl Value {
     }
 
     /// Returns `true` if the value is a big integer.
+    #[cfg(feature = "bigint")]
     #[inline]
     #[must_use]
     pub const fn is_bigint(&self) -> bool {
@@ -444,6 +656,48 @@ impl Value {
         }
     }
 
+    /// If the value is a non-negative integer, a whole-number float or a big
+    /// integer that fits in a `u64`, returns it. Otherwise returns `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::Value;
+    ///
+    /// assert_eq!(Value::from(42).as_u64(), Some(42));
+    /// assert_eq!(Value::from(3.0).as_u64(), Some(3));
+    /// assert_eq!(Value::from(-1).as_u64(), None);
+    /// ```
+    #[must_use]
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::Number(n) => n.as_i64().and_then(|i| u64::try_from(i).ok()),
+            #[cfg(feature = "bigint")]
+            Value::BigInt(bi) => u64::try_from(bi).ok(),
+            _ => None,
+        }
+    }
+
+    /// If the value is a number, returns it as an `f64`. Otherwise returns `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{Number, Value};
+    ///
+    /// assert_eq!(Value::from(42).as_f64(), Some(42.0));
+    /// assert_eq!(Value::Number(Number::Infinity).as_f64(), Some(f64::INFINITY));
+    /// assert_eq!(Value::from("42").as_f64(), None);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(n.as_f64()),
+            _ => None,
+        }
+    }
+
     /// If the value is an array, returns a reference to it. Otherwise returns `None`.
     #[inline]
     #[must_use]
@@ -454,6 +708,26 @@ impl Value {
         }
     }
 
+    /// If the value is an array, returns a mutable reference to it. Otherwise returns `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::toon;
+    ///
+    /// let mut value = toon!([1, 2]);
+    /// value.as_array_mut().unwrap().push(3.into());
+    /// assert_eq!(value, toon!([1, 2, 3]));
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
     /// If the value is an object, returns a reference to it. Otherwise returns `None`.
     #[inline]
     #[must_use]
@@ -464,7 +738,38 @@ impl Value {
         }
     }
 
+    /// If the value is an object, returns a mutable reference to it. Otherwise returns `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::toon;
+    ///
+    /// let mut value = toon!({"id": 1});
+    /// value.as_object_mut().unwrap().insert("name", "Ada".into());
+    /// assert_eq!(value, toon!({"id": 1, "name": "Ada"}));
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn as_object_mut(&mut self) -> Option<&mut ToonMap> {
+        match self {
+            Value::Object(obj) => Some(obj),
+            _ => None,
+        }
+    }
+
+    /// If the value is a table, returns a reference to it. Otherwise returns `None`.
+    #[inline]
+    #[must_use]
+    pub fn as_table(&self) -> Option<&Table> {
+        match self {
+            Value::Table(table) => Some(table),
+            _ => None,
+        }
+    }
+
     /// If the value is a date, returns a reference to it. Otherwise returns `None`.
+    #[cfg(feature = "datetime")]
     #[inline]
     #[must_use]
     pub fn as_date(&self) -> Option<&DateTime<Utc>> {
@@ -475,6 +780,7 @@ impl Value {
     }
 
     /// If the value is a big integer, returns a reference to it. Otherwise returns `None`.
+    #[cfg(feature = "bigint")]
     #[inline]
     #[must_use]
     pub fn as_bigint(&self) -> Option<&BigInt> {
@@ -484,6 +790,79 @@ impl Value {
         }
     }
 
+    /// If the value is a number, or a string holding one, returns it as an
+    /// exact decimal; see [`Number::as_decimal`]. Otherwise returns `None`.
+    ///
+    /// Strings are read too since `rust_decimal::Decimal` serializes as one,
+    /// which is quoted. Requires the `rust_decimal` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rust_decimal::Decimal;
+    /// use serde_toon::{from_str, to_string, Value};
+    ///
+    /// let price: Decimal = "14.990000000000000000000000001".parse().unwrap();
+    /// assert_eq!(to_string(&price).unwrap(), "\"14.990000000000000000000000001\"");
+    ///
+    /// let value: Value = from_str("\"14.990000000000000000000000001\"").unwrap();
+    /// assert_eq!(value.as_decimal(), Some(price));
+    /// assert_eq!(Value::from(price).to_string(), "14.990000000000000000000000001");
+    /// ```
+    #[cfg(feature = "rust_decimal")]
+    #[must_use]
+    pub fn as_decimal(&self) -> Option<rust_decimal::Decimal> {
+        match self {
+            Value::Number(n) => n.as_decimal(),
+            Value::String(s) => parse_decimal(s),
+            _ => None,
+        }
+    }
+
+    /// If the value is a string holding a UUID, in any form
+    /// [`uuid::Uuid::parse_str`] accepts, returns it. Otherwise returns `None`.
+    ///
+    /// UUIDs are written as hyphenated strings, which are never quoted.
+    /// Requires the `uuid` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{from_str, Value};
+    ///
+    /// let value: Value = from_str("550e8400-e29b-41d4-a716-446655440000").unwrap();
+    /// let id = value.as_uuid().unwrap();
+    /// assert_eq!(Value::from(id), value);
+    /// assert_eq!(Value::from("not a uuid").as_uuid(), None);
+    /// ```
+    #[cfg(feature = "uuid")]
+    #[must_use]
+    pub fn as_uuid(&self) -> Option<uuid::Uuid> {
+        self.as_str().and_then(|s| uuid::Uuid::parse_str(s).ok())
+    }
+
+    /// If the value is a string holding an absolute URL, returns it parsed.
+    /// Otherwise returns `None`.
+    ///
+    /// URLs are written as strings, quoted since they contain `:`. Requires
+    /// the `url` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{to_string, Value};
+    ///
+    /// let url = url::Url::parse("https://example.com/hook?id=1").unwrap();
+    /// assert_eq!(to_string(&url).unwrap(), "\"https://example.com/hook?id=1\"");
+    /// assert_eq!(Value::from(url.clone()).as_url(), Some(url));
+    /// assert_eq!(Value::from("/relative").as_url(), None);
+    /// ```
+    #[cfg(feature = "url")]
+    #[must_use]
+    pub fn as_url(&self) -> Option<url::Url> {
+        self.as_str().and_then(|s| url::Url::parse(s).ok())
+    }
+
     #[inline]
     pub fn needs_quotes(&self) -> bool {
         match self {
@@ -504,39 +883,381 @@ impl Value {
             _ => false,
         }
     }
-}
 
-impl fmt::Display for Value {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    /// Renders the value as a TOON document.
+    ///
+    /// This gives the same text as [`to_string_with_options`](crate::to_string_with_options)
+    /// but cannot fail, since every `Value` is representable.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{toon, Delimiter, ToonOptions};
+    ///
+    /// let value = toon!({"users": [{"id": 1, "name": "Ada"}, {"id": 2, "name": "Bob"}]});
+    /// let options = ToonOptions::new().with_delimiter(Delimiter::Pipe);
+    /// assert_eq!(
+    ///     value.to_toon_string(&options),
+    ///     "users: [2|]{id|name}:\n  1|Ada\n  2|Bob"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn to_toon_string(&self, options: &ToonOptions) -> String {
+        let mut output = String::new();
+        write_value(&mut output, self, options, 0);
+        finish_lines(&mut output, options);
+        output
+    }
+
+    /// Rewrites the value into a canonical form, so that values holding the
+    /// same data compare equal:
+    ///
+    /// - object keys are sorted
+    /// - tables become arrays of objects
+    /// - whole floats (`1.0`, `-0.0`) and big integers that fit in an `i64`
+    ///   become integers, and non-finite floats become `Infinity`,
+    ///   `-Infinity` or `NaN`
+    /// - numbers kept as written and decimals become the number their text
+    ///   parses to
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{toon, toon_table, Value};
+    ///
+    /// let mut table = toon_table!((id, score); (1, 2.0));
+    /// table.canonicalize();
+    /// assert_eq!(table, toon!([{"id": 1, "score": 2}]));
+    /// ```
+    pub fn canonicalize(&mut self) {
         match self {
-            Value::Null => write!(f, "null"),
-            Value::Bool(b) => write!(f, "{}", b),
-            Value::Number(n) => write!(f, "{}", n),
-            Value::String(s) => {
-                if self.needs_quotes() {
-                    write!(f, "\"{}\"", s.replace('"', "\\\""))
-                } else {
-                    write!(f, "{}", s)
+            Value::Number(n) => {
+                if let Cow::Owned(resolved) = n.resolved() {
+                    *n = resolved;
+                }
+                if let Number::Float(f) = *n {
+                    *n = if f.is_nan() {
+                        Number::NaN
+                    } else if f == f64::INFINITY {
+                        Number::Infinity
+                    } else if f == f64::NEG_INFINITY {
+                        Number::NegativeInfinity
+                    } else {
+                        match n.as_i64() {
+                            Some(i) => Number::Integer(i),
+                            None => Number::Float(f),
+                        }
+                    };
                 }
             }
-            Value::Array(arr) => {
-                write!(
-                    f,
-                    "[{}]",
-                    arr.iter()
-                        .map(|v| v.to_string())
-                        .collect::<Vec<_>>()
-                        .join(",")
-                )
+            #[cfg(feature = "bigint")]
+            Value::BigInt(bi) => {
+                if let Ok(i) = i64::try_from(&*bi) {
+                    *self = Value::Number(Number::Integer(i));
+                }
             }
-            Value::Object(_) => write!(f, "{{object}}"),
-            Value::Table { headers, rows } => {
-                write!(f, "Table[{}]{{{}}}", rows.len(), headers.join(","))
+            Value::Array(items) => items.iter_mut().for_each(Value::canonicalize),
+            Value::Object(map) => {
+                map.iter_mut().for_each(|(_, v)| v.canonicalize());
+                map.sort_keys();
             }
-            Value::Date(dt) => write!(f, "{}", dt.to_rfc3339()),
-            Value::BigInt(bi) => write!(f, "{}n", bi),
+            Value::Table(table) => {
+                let mut rows = Value::Array(
+                    Vec::<ToonMap>::from(std::mem::take(table))
+                        .into_iter()
+                        .map(Value::Object)
+                        .collect(),
+                );
+                rows.canonicalize();
+                *self = rows;
+            }
+            Value::Null | Value::Bool(_) | Value::String(_) => {}
+            #[cfg(feature = "datetime")]
+            Value::Date(_) => {}
         }
     }
+
+    /// Returns `true` if the two values hold the same data, comparing their
+    /// [canonical forms](Self::canonicalize).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{from_str, toon, Value};
+    ///
+    /// let parsed: Value = from_str("total: 3\nitems: [1]{sku}:\n  A1").unwrap();
+    /// let built = toon!({"items": [{"sku": "A1"}], "total": 3.0});
+    /// assert_ne!(parsed, built);
+    /// assert!(parsed.canonical_eq(&built));
+    /// ```
+    #[must_use]
+    pub fn canonical_eq(&self, other: &Value) -> bool {
+        let (mut a, mut b) = (self.clone(), other.clone());
+        a.canonicalize();
+        b.canonicalize();
+        a == b
+    }
+
+    /// Writes the [canonical form](Self::canonicalize) of the value as a
+    /// deterministic TOON document: keys sorted, comma delimiters and two-space
+    /// indentation, whatever options the value was parsed or built with.
+    ///
+    /// Two values give the same string exactly when they are
+    /// [`canonical_eq`](Self::canonical_eq), so the string can serve as a cache
+    /// key or be hashed with any digest.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{from_str, toon, Value};
+    ///
+    /// let parsed: Value = from_str("total: 3\nitems: [1]{sku}:\n  A1").unwrap();
+    /// let built = toon!({"items": [{"sku": "A1"}], "total": 3.0});
+    /// assert_eq!(parsed.canonical_string(), "items: [1]{sku}:\n  A1\ntotal: 3");
+    /// assert_eq!(parsed.canonical_string(), built.canonical_string());
+    /// ```
+    #[must_use]
+    pub fn canonical_string(&self) -> String {
+        let mut canonical = self.clone();
+        canonical.canonicalize();
+        let options = ToonOptions::new()
+            .with_delimiter(crate::Delimiter::Comma)
+            .with_indent(2)
+            .with_canonical_key_order(crate::KeyOrder::Alphabetical);
+        canonical.to_toon_string(&options)
+    }
+
+    /// Returns a 64-bit digest of the value's data, for deduplicating equal
+    /// results in a cache.
+    ///
+    /// The digest is the FNV-1a hash of [`canonical_string`](Self::canonical_string),
+    /// so it does not depend on key order or on whether rows are held as a
+    /// table or as objects, and it is the same on every platform and run
+    /// (unlike [`Hash`], whose output depends on the hasher).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{toon, toon_table};
+    ///
+    /// let table = toon_table!((id, name); (1, "Ada"); (2, "Bob"));
+    /// let objects = toon!([{"name": "Ada", "id": 1}, {"name": "Bob", "id": 2}]);
+    /// assert_eq!(table.structural_hash(), objects.structural_hash());
+    /// assert_ne!(table.structural_hash(), toon!([{"id": 1, "name": "Ada"}]).structural_hash());
+    /// ```
+    #[must_use]
+    pub fn structural_hash(&self) -> u64 {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0000_0100_0000_01b3;
+        self.canonical_string()
+            .bytes()
+            .fold(OFFSET_BASIS, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+            })
+    }
+
+    /// Calls `visit` with every value inside this one and its [`Path`],
+    /// starting with this value at the root path and visiting each container
+    /// before what it holds.
+    ///
+    /// The cells of a table are visited at the path of their row and column
+    /// (`users[1].name`), as if the table were an array of objects.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::{toon, Value};
+    ///
+    /// let value = toon!({"users": [{"id": 1, "email": "a@x.io"}, {"id": 2, "email": null}]});
+    /// let mut emails = Vec::new();
+    /// value.walk(|path, value| {
+    ///     if path.to_string().ends_with(".email") && !value.is_null() {
+    ///         emails.push(path.to_string());
+    ///     }
+    /// });
+    /// assert_eq!(emails, ["users[0].email"]);
+    /// ```
+    pub fn walk<F>(&self, mut visit: F)
+    where
+        F: FnMut(&Path, &Value),
+    {
+        self.walk_at(&mut Path::root(), &mut visit);
+    }
+
+    fn walk_at<F>(&self, path: &mut Path, visit: &mut F)
+    where
+        F: FnMut(&Path, &Value),
+    {
+        visit(path, self);
+        match self {
+            Value::Array(items) => {
+                for (index, item) in items.iter().enumerate() {
+                    path.push(PathSegment::Index(index));
+                    item.walk_at(path, visit);
+                    path.pop();
+                }
+            }
+            Value::Object(map) => {
+                for (key, value) in map.iter() {
+                    path.push(PathSegment::Key(key.to_string()));
+                    value.walk_at(path, visit);
+                    path.pop();
+                }
+            }
+            Value::Table(table) => {
+                for (index, row) in table.rows.iter().enumerate() {
+                    path.push(PathSegment::Index(index));
+                    for (header, cell) in table.headers.iter().zip(row) {
+                        path.push(PathSegment::Key(header.to_string()));
+                        cell.walk_at(path, visit);
+                        path.pop();
+                    }
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Rebuilds the value by passing every value inside it, with its
+    /// [`Path`], through `f` and keeping what `f` returns.
+    ///
+    /// The contents of a container are transformed before the container
+    /// itself, so `f` sees an object or array with its new contents and may
+    /// still drop or replace entries. Table cells are passed at the path of
+    /// their row and column, as for [`Self::walk`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde_toon::path::PathSegment;
+    /// use serde_toon::{toon, Value};
+    ///
+    /// // Redact emails and drop phone numbers before sending data to a model
+    /// let value = toon!({"users": [{"id": 1, "email": "a@x.io", "phone": "555-0100"}]});
+    /// let redacted = value.transform(|path, value| match path.segments().last() {
+    ///     Some(PathSegment::Key(key)) if key == "email" => Value::from("[redacted]"),
+    ///     _ => match value {
+    ///         Value::Object(mut map) => {
+    ///             map.shift_remove("phone");
+    ///             Value::Object(map)
+    ///         }
+    ///         other => other,
+    ///     },
+    /// });
+    /// assert_eq!(redacted, toon!({"users": [{"id": 1, "email": "[redacted]"}]}));
+    /// ```
+    #[must_use]
+    pub fn transform<F>(self, mut f: F) -> Value
+    where
+        F: FnMut(&Path, Value) -> Value,
+    {
+        self.transform_at(&mut Path::root(), &mut f)
+    }
+
+    fn transform_at<F>(mut self, path: &mut Path, f: &mut F) -> Value
+    where
+        F: FnMut(&Path, Value) -> Value,
+    {
+        match &mut self {
+            Value::Array(items) => {
+                for (index, item) in items.iter_mut().enumerate() {
+                    path.push(PathSegment::Index(index));
+                    *item = std::mem::take(item).transform_at(path, f);
+                    path.pop();
+                }
+            }
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    path.push(PathSegment::Key(key.to_string()));
+                    *value = std::mem::take(value).transform_at(path, f);
+                    path.pop();
+                }
+            }
+            Value::Table(table) => {
+                for (index, row) in table.rows.iter_mut().enumerate() {
+                    path.push(PathSegment::Index(index));
+                    for (header, cell) in table.headers.iter().zip(row.iter_mut()) {
+                        path.push(PathSegment::Key(header.to_string()));
+                        *cell = std::mem::take(cell).transform_at(path, f);
+                        path.pop();
+                    }
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+        f(path, self)
+    }
+
+    /// Reads an unquoted cell of a foreign table format (markdown, CSV):
+    /// empty and `null` are null, `true`/`false` are booleans, plain decimal
+    /// numbers are numbers and anything else is a string.
+    pub(crate) fn from_cell_text(text: String) -> Value {
+        match text.as_str() {
+            "" | "null" => return Value::Null,
+            "true" => return Value::Bool(true),
+            "false" => return Value::Bool(false),
+            _ => {}
+        }
+        let numeric = text
+            .chars()
+            .all(|ch| ch.is_ascii_digit() || matches!(ch, '-' | '+' | '.' | 'e' | 'E'))
+            && text.chars().any(|ch| ch.is_ascii_digit());
+        if numeric {
+            if let Ok(i) = text.parse::<i64>() {
+                return Value::Number(Number::Integer(i));
+            }
+            if let Ok(f) = text.parse::<f64>() {
+                return Value::Number(Number::Float(f));
+            }
+        }
+        Value::String(text)
+    }
+}
+
+/// Formats the value as a TOON document with default options, or with
+/// [`ToonOptions::pretty`] for the alternate form (`{:#}`).
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::toon;
+///
+/// let value = toon!({"id": 1, "tags": ["a", "b"]});
+/// assert_eq!(value.to_string(), "id: 1\ntags: [2]: a,b");
+/// ```
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let options = if f.alternate() {
+            ToonOptions::pretty()
+        } else {
+            ToonOptions::default()
+        };
+        f.write_str(&self.to_toon_string(&options))
+    }
+}
+
+/// Parses a TOON document with default options.
+///
+/// The parser builds the value directly rather than through serde, so tables
+/// stay [`Value::Table`]s and each distinct key is allocated once and shared
+/// (see [`Key`]).
+///
+/// # Examples
+///
+/// ```rust
+/// use serde_toon::{toon, Value};
+///
+/// let value: Value = "id: 1\ntags: [2]: a,b".parse().unwrap();
+/// assert_eq!(value, toon!({"id": 1, "tags": ["a", "b"]}));
+/// ```
+impl std::str::FromStr for Value {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        crate::Deserializer::from_str(s).parse_root()
+    }
 }
 
 impl Serialize for Value {
@@ -552,6 +1273,14 @@ impl Serialize for Value {
             Value::Number(Number::Infinity) => serializer.serialize_f64(f64::INFINITY),
             Value::Number(Number::NegativeInfinity) => serializer.serialize_f64(f64::NEG_INFINITY),
             Value::Number(Number::NaN) => serializer.serialize_f64(f64::NAN),
+            // TOON serializers write the text as is; others see a string
+            Value::Number(Number::Raw(text)) => {
+                serializer.serialize_newtype_struct(NUMBER_TOKEN, text)
+            }
+            #[cfg(feature = "rust_decimal")]
+            Value::Number(Number::Decimal(d)) => {
+                serializer.serialize_newtype_struct(DECIMAL_TOKEN, &d.to_string())
+            }
             Value::String(s) => serializer.serialize_str(s),
             Value::Array(arr) => {
                 use serde::ser::SerializeSeq;
@@ -561,29 +1290,11 @@ impl Serialize for Value {
                 }
                 seq.end()
             }
-            Value::Object(obj) => {
-                use serde::ser::SerializeMap;
-                let mut map = serializer.serialize_map(Some(obj.len()))?;
-                for (k, v) in obj.iter() {
-                    map.serialize_entry(k, v)?;
-                }
-                map.end()
-            }
-            Value::Table { headers, rows } => {
-                use serde::ser::SerializeSeq;
-                let mut seq = serializer.serialize_seq(Some(rows.len()))?;
-                for row in rows {
-                    let mut object = ToonMap::new();
-                    for (i, value) in row.iter().enumerate() {
-                        if let Some(header) = headers.get(i) {
-                            object.insert(header.clone(), value.clone());
-                        }
-                    }
-                    seq.serialize_element(&Value::Object(object))?;
-                }
-                seq.end()
-            }
+            Value::Object(obj) => obj.serialize(serializer),
+            Value::Table(table) => table.serialize(serializer),
+            #[cfg(feature = "datetime")]
             Value::Date(dt) => serializer.serialize_str(&dt.to_rfc3339()),
+            #[cfg(feature = "bigint")]
             Value::BigInt(bi) => serializer.serialize_str(&format!("{}n", bi)),
         }
     }
@@ -648,6 +1359,14 @@ impl<'de> Deserialize<'de> for Value {
                 Deserialize::deserialize(deserializer)
             }
 
+            // Formats other than TOON pass the `Value` request straight on
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                deserializer.deserialize_any(self)
+            }
+
             fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
             where
                 A: de::SeqAccess<'de>,
@@ -664,14 +1383,38 @@ impl<'de> Deserialize<'de> for Value {
                 A: de::MapAccess<'de>,
             {
                 let mut values = ToonMap::new();
-                while let Some((key, value)) = map.next_entry()? {
+                let Some(key) = map.next_key::<Key>()? else {
+                    return Ok(Value::Object(values));
+                };
+                // How a TOON deserializer hands over a number kept as written
+                if key == NUMBER_TOKEN {
+                    return Ok(Value::Number(Number::Raw(map.next_value()?)));
+                }
+                #[cfg(feature = "rust_decimal")]
+                if key == DECIMAL_TOKEN {
+                    let text: String = map.next_value()?;
+                    return parse_decimal(&text)
+                        .map(|d| Value::Number(Number::Decimal(d)))
+                        .ok_or_else(|| {
+                            de::Error::invalid_value(de::Unexpected::Str(&text), &self)
+                        });
+                }
+                values.insert(key, map.next_value()?);
+                while let Some((key, value)) = map.next_entry::<Key, Value>()? {
                     values.insert(key, value);
                 }
                 Ok(Value::Object(values))
             }
         }
 
-        deserializer.deserialize_any(ValueVisitor)
+        #[cfg(feature = "rust_decimal")]
+        {
+            deserializer.deserialize_newtype_struct(VALUE_TOKEN, ValueVisitor)
+        }
+        #[cfg(not(feature = "rust_decimal"))]
+        {
+            deserializer.deserialize_any(ValueVisitor)
+        }
     }
 }
 
@@ -692,6 +1435,9 @@ impl TryFrom<Value> for i64 {
                     )))
                 }
             }
+            Value::Number(number @ Number::Raw(_)) => {
+                i64::try_from(Value::Number(number.resolved().into_owned()))
+            }
             _ => Err(crate::Error::custom(format!(
                 "expected integer, found {:?}",
                 value
@@ -710,6 +1456,7 @@ impl TryFrom<Value> for f64 {
             Value::Number(Number::Infinity) => Ok(f64::INFINITY),
             Value::Number(Number::NegativeInfinity) => Ok(f64::NEG_INFINITY),
             Value::Number(Number::NaN) => Ok(f64::NAN),
+            Value::Number(number @ Number::Raw(_)) => Ok(number.as_f64()),
             _ => Err(crate::Error::custom(format!(
                 "expected number, found {:?}",
                 value
@@ -746,6 +1493,32 @@ impl TryFrom<Value> for String {
     }
 }
 
+/// Converts an array element by element.
+///
+/// ```rust
+/// use serde_toon::{toon, Value};
+///
+/// let ids: Vec<i64> = Vec::try_from(toon!([1, 2, 3])).unwrap();
+/// assert_eq!(ids, [1, 2, 3]);
+/// assert!(Vec::<i64>::try_from(toon!([1, "two"])).is_err());
+/// ```
+impl<T> TryFrom<Value> for Vec<T>
+where
+    T: TryFrom<Value, Error = crate::Error>,
+{
+    type Error = crate::Error;
+
+    fn try_from(value: Value) -> crate::Result<Self> {
+        match value {
+            Value::Array(items) => items.into_iter().map(T::try_from).collect(),
+            _ => Err(crate::Error::custom(format!(
+                "expected array, found {:?}",
+                value
+            ))),
+        }
+    }
+}
+
 // From implementations for creating Value from primitives
 impl From<bool> for Value {
     fn from(value: bool) -> Self {
@@ -795,9 +1568,37 @@ impl From<u32> for Value {
     }
 }
 
+/// Values above `i64::MAX` become `Value::BigInt`, or a float without the
+/// `bigint` feature.
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        match i64::try_from(value) {
+            Ok(i) => Value::Number(Number::Integer(i)),
+            #[cfg(feature = "bigint")]
+            Err(_) => Value::BigInt(BigInt::from(value)),
+            #[cfg(not(feature = "bigint"))]
+            Err(_) => Value::Number(Number::Float(value as f64)),
+        }
+    }
+}
+
+/// Values above `i64::MAX` become `Value::BigInt`, or a float without the
+/// `bigint` feature.
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Value::from(value as u64)
+    }
+}
+
+impl From<isize> for Value {
+    fn from(value: isize) -> Self {
+        Value::Number(Number::Integer(value as i64))
+    }
+}
+
 impl From<f32> for Value {
     fn from(value: f32) -> Self {
-        Value::Number(Number::Float(value as f64))
+        Value::Number(Number::Float(widen_f32(value)))
     }
 }
 
@@ -819,9 +1620,53 @@ impl From<&str> for Value {
     }
 }
 
-impl From<Vec<Value>> for Value {
-    fn from(value: Vec<Value>) -> Self {
-        Value::Array(value)
+#[cfg(feature = "rust_decimal")]
+impl From<rust_decimal::Decimal> for Value {
+    fn from(value: rust_decimal::Decimal) -> Self {
+        Value::Number(Number::Decimal(value))
+    }
+}
+
+/// The hyphenated form, such as `550e8400-e29b-41d4-a716-446655440000`.
+#[cfg(feature = "uuid")]
+impl From<uuid::Uuid> for Value {
+    fn from(value: uuid::Uuid) -> Self {
+        Value::String(value.hyphenated().to_string())
+    }
+}
+
+#[cfg(feature = "url")]
+impl From<url::Url> for Value {
+    fn from(value: url::Url) -> Self {
+        Value::String(value.into())
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(value: Vec<T>) -> Self {
+        Value::Array(value.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Clone + Into<Value>> From<&[T]> for Value {
+    fn from(value: &[T]) -> Self {
+        Value::Array(value.iter().cloned().map(Into::into).collect())
+    }
+}
+
+/// `None` becomes [`Value::Null`].
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+/// Keys are sorted, since a `HashMap` has no order of its own.
+impl<T: Into<Value>> From<HashMap<String, T>> for Value {
+    fn from(value: HashMap<String, T>) -> Self {
+        let mut map: ToonMap = value.into_iter().map(|(k, v)| (k, v.into())).collect();
+        map.sort_keys();
+        Value::Object(map)
     }
 }
 
@@ -910,6 +1755,58 @@ mod tests {
         assert_eq!(value, Value::Object(map));
     }
 
+    #[test]
+    fn test_from_generic_collections() {
+        assert_eq!(
+            Value::from(vec![1, 2]),
+            Value::Array(vec![Value::from(1), Value::from(2)])
+        );
+        assert_eq!(
+            Value::from(&["a", "b"][..]),
+            Value::Array(vec![Value::from("a"), Value::from("b")])
+        );
+        assert_eq!(Value::from(Some(1.5)), Value::from(1.5));
+        assert_eq!(Value::from(None::<&str>), Value::Null);
+
+        let map: HashMap<String, u32> = [("b".to_string(), 2), ("a".to_string(), 1)].into();
+        let value = Value::from(map);
+        let keys: Vec<_> = value.as_object().unwrap().keys().cloned().collect();
+        assert_eq!(keys, ["a", "b"]);
+
+        assert_eq!(Value::from(7usize), Value::from(7));
+        assert_eq!(Value::from(-7isize), Value::from(-7));
+        #[cfg(feature = "bigint")]
+        assert_eq!(Value::from(u64::MAX), Value::BigInt(BigInt::from(u64::MAX)));
+        #[cfg(not(feature = "bigint"))]
+        assert_eq!(Value::from(u64::MAX), Value::from(u64::MAX as f64));
+    }
+
+    #[test]
+    fn test_tryfrom_vec() {
+        let strings: Vec<String> = Vec::try_from(Value::from(vec!["x", "y"])).unwrap();
+        assert_eq!(strings, ["x", "y"]);
+
+        let nested: Vec<Vec<f64>> =
+            Vec::try_from(Value::from(vec![vec![1.0], vec![2.0, 3.0]])).unwrap();
+        assert_eq!(nested, [vec![1.0], vec![2.0, 3.0]]);
+
+        assert!(Vec::<bool>::try_from(Value::from(true)).is_err());
+    }
+
+    #[test]
+    fn test_numeric_and_mut_accessors() {
+        assert_eq!(Value::from(3).as_f64(), Some(3.0));
+        assert_eq!(Value::Null.as_f64(), None);
+        assert_eq!(Value::from(3.0).as_u64(), Some(3));
+        assert_eq!(Value::from(3.5).as_u64(), None);
+        assert_eq!(Value::from("3").as_u64(), None);
+
+        let mut value = Value::from(vec![1]);
+        assert!(value.as_object_mut().is_none());
+        value.as_array_mut().unwrap().clear();
+        assert_eq!(value, Value::Array(vec![]));
+    }
+
     #[test]
     fn test_const_is_methods() {
         const fn check_null(v: &Value) -> bool {
@@ -934,4 +1831,211 @@ mod tests {
         assert!(!value.is_null());
         assert!(!value.is_string());
     }
+
+    #[test]
+    fn test_display_is_toon() {
+        let value = crate::toon!({
+            "name": "a: b",
+            "owner": {"id": 1},
+            "rows": [{"x": 1}, {"x": 2}],
+            "mixed": [1, {"y": null}]
+        });
+        let text = value.to_string();
+        assert_eq!(
+            text,
+            "name: \"a: b\"\nowner:\n  id: 1\nrows: [2]{x}:\n  1\n  2\nmixed: [2]:\n  - 1\n  - y: null"
+        );
+        assert_eq!(text, crate::to_string(&value).unwrap());
+        assert_eq!(crate::from_str::<Value>(&text).unwrap(), value);
+        assert_eq!(
+            format!("{:#}", value),
+            crate::to_string_pretty(&value).unwrap()
+        );
+
+        assert_eq!(Value::from("tab\there").to_string(), "\"tab\\there\"");
+        assert_eq!(Value::Array(vec![]).to_string(), "[0]:");
+        let table = Value::table(["id"]).row((1,)).build();
+        assert_eq!(table.to_string(), "[1]{id}:\n  1");
+    }
+
+    #[test]
+    fn test_total_order_and_hash() {
+        use std::collections::{BTreeSet, HashSet};
+
+        let nan = Value::Number(Number::Float(f64::NAN));
+        assert_eq!(nan, nan.clone());
+        assert_eq!(
+            Value::Number(Number::Float(-0.0)),
+            Value::Number(Number::Float(0.0))
+        );
+        assert_ne!(Value::from(1), Value::from(1.0));
+
+        let numbers: Vec<Number> = [
+            Number::NaN,
+            Number::Float(2.5),
+            Number::Infinity,
+            Number::Integer(i64::MAX),
+            Number::Integer(i64::MAX - 1),
+            Number::NegativeInfinity,
+            Number::Float(1.0),
+            Number::Integer(1),
+        ]
+        .into_iter()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+        assert_eq!(
+            numbers,
+            [
+                Number::NegativeInfinity,
+                Number::Integer(1),
+                Number::Float(1.0),
+                Number::Float(2.5),
+                Number::Integer(i64::MAX - 1),
+                Number::Integer(i64::MAX),
+                Number::Infinity,
+                Number::NaN,
+            ]
+        );
+
+        let mut a = ToonMap::new();
+        a.insert("x", Value::from(1));
+        a.insert("y", nan.clone());
+        let mut b = ToonMap::new();
+        b.insert("y", nan);
+        b.insert("x", Value::from(1));
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+
+        let set: HashSet<Value> = [Value::Object(a), Value::Object(b), Value::Null].into();
+        assert_eq!(set.len(), 2);
+        assert!(Value::Null < Value::Bool(false));
+        assert!(Value::from("z") < Value::Array(vec![]));
+    }
+
+    #[test]
+    fn test_raw_numbers_compare_by_value() {
+        use std::collections::HashSet;
+
+        let raw = |text: &str| Number::Raw(text.to_string());
+        assert_eq!(raw("1.50"), Number::Float(1.5));
+        assert_eq!(raw("1.50"), raw("1.5"));
+        assert_ne!(raw("1.0"), Number::Integer(1));
+        assert_eq!(raw("-0"), Number::Integer(0));
+        assert!(raw("2e0") < raw("2.5"));
+        assert!(raw("9").is_integer() && raw("1e3").is_float());
+        assert_eq!(raw("1e3").as_i64(), Some(1000));
+
+        let set: HashSet<Number> = [raw("0.10"), Number::Float(0.1), raw("1e-1")].into();
+        assert_eq!(set.len(), 1);
+        assert_eq!(raw("1.50").to_string(), "1.50");
+
+        let mut value = Value::Number(raw("1.50"));
+        value.canonicalize();
+        assert!(matches!(value, Value::Number(Number::Float(f)) if f == 1.5));
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let mut value = crate::toon!({
+            "b": [1.0, -0.0, 1.5],
+            "a": {"z": null, "y": true}
+        });
+        value.canonicalize();
+        let keys: Vec<_> = value.as_object().unwrap().keys().cloned().collect();
+        assert_eq!(keys, ["a", "b"]);
+        assert_eq!(
+            value.as_object().unwrap().get("b"),
+            Some(&Value::Array(vec![
+                Value::from(1),
+                Value::from(0),
+                Value::from(1.5),
+            ]))
+        );
+
+        let mut special = Value::Array(vec![
+            Value::Number(Number::Float(f64::INFINITY)),
+            Value::Number(Number::Float(f64::NAN)),
+        ]);
+        special.canonicalize();
+        assert_eq!(
+            special,
+            Value::Array(vec![
+                Value::Number(Number::Infinity),
+                Value::Number(Number::NaN),
+            ])
+        );
+        #[cfg(feature = "bigint")]
+        {
+            let mut small = Value::BigInt(BigInt::from(-7));
+            small.canonicalize();
+            assert_eq!(small, Value::from(-7));
+        }
+
+        let table = Value::table(["id", "tags"]).row((1, "a")).build();
+        let rows = crate::toon!([{"tags": "a", "id": 1.0}]);
+        assert_ne!(table, rows);
+        assert!(table.canonical_eq(&rows));
+        assert!(!table.canonical_eq(&crate::toon!([{"id": 2, "tags": "a"}])));
+    }
+
+    #[test]
+    fn test_walk_and_transform() {
+        let table = |id: i64| Value::table(["id", "price"]).row((id, 2.5)).build();
+        let mut value = crate::toon!({"name": "Store", "items": [1, {"sku": "A1"}]});
+        if let Some(map) = value.as_object_mut() {
+            map.insert("rows".to_string(), table(7));
+        }
+        let mut visited = Vec::new();
+        value.walk(|path, _| visited.push(path.to_string()));
+        assert_eq!(
+            visited,
+            [
+                "(root)",
+                "name",
+                "items",
+                "items[0]",
+                "items[1]",
+                "items[1].sku",
+                "rows",
+                "rows[0].id",
+                "rows[0].price",
+            ]
+        );
+
+        // Contents are transformed before their container
+        let mut order = Vec::new();
+        let doubled = value.transform(|path, value| {
+            order.push(path.to_string());
+            match value {
+                Value::Number(Number::Integer(i)) => Value::from(i * 2),
+                other => other,
+            }
+        });
+        assert_eq!(order.first().map(String::as_str), Some("name"));
+        assert_eq!(order.last().map(String::as_str), Some("(root)"));
+        let doubled = doubled.as_object().unwrap();
+        assert_eq!(
+            doubled.get("items"),
+            Some(&crate::toon!([2, {"sku": "A1"}]))
+        );
+        assert_eq!(doubled.get("rows"), Some(&table(14)));
+    }
+
+    #[test]
+    fn test_structural_hash() {
+        let value = crate::toon!({"b": [1.0, -0.0], "a": {"y": null, "x": "1"}});
+        let reordered = crate::toon!({"a": {"x": "1", "y": null}, "b": [1, 0]});
+        assert_eq!(
+            value.canonical_string(),
+            "a:\n  x: \"1\"\n  y: null\nb: [2]: 1,0"
+        );
+        assert_eq!(value.structural_hash(), reordered.structural_hash());
+
+        // The digest is fixed, not seeded per process
+        assert_eq!(Value::Null.structural_hash(), 0x5b9b_c4ba_5281_08e4);
+        assert_ne!(
+            crate::toon!({"a": "1"}).structural_hash(),
+            crate::toon!({"a": 1}).structural_hash()
+        );
+    }
 }
@@ -0,0 +1,28 @@
+//! Runs `tests/fixtures`, local cases in the format of the reference
+//! implementation's golden fixtures, with `serde_toon::conformance`.
+
+#![cfg(feature = "json")]
+
+use serde_toon::conformance::{self, Case};
+
+/// Cases on which this crate is known to disagree with the reference.
+const KNOWN_FAILURES: &[&str] = &[
+    "rejects blank lines inside arrays",
+    "rejects unknown escapes",
+];
+
+/// Cases using options this crate does not support.
+const UNSUPPORTED: &[&str] = &["folds single-key chains", "expands dotted keys"];
+
+fn names(cases: &[Case]) -> Vec<&str> {
+    cases.iter().map(|case| case.name.as_str()).collect()
+}
+
+#[test]
+fn test_fixtures_conform() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+    let report = conformance::run(dir).unwrap();
+    assert_eq!(names(&report.failures), KNOWN_FAILURES, "{}", report);
+    assert_eq!(names(&report.skipped), UNSUPPORTED, "{}", report);
+    assert!(report.passed > 0, "{}", report);
+}
@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
 use serde_toon::{
-    from_str, to_string, to_string_pretty, to_value, Delimiter, Number, ToonOptions, Value,
+    from_str, to_string, to_string_pretty, to_value, toon, Delimiter, DelimiterStrategy, Error,
+    Number, QuotePolicy, ToonOptions, UnsupportedPolicy, Value, ValueSerializer, WarningKind,
 };
+use std::collections::BTreeMap;
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 struct User {
@@ -74,6 +76,46 @@ fn test_nested_struct() {
     assert_eq!(order, order_back);
 }
 
+#[test]
+fn test_list_of_nested_structs() {
+    let order = |order_id, items: Vec<Product>| Order {
+        order_id,
+        customer: User {
+            id: 1,
+            name: "Ada".to_string(),
+            active: true,
+            tags: vec![],
+        },
+        total: items.iter().map(|item| item.price).sum(),
+        items,
+    };
+    let product = |sku: &str| Product {
+        sku: sku.to_string(),
+        price: 1.5,
+        quantity: 2,
+    };
+    let orders = vec![order(1, vec![product("A"), product("B")]), order(2, vec![])];
+
+    // Every field of an item lines up after its dash, with nested values
+    // indented below the field they belong to
+    let toon = to_string(&orders).unwrap();
+    assert_eq!(
+        toon,
+        "[2]:\n  \
+         - order_id: 1\n    \
+           customer:\n      id: 1\n      name: Ada\n      active: true\n      tags: [0]:\n    \
+           items: [2]{sku,price,quantity}:\n      A,1.5,2\n      B,1.5,2\n    \
+           total: 3\n  \
+         - order_id: 2\n    \
+           customer:\n      id: 1\n      name: Ada\n      active: true\n      tags: [0]:\n    \
+           items: [0]:\n    \
+           total: 0"
+    );
+    assert_eq!(from_str::<Vec<Order>>(&toon).unwrap(), orders);
+    let pretty = to_string_pretty(&orders).unwrap();
+    assert_eq!(from_str::<Vec<Order>>(&pretty).unwrap(), orders);
+}
+
 #[test]
 fn test_array_of_objects() {
     let products = vec![
@@ -146,6 +188,198 @@ fn test_options() {
     assert_eq!(user, user_back);
 }
 
+#[test]
+fn test_tab_table_quoted_headers_round_trip() {
+    // Cells with commas and pipes, so that `Auto` picks tabs too
+    let value = toon!([
+        {"x}": 1, "c": "a,b|c", "": true, "p    q": null},
+        {"x}": 2, "c": "d,e|f", "": false, "p    q": 3}
+    ]);
+    for options in [
+        ToonOptions::new().with_delimiter(Delimiter::Tab),
+        ToonOptions::new().with_delimiter_strategy(DelimiterStrategy::Auto),
+    ] {
+        let toon = serde_toon::to_string_with_options(&value, options).unwrap();
+        assert!(
+            toon.starts_with("[2    ]{\"x}\"    c    \"\"    \"p    q\"}:"),
+            "{:?}",
+            toon
+        );
+        let back: Value = from_str(&toon).unwrap();
+        assert!(back.canonical_eq(&value), "{:?}", toon);
+    }
+}
+
+#[test]
+fn test_delimiter_detection() {
+    use serde_toon::{from_str_with_options, Deserializer};
+
+    // Headers name every delimiter but comma
+    let toon = "a: [3|]: x|y|z\nb: [2\t]{k\tv}:\n  1\t\"p,q\"\n  2\tr";
+    let value: Value = from_str(toon).unwrap();
+    assert_eq!(
+        value,
+        toon!({"a": ["x", "y", "z"], "b": [{"k": 1, "v": "p,q"}, {"k": 2, "v": "r"}]})
+    );
+    let mut de = Deserializer::from_str(toon);
+    Value::deserialize(&mut de).unwrap();
+    assert_eq!(de.detected_delimiter(), Some(&Delimiter::Pipe));
+
+    // Without a marker the delimiter is a comma, unless sniffed from the first row
+    let unmarked = "rows: [2]{id|name}:\n  1|Ada\n  2|Bob\ntags: [2]: a\tb\nsolo: [1]: x|y";
+    assert!(from_str::<Value>(unmarked).is_err());
+    let options = ToonOptions::new().with_sniff_delimiters(true);
+    let mut de = Deserializer::with_options(unmarked, options.clone());
+    assert_eq!(
+        Value::deserialize(&mut de).unwrap(),
+        toon!({
+            "rows": [{"id": 1, "name": "Ada"}, {"id": 2, "name": "Bob"}],
+            "tags": ["a", "b"],
+            "solo": ["x|y"]
+        })
+    );
+    assert_eq!(de.detected_delimiter(), Some(&Delimiter::Pipe));
+    let value: Value = from_str_with_options("[2]: a,b|c", options.clone()).unwrap();
+    assert_eq!(value, toon!(["a", "b|c"]));
+    let lenient = options.with_lenient(true);
+    let value: Value = from_str_with_options("[]: a|b|c", lenient).unwrap();
+    assert_eq!(value, toon!(["a", "b", "c"]));
+}
+
+#[test]
+fn test_delimiter_strategy() {
+    let options = ToonOptions::new().with_delimiter_strategy(DelimiterStrategy::Auto);
+    let value = toon!({
+        "places": [
+            {"city": "Paris, France", "code": "FR"},
+            {"city": "Austin, Texas", "code": "US"}
+        ],
+        "plain": ["a", "b"],
+        "mixed": ["a,b", "c|d"],
+        "note": "x, y"
+    });
+    let toon = serde_toon::to_string_with_options(&value, options.clone()).unwrap();
+    assert_eq!(
+        toon,
+        "places: [2|]{city|code}:\n  Paris, France|FR\n  Austin, Texas|US\n\
+         plain: [2]: a,b\n\
+         mixed: [2    ]: a,b\tc|d\n\
+         note: \"x, y\""
+    );
+    assert_eq!(from_str::<Value>(&toon).unwrap(), value);
+
+    // Ties keep the configured delimiter
+    let pipes = options.with_delimiter(Delimiter::Pipe);
+    let toon = serde_toon::to_string_with_options(&toon!(["a", "b"]), pipes).unwrap();
+    assert_eq!(toon, "[2|]: a|b");
+}
+
+#[test]
+fn test_quote_policy() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Row {
+        id: String,
+        code: String,
+        name: String,
+    }
+
+    let rows = vec![
+        Row {
+            id: "12345".to_string(),
+            code: "1.50".to_string(),
+            name: "true".to_string(),
+        },
+        Row {
+            id: "007".to_string(),
+            code: "null".to_string(),
+            name: "a b".to_string(),
+        },
+    ];
+
+    let always = ToonOptions::new().with_quote_policy(QuotePolicy::AlwaysStrings);
+    let toon = serde_toon::to_string_with_options(&rows, always).unwrap();
+    assert_eq!(
+        toon,
+        "[2]{id,code,name}:\n  \"12345\",\"1.50\",\"true\"\n  \"007\",\"null\",\"a b\""
+    );
+    assert_eq!(from_str::<Vec<Row>>(&toon).unwrap(), rows);
+
+    // Only text that reads back exactly is left unquoted
+    let preserve = ToonOptions::new().with_quote_policy(QuotePolicy::PreserveInput);
+    let toon = serde_toon::to_string_with_options(&rows, preserve.clone()).unwrap();
+    assert_eq!(
+        toon,
+        "[2]{id,code,name}:\n  12345,\"1.50\",true\n  \"007\",\"null\",a b"
+    );
+    let back: Vec<Row> = serde_toon::from_str_with_options(&toon, preserve).unwrap();
+    assert_eq!(back, rows);
+    assert!(from_str::<Vec<Row>>(&toon).is_err());
+}
+
+#[test]
+fn test_unicode_quoting() {
+    let value = toon!({
+        "nbsp": "a\u{00A0}b",
+        "bidi": "\u{202E}evil",
+        "zero\u{200B}width": ["x\u{200B}", "café"],
+        "plain": "naïve ☕"
+    });
+    let toon = to_string(&value).unwrap();
+    assert_eq!(
+        toon,
+        "nbsp: \"a\\u{a0}b\"\n\
+         bidi: \"\\u{202e}evil\"\n\
+         \"zero\\u{200b}width\": [2]: \"x\\u{200b}\",café\n\
+         plain: naïve ☕"
+    );
+    assert_eq!(from_str::<Value>(&toon).unwrap(), value);
+
+    let options = ToonOptions::new().with_escape_non_ascii(true);
+    let toon = serde_toon::to_string_with_options(&value, options).unwrap();
+    assert!(toon.is_ascii());
+    assert!(toon.ends_with("plain: \"na\\u{ef}ve \\u{2615}\""));
+    assert_eq!(from_str::<Value>(&toon).unwrap(), value);
+}
+
+#[test]
+fn test_block_strings() {
+    let input =
+        "name: greet\nprompt: |\n  Say hello.\n\n    - politely\n  key: not a field\nnext: 1";
+    let value: Value = from_str(input).unwrap();
+    assert_eq!(
+        value,
+        toon!({
+            "name": "greet",
+            "prompt": "Say hello.\n\n  - politely\nkey: not a field",
+            "next": 1
+        })
+    );
+    let value: Value = from_str("items[1]:\n  - text: |\n      a\n      b\n    id: 1").unwrap();
+    assert_eq!(value, toon!({"items": [{"text": "a\nb", "id": 1}]}));
+    // Without indented lines below, `|` is the string "|"
+    let value: Value = from_str("sep: |\nnext: 1").unwrap();
+    assert_eq!(value, toon!({"sep": "|", "next": 1}));
+
+    let value = toon!({
+        "code": "fn main() {\n\tprintln!();\n}",
+        "short": "one line",
+        "long": "a line longer than the limit",
+        "kept": "trailing space \nhere",
+        "items": [{"text": "\nx\n\ny", "meta": {"id": 1}}]
+    });
+    let options = ToonOptions::new().with_block_strings(20);
+    let toon = serde_toon::to_string_with_options(&value, options).unwrap();
+    assert_eq!(
+        toon,
+        "code: |\n  fn main() {\n  \tprintln!();\n  }\n\
+         short: one line\n\
+         long: |\n  a line longer than the limit\n\
+         kept: \"trailing space \\nhere\"\n\
+         items: [1]:\n  - text: |\n\n      x\n\n      y\n    meta:\n      id: 1"
+    );
+    assert_eq!(from_str::<Value>(&toon).unwrap(), value);
+}
+
 #[test]
 fn test_to_value() {
     let user = User {
@@ -174,6 +408,139 @@ fn test_to_value() {
     }
 }
 
+#[derive(Serialize, Debug, PartialEq)]
+enum Shape {
+    Circle(f64),
+    Line(i32, i32),
+    Rect { width: u32, height: u32 },
+}
+
+#[test]
+fn test_to_value_enum_variants() {
+    let value = to_value(&Shape::Circle(1.5)).unwrap();
+    assert_eq!(
+        value.as_object().and_then(|obj| obj.get("Circle")),
+        Some(&Value::Number(Number::Float(1.5)))
+    );
+
+    let value = to_value(&Shape::Line(1, 2)).unwrap();
+    assert_eq!(
+        value.as_object().and_then(|obj| obj.get("Line")),
+        Some(&Value::Array(vec![
+            Value::Number(Number::Integer(1)),
+            Value::Number(Number::Integer(2)),
+        ]))
+    );
+
+    let value = to_value(&vec![Shape::Rect {
+        width: 3,
+        height: 4,
+    }])
+    .unwrap();
+    let rect = value.as_array().unwrap()[0]
+        .as_object()
+        .and_then(|obj| obj.get("Rect"))
+        .and_then(|rect| rect.as_object())
+        .unwrap();
+    assert_eq!(rect.get("width"), Some(&Value::Number(Number::Integer(3))));
+    assert_eq!(rect.get("height"), Some(&Value::Number(Number::Integer(4))));
+}
+
+#[test]
+fn test_to_value_i128() {
+    assert_eq!(
+        to_value(&42i128).unwrap(),
+        Value::Number(Number::Integer(42))
+    );
+    #[cfg(feature = "bigint")]
+    assert!(matches!(to_value(&u128::MAX).unwrap(), Value::BigInt(_)));
+    #[cfg(not(feature = "bigint"))]
+    assert_eq!(to_value(&u128::MAX).unwrap(), Value::from(u128::MAX as f64));
+}
+
+#[derive(Serialize)]
+struct Inventory {
+    name: String,
+    bins: Vec<BTreeMap<Vec<u32>, String>>,
+}
+
+fn inventory() -> Inventory {
+    let mut bin = BTreeMap::new();
+    bin.insert(vec![1, 2], "bolts".to_string());
+    Inventory {
+        name: "warehouse".to_string(),
+        bins: vec![BTreeMap::new(), bin],
+    }
+}
+
+#[test]
+fn test_unsupported_policy_error_reports_path() {
+    let err = to_string(&inventory()).unwrap_err();
+    match &err {
+        Error::UnsupportedType { type_name, path } => {
+            assert_eq!(type_name, "map key of type sequence");
+            assert_eq!(path, "bins[1]");
+        }
+        other => panic!("Expected UnsupportedType, got {:?}", other),
+    }
+    assert!(err.to_string().contains("bins[1]"));
+}
+
+#[test]
+fn test_unsupported_policy_stringify() {
+    let options = ToonOptions::new().with_unsupported_policy(UnsupportedPolicy::Stringify);
+    let toon = serde_toon::to_string_with_options(&inventory(), options).unwrap();
+    assert!(toon.contains("\"[1,2]\": bolts"), "{}", toon);
+}
+
+#[test]
+fn test_unsupported_policy_null_records_warning() {
+    let options = ToonOptions::new().with_unsupported_policy(UnsupportedPolicy::Null);
+    let serializer = ValueSerializer::with_options(options);
+    let value = inventory().serialize(serializer.clone()).unwrap();
+
+    let bins = value.as_object().and_then(|obj| obj.get("bins")).unwrap();
+    assert_eq!(bins.as_array().unwrap()[1].as_object().unwrap().len(), 0);
+
+    let warnings = serializer.warnings();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].kind, WarningKind::UnsupportedValue);
+    assert_eq!(warnings[0].path.as_deref(), Some("bins[1]"));
+}
+
+#[test]
+fn test_serializer_reuse() {
+    use serde_toon::{LineEnding, Serializer};
+
+    let options = ToonOptions::new()
+        .with_unsupported_policy(UnsupportedPolicy::Null)
+        .with_line_ending(LineEnding::CrLf)
+        .with_trailing_newline(true);
+    let mut serializer = Serializer::new(options.clone());
+    inventory().serialize(&mut serializer).unwrap();
+    let first = serializer.finish().to_string();
+    assert!(first.ends_with("\r\n") && !first.ends_with("\r\n\r\n"));
+    assert_eq!(serializer.finish(), first);
+    assert_eq!(serializer.warnings().len(), 1);
+
+    serializer.reset();
+    assert!(serializer.warnings().is_empty());
+    vec![1, 2].serialize(&mut serializer).unwrap();
+    assert_eq!(serializer.finish(), "[2]: 1,2\r\n");
+    serializer.reset();
+    inventory().serialize(&mut serializer).unwrap();
+    assert_eq!(serializer.into_inner(), first);
+
+    let mut buf = String::from("stale");
+    serde_toon::to_string_into(&inventory(), &mut buf).unwrap_err();
+    assert_eq!(buf, "");
+    serde_toon::to_string_into_with_options(&inventory(), &mut buf, options).unwrap();
+    assert_eq!(buf, first);
+    let capacity = buf.capacity();
+    serde_toon::to_string_into(&[true], &mut buf).unwrap();
+    assert_eq!((buf.as_str(), buf.capacity()), ("[1]: true", capacity));
+}
+
 #[test]
 fn test_empty_collections() {
     let empty_vec: Vec<i32> = vec![];
@@ -241,6 +608,38 @@ fn test_numbers() {
     assert_roundtrip(&-5.75f64);
 }
 
+#[test]
+fn test_number_grammar() {
+    // Only the TOON/JSON number grammar reads as a number
+    for text in ["0", "-0", "42", "-1.5", "0.25", "1e5", "1E+5", "2.5e-3"] {
+        let value: Value = from_str(text).unwrap();
+        assert!(value.is_number(), "{} should be a number", text);
+    }
+    for text in [
+        "nan",
+        "NaN",
+        "Nan",
+        "Infinity",
+        "-Infinity",
+        "inf",
+        "+5",
+        ".5",
+        "1.",
+        "1E400",
+        "007",
+        "-01",
+        "1e",
+        "1.5.2",
+    ] {
+        let value: Value = from_str(text).unwrap();
+        assert_eq!(value, Value::String(text.to_string()), "{}", text);
+    }
+
+    let row: BTreeMap<String, Value> = from_str("name: Nan\nscore: 1.").unwrap();
+    assert_eq!(row["name"], Value::String("Nan".to_string()));
+    assert_eq!(row["score"], Value::String("1.".to_string()));
+}
+
 fn assert_roundtrip<T>(original: &T)
 where
     T: Serialize + for<'de> Deserialize<'de> + PartialEq + std::fmt::Debug,
@@ -249,3 +648,2751 @@ where
     let deserialized: T = from_str(&toon).unwrap();
     assert_eq!(*original, deserialized);
 }
+
+#[test]
+fn test_missing_length() {
+    let nums: Vec<i32> = from_str("[]: 4,5,6").unwrap();
+    assert_eq!(nums, vec![4, 5, 6]);
+
+    // A missing length is not something to repair
+    let (value, warnings) = serde_toon::repair("[]:\n  - a\n  - b").unwrap();
+    assert_eq!(value.as_array().map(|arr| arr.len()), Some(2));
+    assert!(warnings.is_empty());
+
+    // Output of the llm_compact preset reads back with the default options
+    let value = toon!({"ids": [1, 2], "users": [{"id": 1, "name": "Ada"}]});
+    let toon = serde_toon::to_string_with_options(&value, ToonOptions::llm_compact()).unwrap();
+    assert_eq!(from_str::<Value>(&toon).unwrap(), value);
+}
+
+#[test]
+fn test_lenient_length_mismatch() {
+    let (value, warnings) =
+        serde_toon::repair("items: [3]{sku,qty}:\n  A1,2\n  B2,5\ncount: 2").unwrap();
+    let obj = value.as_object().unwrap();
+    match obj.get("items") {
+        Some(Value::Table(table)) => assert_eq!(table.len(), 2),
+        other => panic!("Expected table, got {:?}", other),
+    }
+    assert_eq!(obj.get("count"), Some(&Value::Number(Number::Integer(2))));
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].kind, WarningKind::LengthMismatch);
+
+    // Strict mode still rejects the same input
+    assert!(from_str::<Value>("tags: [3]: a,b").is_err());
+}
+
+#[test]
+fn test_lenient_trailing_prose_and_unterminated_string() {
+    let input = "name: \"Alice\nid: 7\n\nLet me know if you need anything else.";
+    let (value, warnings) = serde_toon::repair(input).unwrap();
+    let obj = value.as_object().unwrap();
+    assert_eq!(obj.get("name").and_then(|v| v.as_str()), Some("Alice"));
+    assert_eq!(obj.get("id"), Some(&Value::Number(Number::Integer(7))));
+
+    let kinds: Vec<_> = warnings.iter().map(|w| w.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            WarningKind::UnterminatedString,
+            WarningKind::TrailingContent
+        ]
+    );
+    assert_eq!(warnings[1].line, Some(4));
+}
+
+#[test]
+fn test_trailing_content() {
+    let input = "id: 7\nname: Ada\n\nLet me know if you need anything else.";
+    match from_str::<Value>(input) {
+        Err(Error::TrailingContent { line, col }) => assert_eq!((line, col), (4, 1)),
+        other => panic!("Expected trailing content error, got {:?}", other),
+    }
+
+    let (value, rest) = serde_toon::from_str_with_remainder::<Value>(input).unwrap();
+    assert_eq!(value, toon!({"id": 7, "name": "Ada"}));
+    assert_eq!(rest, "Let me know if you need anything else.");
+
+    let (numbers, rest) =
+        serde_toon::from_str_with_remainder::<Vec<u32>>("[2]: 1,2\nDone.\nBye").unwrap();
+    assert_eq!(numbers, [1, 2]);
+    assert_eq!(rest, "Done.\nBye");
+
+    // Nothing is left over from a complete document
+    let (_, rest) = serde_toon::from_str_with_remainder::<Value>("a: 1\n\n").unwrap();
+    assert_eq!(rest, "");
+
+    // A line without a key inside a nested object is still a syntax error
+    assert!(matches!(
+        serde_toon::from_str_with_remainder::<Value>("a:\n  b: 1\n  oops"),
+        Err(Error::Syntax { line: 3, .. })
+    ));
+}
+
+#[test]
+fn test_partial_documents() {
+    // Cut off in the middle of a table row
+    let input = "task: triage\ntickets[3]{id,title}:\n  1,Crash on start\n  2,Typo in \"Ab";
+    let partial = serde_toon::from_str_partial::<Value>(input).unwrap();
+    assert!(!partial.complete);
+    assert_eq!(
+        partial.value,
+        toon!({"task": "triage", "tickets": [{"id": 1, "title": "Crash on start"}]})
+    );
+    assert_eq!(&input[partial.offset..], "  2,Typo in \"Ab");
+    assert_eq!(partial.warnings.len(), 1);
+    assert_eq!(partial.warnings[0].kind, WarningKind::LengthMismatch);
+
+    // Cut off at the end of a line, with the table's header only
+    let partial = serde_toon::from_str_partial::<Value>("a: 1\nrows[2]{x}:\n").unwrap();
+    assert_eq!(partial.offset, 17);
+    assert_eq!(partial.value, toon!({"a": 1, "rows": []}));
+
+    let partial = serde_toon::from_str_partial::<Vec<u32>>("[2]: 1,2").unwrap();
+    assert!(partial.complete);
+    assert_eq!((partial.value, partial.offset), (vec![1, 2], 8));
+
+    // Errors before the last line are not truncation
+    assert!(matches!(
+        serde_toon::from_str_partial::<Value>("a: \"open\nrows[2]{x}:\n  1\n  2"),
+        Err(Error::Syntax { line: 1, .. })
+    ));
+    assert!(serde_toon::from_str_partial::<Value>("a: 1\n  b: 2\nc: [2]: 1").is_err());
+}
+
+#[test]
+fn test_field_filters() {
+    use serde_toon::{FieldAction, RawToon};
+
+    #[derive(Serialize)]
+    struct Account {
+        name: String,
+        password: String,
+        keys: Vec<Key>,
+    }
+
+    #[derive(Serialize)]
+    struct Key {
+        id: u32,
+        api_key: String,
+    }
+
+    let account = Account {
+        name: "ada".to_string(),
+        password: "hunter2".to_string(),
+        keys: vec![
+            Key {
+                id: 1,
+                api_key: "sk-1".to_string(),
+            },
+            Key {
+                id: 2,
+                api_key: "sk-2".to_string(),
+            },
+        ],
+    };
+
+    let options = ToonOptions::new().with_field_filter(|path| !path.matches("password"));
+    let toon = serde_toon::to_string_with_options(&account, options).unwrap();
+    assert_eq!(
+        toon,
+        "name: ada\nkeys: [2]{id,api_key}:\n  1,sk-1\n  2,sk-2"
+    );
+
+    // Masked columns keep the table shape
+    let options = ToonOptions::new()
+        .with_field_filter(|path| !path.matches("**.password"))
+        .with_masked_fields(["**.api_key"], "***");
+    let toon = serde_toon::to_string_with_options(&account, options.clone()).unwrap();
+    assert_eq!(toon, "name: ada\nkeys: [2]{id,api_key}:\n  1,***\n  2,***");
+    assert!(!toon.contains("sk-") && !toon.contains("hunter2"));
+
+    // Values built first and raw documents are filtered the same way
+    let value = to_value(&account).unwrap();
+    let toon = serde_toon::to_string_with_options(&value, options.clone()).unwrap();
+    assert_eq!(toon, "name: ada\nkeys: [2]{id,api_key}:\n  1,***\n  2,***");
+    let raw =
+        RawToon::from_string("password: hunter2\nuser:\n  api_key: sk-3".to_string()).unwrap();
+    let toon = serde_toon::to_string_with_options(&raw, options.clone()).unwrap();
+    assert_eq!(toon, "user:\n  api_key: ***");
+    let nested = toon!({"raw": raw});
+    let toon = serde_toon::to_string_with_options(&nested, options).unwrap();
+    assert_eq!(toon, "raw:\n  user:\n    api_key: ***");
+
+    // Only keys listed under `keys` are dropped
+    let options = ToonOptions::new().with_field_action(|path| {
+        if path.matches("keys[*].api_key") {
+            FieldAction::Omit
+        } else {
+            FieldAction::Keep
+        }
+    });
+    let toon = serde_toon::to_string_with_options(&account, options).unwrap();
+    assert_eq!(
+        toon,
+        "name: ada\npassword: hunter2\nkeys: [2]{id}:\n  1\n  2"
+    );
+}
+
+#[test]
+fn test_lenient_table_row_width() {
+    let (value, warnings) = serde_toon::repair("[2]{a,b}:\n  1\n  2,3,4").unwrap();
+    match value {
+        Value::Table(table) => {
+            let rows = table.rows();
+            assert_eq!(
+                rows[0],
+                vec![Value::Number(Number::Integer(1)), Value::Null]
+            );
+            assert_eq!(rows[1].len(), 2);
+        }
+        other => panic!("Expected table, got {:?}", other),
+    }
+    assert_eq!(warnings.len(), 2);
+}
+
+#[test]
+fn test_keys_starting_with_keyword_letters() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Contact {
+        name: String,
+        nickname: Option<String>,
+        title: String,
+    }
+
+    let contact: Contact = from_str("name: Ada\nnickname: null\ntitle: Countess").unwrap();
+    assert_eq!(contact.name, "Ada");
+    assert_eq!(contact.nickname, None);
+
+    let names: Vec<String> = from_str("[2]: nancy,frank").unwrap();
+    assert_eq!(names, vec!["nancy", "frank"]);
+}
+
+#[test]
+fn test_outline() {
+    use serde_toon::outline::{outline, OutlineKind};
+
+    let doc = "store:\n  name: Corner\n  tags: [2]: a,b\norders: [2]:\n  - id: 1\n    items: [1]{sku,qty}:\n      A1,2\n  - id: 2\ntotal: 9";
+    let nodes = outline(doc);
+    let paths: Vec<String> = nodes.iter().map(|n| n.path.to_string()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            "store",
+            "store.name",
+            "store.tags",
+            "orders",
+            "orders[0]",
+            "orders[0].id",
+            "orders[0].items",
+            "orders[1]",
+            "orders[1].id",
+            "total",
+        ]
+    );
+
+    assert_eq!(nodes[0].kind, OutlineKind::Object);
+    assert_eq!(
+        &doc[nodes[0].span.clone()],
+        "store:\n  name: Corner\n  tags: [2]: a,b"
+    );
+    assert_eq!(nodes[2].row_count, Some(2));
+    assert_eq!(nodes[3].kind, OutlineKind::Array);
+    assert_eq!(nodes[3].row_count, Some(2));
+    assert_eq!(nodes[6].kind, OutlineKind::Table);
+    assert_eq!(nodes[6].row_count, Some(1));
+    assert_eq!(nodes[6].line, 6);
+    assert_eq!(nodes[9].depth, 1);
+
+    // A block string spans its lines, which are not outlined as fields
+    let doc = "note: |\n  a: x\n\n  y\nb: 1";
+    let nodes = outline(doc);
+    assert_eq!(nodes.len(), 2);
+    assert_eq!(&doc[nodes[0].span.clone()], "note: |\n  a: x\n\n  y");
+    assert_eq!(nodes[0].kind, OutlineKind::Value);
+    assert_eq!(nodes[0].row_count, None);
+    assert_eq!(nodes[1].path.to_string(), "b");
+}
+
+#[test]
+fn test_extract_from_text() {
+    let reply = "Here you go:\n\n```toon\nid: 7\ntags: [2]: a,b\n```\n\nHope this helps!";
+    let (value, range) = serde_toon::extract_from_text(reply).unwrap();
+    assert_eq!(&reply[range], "id: 7\ntags: [2]: a,b");
+    let obj = value.as_object().unwrap();
+    assert_eq!(obj.get("id"), Some(&Value::Number(Number::Integer(7))));
+
+    // Unfenced documents are found between prose paragraphs
+    let reply = "I found two products.\n\nproducts: [2]{sku,qty}:\n  A1,2\n  B2,5\n\nThanks!";
+    let (value, range) = serde_toon::extract_from_text(reply).unwrap();
+    assert_eq!(&reply[range], "products: [2]{sku,qty}:\n  A1,2\n  B2,5");
+    assert!(value
+        .as_object()
+        .unwrap()
+        .get("products")
+        .unwrap()
+        .is_table());
+
+    // A broken tagged block is skipped in favour of a later valid one
+    let reply = "```toon\nitems: [2]{a,b}:\n  1,2,\"oops\n```\n```toon\nok: true\n```";
+    let (value, _) = serde_toon::extract_from_text(reply).unwrap();
+    assert_eq!(
+        value.as_object().unwrap().get("ok"),
+        Some(&Value::Bool(true))
+    );
+
+    assert!(serde_toon::extract_from_text("Sorry, I can't help with that.").is_err());
+}
+
+#[test]
+fn test_describe() {
+    use std::collections::HashMap;
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Line {
+        sku: String,
+        price: f64,
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Invoice {
+        id: u32,
+        paid: bool,
+        point: (i32, i32),
+        lines: Vec<Line>,
+        meta: HashMap<String, String>,
+    }
+
+    let skeleton = serde_toon::describe::<Vec<Invoice>>().unwrap();
+    assert_eq!(
+        skeleton,
+        "[N]:\n  - id: <int>\n    paid: <bool>\n    point: [2]: <int>,<int>\n    lines: [N]{sku,price}:\n      <string>,<number>\n    meta:\n      <key>: <string>"
+    );
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Node {
+        name: String,
+        children: Vec<Node>,
+    }
+    assert!(serde_toon::describe::<Node>()
+        .unwrap()
+        .starts_with("name: <string>\nchildren: [N]:\n  - name: <string>"));
+
+    assert!(serde_toon::describe::<Value>().is_err());
+}
+
+#[test]
+fn test_to_string_within_budget() {
+    use serde_toon::budget::{from_str_truncated, Keep};
+    use serde_toon::metrics::{token_estimate, Tokenizer};
+    use serde_toon::{to_string_within_budget, TruncationPolicy};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Doc {
+        id: u32,
+        title: String,
+    }
+
+    let small = vec![1, 2, 3];
+    let policy = TruncationPolicy::new();
+    assert_eq!(
+        to_string_within_budget(&small, 100, &policy).unwrap(),
+        "[3]: 1,2,3"
+    );
+
+    let docs: Vec<Doc> = (0..200)
+        .map(|id| Doc {
+            id,
+            title: format!("Document number {}", id),
+        })
+        .collect();
+    let toon = to_string_within_budget(&docs, 120, &policy).unwrap();
+    assert!(token_estimate(&toon, Tokenizer::Cl100k) <= 120);
+    assert!(toon.starts_with("["));
+    assert!(toon.contains("  0,Document number 0\n"));
+    let last = toon.lines().last().unwrap();
+    assert!(
+        last.starts_with("  ... ") && last.ends_with(" more,null"),
+        "{}",
+        last
+    );
+    // The marker row does not read as a `Doc`, but is dropped when read back
+    assert!(from_str::<Vec<Doc>>(&toon).is_err());
+    let kept: Vec<Doc> = from_str_truncated(&toon, &policy).unwrap();
+    assert!(!kept.is_empty());
+    assert_eq!(kept[..], docs[..kept.len()]);
+
+    let numbers: Vec<u32> = (0..1000).collect();
+    let policy = TruncationPolicy::new()
+        .with_keep(Keep::HeadAndTail)
+        .with_min_items(2)
+        .with_marker("[{n} omitted]");
+    let toon = to_string_within_budget(&numbers, 40, &policy).unwrap();
+    assert!(toon.contains(",0,") || toon.contains(": 0,"), "{}", toon);
+    assert!(toon.contains(" omitted]"));
+    assert!(toon.ends_with(",999"));
+    let kept: Vec<u32> = from_str_truncated(&toon, &policy).unwrap();
+    assert_eq!(kept.first(), Some(&0));
+    assert_eq!(kept.last(), Some(&999));
+
+    // Even two items per array cannot fit in three tokens
+    assert!(to_string_within_budget(&numbers, 3, &policy).is_err());
+}
+
+#[test]
+fn test_toon_map_mutation() {
+    use serde_toon::ToonMap;
+
+    let mut map = ToonMap::new();
+    map.extend([
+        ("c".to_string(), Value::from(3)),
+        ("a".to_string(), Value::from(1)),
+        ("b".to_string(), Value::from(2)),
+    ]);
+    *map.entry("a".to_string()).or_insert(Value::Null) = Value::from(10);
+    map.entry("d".to_string()).or_insert_with(|| Value::from(4));
+
+    for (_, value) in &mut map {
+        if let Some(n) = value.as_i64() {
+            *value = Value::from(n * 2);
+        }
+    }
+    map.retain(|key, _| key != "b");
+    assert_eq!(map.shift_remove("c").and_then(|v| v.as_i64()), Some(6));
+    assert!(map.remove("missing").is_none());
+
+    map.insert("0".to_string(), Value::Null);
+    map.sort_keys();
+    let entries: Vec<(&serde_toon::Key, &Value)> = (&map).into_iter().collect();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(map.keys().collect::<Vec<_>>(), vec!["0", "a", "d"]);
+    assert_eq!(map.get("a").and_then(|v| v.as_i64()), Some(20));
+    assert!(map.contains_key("d"));
+}
+
+#[test]
+fn test_toon_map_as_struct_field() {
+    use serde_toon::ToonMap;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        id: u32,
+        extra: ToonMap,
+    }
+
+    let extra: ToonMap = [("zeta", Value::from(1)), ("alpha", Value::from("x"))]
+        .into_iter()
+        .collect();
+    let record = Record { id: 7, extra };
+
+    let toon = to_string(&record).unwrap();
+    assert!(toon.contains("zeta: 1") && toon.contains("alpha: x"));
+    let back: Record = from_str(&toon).unwrap();
+    assert_eq!(back, record);
+    assert_eq!(back.extra.keys().collect::<Vec<_>>(), vec!["zeta", "alpha"]);
+
+    let json = serde_json::to_string(&record.extra).unwrap();
+    assert_eq!(json, r#"{"zeta":1,"alpha":"x"}"#);
+
+    let mut sorted = BTreeMap::new();
+    sorted.insert("b".to_string(), Value::Null);
+    sorted.insert("a".to_string(), Value::Null);
+    let map = ToonMap::from(sorted);
+    assert_eq!(map.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+}
+
+#[test]
+fn test_value_builders() {
+    let table = Value::table(["id", "name", "price"])
+        .row((1, "Widget", 9.99))
+        .rows(vec![vec![
+            Value::from(2),
+            Value::from("Gadget"),
+            Value::Null,
+        ]])
+        .build();
+    assert_eq!(
+        to_string(&table).unwrap(),
+        "[2]{id,name,price}:\n  1,Widget,9.99\n  2,Gadget,null"
+    );
+
+    let value = Value::object()
+        .field("tags", Value::array().item("a").items(["b", "c"]))
+        .field_opt("missing", None::<i32>)
+        .field("table", table)
+        .build();
+    let parsed: Value = from_str(&to_string(&value).unwrap()).unwrap();
+    let obj = parsed.as_object().unwrap();
+    assert_eq!(obj.len(), 2);
+    assert_eq!(
+        obj.get("tags").and_then(|v| v.as_array()).map(|a| a.len()),
+        Some(3)
+    );
+}
+
+#[test]
+#[should_panic(expected = "table row has 1 cells but the table has 2 columns")]
+fn test_table_builder_rejects_ragged_rows() {
+    let _ = Value::table(["id", "name"]).row((1,));
+}
+
+#[test]
+fn test_bytes_formats() {
+    use serde_toon::{from_str_with_options, to_string_with_options, BytesFormat, ToonOptions};
+
+    // Minimal stand-in for `serde_bytes`
+    mod as_bytes {
+        use serde::{de, Deserializer, Serializer};
+        use std::fmt;
+
+        pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(bytes)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<u8>, D::Error> {
+            struct BytesVisitor;
+
+            impl<'de> de::Visitor<'de> for BytesVisitor {
+                type Value = Vec<u8>;
+
+                fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    f.write_str("bytes")
+                }
+
+                fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Vec<u8>, E> {
+                    Ok(v)
+                }
+
+                fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+                    let mut bytes = Vec::new();
+                    while let Some(byte) = seq.next_element()? {
+                        bytes.push(byte);
+                    }
+                    Ok(bytes)
+                }
+            }
+
+            deserializer.deserialize_byte_buf(BytesVisitor)
+        }
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Blob {
+        name: String,
+        #[serde(with = "as_bytes")]
+        data: Vec<u8>,
+    }
+
+    let blob = Blob {
+        name: "logo".to_string(),
+        data: vec![0, 1, 2, 250, 255],
+    };
+
+    let cases = [
+        (
+            BytesFormat::IntArray,
+            "name: logo\ndata: [5]: 0,1,2,250,255",
+        ),
+        (BytesFormat::Base64, "name: logo\ndata: AAEC+v8="),
+        (BytesFormat::Hex, "name: logo\ndata: 000102faff"),
+    ];
+    for (format, expected) in cases {
+        let options = ToonOptions::new().with_bytes_format(format);
+        let toon = to_string_with_options(&blob, options.clone()).unwrap();
+        assert_eq!(toon, expected);
+        let back: Blob = from_str_with_options(&toon, options.clone()).unwrap();
+        assert_eq!(back, blob);
+
+        // to_value honours the format as well
+        let value = ValueSerializer::with_options(options);
+        assert!(blob.serialize(value).is_ok());
+    }
+
+    // Integer arrays are accepted whatever the configured format
+    let options = ToonOptions::new().with_bytes_format(BytesFormat::Base64);
+    let back: Blob = from_str_with_options("name: x\ndata: [2]: 7,8", options.clone()).unwrap();
+    assert_eq!(back.data, vec![7, 8]);
+    assert!(from_str_with_options::<Blob>("name: x\ndata: not*base64", options).is_err());
+}
+
+#[test]
+fn test_unquoted_strings_starting_with_digits() {
+    let value: Value = from_str("a: 3rd\nb: 2024-01-01\nc: [3]: 1x,-2,0.5").unwrap();
+    let obj = value.as_object().unwrap();
+    assert_eq!(obj.get("a").and_then(|v| v.as_str()), Some("3rd"));
+    assert_eq!(obj.get("b").and_then(|v| v.as_str()), Some("2024-01-01"));
+    assert_eq!(
+        obj.get("c"),
+        Some(&Value::Array(vec![
+            Value::from("1x"),
+            Value::from(-2),
+            Value::from(0.5)
+        ]))
+    );
+}
+
+#[test]
+fn test_enum_representations() {
+    use serde_toon::{from_str_with_options, to_string_with_options, EnumRepr, ToonOptions};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Empty,
+        Circle(u32),
+        Point(i32, i32),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Drawing {
+        name: String,
+        shape: Shape,
+    }
+
+    let shapes = [
+        Shape::Empty,
+        Shape::Circle(3),
+        Shape::Point(1, -2),
+        Shape::Rect { w: 4, h: 5 },
+    ];
+
+    let external = ToonOptions::new();
+    assert_eq!(
+        to_string_with_options(&shapes[3], external.clone()).unwrap(),
+        "Rect:\n  w: 4\n  h: 5"
+    );
+
+    let adjacent = ToonOptions::new().with_enum_repr(EnumRepr::adjacent("t", "c"));
+    assert_eq!(
+        to_string_with_options(&shapes[2], adjacent.clone()).unwrap(),
+        "t: Point\nc: [2]: 1,-2"
+    );
+
+    let internal = ToonOptions::new().with_enum_repr(EnumRepr::internal("type"));
+    assert_eq!(
+        to_string_with_options(&shapes[0], internal.clone()).unwrap(),
+        "type: Empty"
+    );
+
+    for options in [external, adjacent, internal.clone()] {
+        for shape in &shapes {
+            if matches!(options.enum_repr, EnumRepr::Internal { .. })
+                && matches!(shape, Shape::Circle(_) | Shape::Point(..))
+            {
+                let err = to_string_with_options(shape, options.clone()).unwrap_err();
+                assert!(matches!(err, serde_toon::Error::UnsupportedType { .. }));
+                continue;
+            }
+
+            // Top level and nested inside a struct
+            let toon = to_string_with_options(shape, options.clone()).unwrap();
+            let back: Shape = from_str_with_options(&toon, options.clone()).unwrap();
+            assert_eq!(&back, shape, "{}", toon);
+
+            let drawing = Drawing {
+                name: "d".to_string(),
+                shape: shape.clone(),
+            };
+            let toon = to_string_with_options(&drawing, options.clone()).unwrap();
+            let back: Drawing = from_str_with_options(&toon, options.clone()).unwrap();
+            assert_eq!(back, drawing, "{}", toon);
+        }
+    }
+
+    let untagged = ToonOptions::new().with_enum_repr(EnumRepr::Untagged);
+    assert_eq!(
+        to_string_with_options(&shapes[1], untagged.clone()).unwrap(),
+        "3"
+    );
+    assert_eq!(
+        to_string_with_options(&shapes[0], untagged.clone()).unwrap(),
+        "null"
+    );
+    assert!(from_str_with_options::<Shape>("3", untagged).is_err());
+}
+
+#[test]
+fn test_duplicate_keys() {
+    use serde_toon::{from_str_with_options, DuplicateKeys};
+
+    let doc = "a: 1\nb:\n  c: x\n  c: y\na: 2";
+    let field = |value: &Value, key: &str| value.as_object().and_then(|o| o.get(key)).cloned();
+
+    let value: Value = from_str(doc).unwrap();
+    assert_eq!(field(&value, "a"), Some(Value::from(2)));
+
+    let options = ToonOptions::new().with_duplicate_keys(DuplicateKeys::FirstWins);
+    let value: Value = from_str_with_options(doc, options).unwrap();
+    assert_eq!(field(&value, "a"), Some(Value::from(1)));
+    let inner = field(&value, "b").unwrap();
+    assert_eq!(field(&inner, "c"), Some(Value::from("x")));
+
+    let options = ToonOptions::new().with_duplicate_keys(DuplicateKeys::Error);
+    match from_str_with_options::<Value>(doc, options).unwrap_err() {
+        Error::DuplicateKey { key, line, col } => {
+            assert_eq!((key.as_str(), line, col), ("c", 4, 3));
+        }
+        other => panic!("Expected duplicate key error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_warnings_with_successful_parse() {
+    use serde_toon::{from_str_with_warnings, Deserializer, DuplicateKeys};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Note {
+        title: String,
+        tags: Vec<String>,
+    }
+
+    // Repeated keys are reported where they repeat, with the value kept
+    let (value, warnings) = from_str_with_warnings::<Value>("a: 1\nb:\n  c: x\n  c: y\na: 2");
+    assert_eq!(value.unwrap(), toon!({"a": 2, "b": {"c": "y"}}));
+    let found: Vec<_> = warnings
+        .iter()
+        .map(|w| (w.kind, w.line, w.message.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (
+                WarningKind::DuplicateKey,
+                Some(4),
+                "repeated key `c`; kept the last value"
+            ),
+            (
+                WarningKind::DuplicateKey,
+                Some(5),
+                "repeated key `a`; kept the last value"
+            ),
+        ]
+    );
+
+    // Streamed struct fields report them too, and warnings survive errors
+    let options = ToonOptions::new().with_duplicate_keys(DuplicateKeys::FirstWins);
+    let mut de = Deserializer::with_options("title: \"a\\qb\"\ntitle: c\ntags: [1]: x", options);
+    let note = Note::deserialize(&mut de).unwrap();
+    assert_eq!(note.title, "a\\qb");
+    let kinds: Vec<_> = de.warnings().iter().map(|w| w.kind).collect();
+    assert_eq!(
+        kinds,
+        [WarningKind::UnknownEscape, WarningKind::DuplicateKey]
+    );
+    assert_eq!(
+        (de.warnings()[0].line, de.warnings()[0].col),
+        (Some(1), Some(10))
+    );
+
+    let (result, warnings) = from_str_with_warnings::<Note>("title: \"\\x\"\ntags: 5");
+    assert!(result.is_err());
+    assert_eq!(warnings[0].message, "kept unknown escape `\\x` as written");
+}
+
+#[test]
+fn test_deny_unknown_fields() {
+    use serde_toon::{from_str_with_options, EnumRepr};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Address {
+        city: String,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Person {
+        name: String,
+        address: Option<Address>,
+        friends: Vec<Address>,
+    }
+
+    let doc = "name: Ada\naddress:\n  city: London\n  zip: N1\nfriends: [0]:";
+    let person: Person = from_str(doc).unwrap();
+    assert_eq!(person.address.unwrap().city, "London");
+
+    let strict = ToonOptions::new().with_deny_unknown_fields(true);
+    match from_str_with_options::<Person>(doc, strict.clone()).unwrap_err() {
+        Error::UnknownField {
+            field,
+            path,
+            expected,
+        } => {
+            assert_eq!(field, "zip");
+            assert_eq!(path.to_string(), "address.zip");
+            assert_eq!(expected, "city");
+        }
+        other => panic!("Expected unknown field error, got {:?}", other),
+    }
+
+    let doc = "name: Ada\naddress: null\nfriends: [2]{city,zip}:\n  Paris,75\n  Rome,00";
+    let err = from_str_with_options::<Person>(doc, strict.clone()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Unknown field `zip` at friends[0].zip, expected one of: city"
+    );
+
+    let err = from_str_with_options::<Person>("nick: A\nname: Ada", strict.clone()).unwrap_err();
+    assert!(err.to_string().contains("at nick,"), "{}", err);
+
+    let ok = "name: Ada\naddress:\n  city: London\nfriends: [1]{city}:\n  Paris";
+    let person: Person = from_str_with_options(ok, strict.clone()).unwrap();
+    assert_eq!(person.friends[0].city, "Paris");
+
+    // Enum tags are not mistaken for unknown fields
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Shape {
+        Circle { radius: u32 },
+    }
+    let tagged = strict.with_enum_repr(EnumRepr::internal("type"));
+    let shape: Shape = from_str_with_options("type: Circle\nradius: 2", tagged.clone()).unwrap();
+    assert_eq!(shape, Shape::Circle { radius: 2 });
+    assert!(from_str_with_options::<Shape>("type: Circle\nradius: 2\nx: 1", tagged).is_err());
+}
+
+#[test]
+fn test_key_order_round_trip() {
+    use serde_toon::{from_str_with_options, to_string_with_options, KeyOrder, ToonMap};
+
+    fn keys(value: &Value) -> Vec<String> {
+        value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    // Object, table (array of objects), list item with a nested object
+    let value = toon!({
+        "zeta": 1,
+        "alpha": {"y": 1, "x": 2},
+        "rows": [{"name": "A", "id": 1}, {"id": 2, "name": "B"}],
+        "mixed": [{"b": 1, "a": "x"}, 3]
+    });
+
+    let toon = to_string(&value).unwrap();
+    assert_eq!(
+        toon,
+        "zeta: 1\nalpha:\n  y: 1\n  x: 2\nrows: [2]{name,id}:\n  A,1\n  B,2\nmixed: [2]:\n  - b: 1\n    a: x\n  - 3"
+    );
+
+    let back: Value = from_str(&toon).unwrap();
+    assert_eq!(keys(&back), vec!["zeta", "alpha", "rows", "mixed"]);
+    let alpha = back.as_object().and_then(|o| o.get("alpha")).unwrap();
+    assert_eq!(keys(alpha), vec!["y", "x"]);
+    assert_eq!(to_string(&back).unwrap(), toon);
+
+    // ToonMap round trip through typed deserialization
+    let rows: Vec<ToonMap> = from_str("[2]{name,id}:\n  A,1\n  B,2").unwrap();
+    assert_eq!(rows[1].keys().collect::<Vec<_>>(), vec!["name", "id"]);
+
+    // Explicit insertion order is the default behavior
+    let insertion = ToonOptions::new().with_canonical_key_order(KeyOrder::Insertion);
+    assert_eq!(to_string_with_options(&value, insertion).unwrap(), toon);
+
+    let alphabetical = ToonOptions::new().with_canonical_key_order(KeyOrder::Alphabetical);
+    let sorted = to_string_with_options(&value, alphabetical.clone()).unwrap();
+    assert_eq!(
+        sorted,
+        "alpha:\n  x: 2\n  y: 1\nmixed: [2]:\n  - a: x\n    b: 1\n  - 3\nrows: [2]{id,name}:\n  1,A\n  2,B\nzeta: 1"
+    );
+    let back: Value = from_str_with_options(&sorted, alphabetical.clone()).unwrap();
+    assert_eq!(keys(&back), vec!["alpha", "mixed", "rows", "zeta"]);
+    assert_eq!(
+        to_string_with_options(&back, alphabetical.clone()).unwrap(),
+        sorted
+    );
+
+    // Explicit tables follow the same order
+    let table = Value::table(["name", "id"]).row(("A", 1)).build();
+    assert_eq!(to_string(&table).unwrap(), "[1]{name,id}:\n  A,1");
+    assert_eq!(
+        to_string_with_options(&table, alphabetical).unwrap(),
+        "[1]{id,name}:\n  1,A"
+    );
+}
+
+#[test]
+fn test_non_string_map_keys() {
+    use serde_toon::{to_string_with_options, NonStringKeys};
+    use std::collections::HashMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
+    enum Tier {
+        Gold,
+        Silver,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    struct UserId(u64);
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Report {
+        by_id: BTreeMap<i64, String>,
+        by_tier: BTreeMap<Tier, u32>,
+        flags: BTreeMap<bool, u8>,
+        users: BTreeMap<UserId, f64>,
+    }
+
+    let report = Report {
+        by_id: BTreeMap::from([(-1, "n".to_string()), (7, "s".to_string())]),
+        by_tier: BTreeMap::from([(Tier::Gold, 1), (Tier::Silver, 2)]),
+        flags: BTreeMap::from([(false, 0), (true, 1)]),
+        users: BTreeMap::from([(UserId(42), 0.5)]),
+    };
+
+    let toon = to_string(&report).unwrap();
+    assert_eq!(
+        toon,
+        "by_id:\n  \"-1\": n\n  \"7\": s\nby_tier:\n  Gold: 1\n  Silver: 2\nflags:\n  \"false\": 0\n  \"true\": 1\nusers:\n  \"42\": 0.5"
+    );
+    assert_eq!(from_str::<Report>(&toon).unwrap(), report);
+
+    // Unquoted numeric keys from hand-written documents parse too
+    let map: HashMap<u32, String> = from_str("1: one\n2: two").unwrap();
+    assert_eq!(map[&2], "two");
+
+    let err = from_str::<HashMap<u32, String>>("x: one").unwrap_err();
+    assert!(err.to_string().contains("invalid value"), "{}", err);
+
+    let options = ToonOptions::new().with_non_string_keys(NonStringKeys::Error);
+    match to_string_with_options(&report, options).unwrap_err() {
+        Error::UnsupportedType { type_name, path } => {
+            assert_eq!(type_name, "map key of type number");
+            assert_eq!(path, "by_id");
+        }
+        other => panic!("Expected UnsupportedType, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_keys_needing_quotes_round_trip() {
+    let map: BTreeMap<String, u32> = ["", "a b", "x:y", "true", "-dash", "[0]", "say \"hi\""]
+        .iter()
+        .enumerate()
+        .map(|(i, key)| (key.to_string(), i as u32))
+        .collect();
+
+    let toon = to_string(&map).unwrap();
+    assert!(toon.contains("\"x:y\": 2"), "{}", toon);
+    assert!(toon.contains("a b: 1"), "{}", toon);
+    assert_eq!(from_str::<BTreeMap<String, u32>>(&toon).unwrap(), map);
+
+    let rows = vec![BTreeMap::from([
+        ("a,b".to_string(), 1),
+        ("c".to_string(), 2),
+    ])];
+    let toon = to_string(&rows).unwrap();
+    assert_eq!(toon, "[1]{\"a,b\",c}:\n  1,2");
+    assert_eq!(from_str::<Vec<BTreeMap<String, u32>>>(&toon).unwrap(), rows);
+}
+
+#[test]
+fn test_top_level_documents() {
+    // Scalar documents
+    for s in [
+        "pipe|here",
+        "a: b",
+        "[3]",
+        "{a}",
+        "- x",
+        "tab\there",
+        "",
+        " padded ",
+        "true",
+        "42",
+    ] {
+        assert_roundtrip(&s.to_string());
+        let value: Value = from_str(&to_string(&s).unwrap()).unwrap();
+        assert_eq!(value, Value::String(s.to_string()));
+    }
+    assert_eq!(from_str::<String>("hello world").unwrap(), "hello world");
+    assert_eq!(
+        from_str::<String>("\"quoted: yes\"").unwrap(),
+        "quoted: yes"
+    );
+    assert_eq!(from_str::<char>("x").unwrap(), 'x');
+    assert_eq!(from_str::<Value>("3.5").unwrap(), Value::from(3.5));
+    assert_eq!(
+        from_str::<Value>("3 apples").unwrap(),
+        Value::from("3 apples")
+    );
+    assert_eq!(from_str::<Value>("null").unwrap(), Value::Null);
+
+    // Array documents, including heterogeneous and nested items
+    let mixed =
+        toon!([1, "a,b", null, [1, 2], {"id": 1, "tags": ["x", "y"]}, {"k": {"deep": true}}]);
+    let toon = to_string(&mixed).unwrap();
+    assert_eq!(from_str::<Value>(&toon).unwrap(), mixed);
+
+    // Object documents, with values dedenting several levels at once
+    let nested: Value = from_str("a:\n  b:\n    c: 1\nd: 2").unwrap();
+    assert_eq!(nested, toon!({"a": {"b": {"c": 1}}, "d": 2}));
+    assert_eq!(from_str::<Value>("").unwrap(), toon!({}));
+
+    // Anything after the root value is rejected unless lenient
+    assert!(from_str::<Value>("[2]: 1,2\nextra").is_err());
+    let options = ToonOptions::new().with_lenient(true);
+    let mut de = serde_toon::Deserializer::with_options("[2]: 1,2\nextra", options);
+    let value = Value::deserialize(&mut de).unwrap();
+    assert_eq!(value, toon!([1, 2]));
+    assert_eq!(de.warnings().len(), 1);
+}
+
+#[test]
+fn test_pretty_output() {
+    let value = toon!({
+        "name": "inventory",
+        "version": 3,
+        "owner": {"team": "ops", "contact": "ops@example.com"},
+        "items": [
+            {"sku": "A1", "name": "Widget", "qty": 2},
+            {"sku": "B22", "name": "Gadget, large", "qty": 10}
+        ],
+        "tags": ["a", "b"],
+        "history": [{"at": 1, "notes": ["x"]}, 7]
+    });
+
+    let compact = to_string(&value).unwrap();
+    let pretty = serde_toon::to_string_with_options(&value, ToonOptions::pretty()).unwrap();
+    assert_ne!(compact, pretty);
+    assert_eq!(
+        pretty,
+        "name: inventory\n\
+         version: 3\n\
+         \n\
+         owner:\n  team: ops\n  contact: ops@example.com\n\
+         \n\
+         items: [2]{sku,name,qty}:\n  A1, Widget,         2\n  B22,\"Gadget, large\",10\n\
+         \n\
+         tags: [2]: a,b\n\
+         \n\
+         history: [2]:\n  - at: 1\n    notes: [1]: x\n  - 7"
+    );
+    assert_eq!(from_str::<Value>(&pretty).unwrap(), value);
+
+    // Alignment on its own, without section breaks
+    let options = ToonOptions::new().with_align_table_columns(true);
+    let toon = serde_toon::to_string_with_options(&value, options).unwrap();
+    assert!(!toon.contains("\n\n"));
+    assert!(toon.contains("\n  A1, Widget,         2\n"));
+    assert_eq!(from_str::<Value>(&toon).unwrap(), value);
+}
+
+#[test]
+fn test_align_table_columns() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Row {
+        id: u32,
+        city: String,
+        note: Option<String>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Report {
+        region: Vec<Row>,
+    }
+
+    let report = Report {
+        region: vec![
+            Row {
+                id: 7,
+                city: "Zürich".into(),
+                note: None,
+            },
+            Row {
+                id: 1024,
+                city: "Oslo".into(),
+                note: Some("a\tb".into()),
+            },
+            Row {
+                id: 33,
+                city: "São Paulo, BR".into(),
+                note: Some("ok".into()),
+            },
+        ],
+    };
+
+    let options = ToonOptions::new().with_align_table_columns(true);
+    let toon = serde_toon::to_string_with_options(&report, options.clone()).unwrap();
+    assert_eq!(
+        toon,
+        "region: [3]{id,city,note}:\n  \
+         7,   Zürich,         null\n  \
+         1024,Oslo,           \"a\\tb\"\n  \
+         33,  \"São Paulo, BR\",ok"
+    );
+    assert_eq!(from_str::<Report>(&toon).unwrap(), report);
+
+    // Tables nested in list items line up too
+    let nested = toon!([{"rows": [{"a": 1, "b": "x"}, {"a": 100, "b": "y"}]}, 5]);
+    let toon = serde_toon::to_string_with_options(&nested, options).unwrap();
+    assert!(toon.contains("\n      1,  x\n      100,y"));
+    assert_eq!(from_str::<Value>(&toon).unwrap(), nested);
+
+    // Off by default
+    assert!(to_string(&report).unwrap().contains("\n  7,Zürich,null\n"));
+}
+
+#[test]
+fn test_nested_newtype_structs() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Id(u32);
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Group {
+        owner: Id,
+        members: Vec<Id>,
+    }
+
+    let group: Group = from_str("owner: 1\nmembers: [2]: 2,3").unwrap();
+    assert_eq!(
+        group,
+        Group {
+            owner: Id(1),
+            members: vec![Id(2), Id(3)]
+        }
+    );
+}
+
+#[test]
+fn test_sparse_tables() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Event {
+        id: u32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        user: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Log {
+        events: Vec<Event>,
+    }
+
+    let event = |id, user: Option<&str>, error: Option<&str>| Event {
+        id,
+        user: user.map(String::from),
+        error: error.map(String::from),
+    };
+    let log = Log {
+        events: vec![
+            event(1, Some("ada"), None),
+            event(2, None, None),
+            event(3, Some("bob"), None),
+            event(4, Some("cy"), Some("timeout")),
+        ],
+    };
+
+    // Without the option, differing keys force list form
+    assert!(to_string(&log)
+        .unwrap()
+        .starts_with("events: [4]:\n  - id: 1"));
+
+    let options = ToonOptions::new().with_sparse_tables(0.25);
+    let toon = serde_toon::to_string_with_options(&log, options).unwrap();
+    assert_eq!(
+        toon,
+        "events: [4]{id,user,error}:\n  1,ada,null\n  2,null,null\n  3,bob,null\n  4,cy,timeout"
+    );
+    assert_eq!(from_str::<Log>(&toon).unwrap(), log);
+
+    // `error` is only in a quarter of the rows
+    let options = ToonOptions::new().with_sparse_tables(0.5);
+    let toon = serde_toon::to_string_with_options(&log, options).unwrap();
+    assert!(toon.starts_with("events: [4]:\n  - id: 1"));
+    assert_eq!(from_str::<Log>(&toon).unwrap(), log);
+
+    // Nested values still rule out a table
+    let nested = toon!([{"a": 1}, {"b": [1, 2]}]);
+    let options = ToonOptions::new().with_sparse_tables(0.0);
+    let toon = serde_toon::to_string_with_options(&nested, options).unwrap();
+    assert_eq!(from_str::<Value>(&toon).unwrap(), nested);
+}
+
+#[test]
+fn test_row_numbers() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Item {
+        sku: String,
+        qty: u32,
+    }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Order {
+        id: u32,
+        items: Vec<Item>,
+    }
+
+    let order = Order {
+        id: 5,
+        items: vec![
+            Item {
+                sku: "A1".into(),
+                qty: 2,
+            },
+            Item {
+                sku: "B2".into(),
+                qty: 10,
+            },
+        ],
+    };
+    let options = ToonOptions::new().with_row_numbers(true);
+    let toon = serde_toon::to_string_with_options(&order, options.clone()).unwrap();
+    assert_eq!(toon, "id: 5\nitems: [2]{#,sku,qty}:\n  1,A1,2\n  2,B2,10");
+    assert_eq!(
+        serde_toon::from_str_with_options::<Order>(&toon, options.clone()).unwrap(),
+        order
+    );
+
+    // The row number column is aligned like any other
+    let aligned =
+        serde_toon::to_string_with_options(&order, options.clone().with_align_table_columns(true))
+            .unwrap();
+    assert!(aligned.ends_with("{#,sku,qty}:\n  1,A1,2\n  2,B2,10"));
+
+    // Tables without a `#` column read as usual
+    let plain = to_string(&order).unwrap();
+    assert_eq!(
+        serde_toon::from_str_with_options::<Order>(&plain, options.clone()).unwrap(),
+        order
+    );
+
+    // Without the option, `#` is an ordinary column
+    let value: Value = from_str(&toon).unwrap();
+    let items = value.as_object().unwrap().get("items").unwrap();
+    assert_eq!(
+        items.as_array().unwrap()[1].as_object().unwrap().get("#"),
+        Some(&toon!(2))
+    );
+
+    // Wrong numbers are rejected, or reported in lenient mode
+    let skipped = "id: 5\nitems: [2]{#,sku,qty}:\n  1,A1,2\n  3,B2,10";
+    assert!(serde_toon::from_str_with_options::<Order>(skipped, options.clone()).is_err());
+    let mut de = serde_toon::Deserializer::with_options(skipped, options.with_lenient(true));
+    assert_eq!(Order::deserialize(&mut de).unwrap(), order);
+    assert_eq!(de.warnings()[0].kind, WarningKind::RowNumberMismatch);
+}
+
+#[test]
+fn test_table_rows_as_tuples_and_arrays() {
+    let input = "[3]{id,name,score}:\n  1,Alice,9.5\n  2,Bob,7\n  3,Carol,8.25";
+    let rows: Vec<(u32, String, f64)> = from_str(input).unwrap();
+    assert_eq!(rows[1], (2, "Bob".to_string(), 7.0));
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Mesh {
+        name: String,
+        vertices: Vec<[f64; 3]>,
+        faces: Vec<Vec<u32>>,
+    }
+    let mesh: Mesh = from_str(
+        "name: tri\nvertices: [3]{x,y,z}:\n  0,0,0\n  1,0,0\n  0,1,0.5\nfaces: [1]{a,b,c}:\n  0,1,2",
+    )
+    .unwrap();
+    assert_eq!(mesh.vertices[2], [0.0, 1.0, 0.5]);
+    assert_eq!(mesh.faces, vec![vec![0, 1, 2]]);
+
+    // Rows still read as objects and structs
+    let objects: Vec<BTreeMap<String, Value>> = from_str(input).unwrap();
+    assert_eq!(objects[0]["name"], toon!("Alice"));
+
+    // Width mismatches are errors
+    assert!(from_str::<Vec<(u32, String)>>(input).is_err());
+    assert!(from_str::<Vec<[f64; 4]>>("[1]{x,y,z}:\n  1,2,3").is_err());
+
+    // Plain objects are not sequences
+    assert!(from_str::<(u32, u32)>("a: 1\nb: 2").is_err());
+}
+
+#[test]
+fn test_table_rows_as_maps() {
+    use std::collections::HashMap;
+
+    let input = "[2]{id,name,score}:\n  1,Alice,9.5\n  2,Bob,7";
+    let rows: Vec<HashMap<String, Value>> = from_str(input).unwrap();
+    assert_eq!(rows[1]["name"], toon!("Bob"));
+    assert_eq!(rows[0].len(), 3);
+
+    // Headers read as keys of any type
+    let rows: Vec<BTreeMap<u32, i64>> = from_str("[2]{\"1\",\"2\"}:\n  10,20\n  30,40").unwrap();
+    assert_eq!(rows[1][&2], 40);
+
+    // A repeated header keeps its last cell, as a repeated object key does
+    let rows: Vec<BTreeMap<String, i64>> = from_str("[1]{a,b,a}:\n  1,2,3").unwrap();
+    assert_eq!(rows[0].len(), 2);
+    assert_eq!(rows[0]["a"], 3);
+    let value: Value = from_str("[1]{a,b,a}:\n  1,2,3").unwrap();
+    assert_eq!(value, toon!([{"a": 3, "b": 2}]));
+
+    // Cells still have to fit the value type
+    let err = from_str::<Vec<BTreeMap<String, i64>>>(input).unwrap_err();
+    assert!(err.to_string().contains("\"Alice\""), "{err}");
+}
+
+#[test]
+fn test_table_headers_match_fields_by_name() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Item {
+        #[serde(rename = "SKU", alias = "code")]
+        sku: String,
+        qty: u32,
+        note: Option<String>,
+    }
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Order {
+        items: Vec<Item>,
+    }
+
+    // Reordered, renamed, aliased and extra headers resolve by name
+    let order: Order = from_str("items: [2]{qty,extra,SKU}:\n  2,x,A1\n  5,y,B2").unwrap();
+    assert_eq!(order.items[1].sku, "B2");
+    assert_eq!(order.items[1].qty, 5);
+    let order: Order = from_str("items: [1]{code,note,qty}:\n  A1,fragile,2").unwrap();
+    assert_eq!(order.items[0].note.as_deref(), Some("fragile"));
+
+    // A missing required column is reported by name, with the table's path
+    let err = from_str::<Order>("items: [1]{sku,qty}:\n  A1,2").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Missing column `SKU` in table at items, which has columns: sku, qty"
+    );
+    assert!(matches!(err, Error::MissingColumn { ref column, .. } if column == "SKU"));
+    let err = from_str::<Vec<Item>>("[1]{qty}:\n  2").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Missing column `SKU` in table at (root), which has columns: qty"
+    );
+
+    // Outside tables, missing fields carry their path too
+    let err = from_str::<Order>("items: [1]:\n  - qty: 1").unwrap_err();
+    assert_eq!(err.to_string(), "Missing field `SKU` at items[0]");
+}
+
+#[test]
+fn test_type_errors_report_path() {
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Price {
+        amount: u32,
+    }
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    enum Plan {
+        Paid { price: Price },
+    }
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct User {
+        name: String,
+        price: u32,
+    }
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Account {
+        users: Vec<User>,
+        plans: BTreeMap<String, Plan>,
+    }
+
+    let err = |input: &str| from_str::<Account>(input).unwrap_err().to_string();
+    let plans = "plans:\n  basic:\n    Paid:\n      price:\n        amount: 1";
+    assert_eq!(
+        err(&format!("users[2]{{name,price}}:\n  a,1\n  b,x\n{}", plans)),
+        "invalid type: string \"x\", expected u32 at users[1].price"
+    );
+    assert_eq!(
+        err("users[1]:\n  - name: a\n    price: 1\nplans:\n  basic:\n    Paid:\n      price:\n        amount: -1"),
+        "invalid value: integer `-1`, expected u32 at plans.basic.Paid.price.amount"
+    );
+    let err = from_str::<Vec<Vec<u8>>>("[2]:\n  - [1]: 1\n  - [2]: 2,300").unwrap_err();
+    match err {
+        Error::Data { path, .. } => assert_eq!(path.to_string(), "[1][1]"),
+        err => panic!("unexpected error: {:?}", err),
+    }
+}
+
+#[test]
+fn test_skip_none_fields() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Contact {
+        email: Option<String>,
+        phone: Option<String>,
+    }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Person {
+        name: String,
+        nickname: Option<String>,
+        contact: Contact,
+        history: Vec<Contact>,
+        pets: Vec<Contact>,
+    }
+
+    let contact = |email: Option<&str>, phone: Option<&str>| Contact {
+        email: email.map(String::from),
+        phone: phone.map(String::from),
+    };
+    let person = Person {
+        name: "Ada".into(),
+        nickname: None,
+        contact: contact(None, Some("555")),
+        history: vec![contact(Some("a@x.io"), None), contact(None, Some("1"))],
+        pets: vec![
+            contact(None, None),
+            Contact {
+                email: Some("p@x.io".into()),
+                phone: None,
+            },
+        ],
+    };
+
+    // Without the option every None is written as null
+    assert!(to_string(&person).unwrap().contains("nickname: null"));
+
+    let options = ToonOptions::new().with_skip_none_fields(true);
+    let toon = serde_toon::to_string_with_options(&person, options).unwrap();
+    assert_eq!(
+        toon,
+        "name: Ada\ncontact:\n  phone: \"555\"\nhistory: [2]{email,phone}:\n  a@x.io,null\n  null,\"1\"\npets: [2]{email,phone}:\n  null,null\n  p@x.io,null"
+    );
+    assert_eq!(from_str::<Person>(&toon).unwrap(), person);
+
+    // List items that lose all their fields read back as empty objects
+    let items = vec![toon!({"a": null}), toon!({"a": 1, "b": [1, 2]})];
+    let options = ToonOptions::new().with_skip_none_fields(true);
+    let toon = serde_toon::to_string_with_options(&items, options).unwrap();
+    assert_eq!(
+        from_str::<Value>(&toon).unwrap(),
+        toon!([{}, {"a": 1, "b": [1, 2]}])
+    );
+}
+
+#[test]
+fn test_resource_limits() {
+    use serde_toon::from_str_with_options;
+
+    fn limit_of(err: Error) -> String {
+        match err {
+            Error::LimitExceeded { limit, .. } => limit,
+            other => panic!("expected a limit error, got {}", other),
+        }
+    }
+
+    // Deep nesting is rejected by default rather than overflowing the stack
+    let deep: String = (0..200)
+        .map(|i| format!("{}k{}:\n", "  ".repeat(i), i))
+        .collect();
+    let err = from_str::<Value>(&deep).unwrap_err();
+    assert_eq!(limit_of(err), "max_depth");
+    let at_limit: String = (0..127)
+        .map(|i| format!("{}k{}:\n", "  ".repeat(i), i))
+        .collect::<String>()
+        + &"  ".repeat(127)
+        + "leaf: 1";
+    assert!(from_str::<Value>(&at_limit).is_ok());
+    let deep_lists: String = (0..300)
+        .map(|i| format!("{}- [1]:\n", " ".repeat(2 + 4 * i)))
+        .collect();
+    let deep_lists = format!("[1]:\n{}", deep_lists);
+    let err = from_str::<Value>(&deep_lists).unwrap_err();
+    assert_eq!(limit_of(err), "max_depth");
+
+    let options = ToonOptions::new().with_max_depth(3);
+    assert!(from_str_with_options::<Value>("a:\n  b: [2]: 1,2", options.clone()).is_ok());
+    let err =
+        from_str_with_options::<Value>("a:\n  b:\n    c: [1]: 1", options.clone()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Input exceeds max_depth of 3 at line 3, column 8"
+    );
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Outer {
+        a: BTreeMap<String, Vec<u32>>,
+    }
+    assert!(from_str_with_options::<Outer>("a:\n  b: [1]: 1", options.clone()).is_ok());
+    assert!(from_str_with_options::<Outer>("a:\n  b: [1]:\n    - [1]: 1", options).is_err());
+
+    // A declared length is rejected before any item is read
+    let options = ToonOptions::new().with_max_array_length(3);
+    let err = from_str_with_options::<Vec<u32>>("[4294967295]: 1", options.clone()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Input exceeds max_array_length of 3 at line 1, column 2"
+    );
+    assert!(from_str_with_options::<Vec<u32>>("[3]: 1,2,3", options.clone()).is_ok());
+    let table = "[4]{id}:\n  1\n  2\n  3\n  4";
+    assert!(from_str_with_options::<Value>(table, options.clone()).is_err());
+    // Arrays with no declared length are counted instead
+    let err = from_str_with_options::<Value>("[]: 1,2,3,4", options.clone()).unwrap_err();
+    assert_eq!(limit_of(err), "max_array_length");
+    let err = from_str_with_options::<Value>("[]:\n  - 1\n  - 2\n  - 3\n  - 4", options.clone())
+        .unwrap_err();
+    assert_eq!(limit_of(err), "max_array_length");
+
+    let options = ToonOptions::new().with_max_string_length(5);
+    assert!(from_str_with_options::<Value>("a: \"hello\"\nb: world", options.clone()).is_ok());
+    for input in [
+        "a: \"hello!\"",
+        "a: hello!",
+        "too_long: 1",
+        "[1]: hello!",
+        "hello!",
+    ] {
+        let err = from_str_with_options::<Value>(input, options.clone()).unwrap_err();
+        assert_eq!(limit_of(err), "max_string_length", "{}", input);
+    }
+
+    let options = ToonOptions::new().with_max_document_size(16);
+    assert!(from_str_with_options::<Value>("a: 1", options.clone()).is_ok());
+    let err = from_str_with_options::<u32>("00000000000000042", options).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Input exceeds max_document_size of 16 at line 1, column 1"
+    );
+}
+
+#[test]
+fn test_fuzz_regressions() {
+    fn round_trip(value: Value) {
+        let toon = to_string(&value).unwrap();
+        let back: Value = serde_toon::from_str_untrusted(&toon)
+            .unwrap_or_else(|err| panic!("{} reading\n{}", err, toon));
+        assert_eq!(to_string(&back).unwrap(), toon);
+        assert_eq!(
+            serde_json::to_value(&back).unwrap(),
+            serde_json::to_value(&value).unwrap(),
+            "{}",
+            toon
+        );
+    }
+
+    // Strings starting like a keyword or number, or holding another delimiter
+    for s in [
+        "true 0", "null[", "0{", "1 2", "-1 x", "a|b", "a,b", "a\tb", " x", "x\t",
+    ] {
+        round_trip(toon!({"s": s, "list": [s, "y"], "rows": [{"a": s}, {"a": 1}]}));
+        round_trip(toon!([s]));
+    }
+    assert_eq!(
+        from_str::<Value>("a: true story\nb: 12 apples\nc: null{").unwrap(),
+        toon!({"a": "true story", "b": "12 apples", "c": "null{"})
+    );
+
+    // Arrays of empty objects are not written as tables without columns
+    round_trip(toon!([{}, {}]));
+    round_trip(toon!({"a": [{}]}));
+
+    // Whole floats beyond the i64 range
+    round_trip(toon!({"x": -1.5e93, "y": [1e20, 2.5]}));
+    assert_eq!(from_str::<f64>("100000000000000000000").unwrap(), 1e20);
+
+    // A list item following a nested list of objects
+    round_trip(toon!([{"a": [{"b": 1}, {"b": [1]}]}, {"c": false}, "x"]));
+    assert_eq!(
+        from_str::<Value>("[3]:\n  - a: [1]:\n      - b: 1\n  - c: false\n  - x").unwrap(),
+        toon!([{"a": [{"b": 1}]}, {"c": false}, "x"])
+    );
+
+    // Negative zero and non-finite floats have canonical forms
+    assert_eq!(
+        to_string(&toon!([-0.0, f64::NAN, f64::INFINITY])).unwrap(),
+        "[3]: 0,null,null"
+    );
+    assert_eq!(to_string(&f64::NEG_INFINITY).unwrap(), "null");
+}
+
+#[test]
+fn test_identifier_strings() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Call {
+        id: String,
+        targets: Vec<String>,
+        callback: String,
+    }
+
+    // Hyphenated UUIDs never look like numbers or contain delimiters, so they
+    // are written bare; URLs contain `:` and must be quoted
+    let call = Call {
+        id: "550e8400-e29b-41d4-a716-446655440000".to_string(),
+        targets: vec![
+            "00000000-0000-0000-0000-000000000000".to_string(),
+            "1e100000-0000-4000-8000-000000000000".to_string(),
+        ],
+        callback: "https://example.com/hook?id=1&x=y#top".to_string(),
+    };
+    let toon = to_string(&call).unwrap();
+    assert_eq!(
+        toon,
+        "id: 550e8400-e29b-41d4-a716-446655440000\n\
+         targets: [2]: 00000000-0000-0000-0000-000000000000,1e100000-0000-4000-8000-000000000000\n\
+         callback: \"https://example.com/hook?id=1&x=y#top\""
+    );
+    assert_eq!(from_str::<Call>(&toon).unwrap(), call);
+
+    #[cfg(all(feature = "uuid", feature = "url"))]
+    {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct TypedCall {
+            id: uuid::Uuid,
+            targets: Vec<uuid::Uuid>,
+            callback: url::Url,
+        }
+
+        let typed: TypedCall = from_str(&toon).unwrap();
+        assert_eq!(typed.id.to_string(), call.id);
+        assert_eq!(to_string(&typed).unwrap(), toon);
+
+        let value: Value = from_str(&toon).unwrap();
+        let fields = value.as_object().unwrap();
+        assert_eq!(fields.get("id").and_then(Value::as_uuid), Some(typed.id));
+        assert_eq!(
+            fields.get("callback").and_then(Value::as_url),
+            Some(typed.callback.clone())
+        );
+        assert_eq!(fields.get("callback").and_then(Value::as_uuid), None);
+        assert_eq!(fields.get("id"), Some(&Value::from(typed.id)));
+    }
+}
+
+#[test]
+fn test_decimal_text() {
+    // Floats are written in their shortest round-trip form, so prices never
+    // pick up binary rounding noise
+    assert_eq!(
+        to_string(&toon!([14.99, 0.1, 19.95, 123456789.01, 100.0])).unwrap(),
+        "[5]: 14.99,0.1,19.95,123456789.01,100"
+    );
+    assert_eq!(from_str::<f64>("14.99").unwrap(), 14.99);
+
+    // Decimal types that serialize as strings (as `rust_decimal` does by
+    // default) keep every digit; the text is quoted because it looks numeric
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Price {
+        amount: String,
+    }
+    let price = Price {
+        amount: "14.990000000000000000000000001".to_string(),
+    };
+    let toon = to_string(&price).unwrap();
+    assert_eq!(toon, "amount: \"14.990000000000000000000000001\"");
+    assert_eq!(from_str::<Price>(&toon).unwrap(), price);
+
+    #[cfg(feature = "rust_decimal")]
+    {
+        use rust_decimal::Decimal;
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Invoice {
+            amount: Decimal,
+            lines: Vec<Decimal>,
+        }
+        let exact: Decimal = price.amount.parse().unwrap();
+        let invoice = Invoice {
+            amount: exact,
+            lines: vec!["14.99".parse().unwrap(), "0.10".parse().unwrap()],
+        };
+        let toon = to_string(&invoice).unwrap();
+        assert_eq!(
+            toon,
+            "amount: \"14.990000000000000000000000001\"\nlines: [2]: \"14.99\",\"0.10\""
+        );
+        assert_eq!(from_str::<Invoice>(&toon).unwrap(), invoice);
+        // Unquoted prices read exactly too
+        let unquoted: Invoice = from_str("amount: 14.99\nlines: [1]: 0.1").unwrap();
+        assert_eq!(unquoted.amount.to_string(), "14.99");
+
+        // `Number::Decimal` is written unquoted with every digit and its scale
+        let value =
+            toon!({"total": (Value::from(exact)), "fee": (Value::from(Decimal::new(150, 2)))});
+        let text = value.to_toon_string(&ToonOptions::new());
+        assert_eq!(text, "total: 14.990000000000000000000000001\nfee: 1.50");
+        let options = ToonOptions::new().with_preserve_number_text(true);
+        let back: Value = serde_toon::from_str_with_options(&text, options).unwrap();
+        let fields = back.as_object().unwrap();
+        assert_eq!(fields.get("total").and_then(Value::as_decimal), Some(exact));
+        assert_eq!(back, value);
+        assert_eq!(Value::from(exact).as_f64(), Some(14.99));
+
+        // Digits beyond what an `f64` keeps survive a round trip exactly,
+        // through `Value` and through fields written with `serde_toon::decimal`
+        let long: Decimal = "1.2345678901234567890123".parse().unwrap();
+        for text in ["1.2345678901234567890123", "x: 1.2345678901234567890123"] {
+            let value: Value = from_str(text).unwrap();
+            assert_eq!(value.to_toon_string(&ToonOptions::new()), text);
+        }
+        let value: Value = from_str("[2]: 1.2345678901234567890123,0.5").unwrap();
+        assert_eq!(
+            value,
+            Value::Array(vec![Value::from(long), Value::from(0.5)])
+        );
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Payment {
+            #[serde(with = "serde_toon::decimal")]
+            amount: Decimal,
+            plain: Decimal,
+        }
+        let payment = Payment {
+            amount: long,
+            plain: long,
+        };
+        let toon = to_string(&payment).unwrap();
+        assert_eq!(
+            toon,
+            "amount: 1.2345678901234567890123\nplain: \"1.2345678901234567890123\""
+        );
+        assert_eq!(from_str::<Payment>(&toon).unwrap(), payment);
+        let unquoted: Payment =
+            from_str("amount: 1.2345678901234567890123\nplain: 1.2345678901234567890123").unwrap();
+        assert_eq!(unquoted, payment);
+        assert_eq!(
+            serde_toon::to_value(&payment).unwrap(),
+            toon!({"amount": (Value::from(long)), "plain": "1.2345678901234567890123"})
+        );
+    }
+}
+
+#[test]
+fn test_key_aliases() {
+    use serde_toon::{from_str_with_options, to_string_with_options, KeyOrder};
+    use std::collections::HashMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Param {
+        identifier: String,
+        description: String,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Tool {
+        identifier: String,
+        description: Option<String>,
+        parameters: Vec<Param>,
+        examples: Vec<Value>,
+    }
+
+    let aliases: HashMap<&str, &str> = [
+        ("identifier", "id"),
+        ("description", "desc"),
+        ("parameters", "params"),
+    ]
+    .into();
+    let options = ToonOptions::new().with_key_aliases(aliases);
+    let tool = Tool {
+        identifier: "search".into(),
+        description: Some("Full-text search".into()),
+        parameters: vec![Param {
+            identifier: "q".into(),
+            description: "Query".into(),
+        }],
+        examples: vec![toon!({"description": "basic", "args": [1]}), toon!(2)],
+    };
+
+    let toon = to_string_with_options(&tool, options.clone()).unwrap();
+    assert_eq!(
+        toon,
+        "id: search\n\
+         desc: Full-text search\n\
+         params: [1]{id,desc}:\n  q,Query\n\
+         examples: [2]:\n  - desc: basic\n    args: [1]: 1\n  - 2"
+    );
+    assert_eq!(
+        from_str_with_options::<Tool>(&toon, options.clone()).unwrap(),
+        tool
+    );
+
+    // Dynamic values see the full keys, and full keys are still accepted
+    let value: Value = from_str_with_options(&toon, options.clone()).unwrap();
+    assert_eq!(
+        value.get_as::<String>("parameters[0].description").unwrap(),
+        "Query"
+    );
+    let full = to_string(&tool).unwrap();
+    assert_eq!(
+        from_str_with_options::<Tool>(&full, options.clone()).unwrap(),
+        tool
+    );
+    assert!(from_str::<Tool>(&toon).is_err());
+
+    // Canonical order sorts by the written text
+    let sorted = options.with_canonical_key_order(KeyOrder::Alphabetical);
+    assert_eq!(
+        to_string_with_options(&toon!({"identifier": 1, "b": 2}), sorted).unwrap(),
+        "b: 2\nid: 1"
+    );
+}
+
+#[test]
+fn test_line_structure() {
+    // Field values run to the end of their line, whatever they contain
+    let value: Value = from_str("msg: hello, world: yes\nat: 12:30").unwrap();
+    assert_eq!(value, toon!({"msg": "hello, world: yes", "at": "12:30"}));
+    assert_eq!(from_str::<Value>("12:30").unwrap(), Value::from("12:30"));
+
+    // Quoted keys may carry array headers, with any delimiter
+    let value: Value = from_str("\"a: b\"[2|]: x|y\nt: [2\t]{a\tb}:\n  1\t2\n  3\t4").unwrap();
+    assert_eq!(
+        value,
+        toon!({"a: b": ["x", "y"], "t": [{"a": 1, "b": 2}, {"a": 3, "b": 4}]})
+    );
+
+    // Blocks nest by indentation, however many close on one line
+    let toon = "items: [2]:\n  - [2]{a,b}:\n      1,2\n      3,4\n  - x: 1\n    y: [1]:\n      - z: 2\nn: 1";
+    let value: Value = from_str(toon).unwrap();
+    assert_eq!(
+        value,
+        toon!({"items": [[{"a": 1, "b": 2}, {"a": 3, "b": 4}], {"x": 1, "y": [{"z": 2}]}], "n": 1})
+    );
+
+    // Errors point at the line they were found on
+    let err = from_str::<Value>("a: [2]{x,y}:\n  1,2\n  3").unwrap_err();
+    assert!(matches!(err, Error::Syntax { line: 3, .. }));
+}
+
+#[test]
+fn test_indentation() {
+    use serde_toon::{from_str_with_options, to_string_with_options};
+
+    // A block may only follow a line that opens one
+    let err = from_str::<Value>("a: 1\n  b: 2").unwrap_err();
+    assert!(matches!(
+        err,
+        Error::IndentationError {
+            line: 2,
+            expected: 0,
+            found: 2,
+            ..
+        }
+    ));
+    // Lines that end blocks must line up with an enclosing one
+    let err = from_str::<Value>("a:\n    b: 1\n  c: 2").unwrap_err();
+    assert!(matches!(err, Error::IndentationError { line: 3, .. }));
+
+    // Lenient parsing reads such lines as part of the enclosing block
+    let options = ToonOptions::new().with_lenient(true);
+    let mut de = serde_toon::Deserializer::with_options("a: 1\n  b: 2", options);
+    assert_eq!(
+        Value::deserialize(&mut de).unwrap(),
+        toon!({"a": 1, "b": 2})
+    );
+    assert_eq!(de.warnings()[0].kind, WarningKind::Indentation);
+
+    // Output with any indent reads back when that width is required
+    let value = toon!({"a": {"b": [1, 2], "c": [{"d": {"e": 1}, "f": [[1], [2]]}]}, "t": [{"x": 1}, {"x": 2}]});
+    for indent in [2, 4] {
+        let toon = to_string_with_options(&value, ToonOptions::new().with_indent(indent)).unwrap();
+        let options = ToonOptions::new().with_indent_width(indent);
+        assert_eq!(
+            from_str_with_options::<Value>(&toon, options).unwrap(),
+            value
+        );
+    }
+    let toon = to_string(&value).unwrap();
+    let options = ToonOptions::new().with_indent_width(4);
+    assert!(from_str_with_options::<Value>(&toon, options).is_err());
+
+    // Tabs are accepted only when allowed
+    let tabbed = "a:\n\tb:\n\t\tc: 1\n\td: [2]:\n\t\t- x\n\t\t- y";
+    assert!(from_str::<Value>(tabbed).is_err());
+    let options = ToonOptions::new().with_allow_tabs(true);
+    assert_eq!(
+        from_str_with_options::<Value>(tabbed, options).unwrap(),
+        toon!({"a": {"b": {"c": 1}, "d": ["x", "y"]}})
+    );
+}
+
+#[test]
+fn test_large_tables() {
+    // Enough rows to be written in several chunks with the `rayon` feature
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Row {
+        id: usize,
+        name: String,
+        score: f64,
+    }
+    let rows: Vec<Row> = (0..5000)
+        .map(|i| Row {
+            id: i,
+            name: if i % 7 == 0 {
+                format!("r,{}", i)
+            } else {
+                format!("r{}", i)
+            },
+            score: i as f64 / 4.0,
+        })
+        .collect();
+
+    let options = ToonOptions::new()
+        .with_row_numbers(true)
+        .with_align_table_columns(true);
+    let toon = serde_toon::to_string_with_options(&rows, options).unwrap();
+    let lines: Vec<&str> = toon.lines().collect();
+    assert_eq!(lines.len(), 5001);
+    assert_eq!(lines[0], "[5000]{#,id,name,score}:");
+    assert_eq!(lines[1], "  1,   0,   \"r,0\",   0");
+    assert_eq!(lines[4097], "  4097,4096,r4096,   1024");
+    assert_eq!(lines[5000], "  5000,4999,r4999,   1249.75");
+
+    let toon = to_string(&rows).unwrap();
+    assert_eq!(from_str::<Vec<Row>>(&toon).unwrap(), rows);
+}
+
+#[test]
+fn test_line_endings() {
+    use serde_toon::{update_in_place, Event, LineEnding, RawToon, ToonWriter};
+
+    let options = ToonOptions::new()
+        .with_line_ending(LineEnding::CrLf)
+        .with_trailing_newline(true);
+    let value = toon!({"id": 1, "note": "two\nlines", "rows": [{"a": 1}, {"a": 2}]});
+    let toon = value.to_toon_string(&options.clone().with_block_strings(4));
+    assert_eq!(
+        toon,
+        "id: 1\r\nnote: |\r\n  two\r\n  lines\r\nrows: [2]{a}:\r\n  1\r\n  2\r\n"
+    );
+    assert_eq!(from_str::<Value>(&toon).unwrap(), value);
+
+    // An empty document stays empty
+    assert_eq!(toon!({}).to_toon_string(&options), "");
+
+    // Streamed output matches
+    let mut writer = ToonWriter::with_options(Vec::new(), options);
+    for event in [
+        Event::StartObject,
+        Event::Key("a".into()),
+        Event::StartObject,
+        Event::Key("b".into()),
+        Event::Scalar(Value::from(1)),
+        Event::End,
+        Event::End,
+    ] {
+        writer.write_event(&event).unwrap();
+    }
+    let written = String::from_utf8(writer.finish().unwrap()).unwrap();
+    assert_eq!(written, "a:\r\n  b: 1\r\n");
+
+    // Raw values have no `\r` left in them
+    #[derive(Deserialize)]
+    struct Envelope<'a> {
+        #[serde(borrow)]
+        body: RawToon<'a>,
+        kind: String,
+    }
+    let envelope: Envelope = from_str("body:\r\n  x: 1\r\n  y: [2]: a,b\r\nkind: k\r\n").unwrap();
+    assert_eq!(envelope.body.get(), "x: 1\ny: [2]: a,b");
+    assert_eq!(envelope.kind, "k");
+
+    // Edits keep a document's line breaks
+    let original = "a: 1\r\nb:\r\n  c: old\r\n";
+    let edited = toon!({"a": 1, "b": {"c": "new"}, "d": [1, 2]});
+    assert_eq!(
+        update_in_place(original, &edited),
+        "a: 1\r\nb:\r\n  c: new\r\nd: [2]: 1,2\r\n"
+    );
+}
+
+#[test]
+fn test_input_preamble() {
+    // A byte order mark and blank lines before the document are skipped
+    let expected = toon!({"a": 1, "b": [1, 2]});
+    for input in [
+        "\u{feff}a: 1\nb: [2]: 1,2",
+        "\n\n  \na: 1\nb: [2]: 1,2",
+        "\u{feff}\r\n\r\na: 1\r\nb: [2]: 1,2\r\n",
+    ] {
+        assert_eq!(from_str::<Value>(input).unwrap(), expected, "{:?}", input);
+    }
+    assert_eq!(
+        serde_toon::from_slice::<Value>(b"\xef\xbb\xbf[2]: x,y").unwrap(),
+        toon!(["x", "y"])
+    );
+    assert_eq!(from_str::<String>("\u{feff}hello").unwrap(), "hello");
+    assert_eq!(
+        serde_toon::get_field("\u{feff}a: 1", "a").unwrap(),
+        Some("1")
+    );
+
+    // Code fences only when asked
+    let reply =
+        "```toon\nitems: [2]{id,note}:\n  1,x\n  2,y\ntext: |\n  ```\n  quoted\n  ```\n```\n";
+    assert!(from_str::<Value>(reply).is_err());
+    let options = ToonOptions::new().with_strip_code_fences(true);
+    assert_eq!(
+        serde_toon::from_str_with_options::<Value>(reply, options).unwrap(),
+        toon!({
+            "items": [{"id": 1, "note": "x"}, {"id": 2, "note": "y"}],
+            "text": "```\nquoted\n```"
+        })
+    );
+    let (value, warnings) = serde_toon::repair("```\ntags: [3]: a,b\n```").unwrap();
+    assert_eq!(value, toon!({"tags": ["a", "b"]}));
+    assert_eq!(warnings.len(), 1);
+}
+
+#[test]
+fn test_preserve_number_text() {
+    let input = "price: 1.50\nscale: 1e3\nzero: -0\nqty: 2\nratios: [3]: 1.0,0.25,10.10\nrows: [2]{id,amount}:\n  1,0.10\n  2,3";
+    let options = ToonOptions::new().with_preserve_number_text(true);
+    let value: Value = serde_toon::from_str_with_options(input, options.clone()).unwrap();
+    assert_eq!(to_string(&value).unwrap(), input);
+    assert_eq!(
+        serde_toon::to_value(&value)
+            .unwrap()
+            .to_toon_string(&ToonOptions::new()),
+        input
+    );
+    assert_eq!(
+        serde_toon::estimated_size(&value).unwrap().bytes,
+        input.len()
+    );
+
+    // Only numbers written differently keep their text, and all compare by value
+    let obj = value.as_object().unwrap();
+    let price = obj.get("price").unwrap();
+    assert!(matches!(price, Value::Number(Number::Raw(text)) if text == "1.50"));
+    assert!(matches!(
+        obj.get("qty"),
+        Some(Value::Number(Number::Integer(2)))
+    ));
+    assert_eq!(price.as_f64(), Some(1.5));
+    assert_eq!(
+        value,
+        toon!({
+            "price": 1.5,
+            "scale": 1000.0,
+            "zero": 0,
+            "qty": 2,
+            "ratios": [1.0, 0.25, 10.1],
+            "rows": [{"id": 1, "amount": 0.1}, {"id": 2, "amount": 3}]
+        })
+    );
+
+    // Typed fields read the value
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Line {
+        price: f64,
+        scale: f64,
+        ratios: Vec<f32>,
+    }
+    let line: Line = serde_toon::from_str_with_options(input, options.clone()).unwrap();
+    assert_eq!(
+        line,
+        Line {
+            price: 1.5,
+            scale: 1000.0,
+            ratios: vec![1.0, 0.25, 10.1],
+        }
+    );
+    assert_eq!(
+        serde_toon::from_str_with_options::<f64>("1.50", options).unwrap(),
+        1.5
+    );
+
+    // Without the option numbers are written as TOON writes them
+    let value: Value = from_str(input).unwrap();
+    assert!(to_string(&value)
+        .unwrap()
+        .starts_with("price: 1.5\nscale: 1000\nzero: 0\n"));
+}
+
+#[test]
+fn test_float_format() {
+    let write = |value: &Value, format: serde_toon::FloatFormat| {
+        let options = ToonOptions::new().with_float_format(format);
+        let toon = serde_toon::to_string_with_options(value, options.clone()).unwrap();
+        assert_eq!(value.to_toon_string(&options), toon);
+        toon
+    };
+    let floats = toon!([9.9996, -0.0004, 0.5, 1.5, 2.5, 1e21, -2.71872, 0.0, 7]);
+    let format = serde_toon::FloatFormat::new().with_max_decimals(3);
+    assert_eq!(
+        write(&floats, format),
+        "[9]: 10,0,0.5,1.5,2.5,1000000000000000000000,-2.719,0,7"
+    );
+    assert_eq!(
+        write(&floats, format.with_max_decimals(0)),
+        "[9]: 10,0,0,2,2,1000000000000000000000,-3,0,7"
+    );
+    assert_eq!(
+        write(
+            &floats,
+            format
+                .with_max_decimals(0)
+                .with_mode(serde_toon::RoundingMode::HalfAwayFromZero)
+        ),
+        "[9]: 10,0,1,2,3,1000000000000000000000,-3,0,7"
+    );
+    assert_eq!(
+        write(&floats, format.with_trim_trailing_zeros(false)),
+        "[9]: 10.000,0.000,0.500,1.500,2.500,1000000000000000000000.000,-2.719,0.000,7"
+    );
+    assert_eq!(
+        write(&floats, serde_toon::FloatFormat::new()),
+        to_string(&floats).unwrap()
+    );
+
+    // Typed values, f32 widened first, and rounded output reads back
+    #[derive(Serialize)]
+    struct Sample {
+        ratio: f32,
+        mean: f64,
+    }
+    let options = ToonOptions::new().with_float_format(format);
+    let toon = serde_toon::to_string_with_options(
+        &Sample {
+            ratio: 0.1,
+            mean: 12.34567,
+        },
+        options,
+    )
+    .unwrap();
+    assert_eq!(toon, "ratio: 0.1\nmean: 12.346");
+    assert_eq!(
+        from_str::<Value>(&toon).unwrap(),
+        toon!({"ratio": 0.1, "mean": 12.346})
+    );
+}
+
+#[test]
+fn test_sort_keys() {
+    use std::collections::HashMap;
+
+    #[derive(Serialize)]
+    struct Snapshot {
+        zone: HashMap<String, u32>,
+        rows: Vec<HashMap<String, u32>>,
+        nested: Vec<HashMap<String, HashMap<String, u32>>>,
+    }
+    let snapshot = || {
+        let keys = (0..20).map(|i| format!("k{:02}", i));
+        let map: HashMap<String, u32> = keys.clone().zip(0..).collect();
+        Snapshot {
+            zone: map.clone(),
+            rows: vec![map.clone(), map.clone()],
+            nested: vec![keys.map(|key| (key, map.clone())).collect()],
+        }
+    };
+
+    // Fresh maps iterate in different orders, but the output does not change
+    let options = ToonOptions::new().with_sort_keys(true);
+    let first = serde_toon::to_string_with_options(&snapshot(), options.clone()).unwrap();
+    assert!(first.starts_with("nested: [1]:\n  - k00:\n      k00: 0\n      k01: 1\n"));
+    assert!(first.contains("\nrows: [2]{k00,k01,k02,"));
+    assert!(first.contains("\nzone:\n  k00: 0\n  k01: 1\n"));
+    for _ in 0..10 {
+        let output = serde_toon::to_string_with_options(&snapshot(), options.clone()).unwrap();
+        assert_eq!(output, first);
+        let value = to_value(&snapshot()).unwrap();
+        assert_eq!(value.to_toon_string(&options), first);
+    }
+
+    assert_eq!(
+        options.with_sort_keys(false).canonical_key_order,
+        Some(serde_toon::KeyOrder::Insertion)
+    );
+}
+
+#[test]
+fn test_map_targets() {
+    use indexmap::IndexMap;
+    use std::collections::HashMap;
+
+    let doc = "zone: eu\nrows: [2]{b,a}:\n  1,2\n  3,4\nitems[1]:\n  - name: x\n    meta:\n      z: 1\n      y: 2";
+
+    let ordered: IndexMap<String, Value> = from_str(doc).unwrap();
+    assert_eq!(
+        ordered.keys().collect::<Vec<_>>(),
+        ["zone", "rows", "items"]
+    );
+
+    #[derive(Deserialize)]
+    struct Doc<M> {
+        rows: Vec<M>,
+        items: Vec<IndexMap<String, Value>>,
+    }
+    let parsed: Doc<IndexMap<String, u32>> = from_str(doc).unwrap();
+    assert_eq!(parsed.rows[1].keys().collect::<Vec<_>>(), ["b", "a"]);
+    assert_eq!(parsed.rows[1]["a"], 4);
+    let meta = match &parsed.items[0]["meta"] {
+        Value::Object(meta) => meta,
+        other => panic!("expected an object, got {:?}", other),
+    };
+    assert_eq!(meta.keys().collect::<Vec<_>>(), ["z", "y"]);
+
+    let parsed: Doc<HashMap<String, u32>> = from_str(doc).unwrap();
+    assert_eq!(
+        parsed.rows[0],
+        HashMap::from([("a".into(), 2), ("b".into(), 1)])
+    );
+    let parsed: Doc<BTreeMap<String, u32>> = from_str(doc).unwrap();
+    assert_eq!(parsed.rows[0].keys().collect::<Vec<_>>(), ["a", "b"]);
+
+    // Nested maps in list items, with non-string keys
+    let doc = "[2]:\n  - meta:\n      2: b\n      1: a\n  - meta:\n      3: c";
+    let items: Vec<HashMap<String, BTreeMap<u8, char>>> = from_str(doc).unwrap();
+    assert_eq!(items[0]["meta"], BTreeMap::from([(1, 'a'), (2, 'b')]));
+    let items: Vec<IndexMap<String, IndexMap<u8, char>>> = from_str(doc).unwrap();
+    assert_eq!(items[0]["meta"].keys().collect::<Vec<_>>(), [&2, &1]);
+    assert_eq!(items[1]["meta"][&3], 'c');
+
+    // Round trips keep the order of the map
+    let rows = vec![IndexMap::from([("z", 1), ("a", 2)]); 2];
+    let text = to_string(&rows).unwrap();
+    assert_eq!(text, "[2]{z,a}:\n  1,2\n  1,2");
+    let back: Vec<IndexMap<String, i32>> = from_str(&text).unwrap();
+    assert_eq!(back[0].keys().collect::<Vec<_>>(), ["z", "a"]);
+}
+
+#[test]
+fn test_matrices() {
+    let matrix = vec![vec![1.5, 2.0], vec![-3.0, 4.25], vec![]];
+    let text = to_string(&matrix).unwrap();
+    assert_eq!(text, "[3]:\n  - [2]: 1.5,2\n  - [2]: -3,4.25\n  - [0]:");
+    assert_eq!(from_str::<Vec<Vec<f64>>>(&text).unwrap(), matrix);
+
+    // Embeddings keep the short form of each f32
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Batch {
+        model: String,
+        embeddings: Vec<[f32; 3]>,
+    }
+    let batch = Batch {
+        model: "mini".to_string(),
+        embeddings: vec![[0.1, -0.2, 0.3], [1e-7, 3.4028235e38, 0.0]],
+    };
+    let text = to_string(&batch).unwrap();
+    assert_eq!(
+        text,
+        "model: mini\nembeddings: [2]:\n  - [3]: 0.1,-0.2,0.3\n  - [3]: 0.0000001,340282350000000000000000000000000000000,0"
+    );
+    assert_eq!(from_str::<Batch>(&text).unwrap(), batch);
+    assert_eq!(
+        to_value(&batch)
+            .unwrap()
+            .to_toon_string(&ToonOptions::default()),
+        text
+    );
+
+    let cube = vec![vec![vec![1, 2], vec![3]], vec![]];
+    let text = to_string(&cube).unwrap();
+    assert_eq!(
+        text,
+        "[2]:\n  - [2]:\n      - [2]: 1,2\n      - [1]: 3\n  - [0]:"
+    );
+    assert_eq!(from_str::<Vec<Vec<Vec<i32>>>>(&text).unwrap(), cube);
+    let value: Value = from_str(&text).unwrap();
+    assert_eq!(value.to_toon_string(&ToonOptions::default()), text);
+}
+
+#[test]
+fn test_vector_format() {
+    use serde_toon::{from_str_with_options, to_string_with_options, VectorFormat};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Chunk {
+        id: u32,
+        embedding: Vec<f32>,
+        scores: [f64; 4],
+        position: (f64, f64),
+        counts: Vec<u32>,
+    }
+    let chunks: Vec<Chunk> = (0..2)
+        .map(|i| Chunk {
+            id: i,
+            embedding: vec![0.25 * i as f32, -1.0, 3.5, 1e-3],
+            scores: [0.5, 1.0, 2.0, 4.0],
+            position: (1.5, 2.5),
+            counts: vec![1, 2, 3, 4],
+        })
+        .collect();
+
+    // Short arrays and arrays without floats are not vectors
+    let options = ToonOptions::new().with_vector_format(VectorFormat::packed_f32().with_min_len(4));
+    let text = to_string_with_options(&chunks[1], options.clone()).unwrap();
+    assert_eq!(
+        text,
+        "id: 1\nembedding: AACAPgAAgL8AAGBAbxKDOg==\nscores: AAAAPwAAgD8AAABAAACAQA==\nposition: [2]: 1.5,2.5\ncounts: [4]: 1,2,3,4"
+    );
+    assert_eq!(
+        from_str_with_options::<Chunk>(&text, options.clone()).unwrap(),
+        chunks[1]
+    );
+    let text = to_string_with_options(&chunks, options.clone()).unwrap();
+    assert_eq!(
+        from_str_with_options::<Vec<Chunk>>(&text, options.clone()).unwrap(),
+        chunks
+    );
+
+    let matrix = vec![vec![0.5, -0.5, 0.25, 8.0]; 2];
+    let text = to_string_with_options(&matrix, options.clone()).unwrap();
+    assert_eq!(
+        text,
+        "[2]:\n  - AAAAPwAAAL8AAIA+AAAAQQ==\n  - AAAAPwAAAL8AAIA+AAAAQQ=="
+    );
+    assert_eq!(
+        from_str_with_options::<Vec<Vec<f64>>>(&text, options.clone()).unwrap(),
+        matrix
+    );
+
+    // Without the option, a packed vector is just a string
+    assert!(from_str::<Vec<Vec<f64>>>(&text).is_err());
+    assert!(from_str_with_options::<Vec<f32>>("AAAA", options).is_err());
+
+    let options = ToonOptions::new().with_vector_format(VectorFormat::decimals(2).with_min_len(4));
+    let embedding = [0.12345, -0.5, 1.0, 0.999];
+    let text = to_string_with_options(&embedding, options.clone()).unwrap();
+    assert_eq!(text, "[4]: 0.12,-0.5,1,1");
+    assert_eq!(
+        to_string_with_options(&embedding[..3], options).unwrap(),
+        "[3]: 0.12345,-0.5,1"
+    );
+}
+
+#[test]
+fn test_type_codecs() {
+    use serde_toon::{from_str_with_options, to_string_with_options, TypeCodec};
+    use std::time::Duration;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Rgb(u8, u8, u8);
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Step {
+        id: u32,
+        wait: Duration,
+        color: Rgb,
+    }
+
+    let seconds = TypeCodec::new(
+        |value| {
+            let secs = value.as_object().and_then(|d| d.get("secs"));
+            Ok(Value::from(format!(
+                "{}s",
+                secs.and_then(Value::as_u64).unwrap()
+            )))
+        },
+        |value| {
+            let secs = value.as_str().and_then(|text| text.strip_suffix('s'));
+            let secs: u64 = secs
+                .ok_or_else(|| Error::custom("expected seconds"))?
+                .parse()
+                .map_err(Error::custom)?;
+            Ok(toon!({"secs": secs, "nanos": 0}))
+        },
+    );
+    let hex = TypeCodec::new(
+        |value| {
+            let text = value
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|c| format!("{:02x}", c.as_u64().unwrap()));
+            Ok(Value::from(format!("#{}", text.collect::<String>())))
+        },
+        |value| {
+            let text = value.as_str().and_then(|text| text.strip_prefix('#'));
+            let text = text.ok_or_else(|| Error::custom("expected a color"))?;
+            let channel = |i: usize| u8::from_str_radix(&text[i..i + 2], 16).map_err(Error::custom);
+            Ok(Value::Array(vec![
+                channel(0)?.into(),
+                channel(2)?.into(),
+                channel(4)?.into(),
+            ]))
+        },
+    );
+    let options = ToonOptions::new()
+        .with_type_codec("Duration", seconds)
+        .with_type_codec("Rgb", hex);
+
+    let steps = vec![
+        Step {
+            id: 1,
+            wait: Duration::from_secs(90),
+            color: Rgb(255, 0, 16),
+        },
+        Step {
+            id: 2,
+            wait: Duration::from_secs(5),
+            color: Rgb(0, 0, 0),
+        },
+    ];
+    let text = to_string_with_options(&steps, options.clone()).unwrap();
+    assert_eq!(text, "[2]{id,wait,color}:\n  1,90s,#ff0010\n  2,5s,#000000");
+    assert_eq!(
+        from_str_with_options::<Vec<Step>>(&text, options.clone()).unwrap(),
+        steps
+    );
+
+    let text = to_string_with_options(&steps[0], options.clone()).unwrap();
+    assert_eq!(text, "id: 1\nwait: 90s\ncolor: #ff0010");
+    assert_eq!(
+        from_str_with_options::<Step>(&text, options.clone()).unwrap(),
+        steps[0]
+    );
+
+    // Codecs apply at the root, in lists and when building values
+    let text = to_string_with_options(&Rgb(1, 2, 3), options.clone()).unwrap();
+    assert_eq!(text, "#010203");
+    assert_eq!(
+        from_str_with_options::<Rgb>(&text, options.clone()).unwrap(),
+        Rgb(1, 2, 3)
+    );
+    let waits: Vec<Vec<Duration>> =
+        from_str_with_options("[1]:\n  - [2]: 1s,2s", options.clone()).unwrap();
+    assert_eq!(waits, [[Duration::from_secs(1), Duration::from_secs(2)]]);
+    let value = steps[1]
+        .serialize(ValueSerializer::with_options(options.clone()))
+        .unwrap();
+    assert_eq!(value, toon!({"id": 2, "wait": "5s", "color": "#000000"}));
+
+    let err = from_str_with_options::<Step>("id: 1\nwait: soon\ncolor: \"#000000\"", options)
+        .unwrap_err();
+    assert_eq!(err.to_string(), "expected seconds at wait");
+}
+
+#[test]
+fn test_env_interpolation() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Upstream {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Config {
+        name: String,
+        port: u16,
+        hosts: Vec<String>,
+        upstreams: Vec<Upstream>,
+        banner: String,
+    }
+
+    let options = ToonOptions::new().with_env_lookup(|name| match name {
+        "PORT" => Some("8080".to_string()),
+        "REGION" => Some("eu".to_string()),
+        "BLANK" => Some(String::new()),
+        _ => None,
+    });
+    let text = "name: \"api-${REGION}\"\nport: ${PORT}\nhosts: [2]: ${REGION}-a,\"${BLANK:-x}\"\n\
+                upstreams[1]{host,port}:\n  ${UPSTREAM:-cache},${PORT}\nbanner: |\n  region ${REGION}\n  cost $${PRICE}";
+    let config: Config = serde_toon::from_str_with_options(text, options.clone()).unwrap();
+    assert_eq!(
+        config,
+        Config {
+            name: "api-eu".to_string(),
+            port: 8080,
+            hosts: vec!["eu-a".to_string(), "x".to_string()],
+            upstreams: vec![Upstream {
+                host: "cache".to_string(),
+                port: 8080,
+            }],
+            banner: "region eu\ncost ${PRICE}".to_string(),
+        }
+    );
+
+    // Unquoted values are typed after substitution; keys are left alone
+    let value: Value =
+        serde_toon::from_str_with_options("\"${PORT}\": ${PORT}", options.clone()).unwrap();
+    assert_eq!(value, toon!({"${PORT}": 8080}));
+    let value: Value =
+        serde_toon::from_str_with_options("port: \"${PORT}\"", options.clone()).unwrap();
+    assert_eq!(value, toon!({"port": "8080"}));
+
+    // Off by default
+    let value: Value = from_str("port: ${PORT}").unwrap();
+    assert_eq!(value, toon!({"port": "${PORT}"}));
+
+    let err = serde_toon::from_str_with_options::<Value>("a: 1\nb: x ${SECRET}", options.clone())
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::Syntax {
+            line: 2,
+            col: 6,
+            ..
+        }
+    ));
+    assert!(err
+        .to_string()
+        .contains("Environment variable `SECRET` is not set"));
+    let err = serde_toon::from_str_with_options::<Value>("b: ${OPEN", options).unwrap_err();
+    assert!(err.to_string().contains("Unterminated `${` reference"));
+}
+
+#[test]
+fn test_option_presets() {
+    let value = toon!({
+        "name": "report",
+        "note": null,
+        "rows": [{"id": 1, "label": "a,b"}, {"id": 2, "label": "true"}],
+    });
+    for name in ToonOptions::PRESETS {
+        let options = ToonOptions::preset(name).unwrap();
+        let toon = serde_toon::to_string_with_options(&value, options.clone()).unwrap();
+        let back: Value = serde_toon::from_str_with_options(&toon, options).unwrap();
+        let expected = if *name == "llm_compact" {
+            toon!({"name": "report", "rows": [{"id": 1, "label": "a,b"}, {"id": 2, "label": "true"}]})
+        } else {
+            value.clone()
+        };
+        assert_eq!(back, expected, "{}", name);
+    }
+
+    assert_eq!(
+        serde_toon::to_string_with_options(&value, ToonOptions::llm_compact()).unwrap(),
+        "name: report\nrows: [|]{id|label}:\n  1|a,b\n  2|\"true\""
+    );
+    assert_eq!(
+        serde_toon::to_string_with_options(&value, "strict-interchange".parse().unwrap()).unwrap(),
+        "name: \"report\"\nnote: null\nrows: [2]{id,label}:\n  1,\"a,b\"\n  2,\"true\""
+    );
+    let err = serde_toon::from_str_with_options::<Value>(
+        "a:\n    b: 1",
+        ToonOptions::strict_interchange(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, Error::IndentationError { .. }), "{}", err);
+    assert!("verbose".parse::<ToonOptions>().is_err());
+}
+
+#[test]
+fn test_omit_lengths() {
+    let options = ToonOptions::new().with_omit_lengths(true);
+    let value = toon!({
+        "ids": [1, 2, 3],
+        "none": [],
+        "users": [{"id": 1, "name": "Ada"}, {"id": 2, "name": "Bob"}],
+        "mixed": [1, {"a": [true]}, [2, 3]],
+    });
+    let toon = serde_toon::to_string_with_options(&value, options.clone()).unwrap();
+    assert_eq!(
+        toon,
+        "ids: []: 1,2,3\nnone: []:\nusers: []{id,name}:\n  1,Ada\n  2,Bob\nmixed: []:\n  - 1\n  - a: []: true\n  - []: 2,3"
+    );
+    let back: Value = serde_toon::from_str_with_options(&toon, options.clone()).unwrap();
+    assert_eq!(back, value);
+
+    // The delimiter is still named where it is not a comma
+    let pipes = options.clone().with_delimiter(Delimiter::Pipe);
+    let toon = serde_toon::to_string_with_options(&value, pipes.clone()).unwrap();
+    assert!(toon.starts_with("ids: [|]: 1|2|3\n"), "{}", toon);
+    let back: Value = serde_toon::from_str_with_options(&toon, options.clone()).unwrap();
+    assert_eq!(back, value);
+
+    // Lengths that are given are still checked
+    let err =
+        serde_toon::from_str_with_options::<Value>("[2]: 1,2,3", options.clone()).unwrap_err();
+    assert!(
+        err.to_string().contains("array declared 2 items but has 3"),
+        "{}",
+        err
+    );
+    let (result, warnings) = {
+        let mut de = serde_toon::Deserializer::with_options("a: []: 1\nb: [2]: 1,2", options);
+        let result = Value::deserialize(&mut de);
+        (result, de.into_warnings())
+    };
+    assert_eq!(result.unwrap(), toon!({"a": [1], "b": [1, 2]}));
+    assert!(warnings.is_empty());
+}
+
+#[test]
+fn test_dual_strings() {
+    #[derive(Serialize)]
+    struct Order {
+        id: u32,
+        customer: String,
+        card: String,
+        total: f64,
+        note: Option<String>,
+        lines: Vec<Line>,
+    }
+
+    #[derive(Serialize)]
+    struct Line {
+        sku: String,
+        qty: u32,
+        price: f64,
+    }
+
+    let order = Order {
+        id: 7,
+        customer: "Ada".into(),
+        card: "4111".into(),
+        total: 30.0,
+        note: None,
+        lines: vec![
+            Line {
+                sku: "A1".into(),
+                qty: 2,
+                price: 12.5,
+            },
+            Line {
+                sku: "B2".into(),
+                qty: 1,
+                price: 5.0,
+            },
+        ],
+    };
+    let options = ToonOptions::new()
+        .with_masked_fields(["card"], "***")
+        .with_skip_none_fields(true)
+        .with_key_aliases([("customer", "c")]);
+    let (toon, json) = serde_toon::to_dual_strings(&order, options.clone()).unwrap();
+    assert_eq!(
+        toon,
+        serde_toon::to_string_with_options(&order, options).unwrap()
+    );
+    assert_eq!(
+        json,
+        r#"{"id":7,"c":"Ada","card":"***","total":30,"lines":[{"sku":"A1","qty":2,"price":12.5},{"sku":"B2","qty":1,"price":5}]}"#
+    );
+
+    // Both hold the same data
+    let from_toon: serde_json::Value = from_str(&toon).unwrap();
+    let from_json: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(from_toon, from_json);
+}
+
+#[test]
+fn test_reference_ts_compat() {
+    #[derive(Serialize)]
+    struct Event {
+        id: u64,
+        kind: &'static str,
+        score: f64,
+        labels: Vec<&'static str>,
+        meta: BTreeMap<&'static str, &'static str>,
+    }
+
+    #[derive(Serialize)]
+    struct Log {
+        events: Vec<Event>,
+    }
+
+    let options = ToonOptions::new().with_compat(serde_toon::CompatMode::ReferenceTs);
+    let log = Log {
+        events: vec![
+            Event {
+                id: 1,
+                kind: "-1 retry",
+                score: f64::NAN,
+                labels: vec!["a", "b"],
+                meta: BTreeMap::new(),
+            },
+            Event {
+                id: 9_007_199_254_740_993,
+                kind: "ok",
+                score: -0.0,
+                labels: vec![],
+                meta: BTreeMap::from([("x-y", "1.50")]),
+            },
+        ],
+    };
+    let toon = serde_toon::to_string_with_options(&log, options.clone()).unwrap();
+    assert_eq!(
+        toon,
+        "events[2]:\n  - id: 1\n    kind: \"-1 retry\"\n    score: null\n    labels[2]: a,b\n    meta:\n  - id: \"9007199254740993\"\n    kind: ok\n    score: 0\n    labels[0]:\n    meta:\n      \"x-y\": \"1.50\""
+    );
+    // Output in the reference layout still parses
+    let back: Value = serde_toon::from_str(&toon).unwrap();
+    assert_eq!(
+        back.as_object()
+            .and_then(|obj| obj.get("events"))
+            .and_then(Value::as_array)
+            .map(Vec::len),
+        Some(2)
+    );
+
+    // Options the reference encoder has no equivalent for are ignored
+    let ignored = options
+        .clone()
+        .with_delimiter_strategy(DelimiterStrategy::Auto)
+        .with_quote_policy(QuotePolicy::AlwaysStrings)
+        .with_escape_non_ascii(true)
+        .with_block_strings(0);
+    let value = toon!({"text": "café\nbar", "zw": "a\u{200b}b", "list": ["a,b", "c"]});
+    assert_eq!(
+        serde_toon::to_string_with_options(&value, ignored).unwrap(),
+        "text: \"café\\nbar\"\nzw: a\u{200b}b\nlist[2]: \"a,b\",c"
+    );
+
+    // Dates as `toISOString` writes them
+    #[cfg(feature = "datetime")]
+    {
+        let date = "2024-01-15T10:30:00Z".parse().unwrap();
+        let value = Value::Object(
+            [("at".to_string(), Value::Date(date))]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(
+            value.to_toon_string(&options),
+            "at: \"2024-01-15T10:30:00.000Z\""
+        );
+    }
+}
+
+#[test]
+fn test_reference_ts_round_trip() {
+    // Strings the reference encoder leaves unquoted read back as strings
+    let options = ToonOptions::new().with_compat(serde_toon::CompatMode::ReferenceTs);
+    let strings = [
+        "NaN", "inf", "Infinity", ".5", "+1", "1.", "007", "1E400", "|", "a|b",
+    ];
+    for s in strings {
+        let value = toon!({"field": s, "list": [s, "x"], "rows": [{"a": s}, {"a": "y"}]});
+        let toon = serde_toon::to_string_with_options(&value, options.clone()).unwrap();
+        let back: Value = from_str(&toon).unwrap();
+        assert!(
+            back.canonical_eq(&value),
+            "{:?} read back from {:?}",
+            s,
+            toon
+        );
+
+        let toon = serde_toon::to_string_with_options(&s, options.clone()).unwrap();
+        assert_eq!(from_str::<String>(&toon).unwrap(), s, "{:?}", toon);
+    }
+
+    // Whole floats beyond the safe integers stay floats, big integers strings
+    let value = toon!({"float": 1.637879327615487e17, "int": 9007199254740993_i64});
+    let toon = serde_toon::to_string_with_options(&value, options.clone()).unwrap();
+    assert_eq!(toon, "float: 163787932761548700\nint: \"9007199254740993\"");
+    let back: Value = serde_toon::from_str_with_options(&toon, options.clone()).unwrap();
+    assert_eq!(
+        back,
+        toon!({"float": 1.637879327615487e17, "int": "9007199254740993"})
+    );
+    assert_eq!(
+        serde_toon::to_string_with_options(&back, options).unwrap(),
+        toon
+    );
+}
+
+#[test]
+fn test_parsed_keys_are_shared() {
+    use serde_toon::Key;
+
+    let toon = "a:\n  id: 1\nb:\n  id: 2\nrows[2]{id,n}:\n  1,2\n  3,4";
+    let value: Value = toon.parse().unwrap();
+    let field = |value: &Value, key: &str| value.as_object().unwrap().get(key).unwrap().clone();
+    let first_key = |value: &Value| value.as_object().unwrap().keys().next().unwrap().clone();
+    let a = first_key(&field(&value, "a"));
+    let b = first_key(&field(&value, "b"));
+    assert!(Key::ptr_eq(&a, &b));
+
+    match field(&value, "rows") {
+        Value::Table(table) => assert!(Key::ptr_eq(&a, &table.headers()[0])),
+        other => panic!("Expected table, got {:?}", other),
+    }
+
+    let (parsed, _) = serde_toon::repair(toon).unwrap();
+    assert!(Key::ptr_eq(
+        &first_key(&field(&parsed, "a")),
+        &first_key(&field(&parsed, "b"))
+    ));
+}
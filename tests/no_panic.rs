@@ -0,0 +1,98 @@
+//! Parsing never panics, whatever the input.
+//!
+//! Every entry point that reads TOON text is run over random inputs built
+//! from TOON's syntax characters, multi-byte characters and line breaks, in
+//! strict and lenient mode. Each may return an error but must not panic;
+//! slicing a string at a byte offset inside a multi-byte character is the
+//! usual culprit. Syntax errors must also point inside the input. The
+//! `fuzz/` targets run the same check without bounds.
+
+use proptest::prelude::*;
+use serde_toon::event::ToonReader;
+use serde_toon::incremental::IncrementalParser;
+use serde_toon::template::Template;
+use serde_toon::{
+    extract_from_text, from_str, from_str_partial, from_str_with_options, get_field, outline,
+    repair, Deserializer, Error, RawToon, Table, ToonOptions, Value,
+};
+use std::collections::BTreeMap;
+
+/// TOON's syntax characters, a few words, multi-byte characters and
+/// whitespace.
+const INPUT: &str =
+    "(?s)([a-z0-9 :,|#\\-\\[\\]{}\"\\\\\n\t.$]|é|😀|\u{301}|\u{a0}|\r\n|  |true|null|```|```json\n){0,80}";
+
+/// Runs every parser over `input`, ignoring their results.
+fn parse_everything(input: &str, chunk: usize) {
+    // A syntax error points at a character of the input, or just past a line
+    if let Err(Error::Syntax { line, col, .. }) = from_str::<Value>(input) {
+        let text = input.split('\n').nth(line.wrapping_sub(1));
+        let width = text.map_or(0, |text| text.chars().count());
+        assert!(
+            text.is_some() && (1..=width + 1).contains(&col),
+            "line {}, column {} is outside {:?}",
+            line,
+            col,
+            input
+        );
+    }
+    let _ = from_str_with_options::<Value>(input, ToonOptions::new().with_lenient(true));
+    let _ = from_str_with_options::<Value>(
+        input,
+        ToonOptions::new()
+            .with_strip_code_fences(true)
+            .with_sniff_delimiters(true)
+            .with_preserve_number_text(true)
+            .with_env_lookup(|name| Some(format!("{}é", name))),
+    );
+    let _ = from_str::<BTreeMap<String, RawToon>>(input);
+    let _ = from_str::<Vec<(String, Option<f64>)>>(input);
+    let _ = from_str_partial::<Value>(input);
+    #[cfg(feature = "json")]
+    let _ = serde_toon::from_str_any::<Value>(input);
+    let _ = repair(input);
+    let _ = extract_from_text(input);
+    let _ = outline(input);
+    let _ = get_field(input, "a.b");
+    let _ = Table::from_markdown(input);
+    let _ = Template::parse(input);
+    let _ = ToonReader::new(input).take(1000).count();
+
+    let mut deserializer = Deserializer::from_str(input).with_spans();
+    let _ = serde::Deserialize::deserialize(&mut deserializer).map(|_: Value| ());
+
+    // Chunks split at any byte offset that is a character boundary
+    let mut parser = IncrementalParser::new();
+    let mut rest = input;
+    while !rest.is_empty() {
+        let mut at = chunk.min(rest.len());
+        while !rest.is_char_boundary(at) {
+            at += 1;
+        }
+        let (head, tail) = rest.split_at(at);
+        if parser.feed(head).is_err() {
+            return;
+        }
+        rest = tail;
+    }
+    let _ = parser.finish();
+}
+
+proptest! {
+    #[test]
+    fn prop_parsers_never_panic(input in INPUT, chunk in 1..8usize) {
+        parse_everything(&input, chunk);
+    }
+
+    #[test]
+    fn prop_parsers_never_panic_on_nested_lines(
+        lines in prop::collection::vec((0..4usize, INPUT), 0..8),
+        chunk in 1..8usize,
+    ) {
+        let input: String = lines
+            .iter()
+            .map(|(depth, line)| format!("{}{}\n", "  ".repeat(*depth), line))
+            .collect();
+        parse_everything(&input, chunk);
+    }
+}
@@ -5,7 +5,7 @@
 
 use proptest::prelude::*;
 use serde::{Deserialize, Serialize};
-use serde_toon::{from_str, to_string};
+use serde_toon::{from_str, from_str_untrusted, to_string, Value};
 
 fn roundtrip<T: Serialize + for<'de> Deserialize<'de> + PartialEq + std::fmt::Debug>(
     value: &T,
@@ -64,3 +64,200 @@ proptest! {
         prop_assert!(roundtrip(&t));
     }
 }
+
+proptest! {
+    // Test parsing of arbitrary input
+    #[test]
+    fn prop_parse_never_panics(input in "[a-z0-9 :,|#\\-\\[\\]{}\"\\\\\n\t.]{0,60}") {
+        if let Ok(value) = from_str_untrusted::<Value>(&input) {
+            let output = to_string(&value).unwrap();
+            prop_assert!(from_str_untrusted::<Value>(&output).is_ok(), "{:?}", output);
+        }
+    }
+
+    #[test]
+    fn prop_strings(v in prop::collection::vec(any::<String>(), 0..5)) {
+        prop_assert!(roundtrip(&v));
+    }
+
+    #[test]
+    fn prop_string_map(m in prop::collection::btree_map(any::<String>(), any::<String>(), 0..5)) {
+        prop_assert!(roundtrip(&m));
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Address {
+    city: String,
+    zip: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Customer {
+    name: String,
+    address: Address,
+    tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct LineItem {
+    sku: String,
+    qty: u32,
+    price: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Order {
+    id: u32,
+    customer: Customer,
+    items: Vec<LineItem>,
+    notes: Vec<Vec<String>>,
+}
+
+prop_compose! {
+    fn text()(s in "[a-zA-Z0-9 ,:|\\-#\\[\\]{}\"]{0,12}") -> String {
+        s
+    }
+}
+
+prop_compose! {
+    fn customer()(
+        name in text(),
+        city in text(),
+        zip in proptest::option::of(text()),
+        tags in prop::collection::vec(text(), 0..3),
+    ) -> Customer {
+        Customer { name, address: Address { city, zip }, tags }
+    }
+}
+
+prop_compose! {
+    fn order()(
+        id in any::<u32>(),
+        customer in customer(),
+        items in prop::collection::vec(
+            (text(), any::<u32>(), -1e6..1e6f64)
+                .prop_map(|(sku, qty, price)| LineItem { sku, qty, price }),
+            0..4,
+        ),
+        notes in prop::collection::vec(prop::collection::vec(text(), 0..3), 0..3),
+    ) -> Order {
+        Order { id, customer, items, notes }
+    }
+}
+
+proptest! {
+    // Structs nested inside list items, at the root and under a key
+    #[test]
+    fn prop_nested_structs_in_lists(orders in prop::collection::vec(order(), 0..4)) {
+        prop_assert!(roundtrip(&orders));
+        let wrapped = std::collections::BTreeMap::from([("orders".to_string(), orders)]);
+        prop_assert!(roundtrip(&wrapped));
+    }
+
+    // Measuring a document counts exactly what writing it would
+    #[test]
+    fn prop_estimated_size(orders in prop::collection::vec(order(), 0..4)) {
+        let toon = to_string(&orders).unwrap();
+        prop_assert_eq!(serde_toon::estimated_size(&orders).unwrap().bytes, toon.len());
+        let wrapped = std::collections::BTreeMap::from([("orders".to_string(), orders)]);
+        let toon = to_string(&wrapped).unwrap();
+        prop_assert_eq!(serde_toon::estimated_size(&wrapped).unwrap().bytes, toon.len());
+    }
+
+    #[test]
+    fn prop_nested_structs_any_indent(orders in prop::collection::vec(order(), 1..3), indent in 1..5usize) {
+        use serde_toon::{from_str_with_options, to_string_with_options, ToonOptions};
+
+        let toon = to_string_with_options(&orders, ToonOptions::new().with_indent(indent)).unwrap();
+        let options = ToonOptions::new().with_indent_width(indent);
+        let back: Vec<Order> = from_str_with_options(&toon, options).unwrap();
+        prop_assert_eq!(back, orders);
+    }
+
+    // Block strings hold any text they are chosen for, nested or not
+    #[test]
+    fn prop_block_strings(texts in prop::collection::vec("[a-z :|\\-\t\n]{0,24}", 1..4)) {
+        use serde_toon::{to_string_with_options, ToonOptions};
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Note {
+            text: String,
+            tags: Vec<Vec<u8>>,
+        }
+
+        let notes: Vec<Note> = texts
+            .into_iter()
+            .map(|text| Note { text, tags: vec![vec![1]] })
+            .collect();
+        let options = ToonOptions::new().with_block_strings(8);
+        let toon = to_string_with_options(&notes, options).unwrap();
+        prop_assert_eq!(from_str::<Vec<Note>>(&toon).unwrap(), notes);
+    }
+
+    // Documents with `\r\n` line breaks read as they would with `\n`
+    #[test]
+    fn prop_crlf_line_endings(orders in prop::collection::vec(order(), 0..4), note in "[a-z :\n]{0,24}") {
+        use serde_toon::{to_string_with_options, LineEnding, ToonOptions, ToonReader};
+
+        let doc = (orders, note);
+        let options = ToonOptions::new().with_block_strings(8);
+        let lf = to_string_with_options(&doc, options.clone()).unwrap();
+        let options = options.with_line_ending(LineEnding::CrLf).with_trailing_newline(true);
+        let crlf = to_string_with_options(&doc, options).unwrap();
+        if lf.is_empty() {
+            prop_assert_eq!(&crlf, "");
+        } else {
+            prop_assert_eq!(&crlf, &format!("{}\r\n", lf.replace('\n', "\r\n")));
+        }
+
+        prop_assert_eq!(&from_str::<(Vec<Order>, String)>(&crlf).unwrap(), &doc);
+        prop_assert_eq!(from_str::<Value>(&crlf).unwrap(), from_str::<Value>(&lf).unwrap());
+        let events = |toon: &str| ToonReader::new(toon).collect::<Result<Vec<_>, _>>().unwrap();
+        prop_assert_eq!(events(&crlf), events(&lf));
+    }
+
+    // Per-array delimiters read back without options, whatever the cells hold
+    #[test]
+    fn prop_auto_delimiters(
+        rows in prop::collection::vec(("[a-z ,|\t]{1,8}", "[a-z,|]{1,8}"), 1..5),
+        items in prop::collection::vec("[a-z ,|\t]{1,8}", 0..5),
+    ) {
+        use serde_toon::{to_string_with_options, DelimiterStrategy, ToonOptions};
+
+        let options = ToonOptions::new().with_delimiter_strategy(DelimiterStrategy::Auto);
+        let table: Vec<_> = rows
+            .into_iter()
+            .map(|(a, b)| std::collections::BTreeMap::from([("a", a), ("b", b)]))
+            .collect();
+        let toon = to_string_with_options(&(table.clone(), items.clone()), options).unwrap();
+        let back: (Vec<std::collections::BTreeMap<String, String>>, Vec<String>) =
+            from_str(&toon).unwrap();
+        prop_assert_eq!(back.1, items);
+        prop_assert_eq!(back.0.len(), table.len());
+        for (row, expected) in back.0.iter().zip(&table) {
+            prop_assert_eq!(&row["a"], &expected["a"]);
+            prop_assert_eq!(&row["b"], &expected["b"]);
+        }
+    }
+}
+
+#[cfg(feature = "proptest")]
+proptest! {
+    // Under ReferenceTs, a value read back writes the same document again,
+    // including whole floats beyond the integers JavaScript holds exactly
+    #[test]
+    fn prop_reference_ts_round_trip(
+        value in prop_oneof![
+            serde_toon::strategy::any_value(),
+            (-1e21..1e21f64).prop_map(|f| Value::from(f.trunc())),
+        ]
+    ) {
+        use serde_toon::{from_str_with_options, to_string_with_options, CompatMode, ToonOptions};
+
+        let options = ToonOptions::new().with_compat(CompatMode::ReferenceTs);
+        let toon = to_string_with_options(&value, options.clone()).unwrap();
+        let back: Value = from_str_with_options(&toon, options.clone()).unwrap();
+        prop_assert_eq!(to_string_with_options(&back, options).unwrap(), toon);
+    }
+}
@@ -0,0 +1,319 @@
+//! Compatibility of serde attributes with serde_json's behavior.
+//!
+//! Every type is written to TOON and read back, and the TOON document must
+//! hold the same data as serde_json produces for the type. Documents read from
+//! TOON must give the same result as the equivalent JSON.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_toon::{from_str, to_string, Value};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+/// Round-trips `value` through TOON, checking the document against
+/// serde_json's view of `value`, and returns the document.
+fn check<T>(value: &T) -> String
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let toon = to_string(value).unwrap();
+    let back: T = from_str(&toon).unwrap_or_else(|err| panic!("{} reading\n{}", err, toon));
+    assert_eq!(&back, value, "round trip through\n{}", toon);
+
+    let parsed: Value = from_str(&toon).unwrap();
+    assert_eq!(
+        serde_json::to_value(&parsed).unwrap(),
+        serde_json::to_value(value).unwrap(),
+        "data of\n{}",
+        toon
+    );
+    assert_eq!(serde_toon::to_value(value).unwrap(), parsed);
+    toon
+}
+
+/// Reads the same document from TOON and JSON, checking both agree.
+fn read_both<T>(toon: &str, json: &str) -> T
+where
+    T: DeserializeOwned + PartialEq + Debug,
+{
+    let from_json: T = serde_json::from_str(json).unwrap();
+    let from_toon: T = from_str(toon).unwrap();
+    assert_eq!(from_toon, from_json);
+    from_toon
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+struct Audit {
+    created_by: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reviewed_by: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Document {
+    id: u32,
+    #[serde(flatten)]
+    audit: Audit,
+    title: String,
+    #[serde(flatten)]
+    extra: BTreeMap<String, Value>,
+}
+
+#[test]
+fn flatten_keeps_field_order() {
+    let mut extra = BTreeMap::new();
+    extra.insert("pages".to_string(), Value::from(12));
+    extra.insert("tags".to_string(), serde_toon::toon!(["a", "b"]));
+    let doc = Document {
+        id: 1,
+        audit: Audit {
+            created_by: "ada".into(),
+            reviewed_by: None,
+        },
+        title: "Spec".into(),
+        extra,
+    };
+    assert_eq!(
+        check(&doc),
+        "id: 1\ncreated_by: ada\ntitle: Spec\npages: 12\ntags: [2]: a,b"
+    );
+
+    let doc: Document = read_both(
+        "title: x\nid: 2\ncreated_by: bo\nreviewed_by: cy\nnote: hi",
+        r#"{"title":"x","id":2,"created_by":"bo","reviewed_by":"cy","note":"hi"}"#,
+    );
+    assert_eq!(doc.audit.reviewed_by.as_deref(), Some("cy"));
+    assert_eq!(doc.extra["note"], Value::from("hi"));
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Entry {
+    id: u32,
+    #[serde(flatten)]
+    audit: Audit,
+}
+
+#[test]
+fn flatten_in_table_rows() {
+    let entries = vec![
+        Entry {
+            id: 1,
+            audit: Audit {
+                created_by: "ada".into(),
+                reviewed_by: None,
+            },
+        },
+        Entry {
+            id: 2,
+            audit: Audit {
+                created_by: "bo".into(),
+                reviewed_by: None,
+            },
+        },
+    ];
+    assert_eq!(check(&entries), "[2]{id,created_by}:\n  1,ada\n  2,bo");
+
+    // Rows with differing keys fall back to a list
+    let mut mixed = entries;
+    mixed[1].audit.reviewed_by = Some("cy".into());
+    assert!(check(&mixed).starts_with("[2]:\n  - id: 1"));
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "kind")]
+enum Shape {
+    Circle { radius: f64 },
+    Rect { w: f64, h: f64 },
+    Empty,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Layer {
+    name: String,
+    #[serde(flatten)]
+    shape: Shape,
+}
+
+#[test]
+fn internally_tagged_enums() {
+    assert_eq!(
+        check(&Shape::Circle { radius: 1.5 }),
+        "kind: Circle\nradius: 1.5"
+    );
+    assert_eq!(check(&Shape::Empty), "kind: Empty");
+    assert_eq!(
+        check(&vec![
+            Shape::Rect { w: 1.5, h: 2.5 },
+            Shape::Rect { w: 3.5, h: 0.5 }
+        ]),
+        "[2]{kind,w,h}:\n  Rect,1.5,2.5\n  Rect,3.5,0.5"
+    );
+    check(&vec![Shape::Circle { radius: 0.5 }, Shape::Empty]);
+    check(&Layer {
+        name: "bg".into(),
+        shape: Shape::Rect { w: 0.5, h: 0.25 },
+    });
+
+    // The tag may come after the content
+    read_both::<Shape>(
+        "radius: 2.5\nkind: Circle",
+        r#"{"radius":2.5,"kind":"Circle"}"#,
+    );
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "t", content = "c")]
+enum Message {
+    Ping,
+    Text(String),
+    Move { x: i32, y: i32 },
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(untagged)]
+enum Loose {
+    Int(i64),
+    Text(String),
+    List(Vec<u32>),
+    Point { x: i32, y: i32 },
+}
+
+#[test]
+fn adjacently_tagged_and_untagged_enums() {
+    check(&vec![
+        Message::Ping,
+        Message::Text("hi".into()),
+        Message::Move { x: 1, y: -2 },
+    ]);
+    assert_eq!(check(&Message::Text("hi".into())), "t: Text\nc: hi");
+
+    check(&vec![
+        Loose::Int(-4),
+        Loose::Text("four".into()),
+        Loose::List(vec![4]),
+        Loose::Point { x: 4, y: 4 },
+    ]);
+    read_both::<Vec<Loose>>("[2]: 7,seven", r#"[7,"seven"]"#);
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+struct Limits {
+    #[serde(default = "default_retries")]
+    retries: u32,
+    #[serde(default)]
+    verbose: bool,
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Service {
+    name: String,
+    #[serde(default)]
+    limits: Limits,
+    #[serde(default)]
+    replicas: Vec<String>,
+    #[serde(skip)]
+    connections: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+#[serde(default)]
+struct Settings {
+    theme: String,
+    font_size: u32,
+    plugins: Vec<String>,
+    limits: Limits,
+}
+
+#[test]
+fn defaults_fill_missing_nested_keys() {
+    let service: Service = read_both(
+        "name: api\nlimits:\n  verbose: true",
+        r#"{"name":"api","limits":{"verbose":true}}"#,
+    );
+    assert_eq!(service.limits.retries, 3);
+
+    // An empty nested object, as written for a default struct with no fields set
+    let service: Service = read_both(
+        "name: api\nlimits:\nreplicas: [1]: a",
+        r#"{"name":"api","limits":{},"replicas":["a"]}"#,
+    );
+    assert_eq!(service.limits.retries, 3);
+    assert_eq!(service.replicas, ["a"]);
+
+    read_both::<Service>("name: api", r#"{"name":"api"}"#);
+    read_both::<Settings>("", "{}");
+    read_both::<Settings>(
+        "limits:\n  labels:\n    tier: gold\nfont_size: 12",
+        r#"{"limits":{"labels":{"tier":"gold"}},"font_size":12}"#,
+    );
+
+    let settings = Settings::default();
+    assert_eq!(
+        check(&settings),
+        "theme: \"\"\nfont_size: 0\nplugins: [0]:\nlimits:\n  retries: 0\n  verbose: false\n  labels:"
+    );
+}
+
+#[test]
+fn skipped_fields() {
+    let service = Service {
+        name: "api".into(),
+        limits: Limits::default(),
+        replicas: vec![],
+        connections: 0,
+    };
+    assert!(!check(&service).contains("connections"));
+
+    let entry = Audit {
+        created_by: "ada".into(),
+        reviewed_by: None,
+    };
+    assert_eq!(check(&entry), "created_by: ada");
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct Account {
+    user_id: u32,
+    #[serde(rename = "e-mail", alias = "email")]
+    email_address: String,
+    is_active: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(transparent)]
+struct Meters(f64);
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct Strict {
+    a: u32,
+}
+
+#[test]
+fn renames_and_containers() {
+    assert_eq!(
+        check(&vec![Account {
+            user_id: 1,
+            email_address: "a@example.com".into(),
+            is_active: true,
+        }]),
+        "[1]{userId,e-mail,isActive}:\n  1,a@example.com,true"
+    );
+    read_both::<Account>(
+        "userId: 2\nemail: b@example.com\nisActive: false",
+        r#"{"userId":2,"email":"b@example.com","isActive":false}"#,
+    );
+
+    assert_eq!(check(&Meters(2.5)), "2.5");
+    check(&BTreeMap::from([("run".to_string(), Meters(0.5))]));
+
+    assert!(from_str::<Strict>("a: 1\nb: 2").is_err());
+    assert!(serde_json::from_str::<Strict>(r#"{"a":1,"b":2}"#).is_err());
+}
@@ -72,7 +72,7 @@ fn test_tab_delimiter() {
 
     // Should show tab delimiter in header
     assert!(toon.contains("[2    ]{"));
-    assert!(toon.contains("price    qty    sku"));
+    assert!(toon.contains("sku    qty    price"));
 }
 
 #[test]
@@ -96,7 +96,7 @@ fn test_pipe_delimiter() {
 
     // Should show pipe delimiter in header
     assert!(toon.contains("[2|]{"));
-    assert!(toon.contains("price|qty|sku"));
+    assert!(toon.contains("sku|qty|price"));
 }
 
 #[test]
@@ -127,7 +127,7 @@ fn test_mixed_array_list_format() {
     // Should use list format with "- " prefix
     assert!(toon.contains("[3]:"));
     assert!(toon.contains("- 1"));
-    // Fields are sorted alphabetically, so "age" comes before "name"
+    // serde_json::Value sorts its keys, so "age" comes before "name"
     assert!(toon.contains("- age: 30"));
     assert!(toon.contains("name: Alice"));
     assert!(toon.contains("- text"));
@@ -0,0 +1,199 @@
+//! Transcoding between TOON and JSON through serde's data model.
+//!
+//! `transcode` below follows the algorithm of `serde_transcode::transcode`:
+//! every value is read with `deserialize_any` and forwarded to the serializer
+//! as it is visited, so no intermediate `Value` is built by the transcoder.
+
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, Serialize, SerializeMap, SerializeSeq, Serializer};
+use std::cell::RefCell;
+use std::fmt;
+
+fn transcode<'de, D, S>(d: D, s: S) -> Result<S::Ok, S::Error>
+where
+    D: Deserializer<'de>,
+    S: Serializer,
+{
+    Transcoder(RefCell::new(Some(d))).serialize(s)
+}
+
+struct Transcoder<D>(RefCell<Option<D>>);
+
+impl<'de, D: Deserializer<'de>> Serialize for Transcoder<D> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let d = self.0.borrow_mut().take().expect("transcoder used twice");
+        d.deserialize_any(Forward(s)).map_err(ser::Error::custom)
+    }
+}
+
+struct Forward<S>(S);
+
+macro_rules! forward {
+    ($($visit:ident($ty:ty) => $serialize:ident;)*) => {
+        $(
+            fn $visit<E: de::Error>(self, v: $ty) -> Result<Self::Value, E> {
+                self.0.$serialize(v).map_err(de::Error::custom)
+            }
+        )*
+    };
+}
+
+impl<'de, S: Serializer> Visitor<'de> for Forward<S> {
+    type Value = S::Ok;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    forward! {
+        visit_bool(bool) => serialize_bool;
+        visit_i64(i64) => serialize_i64;
+        visit_u64(u64) => serialize_u64;
+        visit_f64(f64) => serialize_f64;
+        visit_str(&str) => serialize_str;
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<S::Ok, E> {
+        self.0.serialize_unit().map_err(de::Error::custom)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<S::Ok, E> {
+        self.0.serialize_none().map_err(de::Error::custom)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, d: D) -> Result<S::Ok, D::Error> {
+        self.0
+            .serialize_some(&Transcoder(RefCell::new(Some(d))))
+            .map_err(de::Error::custom)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<S::Ok, A::Error> {
+        let mut out = self
+            .0
+            .serialize_seq(seq.size_hint())
+            .map_err(de::Error::custom)?;
+        while seq.next_element_seed(ElementSeed(&mut out))?.is_some() {}
+        out.end().map_err(de::Error::custom)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<S::Ok, A::Error> {
+        let mut out = self
+            .0
+            .serialize_map(map.size_hint())
+            .map_err(de::Error::custom)?;
+        while map.next_key_seed(KeySeed(&mut out))?.is_some() {
+            map.next_value_seed(ValueSeed(&mut out))?;
+        }
+        out.end().map_err(de::Error::custom)
+    }
+}
+
+struct ElementSeed<'a, S>(&'a mut S);
+
+impl<'de, S: SerializeSeq> DeserializeSeed<'de> for ElementSeed<'_, S> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, d: D) -> Result<(), D::Error> {
+        self.0
+            .serialize_element(&Transcoder(RefCell::new(Some(d))))
+            .map_err(de::Error::custom)
+    }
+}
+
+struct KeySeed<'a, S>(&'a mut S);
+
+impl<'de, S: SerializeMap> DeserializeSeed<'de> for KeySeed<'_, S> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, d: D) -> Result<(), D::Error> {
+        self.0
+            .serialize_key(&Transcoder(RefCell::new(Some(d))))
+            .map_err(de::Error::custom)
+    }
+}
+
+struct ValueSeed<'a, S>(&'a mut S);
+
+impl<'de, S: SerializeMap> DeserializeSeed<'de> for ValueSeed<'_, S> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, d: D) -> Result<(), D::Error> {
+        self.0
+            .serialize_value(&Transcoder(RefCell::new(Some(d))))
+            .map_err(de::Error::custom)
+    }
+}
+
+fn toon_to_json(toon: &str) -> String {
+    let mut out = Vec::new();
+    let mut de = serde_toon::Deserializer::from_str(toon);
+    transcode(&mut de, &mut serde_json::Serializer::new(&mut out)).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+fn json_to_toon(json: &str) -> String {
+    let mut de = serde_json::Deserializer::from_str(json);
+    let mut ser = serde_toon::Serializer::new(serde_toon::ToonOptions::default());
+    transcode(&mut de, &mut ser).unwrap();
+    ser.into_inner()
+}
+
+#[test]
+fn test_toon_to_json() {
+    assert_eq!(
+        toon_to_json("id: 7\nname: Ada\nscores: [3]: 1,2.5,-3\nactive: true\nnote: null"),
+        r#"{"id":7,"name":"Ada","scores":[1,2.5,-3],"active":true,"note":null}"#
+    );
+    assert_eq!(
+        toon_to_json("users: [2]{id,name}:\n  1,Alice\n  2,Bob"),
+        r#"{"users":[{"id":1,"name":"Alice"},{"id":2,"name":"Bob"}]}"#
+    );
+    assert_eq!(
+        toon_to_json("[2]:\n  - a: 1\n    b:\n      c: x\n  - [2]: 1,2"),
+        r#"[{"a":1,"b":{"c":"x"}},[1,2]]"#
+    );
+    assert_eq!(toon_to_json("hello world"), r#""hello world""#);
+}
+
+#[test]
+fn test_json_to_toon() {
+    assert_eq!(
+        json_to_toon(r#"{"id":7,"name":"Ada","tags":["a","b"],"owner":{"team":"ops"}}"#),
+        "id: 7\nname: Ada\ntags: [2]: a,b\nowner:\n  team: ops"
+    );
+    assert_eq!(
+        json_to_toon(r#"[{"sku":"A1","qty":2},{"sku":"B2","qty":0}]"#),
+        "[2]{sku,qty}:\n  A1,2\n  B2,0"
+    );
+    assert_eq!(json_to_toon(r#""a: b""#), "\"a: b\"");
+    assert_eq!(json_to_toon("null"), "null");
+}
+
+#[test]
+fn test_transcode_round_trip() {
+    let json = r#"{"a":[1,{"b":null,"c":[true,false]},"x,y"],"d":{"e":{"f":-1.5}},"g":[]}"#;
+    assert_eq!(toon_to_json(&json_to_toon(json)), json);
+}
+
+#[test]
+fn test_root_tables_and_objects_stream() {
+    // Rows and fields reach the serializer before later ones are parsed, so
+    // the JSON written before a syntax error holds everything preceding it.
+    // A field's key is visited before its value is parsed.
+    let mut out = Vec::new();
+    let mut de =
+        serde_toon::Deserializer::from_str("[3]{id,name}:\n  1,Alice\n  2,Bob\n  3,\"Carol");
+    assert!(transcode(&mut de, &mut serde_json::Serializer::new(&mut out)).is_err());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        r#"[{"id":1,"name":"Alice"},{"id":2,"name":"Bob"}"#
+    );
+
+    let mut out = Vec::new();
+    let mut de = serde_toon::Deserializer::from_str("a: 1\nb:\n  c: 2\nd: \"open");
+    assert!(transcode(&mut de, &mut serde_json::Serializer::new(&mut out)).is_err());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        r#"{"a":1,"b":{"c":2},"d":"#
+    );
+}